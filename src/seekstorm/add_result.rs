@@ -5,15 +5,15 @@ use std::cmp::Ordering;
 use crate::{
     geo_search::{decode_morton_2_d, euclidian_distance},
     index::{
-        get_document_length_compressed_mmap, AccessType, CompressionType, Index,
+        get_document_length_compressed_mmap, AccessType, CompressionType, FieldType, Index,
         NonUniquePostingListObjectQuery, PostingListObjectQuery, SimilarityType, FIELD_STOP_BIT_1,
         FIELD_STOP_BIT_2, SPEEDUP_FLAG, STOP_BIT,
     },
     min_heap,
     search::{FilterSparse, Ranges, ResultType, SearchResult},
     utils::{
-        read_f32, read_f64, read_i16, read_i32, read_i64, read_i8, read_u16, read_u32, read_u64,
-        read_u8,
+        read_f32, read_f64, read_i16, read_i32, read_i64, read_i8, read_u128, read_u16, read_u32,
+        read_u64, read_u8,
     },
 };
 
@@ -452,6 +452,15 @@ pub(crate) fn is_facet_filter(index: &Index, facet_filter: &[FilterSparse], doci
                     return true;
                 }
             }
+            FilterSparse::Ip(ranges) => {
+                let ip = read_u128(
+                    &index.facets_file_mmap,
+                    (index.facets_size_sum * docid) + facet.offset,
+                );
+                if !ranges.iter().any(|range| range.contains(&ip)) {
+                    return true;
+                }
+            }
             FilterSparse::String(values) => {
                 let facet_value_id = read_u16(
                     &index.facets_file_mmap,
@@ -480,7 +489,22 @@ pub(crate) fn is_facet_filter(index: &Index, facet_filter: &[FilterSparse], doci
                 }
             }
 
-            FilterSparse::None => {}
+            FilterSparse::None | FilterSparse::Expression(..) | FilterSparse::DocIds(_) => {}
+        }
+    }
+
+    // range filters of runtime fields, GeoShape filters and join filters follow the filters of the facet fields
+    for filter in facet_filter.iter().skip(index.facets.len()) {
+        match filter {
+            FilterSparse::Expression(expression, range)
+                if !range.contains(&expression.evaluate(index, docid, 0.0)) =>
+            {
+                return true;
+            }
+            FilterSparse::DocIds(doc_ids) if !doc_ids.contains(&docid) => {
+                return true;
+            }
+            _ => {}
         }
     }
     false
@@ -490,7 +514,10 @@ pub(crate) fn is_facet_filter(index: &Index, facet_filter: &[FilterSparse], doci
 pub(crate) fn facet_count(index: &Index, search_result: &mut SearchResult, docid: usize) {
     if !search_result.query_facets.is_empty() && !search_result.skip_facet_count {
         for (i, facet) in index.facets.iter().enumerate() {
-            if search_result.query_facets[i].length == 0 {
+            if search_result.query_facets[i].length == 0
+                || (matches!(facet.field_type, FieldType::Date | FieldType::Ip)
+                    && index.is_facet_missing(i, docid))
+            {
                 continue;
             }
 
@@ -593,6 +620,17 @@ pub(crate) fn facet_count(index: &Index, search_result: &mut SearchResult, docid
                         .binary_search_by(|range| range.1.partial_cmp(&facet_value).unwrap())
                         .map_or_else(|idx| idx as u16 - 1, |idx| idx as u16)
                 }
+                Ranges::Ip(_range_type, ranges) => {
+                    let facet_value = read_u128(
+                        &index.facets_file_mmap,
+                        (index.facets_size_sum * docid) + facet.offset,
+                    );
+                    match ranges.binary_search_by_key(&facet_value, |range| range.1) {
+                        Ok(idx) => idx as u16,
+                        Err(0) => continue,
+                        Err(idx) => idx as u16 - 1,
+                    }
+                }
 
                 Ranges::Point(_range_type, ranges, base, unit) => {
                     let facet_value = read_u64(
@@ -844,7 +882,7 @@ pub(crate) fn get_bm25f_singleterm_multifield(
 
     if index.indexed_field_vec.len() == 1 {
         let bm25_component =
-            index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
+            index.bm25_component_cache[0][if index.meta.access_type == AccessType::Mmap {
                 get_document_length_compressed_mmap(index, 0, block_id, docid & 0b11111111_11111111)
             } else {
                 index.level_index[block_id].document_length_compressed_array[0]
@@ -854,55 +892,60 @@ pub(crate) fn get_bm25f_singleterm_multifield(
         if !plo_single.is_bigram {
             let tf = field_vec[0].1 as f32;
 
-            bm25f = plo_single.idf * ((tf * (K + 1.0) / (tf + bm25_component)) + SIGMA);
+            bm25f = plo_single.idf * ((tf * (index.meta.k1 + 1.0) / (tf + bm25_component)) + SIGMA);
         } else {
             let tf_bigram1 = field_vec_bigram1[0].1 as f32;
             let tf_bigram2 = field_vec_bigram2[0].1 as f32;
 
             bm25f = plo_single.idf_bigram1
-                * ((tf_bigram1 * (K + 1.0) / (tf_bigram1 + bm25_component)) + SIGMA)
+                * ((tf_bigram1 * (index.meta.k1 + 1.0) / (tf_bigram1 + bm25_component)) + SIGMA)
                 + plo_single.idf_bigram2
-                    * ((tf_bigram2 * (K + 1.0) / (tf_bigram2 + bm25_component)) + SIGMA);
+                    * ((tf_bigram2 * (index.meta.k1 + 1.0) / (tf_bigram2 + bm25_component))
+                        + SIGMA);
         }
     } else if !plo_single.is_bigram || index.meta.similarity == SimilarityType::Bm25fProximity {
         for field in field_vec.iter() {
             let field_id = field.0 as usize;
 
-            let bm25_component =
-                index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
-                    get_document_length_compressed_mmap(
-                        index,
-                        field_id,
-                        block_id,
-                        docid & 0b11111111_11111111,
-                    )
-                } else {
-                    index.level_index[block_id].document_length_compressed_array[field_id]
-                        [docid & 0b11111111_11111111]
-                } as usize] as f32;
+            let bm25_component = index.bm25_component_cache[field_id][if index.meta.access_type
+                == AccessType::Mmap
+            {
+                get_document_length_compressed_mmap(
+                    index,
+                    field_id,
+                    block_id,
+                    docid & 0b11111111_11111111,
+                )
+            } else {
+                index.level_index[block_id].document_length_compressed_array[field_id]
+                    [docid & 0b11111111_11111111]
+            } as usize] as f32;
 
             let tf = field.1 as f32;
 
             let weight = index.indexed_schema_vec[field.0 as usize].boost;
 
-            bm25f += weight * plo_single.idf * ((tf * (K + 1.0) / (tf + bm25_component)) + SIGMA);
+            bm25f += weight
+                * plo_single.idf
+                * ((tf * (index.meta.k1 + 1.0) / (tf + bm25_component)) + SIGMA);
         }
     } else {
         for field in field_vec_bigram1.iter() {
             let field_id = field.0 as usize;
 
-            let bm25_component =
-                index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
-                    get_document_length_compressed_mmap(
-                        index,
-                        field_id,
-                        block_id,
-                        docid & 0b11111111_11111111,
-                    )
-                } else {
-                    index.level_index[block_id].document_length_compressed_array[field_id]
-                        [docid & 0b11111111_11111111]
-                } as usize] as f32;
+            let bm25_component = index.bm25_component_cache[field_id][if index.meta.access_type
+                == AccessType::Mmap
+            {
+                get_document_length_compressed_mmap(
+                    index,
+                    field_id,
+                    block_id,
+                    docid & 0b11111111_11111111,
+                )
+            } else {
+                index.level_index[block_id].document_length_compressed_array[field_id]
+                    [docid & 0b11111111_11111111]
+            } as usize] as f32;
 
             let tf_bigram1 = field.1 as f32;
 
@@ -910,24 +953,25 @@ pub(crate) fn get_bm25f_singleterm_multifield(
 
             bm25f += weight
                 * plo_single.idf_bigram1
-                * ((tf_bigram1 * (K + 1.0) / (tf_bigram1 + bm25_component)) + SIGMA);
+                * ((tf_bigram1 * (index.meta.k1 + 1.0) / (tf_bigram1 + bm25_component)) + SIGMA);
         }
 
         for field in field_vec_bigram2.iter() {
             let field_id = field.0 as usize;
 
-            let bm25_component =
-                index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
-                    get_document_length_compressed_mmap(
-                        index,
-                        field_id,
-                        block_id,
-                        docid & 0b11111111_11111111,
-                    )
-                } else {
-                    index.level_index[block_id].document_length_compressed_array[field_id]
-                        [docid & 0b11111111_11111111]
-                } as usize] as f32;
+            let bm25_component = index.bm25_component_cache[field_id][if index.meta.access_type
+                == AccessType::Mmap
+            {
+                get_document_length_compressed_mmap(
+                    index,
+                    field_id,
+                    block_id,
+                    docid & 0b11111111_11111111,
+                )
+            } else {
+                index.level_index[block_id].document_length_compressed_array[field_id]
+                    [docid & 0b11111111_11111111]
+            } as usize] as f32;
 
             let tf_bigram2 = field.1 as f32;
 
@@ -935,7 +979,7 @@ pub(crate) fn get_bm25f_singleterm_multifield(
 
             bm25f += weight
                 * plo_single.idf_bigram2
-                * ((tf_bigram2 * (K + 1.0) / (tf_bigram2 + bm25_component)) + SIGMA);
+                * ((tf_bigram2 * (index.meta.k1 + 1.0) / (tf_bigram2 + bm25_component)) + SIGMA);
         }
     }
 
@@ -956,7 +1000,7 @@ pub(crate) fn get_bm25f_singleterm_singlefield(
 
     if index.indexed_field_vec.len() == 1 {
         let bm25_component =
-            index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
+            index.bm25_component_cache[0][if index.meta.access_type == AccessType::Mmap {
                 get_document_length_compressed_mmap(index, 0, block_id, docid & 0b11111111_11111111)
             } else {
                 index.level_index[block_id].document_length_compressed_array[0]
@@ -966,19 +1010,22 @@ pub(crate) fn get_bm25f_singleterm_singlefield(
         if !plo_single.is_bigram {
             let tf = positions_count as f32;
 
-            bm25f = plo_single.idf * ((tf * (K + 1.0) / (tf + bm25_component)) + SIGMA);
+            bm25f = plo_single.idf * ((tf * (index.meta.k1 + 1.0) / (tf + bm25_component)) + SIGMA);
         } else {
             bm25f = plo_single.idf_bigram1
-                * ((tf_bigram1 as f32 * (K + 1.0) / (tf_bigram1 as f32 + bm25_component)) + SIGMA)
+                * ((tf_bigram1 as f32 * (index.meta.k1 + 1.0)
+                    / (tf_bigram1 as f32 + bm25_component))
+                    + SIGMA)
                 + plo_single.idf_bigram2
-                    * ((tf_bigram2 as f32 * (K + 1.0) / (tf_bigram2 as f32 + bm25_component))
+                    * ((tf_bigram2 as f32 * (index.meta.k1 + 1.0)
+                        / (tf_bigram2 as f32 + bm25_component))
                         + SIGMA);
         }
     } else {
         let field_id = 0;
 
         let bm25_component =
-            index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
+            index.bm25_component_cache[field_id][if index.meta.access_type == AccessType::Mmap {
                 get_document_length_compressed_mmap(
                     index,
                     field_id,
@@ -993,12 +1040,15 @@ pub(crate) fn get_bm25f_singleterm_singlefield(
         if !plo_single.is_bigram {
             let tf = positions_count as f32;
 
-            bm25f = plo_single.idf * ((tf * (K + 1.0) / (tf + bm25_component)) + SIGMA);
+            bm25f = plo_single.idf * ((tf * (index.meta.k1 + 1.0) / (tf + bm25_component)) + SIGMA);
         } else {
             bm25f = plo_single.idf_bigram1
-                * ((tf_bigram1 as f32 * (K + 1.0) / (tf_bigram1 as f32 + bm25_component)) + SIGMA)
+                * ((tf_bigram1 as f32 * (index.meta.k1 + 1.0)
+                    / (tf_bigram1 as f32 + bm25_component))
+                    + SIGMA)
                 + plo_single.idf_bigram2
-                    * ((tf_bigram2 as f32 * (K + 1.0) / (tf_bigram2 as f32 + bm25_component))
+                    * ((tf_bigram2 as f32 * (index.meta.k1 + 1.0)
+                        / (tf_bigram2 as f32 + bm25_component))
                         + SIGMA);
         }
     }
@@ -1017,7 +1067,7 @@ pub(crate) fn get_bm25f_multiterm_multifield(
 
     if index.indexed_field_vec.len() == 1 {
         let bm25_component =
-            index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
+            index.bm25_component_cache[0][if index.meta.access_type == AccessType::Mmap {
                 get_document_length_compressed_mmap(index, 0, block_id, docid & 0b11111111_11111111)
             } else {
                 index.level_index[block_id].document_length_compressed_array[0]
@@ -1032,14 +1082,14 @@ pub(crate) fn get_bm25f_multiterm_multifield(
             if !plo.is_bigram {
                 let tf = plo.field_vec[0].1 as f32;
 
-                bm25f += plo.idf * ((tf * (K + 1.0) / (tf + bm25_component)) + SIGMA);
+                bm25f += plo.idf * ((tf * (index.meta.k1 + 1.0) / (tf + bm25_component)) + SIGMA);
             } else {
                 bm25f += plo.idf_bigram1
-                    * ((plo.tf_bigram1 as f32 * (K + 1.0)
+                    * ((plo.tf_bigram1 as f32 * (index.meta.k1 + 1.0)
                         / (plo.tf_bigram1 as f32 + bm25_component))
                         + SIGMA)
                     + plo.idf_bigram2
-                        * ((plo.tf_bigram2 as f32 * (K + 1.0)
+                        * ((plo.tf_bigram2 as f32 * (index.meta.k1 + 1.0)
                             / (plo.tf_bigram2 as f32 + bm25_component))
                             + SIGMA);
             }
@@ -1056,22 +1106,21 @@ pub(crate) fn get_bm25f_multiterm_multifield(
                 for field in plo.field_vec.iter() {
                     let field_id = field.0 as usize;
                     if bm25_component_vec[field_id] == 0.0 {
-                        bm25_component_vec[field_id] = index.bm25_component_cache[if index
-                            .meta
-                            .access_type
-                            == AccessType::Mmap
-                        {
-                            get_document_length_compressed_mmap(
-                                index,
-                                field_id,
-                                block_id,
-                                docid & 0b11111111_11111111,
-                            )
-                        } else {
-                            index.level_index[block_id].document_length_compressed_array[field_id]
-                                [docid & 0b11111111_11111111]
-                        }
-                            as usize];
+                        bm25_component_vec[field_id] =
+                            index.bm25_component_cache[field_id][if index.meta.access_type
+                                == AccessType::Mmap
+                            {
+                                get_document_length_compressed_mmap(
+                                    index,
+                                    field_id,
+                                    block_id,
+                                    docid & 0b11111111_11111111,
+                                )
+                            } else {
+                                index.level_index[block_id].document_length_compressed_array
+                                    [field_id][docid & 0b11111111_11111111]
+                            }
+                                as usize];
                     }
 
                     let tf = field.1 as f32;
@@ -1080,28 +1129,28 @@ pub(crate) fn get_bm25f_multiterm_multifield(
 
                     bm25f += weight
                         * plo.idf
-                        * ((tf * (K + 1.0) / (tf + bm25_component_vec[field_id])) + SIGMA);
+                        * ((tf * (index.meta.k1 + 1.0) / (tf + bm25_component_vec[field_id]))
+                            + SIGMA);
                 }
             } else {
                 for field in plo.field_vec_bigram1.iter() {
                     let field_id = field.0 as usize;
                     if bm25_component_vec[field_id] == 0.0 {
-                        bm25_component_vec[field_id] = index.bm25_component_cache[if index
-                            .meta
-                            .access_type
-                            == AccessType::Mmap
-                        {
-                            get_document_length_compressed_mmap(
-                                index,
-                                field_id,
-                                block_id,
-                                docid & 0b11111111_11111111,
-                            )
-                        } else {
-                            index.level_index[block_id].document_length_compressed_array[field_id]
-                                [docid & 0b11111111_11111111]
-                        }
-                            as usize];
+                        bm25_component_vec[field_id] =
+                            index.bm25_component_cache[field_id][if index.meta.access_type
+                                == AccessType::Mmap
+                            {
+                                get_document_length_compressed_mmap(
+                                    index,
+                                    field_id,
+                                    block_id,
+                                    docid & 0b11111111_11111111,
+                                )
+                            } else {
+                                index.level_index[block_id].document_length_compressed_array
+                                    [field_id][docid & 0b11111111_11111111]
+                            }
+                                as usize];
                     }
 
                     let tf_bigram1 = field.1 as f32;
@@ -1110,29 +1159,29 @@ pub(crate) fn get_bm25f_multiterm_multifield(
 
                     bm25f += weight
                         * plo.idf_bigram1
-                        * ((tf_bigram1 * (K + 1.0) / (tf_bigram1 + bm25_component_vec[field_id]))
+                        * ((tf_bigram1 * (index.meta.k1 + 1.0)
+                            / (tf_bigram1 + bm25_component_vec[field_id]))
                             + SIGMA);
                 }
 
                 for field in plo.field_vec_bigram2.iter() {
                     let field_id = field.0 as usize;
                     if bm25_component_vec[field_id] == 0.0 {
-                        bm25_component_vec[field_id] = index.bm25_component_cache[if index
-                            .meta
-                            .access_type
-                            == AccessType::Mmap
-                        {
-                            get_document_length_compressed_mmap(
-                                index,
-                                field_id,
-                                block_id,
-                                docid & 0b11111111_11111111,
-                            )
-                        } else {
-                            index.level_index[block_id].document_length_compressed_array[field_id]
-                                [docid & 0b11111111_11111111]
-                        }
-                            as usize] as f32;
+                        bm25_component_vec[field_id] =
+                            index.bm25_component_cache[field_id][if index.meta.access_type
+                                == AccessType::Mmap
+                            {
+                                get_document_length_compressed_mmap(
+                                    index,
+                                    field_id,
+                                    block_id,
+                                    docid & 0b11111111_11111111,
+                                )
+                            } else {
+                                index.level_index[block_id].document_length_compressed_array
+                                    [field_id][docid & 0b11111111_11111111]
+                            }
+                                as usize] as f32;
                     }
 
                     let tf_bigram2 = field.1 as f32;
@@ -1141,7 +1190,8 @@ pub(crate) fn get_bm25f_multiterm_multifield(
 
                     bm25f += weight
                         * plo.idf_bigram2
-                        * ((tf_bigram2 * (K + 1.0) / (tf_bigram2 + bm25_component_vec[field_id]))
+                        * ((tf_bigram2 * (index.meta.k1 + 1.0)
+                            / (tf_bigram2 + bm25_component_vec[field_id]))
                             + SIGMA);
                 }
             }
@@ -1160,7 +1210,8 @@ pub(crate) fn get_bm25f_multiterm_singlefield(
     let mut bm25f = 0.0;
     let block_id = docid >> 16;
 
-    let bm25_component = index.bm25_component_cache[if index.meta.access_type == AccessType::Mmap {
+    let bm25_component = index.bm25_component_cache[0][if index.meta.access_type == AccessType::Mmap
+    {
         get_document_length_compressed_mmap(index, 0, block_id, docid & 0b11111111_11111111)
     } else {
         index.level_index[block_id].document_length_compressed_array[0][docid & 0b11111111_11111111]
@@ -1174,13 +1225,14 @@ pub(crate) fn get_bm25f_multiterm_singlefield(
         if !plo.is_bigram {
             let tf = plo.positions_count as f32;
 
-            bm25f += plo.idf * ((tf * (K + 1.0) / (tf + bm25_component)) + SIGMA);
+            bm25f += plo.idf * ((tf * (index.meta.k1 + 1.0) / (tf + bm25_component)) + SIGMA);
         } else {
             bm25f += plo.idf_bigram1
-                * ((plo.tf_bigram1 as f32 * (K + 1.0) / (plo.tf_bigram1 as f32 + bm25_component))
+                * ((plo.tf_bigram1 as f32 * (index.meta.k1 + 1.0)
+                    / (plo.tf_bigram1 as f32 + bm25_component))
                     + SIGMA)
                 + plo.idf_bigram2
-                    * ((plo.tf_bigram2 as f32 * (K + 1.0)
+                    * ((plo.tf_bigram2 as f32 * (index.meta.k1 + 1.0)
                         / (plo.tf_bigram2 as f32 + bm25_component))
                         + SIGMA);
         }
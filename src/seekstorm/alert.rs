@@ -0,0 +1,283 @@
+use std::{collections::HashSet, fs::File, io::BufReader, path::Path};
+
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{Document, Index, IndexArc},
+    scroll::Scroll,
+};
+
+pub(crate) const ALERTS_FILENAME: &str = "alerts.json";
+/// Number of matching document ids fetched per scroll batch, when evaluating the alerts
+const ALERT_SCROLL_LENGTH: usize = 1000;
+
+fn default_max_batch_size() -> usize {
+    100
+}
+
+/// Alert on new matches for saved searches: documents newly committed to the index are matched against percolator queries (saved searches),
+/// and the matches are delivered as notifications to a webhook and/or an email address, so that users get "new results for your search" without polling.
+/// The library returns the notifications (see EvaluateAlerts::evaluate_alerts), the server delivers them.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Alert {
+    /// Unique id of the alert
+    pub id: String,
+    /// Ids of the percolator queries evaluated by the alert. If empty, all percolator queries of the index are evaluated.
+    #[serde(default)]
+    pub percolator_query_ids: Vec<String>,
+    /// URL of the webhook, where the notifications are posted as JSON (AlertNotification). Optional if email is set.
+    #[serde(default)]
+    pub webhook_url: String,
+    /// Email address, where the notifications are sent to. Optional if webhook_url is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Maximum number of matches per notification: more matches are split into multiple notifications
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Include the stored fields of the matching documents into the notifications
+    #[serde(default)]
+    pub include_documents: bool,
+}
+
+/// Document newly committed to the index, matching a percolator query of an alert
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertMatch {
+    pub percolator_query_id: String,
+    pub doc_id: usize,
+    /// Stored fields of the document, if Alert.include_documents is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document: Option<Document>,
+}
+
+/// Batch of new matches of an alert, to be posted to its webhook
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertNotification {
+    pub alert_id: String,
+    pub index_id: u64,
+    pub webhook_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub matches: Vec<AlertMatch>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct AlertsFile {
+    /// Documents with lower document ids have already been evaluated
+    next_doc_id: usize,
+    alerts: Vec<Alert>,
+}
+
+pub(crate) fn load_alerts(index_path: &Path) -> (Vec<Alert>, usize) {
+    let alerts_file: AlertsFile =
+        if let Ok(alerts_file) = File::open(index_path.join(ALERTS_FILENAME)) {
+            serde_json::from_reader(BufReader::new(alerts_file)).unwrap_or_default()
+        } else {
+            AlertsFile::default()
+        };
+    (alerts_file.alerts, alerts_file.next_doc_id)
+}
+
+impl Index {
+    /// Add alerts to the index, or replace alerts with the same id.
+    /// Only documents committed after the first alert was added are evaluated.
+    /// Returns the number of alerts of the index.
+    pub fn add_alerts(&mut self, alerts: Vec<Alert>) -> Result<usize, String> {
+        for alert in alerts.iter() {
+            if alert.id.is_empty() {
+                return Err("alert id is empty".to_string());
+            }
+            if alert.webhook_url.is_empty() && alert.email.is_none() {
+                return Err(format!(
+                    "alert has neither webhook_url nor email: {}",
+                    alert.id
+                ));
+            }
+            if alert.max_batch_size == 0 {
+                return Err(format!("alert max_batch_size is 0: {}", alert.id));
+            }
+            if let Some(percolator_query_id) =
+                alert
+                    .percolator_query_ids
+                    .iter()
+                    .find(|percolator_query_id| {
+                        !self
+                            .percolator_queries
+                            .iter()
+                            .any(|percolator_query| percolator_query.id == **percolator_query_id)
+                    })
+            {
+                return Err(format!(
+                    "percolator query not found: {}",
+                    percolator_query_id
+                ));
+            }
+        }
+
+        if self.alerts.is_empty() {
+            self.alert_doc_id = self.committed_doc_count;
+        }
+        let mut stored_alerts = self.alerts.clone();
+        for alert in alerts {
+            if let Some(stored_alert) = stored_alerts
+                .iter_mut()
+                .find(|stored_alert| stored_alert.id == alert.id)
+            {
+                *stored_alert = alert;
+            } else {
+                stored_alerts.push(alert);
+            }
+        }
+        self.set_alerts(stored_alerts, self.alert_doc_id)
+    }
+
+    /// Delete alerts from the index by id.
+    /// Returns the number of deleted alerts.
+    pub fn delete_alerts(&mut self, ids: Vec<String>) -> Result<usize, String> {
+        let mut stored_alerts = self.alerts.clone();
+        stored_alerts.retain(|stored_alert| !ids.contains(&stored_alert.id));
+        let deleted_count = self.alerts.len() - stored_alerts.len();
+        if deleted_count > 0 {
+            self.set_alerts(stored_alerts, self.alert_doc_id)?;
+        }
+        Ok(deleted_count)
+    }
+
+    /// Get the alerts of the index
+    pub fn get_alerts(&self) -> Vec<Alert> {
+        self.alerts.clone()
+    }
+
+    fn set_alerts(&mut self, alerts: Vec<Alert>, next_doc_id: usize) -> Result<usize, String> {
+        let alerts_file = AlertsFile {
+            next_doc_id,
+            alerts,
+        };
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(ALERTS_FILENAME))
+                .map_err(|e| e.to_string())?,
+            &alerts_file,
+        )
+        .map_err(|e| e.to_string())?;
+
+        self.alerts = alerts_file.alerts;
+        self.alert_doc_id = alerts_file.next_doc_id;
+        Ok(self.alerts.len())
+    }
+}
+
+/// Evaluate the alerts of an index for newly committed documents
+#[allow(async_fn_in_trait)]
+pub trait EvaluateAlerts {
+    async fn evaluate_alerts(&self) -> Result<Vec<AlertNotification>, String>;
+}
+
+impl EvaluateAlerts for IndexArc {
+    /// Matches the documents committed since the last evaluation against the percolator queries of the alerts,
+    /// and returns the notifications to be delivered: per alert, batches of at most Alert.max_batch_size matches.
+    /// Each committed document is evaluated only once: the evaluated document range is persisted with the alerts.
+    /// Deleted documents are skipped. Updated documents get a new document id, and are evaluated again.
+    async fn evaluate_alerts(&self) -> Result<Vec<AlertNotification>, String> {
+        let index_ref = self.read().await;
+        let start_doc_id = index_ref.alert_doc_id;
+        let end_doc_id = index_ref.committed_doc_count;
+        if start_doc_id > end_doc_id {
+            // the index was cleared or compacted: continue with the documents committed from now on
+            drop(index_ref);
+            let mut index_mut = self.write().await;
+            let alerts = index_mut.alerts.clone();
+            index_mut.set_alerts(alerts, end_doc_id)?;
+            return Ok(Vec::new());
+        }
+        if index_ref.alerts.is_empty() || start_doc_id == end_doc_id {
+            return Ok(Vec::new());
+        }
+        let alerts = index_ref.alerts.clone();
+        let percolator_queries = index_ref.percolator_queries.clone();
+        let index_id = index_ref.meta.id;
+        drop(index_ref);
+
+        let mut percolator_query_matches: AHashMap<String, Vec<usize>> = AHashMap::new();
+        for percolator_query in percolator_queries.iter().filter(|percolator_query| {
+            alerts.iter().any(|alert| {
+                alert.percolator_query_ids.is_empty()
+                    || alert.percolator_query_ids.contains(&percolator_query.id)
+            })
+        }) {
+            let mut doc_ids = Vec::new();
+            let mut scroll_id = Some(start_doc_id.to_string());
+            while scroll_id.is_some() {
+                let scroll_result = self
+                    .scroll(
+                        percolator_query.query.clone(),
+                        percolator_query.query_type_default.clone(),
+                        ALERT_SCROLL_LENGTH,
+                        false,
+                        percolator_query.field_filter.clone(),
+                        percolator_query.facet_filter.clone(),
+                        scroll_id,
+                    )
+                    .await?;
+                doc_ids.extend(
+                    scroll_result
+                        .results
+                        .into_iter()
+                        .take_while(|doc_id| *doc_id < end_doc_id),
+                );
+                scroll_id = scroll_result.scroll_id.filter(|scroll_id| {
+                    scroll_id
+                        .parse::<usize>()
+                        .is_ok_and(|doc_id| doc_id < end_doc_id)
+                });
+            }
+            percolator_query_matches.insert(percolator_query.id.clone(), doc_ids);
+        }
+
+        let mut notifications = Vec::new();
+        let index_ref = self.read().await;
+        for alert in alerts {
+            let mut matches = Vec::new();
+            for percolator_query in percolator_queries.iter() {
+                if !alert.percolator_query_ids.is_empty()
+                    && !alert.percolator_query_ids.contains(&percolator_query.id)
+                {
+                    continue;
+                }
+                for doc_id in percolator_query_matches
+                    .get(&percolator_query.id)
+                    .into_iter()
+                    .flatten()
+                {
+                    matches.push(AlertMatch {
+                        percolator_query_id: percolator_query.id.clone(),
+                        doc_id: *doc_id,
+                        document: if alert.include_documents {
+                            index_ref
+                                .get_document(*doc_id, false, &None, &HashSet::new(), &[])
+                                .ok()
+                        } else {
+                            None
+                        },
+                    });
+                }
+            }
+
+            for matches in matches.chunks(alert.max_batch_size) {
+                notifications.push(AlertNotification {
+                    alert_id: alert.id.clone(),
+                    index_id,
+                    webhook_url: alert.webhook_url.clone(),
+                    email: alert.email.clone(),
+                    matches: matches.to_vec(),
+                });
+            }
+        }
+        drop(index_ref);
+
+        let mut index_mut = self.write().await;
+        let alerts = index_mut.alerts.clone();
+        index_mut.set_alerts(alerts, end_doc_id)?;
+
+        Ok(notifications)
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use tokio::{runtime::Runtime, sync::RwLock};
+
+use crate::{
+    commit::Commit,
+    error::SeekStormError,
+    highlighter::Highlighter,
+    index::{
+        create_index, open_index, open_index_with_tokenizer, DeleteDocument, DeleteDocuments,
+        DistanceField, Document, FileType, IndexArc, IndexDocument, IndexDocuments,
+        IndexMetaObject, SchemaField, Synonym, UpdateDocument, UpdateDocuments,
+    },
+    search::{ResultObject, Search, SearchRequest},
+    tokenizer::Tokenizer,
+};
+
+/// Runtime of all blocking indices of the process, created on first use
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("tokio runtime for the blocking API")
+    })
+}
+
+/// Index with blocking methods, for CLI tools and non-async applications: the async methods of IndexArc are executed on a runtime managed by the library.
+/// The methods must not be called from within an async runtime (they would block its worker thread), use IndexArc and the async traits there instead.
+/// ```no_run
+/// use seekstorm::{blocking::BlockingIndex, index::FileType, search::SearchRequest};
+/// use std::path::Path;
+///
+/// let index = BlockingIndex::open(Path::new("C:/index/"), false).unwrap();
+/// index.index_document(serde_json::from_str(r#"{"title":"title1 test","body":"body1"}"#).unwrap(), FileType::None);
+/// index.commit().unwrap();
+/// let result_object = index.search(SearchRequest::new("test").length(10));
+/// ```
+/// Async methods without a blocking counterpart can be executed with block_on, e.g. `index.block_on(index.index_arc().delete_documents_by_query(...))`.
+pub struct BlockingIndex {
+    index_arc: IndexArc,
+}
+
+impl BlockingIndex {
+    /// Creates an index, see index::create_index for the parameters
+    pub fn create(
+        index_path: &Path,
+        meta: IndexMetaObject,
+        schema: &Vec<SchemaField>,
+        serialize_schema: bool,
+        synonyms: &Vec<Synonym>,
+        segment_number_bits1: usize,
+        mute: bool,
+    ) -> Result<BlockingIndex, SeekStormError> {
+        let _runtime_guard = runtime().enter();
+        let index = create_index(
+            index_path,
+            meta,
+            schema,
+            serialize_schema,
+            synonyms,
+            segment_number_bits1,
+            mute,
+        )?;
+        Ok(BlockingIndex {
+            index_arc: Arc::new(RwLock::new(index)),
+        })
+    }
+
+    /// Opens an index, see index::open_index
+    pub fn open(index_path: &Path, mute: bool) -> Result<BlockingIndex, SeekStormError> {
+        let index_arc = runtime().block_on(open_index(index_path, mute))?;
+        Ok(BlockingIndex { index_arc })
+    }
+
+    /// Opens an index with TokenizerType::Custom, see index::open_index_with_tokenizer
+    pub fn open_with_tokenizer(
+        index_path: &Path,
+        tokenizer: Arc<dyn Tokenizer>,
+        mute: bool,
+    ) -> Result<BlockingIndex, SeekStormError> {
+        let index_arc =
+            runtime().block_on(open_index_with_tokenizer(index_path, tokenizer, mute))?;
+        Ok(BlockingIndex { index_arc })
+    }
+
+    /// The index for the async APIs, e.g. with block_on
+    pub fn index_arc(&self) -> &IndexArc {
+        &self.index_arc
+    }
+
+    /// Executes a future of the async APIs on the runtime of the blocking API, and waits for its output
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        runtime().block_on(future)
+    }
+
+    pub fn index_document(&self, document: Document, file: FileType) {
+        self.block_on(self.index_arc.index_document(document, file))
+    }
+
+    pub fn index_documents(&self, document_vec: Vec<Document>) {
+        self.block_on(self.index_arc.index_documents(document_vec))
+    }
+
+    pub fn update_document(&self, id_document: (u64, Document)) {
+        self.block_on(self.index_arc.update_document(id_document))
+    }
+
+    pub fn update_documents(&self, id_document_vec: Vec<(u64, Document)>) {
+        self.block_on(self.index_arc.update_documents(id_document_vec))
+    }
+
+    pub fn delete_document(&self, docid: u64) {
+        self.block_on(self.index_arc.delete_document(docid))
+    }
+
+    pub fn delete_documents(&self, docid_vec: Vec<u64>) {
+        self.block_on(self.index_arc.delete_documents(docid_vec))
+    }
+
+    pub fn commit(&self) -> Result<(), SeekStormError> {
+        let mut index_arc = self.index_arc.clone();
+        self.block_on(index_arc.commit())
+    }
+
+    pub fn search(&self, search_request: SearchRequest) -> ResultObject {
+        self.block_on(self.index_arc.search(search_request))
+    }
+
+    /// Returns the stored fields of a document, see Index::get_document
+    pub fn get_document(
+        &self,
+        doc_id: usize,
+        include_uncommited: bool,
+        highlighter_option: &Option<Highlighter>,
+        fields: &HashSet<String>,
+        distance_fields: &[DistanceField],
+    ) -> Result<Document, String> {
+        self.block_on(self.index_arc.read()).get_document(
+            doc_id,
+            include_uncommited,
+            highlighter_option,
+            fields,
+            distance_fields,
+        )
+    }
+
+    /// Commits and removes the index from RAM, see Index::close_index
+    pub fn close(self) {
+        self.block_on(self.index_arc.write()).close_index();
+    }
+}
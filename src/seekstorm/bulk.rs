@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commit::Commit,
+    index::{DeleteDocument, Document, FileType, IndexArc, IndexDocument, UpdateDocument},
+};
+
+/// Operation of a bulk request, serialized as single-key JSON object:
+/// `{"index":{document}}`, `{"update":[document_id,{document}]}` or `{"delete":document_id}`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkOperation {
+    /// Indexes a new document
+    Index(Document),
+    /// Replaces the document with the document id by a new document, which gets a new document id (see UpdateDocument)
+    Update(u64, Document),
+    /// Deletes the document with the document id
+    Delete(u64),
+}
+
+/// Number of applied operations of a bulk request
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BulkResult {
+    pub index_count: usize,
+    pub update_count: usize,
+    pub delete_count: usize,
+    /// Number of indexed documents of the index after the bulk request, including the deleted documents
+    pub indexed_doc_count: usize,
+}
+
+/// Applies an ordered list of index, update and delete operations with a single request, e.g. from a sync pipeline emitting mixed changes.
+/// All operations are validated first: documents with Index::validate_document, document ids against the number of indexed documents.
+/// If any operation is invalid, none is applied and the errors of all invalid operations are returned with their position, separated by "; ".
+/// Otherwise the operations are applied in order, and committed together with a single commit after the last operation.
+/// As with delete_document, deletions are effective immediately, independent of the commit.
+#[allow(async_fn_in_trait)]
+pub trait Bulk {
+    async fn bulk(&self, operations: Vec<BulkOperation>) -> Result<BulkResult, String>;
+}
+
+impl Bulk for IndexArc {
+    async fn bulk(&self, operations: Vec<BulkOperation>) -> Result<BulkResult, String> {
+        let index_ref = self.read().await;
+        index_ref.check_writable()?;
+        let mut errors = Vec::new();
+        for (position, operation) in operations.iter().enumerate() {
+            let (document_id, document) = match operation {
+                BulkOperation::Index(document) => (None, Some(document)),
+                BulkOperation::Update(document_id, document) => {
+                    (Some(*document_id), Some(document))
+                }
+                BulkOperation::Delete(document_id) => (Some(*document_id), None),
+            };
+            if let Some(document_id) = document_id {
+                if document_id as usize >= index_ref.indexed_doc_count {
+                    errors.push(format!(
+                        "operation {}: document id {} does not exist",
+                        position, document_id
+                    ));
+                }
+            }
+            if let Some(document) = document {
+                if let Err(e) = index_ref.validate_document(document) {
+                    errors.push(format!("operation {}: {}", position, e));
+                }
+            }
+        }
+        drop(index_ref);
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+
+        let mut bulk_result = BulkResult::default();
+        for operation in operations {
+            match operation {
+                BulkOperation::Index(document) => {
+                    self.index_document(document, FileType::None).await;
+                    bulk_result.index_count += 1;
+                }
+                BulkOperation::Update(document_id, document) => {
+                    self.update_document((document_id, document)).await;
+                    bulk_result.update_count += 1;
+                }
+                BulkOperation::Delete(document_id) => {
+                    self.delete_document(document_id).await;
+                    bulk_result.delete_count += 1;
+                }
+            }
+        }
+
+        let mut index_arc = self.clone();
+        index_arc.commit().await?;
+
+        bulk_result.indexed_doc_count = self.read().await.indexed_doc_count;
+        Ok(bulk_result)
+    }
+}
@@ -0,0 +1,204 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, SecondsFormat};
+use itertools::Itertools;
+use serde_json::Value;
+
+use crate::{
+    date::parse_date,
+    index::{Document, FieldType, Index, SchemaField},
+};
+
+impl SchemaField {
+    /// Converts a value into the JSON type expected by the field type, if SchemaField.coerce is enabled:
+    /// - numeric fields: numeric strings (e.g. "42") to numbers, floats without fraction to integers
+    /// - Timestamp: numeric strings to numbers, date strings (see parse_date) to epoch seconds
+    /// - Date: numbers, interpreted as epoch seconds, to RFC 3339 strings (without coercion numbers are epoch milliseconds)
+    /// - Bool: "true"/"false" strings and the numbers 0/1 to booleans
+    /// - String, Keyword, Text: numbers and booleans to strings
+    /// - StringSet: a single string or number to a set with one string
+    ///
+    /// Values which can't be converted are returned unchanged, e.g. to be rejected by Index::validate_document.
+    /// Coercion is idempotent, as coerced documents are indexed again by compaction.
+    pub(crate) fn coerce_value<'a>(&self, value: &'a Value) -> Cow<'a, Value> {
+        if !self.coerce {
+            return Cow::Borrowed(value);
+        }
+
+        let coerced = match (&self.field_type, value) {
+            (
+                FieldType::U8
+                | FieldType::U16
+                | FieldType::U32
+                | FieldType::U64
+                | FieldType::I8
+                | FieldType::I16
+                | FieldType::I32
+                | FieldType::I64
+                | FieldType::Timestamp,
+                Value::String(string),
+            ) => {
+                let string = string.trim();
+                string
+                    .parse::<i64>()
+                    .map(Value::from)
+                    .or_else(|_| string.parse::<u64>().map(Value::from))
+                    .ok()
+                    .or_else(|| {
+                        if self.field_type == FieldType::Timestamp {
+                            parse_date(string, &self.date_formats)
+                                .map(|millis| Value::from(millis.div_euclid(1000)))
+                        } else {
+                            None
+                        }
+                    })
+            }
+            (
+                FieldType::U8
+                | FieldType::U16
+                | FieldType::U32
+                | FieldType::U64
+                | FieldType::I8
+                | FieldType::I16
+                | FieldType::I32
+                | FieldType::I64
+                | FieldType::Timestamp,
+                Value::Number(number),
+            ) if number.is_f64() => number
+                .as_f64()
+                .filter(|number| {
+                    number.fract() == 0.0
+                        && *number >= i64::MIN as f64
+                        && *number <= i64::MAX as f64
+                })
+                .map(|number| Value::from(number as i64)),
+            (FieldType::F32 | FieldType::F64, Value::String(string)) => {
+                string.trim().parse::<f64>().ok().map(Value::from)
+            }
+            (FieldType::Date, Value::Number(number)) => number
+                .as_i64()
+                .map(|seconds| seconds.saturating_mul(1000))
+                .or_else(|| {
+                    number
+                        .as_f64()
+                        .map(|seconds| (seconds * 1000.0).round() as i64)
+                })
+                .and_then(DateTime::from_timestamp_millis)
+                .map(|date| Value::from(date.to_rfc3339_opts(SecondsFormat::AutoSi, true))),
+            (FieldType::Bool, Value::String(string)) => {
+                match string.trim().to_lowercase().as_str() {
+                    "true" => Some(Value::Bool(true)),
+                    "false" => Some(Value::Bool(false)),
+                    _ => None,
+                }
+            }
+            (FieldType::Bool, Value::Number(number)) => match number.as_u64() {
+                Some(0) => Some(Value::Bool(false)),
+                Some(1) => Some(Value::Bool(true)),
+                _ => None,
+            },
+            (
+                FieldType::String | FieldType::Keyword | FieldType::Text,
+                Value::Number(_) | Value::Bool(_),
+            ) => Some(Value::from(value.to_string())),
+            (FieldType::StringSet, Value::String(_)) => Some(Value::Array(vec![value.clone()])),
+            (FieldType::StringSet, Value::Number(_)) => {
+                Some(Value::Array(vec![Value::from(value.to_string())]))
+            }
+            _ => None,
+        };
+
+        match coerced {
+            Some(coerced) => Cow::Owned(coerced),
+            None => Cow::Borrowed(value),
+        }
+    }
+
+    /// Whether JSON array values of the field are limited by SchemaField.max_values, or deduplicated as values of a StringSet field
+    fn is_multi_valued(&self) -> bool {
+        match self.field_type {
+            FieldType::StringSet => true,
+            FieldType::String | FieldType::Text | FieldType::Keyword => self.max_values.is_some(),
+            _ => false,
+        }
+    }
+
+    /// Removes duplicate values of a StringSet field (the first occurrence is kept), then the values beyond SchemaField.max_values,
+    /// so that the indexed, faceted and stored values are the same
+    fn limit_values(&self, values: &mut Vec<Value>) {
+        if self.field_type == FieldType::StringSet {
+            *values = values
+                .drain(..)
+                .unique_by(|value| match value {
+                    Value::String(string) => string.clone(),
+                    _ => value.to_string(),
+                })
+                .collect();
+        }
+        if let Some(max_values) = self.max_values {
+            values.truncate(max_values);
+        }
+    }
+}
+
+impl Index {
+    /// Applies the default values and the type coercion of the schema fields (SchemaField.default and SchemaField.coerce) to a document,
+    /// then evaluates the computed fields (SchemaField.computed) from the coerced fields.
+    /// Values of multi-valued fields are deduplicated (StringSet) and limited to SchemaField.max_values.
+    /// If a computed field has no value for the document (e.g. a field of its expression is missing), a value of the field in the document is kept, otherwise its default value is used.
+    /// Invoked by index_document before the document is indexed and stored, so the stored document contains the default, coerced and computed values.
+    pub fn apply_field_rules(&self, document: &mut Document) {
+        for schema_field in self.schema_map.values() {
+            if (!schema_field.coerce
+                && schema_field.default.is_none()
+                && !schema_field.is_multi_valued())
+                || schema_field.computed.is_some()
+            {
+                continue;
+            }
+
+            match document.get_mut(&schema_field.field) {
+                Some(value) if !value.is_null() => {
+                    if let Cow::Owned(coerced) = schema_field.coerce_value(value) {
+                        *value = coerced;
+                    }
+                }
+                _ => {
+                    if let Some(default) = schema_field.default.as_ref() {
+                        document.insert(
+                            schema_field.field.clone(),
+                            schema_field.coerce_value(default).into_owned(),
+                        );
+                    }
+                }
+            }
+
+            if schema_field.is_multi_valued() {
+                if let Some(Value::Array(values)) = document.get_mut(&schema_field.field) {
+                    schema_field.limit_values(values);
+                }
+            }
+        }
+
+        for (schema_field, computed_expression) in self.computed_fields.iter() {
+            match computed_expression.evaluate(document, &schema_field.field_type) {
+                Some(value) => {
+                    document.insert(schema_field.field.clone(), value);
+                }
+                None => {
+                    if document
+                        .get(&schema_field.field)
+                        .is_none_or(|value| value.is_null())
+                    {
+                        if let Some(default) = schema_field.default.as_ref() {
+                            document.insert(
+                                schema_field.field.clone(),
+                                schema_field.coerce_value(default).into_owned(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
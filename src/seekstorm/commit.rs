@@ -7,19 +7,23 @@ use std::{
     io::{Seek, SeekFrom, Write},
     path::Path,
     thread::available_parallelism,
+    time::{Duration, Instant},
 };
+use tokio::sync::RwLockWriteGuard;
 
 use crate::{
     add_result::{
         decode_positions_multiterm_multifield, decode_positions_multiterm_singlefield,
-        get_next_position_multifield, get_next_position_singlefield, B,
-        DOCUMENT_LENGTH_COMPRESSION, K,
+        get_next_position_multifield, get_next_position_singlefield,
     },
+    commit_hook::PendingCommitEvent,
+    compact::compact_index_if_due,
     compatible::{_blsr_u64, _mm_tzcnt_64},
     compress_postinglist::compress_postinglist,
+    error::SeekStormError,
     index::{
         update_list_max_impact_score, update_stopwords_posting_counts, warmup, AccessType,
-        BlockObjectIndex, CompressionType, Index, IndexArc, LevelIndex,
+        BlockObjectIndex, CompressionType, Durability, Index, IndexArc, LevelIndex,
         NonUniquePostingListObjectQuery, PostingListObjectIndex, PostingListObjectQuery,
         TermObject, FACET_VALUES_FILENAME, MAX_POSITIONS_PER_TERM, ROARING_BLOCK_SIZE, STOPWORDS,
     },
@@ -27,19 +31,22 @@ use crate::{
         block_copy, block_copy_mut, cast_byte_ulong_slice, cast_byte_ushort_slice, read_u16,
         read_u32, read_u64, read_u8, write_u16, write_u32, write_u64,
     },
+    verify::IndexChecksums,
 };
 
 pub(crate) const KEY_HEAD_SIZE: usize = 22;
 
 #[allow(async_fn_in_trait)]
 pub trait Commit {
-    async fn commit(&mut self);
+    async fn commit(&mut self) -> Result<(), SeekStormError>;
 }
 
 /// Commit moves indexed documents from the intermediate uncompressed data structure (array lists/HashMap, queryable by realtime search) in RAM
 /// to the final compressed data structure (roaring bitmap) on Mmap or disk -
 /// which is persistent, more compact, with lower query latency and allows search with realtime=false.
 /// Commit is invoked automatically each time 64K documents are newly indexed as well as on close_index (e.g. server quit).
+/// If the ratio of deleted documents reaches IndexMetaObject.compact_deleted_ratio, commit additionally compacts the index (see Compact) and returns the error of the compaction, if any.
+/// The automatic commit every 64K documents compacts the index in a background task.
 /// There is no way to prevent this automatic commit by not manually invoking it.
 /// But commit can also be invoked manually at any time at any number of newly indexed documents.
 /// commit is a **hard commit** for persistence on disk. A **soft commit** for searchability
@@ -47,6 +54,8 @@ pub trait Commit {
 /// i.e. the document can immediately searched and included in the search results
 /// if it matches the query AND the query paramter realtime=true is enabled.
 /// **Use commit with caution, as it is an expensive operation**.
+/// Building the new level (the merge into the last level and the posting list compression) blocks indexing and search,
+/// the facet values, checksums and the flush to stable storage are written afterwards, concurrent to searches.
 /// **Usually, there is no need to invoke it manually**, as it is invoked automatically every 64k documents and when the index is closed with close_index.
 /// Before terminating the program, always call close_index (commit), otherwise all documents indexed since last (manual or automatic) commit are lost.
 /// There are only 2 reasons that justify a manual commit:
@@ -73,30 +82,100 @@ impl Commit for IndexArc {
     /// 2. if after indexing new documents there won't be more documents indexed (for some time),
     ///    so there won't be (soon) a commit invoked automatically at the next 64k threshold or close_index,
     ///    but you still need immediate persistence guarantees on disk to protect against data loss in the event of a crash.
-    async fn commit(&mut self) {
-        let index_ref = self.read().await;
-        let index_permits = index_ref.permits.clone();
-        drop(index_ref);
-        let thread_number = available_parallelism().unwrap().get();
-        let mut permit_vec = Vec::new();
-        for _i in 0..thread_number {
-            permit_vec.push(index_permits.acquire().await.unwrap());
-        }
+    async fn commit(&mut self) -> Result<(), SeekStormError> {
+        commit_index(self).await;
+        compact_index_if_due(self).await?;
+        Ok(())
+    }
+}
+
+/// Waits until all documents indexed in parallel are finished, then commits them and warms up the index
+pub(crate) async fn commit_index(index_arc: &IndexArc) {
+    let index_ref = index_arc.read().await;
+    let index_permits = index_ref.permits.clone();
+    drop(index_ref);
+    let thread_number = available_parallelism().unwrap().get();
+    let mut permit_vec = Vec::new();
+    for _i in 0..thread_number {
+        permit_vec.push(index_permits.acquire().await.unwrap());
+    }
+
+    let mut index_mut = index_arc.write().await;
+    let indexed_doc_count = index_mut.indexed_doc_count;
+    let pending_commit = index_mut.commit_level(indexed_doc_count);
+    // a commit without new documents reports the documents deleted since the last commit
+    let commit_event = if pending_commit.is_none() {
+        let committed_doc_count = index_mut.committed_doc_count;
+        index_mut.take_commit_event(committed_doc_count..committed_doc_count)
+    } else {
+        None
+    };
+    finish_commit(index_arc, index_mut, pending_commit).await;
+    if let Some(commit_event) = commit_event {
+        commit_event.notify();
+    }
+}
 
-        let mut index_mut = self.write().await;
-        let indexed_doc_count = index_mut.indexed_doc_count;
-        index_mut.commit(indexed_doc_count);
-        drop(index_mut);
-        warmup(self).await;
+/// Persists a commit with the write lock downgraded to a read lock, so that searches are not blocked by the checksums and the flush to stable storage,
+/// then notifies the commit hook and warms up the index
+pub(crate) async fn finish_commit(
+    index_arc: &IndexArc,
+    index_mut: RwLockWriteGuard<'_, Index>,
+    pending_commit: Option<PendingCommit>,
+) {
+    let index_ref = index_mut.downgrade();
+    let persisted_commit = pending_commit.map(|pending_commit| {
+        (
+            index_ref.persist_commit(&pending_commit),
+            pending_commit.commit_event,
+        )
+    });
+    drop(index_ref);
+    if let Some((persisted_commit, commit_event)) = persisted_commit {
+        index_arc.write().await.complete_commit(persisted_commit);
+        if let Some(commit_event) = commit_event {
+            commit_event.notify();
+        }
     }
+    warmup(index_arc).await;
+}
+
+/// A commit whose level is searchable, but not yet persisted: facet values, checksums and the flush to stable storage (see Index::commit_level)
+pub(crate) struct PendingCommit {
+    committed_doc_count: usize,
+    commit_event: Option<PendingCommitEvent>,
+}
+
+/// A persisted commit, whose checksums and sync time are applied to the index under a short write lock (see Index::complete_commit)
+pub(crate) struct PersistedCommit {
+    checksums: Option<IndexChecksums>,
+    synced: bool,
 }
 
 impl Index {
+    /// Commits synchronously, all phases under the write lock of the caller, e.g. by close_index
     pub(crate) fn commit(&mut self, indexed_doc_count: usize) {
+        let commit_event = if let Some(pending_commit) = self.commit_level(indexed_doc_count) {
+            let persisted_commit = self.persist_commit(&pending_commit);
+            self.complete_commit(persisted_commit);
+            pending_commit.commit_event
+        } else {
+            let committed_doc_count = self.committed_doc_count;
+            self.take_commit_event(committed_doc_count..committed_doc_count)
+        };
+        if let Some(commit_event) = commit_event {
+            commit_event.notify();
+        }
+    }
+
+    /// First phase of a commit, under the write lock: moves the uncommitted documents into a new or the merged last level, which becomes searchable with realtime=false.
+    /// The remaining work, which doesn't change the searchable state, is returned as PendingCommit for persist_commit.
+    pub(crate) fn commit_level(&mut self, indexed_doc_count: usize) -> Option<PendingCommit> {
         if !self.uncommitted {
-            return;
+            return None;
         }
         let new_document_count = indexed_doc_count - self.committed_doc_count;
+        let committed_doc_ids = self.committed_doc_count..indexed_doc_count;
 
         let is_last_level_incomplete = self.is_last_level_incomplete;
         if self.is_last_level_incomplete {
@@ -189,11 +268,7 @@ impl Index {
         self.document_length_normalized_average =
             self.positions_sum_normalized as f32 / indexed_doc_count as f32;
 
-        for (i, component) in self.bm25_component_cache.iter_mut().enumerate() {
-            let document_length_quotient =
-                DOCUMENT_LENGTH_COMPRESSION[i] as f32 / self.document_length_normalized_average;
-            *component = K * (1.0 - B + B * document_length_quotient);
-        }
+        self.update_bm25_component_cache();
 
         for k0 in 0..self.segment_number1 {
             let strip_compressed = self.commit_segment(k0);
@@ -216,6 +291,8 @@ impl Index {
                 document_length_compressed_array_pointer,
                 docstore_pointer_docs: Vec::new(),
                 docstore_pointer_docs_pointer: 0,
+                index_file_start_pos: self.last_level_index_file_start_pos,
+                docstore_file_start_pos: self.last_level_docstore_file_start_pos,
             });
         }
 
@@ -259,21 +336,25 @@ impl Index {
                 self.facets_file_mmap =
                     unsafe { MmapMut::map_mut(&self.facets_file).expect("Unable to create Mmap") };
             }
-
-            let index_path = Path::new(&self.index_path_string);
-            serde_json::to_writer(
-                &File::create(Path::new(index_path).join(FACET_VALUES_FILENAME)).unwrap(),
-                &self.facets,
-            )
-            .unwrap();
         }
 
         self.string_set_to_single_term_id();
 
+        if let Err(e) = self.commit_geo_shapes() {
+            println!("geo shapes not committed: {}", e);
+        }
+        if let Err(e) = self.commit_joins() {
+            println!("joins not committed: {}", e);
+        }
+
         update_list_max_impact_score(self);
 
         self.committed_doc_count = indexed_doc_count;
         self.is_last_level_incomplete = (self.committed_doc_count) % ROARING_BLOCK_SIZE > 0;
+        if let Err(e) = self.record_level_commit(is_last_level_incomplete) {
+            println!("level history not updated: {}", e);
+        }
+        self.unsynced = true;
         if !self.mute {
             println!(
                 "commit level {} committed documents {} {} mode {}",
@@ -300,6 +381,64 @@ impl Index {
         }
 
         self.uncommitted = false;
+
+        Some(PendingCommit {
+            committed_doc_count: indexed_doc_count,
+            commit_event: self.take_commit_event(committed_doc_ids),
+        })
+    }
+
+    /// Second phase of a commit, under a read lock concurrent to searches: writes the facet values, computes the checksums of the changed levels
+    /// and flushes the index files to stable storage, according to the durability policy.
+    pub(crate) fn persist_commit(&self, pending_commit: &PendingCommit) -> PersistedCommit {
+        if !self.facets.is_empty() {
+            let index_path = Path::new(&self.index_path_string);
+            serde_json::to_writer(
+                &File::create(index_path.join(FACET_VALUES_FILENAME)).unwrap(),
+                &self.facets,
+            )
+            .unwrap();
+        }
+
+        let checksums = match self
+            .level_checksums(pending_commit.committed_doc_count)
+            .and_then(|checksums| self.save_checksums(&checksums).map(|_| checksums))
+        {
+            Ok(checksums) => Some(checksums),
+            Err(e) => {
+                println!("checksums not updated: {}", e);
+                None
+            }
+        };
+
+        let synced = match self.meta.durability {
+            Durability::Os => false,
+            Durability::Commit => true,
+            Durability::Periodic => {
+                self.last_sync.elapsed() >= Duration::from_millis(self.meta.sync_interval_ms)
+            }
+        } && match self.sync_files() {
+            Ok(()) => true,
+            Err(e) => {
+                println!("index files not flushed to disk: {}", e);
+                false
+            }
+        };
+
+        PersistedCommit { checksums, synced }
+    }
+
+    /// Third phase of a commit, under a short write lock. The checksums of a concurrent, more recent commit are not replaced.
+    /// The index remains marked as unsynced, as documents may have been deleted after the flush to stable storage.
+    pub(crate) fn complete_commit(&mut self, persisted_commit: PersistedCommit) {
+        if let Some(checksums) = persisted_commit.checksums {
+            if checksums.committed_doc_count >= self.checksums.committed_doc_count {
+                self.checksums = checksums;
+            }
+        }
+        if persisted_commit.synced {
+            self.last_sync = Instant::now();
+        }
     }
 
     /// Flush a single segment from the key hash range partitioned level to RAM and disk
@@ -768,7 +907,7 @@ impl Index {
         }
 
         let block_id = self.level_index.len() - 1;
-        let committed_doc_count = (self.committed_doc_count - 1 % ROARING_BLOCK_SIZE) + 1;
+        let committed_doc_count = ((self.committed_doc_count - 1) % ROARING_BLOCK_SIZE) + 1;
 
         for i in 0..self.indexed_field_vec.len() {
             if self.meta.access_type == AccessType::Mmap {
@@ -825,6 +964,8 @@ impl Index {
                     let mut pointer_first_new = 0;
                     let mut pointer_last_new = 0;
                     let mut pointer_first_old = 0;
+                    let mut docid_old_new = 0;
+                    let mut docid_delta_max_new = 0;
                     let merge = match self.segments_level0[key0].segment.get_mut(&key_hash) {
                         Some(plo0) => {
                             pointer_pivot_p_docid_new = plo0.pointer_pivot_p_docid;
@@ -832,6 +973,12 @@ impl Index {
                             plo0.pointer_pivot_p_docid = 0;
                             plo0.size_compressed_positions_key = 0;
 
+                            // the committed postings precede the uncommitted postings: their docid deltas start again from the begin of the block
+                            docid_old_new = plo0.docid_old;
+                            docid_delta_max_new = plo0.docid_delta_max;
+                            plo0.docid_old = u16::MAX;
+                            plo0.docid_delta_max = 0;
+
                             pointer_first_new = plo0.pointer_first;
                             pointer_last_new = plo0.pointer_last;
                             pointer_first_old = self.postings_buffer_pointer;
@@ -865,6 +1012,8 @@ impl Index {
                         };
 
                         plo0.size_compressed_positions_key = size_compressed_positions_key_new;
+                        plo0.docid_old = docid_old_new;
+                        plo0.docid_delta_max = plo0.docid_delta_max.max(docid_delta_max_new);
 
                         let pointer_last_old = plo0.pointer_last;
                         plo0.pointer_first = pointer_first_old;
@@ -902,6 +1051,8 @@ impl Index {
                     let mut pointer_first_new = 0;
                     let mut pointer_last_new = 0;
                     let mut pointer_first_old = 0;
+                    let mut docid_old_new = 0;
+                    let mut docid_delta_max_new = 0;
                     let merge = match self.segments_level0[key0].segment.get_mut(&key_hash) {
                         Some(plo0) => {
                             pointer_pivot_p_docid_new = plo0.pointer_pivot_p_docid;
@@ -909,6 +1060,12 @@ impl Index {
                             plo0.pointer_pivot_p_docid = 0;
                             plo0.size_compressed_positions_key = 0;
 
+                            // the committed postings precede the uncommitted postings: their docid deltas start again from the begin of the block
+                            docid_old_new = plo0.docid_old;
+                            docid_delta_max_new = plo0.docid_delta_max;
+                            plo0.docid_old = u16::MAX;
+                            plo0.docid_delta_max = 0;
+
                             pointer_first_new = plo0.pointer_first;
                             pointer_last_new = plo0.pointer_last;
                             pointer_first_old = self.postings_buffer_pointer;
@@ -941,6 +1098,8 @@ impl Index {
                             pointer_pivot_p_docid + pointer_pivot_p_docid_new
                         };
                         plo0.size_compressed_positions_key = size_compressed_positions_key_new;
+                        plo0.docid_old = docid_old_new;
+                        plo0.docid_delta_max = plo0.docid_delta_max.max(docid_delta_max_new);
 
                         let pointer_last_old = plo0.pointer_last;
                         plo0.pointer_first = pointer_first_old;
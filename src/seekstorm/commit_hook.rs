@@ -0,0 +1,109 @@
+use std::{ops::Range, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::Index;
+
+/// Document ids changed by a commit, passed to the CommitHook of the index after the commit succeeded
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct CommitEvent {
+    pub index_id: u64,
+    /// Ids of the documents committed by this commit, including the new versions of updated documents. Documents deleted before the commit are not included.
+    pub added_doc_ids: Vec<usize>,
+    /// Ids of the previous versions of the documents updated since the last commit: an update deletes the previous version, the new version gets a new document id (see added_doc_ids)
+    pub updated_doc_ids: Vec<usize>,
+    /// Ids of the documents deleted since the last commit, which are not contained in updated_doc_ids
+    pub deleted_doc_ids: Vec<usize>,
+}
+
+/// Notifies the application after every successful commit of an index (manual, automatic every 64K documents, and on close_index) with the changed document ids,
+/// e.g. to invalidate downstream caches or to trigger notifications.
+/// The hook is invoked synchronously after the commit, without a lock of the index held: long-running work should be handed off, e.g. to a channel.
+/// Deletions are immediately effective, independent of commit, but are reported with the next commit. A commit with only deletions is reported too.
+pub trait CommitHook: Send + Sync {
+    fn on_commit(&self, event: &CommitEvent);
+}
+
+/// A commit event to be passed to the commit hook once the commit has completed
+pub(crate) struct PendingCommitEvent {
+    commit_hook: Arc<dyn CommitHook>,
+    event: CommitEvent,
+}
+
+impl PendingCommitEvent {
+    pub(crate) fn notify(self) {
+        self.commit_hook.on_commit(&self.event);
+    }
+}
+
+impl Index {
+    /// Set/remove the commit hook of the index, which is invoked after every subsequent commit.
+    /// Only changes after the hook is set are reported. The hook is not persisted: it has to be set again after the index is opened.
+    pub fn set_commit_hook(&mut self, commit_hook: Option<Arc<dyn CommitHook>>) {
+        if commit_hook.is_none() {
+            self.pending_deleted_doc_ids.clear();
+            self.pending_updated_doc_ids.clear();
+        }
+        self.commit_hook = commit_hook;
+    }
+
+    /// Records deleted documents for the commit event, if a commit hook is set
+    pub(crate) fn record_deleted_doc_id(&mut self, doc_id: usize) {
+        if self.commit_hook.is_some() {
+            self.pending_deleted_doc_ids.push(doc_id);
+        }
+    }
+
+    /// Reports deleted documents as updated in the commit event, if they are deleted as previous versions of updated documents
+    pub(crate) fn record_updated_doc_ids(&mut self, doc_ids: &[u64]) {
+        if self.commit_hook.is_none() {
+            return;
+        }
+        for doc_id in doc_ids {
+            if let Some(position) = self
+                .pending_deleted_doc_ids
+                .iter()
+                .position(|deleted_doc_id| *deleted_doc_id == *doc_id as usize)
+            {
+                self.pending_deleted_doc_ids.swap_remove(position);
+                self.pending_updated_doc_ids.push(*doc_id as usize);
+            }
+        }
+    }
+
+    /// Takes the changes recorded since the last commit together with the documents committed now, as event for the commit hook.
+    /// Documents added and deleted (or updated) within the same commit are not reported. Returns None if there is no commit hook or nothing changed.
+    pub(crate) fn take_commit_event(
+        &mut self,
+        committed_doc_ids: Range<usize>,
+    ) -> Option<PendingCommitEvent> {
+        let commit_hook = self.commit_hook.clone()?;
+        let previous_committed_doc_count = committed_doc_ids.start;
+        let added_doc_ids: Vec<usize> = committed_doc_ids
+            .filter(|doc_id| !self.delete_hashset.contains(doc_id))
+            .collect();
+        let mut updated_doc_ids: Vec<usize> = std::mem::take(&mut self.pending_updated_doc_ids)
+            .into_iter()
+            .filter(|doc_id| *doc_id < previous_committed_doc_count)
+            .collect();
+        let mut deleted_doc_ids: Vec<usize> = std::mem::take(&mut self.pending_deleted_doc_ids)
+            .into_iter()
+            .filter(|doc_id| *doc_id < previous_committed_doc_count)
+            .collect();
+        if added_doc_ids.is_empty() && updated_doc_ids.is_empty() && deleted_doc_ids.is_empty() {
+            return None;
+        }
+        updated_doc_ids.sort_unstable();
+        deleted_doc_ids.sort_unstable();
+
+        Some(PendingCommitEvent {
+            commit_hook,
+            event: CommitEvent {
+                index_id: self.meta.id,
+                added_doc_ids,
+                updated_doc_ids,
+                deleted_doc_ids,
+            },
+        })
+    }
+}
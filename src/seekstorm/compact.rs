@@ -0,0 +1,374 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    future::Future,
+    io::BufReader,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    thread::available_parallelism,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, RwLock};
+
+use crate::{
+    commit::commit_index,
+    error::SeekStormError,
+    export::copy_dir,
+    geo_shape::GEO_SHAPES_FILENAME,
+    index::{
+        create_index, index_document_notify, warmup, DeleteDocuments, FileType, Index, IndexArc,
+        SchemaField, CHECKSUMS_FILENAME, DELETE_FILENAME, DOCSTORE_FILENAME, FACET_FILENAME,
+        FACET_VALUES_FILENAME, FILE_PATH, INDEX_FILENAME, SCHEMA_FILENAME,
+    },
+    join::JOINS_FILENAME,
+    level::LEVELS_FILENAME,
+};
+
+/// Sibling directory `<index_path>.compacting` of the index directory, where the compacted index is built
+const COMPACTING_EXTENSION: &str = "compacting";
+/// The completely built compacted index `<index_path>.compacted`, which replaces the index directory
+const COMPACTED_EXTENSION: &str = "compacted";
+/// The replaced index directory `<index_path>.replaced`, which is removed after the compacted index is opened
+const REPLACED_EXTENSION: &str = "replaced";
+
+/// Index files which are rebuilt by the compaction, all other files of the index directory are copied into the compacted index
+const COMPACTED_FILENAMES: [&str; 9] = [
+    INDEX_FILENAME,
+    DOCSTORE_FILENAME,
+    DELETE_FILENAME,
+    FACET_FILENAME,
+    FACET_VALUES_FILENAME,
+    CHECKSUMS_FILENAME,
+    LEVELS_FILENAME,
+    GEO_SHAPES_FILENAME,
+    JOINS_FILENAME,
+];
+
+/// Result of a compaction
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CompactResult {
+    /// Number of deleted documents removed from the index
+    pub deleted_doc_count: usize,
+    /// Number of documents in the compacted index
+    pub indexed_doc_count: usize,
+}
+
+/// Compaction (expunge deletes): rewrites the index without deleted documents.
+/// Deleted and updated documents leave tombstones: they still occupy index space, are still processed (and then skipped) during search,
+/// and are still included in the BM25 statistics. Compaction reclaims the space and restores the query performance.
+/// All live documents are reindexed from the document store into a new index in the sibling directory `<index_path>.compacting`,
+/// which then replaces the index directory.
+/// * Requires that all indexed fields are stored.
+/// * Document ids are reassigned: documents get new, consecutive document ids.
+/// * Uncommitted documents are committed first. Indexing, deletes and search continue during the reindexing,
+///   only applying the documents indexed and deleted meanwhile and swapping the index directories block the index.
+/// * A compaction interrupted by a crash is completed or rolled back when the index is opened.
+/// * Invoked automatically by commit, if the ratio of deleted documents reaches IndexMetaObject.compact_deleted_ratio.
+#[allow(async_fn_in_trait)]
+pub trait Compact {
+    async fn compact(&self) -> Result<CompactResult, SeekStormError>;
+}
+
+impl Compact for IndexArc {
+    async fn compact(&self) -> Result<CompactResult, SeekStormError> {
+        self.read().await.check_writable()?;
+        compact_index(self).await
+    }
+}
+
+/// Compaction, also of frozen indices
+pub(crate) async fn compact_index(index_arc: &IndexArc) -> Result<CompactResult, SeekStormError> {
+    let mut index_mut = index_arc.write().await;
+    if let Some(schema_field) = index_mut
+        .indexed_schema_vec
+        .iter()
+        .find(|schema_field| !schema_field.stored)
+    {
+        return Err(SeekStormError::InvalidRequest(format!(
+            "compaction requires all indexed fields to be stored: {}",
+            schema_field.field
+        )));
+    }
+    if index_mut.compacting {
+        return Err(SeekStormError::Conflict(
+            "index is already being compacted".to_string(),
+        ));
+    }
+    index_mut.compacting = true;
+    drop(index_mut);
+
+    compact_claimed_index(index_arc).await
+}
+
+/// Compacts the index if the ratio of deleted documents reached IndexMetaObject.compact_deleted_ratio (see Commit::commit)
+pub(crate) async fn compact_index_if_due(
+    index_arc: &IndexArc,
+) -> Result<Option<CompactResult>, SeekStormError> {
+    let mut index_mut = index_arc.write().await;
+    if !index_mut.is_compaction_due() {
+        return Ok(None);
+    }
+    index_mut.compacting = true;
+    drop(index_mut);
+
+    compact_claimed_index(index_arc).await.map(Some)
+}
+
+/// Compacts the index in a background task, if the ratio of deleted documents reached IndexMetaObject.compact_deleted_ratio.
+/// Used by the automatic commit every 64K documents, so that the indexing of the document which triggered the commit is not delayed by the compaction.
+pub(crate) async fn spawn_compaction_if_due(index_arc: &IndexArc) {
+    let mut index_mut = index_arc.write().await;
+    if !index_mut.is_compaction_due() {
+        return;
+    }
+    index_mut.compacting = true;
+    drop(index_mut);
+
+    tokio::spawn(background_compaction(index_arc.clone()));
+}
+
+/// The compaction task of spawn_compaction_if_due. Its future is boxed, as the compaction indexes documents, whose indexing may spawn the compaction.
+fn background_compaction(index_arc: IndexArc) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        if let Err(e) = compact_claimed_index(&index_arc).await {
+            println!("index not compacted: {}", e);
+        }
+    })
+}
+
+/// Compaction of an index whose compacting flag was set by the caller, the flag is reset when the compaction is finished.
+/// If the compaction fails, the working directories are removed, or the index directory is restored from them (see recover_compaction).
+async fn compact_claimed_index(index_arc: &IndexArc) -> Result<CompactResult, SeekStormError> {
+    let result = rebuild_index(index_arc).await;
+    let mut index_mut = index_arc.write().await;
+    index_mut.compacting = false;
+    if result.is_err() {
+        let _ = recover_compaction(Path::new(&index_mut.index_path_string));
+    }
+    result
+}
+
+/// Reindexes the live documents into the sibling directory under short read locks,
+/// then applies the documents indexed and deleted meanwhile and swaps the index directories under the write lock
+async fn rebuild_index(index_arc: &IndexArc) -> Result<CompactResult, SeekStormError> {
+    commit_index(index_arc).await;
+
+    let index_ref = index_arc.read().await;
+    if index_ref.delete_hashset.is_empty() {
+        return Ok(CompactResult {
+            deleted_doc_count: 0,
+            indexed_doc_count: index_ref.indexed_doc_count,
+        });
+    }
+
+    let doc_id_generation = index_ref.doc_id_generation;
+    let reindexed_doc_count = index_ref.indexed_doc_count;
+    let reindexed_deleted = index_ref.delete_hashset.clone();
+    let index_path = PathBuf::from(&index_ref.index_path_string);
+    let file_path = index_path.join(FILE_PATH);
+    let compacting_path = sibling_path(&index_path, COMPACTING_EXTENSION);
+    let compacted_path = sibling_path(&index_path, COMPACTED_EXTENSION);
+    let replaced_path = sibling_path(&index_path, REPLACED_EXTENSION);
+    let _ = fs::remove_dir_all(&compacting_path);
+    fs::create_dir_all(&compacting_path)?;
+
+    let schema: Vec<SchemaField> = serde_json::from_reader(BufReader::new(File::open(
+        index_path.join(SCHEMA_FILENAME),
+    )?))
+    .map_err(|e| SeekStormError::Internal(e.to_string()))?;
+    let synonyms = index_ref.get_synonyms().unwrap_or_default();
+
+    let compact_index_arc: IndexArc = Arc::new(RwLock::new(create_index(
+        &compacting_path,
+        index_ref.meta.clone(),
+        &schema,
+        true,
+        &synonyms,
+        index_ref.segment_number_bits1,
+        true,
+    )?));
+    drop(index_ref);
+
+    let pdf_file = |doc_id: usize| {
+        let pdf_path = file_path.join(doc_id.to_string() + ".pdf");
+        if pdf_path.exists() {
+            FileType::Path(pdf_path.into_boxed_path())
+        } else {
+            FileType::None
+        }
+    };
+
+    // the document ids in the compacted index, to apply the documents deleted during the reindexing
+    let mut doc_id_receivers = Vec::new();
+    for doc_id in 0..reindexed_doc_count {
+        if reindexed_deleted.contains(&doc_id) {
+            continue;
+        }
+        let Ok(document) =
+            index_arc
+                .read()
+                .await
+                .get_document(doc_id, true, &None, &HashSet::new(), &[])
+        else {
+            continue;
+        };
+
+        let (doc_id_sender, doc_id_receiver) = oneshot::channel();
+        index_document_notify(
+            &compact_index_arc,
+            document,
+            pdf_file(doc_id),
+            Some(doc_id_sender),
+        )
+        .await;
+        doc_id_receivers.push((doc_id, doc_id_receiver));
+    }
+    let mut compacted_doc_ids = HashMap::new();
+    for (doc_id, doc_id_receiver) in doc_id_receivers {
+        if let Ok(compacted_doc_id) = doc_id_receiver.await {
+            compacted_doc_ids.insert(doc_id, compacted_doc_id);
+        }
+    }
+
+    let index_permits = index_arc.read().await.permits.clone();
+    let thread_number = available_parallelism().unwrap().get();
+    let mut permit_vec = Vec::new();
+    for _i in 0..thread_number {
+        permit_vec.push(index_permits.acquire().await.unwrap());
+    }
+    let mut index_mut = index_arc.write().await;
+    if index_mut.doc_id_generation != doc_id_generation {
+        return Err(SeekStormError::Conflict(
+            "the document ids changed during the compaction".to_string(),
+        ));
+    }
+
+    let indexed_doc_count = index_mut.indexed_doc_count;
+    index_mut.commit(indexed_doc_count);
+    for doc_id in reindexed_doc_count..indexed_doc_count {
+        let Ok(document) = index_mut.get_document(doc_id, true, &None, &HashSet::new(), &[]) else {
+            continue;
+        };
+        index_document_notify(&compact_index_arc, document, pdf_file(doc_id), None).await;
+    }
+    let deleted_doc_ids = index_mut
+        .delete_hashset
+        .iter()
+        .filter(|doc_id| **doc_id < reindexed_doc_count && !reindexed_deleted.contains(doc_id))
+        .filter_map(|doc_id| compacted_doc_ids.get(doc_id))
+        .map(|doc_id| *doc_id as u64)
+        .collect::<Vec<_>>();
+    compact_index_arc.delete_documents(deleted_doc_ids).await;
+    commit_index(&compact_index_arc).await;
+    drop(compact_index_arc);
+
+    for entry in fs::read_dir(&index_path)? {
+        let entry = entry?;
+        let filename = entry.file_name();
+        if filename == FILE_PATH || COMPACTED_FILENAMES.iter().any(|name| filename == *name) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &compacting_path.join(&filename))?;
+        } else {
+            fs::copy(entry.path(), compacting_path.join(&filename))?;
+        }
+    }
+
+    // the compacted index is complete: from here on, it replaces the index directory, also if interrupted by a crash (see recover_compaction)
+    fs::rename(&compacting_path, &compacted_path)?;
+    fs::rename(&index_path, &replaced_path)?;
+    fs::rename(&compacted_path, &index_path)?;
+
+    let old_deleted_doc_count = index_mut.delete_hashset.len();
+    let frozen = index_mut.frozen;
+    let generation = index_mut.level_generation() + 1;
+    index_mut.reopen_index().await?;
+    index_mut.frozen = frozen;
+    index_mut.set_level_generation(generation)?;
+    let _ = fs::remove_dir_all(&replaced_path);
+
+    // documents deleted during the reindexing remain as deleted documents in the compacted index
+    let deleted_doc_count = old_deleted_doc_count - index_mut.delete_hashset.len();
+    let indexed_doc_count = index_mut.indexed_doc_count;
+    drop(index_mut);
+    drop(permit_vec);
+    warmup(index_arc).await;
+
+    if !index_arc.read().await.mute {
+        println!(
+            "index compacted: deleted documents removed {} documents {}",
+            deleted_doc_count, indexed_doc_count
+        );
+    }
+
+    Ok(CompactResult {
+        deleted_doc_count,
+        indexed_doc_count,
+    })
+}
+
+/// Path of a sibling working directory of the index directory: `<index_path>.<extension>`
+fn sibling_path(index_path: &Path, extension: &str) -> PathBuf {
+    let mut dirname = index_path.file_name().unwrap_or_default().to_os_string();
+    dirname.push(".");
+    dirname.push(extension);
+    index_path.with_file_name(dirname)
+}
+
+/// Completes or rolls back a compaction interrupted by a crash, before the index is opened:
+/// if the index directory was already replaced, the compacted index takes its place (or the replaced index directory, if the compacted index was not yet complete),
+/// otherwise the working directories of the compaction are removed.
+pub(crate) fn recover_compaction(index_path: &Path) -> Result<(), SeekStormError> {
+    for extension in [COMPACTED_EXTENSION, REPLACED_EXTENSION] {
+        let path = sibling_path(index_path, extension);
+        if path.exists() {
+            if index_path.exists() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::rename(&path, index_path)?;
+            }
+        }
+    }
+    let compacting_path = sibling_path(index_path, COMPACTING_EXTENSION);
+    if compacting_path.exists() {
+        fs::remove_dir_all(&compacting_path)?;
+    }
+    Ok(())
+}
+
+/// The index directory of a working directory of the compaction, e.g. to open an index whose directory was replaced
+/// when the compaction was interrupted by a crash (see recover_compaction)
+pub fn compaction_index_path(path: &Path) -> Option<PathBuf> {
+    let extension = path.extension()?;
+    [
+        COMPACTING_EXTENSION,
+        COMPACTED_EXTENSION,
+        REPLACED_EXTENSION,
+    ]
+    .contains(&extension.to_str()?)
+    .then(|| path.with_extension(""))
+}
+
+impl Index {
+    /// Number of deleted documents, whose index space is not yet reclaimed by compaction
+    pub fn deleted_doc_count(&self) -> usize {
+        self.delete_hashset.len()
+    }
+
+    /// The ratio of deleted documents reached IndexMetaObject.compact_deleted_ratio, and the index can be compacted (all indexed fields are stored)
+    pub(crate) fn is_compaction_due(&self) -> bool {
+        !self.frozen
+            && !self.compacting
+            && self.meta.compact_deleted_ratio > 0.0
+            && self.indexed_doc_count > 0
+            && self.delete_hashset.len() as f32 / self.indexed_doc_count as f32
+                >= self.meta.compact_deleted_ratio
+            && self
+                .indexed_schema_vec
+                .iter()
+                .all(|schema_field| schema_field.stored)
+    }
+}
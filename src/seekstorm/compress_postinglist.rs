@@ -3,9 +3,9 @@ use std::cmp;
 use smallvec::SmallVec;
 
 use crate::{
-    add_result::{decode_positions_commit, B, DOCUMENT_LENGTH_COMPRESSION, K, SIGMA},
+    add_result::{decode_positions_commit, DOCUMENT_LENGTH_COMPRESSION, SIGMA},
     compatible::_lzcnt_u32,
-    index::{CompressionType, Index, SimilarityType, STOPWORDS, STOP_BIT},
+    index::{bm25_component, CompressionType, Index, SimilarityType, STOPWORDS, STOP_BIT},
     utils::{
         block_copy, read_u16_ref, read_u32_ref, write_u16, write_u16_ref, write_u32_ref,
         write_u64_ref, write_u8_ref,
@@ -336,7 +336,13 @@ pub(crate) fn docid_iterator(
             let weight = index.indexed_schema_vec[field.0 as usize].boost;
 
             posting_score += weight
-                * ((tf * (K + 1.0) / (tf + (K * (1.0 - B + (B * document_length_quotient_doc)))))
+                * ((tf * (index.meta.k1 + 1.0)
+                    / (tf
+                        + bm25_component(
+                            &index.meta,
+                            &index.indexed_schema_vec[field.0 as usize],
+                            document_length_quotient_doc,
+                        )))
                     + SIGMA);
         }
 
@@ -372,8 +378,13 @@ pub(crate) fn docid_iterator(
 
             posting_score += weight
                 * idf_bigram1
-                * ((tf_bigram1 * (K + 1.0)
-                    / (tf_bigram1 + (K * (1.0 - B + (B * document_length_quotient_doc)))))
+                * ((tf_bigram1 * (index.meta.k1 + 1.0)
+                    / (tf_bigram1
+                        + bm25_component(
+                            &index.meta,
+                            &index.indexed_schema_vec[field.0 as usize],
+                            document_length_quotient_doc,
+                        )))
                     + SIGMA);
         }
 
@@ -391,8 +402,13 @@ pub(crate) fn docid_iterator(
 
             posting_score += weight
                 * idf_bigram2
-                * ((tf_bigram2 * (K + 1.0)
-                    / (tf_bigram2 + (K * (1.0 - B + (B * document_length_quotient_doc)))))
+                * ((tf_bigram2 * (index.meta.k1 + 1.0)
+                    / (tf_bigram2
+                        + bm25_component(
+                            &index.meta,
+                            &index.indexed_schema_vec[field.0 as usize],
+                            document_length_quotient_doc,
+                        )))
                     + SIGMA);
         }
 
@@ -0,0 +1,464 @@
+use std::{collections::HashMap, iter::Peekable, str::CharIndices};
+
+use chrono::{DateTime, Datelike, SecondsFormat, Timelike, Utc};
+use serde_json::Value;
+
+use crate::{
+    date::{date_from_json, parse_date},
+    index::{Document, FieldType, SchemaField},
+    utils::decimal_from_json,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Concat,
+    Lower,
+    Upper,
+    Trim,
+    Length,
+    Year,
+    Month,
+    Day,
+    Hour,
+    Weekday,
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+    Min,
+    Max,
+    Coalesce,
+}
+
+impl Function {
+    /// Function and its number of arguments, None for a variable number of arguments (at least one)
+    fn from_name(name: &str) -> Option<(Function, Option<usize>)> {
+        match name {
+            "concat" => Some((Function::Concat, None)),
+            "lower" => Some((Function::Lower, Some(1))),
+            "upper" => Some((Function::Upper, Some(1))),
+            "trim" => Some((Function::Trim, Some(1))),
+            "len" => Some((Function::Length, Some(1))),
+            "year" => Some((Function::Year, Some(1))),
+            "month" => Some((Function::Month, Some(1))),
+            "day" => Some((Function::Day, Some(1))),
+            "hour" => Some((Function::Hour, Some(1))),
+            "weekday" => Some((Function::Weekday, Some(1))),
+            "abs" => Some((Function::Abs, Some(1))),
+            "round" => Some((Function::Round, Some(1))),
+            "floor" => Some((Function::Floor, Some(1))),
+            "ceil" => Some((Function::Ceil, Some(1))),
+            "min" => Some((Function::Min, Some(2))),
+            "max" => Some((Function::Max, Some(2))),
+            "coalesce" => Some((Function::Coalesce, None)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(f64),
+    String(String),
+    /// field of the document: Date fields are evaluated as epoch milliseconds, Timestamp fields as epoch seconds
+    Field(SchemaField),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Function(Function, Vec<Node>),
+}
+
+/// Intermediate value of an expression
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Number(f64),
+    String(String),
+}
+
+/// Expression of a computed field (SchemaField.computed), evaluated against the fields of a document at index time.
+/// Operands: numbers, string literals in single or double quotes, and the names of schema fields which are not computed themselves.
+/// Date fields are evaluated as epoch milliseconds, Timestamp fields as epoch seconds, Bool fields as 1 or 0.
+/// Operators: + - * / and parentheses. + concatenates, if one of the operands is a string.
+/// Functions: concat(x, ...), lower(x), upper(x), trim(x), len(x), year(date), month(date), day(date), hour(date), weekday(date) (1 = Monday ... 7 = Sunday),
+/// abs(x), round(x), floor(x), ceil(x), min(x,y), max(x,y), coalesce(x, ...) (first value which is not missing).
+/// Dates are Date or Timestamp fields, date strings, or epoch milliseconds.
+/// If a field is missing in the document, or an operation is not defined for its operands (e.g. division by zero), the expression has no value.
+/// Example: `year(published_at)`, `concat(brand, ' ', model)`, `price * quantity`
+#[derive(Debug, Clone)]
+pub struct ComputedExpression {
+    root: Node,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    schema_map: &'a HashMap<String, SchemaField>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars.peek().map_or(self.text.len(), |(pos, _)| *pos)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.peek() == Some(expected) {
+            self.chars.next();
+            Ok(())
+        } else {
+            Err(format!(
+                "computed field: '{}' expected at position {}",
+                expected,
+                self.position()
+            ))
+        }
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn parse_expression(&mut self) -> Result<Node, String> {
+        let mut node = self.parse_term()?;
+        loop {
+            let operator = match self.peek() {
+                Some('+') => Operator::Add,
+                Some('-') => Operator::Subtract,
+                _ => return Ok(node),
+            };
+            self.chars.next();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.parse_term()?));
+        }
+    }
+
+    /// term := factor (('*' | '/') factor)*
+    fn parse_term(&mut self) -> Result<Node, String> {
+        let mut node = self.parse_factor()?;
+        loop {
+            let operator = match self.peek() {
+                Some('*') => Operator::Multiply,
+                Some('/') => Operator::Divide,
+                _ => return Ok(node),
+            };
+            self.chars.next();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.parse_factor()?));
+        }
+    }
+
+    /// factor := number | string | field | function '(' expression (',' expression)* ')' | '(' expression ')' | '-' factor
+    fn parse_factor(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some('-') => {
+                self.chars.next();
+                Ok(Node::Negate(Box::new(self.parse_factor()?)))
+            }
+            Some('(') => {
+                self.chars.next();
+                let node = self.parse_expression()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some(quote @ ('\'' | '"')) => {
+                self.chars.next();
+                let start = self.position();
+                while self.chars.next_if(|(_, c)| *c != quote).is_some() {}
+                let string = self.text[start..self.position()].to_string();
+                if self.chars.next().is_none() {
+                    return Err("computed field: unterminated string".to_string());
+                }
+                Ok(Node::String(string))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.position();
+                while self
+                    .chars
+                    .next_if(|(_, c)| c.is_ascii_digit() || *c == '.')
+                    .is_some()
+                {}
+                let number = &self.text[start..self.position()];
+                number
+                    .parse::<f64>()
+                    .map(Node::Number)
+                    .map_err(|_| format!("computed field: invalid number {}", number))
+            }
+            Some(c) if c.is_alphanumeric() || c == '_' => {
+                let start = self.position();
+                while self
+                    .chars
+                    .next_if(|(_, c)| c.is_alphanumeric() || *c == '_')
+                    .is_some()
+                {}
+                let name = &self.text[start..self.position()];
+
+                if self.peek() == Some('(') {
+                    let Some((function, arity)) = Function::from_name(name) else {
+                        return Err(format!("computed field: unknown function {}", name));
+                    };
+                    self.chars.next();
+                    let mut arguments = vec![self.parse_expression()?];
+                    while self.peek() == Some(',') {
+                        self.chars.next();
+                        arguments.push(self.parse_expression()?);
+                    }
+                    self.expect(')')?;
+                    if arity.is_some_and(|arity| arguments.len() != arity) {
+                        return Err(format!(
+                            "computed field: function {} expects {} arguments",
+                            name,
+                            arity.unwrap()
+                        ));
+                    }
+                    return Ok(Node::Function(function, arguments));
+                }
+
+                match self.schema_map.get(name) {
+                    Some(schema_field) if schema_field.computed.is_some() => {
+                        Err(format!("computed field: field {} is computed itself", name))
+                    }
+                    Some(schema_field)
+                        if matches!(
+                            schema_field.field_type,
+                            FieldType::StringSet
+                                | FieldType::Point
+                                | FieldType::GeoShape
+                                | FieldType::Join
+                        ) =>
+                    {
+                        Err(format!(
+                            "computed field: {:?} field {} can't be used in expressions",
+                            schema_field.field_type, name
+                        ))
+                    }
+                    Some(schema_field) => Ok(Node::Field(schema_field.clone())),
+                    None => Err(format!("computed field: field {} not found", name)),
+                }
+            }
+            Some(c) => Err(format!(
+                "computed field: unexpected character '{}' at position {}",
+                c,
+                self.position()
+            )),
+            None => Err("computed field: unexpected end".to_string()),
+        }
+    }
+}
+
+impl ComputedExpression {
+    /// Compiles the expression of a computed field: field names are resolved against the schema.
+    pub fn compile(
+        text: &str,
+        schema_map: &HashMap<String, SchemaField>,
+    ) -> Result<ComputedExpression, String> {
+        let mut parser = Parser {
+            text,
+            chars: text.char_indices().peekable(),
+            schema_map,
+        };
+
+        let root = parser.parse_expression()?;
+        if let Some(c) = parser.peek() {
+            return Err(format!(
+                "computed field: unexpected character '{}' at position {}",
+                c,
+                parser.position()
+            ));
+        }
+
+        Ok(ComputedExpression { root })
+    }
+
+    /// Evaluates the expression against a document and converts the result into the field type of the computed field.
+    /// Returns None if the expression has no value for the document.
+    pub(crate) fn evaluate(&self, document: &Document, field_type: &FieldType) -> Option<Value> {
+        operand_to_value(evaluate_node(&self.root, document)?, field_type)
+    }
+}
+
+fn evaluate_node(node: &Node, document: &Document) -> Option<Operand> {
+    match node {
+        Node::Number(number) => Some(Operand::Number(*number)),
+        Node::String(string) => Some(Operand::String(string.clone())),
+        Node::Field(schema_field) => {
+            field_operand(schema_field, document.get(&schema_field.field)?)
+        }
+        Node::Negate(node) => Some(Operand::Number(-number(evaluate_node(node, document)?)?)),
+        Node::Binary(operator, left, right) => {
+            let left = evaluate_node(left, document)?;
+            let right = evaluate_node(right, document)?;
+            match (operator, left, right) {
+                (Operator::Add, Operand::String(left), right) => {
+                    Some(Operand::String(left + &operand_to_string(right)))
+                }
+                (Operator::Add, left, Operand::String(right)) => {
+                    Some(Operand::String(operand_to_string(left) + &right))
+                }
+                (operator, left, right) => {
+                    let (left, right) = (number(left)?, number(right)?);
+                    let result = match operator {
+                        Operator::Add => left + right,
+                        Operator::Subtract => left - right,
+                        Operator::Multiply => left * right,
+                        Operator::Divide => left / right,
+                    };
+                    result.is_finite().then_some(Operand::Number(result))
+                }
+            }
+        }
+        Node::Function(Function::Concat, arguments) => {
+            let mut result = String::new();
+            for argument in arguments {
+                result.push_str(&operand_to_string(evaluate_node(argument, document)?));
+            }
+            Some(Operand::String(result))
+        }
+        Node::Function(Function::Coalesce, arguments) => arguments
+            .iter()
+            .find_map(|argument| evaluate_node(argument, document)),
+        Node::Function(
+            function @ (Function::Year
+            | Function::Month
+            | Function::Day
+            | Function::Hour
+            | Function::Weekday),
+            arguments,
+        ) => {
+            let date = date_argument(&arguments[0], document)?;
+            let result = match function {
+                Function::Year => date.year() as u32,
+                Function::Month => date.month(),
+                Function::Day => date.day(),
+                Function::Hour => date.hour(),
+                _ => date.weekday().number_from_monday(),
+            };
+            Some(Operand::Number(result as f64))
+        }
+        Node::Function(function, arguments) => {
+            let argument = evaluate_node(&arguments[0], document)?;
+            match function {
+                Function::Lower => {
+                    Some(Operand::String(operand_to_string(argument).to_lowercase()))
+                }
+                Function::Upper => {
+                    Some(Operand::String(operand_to_string(argument).to_uppercase()))
+                }
+                Function::Trim => Some(Operand::String(
+                    operand_to_string(argument).trim().to_string(),
+                )),
+                Function::Length => Some(Operand::Number(
+                    operand_to_string(argument).chars().count() as f64,
+                )),
+                Function::Abs => Some(Operand::Number(number(argument)?.abs())),
+                Function::Round => Some(Operand::Number(number(argument)?.round())),
+                Function::Floor => Some(Operand::Number(number(argument)?.floor())),
+                Function::Ceil => Some(Operand::Number(number(argument)?.ceil())),
+                Function::Min | Function::Max => {
+                    let (left, right) = (
+                        number(argument)?,
+                        number(evaluate_node(&arguments[1], document)?)?,
+                    );
+                    Some(Operand::Number(if *function == Function::Min {
+                        left.min(right)
+                    } else {
+                        left.max(right)
+                    }))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Value of a field of the document as operand, None if the value doesn't match the field type
+fn field_operand(schema_field: &SchemaField, value: &Value) -> Option<Operand> {
+    let value = schema_field.coerce_value(value);
+    match schema_field.field_type {
+        FieldType::String | FieldType::Keyword | FieldType::Text | FieldType::Ip => {
+            match value.as_ref() {
+                Value::String(string) => Some(Operand::String(string.clone())),
+                Value::Null => None,
+                value => Some(Operand::String(value.to_string())),
+            }
+        }
+        FieldType::Date => date_from_json(&value, &schema_field.date_formats)
+            .map(|date| Operand::Number(date as f64)),
+        FieldType::Decimal => decimal_from_json(&value, schema_field.scale)
+            .map(|decimal| Operand::Number(decimal as f64 / 10f64.powi(schema_field.scale as i32))),
+        FieldType::Bool => value
+            .as_bool()
+            .map(|bool| Operand::Number(if bool { 1.0 } else { 0.0 })),
+        _ => value.as_f64().map(Operand::Number),
+    }
+}
+
+/// Date argument of a date function: a Timestamp field (epoch seconds), a date string, or epoch milliseconds
+fn date_argument(node: &Node, document: &Document) -> Option<DateTime<Utc>> {
+    let millis = match (node, evaluate_node(node, document)?) {
+        (Node::Field(schema_field), Operand::Number(seconds))
+            if schema_field.field_type == FieldType::Timestamp =>
+        {
+            (seconds * 1000.0) as i64
+        }
+        (_, Operand::Number(millis)) => millis as i64,
+        (_, Operand::String(string)) => parse_date(&string, &[])?,
+    };
+    DateTime::from_timestamp_millis(millis)
+}
+
+fn number(operand: Operand) -> Option<f64> {
+    match operand {
+        Operand::Number(number) => Some(number),
+        Operand::String(string) => string.trim().parse::<f64>().ok(),
+    }
+}
+
+/// Numbers without fraction are formatted as integers
+fn operand_to_string(operand: Operand) -> String {
+    match operand {
+        Operand::String(string) => string,
+        Operand::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+            (number as i64).to_string()
+        }
+        Operand::Number(number) => number.to_string(),
+    }
+}
+
+/// Converts the result of an expression into the JSON value of the field type: numbers are rounded for integer fields,
+/// numbers are epoch milliseconds for Date fields, and epoch seconds for Timestamp fields
+fn operand_to_value(operand: Operand, field_type: &FieldType) -> Option<Value> {
+    match field_type {
+        FieldType::String
+        | FieldType::Keyword
+        | FieldType::Text
+        | FieldType::Ip
+        | FieldType::Decimal => Some(Value::from(operand_to_string(operand))),
+        FieldType::StringSet => Some(Value::Array(vec![Value::from(operand_to_string(operand))])),
+        FieldType::Date => match operand {
+            Operand::Number(millis) => DateTime::from_timestamp_millis(millis as i64)
+                .map(|date| Value::from(date.to_rfc3339_opts(SecondsFormat::AutoSi, true))),
+            Operand::String(string) => Some(Value::from(string)),
+        },
+        FieldType::F32 | FieldType::F64 => Some(Value::from(number(operand)?)),
+        FieldType::Bool => Some(Value::Bool(number(operand)? != 0.0)),
+        FieldType::Point | FieldType::GeoShape | FieldType::Join => None,
+        _ => {
+            let number = number(operand)?.round();
+            if number < 0.0 {
+                Some(Value::from(number as i64))
+            } else {
+                Some(Value::from(number as u64))
+            }
+        }
+    }
+}
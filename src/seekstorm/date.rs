@@ -0,0 +1,217 @@
+use chrono::{
+    DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
+};
+use serde::{Deserialize, Serialize};
+
+/// Formats of date strings without time zone, which are always accepted in addition to RFC 3339 and epoch milliseconds. Interpreted as UTC.
+const DEFAULT_DATETIME_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Calendar interval of date histogram facets, and unit of date math.
+/// Date math units: y (Year), M (Month), w (Week), d (Day), h (Hour), m (Minute), s (Second).
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+pub enum DateInterval {
+    Second,
+    Minute,
+    Hour,
+    #[default]
+    Day,
+    /// Weeks start on Monday
+    Week,
+    Month,
+    Year,
+}
+
+impl DateInterval {
+    fn from_unit(unit: char) -> Option<DateInterval> {
+        match unit {
+            's' => Some(DateInterval::Second),
+            'm' => Some(DateInterval::Minute),
+            'h' => Some(DateInterval::Hour),
+            'd' => Some(DateInterval::Day),
+            'w' => Some(DateInterval::Week),
+            'M' => Some(DateInterval::Month),
+            'y' => Some(DateInterval::Year),
+            _ => None,
+        }
+    }
+
+    /// Label format of the date histogram buckets
+    fn label_format(&self) -> &'static str {
+        match self {
+            DateInterval::Second | DateInterval::Minute | DateInterval::Hour => {
+                "%Y-%m-%dT%H:%M:%SZ"
+            }
+            DateInterval::Day | DateInterval::Week => "%Y-%m-%d",
+            DateInterval::Month => "%Y-%m",
+            DateInterval::Year => "%Y",
+        }
+    }
+
+    /// Rounds the date down to the start of the interval
+    fn floor(&self, date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let date = date.with_nanosecond(0)?;
+        match self {
+            DateInterval::Second => Some(date),
+            DateInterval::Minute => date.with_second(0),
+            DateInterval::Hour => date.with_second(0)?.with_minute(0),
+            DateInterval::Day => Some(midnight(date.date_naive())),
+            DateInterval::Week => Some(midnight(
+                date.date_naive() - Duration::days(date.weekday().num_days_from_monday() as i64),
+            )),
+            DateInterval::Month => Some(midnight(date.date_naive().with_day(1)?)),
+            DateInterval::Year => Some(midnight(date.date_naive().with_day(1)?.with_month(1)?)),
+        }
+    }
+
+    /// Adds count intervals to the date, months and years in calendar arithmetic
+    fn add(&self, date: DateTime<Utc>, count: i64) -> Option<DateTime<Utc>> {
+        match self {
+            DateInterval::Second => date.checked_add_signed(Duration::try_seconds(count)?),
+            DateInterval::Minute => date.checked_add_signed(Duration::try_minutes(count)?),
+            DateInterval::Hour => date.checked_add_signed(Duration::try_hours(count)?),
+            DateInterval::Day => date.checked_add_signed(Duration::try_days(count)?),
+            DateInterval::Week => date.checked_add_signed(Duration::try_weeks(count)?),
+            DateInterval::Month | DateInterval::Year => {
+                let months = if *self == DateInterval::Year {
+                    count.checked_mul(12)?
+                } else {
+                    count
+                };
+                let months = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+                if count >= 0 {
+                    date.checked_add_months(months)
+                } else {
+                    date.checked_sub_months(months)
+                }
+            }
+        }
+    }
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+}
+
+/// Parses a date into Unix milliseconds (UTC).
+/// Accepted formats: RFC 3339 (e.g. "2024-05-17T10:30:00+02:00", normalized to UTC), epoch milliseconds (e.g. "1715934600000"),
+/// the custom chrono format patterns of date_formats (e.g. "%d.%m.%Y"), "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S" and "%Y-%m-%d".
+/// Dates without time zone are interpreted as UTC, dates without time as midnight.
+pub fn parse_date(text: &str, date_formats: &[String]) -> Option<i64> {
+    let text = text.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.timestamp_millis());
+    }
+
+    for format in date_formats
+        .iter()
+        .map(|format| format.as_str())
+        .chain(DEFAULT_DATETIME_FORMATS)
+        .chain([DEFAULT_DATE_FORMAT])
+    {
+        if let Ok(date) = DateTime::parse_from_str(text, format) {
+            return Some(date.timestamp_millis());
+        }
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(date.and_utc().timestamp_millis());
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(text, format) {
+            return Some(midnight(date).timestamp_millis());
+        }
+    }
+
+    text.parse::<i64>().ok()
+}
+
+/// Converts a JSON number (epoch milliseconds) or string into Unix milliseconds (UTC)
+pub(crate) fn date_from_json(value: &serde_json::Value, date_formats: &[String]) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(number) => number
+            .as_i64()
+            .or_else(|| number.as_f64().map(|number| number.round() as i64)),
+        serde_json::Value::String(string) => parse_date(string, date_formats),
+        _ => None,
+    }
+}
+
+/// Evaluates a date math expression into Unix milliseconds (UTC), e.g. for date range filters and date range facets.
+/// The expression starts with an anchor: `now`, or a date (see parse_date) followed by `||` if math follows.
+/// The anchor is followed by any number of operations: `+` or `-` a number of units, or `/` to round down to the start of a unit.
+/// Units: y (year), M (month), w (week), d (day), h (hour), m (minute), s (second).
+/// Examples: `now-7d`, `now/d`, `now-1M/M`, `2024-01-01||+1M-1d`, `2024-05-17T10:30:00Z`, `1715934600000`.
+pub fn parse_date_math(expression: &str, now: i64, date_formats: &[String]) -> Option<i64> {
+    let expression = expression.trim();
+    let (anchor, math) = if let Some(math) = expression.strip_prefix("now") {
+        (now, math)
+    } else if let Some((date, math)) = expression.split_once("||") {
+        (parse_date(date, date_formats)?, math)
+    } else {
+        return parse_date(expression, date_formats);
+    };
+
+    let mut date = DateTime::<Utc>::from_timestamp_millis(anchor)?;
+    let mut chars = math.trim().chars().peekable();
+    while let Some(operation) = chars.next() {
+        match operation {
+            '+' | '-' => {
+                let mut count = 0i64;
+                let mut has_digits = false;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    count = count.checked_mul(10)?.checked_add(digit as i64)?;
+                    has_digits = true;
+                    chars.next();
+                }
+                let count = if has_digits { count } else { 1 };
+                let interval = DateInterval::from_unit(chars.next()?)?;
+                date = interval.add(date, if operation == '-' { -count } else { count })?;
+            }
+            '/' => {
+                date = DateInterval::from_unit(chars.next()?)?.floor(date)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(date.timestamp_millis())
+}
+
+/// Calendar interval containing the date (Unix milliseconds), e.g. the month of a date partition:
+/// label (e.g. "2024-05" for Month), start (inclusive) and end (exclusive) of the interval in Unix milliseconds.
+pub fn date_interval_range(date: i64, interval: DateInterval) -> Option<(String, i64, i64)> {
+    let start = interval.floor(DateTime::<Utc>::from_timestamp_millis(date)?)?;
+    let end = interval.add(start, 1)?;
+    Some((
+        start.format(interval.label_format()).to_string(),
+        start.timestamp_millis(),
+        end.timestamp_millis(),
+    ))
+}
+
+/// Buckets of a date histogram facet from min to max (Unix milliseconds): label and start of each bucket.
+/// Returns no buckets if the number of buckets would exceed 65.535.
+pub(crate) fn date_histogram_ranges(
+    min: i64,
+    max: i64,
+    interval: DateInterval,
+) -> Vec<(String, i64)> {
+    let mut ranges = Vec::new();
+    let Some(mut start) =
+        DateTime::<Utc>::from_timestamp_millis(min).and_then(|min| interval.floor(min))
+    else {
+        return ranges;
+    };
+
+    while start.timestamp_millis() <= max {
+        if ranges.len() == u16::MAX as usize {
+            return Vec::new();
+        }
+        ranges.push((
+            start.format(interval.label_format()).to_string(),
+            start.timestamp_millis(),
+        ));
+        let Some(next) = interval.add(start, 1) else {
+            break;
+        };
+        start = next;
+    }
+    ranges
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::SeekStormError,
+    index::Index,
+    search::{FacetValue, ResultObject},
+};
+
+/// Result diversification: limits how many consecutive results may share the same value of a facet field, e.g. at most 2 consecutive results per source,
+/// so that one prolific source doesn't dominate the first page. Only the top window_size results are reordered: a result which would exceed the limit
+/// is moved down behind the next result with a different value, results keep their score. The results beyond the window keep their order.
+/// The offset and length of the search should be applied after the diversification, with the search collecting at least offset + length results.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Diversify {
+    /// Name of a facet field, e.g. source, author or domain. Results without a value of the field are not limited.
+    pub field: String,
+    /// Maximum number of consecutive results with the same value of the field, at least 1
+    #[serde(default = "default_max_consecutive")]
+    pub max_consecutive: usize,
+    /// Number of top results to reorder. Should be at least offset + length of the search.
+    #[serde(default = "default_window_size")]
+    pub window_size: usize,
+}
+
+fn default_max_consecutive() -> usize {
+    2
+}
+
+fn default_window_size() -> usize {
+    100
+}
+
+impl Index {
+    /// Checks that the field of the diversification is a facet field and max_consecutive is at least 1
+    pub fn check_diversify(&self, diversify: &Diversify) -> Result<(), SeekStormError> {
+        if !self.facets_map.contains_key(&diversify.field) {
+            return Err(SeekStormError::InvalidRequest(format!(
+                "not a facet field: {}",
+                diversify.field
+            )));
+        }
+        if diversify.max_consecutive == 0 {
+            return Err(SeekStormError::InvalidRequest(
+                "max_consecutive has to be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reorders the top window_size results, so that at most max_consecutive consecutive results share the same value of the field (see Diversify).
+    /// Of the remaining results, always the best ranked one is taken which doesn't exceed the limit, if all remaining results share the value of the current run, the best ranked one.
+    pub fn diversify_window(
+        &self,
+        result_object: &mut ResultObject,
+        diversify: &Diversify,
+    ) -> Result<(), SeekStormError> {
+        self.check_diversify(diversify)?;
+        let idx = self.facets_map[&diversify.field];
+
+        let window_size = diversify.window_size.min(result_object.results.len());
+        let mut remaining: Vec<_> = result_object
+            .results
+            .drain(..window_size)
+            .map(|result| {
+                let value = if self.is_facet_missing(idx, result.doc_id) {
+                    None
+                } else {
+                    Some(self.get_facet_value(&diversify.field, result.doc_id))
+                        .filter(|value| *value != FacetValue::None)
+                };
+                (result, value)
+            })
+            .collect();
+
+        let mut diversified = Vec::with_capacity(window_size);
+        let mut run_value: Option<FacetValue> = None;
+        let mut run_length = 0;
+        while !remaining.is_empty() {
+            let position = remaining
+                .iter()
+                .position(|(_result, value)| {
+                    value.is_none() || *value != run_value || run_length < diversify.max_consecutive
+                })
+                .unwrap_or(0);
+            let (result, value) = remaining.remove(position);
+            if value.is_some() && value == run_value {
+                run_length += 1;
+            } else {
+                run_value = value;
+                run_length = 1;
+            }
+            diversified.push(result);
+        }
+
+        result_object.results.splice(0..0, diversified);
+        Ok(())
+    }
+}
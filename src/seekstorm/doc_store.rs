@@ -5,8 +5,8 @@ use std::fs;
 use std::io::{self, Seek, SeekFrom, Write};
 use std::path::Path;
 
-use crate::geo_search::euclidian_distance;
-use crate::highlighter::{top_fragments_from_field, Highlighter};
+use crate::geo_search::{distance, points_from_json};
+use crate::highlighter::{top_fragments_from_field, HighlightOutput, Highlighter};
 use crate::index::{
     AccessType, DistanceField, Document, FieldType, Index, FILE_PATH, ROARING_BLOCK_SIZE,
 };
@@ -64,8 +64,8 @@ impl Index {
 
             let _ = self.docstore_file.write(&(size_sum as u32).to_le_bytes());
 
-            let committed_doc_count = (self.committed_doc_count - 1 % ROARING_BLOCK_SIZE) + 1;
-            let indexed_doc_count = (indexed_doc_count - 1 % ROARING_BLOCK_SIZE) + 1;
+            let committed_doc_count = ((self.committed_doc_count - 1) % ROARING_BLOCK_SIZE) + 1;
+            let indexed_doc_count = ((indexed_doc_count - 1) % ROARING_BLOCK_SIZE) + 1;
 
             for i in committed_doc_count..indexed_doc_count {
                 let pointer = read_u32(&self.compressed_docstore_segment_block_buffer, i * 4);
@@ -181,8 +181,11 @@ impl Index {
     /// * `include_uncommited`: Return also documents which have not yet been committed.
     /// * `highlighter_option`: Specifies the extraction of keyword-in-context (KWIC) fragments from fields in documents, and the highlighting of the query terms within.
     /// * `fields`: Specifies which of the stored fields to return with each document. Default: If empty return all stoed fields
-    /// * `distance_fields`: insert distance fields into result documents, calculating the distance between a specified facet field of type Point and a base Point, in kilometers or miles.
-    ///   using Euclidian distance (Pythagoras theorem) with Equirectangular approximation.
+    /// * `distance_fields`: insert distance fields into result documents, calculating the distance between a specified facet field of type Point and a base Point, in kilometers, miles or meters,
+    ///   using Euclidian distance (Pythagoras theorem) with Equirectangular approximation, or the haversine formula (DistanceField.method).
+    ///   For a multi-valued Point field the distance to the nearest of the stored points is returned.
+    ///
+    /// The values of the runtime fields of the index (see runtime::RuntimeField) are inserted into the document, if `fields` is empty or contains the runtime field.
     pub fn get_document(
         &self,
         doc_id: usize,
@@ -269,38 +272,79 @@ impl Index {
 
         if let Some(highlighter) = highlighter_option {
             let mut kwic_vec: VecDeque<String> = VecDeque::new();
+            let mut highlight_spans = Vec::new();
             for highlight in highlighter.highlights.iter() {
-                let kwic =
-                    top_fragments_from_field(self, &doc, &highlighter.query_terms_ac, highlight)
-                        .unwrap();
+                let (kwic, spans) =
+                    top_fragments_from_field(self, &doc, highlighter, highlight).unwrap();
                 kwic_vec.push_back(kwic);
+                highlight_spans.extend(spans);
             }
 
+            let mut keyed_highlights = serde_json::Map::new();
             for highlight in highlighter.highlights.iter() {
                 let kwic = kwic_vec.pop_front().unwrap();
-                doc.insert(
-                    (if highlight.name.is_empty() {
-                        &highlight.field
-                    } else {
-                        &highlight.name
-                    })
-                    .to_string(),
-                    json!(kwic),
-                );
+                let name = if highlight.name.is_empty() {
+                    &highlight.field
+                } else {
+                    &highlight.name
+                };
+                match highlighter.output {
+                    HighlightOutput::Field => {
+                        doc.insert(name.to_string(), json!(kwic));
+                    }
+                    HighlightOutput::Keyed => {
+                        keyed_highlights.insert(name.to_string(), json!(kwic));
+                    }
+                }
+            }
+            if highlighter.output == HighlightOutput::Keyed {
+                doc.insert("_highlight".to_string(), Value::Object(keyed_highlights));
+            }
+
+            if highlighter
+                .highlights
+                .iter()
+                .any(|highlight| highlight.offsets)
+            {
+                doc.insert("_highlights".to_string(), json!(highlight_spans));
             }
         }
 
         for distance_field in distance_fields.iter() {
             if let Some(idx) = self.facets_map.get(&distance_field.field) {
                 if self.facets[*idx].field_type == FieldType::Point {
-                    if let FacetValue::Point(point) =
-                        self.get_facet_value(&distance_field.field, doc_id)
-                    {
-                        let distance =
-                            euclidian_distance(&point, &distance_field.base, &distance_field.unit);
+                    // all points of a stored multi-valued Point field, otherwise the point of the facet field
+                    let points = match doc.get(&distance_field.field).and_then(points_from_json) {
+                        Some(points) => points,
+                        None => match self.get_facet_value(&distance_field.field, doc_id) {
+                            FacetValue::Point(point) => vec![point],
+                            _ => continue,
+                        },
+                    };
+                    let nearest_distance = points
+                        .iter()
+                        .map(|point| {
+                            distance(
+                                point,
+                                &distance_field.base,
+                                &distance_field.unit,
+                                &distance_field.method,
+                            )
+                        })
+                        .fold(f64::INFINITY, f64::min);
+
+                    doc.insert(distance_field.distance.clone(), json!(nearest_distance));
+                }
+            }
+        }
 
-                        doc.insert(distance_field.distance.clone(), json!(distance));
-                    }
+        for runtime_field in self.runtime_fields.iter() {
+            if fields.is_empty() || fields.contains(&runtime_field.name) {
+                if let Some(Ok(expression)) = self.compile_runtime_field(&runtime_field.name) {
+                    doc.insert(
+                        runtime_field.name.clone(),
+                        json!(expression.evaluate(self, doc_id, 0.0)),
+                    );
                 }
             }
         }
@@ -0,0 +1,73 @@
+use std::{
+    fs::File,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::index::{Durability, Index, CHECKSUMS_FILENAME, FACET_VALUES_FILENAME, META_FILENAME};
+
+impl Index {
+    /// Flushes all index files from the operating system page cache to stable storage (fsync), independent of the durability policy.
+    /// Only committed documents and deletions are flushed, uncommitted documents are not committed.
+    pub fn sync(&mut self) -> Result<(), String> {
+        self.sync_files()?;
+        self.unsynced = false;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Flushes all index files to stable storage, without updating the sync state, e.g. by a commit under a read lock
+    pub(crate) fn sync_files(&self) -> Result<(), String> {
+        self.index_file.sync_all().map_err(|e| e.to_string())?;
+        self.docstore_file.sync_all().map_err(|e| e.to_string())?;
+        self.delete_file.sync_all().map_err(|e| e.to_string())?;
+        if !self.facets.is_empty() {
+            self.facets_file_mmap.flush().map_err(|e| e.to_string())?;
+            self.facets_file.sync_all().map_err(|e| e.to_string())?;
+        }
+
+        let index_path = Path::new(&self.index_path_string);
+        for filename in [FACET_VALUES_FILENAME, CHECKSUMS_FILENAME, META_FILENAME] {
+            if let Ok(file) = File::open(index_path.join(filename)) {
+                file.sync_all().map_err(|e| e.to_string())?;
+            }
+        }
+        // persists the directory entries of newly created files, not supported on Windows
+        if let Ok(directory) = File::open(index_path) {
+            let _ = directory.sync_all();
+        }
+        Ok(())
+    }
+
+    /// The index uses Durability::Periodic, and commits or deletions are not flushed to stable storage for more than IndexMetaObject.sync_interval_ms
+    pub fn is_sync_due(&self) -> bool {
+        self.meta.durability == Durability::Periodic
+            && self.unsynced
+            && self.last_sync.elapsed() >= Duration::from_millis(self.meta.sync_interval_ms)
+    }
+
+    /// Flushes the index files to stable storage, if the sync is due for Durability::Periodic.
+    /// Invoked at every commit and deletion. Without further commits or deletions, it has to be invoked periodically to keep the interval,
+    /// as the SeekStorm server does for all its indices. Returns true if the index files were flushed.
+    pub fn sync_if_due(&mut self) -> Result<bool, String> {
+        if self.is_sync_due() {
+            self.sync()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Applies the durability policy after commits or deletions were written to the index files
+    pub(crate) fn sync_after_write(&mut self) {
+        self.unsynced = true;
+        let result = match self.meta.durability {
+            Durability::Os => Ok(()),
+            Durability::Commit => self.sync(),
+            Durability::Periodic => self.sync_if_due().map(|_| ()),
+        };
+        if let Err(e) = result {
+            println!("index files not flushed to disk: {}", e);
+        }
+    }
+}
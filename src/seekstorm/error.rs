@@ -0,0 +1,71 @@
+use thiserror::Error;
+
+/// Error of the library and server APIs, with a machine-readable error code (see SeekStormError::code), so that clients can branch on the cause of a failure.
+/// The server maps the error to the HTTP status code and returns it as JSON body: `{"code":"INDEX_NOT_FOUND","message":"index_id not found","details":null}`.
+///
+/// Errors of APIs which still return `Result<_, String>` convert to SeekStormError::Internal, and SeekStormError converts to its message, so both can be propagated with `?`.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SeekStormError {
+    /// The requested document, synonyms or other resource doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+    /// The requested index doesn't exist
+    #[error("{0}")]
+    IndexNotFound(String),
+    /// The request is invalid, e.g. a missing or malformed parameter
+    #[error("{0}")]
+    InvalidRequest(String),
+    /// A limit of the server or API key is exceeded, e.g. max_result_window or max_request_body_size
+    #[error("{0}")]
+    QuotaExceeded(String),
+    /// The schema or a document doesn't match the schema of the index, e.g. an unknown field or a value of the wrong field type
+    #[error("{0}")]
+    SchemaMismatch(String),
+    /// The query request can't be parsed
+    #[error("{0}")]
+    QueryParseError(String),
+    /// The request conflicts with the state of the index, e.g. a document mutation of a frozen index
+    #[error("{0}")]
+    Conflict(String),
+    /// Reading or writing the index files failed
+    #[error("io error: {0}")]
+    Io(String),
+    /// Any other error
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl SeekStormError {
+    /// Machine-readable error code: NOT_FOUND, INDEX_NOT_FOUND, INVALID_REQUEST, QUOTA_EXCEEDED, SCHEMA_MISMATCH, QUERY_PARSE_ERROR, CONFLICT, IO_ERROR or INTERNAL_ERROR
+    pub fn code(&self) -> &'static str {
+        match self {
+            SeekStormError::NotFound(_) => "NOT_FOUND",
+            SeekStormError::IndexNotFound(_) => "INDEX_NOT_FOUND",
+            SeekStormError::InvalidRequest(_) => "INVALID_REQUEST",
+            SeekStormError::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+            SeekStormError::SchemaMismatch(_) => "SCHEMA_MISMATCH",
+            SeekStormError::QueryParseError(_) => "QUERY_PARSE_ERROR",
+            SeekStormError::Conflict(_) => "CONFLICT",
+            SeekStormError::Io(_) => "IO_ERROR",
+            SeekStormError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+}
+
+impl From<String> for SeekStormError {
+    fn from(message: String) -> Self {
+        SeekStormError::Internal(message)
+    }
+}
+
+impl From<std::io::Error> for SeekStormError {
+    fn from(error: std::io::Error) -> Self {
+        SeekStormError::Io(error.to_string())
+    }
+}
+
+impl From<SeekStormError> for String {
+    fn from(error: SeekStormError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    expression::Expression,
+    index::{Index, HASHER_64},
+    rescore::Rescore,
+    search::{FunctionScore, MinimumShouldMatch, ResultSort},
+};
+
+pub(crate) const RANKING_VARIANTS_FILENAME: &str = "ranking_variants.json";
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Named ranking configuration of an A/B test: requests are assigned deterministically to one of the variants of the index by a hash of their user id,
+/// so that relevance changes can be measured with the per-variant metrics before rollout.
+/// The ranking parameters of a variant replace the corresponding parameters of the search request, if set.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RankingVariant {
+    /// Unique name of the variant, e.g. "control" and "popularity_boost"
+    pub name: String,
+    /// Share of the requests assigned to the variant, relative to the sum of the weights of all variants
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Scoring modifier, e.g. boosts by numerical facet fields (see Search::search)
+    #[serde(default)]
+    pub function_score: Option<FunctionScore>,
+    /// Sort fields and expressions, e.g. "_score * log(1+popularity)" (see Search::search)
+    #[serde(default)]
+    pub result_sort: Vec<ResultSort>,
+    #[serde(default)]
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    /// Rescore phase with score functions and a learning-to-rank model (see Rescore)
+    #[serde(default)]
+    pub rescore: Option<Rescore>,
+}
+
+/// Metrics of a ranking variant, collected since the ranking variants were set or the index was opened
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RankingVariantMetrics {
+    pub name: String,
+    /// Number of search requests assigned to the variant
+    pub queries: u64,
+    /// Number of search requests without results
+    pub zero_result_queries: u64,
+    /// Share of the search requests without results
+    pub zero_result_rate: f64,
+    /// Mean query latency in milliseconds
+    pub mean_latency_ms: f64,
+    /// Number of clicks on results, reported with Index::record_ranking_click
+    pub clicks: u64,
+    /// Clicks per search request
+    pub click_through_rate: f64,
+    /// Mean reciprocal rank of the clicked results per search request: 1 for a click on the top result, 0.5 for the second, ...
+    pub mean_reciprocal_rank: f64,
+}
+
+#[derive(Default)]
+struct RankingVariantCounters {
+    queries: u64,
+    zero_result_queries: u64,
+    latency_sum_ns: u128,
+    clicks: u64,
+    reciprocal_rank_sum: f64,
+}
+
+pub(crate) fn load_ranking_variants(index_path: &Path) -> Vec<RankingVariant> {
+    if let Ok(ranking_variants_file) = File::open(index_path.join(RANKING_VARIANTS_FILENAME)) {
+        serde_json::from_reader(BufReader::new(ranking_variants_file)).unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Per-variant metrics counters, updated by concurrent searches holding a read lock of the index
+#[derive(Default, Clone)]
+pub(crate) struct RankingMetrics(Arc<Mutex<AHashMap<String, RankingVariantCounters>>>);
+
+impl Index {
+    /// Set/replace the ranking variants of the index for A/B testing. An empty vector ends the A/B test.
+    /// The metrics of all variants are reset.
+    /// Function scores have to be valid, and LTR models of rescore phases have to be loaded into the index.
+    pub fn set_ranking_variants(
+        &mut self,
+        ranking_variants: Vec<RankingVariant>,
+    ) -> Result<usize, String> {
+        for (i, ranking_variant) in ranking_variants.iter().enumerate() {
+            if ranking_variant.name.is_empty() {
+                return Err("ranking variant name is empty".to_string());
+            }
+            if ranking_variants[..i]
+                .iter()
+                .any(|other| other.name == ranking_variant.name)
+            {
+                return Err(format!(
+                    "ranking variant name is not unique: {}",
+                    ranking_variant.name
+                ));
+            }
+            if let Some(function_score) = ranking_variant.function_score.as_ref() {
+                Expression::from_function_score(function_score, self)
+                    .map_err(|e| format!("ranking variant {}: {}", ranking_variant.name, e))?;
+            }
+            if let Some(rescore) = ranking_variant.rescore.as_ref() {
+                self.check_rescore(rescore)
+                    .map_err(|e| format!("ranking variant {}: {}", ranking_variant.name, e))?;
+            }
+        }
+        if !ranking_variants.is_empty()
+            && ranking_variants
+                .iter()
+                .all(|ranking_variant| ranking_variant.weight == 0)
+        {
+            return Err("the weights of all ranking variants are 0".to_string());
+        }
+
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(RANKING_VARIANTS_FILENAME))
+                .map_err(|e| e.to_string())?,
+            &ranking_variants,
+        )
+        .map_err(|e| e.to_string())?;
+
+        self.ranking_variants = ranking_variants;
+        self.ranking_metrics.0.lock().unwrap().clear();
+        Ok(self.ranking_variants.len())
+    }
+
+    /// Get the ranking variants of the index
+    pub fn get_ranking_variants(&self) -> Vec<RankingVariant> {
+        self.ranking_variants.clone()
+    }
+
+    /// Assigns a user deterministically to a ranking variant, by a hash of the user id and the weights of the variants.
+    /// Returns None if the index has no ranking variants.
+    pub fn assign_ranking_variant(&self, user_id: &str) -> Option<&RankingVariant> {
+        let weight_sum: u64 = self
+            .ranking_variants
+            .iter()
+            .map(|ranking_variant| ranking_variant.weight as u64)
+            .sum();
+        if weight_sum == 0 {
+            return None;
+        }
+
+        let mut bucket = HASHER_64.hash_one(user_id.as_bytes()) % weight_sum;
+        self.ranking_variants.iter().find(|ranking_variant| {
+            if bucket < ranking_variant.weight as u64 {
+                true
+            } else {
+                bucket -= ranking_variant.weight as u64;
+                false
+            }
+        })
+    }
+
+    /// Records a search request of a ranking variant for its metrics: the total number of results and the query latency in nanoseconds.
+    pub fn record_ranking_query(
+        &self,
+        ranking_variant: &str,
+        result_count_total: usize,
+        time: u128,
+    ) {
+        let mut ranking_metrics = self.ranking_metrics.0.lock().unwrap();
+        let counters = ranking_metrics
+            .entry(ranking_variant.to_string())
+            .or_default();
+        counters.queries += 1;
+        if result_count_total == 0 {
+            counters.zero_result_queries += 1;
+        }
+        counters.latency_sum_ns += time;
+    }
+
+    /// Records a click on a search result of a ranking variant for its metrics, with the position of the result (0 for the top result).
+    pub fn record_ranking_click(&self, ranking_variant: &str, position: usize) {
+        let mut ranking_metrics = self.ranking_metrics.0.lock().unwrap();
+        let counters = ranking_metrics
+            .entry(ranking_variant.to_string())
+            .or_default();
+        counters.clicks += 1;
+        counters.reciprocal_rank_sum += 1.0 / (position + 1) as f64;
+    }
+
+    /// Get the metrics of all ranking variants of the index
+    pub fn get_ranking_metrics(&self) -> Vec<RankingVariantMetrics> {
+        let ranking_metrics = self.ranking_metrics.0.lock().unwrap();
+        self.ranking_variants
+            .iter()
+            .map(|ranking_variant| {
+                let Some(counters) = ranking_metrics.get(&ranking_variant.name) else {
+                    return RankingVariantMetrics {
+                        name: ranking_variant.name.clone(),
+                        ..Default::default()
+                    };
+                };
+                let queries = counters.queries.max(1) as f64;
+                RankingVariantMetrics {
+                    name: ranking_variant.name.clone(),
+                    queries: counters.queries,
+                    zero_result_queries: counters.zero_result_queries,
+                    zero_result_rate: counters.zero_result_queries as f64 / queries,
+                    mean_latency_ms: counters.latency_sum_ns as f64 / queries / 1_000_000.0,
+                    clicks: counters.clicks,
+                    click_through_rate: counters.clicks as f64 / queries,
+                    mean_reciprocal_rank: counters.reciprocal_rank_sum / queries,
+                }
+            })
+            .collect()
+    }
+}
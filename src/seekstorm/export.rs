@@ -0,0 +1,257 @@
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{
+    commit::Commit,
+    experiment::RANKING_VARIANTS_FILENAME,
+    index::{
+        create_index, open_index, Document, Index, IndexArc, IndexDocuments, IndexMetaObject,
+        SchemaField, Synonym, FACET_ALIASES_FILENAME, INDEX_FORMAT_VERSION_MAJOR,
+        INDEX_FORMAT_VERSION_MINOR, META_FILENAME, SCHEMA_FILENAME, SYNONYMS_FILENAME, VERSION,
+    },
+    ltr::LTR_MODELS_FILENAME,
+    percolator::PERCOLATOR_QUERIES_FILENAME,
+    query_rewrite::QUERY_RULES_FILENAME,
+    runtime::RUNTIME_FIELDS_FILENAME,
+    word_lists::WORD_LISTS_FILENAME,
+};
+
+/// Version of the export format, incremented on incompatible changes
+pub const EXPORT_FORMAT_VERSION: u16 = 1;
+
+const MANIFEST_FILENAME: &str = "manifest.json";
+const DOCUMENTS_FILENAME: &str = "documents.ndjson";
+const RAW_PATH: &str = "raw";
+
+/// Number of documents indexed per batch when importing
+const IMPORT_BATCH_SIZE: usize = 1000;
+
+/// Manifest of an exported index, stored as manifest.json in the export directory
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExportManifest {
+    /// Version of the export format
+    pub format_version: u16,
+    /// SeekStorm version that created the export
+    pub seekstorm_version: String,
+    /// Index format version of the raw index files
+    pub index_format_version_major: u16,
+    pub index_format_version_minor: u16,
+    /// Unix timestamp of the export, in seconds
+    pub created: u64,
+    /// Index meta data: name, similarity, tokenizer, stemmer, BM25 parameters
+    pub meta: IndexMetaObject,
+    /// Number of exported documents in documents.ndjson
+    pub document_count: usize,
+    /// Files of the export directory besides manifest.json: schema, synonyms, facet aliases, LTR models, query rules, percolator queries, ranking variants, word lists and documents
+    pub files: Vec<String>,
+    /// The raw index files are included in the raw directory
+    pub raw: bool,
+}
+
+impl Index {
+    /// Exports the index to a versioned, portable directory, to move indices between machines and SeekStorm versions:
+    /// manifest.json, schema.json, synonyms.json, facet_aliases.json, ltr_models.json, query_rules.json, runtime_fields.json, percolator_queries.json, ranking_variants.json, word_lists.json and all documents in documents.ndjson.
+    /// Only stored fields are exported: fields with stored=false can't be restored by import_index.
+    /// Deleted documents are skipped, uncommitted documents are included.
+    /// * `export_path`: new or empty directory
+    /// * `include_raw`: additionally copy the raw index files to the raw directory, for a fast import with the same index format version.
+    ///   Requires a committed index.
+    pub fn export_index(
+        &self,
+        export_path: &Path,
+        include_raw: bool,
+    ) -> Result<ExportManifest, String> {
+        if export_path.exists()
+            && fs::read_dir(export_path)
+                .map_err(|e| e.to_string())?
+                .next()
+                .is_some()
+        {
+            return Err(format!(
+                "export_path is not empty: {}",
+                export_path.display()
+            ));
+        }
+        if include_raw && self.uncommitted {
+            return Err("commit the index before exporting the raw index files".to_string());
+        }
+        fs::create_dir_all(export_path).map_err(|e| e.to_string())?;
+
+        let index_path = Path::new(&self.index_path_string);
+        let mut files = Vec::new();
+        for filename in [
+            SCHEMA_FILENAME,
+            SYNONYMS_FILENAME,
+            FACET_ALIASES_FILENAME,
+            LTR_MODELS_FILENAME,
+            QUERY_RULES_FILENAME,
+            RUNTIME_FIELDS_FILENAME,
+            PERCOLATOR_QUERIES_FILENAME,
+            RANKING_VARIANTS_FILENAME,
+            WORD_LISTS_FILENAME,
+        ] {
+            if index_path.join(filename).exists() {
+                fs::copy(index_path.join(filename), export_path.join(filename))
+                    .map_err(|e| e.to_string())?;
+                files.push(filename.to_string());
+            }
+        }
+
+        let mut writer = BufWriter::new(
+            File::create(export_path.join(DOCUMENTS_FILENAME)).map_err(|e| e.to_string())?,
+        );
+        let mut document_count = 0;
+        for (_, document) in self.documents(true, Vec::new()) {
+            serde_json::to_writer(&mut writer, &document).map_err(|e| e.to_string())?;
+            writer.write_all(b"\n").map_err(|e| e.to_string())?;
+            document_count += 1;
+        }
+        writer.flush().map_err(|e| e.to_string())?;
+        files.push(DOCUMENTS_FILENAME.to_string());
+
+        if include_raw {
+            copy_dir(index_path, &export_path.join(RAW_PATH))?;
+        }
+
+        let manifest = ExportManifest {
+            format_version: EXPORT_FORMAT_VERSION,
+            seekstorm_version: VERSION.to_string(),
+            index_format_version_major: INDEX_FORMAT_VERSION_MAJOR,
+            index_format_version_minor: INDEX_FORMAT_VERSION_MINOR,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            meta: self.meta.clone(),
+            document_count,
+            files,
+            raw: include_raw,
+        };
+
+        serde_json::to_writer_pretty(
+            File::create(export_path.join(MANIFEST_FILENAME)).map_err(|e| e.to_string())?,
+            &manifest,
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(manifest)
+    }
+}
+
+/// Imports an index exported with Index::export_index into a new index directory.
+/// If the export includes the raw index files and their index format version can be opened by this SeekStorm version, they are copied directly.
+/// Otherwise a new index is created from the exported schema, synonyms, facet aliases and LTR models, and all documents are reindexed and committed.
+/// When reindexing, documents get new, consecutive document ids.
+/// * `export_path`: directory created by Index::export_index
+/// * `index_path`: new index directory
+pub async fn import_index(export_path: &Path, index_path: &Path) -> Result<IndexArc, String> {
+    let manifest: ExportManifest = serde_json::from_reader(BufReader::new(
+        File::open(export_path.join(MANIFEST_FILENAME))
+            .map_err(|_| format!("export not found: {}", export_path.display()))?,
+    ))
+    .map_err(|e| format!("manifest: {}", e))?;
+
+    if manifest.format_version > EXPORT_FORMAT_VERSION {
+        return Err(format!(
+            "export format version {} not supported, update SeekStorm",
+            manifest.format_version
+        ));
+    }
+    if index_path.join(META_FILENAME).exists() {
+        return Err(format!("index already exists: {}", index_path.display()));
+    }
+
+    if manifest.raw
+        && manifest.index_format_version_major == INDEX_FORMAT_VERSION_MAJOR
+        && manifest.index_format_version_minor <= INDEX_FORMAT_VERSION_MINOR
+    {
+        copy_dir(&export_path.join(RAW_PATH), index_path)?;
+        return open_index(index_path, true).await;
+    }
+
+    let schema: Vec<SchemaField> = serde_json::from_reader(BufReader::new(
+        File::open(export_path.join(SCHEMA_FILENAME)).map_err(|e| format!("schema: {}", e))?,
+    ))
+    .map_err(|e| format!("schema: {}", e))?;
+    let synonyms: Vec<Synonym> =
+        if let Ok(synonyms_file) = File::open(export_path.join(SYNONYMS_FILENAME)) {
+            serde_json::from_reader(BufReader::new(synonyms_file))
+                .map_err(|e| format!("synonyms: {}", e))?
+        } else {
+            Vec::new()
+        };
+
+    fs::create_dir_all(index_path).map_err(|e| e.to_string())?;
+    let mut index = create_index(
+        index_path,
+        manifest.meta,
+        &schema,
+        true,
+        &synonyms,
+        11,
+        true,
+    )?;
+    index.close_index();
+
+    for filename in [
+        FACET_ALIASES_FILENAME,
+        LTR_MODELS_FILENAME,
+        QUERY_RULES_FILENAME,
+        RUNTIME_FIELDS_FILENAME,
+        PERCOLATOR_QUERIES_FILENAME,
+        RANKING_VARIANTS_FILENAME,
+        WORD_LISTS_FILENAME,
+    ] {
+        if export_path.join(filename).exists() {
+            fs::copy(export_path.join(filename), index_path.join(filename))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut index_arc = open_index(index_path, true).await?;
+
+    let reader = BufReader::new(
+        File::open(export_path.join(DOCUMENTS_FILENAME)).map_err(|e| e.to_string())?,
+    );
+    let mut documents: Vec<Document> = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    for line in reader.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        documents.push(serde_json::from_str(&line).map_err(|e| format!("documents: {}", e))?);
+        if documents.len() == IMPORT_BATCH_SIZE {
+            index_arc
+                .index_documents(std::mem::take(&mut documents))
+                .await;
+        }
+    }
+    if !documents.is_empty() {
+        index_arc.index_documents(documents).await;
+    }
+    index_arc.commit().await?;
+
+    Ok(index_arc)
+}
+
+/// Recursively copies all files of the source directory into the (new) target directory
+pub(crate) fn copy_dir(source_path: &Path, target_path: &Path) -> Result<(), String> {
+    for entry in WalkDir::new(source_path) {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let relative_path = entry.path().strip_prefix(source_path).unwrap();
+        let target_entry_path = target_path.join(relative_path);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target_entry_path).map_err(|e| e.to_string())?;
+        } else {
+            fs::copy(entry.path(), &target_entry_path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,727 @@
+use std::{
+    iter::Peekable,
+    str::CharIndices,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    geo_search::{decode_morton_2_d, euclidian_distance},
+    index::{DistanceUnit, FieldType, Index},
+    runtime::RuntimeField,
+    search::{
+        BoostMode, DecayFunction, FieldValueModifier, FunctionScore, ScoreFunction, ScoreMode,
+    },
+    utils::read_u64,
+};
+
+/// Maximum nesting depth of parentheses, function arguments and unary minus in an expression:
+/// expressions are user input, e.g. of a search request, and are parsed and evaluated recursively.
+const MAX_EXPRESSION_DEPTH: usize = 64;
+/// Maximum number of operands of an expression, and of functions of a function score, which bounds the depth of the operator chains
+const MAX_EXPRESSION_OPERANDS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Abs,
+    Sqrt,
+    Exp,
+    Log,
+    Log10,
+    Log1p,
+    Pow,
+    Min,
+    Max,
+    Gauss,
+    Linear,
+    Exponential,
+    Random,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Function, usize)> {
+        match name {
+            "abs" => Some((Function::Abs, 1)),
+            "sqrt" => Some((Function::Sqrt, 1)),
+            "exp" => Some((Function::Exp, 1)),
+            "log" | "ln" => Some((Function::Log, 1)),
+            "log10" => Some((Function::Log10, 1)),
+            "log1p" => Some((Function::Log1p, 1)),
+            "pow" => Some((Function::Pow, 2)),
+            "min" => Some((Function::Min, 2)),
+            "max" => Some((Function::Max, 2)),
+            "gauss" => Some((Function::Gauss, 5)),
+            "linear" => Some((Function::Linear, 5)),
+            "exponential" => Some((Function::Exponential, 5)),
+            "random" => Some((Function::Random, 1)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Score,
+    /// facet index, value used if the facet field is missing in the document
+    Facet(usize, f64),
+    /// distance between the Point facet field and the point (lat, lon), infinite if missing
+    Distance(usize, Box<Node>, Box<Node>, DistanceUnit),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Function(Function, Vec<Node>),
+}
+
+/// Expression compiled against the facet fields of an index, used for sorting (ResultSort) and scoring.
+/// Operands: numbers, numerical facet fields (missing values are 0), _score (BM25 score of the result), now (current Unix timestamp in seconds),
+/// _random (random number in the range 0..1 per document, with a different seed per request).
+/// Operators: + - * / and parentheses.
+/// Functions: abs(x), sqrt(x), exp(x), log(x) (natural logarithm, alias ln), log10(x), log1p(x), pow(x,y), min(x,y), max(x,y),
+/// decay functions gauss|linear|exponential(x, origin, scale, offset, decay) in the range 0..1, distance(point_field, lat, lon) in kilometers,
+/// and random(seed): random number in the range 0..1 per document, identical for the same seed, i.e. stable across pages.
+/// Example: `_score * log(1+popularity) / (1+(now-date)/86400)`
+/// Example: `_score * gauss(distance(location, 38.8951, -77.0364), 0, 10, 0, 0.5)`
+/// Example: `random(42)` as sort field returns a representative random sample of the matching documents.
+/// Non-finite results (e.g. log(0), division by zero) are evaluated as 0.
+/// Operands can also be runtime fields of the index (see runtime::RuntimeField).
+/// Expressions are limited to a nesting depth of 64 and 1024 operands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    root: Node,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    index: &'a Index,
+    now: f64,
+    seed: u64,
+    /// runtime fields which can be referenced by the expression
+    runtime_fields: &'a [RuntimeField],
+    /// true while parsing the expression of a runtime field, where _score is not available
+    runtime_field: bool,
+    /// current nesting depth, including the depth of the expression referencing a runtime field
+    depth: usize,
+    operands: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars.peek().map_or(self.text.len(), |(pos, _)| *pos)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.peek() == Some(expected) {
+            self.chars.next();
+            Ok(())
+        } else {
+            Err(format!(
+                "expression: '{}' expected at position {}",
+                expected,
+                self.position()
+            ))
+        }
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn parse_expression(&mut self) -> Result<Node, String> {
+        let mut node = self.parse_term()?;
+        loop {
+            let operator = match self.peek() {
+                Some('+') => Operator::Add,
+                Some('-') => Operator::Subtract,
+                _ => return Ok(node),
+            };
+            self.chars.next();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.parse_term()?));
+        }
+    }
+
+    /// term := factor (('*' | '/') factor)*
+    fn parse_term(&mut self) -> Result<Node, String> {
+        let mut node = self.parse_factor()?;
+        loop {
+            let operator = match self.peek() {
+                Some('*') => Operator::Multiply,
+                Some('/') => Operator::Divide,
+                _ => return Ok(node),
+            };
+            self.chars.next();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.parse_factor()?));
+        }
+    }
+
+    /// Limits the nesting depth and the number of operands (see MAX_EXPRESSION_DEPTH and MAX_EXPRESSION_OPERANDS)
+    fn parse_factor(&mut self) -> Result<Node, String> {
+        self.depth += 1;
+        self.operands += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(format!(
+                "expression: nesting exceeds the maximum depth of {}",
+                MAX_EXPRESSION_DEPTH
+            ));
+        }
+        if self.operands > MAX_EXPRESSION_OPERANDS {
+            return Err(format!(
+                "expression: exceeds the maximum of {} operands",
+                MAX_EXPRESSION_OPERANDS
+            ));
+        }
+        let node = self.parse_operand();
+        self.depth -= 1;
+        node
+    }
+
+    /// factor := number | identifier | function '(' expression (',' expression)* ')' | '(' expression ')' | '-' factor
+    fn parse_operand(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some('-') => {
+                self.chars.next();
+                Ok(Node::Negate(Box::new(self.parse_factor()?)))
+            }
+            Some('(') => {
+                self.chars.next();
+                let node = self.parse_expression()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.position();
+                while self
+                    .chars
+                    .next_if(|(_, c)| c.is_ascii_digit() || *c == '.')
+                    .is_some()
+                {}
+                let number = &self.text[start..self.position()];
+                number
+                    .parse::<f64>()
+                    .map(Node::Number)
+                    .map_err(|_| format!("expression: invalid number {}", number))
+            }
+            Some(c) if c.is_alphanumeric() || c == '_' => {
+                let start = self.position();
+                while self
+                    .chars
+                    .next_if(|(_, c)| c.is_alphanumeric() || *c == '_')
+                    .is_some()
+                {}
+                let name = &self.text[start..self.position()];
+
+                if name == "distance" && self.peek() == Some('(') {
+                    self.chars.next();
+                    self.skip_whitespace();
+                    let start = self.position();
+                    while self
+                        .chars
+                        .next_if(|(_, c)| c.is_alphanumeric() || *c == '_')
+                        .is_some()
+                    {}
+                    let field = &self.text[start..self.position()];
+                    let idx = match self.index.facets_map.get(field) {
+                        Some(idx) if self.index.facets[*idx].field_type == FieldType::Point => *idx,
+                        _ => {
+                            return Err(format!(
+                                "expression: distance requires a Point facet field: {}",
+                                field
+                            ))
+                        }
+                    };
+                    self.expect(',')?;
+                    let lat = self.parse_expression()?;
+                    self.expect(',')?;
+                    let lon = self.parse_expression()?;
+                    self.expect(')')?;
+                    return Ok(Node::Distance(
+                        idx,
+                        Box::new(lat),
+                        Box::new(lon),
+                        DistanceUnit::Kilometers,
+                    ));
+                }
+
+                if self.peek() == Some('(') {
+                    let Some((function, arity)) = Function::from_name(name) else {
+                        return Err(format!("expression: unknown function {}", name));
+                    };
+                    self.chars.next();
+                    let mut arguments = vec![self.parse_expression()?];
+                    while self.peek() == Some(',') {
+                        self.chars.next();
+                        arguments.push(self.parse_expression()?);
+                    }
+                    self.expect(')')?;
+                    if arguments.len() != arity {
+                        return Err(format!(
+                            "expression: function {} expects {} arguments",
+                            name, arity
+                        ));
+                    }
+                    return Ok(Node::Function(function, arguments));
+                }
+
+                match name {
+                    "_score" if self.runtime_field => {
+                        Err("expression: _score is not available in runtime fields".to_string())
+                    }
+                    "_score" => Ok(Node::Score),
+                    "now" => Ok(Node::Number(self.now)),
+                    "_random" => Ok(Node::Function(
+                        Function::Random,
+                        vec![Node::Number(self.seed as f64)],
+                    )),
+                    _ => match self.index.facets_map.get(name) {
+                        Some(idx) => match self.index.facets[*idx].field_type {
+                            FieldType::String
+                            | FieldType::Keyword
+                            | FieldType::StringSet
+                            | FieldType::Point
+                            | FieldType::Ip => {
+                                Err(format!("expression: facet field {} is not numerical", name))
+                            }
+                            _ => Ok(Node::Facet(*idx, 0.0)),
+                        },
+                        None => match self
+                            .runtime_fields
+                            .iter()
+                            .position(|runtime_field| runtime_field.name == name)
+                        {
+                            Some(i) => parse(
+                                &self.runtime_fields[i].expression,
+                                self.index,
+                                &self.runtime_fields[..i],
+                                true,
+                                self.now,
+                                self.seed,
+                                self.depth,
+                            )
+                            .map_err(|e| format!("runtime field {}: {}", name, e)),
+                            None => Err(format!("expression: facet field {} not found", name)),
+                        },
+                    },
+                }
+            }
+            Some(c) => Err(format!(
+                "expression: unexpected character '{}' at position {}",
+                c,
+                self.position()
+            )),
+            None => Err("expression: unexpected end".to_string()),
+        }
+    }
+}
+
+/// Parses a complete expression. Runtime fields can only refer to the runtime fields defined before them, which excludes cycles.
+/// * `depth`: nesting depth of the expression referencing the runtime field, 0 for the top level expression
+fn parse(
+    text: &str,
+    index: &Index,
+    runtime_fields: &[RuntimeField],
+    runtime_field: bool,
+    now: f64,
+    seed: u64,
+    depth: usize,
+) -> Result<Node, String> {
+    let mut parser = Parser {
+        text,
+        chars: text.char_indices().peekable(),
+        index,
+        now,
+        seed,
+        runtime_fields,
+        runtime_field,
+        depth,
+        operands: 0,
+    };
+
+    let root = parser.parse_expression()?;
+    if let Some(c) = parser.peek() {
+        return Err(format!(
+            "expression: unexpected character '{}' at position {}",
+            c,
+            parser.position()
+        ));
+    }
+    Ok(root)
+}
+
+impl Expression {
+    /// Compiles an expression once per request: facet field and runtime field names are resolved against the index.
+    pub fn compile(text: &str, index: &Index) -> Result<Expression, String> {
+        Self::compile_with(text, index, &index.runtime_fields, false)
+    }
+
+    /// Compiles the expression of a runtime field, which can refer to the given (preceding) runtime fields, but not to _score.
+    pub(crate) fn compile_runtime_field(
+        text: &str,
+        index: &Index,
+        runtime_fields: &[RuntimeField],
+    ) -> Result<Expression, String> {
+        Self::compile_with(text, index, runtime_fields, true)
+    }
+
+    fn compile_with(
+        text: &str,
+        index: &Index,
+        runtime_fields: &[RuntimeField],
+        runtime_field: bool,
+    ) -> Result<Expression, String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let root = parse(
+            text,
+            index,
+            runtime_fields,
+            runtime_field,
+            now.as_secs() as f64,
+            now.subsec_nanos() as u64,
+            0,
+        )?;
+        Ok(Expression { root })
+    }
+
+    /// Compiles declarative score functions, combined with each other by score_mode and with the BM25 score by boost_mode.
+    pub fn from_function_score(
+        function_score: &FunctionScore,
+        index: &Index,
+    ) -> Result<Expression, String> {
+        if function_score.functions.len() > MAX_EXPRESSION_OPERANDS {
+            return Err(format!(
+                "function score: exceeds the maximum of {} functions",
+                MAX_EXPRESSION_OPERANDS
+            ));
+        }
+        let mut function_nodes = Vec::new();
+        for function in function_score.functions.iter() {
+            let (ScoreFunction::FieldValueFactor { field, .. }
+            | ScoreFunction::Decay { field, .. }) = function;
+            let Some(idx) = index.facets_map.get(field).copied() else {
+                return Err(format!("function score: facet field {} not found", field));
+            };
+            let field_type = &index.facets[idx].field_type;
+            if matches!(
+                field_type,
+                FieldType::String | FieldType::Keyword | FieldType::StringSet | FieldType::Ip
+            ) {
+                return Err(format!(
+                    "function score: facet field {} is not numerical",
+                    field
+                ));
+            }
+
+            function_nodes.push(match function {
+                ScoreFunction::FieldValueFactor {
+                    factor,
+                    modifier,
+                    missing,
+                    ..
+                } => {
+                    if *field_type == FieldType::Point {
+                        return Err(format!(
+                            "function score: field_value_factor requires a numerical facet field: {}",
+                            field
+                        ));
+                    }
+                    let value = Node::Binary(
+                        Operator::Multiply,
+                        Box::new(Node::Number(*factor)),
+                        Box::new(Node::Facet(idx, *missing)),
+                    );
+                    let add = |number: f64, node: Node| {
+                        Node::Binary(Operator::Add, Box::new(Node::Number(number)), Box::new(node))
+                    };
+                    match modifier {
+                        FieldValueModifier::None => value,
+                        FieldValueModifier::Log => Node::Function(Function::Log10, vec![value]),
+                        FieldValueModifier::Log1p => {
+                            Node::Function(Function::Log10, vec![add(1.0, value)])
+                        }
+                        FieldValueModifier::Log2p => {
+                            Node::Function(Function::Log10, vec![add(2.0, value)])
+                        }
+                        FieldValueModifier::Ln => Node::Function(Function::Log, vec![value]),
+                        FieldValueModifier::Ln1p => Node::Function(Function::Log1p, vec![value]),
+                        FieldValueModifier::Ln2p => {
+                            Node::Function(Function::Log, vec![add(2.0, value)])
+                        }
+                        FieldValueModifier::Square => Node::Binary(
+                            Operator::Multiply,
+                            Box::new(value.clone()),
+                            Box::new(value),
+                        ),
+                        FieldValueModifier::Sqrt => Node::Function(Function::Sqrt, vec![value]),
+                        FieldValueModifier::Reciprocal => Node::Binary(
+                            Operator::Divide,
+                            Box::new(Node::Number(1.0)),
+                            Box::new(value),
+                        ),
+                    }
+                }
+
+                ScoreFunction::Decay {
+                    function,
+                    origin,
+                    base,
+                    unit,
+                    scale,
+                    offset,
+                    decay,
+                    ..
+                } => {
+                    if !(*scale > 0.0 && *decay > 0.0 && *decay < 1.0) {
+                        return Err(
+                            "function score: decay requires scale > 0 and 0 < decay < 1".to_string()
+                        );
+                    }
+
+                    let (value, origin) = if *field_type == FieldType::Point {
+                        let Some(base) = base.as_ref().filter(|base| base.len() == 2) else {
+                            return Err(format!(
+                                "function score: decay on Point facet field {} requires base [lat, lon]",
+                                field
+                            ));
+                        };
+                        (
+                            Node::Distance(
+                                idx,
+                                Box::new(Node::Number(base[0])),
+                                Box::new(Node::Number(base[1])),
+                                unit.clone(),
+                            ),
+                            0.0,
+                        )
+                    } else {
+                        let origin = origin.unwrap_or(if matches!(field_type, FieldType::Timestamp | FieldType::Date) {
+                            SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap()
+                                .as_secs() as f64
+                        } else {
+                            0.0
+                        });
+                        (Node::Facet(idx, f64::INFINITY), origin)
+                    };
+
+                    Node::Function(
+                        match function {
+                            DecayFunction::Gauss => Function::Gauss,
+                            DecayFunction::Linear => Function::Linear,
+                            DecayFunction::Exponential => Function::Exponential,
+                        },
+                        vec![
+                            value,
+                            Node::Number(origin),
+                            Node::Number(*scale),
+                            Node::Number(*offset),
+                            Node::Number(*decay),
+                        ],
+                    )
+                }
+            });
+        }
+
+        if function_nodes.is_empty() {
+            return Ok(Expression { root: Node::Score });
+        }
+
+        let count = function_nodes.len() as f64;
+        let combined = function_nodes
+            .into_iter()
+            .reduce(|combined, node| match function_score.score_mode {
+                ScoreMode::Multiply => {
+                    Node::Binary(Operator::Multiply, Box::new(combined), Box::new(node))
+                }
+                ScoreMode::Sum | ScoreMode::Avg => {
+                    Node::Binary(Operator::Add, Box::new(combined), Box::new(node))
+                }
+                ScoreMode::Max => Node::Function(Function::Max, vec![combined, node]),
+                ScoreMode::Min => Node::Function(Function::Min, vec![combined, node]),
+            })
+            .unwrap();
+        let combined = if function_score.score_mode == ScoreMode::Avg {
+            Node::Binary(
+                Operator::Divide,
+                Box::new(combined),
+                Box::new(Node::Number(count)),
+            )
+        } else {
+            combined
+        };
+
+        let root = match function_score.boost_mode {
+            BoostMode::Multiply => Node::Binary(
+                Operator::Multiply,
+                Box::new(Node::Score),
+                Box::new(combined),
+            ),
+            BoostMode::Sum => {
+                Node::Binary(Operator::Add, Box::new(Node::Score), Box::new(combined))
+            }
+            BoostMode::Replace => combined,
+        };
+
+        Ok(Expression { root })
+    }
+
+    /// Evaluates the expression for a result, with facet values read from the facet file (doc-values) of the index.
+    #[inline]
+    pub(crate) fn evaluate(&self, index: &Index, doc_id: usize, score: f32) -> f64 {
+        let value = evaluate_node(&self.root, index, doc_id, score);
+        if value.is_finite() {
+            value
+        } else {
+            0.0
+        }
+    }
+
+    /// Names of the facet fields the expression reads, including the facet fields of the runtime fields it refers to
+    pub fn fields(&self, index: &Index) -> Vec<String> {
+        let mut fields = Vec::new();
+        node_fields(&self.root, index, &mut fields);
+        fields
+    }
+}
+
+fn node_fields(node: &Node, index: &Index, fields: &mut Vec<String>) {
+    let push = |idx: usize, fields: &mut Vec<String>| {
+        let name = &index.facets[idx].name;
+        if !fields.contains(name) {
+            fields.push(name.clone());
+        }
+    };
+    match node {
+        Node::Number(_) | Node::Score => {}
+        Node::Facet(idx, _) => push(*idx, fields),
+        Node::Distance(idx, lat, lon, _) => {
+            push(*idx, fields);
+            node_fields(lat, index, fields);
+            node_fields(lon, index, fields);
+        }
+        Node::Negate(node) => node_fields(node, index, fields),
+        Node::Binary(_, left, right) => {
+            node_fields(left, index, fields);
+            node_fields(right, index, fields);
+        }
+        Node::Function(_, arguments) => {
+            for argument in arguments.iter() {
+                node_fields(argument, index, fields);
+            }
+        }
+    }
+}
+
+fn evaluate_node(node: &Node, index: &Index, doc_id: usize, score: f32) -> f64 {
+    match node {
+        Node::Number(number) => *number,
+        Node::Score => score as f64,
+        Node::Facet(idx, missing) => {
+            if index.is_facet_missing(*idx, doc_id) {
+                *missing
+            } else {
+                index.get_facet_value_f64(*idx, doc_id)
+            }
+        }
+        Node::Distance(idx, lat, lon, unit) => {
+            if index.is_facet_missing(*idx, doc_id) {
+                return f64::INFINITY;
+            }
+            let point = decode_morton_2_d(read_u64(
+                &index.facets_file_mmap,
+                (index.facets_size_sum * doc_id) + index.facets[*idx].offset,
+            ));
+            let base = vec![
+                evaluate_node(lat, index, doc_id, score),
+                evaluate_node(lon, index, doc_id, score),
+            ];
+            euclidian_distance(&point, &base, unit)
+        }
+        Node::Negate(node) => -evaluate_node(node, index, doc_id, score),
+        Node::Binary(operator, left, right) => {
+            let left = evaluate_node(left, index, doc_id, score);
+            let right = evaluate_node(right, index, doc_id, score);
+            match operator {
+                Operator::Add => left + right,
+                Operator::Subtract => left - right,
+                Operator::Multiply => left * right,
+                Operator::Divide => left / right,
+            }
+        }
+        Node::Function(function, arguments) => {
+            let x = evaluate_node(&arguments[0], index, doc_id, score);
+            match function {
+                Function::Abs => x.abs(),
+                Function::Sqrt => x.sqrt(),
+                Function::Exp => x.exp(),
+                Function::Log => x.ln(),
+                Function::Log10 => x.log10(),
+                Function::Log1p => x.ln_1p(),
+                Function::Pow => x.powf(evaluate_node(&arguments[1], index, doc_id, score)),
+                Function::Min => x.min(evaluate_node(&arguments[1], index, doc_id, score)),
+                Function::Max => x.max(evaluate_node(&arguments[1], index, doc_id, score)),
+                Function::Gauss | Function::Linear | Function::Exponential => {
+                    let origin = evaluate_node(&arguments[1], index, doc_id, score);
+                    let scale = evaluate_node(&arguments[2], index, doc_id, score);
+                    let offset = evaluate_node(&arguments[3], index, doc_id, score);
+                    let decay = evaluate_node(&arguments[4], index, doc_id, score);
+                    decay_function(*function, x, origin, scale, offset, decay)
+                }
+                Function::Random => random_value(doc_id, x),
+            }
+        }
+    }
+}
+
+/// Deterministic pseudo-random number in the range 0..1 for a document and seed (SplitMix64 finalizer).
+#[inline]
+fn random_value(doc_id: usize, seed: f64) -> f64 {
+    let mut z = (doc_id as u64)
+        .wrapping_add((seed as i64 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        .wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 40) as f64 / (1u64 << 24) as f64
+}
+
+/// Decay functions: 1.0 within origin +- offset, and decay at distance offset+scale from origin.
+/// Infinite values (e.g. missing facet values) decay to 0.
+#[inline]
+fn decay_function(
+    function: Function,
+    value: f64,
+    origin: f64,
+    scale: f64,
+    offset: f64,
+    decay: f64,
+) -> f64 {
+    let distance = ((value - origin).abs() - offset).max(0.0);
+    if distance.is_infinite() {
+        return 0.0;
+    }
+
+    match function {
+        Function::Gauss => {
+            let sigma_square = -(scale * scale) / (2.0 * decay.ln());
+            (-(distance * distance) / (2.0 * sigma_square)).exp()
+        }
+        Function::Exponential => (decay.ln() / scale * distance).exp(),
+        _ => {
+            let s = scale / (1.0 - decay);
+            ((s - distance) / s).max(0.0)
+        }
+    }
+}
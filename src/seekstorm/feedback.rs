@@ -0,0 +1,253 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::index::Index;
+
+pub(crate) const QUERY_FEEDBACK_FILENAME: &str = "query_feedback.json";
+/// Maximum number of recent searches, whose search_id can be referenced by feedback events
+const RECENT_SEARCHES_MAX: usize = 100_000;
+/// Maximum number of distinct queries with feedback statistics: further queries are not tracked
+const QUERIES_MAX: usize = 100_000;
+
+/// Type of a feedback event
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum FeedbackEventType {
+    /// The user clicked on a search result
+    Click,
+    /// The user converted after clicking on a search result, e.g. purchased, downloaded or signed up
+    Conversion,
+}
+
+/// Click or conversion signal for a search result, referencing the search by its search_id (see Index::register_search and Index::record_feedback_events)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FeedbackEvent {
+    pub search_id: String,
+    /// Position of the result within the results (0 for the top result), including the offset of the search
+    pub position: usize,
+    pub doc_id: usize,
+    pub event_type: FeedbackEventType,
+}
+
+/// Click-through statistics of a query, aggregated from all searches and feedback events of the query
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct QueryAnalytics {
+    /// Normalized query string: lowercase, single spaces
+    pub query: String,
+    pub searches: u64,
+    pub clicks: u64,
+    pub conversions: u64,
+    /// Clicks per search
+    pub click_through_rate: f64,
+    /// Conversions per search
+    pub conversion_rate: f64,
+    /// Mean position of the clicked results (0 for the top result)
+    pub mean_click_position: f64,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct QueryCounters {
+    searches: u64,
+    clicks: u64,
+    conversions: u64,
+    click_position_sum: u64,
+    /// Clicks and conversions per document id
+    documents: AHashMap<usize, (u64, u64)>,
+}
+
+#[derive(Default)]
+struct QueryFeedbackData {
+    queries: AHashMap<String, QueryCounters>,
+    /// search_id and normalized query string of the recent searches, in search order
+    recent_searches: VecDeque<(String, String)>,
+    recent_searches_map: AHashMap<String, String>,
+    unsaved: bool,
+}
+
+/// Click and conversion feedback of an index, updated by concurrent searches holding a read lock of the index
+#[derive(Default, Clone)]
+pub(crate) struct QueryFeedback(Arc<Mutex<QueryFeedbackData>>);
+
+pub(crate) fn load_query_feedback(index_path: &Path) -> QueryFeedback {
+    let queries =
+        if let Ok(query_feedback_file) = File::open(index_path.join(QUERY_FEEDBACK_FILENAME)) {
+            serde_json::from_reader(BufReader::new(query_feedback_file)).unwrap_or_default()
+        } else {
+            AHashMap::new()
+        };
+    QueryFeedback(Arc::new(Mutex::new(QueryFeedbackData {
+        queries,
+        ..Default::default()
+    })))
+}
+
+fn normalize_query(query_string: &str) -> String {
+    query_string
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+impl Index {
+    /// Registers a search for the click-through statistics of its query, and returns its search_id.
+    /// Feedback events (clicks, conversions) for the results of the search reference the search_id.
+    /// Only the most recent 100.000 searches can be referenced by feedback events.
+    pub fn register_search(&self, query_string: &str) -> String {
+        let query = normalize_query(query_string);
+        let search_id = format!("{:016x}", rand::random::<u64>());
+
+        let mut feedback = self.query_feedback.0.lock().unwrap();
+        let tracked = feedback.queries.len() < QUERIES_MAX || feedback.queries.contains_key(&query);
+        if tracked {
+            feedback.queries.entry(query.clone()).or_default().searches += 1;
+            feedback.unsaved = true;
+        }
+
+        if feedback.recent_searches.len() == RECENT_SEARCHES_MAX {
+            if let Some((expired_search_id, _)) = feedback.recent_searches.pop_front() {
+                feedback.recent_searches_map.remove(&expired_search_id);
+            }
+        }
+        feedback
+            .recent_searches
+            .push_back((search_id.clone(), query.clone()));
+        feedback
+            .recent_searches_map
+            .insert(search_id.clone(), query);
+        search_id
+    }
+
+    /// Records click or conversion signals for results of registered searches.
+    /// Either all events are recorded, or none if a search_id is unknown or expired. Returns the number of recorded events.
+    pub fn record_feedback_events(
+        &self,
+        feedback_events: &[FeedbackEvent],
+    ) -> Result<usize, String> {
+        let mut feedback = self.query_feedback.0.lock().unwrap();
+        if let Some(feedback_event) = feedback_events.iter().find(|feedback_event| {
+            !feedback
+                .recent_searches_map
+                .contains_key(&feedback_event.search_id)
+        }) {
+            return Err(format!(
+                "search_id unknown or expired: {}",
+                feedback_event.search_id
+            ));
+        }
+
+        for feedback_event in feedback_events.iter() {
+            let query = feedback.recent_searches_map[&feedback_event.search_id].clone();
+            let Some(counters) = feedback.queries.get_mut(&query) else {
+                continue;
+            };
+            let document_counters = counters.documents.entry(feedback_event.doc_id).or_default();
+            match feedback_event.event_type {
+                FeedbackEventType::Click => {
+                    document_counters.0 += 1;
+                    counters.clicks += 1;
+                    counters.click_position_sum += feedback_event.position as u64;
+                }
+                FeedbackEventType::Conversion => {
+                    document_counters.1 += 1;
+                    counters.conversions += 1;
+                }
+            }
+            feedback.unsaved = true;
+        }
+        Ok(feedback_events.len())
+    }
+
+    /// Get the click-through statistics of the queries with the most searches, in descending order of searches
+    pub fn get_query_analytics(&self, length: usize) -> Vec<QueryAnalytics> {
+        let feedback = self.query_feedback.0.lock().unwrap();
+        let mut query_analytics: Vec<QueryAnalytics> = feedback
+            .queries
+            .iter()
+            .map(|(query, counters)| {
+                let searches = counters.searches.max(1) as f64;
+                QueryAnalytics {
+                    query: query.clone(),
+                    searches: counters.searches,
+                    clicks: counters.clicks,
+                    conversions: counters.conversions,
+                    click_through_rate: counters.clicks as f64 / searches,
+                    conversion_rate: counters.conversions as f64 / searches,
+                    mean_click_position: if counters.clicks > 0 {
+                        counters.click_position_sum as f64 / counters.clicks as f64
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        query_analytics.sort_unstable_by(|a, b| {
+            b.searches
+                .cmp(&a.searches)
+                .then_with(|| a.query.cmp(&b.query))
+        });
+        query_analytics.truncate(length);
+        query_analytics
+    }
+
+    /// Queries starting with the prefix, with their popularity: the sum of searches, clicks and conversions of the query.
+    /// The prefix is normalized like the queries, except that trailing whitespace is kept, to complete only the following term.
+    pub(crate) fn get_popular_queries(&self, prefix: &str) -> Vec<(String, u64)> {
+        let mut prefix_normalized = normalize_query(prefix);
+        if !prefix_normalized.is_empty() && prefix.ends_with(char::is_whitespace) {
+            prefix_normalized.push(' ');
+        }
+        let feedback = self.query_feedback.0.lock().unwrap();
+        feedback
+            .queries
+            .iter()
+            .filter(|(query, _)| query.starts_with(&prefix_normalized))
+            .map(|(query, counters)| {
+                (
+                    query.clone(),
+                    counters.searches + counters.clicks + counters.conversions,
+                )
+            })
+            .collect()
+    }
+
+    /// Click-through rate and conversion rate of a document for a query: clicks and conversions of the document per search of the query
+    pub(crate) fn get_document_feedback(&self, query_string: &str, doc_id: usize) -> (f64, f64) {
+        let feedback = self.query_feedback.0.lock().unwrap();
+        match feedback.queries.get(&normalize_query(query_string)) {
+            Some(counters) if counters.searches > 0 => {
+                let (clicks, conversions) =
+                    counters.documents.get(&doc_id).copied().unwrap_or_default();
+                (
+                    clicks as f64 / counters.searches as f64,
+                    conversions as f64 / counters.searches as f64,
+                )
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// Saves the click-through statistics of the queries to the index directory, if they have changed since the last save.
+    /// Invoked by close_index. Without closing the index, it has to be invoked periodically, as the SeekStorm server does for all its indices.
+    pub fn save_query_feedback(&self) -> Result<(), String> {
+        let mut feedback = self.query_feedback.0.lock().unwrap();
+        if !feedback.unsaved {
+            return Ok(());
+        }
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(QUERY_FEEDBACK_FILENAME))
+                .map_err(|e| e.to_string())?,
+            &feedback.queries,
+        )
+        .map_err(|e| e.to_string())?;
+        feedback.unsaved = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+
+use crate::{
+    commit::commit_index,
+    compact::compact_index,
+    error::SeekStormError,
+    index::{Index, IndexArc, FROZEN_FILENAME},
+};
+
+/// Freeze the index to read-only, e.g. for closed monthly archive indices:
+/// commits all uncommitted documents, compacts the index if it contains deleted documents and all indexed fields are stored,
+/// marks the index as frozen (persistent, also after reopening), and releases the write buffers to reclaim memory.
+/// A frozen index can be searched as usual, but document mutations (index, update, delete, clear) are rejected by the REST API and ignored by the library.
+/// If the commit, compaction or persisting the frozen state fails, the error is returned and the index stays writable.
+#[allow(async_fn_in_trait)]
+pub trait Freeze {
+    async fn freeze(&self) -> Result<(), SeekStormError>;
+    /// Unfreeze a frozen index, to allow document mutations again
+    async fn unfreeze(&self) -> Result<(), SeekStormError>;
+}
+
+impl Freeze for IndexArc {
+    async fn freeze(&self) -> Result<(), SeekStormError> {
+        let mut index_mut = self.write().await;
+        if index_mut.frozen {
+            return Ok(());
+        }
+        // document mutations are rejected from here on, so that the committed and compacted index is complete
+        index_mut.frozen = true;
+        drop(index_mut);
+
+        let result: Result<(), SeekStormError> = async {
+            commit_index(self).await;
+
+            let index_ref = self.read().await;
+            let is_compactable = index_ref.deleted_doc_count() > 0
+                && index_ref
+                    .indexed_schema_vec
+                    .iter()
+                    .all(|schema_field| schema_field.stored);
+            drop(index_ref);
+            if is_compactable {
+                compact_index(self).await?;
+            }
+
+            let index_ref = self.read().await;
+            File::create(Path::new(&index_ref.index_path_string).join(FROZEN_FILENAME))?;
+            Ok(())
+        }
+        .await;
+
+        // the index stays writable if it couldn't be frozen
+        let mut index_mut = self.write().await;
+        if result.is_err() {
+            index_mut.frozen = false;
+            return result;
+        }
+        index_mut.release_write_buffers();
+
+        Ok(())
+    }
+
+    async fn unfreeze(&self) -> Result<(), SeekStormError> {
+        let mut index_mut = self.write().await;
+        if !index_mut.frozen {
+            return Ok(());
+        }
+        let frozen_path = Path::new(&index_mut.index_path_string).join(FROZEN_FILENAME);
+        if frozen_path.exists() {
+            fs::remove_file(frozen_path)?;
+        }
+
+        Ok(index_mut.reopen_index().await?)
+    }
+}
+
+impl Index {
+    /// Returns an error if the index is frozen (read-only)
+    pub fn check_writable(&self) -> Result<(), SeekStormError> {
+        if self.frozen {
+            Err(SeekStormError::Conflict(
+                "index is frozen (read-only)".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Releases the buffers only required for indexing and commit
+    pub(crate) fn release_write_buffers(&mut self) {
+        self.compressed_index_segment_block_buffer = Vec::new();
+        self.compressed_docstore_segment_block_buffer = Vec::new();
+        self.postings_buffer = Vec::new();
+        for segment in self.segments_level0.iter_mut() {
+            segment.positions_compressed = Vec::new();
+            segment.segment = Default::default();
+        }
+    }
+}
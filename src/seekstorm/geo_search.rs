@@ -4,7 +4,7 @@ use std::cmp::Ordering;
 use std::arch::x86_64::{_pdep_u64, _pext_u64};
 
 use crate::{
-    index::DistanceUnit,
+    index::{DistanceMethod, DistanceUnit},
     search::{Point, SortOrder},
 };
 
@@ -114,17 +114,67 @@ const EARTH_RADIUS_KM: f64 = 6371.0087714;
 const EARTH_RADIUS_MI: f64 = 3_958.761_315_801_475;
 const DEG2RAD: f64 = 0.017_453_292_519_943_295;
 
-/// calculates distance in kilometers or miles between two 2D-coordinates using Euclidian distance (Pythagoras theorem) with Equirectangular approximation.
+impl DistanceUnit {
+    /// Earth radius in the distance unit
+    #[inline]
+    fn earth_radius(&self) -> f64 {
+        match self {
+            DistanceUnit::Kilometers => EARTH_RADIUS_KM,
+            DistanceUnit::Miles => EARTH_RADIUS_MI,
+            DistanceUnit::Meters => EARTH_RADIUS_KM * 1000.0,
+        }
+    }
+}
+
+/// calculates distance in kilometers, miles or meters between two 2D-coordinates using Euclidian distance (Pythagoras theorem) with Equirectangular approximation.
 #[inline]
 pub fn euclidian_distance(point1: &Point, point2: &Point, unit: &DistanceUnit) -> f64 {
     let x = DEG2RAD * (point2[1] - point1[1]) * f64::cos(DEG2RAD * (point1[0] + point2[0]) / 2.0);
     let y = DEG2RAD * (point2[0] - point1[0]);
 
-    (if *unit == DistanceUnit::Kilometers {
-        EARTH_RADIUS_KM
-    } else {
-        EARTH_RADIUS_MI
-    }) * (x * x + y * y).sqrt()
+    unit.earth_radius() * (x * x + y * y).sqrt()
+}
+
+/// calculates the great-circle distance in kilometers, miles or meters between two 2D-coordinates using the haversine formula.
+/// Slower than the Equirectangular approximation, but accurate also for long distances and near the poles.
+#[inline]
+pub fn haversine_distance(point1: &Point, point2: &Point, unit: &DistanceUnit) -> f64 {
+    let lat_sin = (DEG2RAD * (point2[0] - point1[0]) / 2.0).sin();
+    let lon_sin = (DEG2RAD * (point2[1] - point1[1]) / 2.0).sin();
+    let a = lat_sin * lat_sin
+        + f64::cos(DEG2RAD * point1[0]) * f64::cos(DEG2RAD * point2[0]) * lon_sin * lon_sin;
+
+    unit.earth_radius() * 2.0 * a.sqrt().min(1.0).asin()
+}
+
+/// calculates the distance between two 2D-coordinates with the given method
+#[inline]
+pub fn distance(
+    point1: &Point,
+    point2: &Point,
+    unit: &DistanceUnit,
+    method: &DistanceMethod,
+) -> f64 {
+    match method {
+        DistanceMethod::Planar => euclidian_distance(point1, point2, unit),
+        DistanceMethod::Haversine => haversine_distance(point1, point2, unit),
+    }
+}
+
+/// Points of the value of a Point field: either a single point `[lat, lon]`, or multiple points `[[lat, lon], [lat, lon]]` of a multi-valued Point field.
+/// None if the value is no point or no non-empty array of points, or if any point is outside the valid coordinate range.
+pub(crate) fn points_from_json(value: &serde_json::Value) -> Option<Vec<Point>> {
+    let points = serde_json::from_value::<Point>(value.clone())
+        .map(|point| vec![point])
+        .or_else(|_| serde_json::from_value::<Vec<Point>>(value.clone()))
+        .ok()?;
+    (!points.is_empty()
+        && points.iter().all(|point| {
+            point.len() == 2
+                && (-90.0..=90.0).contains(&point[0])
+                && (-180.0..=180.0).contains(&point[1])
+        }))
+    .then_some(points)
 }
 
 /// Converts a Point and a distance radius into a range of morton_codes for geo search range filtering.
@@ -134,11 +184,7 @@ pub fn point_distance_to_morton_range(
     distance: f64,
     unit: &DistanceUnit,
 ) -> std::ops::Range<u64> {
-    let earth_radius = if *unit == DistanceUnit::Kilometers {
-        EARTH_RADIUS_KM
-    } else {
-        EARTH_RADIUS_MI
-    };
+    let earth_radius = unit.earth_radius();
     let lat_delta = distance / (DEG2RAD * earth_radius);
     let lon_delta = distance / (DEG2RAD * earth_radius * f64::cos(DEG2RAD * point[0]));
     let morton_min = encode_morton_2_d(&vec![point[0] - lat_delta, point[1] - lon_delta]);
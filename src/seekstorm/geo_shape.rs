@@ -0,0 +1,534 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+
+use ahash::{AHashMap, AHashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::index::{Document, FieldType, Index, SchemaField};
+
+pub(crate) const GEO_SHAPES_FILENAME: &str = "geo_shapes.ndjson";
+
+/// Maximum number of children of a R-tree node
+const NODE_CAPACITY: usize = 16;
+
+/// GeoJSON position: longitude and latitude in decimal degrees (note the GeoJSON order, unlike Point fields with latitude first)
+pub type Position = [f64; 2];
+
+/// Geometry of a GeoShape field in GeoJSON format, e.g. `{"type":"Polygon","coordinates":[[[13.3,52.5],[13.5,52.5],[13.5,52.6],[13.3,52.6],[13.3,52.5]]]}`.
+/// Polygons consist of a closed outer ring followed by optional closed rings of holes.
+/// Geometries are evaluated on the plane of longitude and latitude: edges are straight lines in decimal degrees, shapes crossing the antimeridian are not supported.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum GeoShape {
+    Point(Position),
+    MultiPoint(Vec<Position>),
+    LineString(Vec<Position>),
+    MultiLineString(Vec<Vec<Position>>),
+    Polygon(Vec<Vec<Position>>),
+    MultiPolygon(Vec<Vec<Vec<Position>>>),
+}
+
+/// Spatial relation between the GeoShape field of a document and the shape of a GeoShapeFilter
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Default)]
+pub enum ShapeRelation {
+    /// The document shape and the filter shape have at least one point in common, e.g. the coverage areas containing a location
+    #[default]
+    Intersects,
+    /// The document shape contains the whole filter shape, e.g. the territory containing a delivery area
+    Contains,
+    /// The document shape lies within the filter shape, e.g. the routes within a region
+    Within,
+}
+
+/// Filter of a GeoShape field (FacetFilter::GeoShape): documents whose shape has the relation to the shape of the filter.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct GeoShapeFilter {
+    #[serde(default)]
+    pub relation: ShapeRelation,
+    pub shape: GeoShape,
+}
+
+/// Shape of a document, as line of the geo_shapes.ndjson file
+#[derive(Deserialize, Serialize)]
+struct GeoShapeEntry {
+    field: String,
+    doc_id: usize,
+    shape: GeoShape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BoundingBox {
+    min: Position,
+    max: Position,
+}
+
+impl BoundingBox {
+    const EMPTY: BoundingBox = BoundingBox {
+        min: [f64::INFINITY, f64::INFINITY],
+        max: [f64::NEG_INFINITY, f64::NEG_INFINITY],
+    };
+
+    fn extend(&mut self, position: &Position) {
+        for (i, coordinate) in position.iter().enumerate() {
+            self.min[i] = self.min[i].min(*coordinate);
+            self.max[i] = self.max[i].max(*coordinate);
+        }
+    }
+
+    fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let mut bounding_box = *self;
+        bounding_box.extend(&other.min);
+        bounding_box.extend(&other.max);
+        bounding_box
+    }
+
+    fn intersects(&self, other: &BoundingBox) -> bool {
+        (0..2).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    fn contains(&self, other: &BoundingBox) -> bool {
+        (0..2).all(|i| self.min[i] <= other.min[i] && other.max[i] <= self.max[i])
+    }
+
+    fn center(&self, i: usize) -> f64 {
+        (self.min[i] + self.max[i]) / 2.0
+    }
+}
+
+/// GeoShape decomposed into points, lines and polygons for the spatial predicates
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Geometry {
+    points: Vec<Position>,
+    lines: Vec<Vec<Position>>,
+    /// outer ring followed by the rings of the holes
+    polygons: Vec<Vec<Vec<Position>>>,
+    bounding_box: BoundingBox,
+}
+
+impl GeoShape {
+    /// Checks the coordinate ranges, the minimum number of positions of lines (2) and rings (4), and that rings are closed.
+    pub fn validate(&self) -> Result<(), String> {
+        let check_positions = |positions: &[Position], min_len: usize| -> Result<(), String> {
+            if positions.len() < min_len {
+                return Err(format!(
+                    "geo shape: at least {} positions required",
+                    min_len
+                ));
+            }
+            if let Some(position) = positions.iter().find(|position| {
+                !(-180.0..=180.0).contains(&position[0]) || !(-90.0..=90.0).contains(&position[1])
+            }) {
+                return Err(format!(
+                    "geo shape: position [{}, {}] outside valid coordinate range",
+                    position[0], position[1]
+                ));
+            }
+            Ok(())
+        };
+        let check_polygon = |polygon: &Vec<Vec<Position>>| -> Result<(), String> {
+            if polygon.is_empty() {
+                return Err("geo shape: polygon without rings".to_string());
+            }
+            for ring in polygon.iter() {
+                check_positions(ring, 4)?;
+                if ring.first() != ring.last() {
+                    return Err("geo shape: polygon ring is not closed".to_string());
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            GeoShape::Point(position) => check_positions(std::slice::from_ref(position), 1),
+            GeoShape::MultiPoint(positions) => check_positions(positions, 1),
+            GeoShape::LineString(positions) => check_positions(positions, 2),
+            GeoShape::MultiLineString(lines) => {
+                if lines.is_empty() {
+                    return Err("geo shape: no lines".to_string());
+                }
+                lines.iter().try_for_each(|line| check_positions(line, 2))
+            }
+            GeoShape::Polygon(polygon) => check_polygon(polygon),
+            GeoShape::MultiPolygon(polygons) => {
+                if polygons.is_empty() {
+                    return Err("geo shape: no polygons".to_string());
+                }
+                polygons.iter().try_for_each(check_polygon)
+            }
+        }
+    }
+
+    pub(crate) fn geometry(&self) -> Geometry {
+        let (points, lines, polygons) = match self {
+            GeoShape::Point(position) => (vec![*position], Vec::new(), Vec::new()),
+            GeoShape::MultiPoint(positions) => (positions.clone(), Vec::new(), Vec::new()),
+            GeoShape::LineString(positions) => (Vec::new(), vec![positions.clone()], Vec::new()),
+            GeoShape::MultiLineString(lines) => (Vec::new(), lines.clone(), Vec::new()),
+            GeoShape::Polygon(polygon) => (Vec::new(), Vec::new(), vec![polygon.clone()]),
+            GeoShape::MultiPolygon(polygons) => (Vec::new(), Vec::new(), polygons.clone()),
+        };
+
+        let mut geometry = Geometry {
+            points,
+            lines,
+            polygons,
+            bounding_box: BoundingBox::EMPTY,
+        };
+        let mut bounding_box = BoundingBox::EMPTY;
+        geometry.for_each_vertex(|position| {
+            bounding_box.extend(position);
+            false
+        });
+        geometry.bounding_box = bounding_box;
+        geometry
+    }
+}
+
+/// Cross product of the vectors p->q and p->r: > 0 counterclockwise, < 0 clockwise, 0 collinear
+#[inline]
+fn orientation(p: &Position, q: &Position, r: &Position) -> f64 {
+    (q[0] - p[0]) * (r[1] - p[1]) - (q[1] - p[1]) * (r[0] - p[0])
+}
+
+/// Position is on the segment a-b, with a tolerance of 1e-9 of the segment length for rounding errors
+#[inline]
+fn is_on_segment(p: &Position, a: &Position, b: &Position) -> bool {
+    orientation(a, b, p).abs() <= 1e-9 * ((b[0] - a[0]).abs() + (b[1] - a[1]).abs())
+        && p[0] >= a[0].min(b[0])
+        && p[0] <= a[0].max(b[0])
+        && p[1] >= a[1].min(b[1])
+        && p[1] <= a[1].max(b[1])
+}
+
+/// Segments a1-a2 and b1-b2 have at least one point in common, including touching endpoints
+fn segments_intersect(a1: &Position, a2: &Position, b1: &Position, b2: &Position) -> bool {
+    let o1 = orientation(a1, a2, b1);
+    let o2 = orientation(a1, a2, b2);
+    let o3 = orientation(b1, b2, a1);
+    let o4 = orientation(b1, b2, a2);
+    (o1 * o2 < 0.0 && o3 * o4 < 0.0)
+        || is_on_segment(b1, a1, a2)
+        || is_on_segment(b2, a1, a2)
+        || is_on_segment(a1, b1, b2)
+        || is_on_segment(a2, b1, b2)
+}
+
+/// Segments a1-a2 and b1-b2 cross each other in a single point inside both segments
+fn segments_cross(a1: &Position, a2: &Position, b1: &Position, b2: &Position) -> bool {
+    orientation(a1, a2, b1) * orientation(a1, a2, b2) < 0.0
+        && orientation(b1, b2, a1) * orientation(b1, b2, a2) < 0.0
+}
+
+/// Position is inside the ring (ray casting), positions on the ring are not distinguished
+fn ring_contains(ring: &[Position], position: &Position) -> bool {
+    let mut inside = false;
+    for segment in ring.windows(2) {
+        let (a, b) = (&segment[0], &segment[1]);
+        if (a[1] > position[1]) != (b[1] > position[1])
+            && position[0] < (b[0] - a[0]) * (position[1] - a[1]) / (b[1] - a[1]) + a[0]
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn is_on_ring(ring: &[Position], position: &Position) -> bool {
+    ring.windows(2)
+        .any(|segment| is_on_segment(position, &segment[0], &segment[1]))
+}
+
+impl Geometry {
+    /// Calls f for every vertex, until f returns true
+    fn for_each_vertex(&self, mut f: impl FnMut(&Position) -> bool) -> bool {
+        self.points.iter().any(&mut f)
+            || self.lines.iter().flatten().any(&mut f)
+            || self.polygons.iter().flatten().flatten().any(&mut f)
+    }
+
+    /// Calls f for every segment of the lines and polygon rings, until f returns true
+    fn for_each_segment(&self, mut f: impl FnMut(&Position, &Position) -> bool) -> bool {
+        self.lines
+            .iter()
+            .chain(self.polygons.iter().flatten())
+            .any(|line| line.windows(2).any(|segment| f(&segment[0], &segment[1])))
+    }
+
+    /// Position is inside or on the boundary of a polygon, on a line or equal to a point of the geometry
+    fn covers(&self, position: &Position) -> bool {
+        self.points.iter().any(|point| point == position)
+            || self.lines.iter().any(|line| is_on_ring(line, position))
+            || self.polygons.iter().any(|polygon| {
+                polygon.iter().any(|ring| is_on_ring(ring, position))
+                    || (ring_contains(&polygon[0], position)
+                        && !polygon[1..]
+                            .iter()
+                            .any(|hole| ring_contains(hole, position)))
+            })
+    }
+
+    /// Position is inside a polygon of the geometry, but not on its boundary
+    fn covers_interior(&self, position: &Position) -> bool {
+        self.polygons.iter().any(|polygon| {
+            !polygon.iter().any(|ring| is_on_ring(ring, position))
+                && ring_contains(&polygon[0], position)
+                && !polygon[1..]
+                    .iter()
+                    .any(|hole| ring_contains(hole, position))
+        })
+    }
+
+    pub(crate) fn intersects(&self, other: &Geometry) -> bool {
+        self.bounding_box.intersects(&other.bounding_box)
+            && (self.for_each_segment(|a1, a2| {
+                other.for_each_segment(|b1, b2| segments_intersect(a1, a2, b1, b2))
+            }) || self.for_each_vertex(|position| other.covers(position))
+                || other.for_each_vertex(|position| self.covers(position)))
+    }
+
+    /// All points of other are covered by self: all vertices and segment midpoints of other are covered,
+    /// no segment of other crosses the boundary of self, and no hole of self lies inside other
+    pub(crate) fn contains(&self, other: &Geometry) -> bool {
+        self.bounding_box.contains(&other.bounding_box)
+            && !other.for_each_vertex(|position| !self.covers(position))
+            && !other.for_each_segment(|b1, b2| {
+                !self.covers(&[(b1[0] + b2[0]) / 2.0, (b1[1] + b2[1]) / 2.0])
+                    || self.for_each_segment(|a1, a2| segments_cross(a1, a2, b1, b2))
+            })
+            && !self.polygons.iter().any(|polygon| {
+                polygon[1..]
+                    .iter()
+                    .flatten()
+                    .any(|position| other.covers_interior(position))
+            })
+    }
+
+    pub(crate) fn relates(&self, other: &Geometry, relation: &ShapeRelation) -> bool {
+        match relation {
+            ShapeRelation::Intersects => self.intersects(other),
+            ShapeRelation::Contains => self.contains(other),
+            ShapeRelation::Within => other.contains(self),
+        }
+    }
+}
+
+/// Packed R-tree, bulk loaded with the Sort-Tile-Recursive (STR) algorithm
+#[derive(Debug, Clone, Default)]
+struct RTree {
+    /// entry indices in leaf order
+    order: Vec<usize>,
+    /// levels of nodes from the leaves to the root: bounding box and range of children in the level below (or in order for the leaves)
+    levels: Vec<Vec<(BoundingBox, Range<usize>)>>,
+}
+
+impl RTree {
+    fn new(bounding_boxes: &[BoundingBox]) -> RTree {
+        let mut order: Vec<usize> = (0..bounding_boxes.len()).collect();
+        if order.is_empty() {
+            return RTree::default();
+        }
+
+        let by_center = |i: usize| {
+            move |a: &usize, b: &usize| {
+                bounding_boxes[*a]
+                    .center(i)
+                    .total_cmp(&bounding_boxes[*b].center(i))
+            }
+        };
+        order.sort_unstable_by(by_center(0));
+        let leaf_count = order.len().div_ceil(NODE_CAPACITY);
+        let slice_count = (leaf_count as f64).sqrt().ceil() as usize;
+        let slice_size = leaf_count.div_ceil(slice_count) * NODE_CAPACITY;
+        for slice in order.chunks_mut(slice_size) {
+            slice.sort_unstable_by(by_center(1));
+        }
+
+        let leaf_bounding_boxes: Vec<BoundingBox> =
+            order.iter().map(|i| bounding_boxes[*i]).collect();
+        let mut levels = vec![RTree::group(&leaf_bounding_boxes)];
+        while levels.last().unwrap().len() > 1 {
+            let node_bounding_boxes: Vec<BoundingBox> =
+                levels.last().unwrap().iter().map(|node| node.0).collect();
+            levels.push(RTree::group(&node_bounding_boxes));
+        }
+
+        RTree { order, levels }
+    }
+
+    /// Groups consecutive bounding boxes into nodes of NODE_CAPACITY children
+    fn group(bounding_boxes: &[BoundingBox]) -> Vec<(BoundingBox, Range<usize>)> {
+        bounding_boxes
+            .chunks(NODE_CAPACITY)
+            .enumerate()
+            .map(|(i, chunk)| {
+                (
+                    chunk.iter().fold(BoundingBox::EMPTY, |a, b| a.union(b)),
+                    i * NODE_CAPACITY..i * NODE_CAPACITY + chunk.len(),
+                )
+            })
+            .collect()
+    }
+
+    /// Entry indices whose bounding box intersects the bounding box
+    fn search(&self, bounding_box: &BoundingBox, result: &mut Vec<usize>) {
+        let Some(root_level) = self.levels.len().checked_sub(1) else {
+            return;
+        };
+        let mut stack: Vec<(usize, usize)> = (0..self.levels[root_level].len())
+            .map(|i| (root_level, i))
+            .collect();
+        while let Some((level, i)) = stack.pop() {
+            let (node_bounding_box, children) = &self.levels[level][i];
+            if !node_bounding_box.intersects(bounding_box) {
+                continue;
+            }
+            if level == 0 {
+                result.extend_from_slice(&self.order[children.clone()]);
+            } else {
+                stack.extend(children.clone().map(|child| (level - 1, child)));
+            }
+        }
+    }
+}
+
+/// Shapes of a GeoShape field, with a R-tree over the shapes of the committed documents.
+/// The shapes of uncommitted documents are searched sequentially, the R-tree is rebuilt at commit.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ShapeIndex {
+    shapes: Vec<(usize, GeoShape, Geometry)>,
+    tree: RTree,
+    /// number of shapes covered by the R-tree and persisted in geo_shapes.ndjson
+    committed: usize,
+}
+
+impl ShapeIndex {
+    /// Document ids with a shape of the relation to the filter shape
+    pub(crate) fn search(&self, filter: &GeoShapeFilter) -> AHashSet<usize> {
+        let geometry = filter.shape.geometry();
+        let mut candidates = Vec::new();
+        self.tree.search(&geometry.bounding_box, &mut candidates);
+        candidates.extend(self.committed..self.shapes.len());
+
+        candidates
+            .into_iter()
+            .filter_map(|i| {
+                let (doc_id, _, shape_geometry) = &self.shapes[i];
+                shape_geometry
+                    .relates(&geometry, &filter.relation)
+                    .then_some(*doc_id)
+            })
+            .collect()
+    }
+}
+
+/// Empty shape indices of the GeoShape fields of the schema
+pub(crate) fn new_geo_shapes(schema: &[SchemaField]) -> AHashMap<String, ShapeIndex> {
+    schema
+        .iter()
+        .filter(|schema_field| schema_field.field_type == FieldType::GeoShape)
+        .map(|schema_field| (schema_field.field.clone(), ShapeIndex::default()))
+        .collect()
+}
+
+impl ShapeIndex {
+    fn commit(&mut self) {
+        let bounding_boxes: Vec<BoundingBox> = self
+            .shapes
+            .iter()
+            .map(|shape| shape.2.bounding_box)
+            .collect();
+        self.tree = RTree::new(&bounding_boxes);
+        self.committed = self.shapes.len();
+    }
+}
+
+impl Index {
+    /// Loads the shapes of the committed documents from geo_shapes.ndjson and builds the R-trees
+    pub(crate) fn load_geo_shapes(&mut self) {
+        if self.geo_shapes.is_empty() {
+            return;
+        }
+
+        let geo_shapes_path = Path::new(&self.index_path_string).join(GEO_SHAPES_FILENAME);
+        let data = fs::read(&geo_shapes_path).unwrap_or_default();
+        for line in data.as_slice().lines().map_while(Result::ok) {
+            if let Ok(entry) = serde_json::from_str::<GeoShapeEntry>(&line) {
+                if let Some(shape_index) = self.geo_shapes.get_mut(&entry.field) {
+                    let geometry = entry.shape.geometry();
+                    shape_index
+                        .shapes
+                        .push((entry.doc_id, entry.shape, geometry));
+                }
+            }
+        }
+
+        for shape_index in self.geo_shapes.values_mut() {
+            shape_index.commit();
+        }
+    }
+
+    /// Removes all shapes, e.g. by clear_index
+    pub(crate) fn clear_geo_shapes(&mut self) {
+        let _ = fs::remove_file(Path::new(&self.index_path_string).join(GEO_SHAPES_FILENAME));
+        for shape_index in self.geo_shapes.values_mut() {
+            *shape_index = ShapeIndex::default();
+        }
+    }
+
+    /// Adds the valid shapes of the GeoShape fields of a document to the shape indices
+    pub(crate) fn index_geo_shapes(&mut self, document: &Document, doc_id: usize) {
+        for (field, shape_index) in self.geo_shapes.iter_mut() {
+            let Some(value) = document.get(field) else {
+                continue;
+            };
+            match serde_json::from_value::<GeoShape>(value.clone())
+                .map_err(|e| e.to_string())
+                .and_then(|shape| shape.validate().map(|_| shape))
+            {
+                Ok(shape) => {
+                    let geometry = shape.geometry();
+                    shape_index.shapes.push((doc_id, shape, geometry));
+                }
+                Err(e) => {
+                    if !value.is_null() {
+                        println!("invalid geo shape of field {}: {}", field, e)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Appends the shapes of the newly committed documents to geo_shapes.ndjson and rebuilds the R-trees
+    pub(crate) fn commit_geo_shapes(&mut self) -> Result<(), String> {
+        if self.geo_shapes.is_empty() {
+            return Ok(());
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Path::new(&self.index_path_string).join(GEO_SHAPES_FILENAME))
+            .map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        for (field, shape_index) in self.geo_shapes.iter_mut() {
+            for (doc_id, shape, _) in shape_index.shapes[shape_index.committed..].iter() {
+                serde_json::to_writer(
+                    &mut writer,
+                    &GeoShapeEntry {
+                        field: field.clone(),
+                        doc_id: *doc_id,
+                        shape: shape.clone(),
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+                writer.write_all(b"\n").map_err(|e| e.to_string())?;
+            }
+            shape_index.commit();
+        }
+        writer.flush().map_err(|e| e.to_string())
+    }
+}
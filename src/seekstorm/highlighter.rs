@@ -1,11 +1,12 @@
 use crate::index::{Document, FieldType, Index, IndexArc};
 use crate::min_heap::{self, MinHeap};
+use ahash::AHashMap;
 use aho_corasick::{AhoCorasick, MatchKind};
 use serde::{Deserialize, Serialize};
 
 /// Specifies the number and size of fragments (snippets, summaries) to generate from each specified field to provide a "keyword in context" (KWIC) functionality.
 /// With highlight_markup the matching query terms within the fragments can be highlighted with HTML markup.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Highlight {
     /// Specifies the field from which the fragments  (snippets, summaries) are created.
     pub field: String,
@@ -17,13 +18,64 @@ pub struct Highlight {
     /// If 0/default then return the full original text without fragmenting.
     #[serde(default)]
     pub fragment_number: usize,
-    /// Specifies the length of a highlight fragment.
+    /// Specifies the length of a highlight fragment in characters.
     /// The default 0 returns the full original text without truncating, but still with highlighting if highlight_markup is enabled.
     #[serde(default)]
     pub fragment_size: usize,
-    /// if true, the matching query terms within the fragments are highlighted with HTML markup **\<b\>term\<\/b\>**.
+    /// if true, the matching query terms within the fragments are highlighted with HTML markup, by default **\<b\>term\<\/b\>**.
     #[serde(default)]
     pub highlight_markup: bool,
+    /// Markup inserted before each highlighted query term, e.g. `<em class="hl">`. Default: `<b>`.
+    #[serde(default = "default_pre_tag")]
+    pub pre_tag: String,
+    /// Markup inserted after each highlighted query term, e.g. `</em>`. Default: `</b>`.
+    #[serde(default = "default_post_tag")]
+    pub post_tag: String,
+    /// Order of the returned fragments: Position (default) in the order they appear in the field, or Score with the most relevant fragment first.
+    #[serde(default)]
+    pub fragment_order: FragmentOrder,
+    /// if true, the positions of the matching query terms within the returned highlight field are additionally returned as structured spans
+    /// in the `_highlights` property of the document, e.g. for native apps or PDF viewers that render their own highlighting.
+    /// The offsets refer to the returned field text, including the highlight markup if highlight_markup is enabled.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default_bool")]
+    pub offsets: bool,
+    /// Fragments returned if none of the query terms appear in the field, so that result snippets are never empty:
+    /// First (default) sentence, None, Leading fragment_number sentences, or an extractive Summary of fragment_number sentences.
+    #[serde(default)]
+    pub fallback: FallbackFragment,
+    /// If > 0, for queries with multiple terms only those query term matches are highlighted,
+    /// which are within a distance of proximity words to a match of another query term.
+    /// Independent from proximity, for phrase queries only the terms participating in a complete phrase match are highlighted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default_usize")]
+    pub proximity: usize,
+}
+
+fn is_default_usize(value: &usize) -> bool {
+    *value == 0
+}
+
+fn is_default_bool(value: &bool) -> bool {
+    !*value
+}
+
+fn default_pre_tag() -> String {
+    "<b>".to_string()
+}
+
+fn default_post_tag() -> String {
+    "</b>".to_string()
+}
+
+/// Order of the fragments (snippets, summaries) returned from a field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum FragmentOrder {
+    /// Fragments are returned in the order they appear in the field
+    #[default]
+    Position,
+    /// Fragments are returned in descending order of their fragment ranking score
+    Score,
 }
 
 impl Default for Highlight {
@@ -34,15 +86,92 @@ impl Default for Highlight {
             fragment_number: 1,
             fragment_size: usize::MAX,
             highlight_markup: true,
+            pre_tag: default_pre_tag(),
+            post_tag: default_post_tag(),
+            fragment_order: FragmentOrder::Position,
+            offsets: false,
+            fallback: FallbackFragment::First,
+            proximity: 0,
         }
     }
 }
 
+/// Query-independent fragments returned if none of the query terms appear in the highlighted field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum FallbackFragment {
+    /// The first sentence of the field
+    #[default]
+    First,
+    /// No fragment, an empty string is returned
+    None,
+    /// The leading fragment_number sentences of the field
+    Leading,
+    /// Extractive summary: the fragment_number sentences containing the most frequent words of the field, in the order they appear in the field
+    Summary,
+}
+
+/// Position of a matching query term within a returned highlight field, returned in the `_highlights` property of the document if Highlight.offsets is true.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HighlightSpan {
+    /// Name of the returned highlight field: Highlight.name, or Highlight.field if name is empty.
+    pub field: String,
+    /// Matched query term, as it appears in the field text.
+    pub term: String,
+    /// Byte offset of the match start within the returned field text.
+    pub start: usize,
+    /// Byte offset of the match end (exclusive) within the returned field text.
+    pub end: usize,
+    /// Character offset of the match start within the returned field text.
+    pub char_start: usize,
+    /// Character offset of the match end (exclusive) within the returned field text.
+    pub char_end: usize,
+}
+
+/// Where get_document returns the highlights of a Highlighter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum HighlightOutput {
+    /// Each highlight is returned as field of the document: Highlight.name, or Highlight.field if name is empty, i.e. the original field is overwritten with the highlight.
+    #[default]
+    Field,
+    /// The highlights are returned in the `_highlight` object of the document, keyed by Highlight.name, or Highlight.field if name is empty.
+    /// The stored fields of the document are left intact, e.g. to render the full title and the body fragments side by side.
+    Keyed,
+}
+
 /// Highlighter object used as get_document parameter for extracting keyword-in-context (KWIC) fragments from fields in documents, and highlighting the query terms within.
 #[derive(Debug)]
 pub struct Highlighter {
     pub(crate) highlights: Vec<Highlight>,
     pub(crate) query_terms_ac: AhoCorasick,
+    pub(crate) query_terms: Vec<String>,
+    pub(crate) query_phrases: Vec<Vec<String>>,
+    pub(crate) output: HighlightOutput,
+}
+
+impl Highlighter {
+    /// Sets where get_document returns the highlights, see HighlightOutput. Default: HighlightOutput::Field.
+    /// Each Highlight of the highlighter has its own settings, e.g. fragments of the body and the full title:
+    /// ```no_run
+    /// use seekstorm::{
+    ///     highlighter::{highlighter, Highlight, HighlightOutput, Highlighter},
+    ///     index::IndexArc,
+    ///     search::ResultObject,
+    /// };
+    ///
+    /// async fn keyed_highlighter(index_arc: &IndexArc, result_object: ResultObject) -> Highlighter {
+    ///     let highlights = vec![
+    ///         Highlight { field: "title".into(), fragment_number: 0, ..Default::default() },
+    ///         Highlight { field: "body".into(), fragment_number: 2, fragment_size: 160, ..Default::default() },
+    ///     ];
+    ///     highlighter(index_arc, highlights, result_object.query_terms)
+    ///         .await
+    ///         .with_output(HighlightOutput::Keyed)
+    /// }
+    /// ```
+    pub fn with_output(mut self, output: HighlightOutput) -> Highlighter {
+        self.output = output;
+        self
+    }
 }
 
 /// Returns the Highlighter object used as get_document parameter for highlighting fields in documents
@@ -50,6 +179,17 @@ pub async fn highlighter(
     index_arc: &IndexArc,
     highlights: Vec<Highlight>,
     query_terms_vec: Vec<String>,
+) -> Highlighter {
+    highlighter_with_phrases(index_arc, highlights, query_terms_vec, Vec::new()).await
+}
+
+/// Returns the Highlighter object used as get_document parameter for highlighting fields in documents.
+/// For the query_phrases (ResultObject.query_phrases) only the terms participating in a complete phrase match are highlighted.
+pub async fn highlighter_with_phrases(
+    index_arc: &IndexArc,
+    highlights: Vec<Highlight>,
+    query_terms_vec: Vec<String>,
+    query_phrases: Vec<Vec<String>>,
 ) -> Highlighter {
     let index_ref = index_arc.read().await;
     let query_terms = if !index_ref.synonyms_map.is_empty() {
@@ -72,12 +212,20 @@ pub async fn highlighter(
     let query_terms_ac = AhoCorasick::builder()
         .ascii_case_insensitive(true)
         .match_kind(MatchKind::LeftmostLongest)
-        .build(query_terms)
+        .build(&query_terms)
         .unwrap();
 
+    let query_phrases = query_phrases
+        .into_iter()
+        .map(|phrase| phrase.iter().map(|term| term.to_lowercase()).collect())
+        .collect();
+
     Highlighter {
         highlights,
         query_terms_ac,
+        query_terms,
+        query_phrases,
+        output: HighlightOutput::Field,
     }
 }
 
@@ -125,12 +273,14 @@ pub(crate) fn add_fragment<'a>(
         }
     }
 
-    if first_end > fragment_size {
-        let mut idx = fragment.text.len() - fragment_size;
+    let char_count = if fragment.text.len() > fragment_size {
+        fragment.text.chars().count()
+    } else {
+        0
+    };
 
-        while !fragment.text.is_char_boundary(idx) {
-            idx -= 1;
-        }
+    if char_count > fragment_size && fragment.text[..first_end].chars().count() > fragment_size {
+        let mut idx = char_to_byte_index(fragment.text, char_count - fragment_size);
 
         match fragment.text[idx..].find(' ') {
             None => idx = 0,
@@ -140,20 +290,8 @@ pub(crate) fn add_fragment<'a>(
         let adjusted_fragment = &fragment.text[idx..];
         fragment.text = adjusted_fragment;
         fragment.trim_left = true;
-    } else if fragment.text.len() > fragment_size {
-        let mut idx = fragment_size;
-
-        while !fragment.text.is_char_boundary(idx) {
-            idx -= 1;
-        }
-
-        match fragment.text[idx..].find(' ') {
-            None => idx = fragment.text.len(),
-            Some(value) => idx += value,
-        }
-
-        let adjusted_fragment = &fragment.text[..idx];
-        fragment.text = adjusted_fragment;
+    } else if char_count > fragment_size {
+        fragment.text = truncate_right(fragment.text, fragment_size);
         fragment.trim_right = true;
     }
 
@@ -174,6 +312,25 @@ pub(crate) fn add_fragment<'a>(
     }
 }
 
+/// Truncates the text after fragment_size characters, at the next word boundary
+fn truncate_right(text: &str, fragment_size: usize) -> &str {
+    let mut idx = char_to_byte_index(text, fragment_size);
+
+    match text[idx..].find(' ') {
+        None => idx = text.len(),
+        Some(value) => idx += value,
+    }
+
+    &text[..idx]
+}
+
+/// Returns the byte index of the character at char_index, or the text length if the text is shorter
+fn char_to_byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(byte_index, _)| byte_index)
+}
+
 const SENTENCE_BOUNDARY_CHARS: [char; 11] =
     ['!', '?', '.', '¿', '¡', '。', '、', '！', '？', '︒', '。'];
 
@@ -186,18 +343,20 @@ pub(crate) struct Fragment<'a> {
 /// I.e. the sentences containing the matches of the query terms within the field is displayed and the query term matches are optionally highlighted (e.g. bold) by injecting HTML tags in to the text.
 /// Instead of showing the complete text only the relevant fragments containing keyword matches are extracted. The user is provided with concise visual feedback for relevancy of the document regarding to the query.
 /// The fragment ranking score takes into account the number of matching terms, their order and proximity (phrase).
-/// The score is used for the selection of top-k most relevant fragments, but the order of selected fragments is preserved how they originally appear in the field.
+/// The score is used for the selection of top-k most relevant fragments, by default the order of selected fragments is preserved how they originally appear in the field (FragmentOrder::Position),
+/// with FragmentOrder::Score the most relevant fragment comes first.
 /// The field is fragmented into sentences, using punctuation marks '.?!' as sentence boundaries.
 /// If the fragment length exceeds the specified fragment_size, then the fragment is truncated at the right or left side, so that the query term higlight positions are kept within the remaining fragment window.
 /// Selecting the right fragment and the right fragment window is fundamental for the users perceived relevancy of the search results.
 pub(crate) fn top_fragments_from_field(
     index: &Index,
     document: &Document,
-    query_terms_ac: &AhoCorasick,
+    highlighter: &Highlighter,
     highlight: &Highlight,
-) -> Result<String, String> {
+) -> Result<(String, Vec<HighlightSpan>), String> {
+    let query_terms_ac = &highlighter.query_terms_ac;
     match document.get(&highlight.field) {
-        None => Ok("".to_string()),
+        None => Ok(("".to_string(), Vec::new())),
         Some(value) => {
             let no_score_no_highlight =
                 query_terms_ac.patterns_len() == 1 && query_terms_ac.max_pattern_len() == 1;
@@ -207,6 +366,11 @@ pub(crate) fn top_fragments_from_field(
             } else {
                 highlight.fragment_number
             };
+            let fragment_size = if highlight.fragment_size == 0 {
+                usize::MAX
+            } else {
+                highlight.fragment_size
+            };
             let result_sort = Vec::new();
             let mut topk_candidates = MinHeap::new(fragment_number, index, &result_sort);
 
@@ -239,7 +403,7 @@ pub(crate) fn top_fragments_from_field(
                                 &mut fragments,
                                 &mut topk_candidates,
                                 fragment_number,
-                                highlight.fragment_size,
+                                fragment_size,
                             );
 
                             if no_score_no_highlight
@@ -266,13 +430,24 @@ pub(crate) fn top_fragments_from_field(
                         &mut fragments,
                         &mut topk_candidates,
                         fragment_number,
-                        highlight.fragment_size,
+                        fragment_size,
                     );
                 }
 
                 let mut combined_string = String::with_capacity(text.len());
 
-                if !fragments.is_empty() {
+                if topk_candidates.current_heap_size == 0
+                    && !no_fragmentation
+                    && highlight.fallback != FallbackFragment::First
+                {
+                    fallback_fragments(
+                        &text,
+                        highlight.fallback,
+                        fragment_number,
+                        fragment_size,
+                        &mut combined_string,
+                    );
+                } else if !fragments.is_empty() {
                     if topk_candidates.current_heap_size > 0 {
                         if topk_candidates.current_heap_size < fragment_number {
                             topk_candidates
@@ -280,9 +455,18 @@ pub(crate) fn top_fragments_from_field(
                                 .truncate(topk_candidates.current_heap_size);
                         }
 
-                        topk_candidates
-                            ._elements
-                            .sort_by(|a, b| a.doc_id.partial_cmp(&b.doc_id).unwrap());
+                        if highlight.fragment_order == FragmentOrder::Score {
+                            topk_candidates._elements.sort_by(|a, b| {
+                                b.score
+                                    .partial_cmp(&a.score)
+                                    .unwrap()
+                                    .then(a.doc_id.cmp(&b.doc_id))
+                            });
+                        } else {
+                            topk_candidates
+                                ._elements
+                                .sort_by(|a, b| a.doc_id.partial_cmp(&b.doc_id).unwrap());
+                        }
 
                         let mut previous_docid = 0;
                         for candidate in topk_candidates._elements {
@@ -307,33 +491,317 @@ pub(crate) fn top_fragments_from_field(
                     }
                 }
 
+                let matches = if (highlight.offsets || highlight.highlight_markup)
+                    && !no_score_no_highlight
+                {
+                    highlight_matches(&combined_string, highlighter, highlight.proximity)
+                } else {
+                    Vec::new()
+                };
+
+                let mut spans = Vec::new();
+                if highlight.offsets && !no_score_no_highlight {
+                    let (pre_tag, post_tag) = if highlight.highlight_markup {
+                        (highlight.pre_tag.as_str(), highlight.post_tag.as_str())
+                    } else {
+                        ("", "")
+                    };
+                    spans = highlight_spans(
+                        &combined_string,
+                        &matches,
+                        if highlight.name.is_empty() {
+                            &highlight.field
+                        } else {
+                            &highlight.name
+                        },
+                        pre_tag,
+                        post_tag,
+                    );
+                }
+
                 if highlight.highlight_markup && !no_score_no_highlight {
-                    highlight_terms(&mut combined_string, query_terms_ac);
+                    highlight_terms(
+                        &mut combined_string,
+                        &matches,
+                        &highlight.pre_tag,
+                        &highlight.post_tag,
+                    );
                 }
 
-                Ok(combined_string)
+                Ok((combined_string, spans))
             } else {
-                Ok("".to_string())
+                Ok(("".to_string(), Vec::new()))
             }
         }
     }
 }
 
-pub(crate) fn highlight_terms(text: &mut String, query_terms_ac: &AhoCorasick) {
-    let mut result = String::new();
+/// Composes the query-independent fallback fragments, if none of the query terms appear in the field.
+/// The field is fragmented into sentences, like for the query-dependent fragments.
+/// For the extractive summary, each sentence is scored by the average frequency of its words (longer than 2 characters) within the field,
+/// and the fragment_number top scoring sentences are returned in the order they appear in the field.
+fn fallback_fragments(
+    text: &str,
+    fallback: FallbackFragment,
+    fragment_number: usize,
+    fragment_size: usize,
+    combined_string: &mut String,
+) {
+    let mut sentences: Vec<&str> = Vec::new();
+    let mut last = 0;
+    for (character_index, matched) in text.match_indices(&SENTENCE_BOUNDARY_CHARS[..]) {
+        if last != character_index {
+            sentences.push(&text[last..character_index + matched.len()]);
+        }
+        last = character_index + matched.len();
+    }
+    if last < text.len() {
+        sentences.push(&text[last..]);
+    }
+
+    let selected: Vec<usize> = match fallback {
+        FallbackFragment::First | FallbackFragment::None => Vec::new(),
+        FallbackFragment::Leading => (0..fragment_number.min(sentences.len())).collect(),
+        FallbackFragment::Summary => {
+            let sentence_words: Vec<Vec<String>> = sentences
+                .iter()
+                .map(|sentence| {
+                    sentence
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| word.chars().count() > 2)
+                        .map(|word| word.to_lowercase())
+                        .collect()
+                })
+                .collect();
+
+            let mut word_frequencies: AHashMap<&str, usize> = AHashMap::new();
+            for word in sentence_words.iter().flatten() {
+                *word_frequencies.entry(word).or_insert(0) += 1;
+            }
+
+            let mut sentence_scores: Vec<(usize, f32)> = sentence_words
+                .iter()
+                .enumerate()
+                .map(|(index, words)| {
+                    let score = if words.is_empty() {
+                        0.0
+                    } else {
+                        words
+                            .iter()
+                            .map(|word| word_frequencies[word.as_str()])
+                            .sum::<usize>() as f32
+                            / words.len() as f32
+                    };
+                    (index, score)
+                })
+                .collect();
+
+            sentence_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+            let mut selected: Vec<usize> = sentence_scores
+                .iter()
+                .take(fragment_number)
+                .map(|(index, _)| *index)
+                .collect();
+            selected.sort_unstable();
+            selected
+        }
+    };
+
+    let mut previous_index = 0;
+    for index in selected {
+        if !combined_string.is_empty()
+            && !combined_string.ends_with("...")
+            && index != previous_index + 1
+        {
+            combined_string.push_str("...");
+        }
+
+        let sentence = sentences[index];
+        if sentence.chars().count() > fragment_size {
+            combined_string.push_str(truncate_right(sentence, fragment_size));
+            combined_string.push_str("...");
+        } else {
+            combined_string.push_str(sentence);
+        }
+        previous_index = index;
+    }
+}
+
+/// Returns the byte ranges of the query term matches to highlight within the text.
+/// For phrase queries only the terms participating in a complete phrase match (adjacent and in order) are returned.
+/// With proximity > 0 only the matches within a distance of proximity words to a match of another query term are returned.
+pub(crate) fn highlight_matches(
+    text: &str,
+    highlighter: &Highlighter,
+    proximity: usize,
+) -> Vec<(usize, usize)> {
+    // start, end, pattern, word position
+    let mut matches: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let mut word_count = 0;
+    let mut previous_alphanumeric = false;
     let mut prev_end = 0;
+    for mat in highlighter.query_terms_ac.find_iter(text) {
+        for char in text[prev_end..mat.start()].chars() {
+            let alphanumeric = char.is_alphanumeric();
+            if alphanumeric && !previous_alphanumeric {
+                word_count += 1;
+            }
+            previous_alphanumeric = alphanumeric;
+        }
 
-    for mat in query_terms_ac.find_iter(&text) {
-        result.push_str(&text[prev_end..mat.start()]);
-        result.push_str("<b>");
-        result.push_str(&text[mat.start()..mat.end()]);
-        result.push_str("</b>");
+        let word_position: usize = if previous_alphanumeric {
+            word_count - 1
+        } else {
+            word_count
+        };
+
+        for char in text[mat.start()..mat.end()].chars() {
+            let alphanumeric = char.is_alphanumeric();
+            if alphanumeric && !previous_alphanumeric {
+                word_count += 1;
+            }
+            previous_alphanumeric = alphanumeric;
+        }
+
+        matches.push((
+            mat.start(),
+            mat.end(),
+            mat.pattern().as_usize(),
+            word_position,
+        ));
         prev_end = mat.end();
     }
 
+    let pattern_words = |pattern: usize| -> Vec<&str> {
+        highlighter.query_terms[pattern]
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+    };
+
+    let mut in_phrase = vec![false; matches.len()];
+    for phrase in highlighter.query_phrases.iter() {
+        for i in 0..matches.len() {
+            let mut phrase_position = 0;
+            let mut j = i;
+            while phrase_position < phrase.len() && j < matches.len() {
+                let words = pattern_words(matches[j].2);
+                if matches[j].3 != matches[i].3 + phrase_position
+                    || words.is_empty()
+                    || phrase_position + words.len() > phrase.len()
+                    || words
+                        .iter()
+                        .zip(phrase[phrase_position..].iter())
+                        .any(|(word, phrase_word)| !word.eq_ignore_ascii_case(phrase_word))
+                {
+                    break;
+                }
+                phrase_position += words.len();
+                j += 1;
+            }
+            if phrase_position == phrase.len() {
+                in_phrase[i..j].iter_mut().for_each(|flag| *flag = true);
+            }
+        }
+    }
+
+    let is_phrase_term = |pattern: usize| -> bool {
+        let words = pattern_words(pattern);
+        !words.is_empty()
+            && words.iter().all(|word| {
+                highlighter.query_phrases.iter().any(|phrase| {
+                    phrase
+                        .iter()
+                        .any(|phrase_word| word.eq_ignore_ascii_case(phrase_word))
+                })
+            })
+    };
+
+    let multiple_terms = highlighter.query_terms.len() > 1;
+
+    matches
+        .iter()
+        .enumerate()
+        .filter(|(i, mat)| {
+            if is_phrase_term(mat.2) {
+                in_phrase[*i]
+            } else if proximity > 0 && multiple_terms {
+                matches.iter().any(|other| {
+                    other.2 != mat.2
+                        && !highlighter.query_terms[other.2]
+                            .eq_ignore_ascii_case(&highlighter.query_terms[mat.2])
+                        && other.3.abs_diff(mat.3) <= proximity
+                })
+            } else {
+                true
+            }
+        })
+        .map(|(_, mat)| (mat.0, mat.1))
+        .collect()
+}
+
+pub(crate) fn highlight_terms(
+    text: &mut String,
+    matches: &[(usize, usize)],
+    pre_tag: &str,
+    post_tag: &str,
+) {
+    let mut result = String::new();
+    let mut prev_end = 0;
+
+    for (start, end) in matches.iter() {
+        result.push_str(&text[prev_end..*start]);
+        result.push_str(pre_tag);
+        result.push_str(&text[*start..*end]);
+        result.push_str(post_tag);
+        prev_end = *end;
+    }
+
     if prev_end < text.len() {
         result.push_str(&text[prev_end..text.len()]);
     }
 
     *text = result;
 }
+
+/// Returns the spans of the matching query terms, with the offsets shifted by the highlight markup that highlight_terms will insert before and after each match.
+pub(crate) fn highlight_spans(
+    text: &str,
+    matches: &[(usize, usize)],
+    field: &str,
+    pre_tag: &str,
+    post_tag: &str,
+) -> Vec<HighlightSpan> {
+    let pre_tag_chars = pre_tag.chars().count();
+    let post_tag_chars = post_tag.chars().count();
+
+    let mut spans = Vec::new();
+    let mut byte_shift = 0;
+    let mut char_shift = 0;
+    let mut char_index = 0;
+    let mut prev_end = 0;
+
+    for (start, end) in matches.iter() {
+        let term = &text[*start..*end];
+        char_index += text[prev_end..*start].chars().count();
+        let term_chars = term.chars().count();
+
+        byte_shift += pre_tag.len();
+        char_shift += pre_tag_chars;
+
+        spans.push(HighlightSpan {
+            field: field.to_string(),
+            term: term.to_string(),
+            start: start + byte_shift,
+            end: end + byte_shift,
+            char_start: char_index + char_shift,
+            char_end: char_index + term_chars + char_shift,
+        });
+
+        byte_shift += post_tag.len();
+        char_shift += post_tag_chars;
+        char_index += term_chars;
+        prev_end = *end;
+    }
+
+    spans
+}
@@ -9,7 +9,9 @@ use num_derive::FromPrimitive;
 
 use num_format::{Locale, ToFormattedString};
 
-use search::{decode_posting_list_object, QueryType, Search};
+use search::{
+    decode_posting_list_object, QueryExpansion, QueryType, Search, SearchFrom, SearchRequest,
+};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use std::{
@@ -19,23 +21,52 @@ use std::{
     path::Path,
     sync::Arc,
     thread::available_parallelism,
-    time::Instant,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
-use tokio::sync::{RwLock, Semaphore};
+use tokio::sync::{oneshot, RwLock, Semaphore};
 use utils::{read_u32, write_u16};
 
 #[cfg(feature = "zh")]
 use crate::word_segmentation::WordSegmentationTM;
 use crate::{
     add_result::{self, B, DOCUMENT_LENGTH_COMPRESSION, K, SIGMA},
-    commit::KEY_HEAD_SIZE,
-    geo_search::encode_morton_2_d,
-    search::{self, FacetFilter, Point, QueryFacet, Ranges, ResultObject, ResultSort, ResultType},
-    tokenizer::tokenizer,
+    alert::{load_alerts, Alert, ALERTS_FILENAME},
+    commit::{finish_commit, KEY_HEAD_SIZE},
+    commit_hook::CommitHook,
+    compact::{recover_compaction, spawn_compaction_if_due},
+    computed::ComputedExpression,
+    date::date_from_json,
+    error::SeekStormError,
+    experiment::{
+        load_ranking_variants, RankingMetrics, RankingVariant, RANKING_VARIANTS_FILENAME,
+    },
+    feedback::{load_query_feedback, QueryFeedback, QUERY_FEEDBACK_FILENAME},
+    geo_search::{encode_morton_2_d, points_from_json},
+    geo_shape::{new_geo_shapes, ShapeIndex, GEO_SHAPES_FILENAME},
+    ip::parse_ip,
+    join::{new_joins, JoinIndex, JOINS_FILENAME},
+    level::{load_level_history, LevelHistory, LEVELS_FILENAME},
+    ltr::{load_ltr_models, LtrModel, LTR_MODELS_FILENAME},
+    percolator::{load_percolator_queries, PercolatorQuery, PERCOLATOR_QUERIES_FILENAME},
+    query_rewrite::{load_query_rules, QueryRewriter, QueryRule, QUERY_RULES_FILENAME},
+    runtime::{load_runtime_fields, RuntimeField, RUNTIME_FIELDS_FILENAME},
+    search::{
+        self, FacetFilter, FacetSort, Point, QueryFacet, Ranges, ResultObject, ResultSort,
+        ResultType,
+    },
+    terms::TermVocabulary,
+    tokenizer::{
+        add_unstemmed_terms, detect_language, prefixed_term, tokenizer, Tokenizer,
+        UNEXPANDED_TERM_PREFIX,
+    },
+    usage::{load_usage_counters, UsageCountersAtomic, USAGE_COUNTERS_FILENAME},
     utils::{
-        self, read_u16, read_u16_ref, read_u32_ref, read_u64, read_u64_ref, read_u8_ref, write_f32,
-        write_f64, write_i16, write_i32, write_i64, write_i8, write_u32, write_u64,
+        self, decimal_from_json, decimal_to_f64, read_u16, read_u16_ref, read_u32_ref, read_u64,
+        read_u64_ref, read_u8_ref, write_f32, write_f64, write_i16, write_i32, write_i64, write_i8,
+        write_u128, write_u32, write_u64,
     },
+    verify::IndexChecksums,
+    word_lists::{load_word_lists, WordLists},
 };
 
 pub(crate) const FILE_PATH: &str = "files";
@@ -44,6 +75,9 @@ pub(crate) const DOCSTORE_FILENAME: &str = "docstore.bin";
 pub(crate) const DELETE_FILENAME: &str = "delete.bin";
 pub(crate) const SCHEMA_FILENAME: &str = "schema.json";
 pub(crate) const SYNONYMS_FILENAME: &str = "synonyms.json";
+pub(crate) const FACET_ALIASES_FILENAME: &str = "facet_aliases.json";
+pub(crate) const FROZEN_FILENAME: &str = "frozen";
+pub(crate) const CHECKSUMS_FILENAME: &str = "checksums.json";
 pub(crate) const META_FILENAME: &str = "index.json";
 pub(crate) const FACET_FILENAME: &str = "facet.bin";
 pub(crate) const FACET_VALUES_FILENAME: &str = "facet.json";
@@ -54,7 +88,7 @@ const INDEX_HEADER_SIZE: u64 = 4;
 /// Incompatible index  format change: new library can't open old format, and old library can't open new format
 pub const INDEX_FORMAT_VERSION_MAJOR: u16 = 3;
 /// Backward compatible format change: new library can open old format, but old library can't open new format
-pub const INDEX_FORMAT_VERSION_MINOR: u16 = 2;
+pub const INDEX_FORMAT_VERSION_MINOR: u16 = 3;
 
 /// Maximum processed positions per term per document: default=65_536. E.g. 65,536 * 'the' per document, exceeding positions are ignored for search.
 pub const MAX_POSITIONS_PER_TERM: usize = 65_536;
@@ -85,6 +119,22 @@ pub enum AccessType {
     Mmap = 1,
 }
 
+/// Durability policy: when the index files are flushed from the operating system page cache to stable storage (fsync).
+/// Picks a point on the latency/durability spectrum. Uncommitted documents are always lost on a crash, independent of the durability.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Durability {
+    /// The operating system decides when to write the index files to disk (fastest).
+    /// Committed documents and deletions survive a crash of the process, but not a crash of the operating system or a power loss.
+    #[default]
+    Os = 0,
+    /// Every commit and every deletion is flushed to disk (fsync) before it returns.
+    /// Committed documents and deletions survive a crash of the operating system or a power loss, at the cost of commit and delete latency.
+    Commit = 1,
+    /// Commits and deletions are flushed to disk (fsync) at most every IndexMetaObject.sync_interval_ms, see Index::sync_if_due.
+    /// On a crash of the operating system or a power loss, at most the commits and deletions of the last interval are lost.
+    Periodic = 2,
+}
+
 /// Similarity type defines the scoring and ranking of the search results: Bm25f or Bm25fProximity (considers term proximity, e.g. for implicit phrase search with improved relevancy)
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
 pub enum SimilarityType {
@@ -110,6 +160,56 @@ pub enum TokenizerType {
     UnicodeAlphanumericFolded = 2,
     #[cfg(feature = "zh")]
     UnicodeAlphanumericZH = 3,
+    /// Custom tokenizer of the embedding application (see tokenizer::Tokenizer), registered with Index::set_tokenizer after create_index,
+    /// and passed to open_index_with_tokenizer: open_index refuses indices with a custom tokenizer.
+    /// Until a tokenizer is registered, no terms are indexed or searched.
+    Custom = 4,
+}
+
+/// Languages supported for stemming (Snowball stemmers) and language detection.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Copy)]
+pub enum Language {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+/// Defines stemming behavior: None (default), a fixed language for all documents, or language detection per document.
+/// Stemming is applied both at index time and at query time, so that e.g. "running" finds "runs".
+/// Terms of the stopword list are never stemmed, bigrams of stopwords are only created for English or unstemmed text.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub enum StemmerType {
+    /// No stemming.
+    #[default]
+    None,
+    /// All documents and queries are stemmed with the stemmer of the specified language.
+    Language(Language),
+    /// The language of each document is detected at index time (from all indexed text fields), and the document is stemmed with the matching stemmer.
+    /// The language of a query is detected too, but short queries are often ambiguous: use the query_language parameter of search to specify it explicitly.
+    Detect {
+        /// Language used when detection is not reliable. If None, such documents and queries are not stemmed.
+        #[serde(default)]
+        default_language: Option<Language>,
+        /// Optional name of a String schema field, where the language used for stemming is stored (e.g. "English") for retrieval and faceting.
+        /// If the document already contains a valid language in this field, detection is skipped and that language is used.
+        #[serde(default)]
+        language_field: String,
+    },
 }
 
 pub(crate) struct LevelIndex {
@@ -118,6 +218,9 @@ pub(crate) struct LevelIndex {
     pub docstore_pointer_docs: Vec<u8>,
     pub docstore_pointer_docs_pointer: usize,
     pub document_length_compressed_array_pointer: usize,
+
+    pub index_file_start_pos: u64,
+    pub docstore_file_start_pos: u64,
 }
 
 /// Posting lists are divided into blocks of a doc id range of 65.536 (16 bit).
@@ -349,9 +452,25 @@ pub enum FieldType {
     Timestamp,
     F32,
     F64,
+    /// Fixed-precision decimal, e.g. for prices: stored as i64 scaled by 10^SchemaField.scale (default 2 decimal places),
+    /// to avoid floating point artifacts in range filtering, sorting and min/max statistics.
+    /// Values can be JSON numbers or strings (e.g. "19.99"), digits beyond the scale are rounded half away from zero.
+    Decimal,
+    /// Date and time, normalized to UTC and stored as i64 Unix milliseconds. For date range filters with date math (e.g. `now-7d`), date range facets and date histogram facets.
+    /// Values can be RFC 3339 strings (e.g. "2024-05-17T10:30:00+02:00"), epoch milliseconds (numbers or strings),
+    /// strings matching one of the chrono format patterns of SchemaField.date_formats, "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S" or "%Y-%m-%d".
+    /// Dates without time zone are interpreted as UTC.
+    Date,
+    /// IPv4 or IPv6 address, e.g. for log and security data. Stored as u128, IPv4 addresses mapped into the IPv6 address space (::ffff:a.b.c.d).
+    /// For exact and CIDR subnet filters (e.g. "10.0.0.0/8"), range facets and sorting.
+    Ip,
     Bool,
     #[default]
     String,
+    /// Untokenized string for exact filtering (FacetFilter::String), term facets (QueryFacet::String) and sorting, e.g. for IDs, SKUs and tags.
+    /// The whole value is a single term, optionally normalized by the normalizers of SchemaField.normalizers.
+    /// Keyword fields are always facet fields, and are not part of the full-text index (indexed=false).
+    Keyword,
     StringSet,
     /// Point is a geographic field type: A `Vec<f64>` with two coordinate values (latitude and longitude) are internally encoded into a single u64 value (Morton code).
     /// Morton codes enable efficient range queries.
@@ -361,6 +480,49 @@ pub enum FieldType {
     /// The conversion between longitude/latitude coordinates and Morton code is lossy due to rounding errors.
     Point,
     Text,
+    /// Geographic shape in GeoJSON format: Point, MultiPoint, LineString, MultiLineString, Polygon or MultiPolygon (see geo_shape::GeoShape), e.g. for territories and coverage areas.
+    /// Shapes are indexed in a R-tree, and searched with FacetFilter::GeoShape for documents whose shape intersects, contains or lies within a given shape.
+    /// GeoShape fields can't be indexed (full-text) or facet fields.
+    GeoShape,
+    /// Parent-child relation between documents of the index, e.g. `{"name":"question","key":"q1"}` and `{"name":"answer","parent":"q1"}` (see join::JoinValue),
+    /// so that one-to-many data doesn't have to be denormalized into every child document.
+    /// Searched with FacetFilter::HasChild and FacetFilter::HasParent. Join fields can't be indexed (full-text) or facet fields.
+    Join,
+}
+
+/// Normalizer of a Keyword field, applied in the defined order to indexed values, filter values and facet prefixes.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Normalizer {
+    /// Converts the value to lowercase, for case-insensitive matching
+    Lowercase,
+    /// Removes leading and trailing whitespace
+    Trim,
+}
+
+/// Applies the normalizers of a Keyword field to a value
+pub(crate) fn normalize_keyword(value: &str, normalizers: &[Normalizer]) -> String {
+    let mut value = value.to_string();
+    for normalizer in normalizers.iter() {
+        value = match normalizer {
+            Normalizer::Lowercase => value.to_lowercase(),
+            Normalizer::Trim => value.trim().to_string(),
+        };
+    }
+    value
+}
+
+/// Returns the values of a StringSet field: a JSON array of strings, numbers or booleans, or a single string.
+/// Other values (e.g. nested arrays or objects) are ignored.
+pub(crate) fn string_set_from_json(value: &serde_json::Value) -> Vec<String> {
+    let string_from_json = |value: &serde_json::Value| match value {
+        serde_json::Value::String(string) => Some(string.clone()),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    };
+    match value {
+        serde_json::Value::Array(values) => values.iter().filter_map(string_from_json).collect(),
+        _ => string_from_json(value).into_iter().collect(),
+    }
 }
 
 /// Defines synonyms for terms per index.
@@ -385,6 +547,21 @@ fn default_as_true() -> bool {
     true
 }
 
+/// Defines a display label for raw values of a String or StringSet facet field per index.
+/// All raw values are merged into a single facet bucket with the label as value, e.g. ["US", "USA"] → "United States".
+/// Applied at query time to the returned facet values and to facet filters: the documents are not rewritten,
+/// and aliases can be changed at any time without reindexing.
+/// A facet filter with the label matches all documents with any of the raw values.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FacetAlias {
+    /// Name of a String or StringSet facet field
+    pub field: String,
+    /// Display label returned instead of the raw values
+    pub label: String,
+    /// Raw facet values as stored in the documents, mapped to the label
+    pub values: Vec<String>,
+}
+
 /// Defines a field in index schema: field_name, stored, indexed , field_type, field_boost.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SchemaField {
@@ -405,10 +582,55 @@ pub struct SchemaField {
     pub facet: bool,
 
     /// optional custom weight factor for Bm25 ranking
+    /// The BM25 score of each field is multiplied by its boost before the fields are combined (BM25F), e.g. boost=10.0 for a title field to outweigh the body field for every query.
     #[serde(skip_serializing_if = "is_default_f32")]
     #[serde(default = "default_1")]
     pub boost: f32,
 
+    /// optional per-field BM25 length normalization b (0.0 = no length normalization, 1.0 = full length normalization), overrides IndexMetaObject.b
+    /// e.g. a lower b for short title fields, where the field length carries little information.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub b: Option<f32>,
+
+    /// optional number of decimal places of a Decimal field (default 2, maximum 18)
+    #[serde(skip_serializing_if = "is_default_scale")]
+    #[serde(default = "default_scale")]
+    pub scale: u8,
+
+    /// optional custom input formats of a Date field: chrono format patterns, e.g. "%d.%m.%Y %H:%M" (<https://docs.rs/chrono/latest/chrono/format/strftime/index.html>)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub date_formats: Vec<String>,
+
+    /// optional normalizers of a Keyword field, applied in the defined order, e.g. `["Trim","Lowercase"]`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub normalizers: Vec<Normalizer>,
+
+    /// optional type coercion at index time: values of the wrong JSON type are converted into the field type, e.g. the string "42" into the number 42,
+    /// or epoch seconds into a date (see SchemaField::coerce_value for all rules)
+    #[serde(skip_serializing_if = "is_default_bool")]
+    #[serde(default = "default_false")]
+    pub coerce: bool,
+
+    /// optional default value, indexed and stored if the field is missing or null in a document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
+
+    /// optional expression of a computed field, evaluated from other fields of the document at index time, e.g. `year(published_at)`
+    /// (see ComputedExpression). The computed value replaces a value of the field in the document, and can be used for facets and sorting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub computed: Option<String>,
+
+    /// optional maximum number of values of a multi-valued String, Text, Keyword or StringSet field (JSON array), e.g. for tag lists of unbounded length.
+    /// Values beyond the limit are dropped at index time: they are neither indexed, faceted nor stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_values: Option<usize>,
+
     #[serde(skip)]
     pub(crate) indexed_field_id: usize,
     #[serde(skip_deserializing)]
@@ -431,6 +653,14 @@ fn is_default_f32(num: &f32) -> bool {
     *num == 1.0
 }
 
+fn default_scale() -> u8 {
+    2
+}
+
+fn is_default_scale(scale: &u8) -> bool {
+    *scale == 2
+}
+
 pub(crate) struct IndexedField {
     pub schema_field_name: String,
     pub field_length_sum: usize,
@@ -439,7 +669,7 @@ pub(crate) struct IndexedField {
     pub is_longest_field: bool,
 }
 
-/// Specifies SimilarityType, TokenizerType and AccessType when creating an new index
+/// Specifies SimilarityType, TokenizerType, AccessType and StemmerType when creating an new index
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct IndexMetaObject {
     #[serde(skip)]
@@ -451,6 +681,53 @@ pub struct IndexMetaObject {
     pub tokenizer: TokenizerType,
 
     pub access_type: AccessType,
+
+    #[serde(default)]
+    pub stemmer: StemmerType,
+
+    /// BM25 term frequency saturation k1: higher values increase the influence of repeated terms (default 1.2)
+    #[serde(default = "default_k1")]
+    pub k1: f32,
+    /// BM25 length normalization b, for all fields without SchemaField.b (default 0.75)
+    #[serde(default = "default_b")]
+    pub b: f32,
+    /// Ratio of deleted documents (deleted/indexed documents) at which commit automatically compacts the index, 0.0 disables automatic compaction (default)
+    #[serde(default)]
+    pub compact_deleted_ratio: f32,
+    /// Durability policy: when the index files are flushed to stable storage (fsync), default Durability::Os
+    #[serde(default)]
+    pub durability: Durability,
+    /// Maximum interval between flushes to stable storage in milliseconds for Durability::Periodic (default 1000)
+    #[serde(default = "default_sync_interval_ms")]
+    pub sync_interval_ms: u64,
+    /// Additionally index the unstemmed terms of stemmed indices (StemmerType other than None), which enables searching with stemming disabled per query (see QueryExpansion).
+    /// Increases the index size. Affects only subsequently indexed documents (default false).
+    #[serde(default)]
+    pub index_unstemmed: bool,
+    /// Reject documents with fields which are not in the schema, or with values of the wrong type (see Index::validate_document), instead of ignoring them (default false).
+    /// The REST API returns the errors, index_document skips invalid documents: use validate_document or index_documents_with_results to get the errors.
+    #[serde(default)]
+    pub strict_schema: bool,
+    /// Custom metadata of the application, an arbitrary JSON value stored with the index, e.g. display name, owner or UI configuration.
+    /// Not interpreted by SeekStorm (default null). Can be changed after index creation with Index::set_metadata.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+    /// Unix timestamp, in seconds, of the index creation, e.g. for index lifecycle policies. Set by create_index (default 0).
+    /// For indices created by earlier versions, the modification time of the index meta file is used when the index is opened.
+    #[serde(default)]
+    pub created: u64,
+}
+
+fn default_sync_interval_ms() -> u64 {
+    1000
+}
+
+fn default_k1() -> f32 {
+    K
+}
+
+fn default_b() -> f32 {
+    B
 }
 
 #[derive(Debug, Clone, Default)]
@@ -460,20 +737,64 @@ pub(crate) struct ResultFacet {
     pub prefix: String,
     pub length: u16,
     pub ranges: Ranges,
+    pub sort: FacetSort,
+    pub min_count: usize,
+    pub offset: usize,
+}
+
+impl ResultFacet {
+    /// Sorts the String or StringSet facet values with their counts, filters them by prefix and min_count, and returns the page within offset and length
+    pub(crate) fn page(&self, values: AHashMap<String, usize>) -> Facet {
+        let mut values = values
+            .into_iter()
+            .filter(|(value, count)| {
+                *count >= self.min_count
+                    && (self.prefix.is_empty() || value.starts_with(&self.prefix))
+            })
+            .collect::<Vec<_>>();
+
+        match self.sort {
+            FacetSort::Count => {
+                values.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+            }
+            FacetSort::Value => values.sort_unstable_by(|a, b| a.0.cmp(&b.0)),
+        }
+
+        values
+            .into_iter()
+            .skip(self.offset)
+            .take(self.length as usize)
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum DistanceUnit {
     Kilometers,
     Miles,
+    Meters,
+}
+
+/// Method of the distance calculation between two points
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum DistanceMethod {
+    /// Euclidian distance (Pythagoras theorem) with Equirectangular approximation: fast, accurate for short distances
+    #[default]
+    Planar,
+    /// Great-circle distance with the haversine formula: accurate also for long distances and near the poles
+    Haversine,
 }
 
+/// Distance field inserted into result documents by get_document: distance between the Point field and the base point.
+/// For a multi-valued Point field (`[[lat, lon], [lat, lon]]`) the distance to the nearest of the stored points is returned.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DistanceField {
     pub field: String,
     pub distance: String,
     pub base: Point,
     pub unit: DistanceUnit,
+    #[serde(default)]
+    pub method: DistanceMethod,
 }
 
 impl Default for DistanceField {
@@ -483,6 +804,7 @@ impl Default for DistanceField {
             distance: String::new(),
             base: Vec::new(),
             unit: DistanceUnit::Kilometers,
+            method: DistanceMethod::Planar,
         }
     }
 }
@@ -513,6 +835,10 @@ pub enum ValueType {
     Timestamp(i64),
     F32(f32),
     F64(f64),
+    /// Decimal scaled by 10^scale of the facet field
+    Decimal(i64),
+    /// Date: Unix milliseconds (UTC)
+    Date(i64),
     Point(Point, DistanceUnit),
     #[default]
     None,
@@ -533,6 +859,12 @@ pub struct FacetField {
     pub(crate) offset: usize,
     #[serde(skip)]
     pub(crate) field_type: FieldType,
+    #[serde(skip)]
+    pub(crate) scale: u8,
+    #[serde(skip)]
+    pub(crate) date_formats: Vec<String>,
+    #[serde(skip)]
+    pub(crate) normalizers: Vec<Normalizer>,
 }
 
 /// Facet field, with field name and a vector of unique values and their count (number of times the specific value appears in the whole index).
@@ -554,6 +886,10 @@ pub struct Index {
 
     /// Number of indexed documents
     pub indexed_doc_count: usize,
+    /// Incremented whenever the document ids are reassigned (compaction, clear_index), which invalidates all snapshots of the index (see snapshot::Snapshot)
+    pub(crate) doc_id_generation: u64,
+    /// A compaction is running, which prevents a concurrent compaction (see compact::Compact)
+    pub(crate) compacting: bool,
     /// Number of comitted documents
     pub committed_doc_count: usize,
     pub(crate) is_last_level_incomplete: bool,
@@ -577,6 +913,13 @@ pub struct Index {
     pub(crate) delete_file: File,
     pub(crate) delete_hashset: AHashSet<usize>,
 
+    /// Hook invoked after every commit, registered with set_commit_hook
+    pub(crate) commit_hook: Option<Arc<dyn CommitHook>>,
+    /// Documents deleted since the last commit, reported to the commit hook
+    pub(crate) pending_deleted_doc_ids: Vec<usize>,
+    /// Previous versions of the documents updated since the last commit, reported to the commit hook
+    pub(crate) pending_updated_doc_ids: Vec<usize>,
+
     pub(crate) index_file: File,
     pub(crate) index_path_string: String,
     pub(crate) index_file_mmap: Mmap,
@@ -595,6 +938,13 @@ pub struct Index {
     pub(crate) segments_level0: Vec<SegmentLevel0>,
     /// The index countains indexed, but uncommitted documents. Documents will either committed automatically once the number exceeds 64K documents, or once commit is invoked manually.
     pub uncommitted: bool,
+    /// The index is frozen (read-only): document mutations are rejected, see Freeze.
+    pub frozen: bool,
+    /// Checksums of the committed index files, see Verify.
+    pub(crate) checksums: IndexChecksums,
+    /// Commits or deletions not yet flushed to stable storage, see Durability.
+    pub(crate) unsynced: bool,
+    pub(crate) last_sync: Instant,
 
     pub(crate) enable_bigram: bool,
     pub(crate) enable_fallback: bool,
@@ -632,13 +982,52 @@ pub struct Index {
     pub(crate) facets: Vec<FacetField>,
     pub(crate) facets_map: AHashMap<String, usize>,
     pub(crate) facets_size_sum: usize,
+    /// Offset of the facet presence bits within the facet record of a document: one bit per facet field, set if the field is present in the document.
+    /// None for indices created before index format version 3.3, where missing facet values can't be distinguished from 0.
+    pub(crate) facets_presence_offset: Option<usize>,
     pub(crate) facets_file: File,
     pub(crate) facets_file_mmap: MmapMut,
-    pub(crate) bm25_component_cache: [f32; 256],
+    /// BM25 length normalization component per indexed field and compressed document length
+    pub(crate) bm25_component_cache: Vec<[f32; 256]>,
 
     pub(crate) string_set_to_single_term_id_vec: Vec<AHashMap<String, AHashSet<u16>>>,
 
     pub(crate) synonyms_map: AHashMap<u64, SynonymItem>,
+    /// Hashes of all terms which other terms are expanded to as synonyms
+    pub(crate) synonym_targets: AHashSet<u64>,
+    /// Display label per raw facet value, per facet field
+    pub(crate) facet_aliases_map: AHashMap<String, AHashMap<String, String>>,
+
+    pub(crate) ltr_models: AHashMap<String, LtrModel>,
+
+    pub(crate) query_rules: Vec<QueryRule>,
+    pub(crate) query_rewriter: Option<Arc<dyn QueryRewriter>>,
+
+    pub(crate) runtime_fields: Vec<RuntimeField>,
+
+    /// Shape index per GeoShape field
+    pub(crate) geo_shapes: AHashMap<String, ShapeIndex>,
+
+    /// Join index per Join field
+    pub(crate) joins: AHashMap<String, JoinIndex>,
+
+    pub(crate) percolator_queries: Vec<PercolatorQuery>,
+    pub(crate) alerts: Vec<Alert>,
+    /// Committed documents with lower document ids have already been evaluated by the alerts
+    pub(crate) alert_doc_id: usize,
+
+    pub(crate) ranking_variants: Vec<RankingVariant>,
+    pub(crate) ranking_metrics: RankingMetrics,
+    pub(crate) query_feedback: QueryFeedback,
+    pub(crate) term_vocabulary: TermVocabulary,
+    pub(crate) word_lists: WordLists,
+    pub(crate) usage_counters: UsageCountersAtomic,
+    pub(crate) level_history: LevelHistory,
+    /// Computed fields with their compiled expressions, in schema order
+    pub(crate) computed_fields: Vec<(SchemaField, ComputedExpression)>,
+
+    /// Tokenizer of TokenizerType::Custom, registered with set_tokenizer
+    pub(crate) custom_tokenizer: Option<Arc<dyn Tokenizer>>,
 
     #[cfg(feature = "zh")]
     pub(crate) word_segmentation_option: Option<WordSegmentationTM>,
@@ -646,6 +1035,64 @@ pub struct Index {
 
 pub type SynonymItem = Vec<(String, (u64, u32))>;
 
+pub(crate) fn get_facet_aliases_map(
+    facet_aliases: &[FacetAlias],
+) -> AHashMap<String, AHashMap<String, String>> {
+    let mut facet_aliases_map: AHashMap<String, AHashMap<String, String>> = AHashMap::new();
+    for facet_alias in facet_aliases.iter() {
+        let field_map = facet_aliases_map
+            .entry(facet_alias.field.clone())
+            .or_default();
+        for value in facet_alias.values.iter() {
+            field_map.insert(value.clone(), facet_alias.label.clone());
+        }
+    }
+    facet_aliases_map
+}
+
+/// BM25 length normalization component k1 * (1 - b + b * document_length_quotient) of an indexed field
+#[inline(always)]
+pub(crate) fn bm25_component(
+    meta: &IndexMetaObject,
+    schema_field: &SchemaField,
+    document_length_quotient: f32,
+) -> f32 {
+    let b = schema_field.b.unwrap_or(meta.b);
+    meta.k1 * (1.0 - b + b * document_length_quotient)
+}
+
+impl Index {
+    /// BM25 length normalization component k1 * (1 - b + b * document_length_quotient) of an indexed field
+    #[inline(always)]
+    pub(crate) fn bm25_component(
+        &self,
+        indexed_field_id: usize,
+        document_length_quotient: f32,
+    ) -> f32 {
+        bm25_component(
+            &self.meta,
+            &self.indexed_schema_vec[indexed_field_id],
+            document_length_quotient,
+        )
+    }
+
+    pub(crate) fn update_bm25_component_cache(&mut self) {
+        for (cache, schema_field) in self
+            .bm25_component_cache
+            .iter_mut()
+            .zip(self.indexed_schema_vec.iter())
+        {
+            for (component, document_length) in
+                cache.iter_mut().zip(DOCUMENT_LENGTH_COMPRESSION.iter())
+            {
+                let document_length_quotient =
+                    *document_length as f32 / self.document_length_normalized_average;
+                *component = bm25_component(&self.meta, schema_field, document_length_quotient);
+            }
+        }
+    }
+}
+
 /// Get the version of the SeekStorm search library
 pub fn version() -> &'static str {
     VERSION
@@ -701,6 +1148,14 @@ pub(crate) fn get_synonyms_map(
     synonyms_map
 }
 
+pub(crate) fn get_synonym_targets(synonyms_map: &AHashMap<u64, SynonymItem>) -> AHashSet<u64> {
+    synonyms_map
+        .values()
+        .flatten()
+        .map(|synonym| synonym.1 .0)
+        .collect()
+}
+
 /// Create index in RAM.
 /// Inner data structures for create index and open_index
 /// * `index_path` - index path.  
@@ -788,7 +1243,48 @@ pub fn create_index(
                 schema_field_clone.field_id = i;
                 schema_map.insert(schema_field.field.clone(), schema_field_clone.clone());
 
-                if schema_field.facet {
+                if schema_field.field_type == FieldType::Decimal && schema_field.scale > 18 {
+                    return Err(format!(
+                        "scale of decimal field {} exceeds 18",
+                        schema_field.field
+                    ));
+                }
+
+                if let Some(default) = schema_field.default.as_ref() {
+                    if !schema_field.is_valid_value(&schema_field.coerce_value(default), true) {
+                        return Err(format!(
+                            "default value {} of {:?} field {} is invalid",
+                            default, schema_field.field_type, schema_field.field
+                        ));
+                    }
+                }
+
+                if schema_field.field_type == FieldType::Keyword && schema_field.indexed {
+                    return Err(format!(
+                        "keyword field {} can't be indexed: keyword fields are not tokenized and are matched exactly with facet filters",
+                        schema_field.field
+                    ));
+                }
+
+                if schema_field.field_type == FieldType::GeoShape
+                    && (schema_field.indexed || schema_field.facet)
+                {
+                    return Err(format!(
+                        "geo shape field {} can't be indexed or a facet field: geo shapes are searched with FacetFilter::GeoShape",
+                        schema_field.field
+                    ));
+                }
+
+                if schema_field.field_type == FieldType::Join
+                    && (schema_field.indexed || schema_field.facet)
+                {
+                    return Err(format!(
+                        "join field {} can't be indexed or a facet field: joins are searched with FacetFilter::HasChild and FacetFilter::HasParent",
+                        schema_field.field
+                    ));
+                }
+
+                if schema_field.facet || schema_field.field_type == FieldType::Keyword {
                     let facet_size = match schema_field.field_type {
                         FieldType::U8 => 1,
                         FieldType::U16 => 2,
@@ -801,7 +1297,11 @@ pub fn create_index(
                         FieldType::Timestamp => 8,
                         FieldType::F32 => 4,
                         FieldType::F64 => 8,
+                        FieldType::Decimal => 8,
+                        FieldType::Date => 8,
+                        FieldType::Ip => 16,
                         FieldType::String => 2,
+                        FieldType::Keyword => 2,
                         FieldType::StringSet => 2,
                         FieldType::Point => 8,
                         _ => 1,
@@ -815,6 +1315,9 @@ pub fn create_index(
                         max: ValueType::None,
                         offset: facets_size_sum,
                         field_type: schema_field.field_type.clone(),
+                        scale: schema_field.scale,
+                        date_formats: schema_field.date_formats.clone(),
+                        normalizers: schema_field.normalizers.clone(),
                     });
                     facets_size_sum += facet_size;
                 }
@@ -836,6 +1339,9 @@ pub fn create_index(
                 }
             }
 
+            let facets_presence_offset = facets_size_sum;
+            facets_size_sum += facets_vec.len().div_ceil(8);
+
             let indexed_field_id_bits =
                 (u64::BITS - (indexed_field_vec.len() - 1).leading_zeros()) as usize;
 
@@ -870,6 +1376,9 @@ pub fn create_index(
                             for i in 0..facets.len() {
                                 facets[i].offset = facets_vec[i].offset;
                                 facets[i].field_type = facets_vec[i].field_type.clone();
+                                facets[i].scale = facets_vec[i].scale;
+                                facets[i].date_formats = facets_vec[i].date_formats.clone();
+                                facets[i].normalizers = facets_vec[i].normalizers.clone();
                             }
                         }
                         facets_vec = facets;
@@ -904,6 +1413,18 @@ pub fn create_index(
                 None
             };
 
+            let mut computed_fields = Vec::new();
+            for schema_field in schema.iter() {
+                if let Some(computed) = schema_field.computed.as_ref() {
+                    computed_fields.push((
+                        schema_field.clone(),
+                        ComputedExpression::compile(computed, &schema_map)
+                            .map_err(|e| format!("{} {}", schema_field.field, e))?,
+                    ));
+                }
+            }
+
+            let indexed_schema_vec_len = indexed_schema_vec.len();
             let mut index = Index {
                 index_format_version_major: INDEX_FORMAT_VERSION_MAJOR,
                 index_format_version_minor: INDEX_FORMAT_VERSION_MINOR,
@@ -913,6 +1434,9 @@ pub fn create_index(
                 docstore_file,
                 delete_file,
                 delete_hashset: AHashSet::new(),
+                commit_hook: None,
+                pending_deleted_doc_ids: Vec::new(),
+                pending_updated_doc_ids: Vec::new(),
                 index_file,
                 index_path_string: index_path_string.to_owned(),
                 index_file_mmap,
@@ -926,6 +1450,8 @@ pub fn create_index(
                 },
                 document_length_normalized_average: 0.0,
                 indexed_doc_count: 0,
+                doc_id_generation: 0,
+                compacting: false,
                 committed_doc_count: 0,
                 is_last_level_incomplete: false,
                 last_level_index_file_start_pos: 0,
@@ -938,6 +1464,10 @@ pub fn create_index(
                 segments_index: Vec::new(),
                 segments_level0: Vec::new(),
                 uncommitted: false,
+                frozen: false,
+                checksums: IndexChecksums::default(),
+                unsynced: false,
+                last_sync: Instant::now(),
                 enable_bigram: BIGRAM_FLAG,
                 enable_fallback: false,
                 enable_single_term_topk: false,
@@ -970,11 +1500,32 @@ pub fn create_index(
                 facets: facets_vec,
                 facets_map,
                 facets_size_sum,
+                facets_presence_offset: Some(facets_presence_offset),
                 facets_file,
                 facets_file_mmap,
                 string_set_to_single_term_id_vec: vec![AHashMap::new(); facets_len],
-                bm25_component_cache: [0.0; 256],
+                bm25_component_cache: vec![[0.0; 256]; indexed_schema_vec_len],
+                synonym_targets: get_synonym_targets(&synonyms_map),
                 synonyms_map,
+                facet_aliases_map: AHashMap::new(),
+                ltr_models: AHashMap::new(),
+                query_rules: Vec::new(),
+                query_rewriter: None,
+                runtime_fields: Vec::new(),
+                geo_shapes: new_geo_shapes(schema),
+                joins: new_joins(schema),
+                percolator_queries: Vec::new(),
+                alerts: Vec::new(),
+                alert_doc_id: 0,
+                ranking_variants: Vec::new(),
+                ranking_metrics: RankingMetrics::default(),
+                query_feedback: QueryFeedback::default(),
+                term_vocabulary: TermVocabulary::default(),
+                word_lists: WordLists::default(),
+                usage_counters: UsageCountersAtomic::default(),
+                level_history: LevelHistory::default(),
+                computed_fields,
+                custom_tokenizer: None,
                 #[cfg(feature = "zh")]
                 word_segmentation_option,
             };
@@ -1009,6 +1560,11 @@ pub fn create_index(
                         + " "
                         + &index.index_format_version_major.to_string());
                 };
+
+                if index.index_format_version_minor < 3 {
+                    index.facets_size_sum = facets_presence_offset;
+                    index.facets_presence_offset = None;
+                }
             }
 
             index.segment_number1 = segment_number1;
@@ -1044,6 +1600,10 @@ pub fn create_index(
                     .unwrap();
                 }
 
+                index.meta.created = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default();
                 serde_json::to_writer(
                     &File::create(Path::new(index_path).join(META_FILENAME)).unwrap(),
                     &index.meta,
@@ -1176,7 +1736,8 @@ pub(crate) fn get_max_score(
 
             bm25f += weight
                 * idf
-                * ((tf * (K + 1.0) / (tf + (K * (1.0 - B + (B * document_length_quotient)))))
+                * ((tf * (index.meta.k1 + 1.0)
+                    / (tf + index.bm25_component(field.0 as usize, document_length_quotient)))
                     + SIGMA);
         }
     } else {
@@ -1212,8 +1773,9 @@ pub(crate) fn get_max_score(
 
             bm25f += weight
                 * idf_bigram1
-                * ((tf_bigram1 * (K + 1.0)
-                    / (tf_bigram1 + (K * (1.0 - B + (B * document_length_quotient)))))
+                * ((tf_bigram1 * (index.meta.k1 + 1.0)
+                    / (tf_bigram1
+                        + index.bm25_component(field.0 as usize, document_length_quotient)))
                     + SIGMA);
         }
 
@@ -1236,8 +1798,9 @@ pub(crate) fn get_max_score(
 
             bm25f += weight
                 * idf_bigram2
-                * ((tf_bigram2 * (K + 1.0)
-                    / (tf_bigram2 + (K * (1.0 - B + (B * document_length_quotient)))))
+                * ((tf_bigram2 * (index.meta.k1 + 1.0)
+                    / (tf_bigram2
+                        + index.bm25_component(field.0 as usize, document_length_quotient)))
                     + SIGMA);
         }
     }
@@ -1334,6 +1897,31 @@ pub(crate) fn update_list_max_impact_score(index: &mut Index) {
 /// * `index_path` - index path.  
 /// * `mute` - prevent emitting status messages (e.g. when using pipes for data interprocess communication).  
 pub async fn open_index(index_path: &Path, mute: bool) -> Result<IndexArc, String> {
+    recover_compaction(index_path)?;
+    open_index_tokenizer(index_path, None, mute).await
+}
+
+/// Loads an index with TokenizerType::Custom from disk into RAM, with the tokenizer registered before the index is used.
+/// The same tokenizer as for create_index has to be used, as the index stores the terms, but not the tokenizer.
+/// * `index_path` - index path.  
+/// * `tokenizer` - custom tokenizer of the index (see tokenizer::Tokenizer).  
+/// * `mute` - prevent emitting status messages (e.g. when using pipes for data interprocess communication).  
+pub async fn open_index_with_tokenizer(
+    index_path: &Path,
+    tokenizer: Arc<dyn Tokenizer>,
+    mute: bool,
+) -> Result<IndexArc, String> {
+    recover_compaction(index_path)?;
+    open_index_tokenizer(index_path, Some(tokenizer), mute).await
+}
+
+/// Opens the index, an index with TokenizerType::Custom is refused without its tokenizer,
+/// as indexing and searching without it would silently produce and match different terms
+async fn open_index_tokenizer(
+    index_path: &Path,
+    custom_tokenizer: Option<Arc<dyn Tokenizer>>,
+    mute: bool,
+) -> Result<IndexArc, String> {
     if !mute {
         println!("opening index ...");
     }
@@ -1345,7 +1933,29 @@ pub async fn open_index(index_path: &Path, mute: bool) -> Result<IndexArc, Strin
 
     match File::open(Path::new(index_path).join(META_FILENAME)) {
         Ok(meta_file) => {
-            let meta = serde_json::from_reader(BufReader::new(meta_file)).unwrap();
+            let mut meta: IndexMetaObject =
+                serde_json::from_reader(BufReader::new(&meta_file)).unwrap();
+            if meta.created == 0 {
+                meta.created = meta_file
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default();
+            }
+            match (meta.tokenizer, &custom_tokenizer) {
+                (TokenizerType::Custom, None) => {
+                    return Err(
+                        "index tokenizer is Custom: open it with open_index_with_tokenizer"
+                            .to_string(),
+                    );
+                }
+                (tokenizer, Some(_)) if tokenizer != TokenizerType::Custom => {
+                    return Err(format!("index tokenizer is {:?}, not Custom", tokenizer));
+                }
+                _ => {}
+            }
 
             match File::open(Path::new(index_path).join(SCHEMA_FILENAME)) {
                 Ok(schema_file) => {
@@ -1361,6 +1971,24 @@ pub async fn open_index(index_path: &Path, mute: bool) -> Result<IndexArc, Strin
 
                     match create_index(index_path, meta, &schema, false, &synonyms, 11, false) {
                         Ok(mut index) => {
+                            index.custom_tokenizer = custom_tokenizer;
+                            index.ltr_models = load_ltr_models(Path::new(index_path));
+                            index.query_rules = load_query_rules(Path::new(index_path));
+                            index.runtime_fields = load_runtime_fields(Path::new(index_path));
+                            index.load_geo_shapes();
+                            index.load_joins();
+                            index.percolator_queries =
+                                load_percolator_queries(Path::new(index_path));
+                            (index.alerts, index.alert_doc_id) = load_alerts(Path::new(index_path));
+                            index.ranking_variants = load_ranking_variants(Path::new(index_path));
+                            index.query_feedback = load_query_feedback(Path::new(index_path));
+                            index.word_lists = load_word_lists(Path::new(index_path));
+                            index.usage_counters = load_usage_counters(Path::new(index_path));
+                            index.level_history = load_level_history(Path::new(index_path));
+                            if let Ok(facet_aliases) = index.get_facet_aliases() {
+                                index.facet_aliases_map = get_facet_aliases_map(&facet_aliases);
+                            }
+
                             let mut block_count_sum = 0;
 
                             let is_mmap = index.meta.access_type == AccessType::Mmap;
@@ -1556,6 +2184,10 @@ pub async fn open_index(index_path: &Path, mute: bool) -> Result<IndexArc, Strin
                                             docstore_pointer_docs,
                                             docstore_pointer_docs_pointer,
                                             document_length_compressed_array_pointer,
+                                            index_file_start_pos: index
+                                                .last_level_index_file_start_pos,
+                                            docstore_file_start_pos: index
+                                                .last_level_docstore_file_start_pos,
                                         });
                                     }
 
@@ -1762,13 +2394,7 @@ pub async fn open_index(index_path: &Path, mute: bool) -> Result<IndexArc, Strin
                             index.is_last_level_incomplete =
                                 index.committed_doc_count % ROARING_BLOCK_SIZE > 0;
 
-                            for (i, component) in index.bm25_component_cache.iter_mut().enumerate()
-                            {
-                                let document_length_quotient = DOCUMENT_LENGTH_COMPRESSION[i]
-                                    as f32
-                                    / index.document_length_normalized_average;
-                                *component = K * (1.0 - B + B * document_length_quotient);
-                            }
+                            index.update_bm25_component_cache();
 
                             index.string_set_to_single_term_id();
 
@@ -1815,6 +2441,16 @@ pub async fn open_index(index_path: &Path, mute: bool) -> Result<IndexArc, Strin
                         elapsed_time/1_000_000_000
                     );
                             }
+                            if let Ok(checksums) = fs::read(index_path.join(CHECKSUMS_FILENAME)) {
+                                index.checksums =
+                                    serde_json::from_slice(&checksums).unwrap_or_default();
+                            }
+
+                            if index_path.join(FROZEN_FILENAME).exists() {
+                                index.frozen = true;
+                                index.release_write_buffers();
+                            }
+
                             let index_arc = Arc::new(RwLock::new(index));
                             warmup(&index_arc).await;
                             Ok(index_arc)
@@ -1834,15 +2470,21 @@ pub(crate) async fn warmup(index_object_arc: &IndexArc) {
     let mut query_facets: Vec<QueryFacet> = Vec::new();
     for facet in index_object_arc.read().await.facets.iter() {
         match facet.field_type {
-            FieldType::String => query_facets.push(QueryFacet::String {
+            FieldType::String | FieldType::Keyword => query_facets.push(QueryFacet::String {
                 field: facet.name.clone(),
                 prefix: "".into(),
                 length: u16::MAX,
+                sort: FacetSort::Count,
+                min_count: 0,
+                offset: 0,
             }),
             FieldType::StringSet => query_facets.push(QueryFacet::StringSet {
                 field: facet.name.clone(),
                 prefix: "".into(),
                 length: u16::MAX,
+                sort: FacetSort::Count,
+                min_count: 0,
+                offset: 0,
             }),
             _ => {}
         }
@@ -1851,16 +2493,10 @@ pub(crate) async fn warmup(index_object_arc: &IndexArc) {
     for stopword in STOPWORDS {
         let results_list = index_object_arc
             .search(
-                stopword.to_owned(),
-                QueryType::Union,
-                0,
-                1000,
-                search::ResultType::TopkCount,
-                false,
-                Vec::new(),
-                query_facets.clone(),
-                Vec::new(),
-                Vec::new(),
+                SearchRequest::new(stopword.to_owned())
+                    .query_type_default(QueryType::Union)
+                    .length(1000)
+                    .query_facets(query_facets.clone()),
             )
             .await;
 
@@ -1893,6 +2529,8 @@ pub(crate) struct NonUniqueTermObject {
     pub term_bigram2: String,
     pub is_bigram: bool,
     pub op: QueryType,
+    /// Number of removed tokens directly preceding the term (see Tokenizer), kept as position gap in phrase matching
+    pub position_gap: usize,
 }
 
 lazy_static! {
@@ -2034,6 +2672,24 @@ impl Index {
                         },
                     );
                 }
+                (ValueType::Decimal(min), ValueType::Decimal(max)) => {
+                    facets_minmax.insert(
+                        facet.name.clone(),
+                        MinMaxFieldJson {
+                            min: decimal_to_f64(*min, facet.scale).into(),
+                            max: decimal_to_f64(*max, facet.scale).into(),
+                        },
+                    );
+                }
+                (ValueType::Date(min), ValueType::Date(max)) => {
+                    facets_minmax.insert(
+                        facet.name.clone(),
+                        MinMaxFieldJson {
+                            min: (*min).into(),
+                            max: (*max).into(),
+                        },
+                    );
+                }
                 _ => {}
             }
         }
@@ -2047,7 +2703,7 @@ impl Index {
     ///    The length property of a QueryFacet allows limiting the number of returned distinct values per facet field, if there are too many distinct values.  The QueryFacet can be used to improve the usability in an UI.
     ///    If the length property of a QueryFacet is set to 0 then no facet values for that facet are returned.
     ///    The facet values are sorted by the frequency of the appearance of the value within the indexed documents matching the query in descending order.
-    ///    Example: query_facets = vec![QueryFacet::String {field: "language".to_string(),prefix: "ger".to_string(),length: 5},QueryFacet::String {field: "brand".to_string(),prefix: "a".to_string(),length: 5}];
+    ///    Example: query_facets = vec![QueryFacet::String {field: "language".to_string(),prefix: "ger".to_string(),length: 5,sort: FacetSort::Count,min_count: 0,offset: 0},QueryFacet::String {field: "brand".to_string(),prefix: "a".to_string(),length: 5,sort: FacetSort::Count,min_count: 0,offset: 0}];
     pub fn get_index_string_facets(
         &self,
         query_facets: Vec<QueryFacet>,
@@ -2065,13 +2721,22 @@ impl Index {
                         field,
                         prefix,
                         length,
+                        sort,
+                        min_count,
+                        offset,
                     } => {
                         if let Some(idx) = self.facets_map.get(field) {
-                            if self.facets[*idx].field_type == FieldType::String {
+                            if matches!(
+                                self.facets[*idx].field_type,
+                                FieldType::String | FieldType::Keyword
+                            ) {
                                 result_query_facets[*idx] = ResultFacet {
                                     field: field.clone(),
                                     prefix: prefix.clone(),
                                     length: *length,
+                                    sort: *sort,
+                                    min_count: *min_count,
+                                    offset: *offset,
                                     ..Default::default()
                                 }
                             }
@@ -2081,6 +2746,9 @@ impl Index {
                         field,
                         prefix,
                         length,
+                        sort,
+                        min_count,
+                        offset,
                     } => {
                         if let Some(idx) = self.facets_map.get(field) {
                             if self.facets[*idx].field_type == FieldType::StringSet {
@@ -2088,6 +2756,9 @@ impl Index {
                                     field: field.clone(),
                                     prefix: prefix.clone(),
                                     length: *length,
+                                    sort: *sort,
+                                    min_count: *min_count,
+                                    offset: *offset,
                                     ..Default::default()
                                 }
                             }
@@ -2104,38 +2775,19 @@ impl Index {
                 continue;
             }
 
-            if self.facets[i].field_type == FieldType::StringSet {
-                let mut hash_map: AHashMap<String, usize> = AHashMap::new();
-                for value in self.facets[i].values.iter() {
-                    for term in value.1 .0.iter() {
-                        *hash_map.entry(term.clone()).or_insert(0) += value.1 .1;
-                    }
-                }
+            let v = facet.page(
+                self.facet_label_counts(
+                    i,
+                    self.facets[i]
+                        .values
+                        .values()
+                        .enumerate()
+                        .map(|(value_id, value)| (value_id, value.1)),
+                ),
+            );
 
-                let v = hash_map
-                    .iter()
-                    .sorted_unstable_by(|a, b| b.1.cmp(a.1))
-                    .map(|(a, c)| (a.to_string(), *c))
-                    .filter(|(a, _c)| facet.prefix.is_empty() || a.starts_with(&facet.prefix))
-                    .take(facet.length as usize)
-                    .collect::<Vec<_>>();
-
-                if !v.is_empty() {
-                    facets.insert(facet.field.clone(), v);
-                }
-            } else {
-                let v = self.facets[i]
-                    .values
-                    .iter()
-                    .sorted_unstable_by(|a, b| b.1.cmp(a.1))
-                    .map(|(a, c)| (a.to_string(), c.1))
-                    .filter(|(a, _c)| facet.prefix.is_empty() || a.starts_with(&facet.prefix))
-                    .take(facet.length as usize)
-                    .collect::<Vec<_>>();
-
-                if !v.is_empty() {
-                    facets.insert(facet.field.clone(), v);
-                }
+            if !v.is_empty() {
+                facets.insert(facet.field.clone(), v);
             }
         }
 
@@ -2158,7 +2810,11 @@ impl Index {
     }
 
     /// Reset index to empty, while maintaining schema
+    /// Has no effect if the index is frozen.
     pub fn clear_index(&mut self) {
+        if self.frozen {
+            return;
+        }
         let _ = self.index_file.rewind();
         let _ = self.index_file.set_len(0);
         write_u16(
@@ -2184,6 +2840,8 @@ impl Index {
         let _ = self.delete_file.rewind();
         let _ = self.delete_file.set_len(0);
         self.delete_hashset.clear();
+        self.pending_deleted_doc_ids.clear();
+        self.pending_updated_doc_ids.clear();
 
         let _ = self.facets_file.rewind();
         let _ = self
@@ -2193,7 +2851,12 @@ impl Index {
             unsafe { MmapMut::map_mut(&self.facets_file).expect("Unable to create Mmap") };
         let index_path = Path::new(&self.index_path_string);
         let _ = fs::remove_file(index_path.join(FACET_VALUES_FILENAME));
+        let _ = fs::remove_file(index_path.join(CHECKSUMS_FILENAME));
         self.facets.clear();
+        self.clear_geo_shapes();
+        self.clear_joins();
+        self.checksums = IndexChecksums::default();
+        self.sync_after_write();
 
         if !self.stored_field_names.is_empty() && self.meta.access_type == AccessType::Mmap {
             self.docstore_file_mmap =
@@ -2202,10 +2865,14 @@ impl Index {
 
         self.document_length_normalized_average = 0.0;
         self.indexed_doc_count = 0;
+        self.doc_id_generation += 1;
         self.positions_sum_normalized = 0;
         self.segment_number1 = 0;
 
         self.level_index = Vec::new();
+        if let Err(e) = self.clear_level_history() {
+            println!("level history not cleared: {}", e);
+        }
         self.segments_index = Vec::new();
         self.segments_level0 = Vec::new();
 
@@ -2230,37 +2897,128 @@ impl Index {
         let _ = fs::remove_file(index_path.join(DELETE_FILENAME));
         let _ = fs::remove_file(index_path.join(FACET_FILENAME));
         let _ = fs::remove_file(index_path.join(FACET_VALUES_FILENAME));
+        let _ = fs::remove_file(index_path.join(LTR_MODELS_FILENAME));
+        let _ = fs::remove_file(index_path.join(FACET_ALIASES_FILENAME));
+        let _ = fs::remove_file(index_path.join(QUERY_RULES_FILENAME));
+        let _ = fs::remove_file(index_path.join(RUNTIME_FIELDS_FILENAME));
+        let _ = fs::remove_file(index_path.join(GEO_SHAPES_FILENAME));
+        let _ = fs::remove_file(index_path.join(JOINS_FILENAME));
+        let _ = fs::remove_file(index_path.join(PERCOLATOR_QUERIES_FILENAME));
+        let _ = fs::remove_file(index_path.join(ALERTS_FILENAME));
+        let _ = fs::remove_file(index_path.join(RANKING_VARIANTS_FILENAME));
+        let _ = fs::remove_file(index_path.join(QUERY_FEEDBACK_FILENAME));
+        let _ = fs::remove_file(index_path.join(USAGE_COUNTERS_FILENAME));
+        let _ = fs::remove_file(index_path.join(LEVELS_FILENAME));
+        let _ = fs::remove_file(index_path.join(FROZEN_FILENAME));
+        let _ = fs::remove_file(index_path.join(CHECKSUMS_FILENAME));
         let _ = fs::remove_dir(index_path);
     }
 
     /// Remove index from RAM (Reverse of open_index)
     pub fn close_index(&mut self) {
         self.commit(self.indexed_doc_count);
+        if let Err(e) = self.save_query_feedback() {
+            println!("query feedback not saved: {}", e);
+        }
+        if let Err(e) = self.save_usage_counters() {
+            println!("usage counters not saved: {}", e);
+        }
+    }
+
+    /// Replaces the index in RAM with the index reopened from its index directory, e.g. after the index files have been rewritten
+    pub(crate) async fn reopen_index(&mut self) -> Result<(), String> {
+        self.save_query_feedback()?;
+        self.save_usage_counters()?;
+        let Ok(index) = Arc::try_unwrap(
+            open_index_tokenizer(
+                Path::new(&self.index_path_string),
+                self.custom_tokenizer.clone(),
+                true,
+            )
+            .await?,
+        ) else {
+            return Err("reopened index is in use".to_string());
+        };
+        let mut index = index.into_inner();
+        index.meta.id = self.meta.id;
+        index.mute = self.mute;
+        index.doc_id_generation = self.doc_id_generation + 1;
+        index.commit_hook = self.commit_hook.take();
+        *self = index;
+        Ok(())
+    }
+
+    /// Unstemmed terms are indexed additionally (IndexMetaObject.index_unstemmed), so that stemming can be disabled per query
+    pub(crate) fn is_unstemmed_indexed(&self) -> bool {
+        self.meta.index_unstemmed && self.meta.stemmer != StemmerType::None
+    }
+
+    /// Set/replace the custom metadata of the index (IndexMetaObject.metadata), which is persisted in the index meta file.
+    /// The meta file is written to a temporary file, flushed to disk and then renamed, so that a crash never leaves a truncated meta file.
+    pub fn set_metadata(&mut self, metadata: serde_json::Value) -> Result<(), SeekStormError> {
+        let meta_path = Path::new(&self.index_path_string).join(META_FILENAME);
+        let temp_path = meta_path.with_extension("json.tmp");
+        let mut meta = self.meta.clone();
+        meta.metadata = metadata;
+        let meta_json =
+            serde_json::to_vec(&meta).map_err(|e| SeekStormError::Internal(e.to_string()))?;
+
+        let result = File::create(&temp_path)
+            .and_then(|mut temp_file| {
+                temp_file.write_all(&meta_json)?;
+                temp_file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_path, &meta_path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(SeekStormError::Io(format!(
+                "{}: {}",
+                meta_path.display(),
+                e
+            )));
+        }
+
+        self.meta = meta;
+        Ok(())
+    }
+
+    /// Get the custom metadata of the index (IndexMetaObject.metadata)
+    pub fn get_metadata(&self) -> serde_json::Value {
+        self.meta.metadata.clone()
+    }
+
+    /// Get the schema of the index, in the order of the fields at index creation
+    pub fn get_schema(&self) -> Vec<SchemaField> {
+        self.schema_map
+            .values()
+            .sorted_by_key(|schema_field| schema_field.field_id)
+            .cloned()
+            .collect()
     }
 
     /// Get synonyms from index
-    pub fn get_synonyms(&self) -> Result<Vec<Synonym>, String> {
+    pub fn get_synonyms(&self) -> Result<Vec<Synonym>, SeekStormError> {
         if let Ok(synonym_file) =
             File::open(Path::new(&self.index_path_string).join(SYNONYMS_FILENAME))
         {
             if let Ok(synonyms) = serde_json::from_reader(BufReader::new(synonym_file)) {
                 Ok(synonyms)
             } else {
-                Err("not found".into())
+                Err(SeekStormError::NotFound("synonyms not found".into()))
             }
         } else {
-            Err("not found".into())
+            Err(SeekStormError::NotFound("synonyms not found".into()))
         }
     }
 
     /// Set/replace/overwrite synonyms in index
     /// Affects only subsequently indexed documents
-    pub fn set_synonyms(&mut self, synonyms: &Vec<Synonym>) -> Result<usize, String> {
+    pub fn set_synonyms(&mut self, synonyms: &Vec<Synonym>) -> Result<usize, SeekStormError> {
         serde_json::to_writer(
-            &File::create(Path::new(&self.index_path_string).join(SYNONYMS_FILENAME)).unwrap(),
+            &File::create(Path::new(&self.index_path_string).join(SYNONYMS_FILENAME))?,
             &synonyms,
         )
-        .unwrap();
+        .map_err(|e| SeekStormError::Io(e.to_string()))?;
 
         self.synonyms_map = get_synonyms_map(
             synonyms,
@@ -2268,12 +3026,13 @@ impl Index {
             &self.hasher_32,
             self.segment_number_mask1,
         );
+        self.synonym_targets = get_synonym_targets(&self.synonyms_map);
         Ok(synonyms.len())
     }
 
     /// Add/append/update/merge synonyms in index
     /// Affects only subsequently indexed documents
-    pub fn add_synonyms(&mut self, synonyms: &[Synonym]) -> Result<usize, String> {
+    pub fn add_synonyms(&mut self, synonyms: &[Synonym]) -> Result<usize, SeekStormError> {
         let mut merged_synonyms = if let Ok(synonym_file) =
             File::open(Path::new(&self.index_path_string).join(SYNONYMS_FILENAME))
         {
@@ -2285,10 +3044,10 @@ impl Index {
         merged_synonyms.extend(synonyms.iter().cloned());
 
         serde_json::to_writer(
-            &File::create(Path::new(&self.index_path_string).join(SYNONYMS_FILENAME)).unwrap(),
+            &File::create(Path::new(&self.index_path_string).join(SYNONYMS_FILENAME))?,
             &merged_synonyms,
         )
-        .unwrap();
+        .map_err(|e| SeekStormError::Io(e.to_string()))?;
 
         self.synonyms_map = get_synonyms_map(
             &merged_synonyms,
@@ -2296,8 +3055,125 @@ impl Index {
             &self.hasher_32,
             self.segment_number_mask1,
         );
+        self.synonym_targets = get_synonym_targets(&self.synonyms_map);
         Ok(merged_synonyms.len())
     }
+
+    /// Get facet aliases from index
+    pub fn get_facet_aliases(&self) -> Result<Vec<FacetAlias>, SeekStormError> {
+        if let Ok(facet_aliases_file) =
+            File::open(Path::new(&self.index_path_string).join(FACET_ALIASES_FILENAME))
+        {
+            if let Ok(facet_aliases) = serde_json::from_reader(BufReader::new(facet_aliases_file)) {
+                Ok(facet_aliases)
+            } else {
+                Err(SeekStormError::NotFound("facet aliases not found".into()))
+            }
+        } else {
+            Err(SeekStormError::NotFound("facet aliases not found".into()))
+        }
+    }
+
+    /// Set/replace/overwrite facet aliases in index
+    /// Affects all subsequent queries, including previously indexed documents
+    pub fn set_facet_aliases(
+        &mut self,
+        facet_aliases: &[FacetAlias],
+    ) -> Result<usize, SeekStormError> {
+        for facet_alias in facet_aliases.iter() {
+            match self.facets_map.get(&facet_alias.field) {
+                Some(idx)
+                    if matches!(
+                        self.facets[*idx].field_type,
+                        FieldType::String | FieldType::Keyword | FieldType::StringSet
+                    ) => {}
+                _ => {
+                    return Err(SeekStormError::InvalidRequest(format!(
+                        "not a String, Keyword or StringSet facet field: {}",
+                        facet_alias.field
+                    )))
+                }
+            }
+        }
+
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(FACET_ALIASES_FILENAME))?,
+            &facet_aliases,
+        )
+        .map_err(|e| SeekStormError::Io(e.to_string()))?;
+
+        self.facet_aliases_map = get_facet_aliases_map(facet_aliases);
+        Ok(facet_aliases.len())
+    }
+
+    /// Add/append/update/merge facet aliases in index
+    /// Affects all subsequent queries, including previously indexed documents
+    pub fn add_facet_aliases(
+        &mut self,
+        facet_aliases: &[FacetAlias],
+    ) -> Result<usize, SeekStormError> {
+        let mut merged_facet_aliases = self.get_facet_aliases().unwrap_or_default();
+        merged_facet_aliases.extend(facet_aliases.iter().cloned());
+        self.set_facet_aliases(&merged_facet_aliases)
+    }
+
+    /// Returns the display label of a raw String or StringSet facet value, or the raw value itself if no alias is defined
+    #[inline]
+    pub(crate) fn facet_label<'a>(&'a self, field: &str, value: &'a str) -> &'a str {
+        self.facet_aliases_map
+            .get(field)
+            .and_then(|field_map| field_map.get(value))
+            .map_or(value, |label| label.as_str())
+    }
+
+    /// Returns the raw String or StringSet facet values a facet filter value matches:
+    /// the value itself and all raw values mapped to it as label
+    pub(crate) fn facet_filter_values(&self, field: &str, value: &str) -> Vec<String> {
+        let mut values = vec![value.to_string()];
+        if let Some(field_map) = self.facet_aliases_map.get(field) {
+            values.extend(
+                field_map
+                    .iter()
+                    .filter(|(_raw_value, label)| *label == value)
+                    .map(|(raw_value, _label)| raw_value.clone()),
+            );
+        }
+        values
+    }
+
+    /// Counts the values of a String or StringSet facet field (facet index idx) by display label,
+    /// merging raw values with the same label. A StringSet value is counted at most once per label.
+    /// * `values`: facet value ids with their counts
+    pub(crate) fn facet_label_counts(
+        &self,
+        idx: usize,
+        values: impl Iterator<Item = (usize, usize)>,
+    ) -> AHashMap<String, usize> {
+        let facet = &self.facets[idx];
+        let mut label_counts: AHashMap<String, usize> = AHashMap::new();
+        for (value_id, count) in values {
+            let Some(value) = facet.values.get_index(value_id) else {
+                continue;
+            };
+
+            if facet.field_type == FieldType::StringSet {
+                for label in value
+                    .1
+                     .0
+                    .iter()
+                    .map(|term| self.facet_label(&facet.name, term))
+                    .unique()
+                {
+                    *label_counts.entry(label.to_string()).or_insert(0) += count;
+                }
+            } else {
+                *label_counts
+                    .entry(self.facet_label(&facet.name, value.0).to_string())
+                    .or_insert(0) += count;
+            }
+        }
+        label_counts
+    }
 }
 
 /// Delete document from index by document id
@@ -2308,16 +3184,16 @@ pub trait DeleteDocument {
 
 /// Delete document from index by document id
 /// Document ID can by obtained by search.
-/// Immediately effective, indpendent of commit.
-/// Index space used by deleted documents is not reclaimed (until compaction is implemented), but result_count_total is updated.
+/// Immediately effective, indpendent of commit. Has no effect if the index is frozen.
+/// Index space used by deleted documents is not reclaimed until compaction, but result_count_total is updated.
 /// By manually deleting the delete.bin file the deleted documents can be recovered (until compaction).
-/// Deleted documents impact performance, especially but not limited to counting (Count, TopKCount). They also increase the size of the index (until compaction is implemented).
-/// For minimal query latency delete index and reindexing documents is preferred over deleting documents (until compaction is implemented).
-/// BM25 scores are not updated (until compaction is implemented), but the impact is minimal.
+/// Deleted documents impact performance, especially but not limited to counting (Count, TopKCount). They also increase the size of the index until compaction.
+/// Compaction (Compact::compact) rewrites the index without deleted documents, manually or automatically at IndexMetaObject.compact_deleted_ratio.
+/// BM25 scores are not updated until compaction, but the impact is minimal.
 impl DeleteDocument for IndexArc {
     async fn delete_document(&self, docid: u64) {
         let mut index_mut = self.write().await;
-        if docid as usize >= index_mut.indexed_doc_count {
+        if docid as usize >= index_mut.indexed_doc_count || index_mut.frozen {
             return;
         }
         if index_mut.delete_hashset.insert(docid as usize) {
@@ -2325,6 +3201,8 @@ impl DeleteDocument for IndexArc {
             write_u64(docid, &mut buffer, 0);
             let _ = index_mut.delete_file.write(&buffer);
             let _ = index_mut.delete_file.flush();
+            index_mut.sync_after_write();
+            index_mut.record_deleted_doc_id(docid as usize);
         }
     }
 }
@@ -2337,26 +3215,40 @@ pub trait DeleteDocuments {
 
 /// Delete documents from index by document id
 /// Document ID can by obtained by search.
-/// Immediately effective, indpendent of commit.
-/// Index space used by deleted documents is not reclaimed (until compaction is implemented), but result_count_total is updated.
+/// Immediately effective, indpendent of commit. Has no effect if the index is frozen.
+/// Index space used by deleted documents is not reclaimed until compaction, but result_count_total is updated.
 /// By manually deleting the delete.bin file the deleted documents can be recovered (until compaction).
-/// Deleted documents impact performance, especially but not limited to counting (Count, TopKCount). They also increase the size of the index (until compaction is implemented).
-/// For minimal query latency delete index and reindexing documents is preferred over deleting documents (until compaction is implemented).
-/// BM25 scores are not updated (until compaction is implemented), but the impact is minimal.
+/// Deleted documents impact performance, especially but not limited to counting (Count, TopKCount). They also increase the size of the index until compaction.
+/// Compaction (Compact::compact) rewrites the index without deleted documents, manually or automatically at IndexMetaObject.compact_deleted_ratio.
+/// BM25 scores are not updated until compaction, but the impact is minimal.
 impl DeleteDocuments for IndexArc {
     async fn delete_documents(&self, docid_vec: Vec<u64>) {
-        let mut index_mut = self.write().await;
+        self.write().await.delete_doc_ids(docid_vec);
+    }
+}
+
+impl Index {
+    /// Deletes documents by document id, returns the number of deleted documents: ids of non-existing or already deleted documents are not counted
+    pub(crate) fn delete_doc_ids(&mut self, docid_vec: Vec<u64>) -> usize {
+        if self.frozen {
+            return 0;
+        }
+        let mut deleted_doc_count = 0;
         let mut buffer: [u8; 8] = [0; 8];
         for docid in docid_vec {
-            if docid as usize >= index_mut.indexed_doc_count {
+            if docid as usize >= self.indexed_doc_count {
                 continue;
             }
-            if index_mut.delete_hashset.insert(docid as usize) {
+            if self.delete_hashset.insert(docid as usize) {
                 write_u64(docid, &mut buffer, 0);
-                let _ = index_mut.delete_file.write(&buffer);
+                let _ = self.delete_file.write(&buffer);
+                self.record_deleted_doc_id(docid as usize);
+                deleted_doc_count += 1;
             }
         }
-        let _ = index_mut.delete_file.flush();
+        let _ = self.delete_file.flush();
+        self.sync_after_write();
+        deleted_doc_count
     }
 }
 
@@ -2376,12 +3268,28 @@ pub trait DeleteDocumentsByQuery {
         field_filter: Vec<String>,
         facet_filter: Vec<FacetFilter>,
         result_sort: Vec<ResultSort>,
-    );
+    ) -> usize;
+
+    /// Dry run of delete_documents_by_query with identical parameters:
+    /// returns the document ids of the documents which would be deleted, without deleting them.
+    async fn delete_documents_by_query_dry_run(
+        &self,
+        query_string: String,
+        query_type_default: QueryType,
+        offset: usize,
+        length: usize,
+        include_uncommited: bool,
+        field_filter: Vec<String>,
+        facet_filter: Vec<FacetFilter>,
+        result_sort: Vec<ResultSort>,
+    ) -> Vec<u64>;
 }
 
 /// Delete documents from index by query
 /// Delete and search have identical parameters.
 /// It is recommended to test with search prior to delete to verify that only those documents are returned that you really want to delete.
+/// Pinned and hidden documents of query rules (QueryRule::Curate) are ignored: only documents matching the query are deleted.
+/// Returns the number of documents which matched the query and were deleted, 0 if the index is frozen.
 impl DeleteDocumentsByQuery for IndexArc {
     async fn delete_documents_by_query(
         &self,
@@ -2393,9 +3301,35 @@ impl DeleteDocumentsByQuery for IndexArc {
         field_filter: Vec<String>,
         facet_filter: Vec<FacetFilter>,
         result_sort: Vec<ResultSort>,
-    ) {
+    ) -> usize {
+        let document_id_vec = self
+            .delete_documents_by_query_dry_run(
+                query_string,
+                query_type_default,
+                offset,
+                length,
+                include_uncommited,
+                field_filter,
+                facet_filter,
+                result_sort,
+            )
+            .await;
+        self.write().await.delete_doc_ids(document_id_vec)
+    }
+
+    async fn delete_documents_by_query_dry_run(
+        &self,
+        query_string: String,
+        query_type_default: QueryType,
+        offset: usize,
+        length: usize,
+        include_uncommited: bool,
+        field_filter: Vec<String>,
+        facet_filter: Vec<FacetFilter>,
+        result_sort: Vec<ResultSort>,
+    ) -> Vec<u64> {
         let rlo = self
-            .search(
+            .search_from(
                 query_string.to_owned(),
                 query_type_default,
                 offset,
@@ -2406,15 +3340,19 @@ impl DeleteDocumentsByQuery for IndexArc {
                 Vec::new(),
                 facet_filter,
                 result_sort,
+                None,
+                None,
+                None,
+                QueryExpansion::default(),
+                0,
+                None,
             )
             .await;
 
-        let document_id_vec: Vec<u64> = rlo
-            .results
+        rlo.results
             .iter()
             .map(|result| result.doc_id as u64)
-            .collect();
-        self.delete_documents(document_id_vec).await;
+            .collect()
     }
 }
 
@@ -2432,6 +3370,9 @@ pub trait UpdateDocument {
 impl UpdateDocument for IndexArc {
     async fn update_document(&self, id_document: (u64, Document)) {
         self.delete_document(id_document.0).await;
+        self.write()
+            .await
+            .record_updated_doc_ids(std::slice::from_ref(&id_document.0));
         self.index_document(id_document.1, FileType::None).await;
     }
 }
@@ -2450,7 +3391,8 @@ pub trait UpdateDocuments {
 impl UpdateDocuments for IndexArc {
     async fn update_documents(&self, id_document_vec: Vec<(u64, Document)>) {
         let (docid_vec, document_vec): (Vec<_>, Vec<_>) = id_document_vec.into_iter().unzip();
-        self.delete_documents(docid_vec).await;
+        self.delete_documents(docid_vec.clone()).await;
+        self.write().await.record_updated_doc_ids(&docid_vec);
         self.index_documents(document_vec).await;
     }
 }
@@ -2479,139 +3421,233 @@ pub trait IndexDocument {
 impl IndexDocument for IndexArc {
     /// Index document
     /// May block, if the threshold of documents indexed in parallel is exceeded.
+    /// Has no effect if the index is frozen, or if the document is invalid with IndexMetaObject.strict_schema.
     async fn index_document(&self, document: Document, file: FileType) {
-        let index_arc_clone = self.clone();
-        let index_ref = self.read().await;
-        let schema = index_ref.indexed_schema_vec.clone();
-        let enable_bigram = index_ref.enable_bigram;
-        let indexed_field_vec_len = index_ref.indexed_field_vec.len();
-        let tokenizer_type = index_ref.meta.tokenizer;
-        let segment_number_mask1 = index_ref.segment_number_mask1;
-        let index_permits = index_ref.permits.clone();
-        drop(index_ref);
-
-        let permit_thread = index_permits.clone().acquire_owned().await.unwrap();
-
-        tokio::spawn(async move {
-            let token_per_field_max: u32 = u16::MAX as u32;
-            let mut unique_terms: AHashMap<String, TermObject> = AHashMap::new();
-            let mut field_vec: Vec<(usize, u8, u32, u32)> = Vec::new();
-            let index_ref2 = index_arc_clone.read().await;
+        index_document_notify(self, document, file, None).await;
+    }
+}
 
-            for schema_field in schema.iter() {
-                if !schema_field.indexed {
-                    continue;
+/// Indexes a document in parallel, as index_document. When the document is indexed, its document id is sent to the doc_id_sender, if any.
+/// If the index is frozen, or the document is invalid with IndexMetaObject.strict_schema, the document is not indexed and the doc_id_sender is dropped.
+pub(crate) async fn index_document_notify(
+    index_arc: &IndexArc,
+    document: Document,
+    file: FileType,
+    doc_id_sender: Option<oneshot::Sender<usize>>,
+) {
+    let index_arc_clone = index_arc.clone();
+    let index_ref = index_arc.read().await;
+    if index_ref.frozen {
+        return;
+    }
+    let mut document = document;
+    index_ref.apply_field_rules(&mut document);
+    if index_ref.meta.strict_schema && index_ref.validate_document(&document).is_err() {
+        return;
+    }
+    let schema = index_ref.indexed_schema_vec.clone();
+    let enable_bigram = index_ref.enable_bigram;
+    let indexed_field_vec_len = index_ref.indexed_field_vec.len();
+    let tokenizer_type = index_ref.meta.tokenizer;
+    let stemmer_type = index_ref.meta.stemmer.clone();
+    let index_unstemmed = index_ref.is_unstemmed_indexed();
+    let language_field_stored = match &stemmer_type {
+        StemmerType::Detect { language_field, .. } => {
+            index_ref.schema_map.contains_key(language_field)
+        }
+        _ => false,
+    };
+    let segment_number_mask1 = index_ref.segment_number_mask1;
+    let index_permits = index_ref.permits.clone();
+    drop(index_ref);
+
+    let permit_thread = index_permits.clone().acquire_owned().await.unwrap();
+
+    tokio::spawn(async move {
+        let mut document = document;
+        let language = document_language(&stemmer_type, &schema, &document);
+        if let StemmerType::Detect { language_field, .. } = &stemmer_type {
+            if language_field_stored {
+                if let Some(language) = language {
+                    document.insert(language_field.clone(), serde_json::json!(language));
                 }
+            }
+        }
+
+        let token_per_field_max: u32 = u16::MAX as u32;
+        let mut unique_terms: AHashMap<String, TermObject> = AHashMap::new();
+        let mut field_vec: Vec<(usize, u8, u32, u32)> = Vec::new();
+        let index_ref2 = index_arc_clone.read().await;
 
-                let field_name = &schema_field.field;
+        for schema_field in schema.iter() {
+            if !schema_field.indexed {
+                continue;
+            }
 
-                if let Some(field_value) = document.get(field_name) {
-                    let mut non_unique_terms: Vec<NonUniqueTermObject> = Vec::new();
-                    let mut nonunique_terms_count = 0u32;
+            let field_name = &schema_field.field;
 
-                    let text = match schema_field.field_type {
-                        FieldType::Text | FieldType::String => {
-                            serde_json::from_str(&field_value.to_string())
-                                .unwrap_or(field_value.to_string())
-                                .to_string()
-                        }
-                        _ => field_value.to_string(),
-                    };
+            if let Some(field_value) = document.get(field_name) {
+                let mut non_unique_terms: Vec<NonUniqueTermObject> = Vec::new();
+                let mut nonunique_terms_count = 0u32;
 
-                    let mut query_type_mut = QueryType::Union;
+                let text = match schema_field.field_type {
+                    FieldType::Text | FieldType::String => {
+                        serde_json::from_str(&field_value.to_string())
+                            .unwrap_or(field_value.to_string())
+                            .to_string()
+                    }
+                    _ => field_value.to_string(),
+                };
+
+                let mut query_type_mut = QueryType::Union;
+
+                tokenizer(
+                    &index_ref2,
+                    &text,
+                    &mut unique_terms,
+                    &mut non_unique_terms,
+                    tokenizer_type,
+                    segment_number_mask1,
+                    &mut nonunique_terms_count,
+                    token_per_field_max,
+                    MAX_POSITIONS_PER_TERM,
+                    false,
+                    &mut query_type_mut,
+                    enable_bigram,
+                    schema_field.indexed_field_id,
+                    indexed_field_vec_len,
+                    language,
+                );
 
-                    tokenizer(
+                if index_unstemmed {
+                    add_unstemmed_terms(
                         &index_ref2,
                         &text,
                         &mut unique_terms,
-                        &mut non_unique_terms,
                         tokenizer_type,
                         segment_number_mask1,
-                        &mut nonunique_terms_count,
                         token_per_field_max,
-                        MAX_POSITIONS_PER_TERM,
-                        false,
-                        &mut query_type_mut,
-                        enable_bigram,
                         schema_field.indexed_field_id,
                         indexed_field_vec_len,
                     );
-
-                    let document_length_compressed: u8 = norm_frequency(nonunique_terms_count);
-                    let document_length_normalized: u32 =
-                        DOCUMENT_LENGTH_COMPRESSION[document_length_compressed as usize];
-                    field_vec.push((
-                        schema_field.indexed_field_id,
-                        document_length_compressed,
-                        document_length_normalized,
-                        nonunique_terms_count,
-                    ));
                 }
+
+                let document_length_compressed: u8 = norm_frequency(nonunique_terms_count);
+                let document_length_normalized: u32 =
+                    DOCUMENT_LENGTH_COMPRESSION[document_length_compressed as usize];
+                field_vec.push((
+                    schema_field.indexed_field_id,
+                    document_length_compressed,
+                    document_length_normalized,
+                    nonunique_terms_count,
+                ));
             }
-            drop(index_ref2);
-
-            let bigrams: Vec<String> = unique_terms
-                .iter()
-                .filter(|term| term.1.is_bigram)
-                .map(|term| term.1.term.clone())
-                .collect();
-
-            for term in bigrams.iter() {
-                let bigram = unique_terms.get(term).unwrap();
-                let term_bigram1 = bigram.term_bigram1.clone();
-                let term_bigram2 = bigram.term_bigram2.clone();
-
-                for indexed_field_id in 0..indexed_field_vec_len {
-                    let positions_count_bigram1 =
-                        unique_terms[&term_bigram1].field_positions_vec[indexed_field_id].len();
-                    let positions_count_bigram2 =
-                        unique_terms[&term_bigram2].field_positions_vec[indexed_field_id].len();
-                    let bigram = unique_terms.get_mut(term).unwrap();
-
-                    if positions_count_bigram1 > 0 {
-                        bigram
-                            .field_vec_bigram1
-                            .push((indexed_field_id, positions_count_bigram1 as u32));
-                    }
-                    if positions_count_bigram2 > 0 {
-                        bigram
-                            .field_vec_bigram2
-                            .push((indexed_field_id, positions_count_bigram2 as u32));
-                    }
+        }
+        drop(index_ref2);
+
+        let bigrams: Vec<String> = unique_terms
+            .iter()
+            .filter(|term| term.1.is_bigram)
+            .map(|term| term.1.term.clone())
+            .collect();
+
+        for term in bigrams.iter() {
+            let bigram = unique_terms.get(term).unwrap();
+            let term_bigram1 = bigram.term_bigram1.clone();
+            let term_bigram2 = bigram.term_bigram2.clone();
+
+            for indexed_field_id in 0..indexed_field_vec_len {
+                let positions_count_bigram1 =
+                    unique_terms[&term_bigram1].field_positions_vec[indexed_field_id].len();
+                let positions_count_bigram2 =
+                    unique_terms[&term_bigram2].field_positions_vec[indexed_field_id].len();
+                let bigram = unique_terms.get_mut(term).unwrap();
+
+                if positions_count_bigram1 > 0 {
+                    bigram
+                        .field_vec_bigram1
+                        .push((indexed_field_id, positions_count_bigram1 as u32));
+                }
+                if positions_count_bigram2 > 0 {
+                    bigram
+                        .field_vec_bigram2
+                        .push((indexed_field_id, positions_count_bigram2 as u32));
                 }
             }
+        }
 
-            let document_item = DocumentItem {
-                document,
-                unique_terms,
-                field_vec,
-            };
+        let document_item = DocumentItem {
+            document,
+            unique_terms,
+            field_vec,
+        };
+
+        let doc_id = index_arc_clone.index_document_2(document_item, file).await;
+        if let Some(doc_id_sender) = doc_id_sender {
+            let _ = doc_id_sender.send(doc_id);
+        }
+
+        drop(permit_thread);
+    });
+}
 
-            index_arc_clone.index_document_2(document_item, file).await;
+/// Language of a document for stemming: the language of the index, or the language stored in the language_field, or the detected language, with fallback to the default_language
+pub(crate) fn document_language(
+    stemmer_type: &StemmerType,
+    schema: &[SchemaField],
+    document: &Document,
+) -> Option<Language> {
+    match stemmer_type {
+        StemmerType::None => None,
+        StemmerType::Language(language) => Some(*language),
+        StemmerType::Detect {
+            default_language,
+            language_field,
+        } => document
+            .get(language_field)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .or_else(|| detect_language(&document_text(schema, document)))
+            .or(*default_language),
+    }
+}
 
-            drop(permit_thread);
-        });
+/// Concatenated text of the indexed text fields of a document, used for language detection
+fn document_text(schema: &[SchemaField], document: &Document) -> String {
+    let mut text = String::new();
+    for schema_field in schema.iter() {
+        if schema_field.indexed && schema_field.field_type == FieldType::Text {
+            if let Some(serde_json::Value::String(value)) = document.get(&schema_field.field) {
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text.push_str(value);
+            }
+        }
     }
+    text
 }
 
 #[allow(async_fn_in_trait)]
 pub(crate) trait IndexDocument2 {
-    async fn index_document_2(&self, document_item: DocumentItem, file: FileType);
+    async fn index_document_2(&self, document_item: DocumentItem, file: FileType) -> usize;
 }
 
 impl IndexDocument2 for IndexArc {
-    async fn index_document_2(&self, document_item: DocumentItem, file: FileType) {
+    async fn index_document_2(&self, document_item: DocumentItem, file: FileType) -> usize {
         let mut index_mut = self.write().await;
 
         let doc_id: usize = index_mut.indexed_doc_count;
         index_mut.indexed_doc_count += 1;
 
         let do_commit = index_mut.block_id != doc_id >> 16;
-        if do_commit {
-            index_mut.commit(doc_id);
+        let pending_commit = if do_commit {
+            let pending_commit = index_mut.commit_level(doc_id);
 
             index_mut.block_id = doc_id >> 16;
-        }
+            pending_commit
+        } else {
+            None
+        };
 
         if !index_mut.facets.is_empty() {
             let facets_size_sum = index_mut.facets_size_sum;
@@ -2833,11 +3869,66 @@ impl IndexDocument2 for IndexArc {
 
                             write_f64(value, &mut index_mut.facets_file_mmap, address)
                         }
-                        FieldType::String => {
+                        FieldType::Decimal => {
+                            let value =
+                                decimal_from_json(field_value, facet.scale).unwrap_or_default();
+                            match (&facet.min, &facet.max) {
+                                (ValueType::Decimal(min), ValueType::Decimal(max)) => {
+                                    if value < *min {
+                                        facet.min = ValueType::Decimal(value);
+                                    }
+                                    if value > *max {
+                                        facet.max = ValueType::Decimal(value);
+                                    }
+                                }
+                                (ValueType::None, ValueType::None) => {
+                                    facet.min = ValueType::Decimal(value);
+                                    facet.max = ValueType::Decimal(value);
+                                }
+                                _ => {}
+                            }
+
+                            write_i64(value, &mut index_mut.facets_file_mmap, address);
+                        }
+                        FieldType::Ip => {
+                            let value = field_value.as_str().and_then(parse_ip).unwrap_or_default();
+                            write_u128(value, &mut index_mut.facets_file_mmap, address);
+                        }
+                        FieldType::Date => {
+                            let value = date_from_json(field_value, &facet.date_formats)
+                                .unwrap_or_default();
+                            match (&facet.min, &facet.max) {
+                                (ValueType::Date(min), ValueType::Date(max)) => {
+                                    if value < *min {
+                                        facet.min = ValueType::Date(value);
+                                    }
+                                    if value > *max {
+                                        facet.max = ValueType::Date(value);
+                                    }
+                                }
+                                (ValueType::None, ValueType::None) => {
+                                    facet.min = ValueType::Date(value);
+                                    facet.max = ValueType::Date(value);
+                                }
+                                _ => {}
+                            }
+
+                            write_i64(value, &mut index_mut.facets_file_mmap, address);
+                        }
+                        FieldType::String | FieldType::Keyword => {
                             if facet.values.len() < u16::MAX as usize {
-                                let key = serde_json::from_str(&field_value.to_string())
-                                    .unwrap_or(field_value.to_string())
-                                    .to_string();
+                                // of a multi-valued String or Keyword field, the first value is used for filtering, sorting and facets
+                                let field_value = match field_value {
+                                    serde_json::Value::Array(values) => {
+                                        values.first().unwrap_or(&serde_json::Value::Null)
+                                    }
+                                    _ => field_value,
+                                };
+                                let key = normalize_keyword(
+                                    &serde_json::from_str(&field_value.to_string())
+                                        .unwrap_or(field_value.to_string()),
+                                    &facet.normalizers,
+                                );
 
                                 let key_string = key.clone();
                                 let key = vec![key];
@@ -2852,49 +3943,65 @@ impl IndexDocument2 for IndexArc {
 
                         FieldType::StringSet => {
                             if facet.values.len() < u16::MAX as usize {
-                                let mut key: Vec<String> =
-                                    serde_json::from_value(field_value.clone()).unwrap();
+                                let mut key = string_set_from_json(field_value);
                                 key.sort();
-
-                                let key_string = key.join("_");
-                                facet.values.entry(key_string.clone()).or_insert((key, 0)).1 += 1;
-
-                                let facet_value_id =
-                                    facet.values.get_index_of(&key_string).unwrap() as u16;
+                                key.dedup();
+
+                                // the JSON array is an unambiguous key, unlike joined values: ["a_b"] and ["a","b"] are different sets
+                                let key_string = serde_json::to_string(&key).unwrap();
+                                let values_len = facet.values.len();
+                                let entry = facet.values.entry(key_string);
+                                let facet_value_id = entry.index() as u16;
+                                entry.or_insert((key.clone(), 0)).1 += 1;
+                                let is_new_value = facet.values.len() > values_len;
+
+                                // the single values of a new set are immediately filterable, also before the commit
+                                if is_new_value {
+                                    for term in key {
+                                        index_mut.string_set_to_single_term_id_vec[i]
+                                            .entry(term)
+                                            .or_default()
+                                            .insert(facet_value_id);
+                                    }
+                                }
                                 write_u16(facet_value_id, &mut index_mut.facets_file_mmap, address)
                             }
                         }
                         FieldType::Point => {
-                            if let Ok(point) = serde_json::from_value::<Point>(field_value.clone())
-                            {
-                                if point.len() == 2 {
-                                    if point[0] >= -90.0
-                                        && point[0] <= 90.0
-                                        && point[1] >= -180.0
-                                        && point[1] <= 180.0
-                                    {
-                                        let morton_code = encode_morton_2_d(&point);
-                                        write_u64(
-                                            morton_code,
-                                            &mut index_mut.facets_file_mmap,
-                                            address,
-                                        )
-                                    } else {
-                                        println!(
-                                            "outside valid coordinate range: {} {}",
-                                            point[0], point[1]
-                                        );
-                                    }
-                                }
+                            // of a multi-valued Point field, the first point is used for filtering, sorting and facets
+                            if let Some(points) = points_from_json(field_value) {
+                                let morton_code = encode_morton_2_d(&points[0]);
+                                write_u64(morton_code, &mut index_mut.facets_file_mmap, address)
+                            } else {
+                                println!(
+                                    "invalid point or outside valid coordinate range: {}",
+                                    field_value
+                                );
                             }
                         }
 
                         _ => {}
                     };
+
+                    if let Some(facets_presence_offset) = index_mut.facets_presence_offset {
+                        if !field_value.is_null() {
+                            index_mut.facets_file_mmap
+                                [(facets_size_sum * doc_id) + facets_presence_offset + (i >> 3)] |=
+                                1 << (i & 7);
+                        }
+                    }
                 }
             }
         }
 
+        if !index_mut.geo_shapes.is_empty() {
+            index_mut.index_geo_shapes(&document_item.document, doc_id);
+        }
+
+        if !index_mut.joins.is_empty() {
+            index_mut.index_joins(&document_item.document, doc_id);
+        }
+
         if !index_mut.uncommitted {
             for strip0 in index_mut.segments_level0.iter_mut() {
                 strip0.positions_compressed = vec![0; MAX_POSITIONS_PER_TERM * 2];
@@ -2934,6 +4041,15 @@ impl IndexDocument2 for IndexArc {
             let unique_terms_clone = unique_terms.clone();
             for term in unique_terms_clone.iter() {
                 if !term.1.is_bigram {
+                    if index_mut.synonym_targets.contains(&term.1.key_hash) {
+                        let unexpanded_term = prefixed_term(
+                            UNEXPANDED_TERM_PREFIX,
+                            term.1,
+                            index_mut.segment_number_mask1,
+                        );
+                        unique_terms.insert(unexpanded_term.term.clone(), unexpanded_term);
+                    }
+
                     let synonym = index_mut.synonyms_map.get(&term.1.key_hash).cloned();
                     if let Some(synonym) = synonym {
                         for synonym_term in synonym {
@@ -2985,9 +4101,11 @@ impl IndexDocument2 for IndexArc {
         }
 
         if do_commit {
-            drop(index_mut);
-            warmup(self).await;
+            finish_commit(self, index_mut, pending_commit).await;
+            spawn_compaction_if_due(self).await;
         }
+
+        doc_id
     }
 }
 
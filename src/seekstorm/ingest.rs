@@ -2,7 +2,7 @@ use std::{
     collections::HashMap,
     ffi::OsStr,
     fs::{self, metadata, File},
-    io::{self, BufReader, Read},
+    io::{self, BufRead, BufReader, Read},
     path::Path,
     sync::Arc,
     time::{Instant, SystemTime},
@@ -12,13 +12,13 @@ use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use colored::Colorize;
 use num_format::{Locale, ToFormattedString};
 use pdfium_render::prelude::{PdfDocumentMetadataTagType, Pdfium};
-use serde_json::{json, Deserializer};
+use serde_json::{json, Deserializer, Value};
 use tokio::sync::RwLock;
 use walkdir::WalkDir;
 
 use crate::{
     commit::Commit,
-    index::{Document, FileType, Index, IndexArc, IndexDocument},
+    index::{Document, FieldType, FileType, Index, IndexArc, IndexDocument, SchemaField},
     utils::truncate,
 };
 
@@ -379,7 +379,9 @@ impl IngestPdf for IndexArc {
                         path_recurse(self, data_path, &mut docid).await;
                     }
 
-                    self.commit().await;
+                    if let Err(e) = self.commit().await {
+                        println!("{} {}", "Compaction error:".bright_red(), e);
+                    }
 
                     let elapsed_time = start_time.elapsed().as_nanos();
 
@@ -473,7 +475,202 @@ impl IngestJson for IndexArc {
                     }
                 }
 
-                self.commit().await;
+                if let Err(e) = self.commit().await {
+                    println!("{} {}", "Compaction error:".bright_red(), e);
+                }
+
+                let elapsed_time = start_time.elapsed().as_nanos();
+
+                println!(
+                    "{}: docs {}  docs/sec {}  docs/day {} minutes {:.2} seconds {}",
+                    "Indexing finished".green(),
+                    docid.to_formatted_string(&Locale::en),
+                    (docid as u128 * 1_000_000_000 / elapsed_time).to_formatted_string(&Locale::en),
+                    ((docid as u128 * 1_000_000_000 / elapsed_time) * 3600 * 24)
+                        .to_formatted_string(&Locale::en),
+                    elapsed_time as f64 / 1_000_000_000.0 / 60.0,
+                    elapsed_time / 1_000_000_000
+                );
+            }
+            false => {
+                println!("data file not found: {}", data_path.display());
+            }
+        }
+    }
+}
+
+/// Streaming reader for [CSV](https://en.wikipedia.org/wiki/Comma-separated_values) files, returning one document per record.
+/// - the first record is expected to contain the field names
+/// - quoted values may contain delimiters, double quotes ("") and line breaks
+/// - values of fields which are not of type Text/String in the schema are converted to JSON numbers, booleans or points
+/// - empty values are skipped
+pub struct CsvDocumentReader<R: BufRead> {
+    reader: R,
+    field_names: Vec<String>,
+    field_types: Vec<Option<FieldType>>,
+}
+
+impl<R: BufRead> CsvDocumentReader<R> {
+    /// Creates a new CSV document reader. The first record is read as header with the field names.
+    pub fn new(mut reader: R, schema_map: &HashMap<String, SchemaField>) -> Result<Self, String> {
+        let field_names = match read_csv_record(&mut reader)? {
+            Some(field_names) => field_names,
+            None => return Err("CSV header missing".to_string()),
+        };
+        let field_types = field_names
+            .iter()
+            .map(|field_name| {
+                schema_map
+                    .get(field_name)
+                    .map(|schema_field| schema_field.field_type.clone())
+            })
+            .collect();
+
+        Ok(CsvDocumentReader {
+            reader,
+            field_names,
+            field_types,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for CsvDocumentReader<R> {
+    type Item = Result<Document, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let values = match read_csv_record(&mut self.reader) {
+            Ok(Some(values)) => values,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let mut document = Document::new();
+        for ((field_name, field_type), value) in self
+            .field_names
+            .iter()
+            .zip(self.field_types.iter())
+            .zip(values)
+        {
+            if value.is_empty() {
+                continue;
+            }
+
+            let value = match field_type {
+                None
+                | Some(FieldType::Text)
+                | Some(FieldType::String)
+                | Some(FieldType::Keyword) => {
+                    json!(value)
+                }
+                Some(FieldType::StringSet) => json!([value]),
+                Some(FieldType::Point) => {
+                    match serde_json::from_str::<Value>(&format!("[{}]", value)) {
+                        Ok(point) => point,
+                        Err(_) => json!(value),
+                    }
+                }
+                Some(_) => serde_json::from_str::<Value>(&value).unwrap_or(json!(value)),
+            };
+            document.insert(field_name.clone(), value);
+        }
+
+        Some(Ok(document))
+    }
+}
+
+/// Reads a single CSV record, which may span multiple lines if a quoted value contains line breaks.
+fn read_csv_record(reader: &mut impl BufRead) -> Result<Option<Vec<String>>, String> {
+    let mut record = String::new();
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => {
+                if record.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            Ok(_) => {
+                record.push_str(&line);
+                if record.matches('"').count() & 1 == 0 {
+                    if record.trim_end_matches(['\r', '\n']).is_empty() {
+                        record.clear();
+                        continue;
+                    }
+                    break;
+                }
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let record = record.trim_end_matches(['\r', '\n']);
+    let mut values = Vec::new();
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                value.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => values.push(std::mem::take(&mut value)),
+            _ => value.push(char),
+        }
+    }
+    values.push(value);
+
+    Ok(Some(values))
+}
+
+/// Ingest local data files in [CSV](https://en.wikipedia.org/wiki/Comma-separated_values) format via console command.  
+/// The first line is expected to contain the field names, values are converted according to the field types of the index schema.  
+/// The document ingestion is streamed without loading the whole document vector into memory to allow for unlimited file size while keeping RAM consumption low.
+#[allow(async_fn_in_trait)]
+pub trait IngestCsv {
+    async fn ingest_csv(&mut self, data_path: &Path);
+}
+
+impl IngestCsv for IndexArc {
+    /// Ingest local data files in [CSV](https://en.wikipedia.org/wiki/Comma-separated_values) format via console command.  
+    /// The first line is expected to contain the field names, values are converted according to the field types of the index schema.  
+    /// The document ingestion is streamed without loading the whole document vector into memory to allow for unlimited file size while keeping RAM consumption low.
+    async fn ingest_csv(&mut self, data_path: &Path) {
+        match data_path.exists() {
+            true => {
+                println!("ingesting data from: {}", data_path.display());
+
+                let start_time = Instant::now();
+                let mut docid: i64 = 0;
+
+                let schema_map = self.read().await.schema_map.clone();
+                let file = File::open(data_path).unwrap();
+                let csv_reader = match CsvDocumentReader::new(BufReader::new(file), &schema_map) {
+                    Ok(csv_reader) => csv_reader,
+                    Err(e) => {
+                        println!("{} {}", "CSV error:".bright_red(), e);
+                        return;
+                    }
+                };
+
+                for doc_object in csv_reader {
+                    match doc_object {
+                        Ok(doc_object) => {
+                            self.index_document(doc_object, FileType::None).await;
+                            docid += 1;
+                        }
+                        Err(e) => {
+                            println!("{} {}", "CSV error:".bright_red(), e);
+                            break;
+                        }
+                    }
+                }
+
+                if let Err(e) = self.commit().await {
+                    println!("{} {}", "Compaction error:".bright_red(), e);
+                }
 
                 let elapsed_time = start_time.elapsed().as_nanos();
 
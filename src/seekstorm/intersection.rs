@@ -1970,6 +1970,9 @@ pub(crate) async fn intersection_blockid<'a>(
     let mut block_vec: Vec<BlockObject> = Vec::new();
 
     'exit: loop {
+        if search_result.timed_out() {
+            break;
+        }
         match block_id1.cmp(&block_id2) {
             std::cmp::Ordering::Less => {
                 let item_1 = &mut query_list[t1 as usize];
@@ -2138,6 +2141,9 @@ pub(crate) async fn intersection_blockid<'a>(
     if SORT_FLAG && SPEEDUP_FLAG && (result_type != &ResultType::Count) {
         block_vec.sort_unstable_by(|x, y| y.block_score.partial_cmp(&x.block_score).unwrap());
         for block in block_vec {
+            if search_result.timed_out() {
+                break;
+            }
             if (result_type == &ResultType::Topk)
                 && search_result.topk_candidates.result_sort.is_empty()
                 && (search_result.topk_candidates.current_heap_size == top_k)
@@ -0,0 +1,37 @@
+use std::{
+    net::{IpAddr, Ipv6Addr},
+    ops::RangeInclusive,
+};
+
+/// Parses an IPv4 or IPv6 address into a u128. IPv4 addresses are mapped into the IPv6 address space (::ffff:a.b.c.d),
+/// so that IPv4 and IPv6 addresses can be stored, filtered and sorted in the same field.
+pub(crate) fn parse_ip(text: &str) -> Option<u128> {
+    match text.trim().parse::<IpAddr>().ok()? {
+        IpAddr::V4(ip) => Some(u128::from(ip.to_ipv6_mapped())),
+        IpAddr::V6(ip) => Some(u128::from(ip)),
+    }
+}
+
+/// Parses an IP address or a CIDR subnet (e.g. "10.0.0.0/8", "2001:db8::/32") into the inclusive range of contained addresses.
+/// A single IP address matches exactly.
+pub(crate) fn parse_cidr(text: &str) -> Option<RangeInclusive<u128>> {
+    let Some((ip, prefix_length)) = text.trim().split_once('/') else {
+        let ip = parse_ip(text)?;
+        return Some(ip..=ip);
+    };
+
+    let prefix_length: u32 = prefix_length.parse().ok()?;
+    let prefix_length = match ip.parse::<IpAddr>().ok()? {
+        IpAddr::V4(_) if prefix_length <= 32 => prefix_length + 96,
+        IpAddr::V6(_) if prefix_length <= 128 => prefix_length,
+        _ => return None,
+    };
+    let ip = parse_ip(ip)?;
+    let host_mask = u128::MAX.checked_shr(prefix_length).unwrap_or(0);
+    Some((ip & !host_mask)..=(ip | host_mask))
+}
+
+/// Formats an IP address stored as u128: IPv4-mapped addresses in IPv4 notation
+pub(crate) fn ip_to_string(ip: u128) -> String {
+    Ipv6Addr::from(ip).to_canonical().to_string()
+}
@@ -0,0 +1,472 @@
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    future::Future,
+    io::{BufRead, BufWriter, Write},
+    path::Path,
+    pin::Pin,
+};
+
+use ahash::{AHashMap, AHashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    highlighter::{highlighter_with_phrases, Highlight},
+    index::{Document, FieldType, Index, IndexArc, SchemaField},
+    scroll::Scroll,
+    search::{FacetFilter, FilterSparse, QueryType, ResultType, Search, SearchRequest},
+    snapshot::Snapshot,
+};
+
+pub(crate) const JOINS_FILENAME: &str = "joins.ndjson";
+
+/// Number of documents per scroll batch, when collecting the documents matching the query of a JoinFilter
+const JOIN_BATCH_SIZE: usize = 10_000;
+
+/// Value of a Join field: the relation of the document within the index, e.g. `{"name":"question","key":"q1"}` for a parent document
+/// and `{"name":"answer","parent":"q1"}` for its child documents. A document can be both parent and child, e.g. a comment of an answer.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct JoinValue {
+    /// Relation name of the document, e.g. question or answer
+    pub name: String,
+    /// Key of the document, referenced by the parent property of its child documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Key of the parent document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+impl JoinValue {
+    /// Checks that the relation name and the keys are not empty
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("join name is empty".to_string());
+        }
+        if self.key.as_ref().is_some_and(String::is_empty)
+            || self.parent.as_ref().is_some_and(String::is_empty)
+        {
+            return Err("join key or parent is empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn default_query_type() -> QueryType {
+    QueryType::Intersection
+}
+
+/// Filter of a Join field (FacetFilter::HasChild, FacetFilter::HasParent): the documents of the relation matching the query and facet filter,
+/// e.g. `{"relation":"answer","query":"borrow checker"}`. An empty query matches all documents of the relation.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct JoinFilter {
+    /// Relation name of the related documents
+    pub relation: String,
+    #[serde(default)]
+    pub query: String,
+    #[serde(default = "default_query_type")]
+    pub query_type_default: QueryType,
+    /// Searched fields of the query, empty for all indexed fields
+    #[serde(default)]
+    pub field_filter: Vec<String>,
+    #[serde(default)]
+    pub facet_filter: Vec<FacetFilter>,
+    /// Returns the related documents which caused the match of a result, see inner_hits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_hits: Option<InnerHits>,
+}
+
+fn default_inner_hits_length() -> usize {
+    3
+}
+
+/// Inner hits of a JoinFilter: the related documents of each result which matched the query of the filter,
+/// e.g. the answers which made a question match, to show "matched because of this answer".
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct InnerHits {
+    /// Maximum number of inner hits per result, in document id order. Default: 3
+    #[serde(default = "default_inner_hits_length")]
+    pub length: usize,
+    /// Returned fields of the inner hits, empty for all stored fields
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// Fragments and highlighting of the inner hits, with the query terms of the join filter
+    #[serde(default)]
+    pub highlights: Vec<Highlight>,
+}
+
+/// Join value of a document, as line of the joins.ndjson file
+#[derive(Deserialize, Serialize)]
+struct JoinEntry {
+    field: String,
+    doc_id: usize,
+    value: JoinValue,
+}
+
+/// Join values of a Join field, with the documents per key and the child documents per parent key.
+/// Updated documents keep their previous entries under the old document id, which are skipped by search as deleted documents.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct JoinIndex {
+    values: Vec<(usize, JoinValue)>,
+    /// position in values per document id
+    doc_values: AHashMap<usize, usize>,
+    keys: AHashMap<String, Vec<usize>>,
+    children: AHashMap<String, Vec<usize>>,
+    /// number of values persisted in joins.ndjson
+    committed: usize,
+}
+
+impl JoinIndex {
+    fn insert(&mut self, doc_id: usize, value: JoinValue) {
+        if let Some(key) = value.key.as_ref() {
+            self.keys.entry(key.clone()).or_default().push(doc_id);
+        }
+        if let Some(parent) = value.parent.as_ref() {
+            self.children
+                .entry(parent.clone())
+                .or_default()
+                .push(doc_id);
+        }
+        self.doc_values.insert(doc_id, self.values.len());
+        self.values.push((doc_id, value));
+    }
+
+    fn value(&self, doc_id: usize) -> Option<&JoinValue> {
+        self.doc_values
+            .get(&doc_id)
+            .map(|position| &self.values[*position].1)
+    }
+
+    /// Document ids of the relation
+    fn relation_doc_ids(&self, relation: &str) -> Vec<usize> {
+        self.values
+            .iter()
+            .filter(|(_, value)| value.name == relation)
+            .map(|(doc_id, _)| *doc_id)
+            .collect()
+    }
+
+    /// Related documents of a result of a join filter: the child documents for HasChild, the parent documents for HasParent
+    fn related_doc_ids(&self, doc_id: usize, is_child: bool) -> &[usize] {
+        let related = match self.value(doc_id) {
+            Some(JoinValue { key: Some(key), .. }) if is_child => self.children.get(key),
+            Some(JoinValue {
+                parent: Some(parent),
+                ..
+            }) if !is_child => self.keys.get(parent),
+            _ => None,
+        };
+        related.map_or(&[], |doc_ids| doc_ids.as_slice())
+    }
+
+    /// Parent documents of the child documents of the relation
+    pub(crate) fn has_child(&self, relation: &str, doc_ids: &[usize]) -> AHashSet<usize> {
+        let mut parent_doc_ids = AHashSet::new();
+        for doc_id in doc_ids {
+            if let Some(JoinValue {
+                name,
+                parent: Some(parent),
+                ..
+            }) = self.value(*doc_id)
+            {
+                if name == relation {
+                    if let Some(doc_ids) = self.keys.get(parent) {
+                        parent_doc_ids.extend(doc_ids);
+                    }
+                }
+            }
+        }
+        parent_doc_ids
+    }
+
+    /// Child documents of the parent documents of the relation
+    pub(crate) fn has_parent(&self, relation: &str, doc_ids: &[usize]) -> AHashSet<usize> {
+        let mut child_doc_ids = AHashSet::new();
+        for doc_id in doc_ids {
+            if let Some(JoinValue {
+                name,
+                key: Some(key),
+                ..
+            }) = self.value(*doc_id)
+            {
+                if name == relation {
+                    if let Some(doc_ids) = self.children.get(key) {
+                        child_doc_ids.extend(doc_ids);
+                    }
+                }
+            }
+        }
+        child_doc_ids
+    }
+}
+
+/// Empty join indices of the Join fields of the schema
+pub(crate) fn new_joins(schema: &[SchemaField]) -> AHashMap<String, JoinIndex> {
+    schema
+        .iter()
+        .filter(|schema_field| schema_field.field_type == FieldType::Join)
+        .map(|schema_field| (schema_field.field.clone(), JoinIndex::default()))
+        .collect()
+}
+
+impl Index {
+    /// Loads the join values of the committed documents from joins.ndjson
+    pub(crate) fn load_joins(&mut self) {
+        if self.joins.is_empty() {
+            return;
+        }
+
+        let joins_path = Path::new(&self.index_path_string).join(JOINS_FILENAME);
+        let data = fs::read(&joins_path).unwrap_or_default();
+        for line in data.as_slice().lines().map_while(Result::ok) {
+            if let Ok(entry) = serde_json::from_str::<JoinEntry>(&line) {
+                if let Some(join_index) = self.joins.get_mut(&entry.field) {
+                    join_index.insert(entry.doc_id, entry.value);
+                }
+            }
+        }
+
+        for join_index in self.joins.values_mut() {
+            join_index.committed = join_index.values.len();
+        }
+    }
+
+    /// Removes all join values, e.g. by clear_index
+    pub(crate) fn clear_joins(&mut self) {
+        let _ = fs::remove_file(Path::new(&self.index_path_string).join(JOINS_FILENAME));
+        for join_index in self.joins.values_mut() {
+            *join_index = JoinIndex::default();
+        }
+    }
+
+    /// Adds the valid values of the Join fields of a document to the join indices
+    pub(crate) fn index_joins(&mut self, document: &Document, doc_id: usize) {
+        for (field, join_index) in self.joins.iter_mut() {
+            let Some(value) = document.get(field) else {
+                continue;
+            };
+            match serde_json::from_value::<JoinValue>(value.clone())
+                .map_err(|e| e.to_string())
+                .and_then(|join_value| join_value.validate().map(|_| join_value))
+            {
+                Ok(join_value) => join_index.insert(doc_id, join_value),
+                Err(e) => {
+                    if !value.is_null() {
+                        println!("invalid join value of field {}: {}", field, e)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Appends the join values of the newly committed documents to joins.ndjson
+    pub(crate) fn commit_joins(&mut self) -> Result<(), String> {
+        if self.joins.is_empty() {
+            return Ok(());
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Path::new(&self.index_path_string).join(JOINS_FILENAME))
+            .map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        for (field, join_index) in self.joins.iter_mut() {
+            for (doc_id, value) in join_index.values[join_index.committed..].iter() {
+                serde_json::to_writer(
+                    &mut writer,
+                    &JoinEntry {
+                        field: field.clone(),
+                        doc_id: *doc_id,
+                        value: value.clone(),
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+                writer.write_all(b"\n").map_err(|e| e.to_string())?;
+            }
+            join_index.committed = join_index.values.len();
+        }
+        writer.flush().map_err(|e| e.to_string())
+    }
+}
+
+/// Document ids of the snapshot matching the query and facet filter of a JoinFilter, collected by scrolling through all results
+async fn join_filter_doc_ids(
+    index_arc: &IndexArc,
+    filter: &JoinFilter,
+    include_uncommited: bool,
+    snapshot: &Snapshot,
+) -> Vec<usize> {
+    if filter.query.is_empty() {
+        let index_ref = index_arc.read().await;
+        return index_ref
+            .joins
+            .values()
+            .flat_map(|join_index| join_index.relation_doc_ids(&filter.relation))
+            .filter(|doc_id| {
+                snapshot.contains(*doc_id)
+                    && !index_ref.delete_hashset.contains(doc_id)
+                    && index_ref.is_facet_filter_match(*doc_id, &filter.facet_filter)
+            })
+            .collect();
+    }
+
+    let mut doc_ids = Vec::new();
+    let mut scroll_id = None;
+    loop {
+        let Ok(scroll_result) = index_arc
+            .scroll(
+                filter.query.clone(),
+                filter.query_type_default.clone(),
+                JOIN_BATCH_SIZE,
+                include_uncommited,
+                filter.field_filter.clone(),
+                filter.facet_filter.clone(),
+                scroll_id,
+            )
+            .await
+        else {
+            break;
+        };
+        doc_ids.extend(
+            scroll_result
+                .results
+                .into_iter()
+                .filter(|doc_id| snapshot.contains(*doc_id)),
+        );
+        scroll_id = scroll_result.scroll_id;
+        if scroll_id.is_none() {
+            break;
+        }
+    }
+    doc_ids
+}
+
+/// Resolves the HasChild and HasParent filters into the matching document ids, which are appended to the sparse facet filters by search.
+/// The queries of the join filters are searched before the search itself, without holding the index lock, as they may contain join filters themselves.
+/// Documents indexed after the snapshot of the search are excluded.
+pub(crate) fn join_filter_sparse<'a>(
+    index_arc: &'a IndexArc,
+    facet_filter: &'a [FacetFilter],
+    include_uncommited: bool,
+    snapshot: &'a Snapshot,
+) -> Pin<Box<dyn Future<Output = Vec<FilterSparse>> + Send + 'a>> {
+    Box::pin(async move {
+        let mut join_filter_sparse = Vec::new();
+        for facet_filter_item in facet_filter.iter() {
+            let (field, filter, is_child) = match facet_filter_item {
+                FacetFilter::HasChild { field, filter } => (field, filter, true),
+                FacetFilter::HasParent { field, filter } => (field, filter, false),
+                _ => continue,
+            };
+            if !index_arc.read().await.joins.contains_key(field) {
+                continue;
+            }
+
+            let doc_ids =
+                join_filter_doc_ids(index_arc, filter, include_uncommited, snapshot).await;
+            let index_ref = index_arc.read().await;
+            let Some(join_index) = index_ref.joins.get(field) else {
+                continue;
+            };
+            let mut join_doc_ids = if is_child {
+                join_index.has_child(&filter.relation, &doc_ids)
+            } else {
+                join_index.has_parent(&filter.relation, &doc_ids)
+            };
+            join_doc_ids.retain(|doc_id| snapshot.contains(*doc_id));
+            join_filter_sparse.push(FilterSparse::DocIds(join_doc_ids));
+        }
+        join_filter_sparse
+    })
+}
+
+/// Inner hits of the join filters with JoinFilter.inner_hits, per result document id: the related documents which matched the query of the filter,
+/// with their document id as `_id`, grouped by the relation of the filter, e.g. `{"answer":[{"_id":3,"body":"..."}]}`.
+/// The document of a result without inner hits is empty. Related documents indexed after the snapshot of the search (ResultObject.snapshot) are excluded.
+pub async fn inner_hits(
+    index_arc: &IndexArc,
+    facet_filter: &[FacetFilter],
+    doc_ids: &[usize],
+    include_uncommited: bool,
+    snapshot: &Snapshot,
+) -> Vec<Document> {
+    let mut inner_hits = vec![Document::new(); doc_ids.len()];
+    for facet_filter_item in facet_filter.iter() {
+        let (field, filter, is_child) = match facet_filter_item {
+            FacetFilter::HasChild { field, filter } => (field, filter, true),
+            FacetFilter::HasParent { field, filter } => (field, filter, false),
+            _ => continue,
+        };
+        let Some(inner_hits_request) = filter.inner_hits.as_ref() else {
+            continue;
+        };
+
+        let matching_doc_ids: AHashSet<usize> =
+            join_filter_doc_ids(index_arc, filter, include_uncommited, snapshot)
+                .await
+                .into_iter()
+                .collect();
+
+        let highlighter_option =
+            if inner_hits_request.highlights.is_empty() || filter.query.is_empty() {
+                None
+            } else {
+                let result_object = index_arc
+                    .search(
+                        SearchRequest::new(filter.query.clone())
+                            .query_type_default(filter.query_type_default.clone())
+                            .length(1)
+                            .result_type(ResultType::Topk)
+                            .include_uncommited(include_uncommited)
+                            .field_filter(filter.field_filter.clone())
+                            .facet_filter(filter.facet_filter.clone()),
+                    )
+                    .await;
+                Some(
+                    highlighter_with_phrases(
+                        index_arc,
+                        inner_hits_request.highlights.clone(),
+                        result_object.query_terms,
+                        result_object.query_phrases,
+                    )
+                    .await,
+                )
+            };
+        let fields: HashSet<String> = inner_hits_request.fields.iter().cloned().collect();
+
+        let index_ref = index_arc.read().await;
+        let Some(join_index) = index_ref.joins.get(field) else {
+            continue;
+        };
+        for (doc_id, document) in doc_ids.iter().zip(inner_hits.iter_mut()) {
+            let mut hits = Vec::new();
+            for related_doc_id in join_index
+                .related_doc_ids(*doc_id, is_child)
+                .iter()
+                .filter(|related_doc_id| {
+                    matching_doc_ids.contains(related_doc_id)
+                        && join_index
+                            .value(**related_doc_id)
+                            .is_some_and(|value| value.name == filter.relation)
+                })
+                .take(inner_hits_request.length)
+            {
+                if let Ok(mut hit) = index_ref.get_document(
+                    *related_doc_id,
+                    include_uncommited,
+                    &highlighter_option,
+                    &fields,
+                    &[],
+                ) {
+                    hit.insert("_id".to_string(), (*related_doc_id).into());
+                    hits.push(serde_json::Value::Object(hit.into_iter().collect()));
+                }
+            }
+            if !hits.is_empty() {
+                document.insert(filter.relation.clone(), hits.into());
+            }
+        }
+    }
+    inner_hits
+}
@@ -0,0 +1,182 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::{Index, ROARING_BLOCK_SIZE};
+
+pub(crate) const LEVELS_FILENAME: &str = "levels.json";
+
+/// Commit history of a level
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct LevelCommits {
+    created: u64,
+    updated: u64,
+    commit_count: u64,
+    generation: u64,
+}
+
+/// Commit history of all levels of the index, and the number of compactions of the index
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct LevelHistory {
+    generation: u64,
+    levels: Vec<LevelCommits>,
+}
+
+/// Stats of a level of the index, returned by Index::get_level_stats.
+/// Levels are the unit of commit: each level holds the committed documents of a document id range of 65.536 (ROARING_BLOCK_SIZE),
+/// with its posting lists appended to index.bin and its documents appended to docstore.bin.
+/// The last level may be incomplete: then each commit merges the new documents into it, and rewrites it.
+/// Complete levels are never rewritten, except by compaction, which rewrites all levels without the deleted documents.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LevelStats {
+    /// Level number, equal to the document id range: doc_id / 65.536
+    pub level: usize,
+    /// Number of committed documents in the level, including the deleted documents
+    pub doc_count: usize,
+    /// Number of deleted documents in the level, whose space is reclaimed by compaction
+    pub deleted_doc_count: usize,
+    /// The level holds 65.536 documents and is no longer rewritten by commit
+    pub complete: bool,
+    /// Bytes of the level in index.bin: posting lists and document lengths
+    pub index_bytes: u64,
+    /// Bytes of the level in docstore.bin: stored documents
+    pub docstore_bytes: u64,
+    /// Unix timestamp of the commit that created the level. None for levels created before the level history was introduced.
+    pub created: Option<u64>,
+    /// Unix timestamp of the last commit that merged documents into the level
+    pub updated: Option<u64>,
+    /// Number of commits that wrote the level: an incomplete level is rewritten by each commit
+    pub commit_count: Option<u64>,
+    /// Merge generation: number of compactions of the index when the level was written, 0 if the index was never compacted
+    pub generation: Option<u64>,
+}
+
+pub(crate) fn load_level_history(index_path: &Path) -> LevelHistory {
+    if let Ok(level_history_file) = File::open(index_path.join(LEVELS_FILENAME)) {
+        serde_json::from_reader(BufReader::new(level_history_file)).unwrap_or_default()
+    } else {
+        LevelHistory::default()
+    }
+}
+
+impl Index {
+    /// Records a commit in the level history: a new level is created, or the documents are merged into the incomplete last level.
+    pub(crate) fn record_level_commit(
+        &mut self,
+        is_last_level_incomplete: bool,
+    ) -> Result<(), String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let level_count = self.level_index.len();
+        let history = &mut self.level_history;
+        if is_last_level_incomplete {
+            if history.levels.len() == level_count {
+                let level = history.levels.last_mut().unwrap();
+                level.updated = timestamp;
+                level.commit_count += 1;
+                level.generation = history.generation;
+            }
+        } else if history.levels.len() < level_count {
+            history
+                .levels
+                .resize(level_count - 1, LevelCommits::default());
+            history.levels.push(LevelCommits {
+                created: timestamp,
+                updated: timestamp,
+                commit_count: 1,
+                generation: history.generation,
+            });
+        }
+        self.save_level_history()
+    }
+
+    /// Sets the compaction generation of the index and all its levels, after compaction has rewritten all levels
+    pub(crate) fn set_level_generation(&mut self, generation: u64) -> Result<(), String> {
+        self.level_history.generation = generation;
+        for level in self.level_history.levels.iter_mut() {
+            level.generation = generation;
+        }
+        self.save_level_history()
+    }
+
+    /// Number of compactions of the index
+    pub(crate) fn level_generation(&self) -> u64 {
+        self.level_history.generation
+    }
+
+    /// Removes all levels from the level history, e.g. by clear_index
+    pub(crate) fn clear_level_history(&mut self) -> Result<(), String> {
+        self.level_history.levels.clear();
+        self.save_level_history()
+    }
+
+    fn save_level_history(&self) -> Result<(), String> {
+        let file = File::create(Path::new(&self.index_path_string).join(LEVELS_FILENAME))
+            .map_err(|e| e.to_string())?;
+        serde_json::to_writer(&file, &self.level_history).map_err(|e| e.to_string())
+    }
+
+    /// Returns the stats of all committed levels of the index, in ascending order:
+    /// document and deleted document count, bytes in index.bin and docstore.bin, and the commit history of the level.
+    /// E.g. to see how many deleted documents could be reclaimed by compaction, and in which levels.
+    pub fn get_level_stats(&self) -> Vec<LevelStats> {
+        let level_count = self.level_index.len();
+
+        let mut deleted_doc_counts = vec![0; level_count];
+        for doc_id in self.delete_hashset.iter() {
+            if let Some(deleted_doc_count) = deleted_doc_counts.get_mut(doc_id / ROARING_BLOCK_SIZE)
+            {
+                *deleted_doc_count += 1;
+            }
+        }
+
+        let (index_file_len, docstore_file_len) = (
+            self.index_file.metadata().map(|m| m.len()).unwrap_or(0),
+            self.docstore_file.metadata().map(|m| m.len()).unwrap_or(0),
+        );
+
+        let mut level_stats = Vec::with_capacity(level_count);
+        for (level, level_index) in self.level_index.iter().enumerate() {
+            let (index_file_end_pos, docstore_file_end_pos) =
+                if let Some(next_level_index) = self.level_index.get(level + 1) {
+                    (
+                        next_level_index.index_file_start_pos,
+                        next_level_index.docstore_file_start_pos,
+                    )
+                } else {
+                    (index_file_len, docstore_file_len)
+                };
+            let doc_count = self
+                .committed_doc_count
+                .saturating_sub(level * ROARING_BLOCK_SIZE)
+                .min(ROARING_BLOCK_SIZE);
+            let commits = self
+                .level_history
+                .levels
+                .get(level)
+                .filter(|commits| commits.commit_count > 0);
+
+            level_stats.push(LevelStats {
+                level,
+                doc_count,
+                deleted_doc_count: deleted_doc_counts[level],
+                complete: doc_count == ROARING_BLOCK_SIZE,
+                index_bytes: index_file_end_pos.saturating_sub(level_index.index_file_start_pos),
+                docstore_bytes: docstore_file_end_pos
+                    .saturating_sub(level_index.docstore_file_start_pos),
+                created: commits.map(|commits| commits.created),
+                updated: commits.map(|commits| commits.updated),
+                commit_count: commits.map(|commits| commits.commit_count),
+                generation: commits.map(|commits| commits.generation),
+            });
+        }
+        level_stats
+    }
+}
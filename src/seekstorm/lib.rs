@@ -37,6 +37,16 @@
 //! similarity:SimilarityType::Bm25f,
 //! tokenizer:TokenizerType::AsciiAlphabetic,
 //! access_type: AccessType::Mmap,
+//! stemmer: StemmerType::None,
+//! k1: 1.2,
+//! b: 0.75,
+//! compact_deleted_ratio: 0.0,
+//! durability: Durability::Os,
+//! sync_interval_ms: 1000,
+//! index_unstemmed: false,
+//! strict_schema: false,
+//! metadata: serde_json::Value::Null,
+//! created: 0,
 //! };
 //! let segment_number_bits1=11;
 //! let serialize_schema=true;
@@ -77,7 +87,7 @@
 //! let query_type=QueryType::Intersection;
 //! let include_uncommitted=false;
 //! let field_filter=Vec::new();
-//! index_arc.delete_documents_by_query(query, query_type, offset, length, include_uncommitted,field_filter).await;
+//! let deleted_doc_count = index_arc.delete_documents_by_query(query, query_type, offset, length, include_uncommitted,field_filter).await;
 //! ```
 //! ### update documents
 //! ```rust
@@ -89,18 +99,17 @@
 //! ```
 //! ### commit documents
 //! ```rust
-//! index_arc.commit().await;
+//! index_arc.commit().await.unwrap();
 //! ```
 //! ### search index
 //! ```rust
-//! let query="test".to_string();
-//! let offset=10;
-//! let length=10;
-//! let query_type=QueryType::Intersection;
-//! let result_type=ResultType::TopkCount;
-//! let include_uncommitted=false;
-//! let field_filter=Vec::new();
-//! let result_object = index_arc.search(query, query_type, offset, length, result_type,include_uncommitted,field_filter).await;
+//! let search_request = SearchRequest::new("test")
+//!     .query_type_default(QueryType::Intersection)
+//!     .offset(10)
+//!     .length(10)
+//!     .result_type(ResultType::TopkCount)
+//!     .include_uncommited(false);
+//! let result_object = index_arc.search(search_request).await;
 //! ```
 //! ### display results
 //! ```rust
@@ -111,6 +120,7 @@
 //!     fragment_number: 2,
 //!     fragment_size: 160,
 //!     highlight_markup: true,
+//!     ..Default::default()
 //! },
 //! ];    
 //! let highlighter=Some(highlighter(&index_arc,highlights, result_object.query_term_strings));
@@ -252,6 +262,16 @@
 //!     similarity:SimilarityType::Bm25f,
 //!     tokenizer:TokenizerType::AsciiAlphabetic,
 //!     access_type: AccessType::Mmap,
+//!     stemmer: StemmerType::None,
+//!     k1: 1.2,
+//!     b: 0.75,
+//!     compact_deleted_ratio: 0.0,
+//!     durability: Durability::Os,
+//!     sync_interval_ms: 1000,
+//!     index_unstemmed: false,
+//!     strict_schema: false,
+//!     metadata: serde_json::Value::Null,
+//!     created: 0,
 //! };
 //! let serialize_schema=true;
 //! let segment_number_bits1=11;
@@ -269,21 +289,17 @@
 //! ```
 //! ### commit documents
 //! ```rust
-//! index_arc.commit().await;
+//! index_arc.commit().await.unwrap();
 //! ```
 //! ### search index
 //! ```rust
-//! let query="test".to_string();
-//! let offset=0;
-//! let length=10;
-//! let query_type=QueryType::Intersection;
-//! let result_type=ResultType::TopkCount;
-//! let include_uncommitted=false;
-//! let field_filter=Vec::new();
-//! let query_facets = vec![QueryFacet::String {field: "town".to_string(),prefix: "".to_string(),length: u16::MAX}];
-//! let facet_filter=Vec::new();
-//! //let facet_filter = vec![FacetFilter {field: "town".to_string(),   filter:Filter::String(vec!["Berlin".to_string()])}];
-//! let result_object = index_arc.search(query, query_type, offset, length, result_type,include_uncommitted,field_filter,query_facets,facet_filter).await;
+//! let query_facets = vec![QueryFacet::String {field: "town".to_string(),prefix: "".to_string(),length: u16::MAX,sort: FacetSort::Count,min_count: 0,offset: 0}];
+//! //let facet_filter = vec![FacetFilter::String {field: "town".to_string(),filter: vec!["Berlin".to_string()]}];
+//! let search_request = SearchRequest::new("test")
+//!     .query_type_default(QueryType::Intersection)
+//!     .length(10)
+//!     .query_facets(query_facets);
+//! let result_object = index_arc.search(search_request).await;
 //! ```
 //! ### display results
 //! ```rust
@@ -294,6 +310,7 @@
 //!             fragment_number: 2,
 //!             fragment_size: 160,
 //!             highlight_markup: true,
+//!             ..Default::default()
 //!         },
 //!     ];    
 //! let highlighter2=Some(highlighter(&index_arc,highlights, result_object.query_terms));
@@ -315,13 +332,47 @@
 //! ```
 
 pub(crate) mod add_result;
+/// Alerting on new matches for saved searches: newly committed documents are matched against percolator queries, and the matches are batched into webhook notifications.
+pub mod alert;
+/// Blocking (non-async) API: BlockingIndex wraps the async index and search APIs in a runtime managed by the library, for CLI tools and non-async applications.
+pub mod blocking;
+/// Bulk requests: an ordered list of index, update and delete operations, validated before they are applied, and committed together.
+pub mod bulk;
+/// Type coercion and default values of schema fields, applied to documents at index time.
+pub mod coerce;
 /// Commit moves indexed documents from the intermediate uncompressed data structure in RAM
 /// to the final compressed data structure on disk.
 pub mod commit;
+/// Commit hook: the application is notified after every successful commit with the ids of the added, updated and deleted documents, e.g. to invalidate downstream caches.
+pub mod commit_hook;
+/// Compaction: rewrites the index without deleted documents, manually or automatically at a configurable ratio of deleted documents.
+pub mod compact;
 pub(crate) mod compatible;
 pub(crate) mod compress_postinglist;
+/// Computed fields: derived fields of the schema, evaluated at index time from other fields of the document with a small expression language.
+pub mod computed;
+/// Date field type: parsing of date formats (RFC 3339, epoch milliseconds, custom patterns), date math (e.g. `now-7d/d`) and date histogram intervals.
+pub mod date;
+/// Result diversification: limit how many consecutive results may share the same value of a facet field, e.g. at most 2 per source.
+pub mod diversify;
 pub(crate) mod doc_store;
+/// Durability policy: flush the index files to stable storage (fsync) at every commit, periodically, or when the operating system decides.
+pub mod durability;
+/// Error type of the library and server APIs with machine-readable error codes, e.g. to distinguish a missing resource from an invalid request.
+pub mod error;
+/// A/B testing of ranking configurations: requests are assigned deterministically to named ranking variants by a hash of their user id, with per-variant metrics.
+pub mod experiment;
+/// Export an index to a versioned, portable directory (manifest, schema, synonyms, NDJSON documents, optionally the raw index files) and import it on another machine or SeekStorm version.
+pub mod export;
+/// Expressions for sorting and scoring, evaluated against the numerical facet fields (doc-values) of a result: e.g. `_score * log(1+popularity)`.
+pub mod expression;
+/// Click and conversion feedback for search results, aggregated into per-query click-through statistics for analytics and as learning-to-rank features.
+pub mod feedback;
+/// Freeze an index to read-only, e.g. for closed archive indices: commit, compact, reject document mutations and release the write buffers.
+pub mod freeze;
 pub mod geo_search;
+/// GeoShape field type: GeoJSON polygons, lines and points, indexed in a R-tree, for territory and coverage search with intersects, contains and within filters.
+pub mod geo_shape;
 /// Extracts the most relevant fragments (snippets, summaries) from specified fields of the document to provide a "keyword in context" (KWIC) functionality.
 /// With highlight_markup the matching query terms within the fragments can be highlighted with HTML markup.
 pub mod highlighter;
@@ -331,14 +382,57 @@ pub(crate) mod index_posting;
 pub mod ingest;
 pub(crate) mod intersection;
 pub(crate) mod intersection_simd;
+pub(crate) mod ip;
+/// Join field type: parent-child relations between documents of an index (e.g. question and answers), searched with has_child and has_parent filters.
+pub mod join;
+/// Level stats for operators: documents, deleted documents, bytes and commit history of each level (the unit of commit) of an index.
+pub mod level;
+/// Learning-to-rank: rescore the top-N results of a search with a linear, XGBoost or LightGBM model over per-document features.
+pub mod ltr;
 pub(crate) mod min_heap;
+/// Reverse search: store queries with the index, and return which stored queries match a given document, e.g. for alerting and content routing.
+pub mod percolator;
+/// Estimate the cost of a search request before it is executed, from the document frequencies of the query terms, the requested facets and the result window.
+pub mod query_cost;
+/// Rewrite queries before execution with declarative query rules stored with the index, or with a custom query rewriter hook.
+pub mod query_rewrite;
 pub(crate) mod realtime_search;
+/// Repair a partially corrupted index: salvage the intact documents from the document store and rebuild the posting lists, facets and checksums, preserving the document ids.
+pub mod repair;
+/// Rescore phase of a two-phase retrieval: the top results of the cheap first phase are rescored with score functions and learning-to-rank models.
+pub mod rescore;
+/// Runtime fields: named expressions of numerical facet fields, evaluated at query time for sorting, range filters and returned documents, without reindexing.
+pub mod runtime;
+/// Full scan of all live documents of an index without going through search: Index::documents iterator and DocumentCursor for batches, e.g. for exports and re-embedding jobs.
+pub mod scan;
+/// Scroll through all documents matching a query in stable document id order with a continuation token, e.g. for exporting all matches.
+pub mod scroll;
 /// Search the index for all indexed documents, both for committed and uncommitted documents.
 /// The latter enables true realtime search: documents are available for search in exact the same millisecond they are indexed.
 pub mod search;
 pub(crate) mod single;
+/// Point-in-time view of an index for the multiple steps of a query (search, join filters, inner hits, document retrieval), both for committed and uncommitted documents.
+pub mod snapshot;
+/// Disk and memory usage of an index, broken down by component (postings, docstore, facets), for capacity planning.
+pub mod storage;
+/// Context-aware query suggestions: completions of a prefix from previous queries, ranked by popularity from the click and conversion feedback, filtered or boosted by a context facet filter.
+pub mod suggest;
+/// Synonyms import/export in the Solr/Elasticsearch text format (`a, b, c` and `a, b => c`), to reuse existing curated synonym files of other engines.
+pub mod synonyms;
+/// Terms of a field with document frequencies, obtained by analyzing the stored field values with the tokenizer of the index.
+pub mod terms;
 pub mod tokenizer;
 pub(crate) mod union;
+/// Update by query: applies a partial-document patch or a field-set script to every document matching a query.
+pub mod update_by_query;
+/// Usage counters of an index (indexing, delete and query operations) for billing and monitoring, persisted across restarts.
+pub mod usage;
 pub(crate) mod utils;
+/// Validation of documents before indexing: the values of the facet fields are checked against their field type. Batch indexing with a result per document.
+pub mod validate;
+/// Index integrity verification: validates the index files against the checksums stored at every commit, per level (segment of 65.536 documents).
+pub mod verify;
+/// Per-index word lists managed at runtime with versioning: custom stopwords, removed from documents and queries, and protected words, never stemmed or split.
+pub mod word_lists;
 #[cfg(feature = "zh")]
 pub(crate) mod word_segmentation;
@@ -0,0 +1,630 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::BufReader,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    expression::Expression,
+    index::{Index, Language, TermObject, MAX_POSITIONS_PER_TERM},
+    query_cost::RESULT_DOCUMENT_COST,
+    search::{FacetValue, QueryType, ResultObject},
+    tokenizer::{self, tokenizer},
+};
+
+pub(crate) const LTR_MODELS_FILENAME: &str = "ltr_models.json";
+
+/// Per-document feature extracted for the learning-to-rank rescore phase.
+/// The order of the features in LtrModel.features must match the feature order the model was trained with.
+/// Missing values (field not set in the document) are NaN, and are routed along the default/missing branch of tree models (0 for linear models).
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub enum LtrFeature {
+    /// Score of the result from the first phase (BM25, or the result of a function score / expression scoring modifier)
+    Score,
+    /// BM25 score of the query terms within a single indexed and stored Text field.
+    /// Term frequency and field length are calculated from the stored field value, document frequency from the committed index.
+    FieldBm25 { field: String },
+    /// Age of the document in seconds: now minus the value of a Timestamp facet field
+    Recency { field: String },
+    /// 1.0 if the String or StringSet facet field value of the document matches any of the values, otherwise 0.0
+    FacetMatch { field: String, values: Vec<String> },
+    /// Value of a numerical facet field
+    Facet { field: String },
+    /// Value of an expression of numerical facet fields, _score and now (see expression::Expression)
+    Expression { expression: String },
+    /// Clicks on the document per search of the query, from the click and conversion feedback events (see Index::record_feedback_events)
+    ClickThroughRate,
+    /// Conversions of the document per search of the query, from the click and conversion feedback events (see Index::record_feedback_events)
+    ConversionRate,
+}
+
+/// Model format of an LTR model.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub enum LtrModelType {
+    /// score = bias + Σ weights[i] * feature[i]
+    Linear {
+        weights: Vec<f64>,
+        #[serde(default)]
+        bias: f64,
+    },
+    /// XGBoost JSON model dump: `booster.get_dump(dump_format="json")`, one JSON object per tree.
+    /// Split features are referenced by index: "f0", "f1", ... in the order of LtrModel.features.
+    XGBoost {
+        trees: Vec<Value>,
+        #[serde(default)]
+        base_score: f64,
+    },
+    /// LightGBM JSON model dump: `booster.dump_model()`
+    LightGBM(Value),
+}
+
+/// Learning-to-rank model: feature definitions and model, used in the rescore phase (see Index::rescore).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LtrModel {
+    pub features: Vec<LtrFeature>,
+    pub model: LtrModelType,
+    /// Decision trees, compiled from XGBoost and LightGBM dumps
+    #[serde(skip)]
+    trees: Vec<Vec<TreeNode>>,
+}
+
+#[derive(Debug, Clone)]
+enum TreeNode {
+    Split {
+        feature: usize,
+        threshold: f64,
+        /// LightGBM decision_type "<=", otherwise "<" (XGBoost)
+        less_equal: bool,
+        yes: usize,
+        no: usize,
+        missing: usize,
+    },
+    Leaf(f64),
+}
+
+/// Rescore phase: the top window_size results of the first phase are rescored with the LTR model and reordered by the model score.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LtrRescore {
+    /// Name of an LTR model loaded into the index with Index::set_ltr_model
+    pub model: String,
+    /// Number of top results to rescore. Should be at least offset + length of the search.
+    #[serde(default = "default_window_size")]
+    pub window_size: usize,
+}
+
+fn default_window_size() -> usize {
+    100
+}
+
+impl LtrModel {
+    /// Estimated cost of scoring a document with the model, relative to scoring a matching document in the first phase (see Index::estimate_query_cost):
+    /// the features and trees of the model, plus fetching the document if the model has FieldBm25 features
+    pub fn document_cost(&self) -> u64 {
+        let document_cost = (self.features.len() + self.trees.len()) as u64;
+        if self
+            .features
+            .iter()
+            .any(|feature| matches!(feature, LtrFeature::FieldBm25 { .. }))
+        {
+            document_cost + RESULT_DOCUMENT_COST
+        } else {
+            document_cost
+        }
+    }
+
+    /// Validates the model and compiles the decision trees
+    pub(crate) fn compile(&mut self) -> Result<(), String> {
+        let feature_count = self.features.len();
+        self.trees = match &self.model {
+            LtrModelType::Linear { weights, .. } => {
+                if weights.len() != feature_count {
+                    return Err(format!(
+                        "ltr model: {} weights for {} features",
+                        weights.len(),
+                        feature_count
+                    ));
+                }
+                Vec::new()
+            }
+            LtrModelType::XGBoost { trees, .. } => trees
+                .iter()
+                .map(|tree| {
+                    let mut nodes = Vec::new();
+                    compile_xgboost_node(tree, &mut nodes)?;
+                    Ok(nodes)
+                })
+                .collect::<Result<_, String>>()?,
+            LtrModelType::LightGBM(model) => model["tree_info"]
+                .as_array()
+                .ok_or("ltr model: LightGBM tree_info missing")?
+                .iter()
+                .map(|tree| {
+                    let mut nodes = Vec::new();
+                    compile_lightgbm_node(&tree["tree_structure"], &mut nodes)?;
+                    Ok(nodes)
+                })
+                .collect::<Result<_, String>>()?,
+        };
+
+        for node in self.trees.iter().flatten() {
+            if let TreeNode::Split { feature, .. } = node {
+                if *feature >= feature_count {
+                    return Err(format!(
+                        "ltr model: split feature {} out of range, {} features defined",
+                        feature, feature_count
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Scores a feature vector with the model
+    pub(crate) fn score(&self, features: &[f64]) -> f64 {
+        match &self.model {
+            LtrModelType::Linear { weights, bias } => {
+                bias + weights
+                    .iter()
+                    .zip(features)
+                    .filter(|(_, feature)| !feature.is_nan())
+                    .map(|(weight, feature)| weight * feature)
+                    .sum::<f64>()
+            }
+            LtrModelType::XGBoost { base_score, .. } => {
+                base_score
+                    + self
+                        .trees
+                        .iter()
+                        .map(|tree| score_tree(tree, features))
+                        .sum::<f64>()
+            }
+            LtrModelType::LightGBM(_) => self
+                .trees
+                .iter()
+                .map(|tree| score_tree(tree, features))
+                .sum(),
+        }
+    }
+}
+
+fn score_tree(tree: &[TreeNode], features: &[f64]) -> f64 {
+    let mut node = &tree[0];
+    loop {
+        match node {
+            TreeNode::Leaf(value) => return *value,
+            TreeNode::Split {
+                feature,
+                threshold,
+                less_equal,
+                yes,
+                no,
+                missing,
+            } => {
+                let value = features[*feature];
+                node = &tree[if value.is_nan() {
+                    *missing
+                } else if value < *threshold || (*less_equal && value == *threshold) {
+                    *yes
+                } else {
+                    *no
+                }];
+            }
+        }
+    }
+}
+
+/// Appends the XGBoost node and its children to the flat node vector, returns the position of the node
+fn compile_xgboost_node(node: &Value, nodes: &mut Vec<TreeNode>) -> Result<usize, String> {
+    let position = nodes.len();
+    if let Some(leaf) = node["leaf"].as_f64() {
+        nodes.push(TreeNode::Leaf(leaf));
+        return Ok(position);
+    }
+
+    let split = node["split"]
+        .as_str()
+        .map(|split| split.trim_start_matches('f').to_string())
+        .or_else(|| node["split"].as_u64().map(|split| split.to_string()))
+        .ok_or("ltr model: XGBoost split missing")?;
+    let feature = split.parse().map_err(|_| {
+        format!(
+            "ltr model: XGBoost split feature must be f<index>: {}",
+            split
+        )
+    })?;
+    let threshold = node["split_condition"]
+        .as_f64()
+        .ok_or("ltr model: XGBoost split_condition missing")?;
+    let children = node["children"]
+        .as_array()
+        .ok_or("ltr model: XGBoost children missing")?;
+
+    nodes.push(TreeNode::Leaf(0.0));
+    let mut child_positions = AHashMap::new();
+    for child in children {
+        let child_position = compile_xgboost_node(child, nodes)?;
+        child_positions.insert(child["nodeid"].as_u64(), child_position);
+    }
+    let child_position = |key: &str| {
+        child_positions
+            .get(&node[key].as_u64())
+            .copied()
+            .ok_or(format!("ltr model: XGBoost child node {} missing", key))
+    };
+    nodes[position] = TreeNode::Split {
+        feature,
+        threshold,
+        less_equal: false,
+        yes: child_position("yes")?,
+        no: child_position("no")?,
+        missing: child_position("missing").or_else(|_| child_position("yes"))?,
+    };
+    Ok(position)
+}
+
+/// Appends the LightGBM node and its children to the flat node vector, returns the position of the node
+fn compile_lightgbm_node(node: &Value, nodes: &mut Vec<TreeNode>) -> Result<usize, String> {
+    let position = nodes.len();
+    if let Some(leaf) = node["leaf_value"].as_f64() {
+        nodes.push(TreeNode::Leaf(leaf));
+        return Ok(position);
+    }
+
+    let feature = node["split_feature"]
+        .as_u64()
+        .ok_or("ltr model: LightGBM split_feature missing")? as usize;
+    let threshold = node["threshold"]
+        .as_f64()
+        .ok_or("ltr model: LightGBM threshold missing")?;
+    if node["decision_type"].as_str().unwrap_or("<=") != "<=" {
+        return Err(
+            "ltr model: only numerical LightGBM splits (decision_type <=) are supported".into(),
+        );
+    }
+
+    nodes.push(TreeNode::Leaf(0.0));
+    let yes = compile_lightgbm_node(&node["left_child"], nodes)?;
+    let no = compile_lightgbm_node(&node["right_child"], nodes)?;
+    nodes[position] = TreeNode::Split {
+        feature,
+        threshold,
+        less_equal: true,
+        yes,
+        no,
+        missing: if node["default_left"].as_bool().unwrap_or(true) {
+            yes
+        } else {
+            no
+        },
+    };
+    Ok(position)
+}
+
+pub(crate) fn load_ltr_models(index_path: &Path) -> AHashMap<String, LtrModel> {
+    let mut ltr_models: AHashMap<String, LtrModel> =
+        if let Ok(ltr_models_file) = File::open(index_path.join(LTR_MODELS_FILENAME)) {
+            serde_json::from_reader(BufReader::new(ltr_models_file)).unwrap_or_default()
+        } else {
+            AHashMap::new()
+        };
+
+    ltr_models.retain(|name, ltr_model| match ltr_model.compile() {
+        Ok(()) => true,
+        Err(e) => {
+            println!("{}: {}", name, e);
+            false
+        }
+    });
+    ltr_models
+}
+
+impl Index {
+    /// Set/replace an LTR model in the index, used by the rescore phase (see Index::rescore).
+    pub fn set_ltr_model(&mut self, name: &str, mut ltr_model: LtrModel) -> Result<usize, String> {
+        ltr_model.compile()?;
+        for feature in ltr_model.features.iter() {
+            if let LtrFeature::Expression { expression } = feature {
+                Expression::compile(expression, self)?;
+            }
+        }
+
+        self.ltr_models.insert(name.to_string(), ltr_model);
+        self.save_ltr_models()?;
+        Ok(self.ltr_models.len())
+    }
+
+    /// Delete an LTR model from the index
+    pub fn delete_ltr_model(&mut self, name: &str) -> Result<usize, String> {
+        if self.ltr_models.remove(name).is_none() {
+            return Err("not found".into());
+        }
+        self.save_ltr_models()?;
+        Ok(self.ltr_models.len())
+    }
+
+    /// Get the names of the LTR models in the index
+    pub fn get_ltr_models(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.ltr_models.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn save_ltr_models(&self) -> Result<(), String> {
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(LTR_MODELS_FILENAME))
+                .map_err(|e| e.to_string())?,
+            &self.ltr_models,
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Extract the LTR features of the results, e.g. to log training data for a model.
+    /// Returns one feature vector per result, in the order of features.
+    pub fn get_ltr_features(
+        &self,
+        query_string: &str,
+        query_language: Option<Language>,
+        result_object: &ResultObject,
+        features: &[LtrFeature],
+    ) -> Result<Vec<Vec<f64>>, String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as f64;
+
+        let expressions = features
+            .iter()
+            .map(|feature| match feature {
+                LtrFeature::Expression { expression } => {
+                    Expression::compile(expression, self).map(Some)
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<Option<Expression>>, String>>()?;
+
+        let mut facet_indices = Vec::new();
+        for feature in features.iter() {
+            facet_indices.push(match feature {
+                LtrFeature::Recency { field }
+                | LtrFeature::Facet { field }
+                | LtrFeature::FacetMatch { field, .. } => match self.facets_map.get(field) {
+                    Some(idx) => Some(*idx),
+                    None => return Err(format!("ltr feature: facet field {} not found", field)),
+                },
+                LtrFeature::FieldBm25 { field } => match self.schema_map.get(field) {
+                    Some(schema_field) if schema_field.indexed && schema_field.stored => None,
+                    _ => {
+                        return Err(format!(
+                            "ltr feature: field {} must be indexed and stored",
+                            field
+                        ))
+                    }
+                },
+                _ => None,
+            });
+        }
+
+        let language = tokenizer::query_language(&self.meta.stemmer, query_language, query_string);
+        let query_idf = if features
+            .iter()
+            .any(|feature| matches!(feature, LtrFeature::FieldBm25 { .. }))
+        {
+            self.query_idf(query_string, language)
+        } else {
+            Vec::new()
+        };
+        let field_length_average = if self.document_length_normalized_average > 0.0 {
+            self.document_length_normalized_average as f64
+        } else {
+            1.0
+        };
+
+        let mut feature_vectors = Vec::with_capacity(result_object.results.len());
+        for result in result_object.results.iter() {
+            let document = if query_idf.is_empty() {
+                None
+            } else {
+                self.get_document(result.doc_id, true, &None, &HashSet::new(), &[])
+                    .ok()
+            };
+
+            let mut feature_vector = Vec::with_capacity(features.len());
+            for (i, feature) in features.iter().enumerate() {
+                let missing =
+                    facet_indices[i].is_some_and(|idx| self.is_facet_missing(idx, result.doc_id));
+                feature_vector.push(match feature {
+                    LtrFeature::Score => result.score as f64,
+                    LtrFeature::FieldBm25 { field } => {
+                        match document.as_ref().and_then(|document| document.get(field)) {
+                            Some(Value::String(text)) => self.field_bm25(
+                                self.schema_map[field].indexed_field_id,
+                                text,
+                                &query_idf,
+                                field_length_average,
+                                language,
+                            ),
+                            _ => 0.0,
+                        }
+                    }
+                    LtrFeature::ClickThroughRate => {
+                        self.get_document_feedback(query_string, result.doc_id).0
+                    }
+                    LtrFeature::ConversionRate => {
+                        self.get_document_feedback(query_string, result.doc_id).1
+                    }
+                    _ if missing => f64::NAN,
+                    LtrFeature::Recency { .. } => {
+                        now - self.get_facet_value_f64(facet_indices[i].unwrap(), result.doc_id)
+                    }
+                    LtrFeature::Facet { .. } => {
+                        self.get_facet_value_f64(facet_indices[i].unwrap(), result.doc_id)
+                    }
+                    LtrFeature::FacetMatch { field, values } => {
+                        let is_match = match self.get_facet_value(field, result.doc_id) {
+                            FacetValue::String(value) => values.contains(&value),
+                            FacetValue::StringSet(set) => {
+                                set.iter().any(|value| values.contains(value))
+                            }
+                            _ => false,
+                        };
+                        if is_match {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    }
+                    LtrFeature::Expression { .. } => {
+                        expressions[i]
+                            .as_ref()
+                            .unwrap()
+                            .evaluate(self, result.doc_id, result.score)
+                    }
+                });
+            }
+            feature_vectors.push(feature_vector);
+        }
+
+        Ok(feature_vectors)
+    }
+
+    /// Rescore phase: the top ltr_rescore.window_size results are scored with the LTR model, and reordered by the model score in descending order.
+    /// The model score replaces the score of the rescored results, which are ranked before the remaining results.
+    /// Call after search, with offset 0 and a length of at least the window size, then apply offset and length to the rescored results.
+    pub fn rescore(
+        &self,
+        query_string: &str,
+        query_language: Option<Language>,
+        result_object: &mut ResultObject,
+        ltr_rescore: &LtrRescore,
+    ) -> Result<(), String> {
+        let Some(ltr_model) = self.ltr_models.get(&ltr_rescore.model) else {
+            return Err(format!("ltr model not found: {}", ltr_rescore.model));
+        };
+
+        let window_size = ltr_rescore.window_size.min(result_object.results.len());
+        let mut window = ResultObject {
+            results: result_object.results[..window_size].to_vec(),
+            ..Default::default()
+        };
+        let feature_vectors =
+            self.get_ltr_features(query_string, query_language, &window, &ltr_model.features)?;
+
+        for (result, feature_vector) in window.results.iter_mut().zip(feature_vectors) {
+            result.score = ltr_model.score(&feature_vector) as f32;
+        }
+        window.results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        result_object.results.splice(..window_size, window.results);
+
+        Ok(())
+    }
+
+    /// Query terms with their inverse document frequency within the committed index
+    fn query_idf(&self, query_string: &str, language: Option<Language>) -> Vec<(String, f64)> {
+        let mut unique_terms: AHashMap<String, TermObject> = AHashMap::new();
+        let mut non_unique_terms = Vec::new();
+        let mut nonunique_terms_count = 0u32;
+        let mut query_type = QueryType::Union;
+        tokenizer(
+            self,
+            query_string,
+            &mut unique_terms,
+            &mut non_unique_terms,
+            self.meta.tokenizer,
+            self.segment_number_mask1,
+            &mut nonunique_terms_count,
+            u16::MAX as u32,
+            MAX_POSITIONS_PER_TERM,
+            true,
+            &mut query_type,
+            false,
+            0,
+            1,
+            language,
+        );
+
+        let mut query_idf: Vec<(String, f64)> = Vec::new();
+        for non_unique_term in non_unique_terms.iter() {
+            if non_unique_term.op == QueryType::Not
+                || query_idf
+                    .iter()
+                    .any(|(term, _)| *term == non_unique_term.term)
+            {
+                continue;
+            }
+            let Some(term) = unique_terms.get(&non_unique_term.term) else {
+                continue;
+            };
+
+            let posting_count = self.committed_posting_count(term) as f64;
+
+            let idf = (((self.indexed_doc_count as f64 - posting_count + 0.5)
+                / (posting_count + 0.5))
+                + 1.0)
+                .ln();
+            query_idf.push((term.term.clone(), idf));
+        }
+        query_idf
+    }
+
+    /// BM25 score of the query terms within the text of a single field
+    fn field_bm25(
+        &self,
+        indexed_field_id: usize,
+        text: &str,
+        query_idf: &[(String, f64)],
+        field_length_average: f64,
+        language: Option<Language>,
+    ) -> f64 {
+        let mut unique_terms: AHashMap<String, TermObject> = AHashMap::new();
+        let mut non_unique_terms = Vec::new();
+        let mut nonunique_terms_count = 0u32;
+        tokenizer(
+            self,
+            text,
+            &mut unique_terms,
+            &mut non_unique_terms,
+            self.meta.tokenizer,
+            self.segment_number_mask1,
+            &mut nonunique_terms_count,
+            u16::MAX as u32,
+            MAX_POSITIONS_PER_TERM,
+            false,
+            &mut QueryType::Union,
+            false,
+            0,
+            1,
+            language,
+        );
+
+        let field_length: usize = unique_terms
+            .values()
+            .map(|term| term.field_positions_vec[0].len())
+            .sum();
+        let bm25_component = self.bm25_component(
+            indexed_field_id,
+            (field_length as f64 / field_length_average) as f32,
+        ) as f64;
+        let k1 = self.meta.k1 as f64;
+
+        query_idf
+            .iter()
+            .filter_map(|(term, idf)| {
+                unique_terms.get(term).map(|term_object| {
+                    let tf = term_object.field_positions_vec[0].len() as f64;
+                    idf * (tf * (k1 + 1.0) / (tf + bm25_component))
+                })
+            })
+            .sum()
+    }
+}
@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     geo_search::morton_ordering,
     index::{FieldType, Index},
-    search::{FacetValue, ResultSortIndex, SortOrder},
+    search::{FacetValue, MissingOrder, ResultSortIndex, SortOrder},
     utils::{
-        read_f32, read_f64, read_i16, read_i32, read_i64, read_i8, read_u16, read_u32, read_u64,
+        read_f32, read_f64, read_i16, read_i32, read_i64, read_i8, read_u128, read_u16, read_u32,
+        read_u64,
     },
 };
 
@@ -23,6 +24,8 @@ pub(crate) struct MinHeap<'a> {
     pub _elements: Vec<Result>,
     pub current_heap_size: usize,
     pub docid_hashset: AHashMap<usize, f32>,
+    /// Results with a lower doc_id are skipped, used as continuation point by scroll
+    pub min_doc_id: usize,
 
     pub index: &'a Index,
     pub result_sort: &'a Vec<ResultSortIndex<'a>>,
@@ -38,6 +41,7 @@ impl<'a> MinHeap<'a> {
         MinHeap {
             current_heap_size: 0,
             docid_hashset: AHashMap::new(),
+            min_doc_id: 0,
             _elements: vec![
                 Result {
                     doc_id: 0,
@@ -52,10 +56,58 @@ impl<'a> MinHeap<'a> {
 
     #[inline]
     pub fn result_ordering(&self, result1: Result, result2: Result) -> core::cmp::Ordering {
-        for field in self.result_sort.iter() {
-            match self.index.facets[field.idx].field_type {
+        for (i, field) in self.result_sort.iter().enumerate() {
+            if field.doc_id {
+                let order = if field.order == SortOrder::Descending {
+                    result1.doc_id.cmp(&result2.doc_id)
+                } else {
+                    result2.doc_id.cmp(&result1.doc_id)
+                };
+
+                if order != core::cmp::Ordering::Equal {
+                    return order;
+                };
+                continue;
+            }
+
+            let Some(idx) = field.idx else {
+                let (value1, value2) = match &field.expression {
+                    Some(expression) if i > 0 => (
+                        expression.evaluate(self.index, result1.doc_id, result1.score),
+                        expression.evaluate(self.index, result2.doc_id, result2.score),
+                    ),
+                    _ => (result1.score as f64, result2.score as f64),
+                };
+
+                let order = if field.order == SortOrder::Descending {
+                    value1.partial_cmp(&value2)
+                } else {
+                    value2.partial_cmp(&value1)
+                }
+                .unwrap_or(core::cmp::Ordering::Equal);
+
+                if order != core::cmp::Ordering::Equal {
+                    return order;
+                };
+                continue;
+            };
+
+            let missing1 = self.index.is_facet_missing(idx, result1.doc_id);
+            let missing2 = self.index.is_facet_missing(idx, result2.doc_id);
+            if missing1 || missing2 {
+                if missing1 && missing2 {
+                    continue;
+                }
+                return if missing1 == (field.missing == MissingOrder::First) {
+                    core::cmp::Ordering::Greater
+                } else {
+                    core::cmp::Ordering::Less
+                };
+            }
+
+            match self.index.facets[idx].field_type {
                 FieldType::U8 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = &self.index.facets_file_mmap
                         [(self.index.facets_size_sum * result1.doc_id) + offset];
@@ -75,7 +127,7 @@ impl<'a> MinHeap<'a> {
                 }
 
                 FieldType::U16 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_u16(
                         &self.index.facets_file_mmap,
@@ -97,7 +149,7 @@ impl<'a> MinHeap<'a> {
                     };
                 }
                 FieldType::U32 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_u32(
                         &self.index.facets_file_mmap,
@@ -119,7 +171,7 @@ impl<'a> MinHeap<'a> {
                     };
                 }
                 FieldType::U64 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_u64(
                         &self.index.facets_file_mmap,
@@ -142,7 +194,7 @@ impl<'a> MinHeap<'a> {
                 }
 
                 FieldType::I8 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_i8(
                         &self.index.facets_file_mmap,
@@ -165,7 +217,7 @@ impl<'a> MinHeap<'a> {
                 }
 
                 FieldType::I16 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_i16(
                         &self.index.facets_file_mmap,
@@ -187,7 +239,7 @@ impl<'a> MinHeap<'a> {
                     };
                 }
                 FieldType::I32 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_i32(
                         &self.index.facets_file_mmap,
@@ -208,8 +260,8 @@ impl<'a> MinHeap<'a> {
                         return order;
                     };
                 }
-                FieldType::I64 => {
-                    let offset = self.index.facets[field.idx].offset;
+                FieldType::I64 | FieldType::Decimal | FieldType::Date => {
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_i64(
                         &self.index.facets_file_mmap,
@@ -232,7 +284,7 @@ impl<'a> MinHeap<'a> {
                 }
 
                 FieldType::Timestamp => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_i64(
                         &self.index.facets_file_mmap,
@@ -255,7 +307,7 @@ impl<'a> MinHeap<'a> {
                 }
 
                 FieldType::F32 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_f32(
                         &self.index.facets_file_mmap,
@@ -282,7 +334,7 @@ impl<'a> MinHeap<'a> {
                 }
 
                 FieldType::F64 => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_value_1 = read_f64(
                         &self.index.facets_file_mmap,
@@ -308,8 +360,8 @@ impl<'a> MinHeap<'a> {
                     };
                 }
 
-                FieldType::String => {
-                    let offset = self.index.facets[field.idx].offset;
+                FieldType::String | FieldType::Keyword => {
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_id_1 = read_u16(
                         &self.index.facets_file_mmap,
@@ -320,7 +372,7 @@ impl<'a> MinHeap<'a> {
                         (self.index.facets_size_sum * result2.doc_id) + offset,
                     );
 
-                    let facet_value_1 = self.index.facets[field.idx]
+                    let facet_value_1 = self.index.facets[idx]
                         .values
                         .get_index((facet_id_1).into())
                         .unwrap()
@@ -328,7 +380,7 @@ impl<'a> MinHeap<'a> {
                          .0[0]
                         .clone();
 
-                    let facet_value_2 = self.index.facets[field.idx]
+                    let facet_value_2 = self.index.facets[idx]
                         .values
                         .get_index((facet_id_2).into())
                         .unwrap()
@@ -347,8 +399,31 @@ impl<'a> MinHeap<'a> {
                     };
                 }
 
+                FieldType::Ip => {
+                    let offset = self.index.facets[idx].offset;
+
+                    let facet_value_1 = read_u128(
+                        &self.index.facets_file_mmap,
+                        (self.index.facets_size_sum * result1.doc_id) + offset,
+                    );
+                    let facet_value_2 = read_u128(
+                        &self.index.facets_file_mmap,
+                        (self.index.facets_size_sum * result2.doc_id) + offset,
+                    );
+
+                    let order = if field.order == SortOrder::Descending {
+                        facet_value_1.cmp(&facet_value_2)
+                    } else {
+                        facet_value_2.cmp(&facet_value_1)
+                    };
+
+                    if order != core::cmp::Ordering::Equal {
+                        return order;
+                    };
+                }
+
                 FieldType::StringSet => {
-                    let offset = self.index.facets[field.idx].offset;
+                    let offset = self.index.facets[idx].offset;
 
                     let facet_id_1 = read_u16(
                         &self.index.facets_file_mmap,
@@ -359,7 +434,7 @@ impl<'a> MinHeap<'a> {
                         (self.index.facets_size_sum * result2.doc_id) + offset,
                     );
 
-                    let facet_value_1 = self.index.facets[field.idx]
+                    let facet_value_1 = self.index.facets[idx]
                         .values
                         .get_index((facet_id_1).into())
                         .unwrap()
@@ -367,7 +442,7 @@ impl<'a> MinHeap<'a> {
                          .0[0]
                         .clone();
 
-                    let facet_value_2 = self.index.facets[field.idx]
+                    let facet_value_2 = self.index.facets[idx]
                         .values
                         .get_index((facet_id_2).into())
                         .unwrap()
@@ -388,7 +463,7 @@ impl<'a> MinHeap<'a> {
 
                 FieldType::Point => {
                     if let FacetValue::Point(base) = &field.base {
-                        let offset = self.index.facets[field.idx].offset;
+                        let offset = self.index.facets[idx].offset;
 
                         let facet_value_1 = read_u64(
                             &self.index.facets_file_mmap,
@@ -553,6 +628,21 @@ impl<'a> MinHeap<'a> {
 
     #[inline(always)]
     pub(crate) fn add_topk(&mut self, result: Result, top_k: usize) -> bool {
+        if result.doc_id < self.min_doc_id {
+            return false;
+        }
+
+        let result = match self.result_sort.first() {
+            Some(ResultSortIndex {
+                expression: Some(expression),
+                ..
+            }) => Result {
+                doc_id: result.doc_id,
+                score: expression.evaluate(self.index, result.doc_id, result.score) as f32,
+            },
+            _ => result,
+        };
+
         if self.current_heap_size > top_k && self.result_ordering(self._elements[0], result).is_ge()
         {
             return false;
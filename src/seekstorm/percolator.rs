@@ -0,0 +1,201 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+    sync::Arc,
+    thread::available_parallelism,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    index::{
+        create_index, AccessType, Document, FileType, Index, IndexArc, IndexDocument, SchemaField,
+    },
+    search::{FacetFilter, QueryType, ResultType, Search, SearchRequest},
+};
+
+pub(crate) const PERCOLATOR_QUERIES_FILENAME: &str = "percolator_queries.json";
+/// Subdirectory of the index directory, where the temporary indices of the percolated documents are created
+const PERCOLATE_PATH: &str = "percolate";
+
+/// Query stored with the index for reverse search: percolate returns which stored queries match a given document,
+/// e.g. for alerting, saved-search notifications and content routing.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct PercolatorQuery {
+    /// Unique id of the stored query, returned by percolate if the query matches
+    pub id: String,
+    /// Query string, with the same syntax as the query string of Search::search (+ - "" search operators)
+    pub query: String,
+    #[serde(default)]
+    pub query_type_default: QueryType,
+    /// Field names where to search. If empty, then all indexed fields are searched.
+    #[serde(default)]
+    pub field_filter: Vec<String>,
+    /// The document has to match the facet filter in addition to the query.
+    #[serde(default)]
+    pub facet_filter: Vec<FacetFilter>,
+}
+
+pub(crate) fn load_percolator_queries(index_path: &Path) -> Vec<PercolatorQuery> {
+    if let Ok(percolator_queries_file) = File::open(index_path.join(PERCOLATOR_QUERIES_FILENAME)) {
+        serde_json::from_reader(BufReader::new(percolator_queries_file)).unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+
+impl Index {
+    /// Add percolator queries to the index, or replace stored queries with the same id.
+    /// Returns the number of stored percolator queries.
+    pub fn add_percolator_queries(
+        &mut self,
+        percolator_queries: Vec<PercolatorQuery>,
+    ) -> Result<usize, String> {
+        for percolator_query in percolator_queries.iter() {
+            if percolator_query.id.is_empty() {
+                return Err("percolator query id is empty".to_string());
+            }
+            if percolator_query.query.trim().is_empty() {
+                return Err(format!(
+                    "percolator query is empty: {}",
+                    percolator_query.id
+                ));
+            }
+            if let Some(field) = percolator_query.field_filter.iter().find(|field| {
+                !self
+                    .schema_map
+                    .get(*field)
+                    .is_some_and(|schema_field| schema_field.indexed)
+            }) {
+                return Err(format!("field not found or not indexed: {}", field));
+            }
+            self.check_facet_filter(&percolator_query.facet_filter)?;
+        }
+
+        let mut stored_queries = self.percolator_queries.clone();
+        for percolator_query in percolator_queries {
+            if let Some(stored_query) = stored_queries
+                .iter_mut()
+                .find(|stored_query| stored_query.id == percolator_query.id)
+            {
+                *stored_query = percolator_query;
+            } else {
+                stored_queries.push(percolator_query);
+            }
+        }
+        self.set_percolator_queries(stored_queries)
+    }
+
+    /// Delete percolator queries from the index by id.
+    /// Returns the number of deleted percolator queries.
+    pub fn delete_percolator_queries(&mut self, ids: Vec<String>) -> Result<usize, String> {
+        let mut stored_queries = self.percolator_queries.clone();
+        stored_queries.retain(|stored_query| !ids.contains(&stored_query.id));
+        let deleted_count = self.percolator_queries.len() - stored_queries.len();
+        if deleted_count > 0 {
+            self.set_percolator_queries(stored_queries)?;
+        }
+        Ok(deleted_count)
+    }
+
+    /// Get the percolator queries of the index
+    pub fn get_percolator_queries(&self) -> Vec<PercolatorQuery> {
+        self.percolator_queries.clone()
+    }
+
+    fn set_percolator_queries(
+        &mut self,
+        percolator_queries: Vec<PercolatorQuery>,
+    ) -> Result<usize, String> {
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(PERCOLATOR_QUERIES_FILENAME))
+                .map_err(|e| e.to_string())?,
+            &percolator_queries,
+        )
+        .map_err(|e| e.to_string())?;
+
+        self.percolator_queries = percolator_queries;
+        Ok(self.percolator_queries.len())
+    }
+}
+
+/// Reverse search: match documents against the percolator queries stored with the index
+#[allow(async_fn_in_trait)]
+pub trait Percolate {
+    async fn percolate(&self, documents: Vec<Document>) -> Result<Vec<Vec<String>>, String>;
+}
+
+impl Percolate for IndexArc {
+    /// Returns for each document the ids of the stored percolator queries matching it, in the order of the documents.
+    /// The documents are not added to the index: they are indexed into a temporary index with the schema, synonyms and settings of the index,
+    /// where the percolator queries are executed with the full query semantics of Search::search (tokenizer, stemming, synonyms, facet filters).
+    async fn percolate(&self, documents: Vec<Document>) -> Result<Vec<Vec<String>>, String> {
+        let mut matches: Vec<Vec<String>> = vec![Vec::new(); documents.len()];
+
+        let index_ref = self.read().await;
+        if documents.is_empty() || index_ref.percolator_queries.is_empty() {
+            return Ok(matches);
+        }
+        let percolator_queries = index_ref.percolator_queries.clone();
+        let mut meta = index_ref.meta.clone();
+        meta.access_type = AccessType::Ram;
+        meta.compact_deleted_ratio = 0.0;
+        let mut schema: Vec<SchemaField> = index_ref.schema_map.values().cloned().collect();
+        schema.sort_by_key(|schema_field| schema_field.field_id);
+        let synonyms = index_ref.get_synonyms().unwrap_or_default();
+        let percolate_path = Path::new(&index_ref.index_path_string)
+            .join(PERCOLATE_PATH)
+            .join(rand::random::<u64>().to_string());
+        drop(index_ref);
+
+        let percolate_index_arc: IndexArc = Arc::new(RwLock::new(create_index(
+            &percolate_path,
+            meta,
+            &schema,
+            false,
+            &synonyms,
+            11,
+            true,
+        )?));
+        let permits = percolate_index_arc.read().await.permits.clone();
+        let thread_number = available_parallelism().unwrap().get() as u32;
+
+        let document_count = documents.len();
+        for document in documents {
+            percolate_index_arc
+                .index_document(document, FileType::None)
+                .await;
+            // documents are indexed in parallel: wait until the document is indexed, to preserve the document ids
+            drop(permits.acquire_many(thread_number).await.unwrap());
+        }
+
+        for percolator_query in percolator_queries {
+            let result_object = percolate_index_arc
+                .search(
+                    SearchRequest::new(percolator_query.query)
+                        .query_type_default(percolator_query.query_type_default)
+                        .length(document_count)
+                        .result_type(ResultType::Topk)
+                        .include_uncommited(true)
+                        .field_filter(percolator_query.field_filter)
+                        .facet_filter(percolator_query.facet_filter),
+                )
+                .await;
+            for result in result_object.results {
+                if let Some(document_matches) = matches.get_mut(result.doc_id) {
+                    document_matches.push(percolator_query.id.clone());
+                }
+            }
+        }
+
+        drop(percolate_index_arc);
+        let _ = fs::remove_dir_all(&percolate_path);
+        if let Some(parent_path) = percolate_path.parent() {
+            let _ = fs::remove_dir(parent_path);
+        }
+
+        Ok(matches)
+    }
+}
@@ -0,0 +1,144 @@
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diversify::Diversify,
+    index::{Index, TermObject, MAX_POSITIONS_PER_TERM},
+    rescore::Rescore,
+    search::{QueryType, ResultType, SearchRequest},
+    tokenizer::{self, tokenizer},
+};
+
+/// Estimated cost of fetching a result document (reading and decompressing the stored fields), relative to scoring a matching document
+pub const RESULT_DOCUMENT_COST: u64 = 100;
+
+/// Estimated cost of a search request before it is executed, see Index::estimate_query_cost
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct QueryCost {
+    /// Estimated number of documents the search has to score: the sum of the document frequencies of the query terms for a Union query,
+    /// the lowest document frequency for Intersection and Phrase queries, all documents of the index for a query without terms (e.g. facets over all documents)
+    pub matching_documents: usize,
+    /// Number of query facets counted per matching document, 0 for ResultType::Topk (facets are not collected)
+    pub facet_count: usize,
+    /// Number of result documents to fetch: offset + length, 0 for ResultType::Count
+    pub result_window: usize,
+    /// Number of top results collected and reordered by the rescore and diversify phases: the largest window_size, 0 without rescore and diversify
+    pub rescore_window: usize,
+    /// Estimated cost per document of the rescore window: 1 for collecting and reordering it, plus 1 for the score functions of the rescore,
+    /// plus the features and trees of the LTR model of the rescore, plus RESULT_DOCUMENT_COST if the model has FieldBm25 features (see LtrModel::document_cost)
+    pub rescore_document_cost: u64,
+    /// matching_documents × (1 + facet_count) + result_window × RESULT_DOCUMENT_COST + rescore_window × rescore_document_cost
+    pub cost: u64,
+}
+
+impl Index {
+    /// Estimates the cost of a search request before it is executed, from the document frequencies of the query terms, the number of requested facets, the result window,
+    /// and the window and LTR model of the rescore and diversify phases applied to the results of the search, if any,
+    /// so that requests like a frequent term with 50 facets and length=100000 can be rejected or downgraded before they occupy the server.
+    /// The estimate doesn't execute the search: stopwords, synonyms, facet filters and the pruning of Topk searches are not taken into account.
+    pub fn estimate_query_cost(
+        &self,
+        search_request: &SearchRequest,
+        rescore: Option<&Rescore>,
+        diversify: Option<&Diversify>,
+    ) -> QueryCost {
+        let mut unique_terms: AHashMap<String, TermObject> = AHashMap::new();
+        let mut non_unique_terms = Vec::new();
+        let mut nonunique_terms_count = 0u32;
+        let mut query_type = search_request.query_type_default.clone();
+        let language = tokenizer::query_language(
+            &self.meta.stemmer,
+            search_request.query_language,
+            &search_request.query_string,
+        );
+        tokenizer(
+            self,
+            &search_request.query_string,
+            &mut unique_terms,
+            &mut non_unique_terms,
+            self.meta.tokenizer,
+            self.segment_number_mask1,
+            &mut nonunique_terms_count,
+            u16::MAX as u32,
+            MAX_POSITIONS_PER_TERM,
+            true,
+            &mut query_type,
+            false,
+            0,
+            1,
+            language,
+        );
+
+        let document_frequencies = unique_terms
+            .values()
+            .filter(|term| {
+                non_unique_terms.iter().any(|non_unique_term| {
+                    non_unique_term.term == term.term && non_unique_term.op != QueryType::Not
+                })
+            })
+            .map(|term| {
+                let mut document_frequency = self.committed_posting_count(term);
+                if search_request.include_uncommited && self.uncommitted {
+                    document_frequency += self.get_posting_count_uncommited(&term.term);
+                }
+                document_frequency
+            });
+        let matching_documents = if non_unique_terms.is_empty() {
+            if search_request.include_uncommited {
+                self.indexed_doc_count
+            } else {
+                self.committed_doc_count
+            }
+        } else if query_type == QueryType::Union {
+            document_frequencies.sum()
+        } else {
+            document_frequencies.min().unwrap_or(0)
+        };
+
+        let facet_count = if search_request.result_type == ResultType::Topk {
+            0
+        } else {
+            search_request.query_facets.len()
+        };
+        let result_window = if search_request.result_type == ResultType::Count {
+            0
+        } else {
+            search_request.offset.saturating_add(search_request.length)
+        };
+
+        let rescore_window = if search_request.result_type == ResultType::Count {
+            0
+        } else {
+            rescore
+                .map(|rescore| rescore.window_size)
+                .into_iter()
+                .chain(diversify.map(|diversify| diversify.window_size))
+                .max()
+                .unwrap_or(0)
+        };
+        let rescore_document_cost = if rescore_window == 0 {
+            0
+        } else {
+            1 + rescore.map_or(0, |rescore| {
+                rescore.function_score.is_some() as u64
+                    + rescore
+                        .model
+                        .as_ref()
+                        .and_then(|model| self.ltr_models.get(model))
+                        .map_or(0, |ltr_model| ltr_model.document_cost())
+            })
+        };
+
+        QueryCost {
+            matching_documents,
+            facet_count,
+            result_window,
+            rescore_window,
+            rescore_document_cost,
+            cost: (matching_documents as u64)
+                .saturating_mul(1 + facet_count as u64)
+                .saturating_add((result_window as u64).saturating_mul(RESULT_DOCUMENT_COST))
+                .saturating_add((rescore_window as u64).saturating_mul(rescore_document_cost)),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::Index,
+    search::{FacetFilter, QueryFacet, QueryType, ResultSort},
+};
+
+pub(crate) const QUERY_RULES_FILENAME: &str = "query_rules.json";
+
+/// The rewritable parts of a search request, passed to the query rules and the QueryRewriter before the query is executed
+#[derive(Clone)]
+pub struct SearchQuery {
+    pub query_string: String,
+    pub query_type_default: QueryType,
+    pub field_filter: Vec<String>,
+    pub query_facets: Vec<QueryFacet>,
+    pub facet_filter: Vec<FacetFilter>,
+    pub result_sort: Vec<ResultSort>,
+}
+
+/// Rewrites every query of an index before execution, e.g. to inject filters, expand abbreviations or strip forbidden fields.
+/// Implement this trait as hook to encode business rules centrally in the application, or use the query rules of the index (see QueryRule).
+/// The hook is invoked after the query rules, for every search of the index, including scroll and delete_documents_by_query.
+pub trait QueryRewriter: Send + Sync {
+    fn rewrite(&self, index: &Index, query: &mut SearchQuery);
+}
+
+/// Declarative query rule, stored with the index in query_rules.json and applied to every query before execution, in the order of the rules.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum QueryRule {
+    /// Replaces a single query term (case-insensitive, whole term) with the replacement, e.g. to expand abbreviations: "nyc" to "new york city".
+    /// The operators (+, -, ") of the query term are preserved.
+    Replace { term: String, replacement: String },
+    /// Adds a facet filter to the query, e.g. to return only published documents.
+    /// If terms are specified, the filter is added only if the query contains any of the terms (case-insensitive), e.g. "cheap" adds a price filter.
+    AddFacetFilter {
+        filter: FacetFilter,
+        #[serde(default)]
+        terms: Vec<String>,
+    },
+    /// Removes a field from the searched fields, facets, facet filters and sort fields of the query, e.g. internal fields which must not be queried.
+    RemoveField { field: String },
+    /// Curated results: if the query matches, the pinned documents are returned at the top of the results, in the given order,
+    /// and the hidden documents are removed from the results, e.g. to promote a landing page or to suppress an outdated document.
+    /// The query matches exactly (case-insensitive, ignoring redundant whitespace), or if regex is true, the query is matched against the regular expression.
+    /// Only the first matching Curate rule is applied. Curation is applied by Search::search to the original query string, but not to scroll and delete_documents_by_query.
+    /// Documents are pinned and hidden by the value of key_field, a String facet field with a unique value per document, e.g. a product id,
+    /// as document ids change when a document is updated or the index is compacted.
+    /// Pinned documents are returned only if they match the facet filters of the query, including the filters added by query rules and the security filter of a scoped API key.
+    Curate {
+        query: String,
+        #[serde(default)]
+        regex: bool,
+        key_field: String,
+        #[serde(default)]
+        pinned_keys: Vec<String>,
+        #[serde(default)]
+        hidden_keys: Vec<String>,
+        #[serde(skip)]
+        query_regex: Option<Regex>,
+    },
+}
+
+pub(crate) fn load_query_rules(index_path: &Path) -> Vec<QueryRule> {
+    let mut query_rules: Vec<QueryRule> =
+        if let Ok(query_rules_file) = File::open(index_path.join(QUERY_RULES_FILENAME)) {
+            serde_json::from_reader(BufReader::new(query_rules_file)).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+    query_rules.retain_mut(|query_rule| match query_rule.compile() {
+        Ok(()) => true,
+        Err(e) => {
+            println!("query rule ignored: {}", e);
+            false
+        }
+    });
+    query_rules
+}
+
+/// Normalizes a query string for exact matching of Curate rules: lowercase, single spaces
+fn normalize_query(query_string: &str) -> String {
+    query_string
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Splits a query term into its operator prefix, the term itself, and its phrase suffix
+fn split_query_term(query_term: &str) -> (&str, &str, &str) {
+    let term_start = query_term.len() - query_term.trim_start_matches(['+', '-', '"']).len();
+    let term_end = query_term.trim_end_matches('"').len().max(term_start);
+    (
+        &query_term[..term_start],
+        &query_term[term_start..term_end],
+        &query_term[term_end..],
+    )
+}
+
+impl QueryRule {
+    /// Compiles the regular expression of a Curate rule
+    fn compile(&mut self) -> Result<(), String> {
+        if let QueryRule::Curate {
+            query,
+            regex: true,
+            query_regex,
+            ..
+        } = self
+        {
+            *query_regex = Some(Regex::new(query).map_err(|e| e.to_string())?);
+        }
+        Ok(())
+    }
+
+    fn apply(&self, index: &Index, query: &mut SearchQuery) {
+        match self {
+            QueryRule::Replace { term, replacement } => {
+                query.query_string = query
+                    .query_string
+                    .split_whitespace()
+                    .map(|query_term| {
+                        let (prefix, query_term_core, suffix) = split_query_term(query_term);
+                        if query_term_core.to_lowercase() == term.to_lowercase() {
+                            [prefix, replacement, suffix].concat()
+                        } else {
+                            query_term.to_string()
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ");
+            }
+
+            QueryRule::AddFacetFilter { filter, terms } => {
+                if terms.is_empty()
+                    || query.query_string.split_whitespace().any(|query_term| {
+                        let query_term_core = split_query_term(query_term).1.to_lowercase();
+                        terms
+                            .iter()
+                            .any(|term| term.to_lowercase() == query_term_core)
+                    })
+                {
+                    query.facet_filter.push(filter.clone());
+                }
+            }
+
+            QueryRule::RemoveField { field } => {
+                if query.field_filter.is_empty() {
+                    query.field_filter = index
+                        .schema_map
+                        .values()
+                        .filter(|schema_field| schema_field.indexed)
+                        .map(|schema_field| schema_field.field.clone())
+                        .collect();
+                }
+                query
+                    .field_filter
+                    .retain(|field_filter| field_filter != field);
+                query
+                    .query_facets
+                    .retain(|query_facet| query_facet.field() != field);
+                query
+                    .facet_filter
+                    .retain(|facet_filter| facet_filter.field() != field);
+                query
+                    .result_sort
+                    .retain(|result_sort| result_sort.field != *field);
+            }
+
+            QueryRule::Curate { .. } => {}
+        }
+    }
+}
+
+impl Index {
+    /// Set/replace the query rules of the index, which are applied to every subsequent query before execution (see QueryRule).
+    /// Facet filters of AddFacetFilter rules and key fields of Curate rules have to refer to facet fields of the matching type, regular expressions of Curate rules have to be valid.
+    pub fn set_query_rules(&mut self, mut query_rules: Vec<QueryRule>) -> Result<usize, String> {
+        for query_rule in query_rules.iter_mut() {
+            match query_rule {
+                QueryRule::AddFacetFilter { filter, .. } => {
+                    self.check_facet_filter(std::slice::from_ref(filter))?;
+                }
+                QueryRule::Curate { key_field, .. } => {
+                    self.check_facet_filter(&[FacetFilter::String {
+                        field: key_field.clone(),
+                        filter: Vec::new(),
+                    }])?;
+                }
+                _ => {}
+            }
+            query_rule.compile()?;
+        }
+
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(QUERY_RULES_FILENAME))
+                .map_err(|e| e.to_string())?,
+            &query_rules,
+        )
+        .map_err(|e| e.to_string())?;
+
+        self.query_rules = query_rules;
+        Ok(self.query_rules.len())
+    }
+
+    /// Get the query rules of the index
+    pub fn get_query_rules(&self) -> Vec<QueryRule> {
+        self.query_rules.clone()
+    }
+
+    /// Set/remove the query rewriter hook of the index, which is invoked for every subsequent query after the query rules.
+    /// The hook is not persisted: it has to be set again after the index is opened.
+    pub fn set_query_rewriter(&mut self, query_rewriter: Option<Arc<dyn QueryRewriter>>) {
+        self.query_rewriter = query_rewriter;
+    }
+
+    /// Applies the query rules and the query rewriter hook of the index to a query
+    pub(crate) fn rewrite_query(&self, query: &mut SearchQuery) {
+        for query_rule in self.query_rules.iter() {
+            query_rule.apply(self, query);
+        }
+        if let Some(query_rewriter) = self.query_rewriter.as_ref() {
+            query_rewriter.rewrite(self, query);
+        }
+    }
+
+    /// Returns the key field, the pinned keys and the hidden keys of the first Curate rule matching the query string
+    pub(crate) fn query_curation(
+        &self,
+        query_string: &str,
+    ) -> Option<(String, Vec<String>, Vec<String>)> {
+        let normalized_query = normalize_query(query_string);
+        self.query_rules
+            .iter()
+            .find_map(|query_rule| match query_rule {
+                QueryRule::Curate {
+                    query,
+                    key_field,
+                    pinned_keys,
+                    hidden_keys,
+                    query_regex,
+                    ..
+                } if query_regex.as_ref().map_or_else(
+                    || normalize_query(query) == normalized_query,
+                    |query_regex| query_regex.is_match(query_string.trim()),
+                ) =>
+                {
+                    Some((key_field.clone(), pinned_keys.clone(), hidden_keys.clone()))
+                }
+                _ => None,
+            })
+    }
+}
@@ -8,7 +8,7 @@ use smallvec::SmallVec;
 
 use crate::{
     add_result::{
-        facet_count, is_facet_filter, read_multifield_vec, B, DOCUMENT_LENGTH_COMPRESSION, K, SIGMA,
+        facet_count, is_facet_filter, read_multifield_vec, DOCUMENT_LENGTH_COMPRESSION, SIGMA,
     },
     index::{
         Index, NonUniquePostingListObjectQuery, NonUniqueTermObject, PostingListObjectQuery,
@@ -526,18 +526,19 @@ pub(crate) fn get_bm25f_singleterm_multifield_uncommitted(
             let tf = plo_single.field_vec[0].1 as f32;
 
             bm25f = plo_single.idf
-                * ((tf * (K + 1.0) / (tf + (K * (1.0 - B + (B * document_length_quotient)))))
+                * ((tf * (index.meta.k1 + 1.0)
+                    / (tf + index.bm25_component(0, document_length_quotient)))
                     + SIGMA);
         } else {
             let tf_bigram1 = plo_single.field_vec_bigram1[0].1 as f32;
             let tf_bigram2 = plo_single.field_vec_bigram2[0].1 as f32;
             bm25f = plo_single.idf_bigram1
-                * ((tf_bigram1 * (K + 1.0)
-                    / (tf_bigram1 + (K * (1.0 - B + (B * document_length_quotient)))))
+                * ((tf_bigram1 * (index.meta.k1 + 1.0)
+                    / (tf_bigram1 + index.bm25_component(0, document_length_quotient)))
                     + SIGMA)
                 + plo_single.idf_bigram2
-                    * ((tf_bigram2 * (K + 1.0)
-                        / (tf_bigram2 + (K * (1.0 - B + (B * document_length_quotient)))))
+                    * ((tf_bigram2 * (index.meta.k1 + 1.0)
+                        / (tf_bigram2 + index.bm25_component(0, document_length_quotient)))
                         + SIGMA);
         }
     } else if !plo_single.is_bigram {
@@ -553,8 +554,12 @@ pub(crate) fn get_bm25f_singleterm_multifield_uncommitted(
 
             let tf = field.1 as f32;
 
-            bm25f += plo_single.idf
-                * ((tf * (K + 1.0) / (tf + (K * (1.0 - B + (B * document_length_quotient)))))
+            let weight = index.indexed_schema_vec[field_id].boost;
+
+            bm25f += weight
+                * plo_single.idf
+                * ((tf * (index.meta.k1 + 1.0)
+                    / (tf + index.bm25_component(field_id, document_length_quotient)))
                     + SIGMA);
         }
     } else {
@@ -570,9 +575,12 @@ pub(crate) fn get_bm25f_singleterm_multifield_uncommitted(
 
             let tf_bigram1 = field.1 as f32;
 
-            bm25f += plo_single.idf_bigram1
-                * ((tf_bigram1 * (K + 1.0)
-                    / (tf_bigram1 + (K * (1.0 - B + (B * document_length_quotient)))))
+            let weight = index.indexed_schema_vec[field_id].boost;
+
+            bm25f += weight
+                * plo_single.idf_bigram1
+                * ((tf_bigram1 * (index.meta.k1 + 1.0)
+                    / (tf_bigram1 + index.bm25_component(field_id, document_length_quotient)))
                     + SIGMA);
         }
 
@@ -588,9 +596,12 @@ pub(crate) fn get_bm25f_singleterm_multifield_uncommitted(
 
             let tf_bigram2 = field.1 as f32;
 
-            bm25f += plo_single.idf_bigram2
-                * ((tf_bigram2 * (K + 1.0)
-                    / (tf_bigram2 + (K * (1.0 - B + (B * document_length_quotient)))))
+            let weight = index.indexed_schema_vec[field_id].boost;
+
+            bm25f += weight
+                * plo_single.idf_bigram2
+                * ((tf_bigram2 * (index.meta.k1 + 1.0)
+                    / (tf_bigram2 + index.bm25_component(field_id, document_length_quotient)))
                     + SIGMA);
         }
     }
@@ -633,19 +644,20 @@ pub(crate) fn get_bm25f_multiterm_multifield_uncommitted(
                 let tf = plo.field_vec[0].1 as f32;
 
                 bm25f += plo.idf
-                    * ((tf * (K + 1.0) / (tf + (K * (1.0 - B + (B * document_length_quotient)))))
+                    * ((tf * (index.meta.k1 + 1.0)
+                        / (tf + index.bm25_component(0, document_length_quotient)))
                         + SIGMA);
             } else {
                 let tf_bigram1 = plo.field_vec_bigram1[0].1 as f32;
                 let tf_bigram2 = plo.field_vec_bigram2[0].1 as f32;
 
                 bm25f += plo.idf_bigram1
-                    * ((tf_bigram1 * (K + 1.0)
-                        / (tf_bigram1 + (K * (1.0 - B + (B * document_length_quotient)))))
+                    * ((tf_bigram1 * (index.meta.k1 + 1.0)
+                        / (tf_bigram1 + index.bm25_component(0, document_length_quotient)))
                         + SIGMA)
                     + plo.idf_bigram2
-                        * ((tf_bigram2 * (K + 1.0)
-                            / (tf_bigram2 + (K * (1.0 - B + (B * document_length_quotient)))))
+                        * ((tf_bigram2 * (index.meta.k1 + 1.0)
+                            / (tf_bigram2 + index.bm25_component(0, document_length_quotient)))
                             + SIGMA);
             }
         }
@@ -672,8 +684,8 @@ pub(crate) fn get_bm25f_multiterm_multifield_uncommitted(
 
                     bm25f += weight
                         * plo.idf
-                        * ((tf * (K + 1.0)
-                            / (tf + (K * (1.0 - B + (B * document_length_quotient)))))
+                        * ((tf * (index.meta.k1 + 1.0)
+                            / (tf + index.bm25_component(field_id, document_length_quotient)))
                             + SIGMA);
                 }
             } else {
@@ -693,12 +705,13 @@ pub(crate) fn get_bm25f_multiterm_multifield_uncommitted(
 
                     bm25f += weight
                         * plo.idf_bigram1
-                        * ((tf_bigram1 * (K + 1.0)
-                            / (tf_bigram1 + (K * (1.0 - B + (B * document_length_quotient)))))
+                        * ((tf_bigram1 * (index.meta.k1 + 1.0)
+                            / (tf_bigram1
+                                + index.bm25_component(field_id, document_length_quotient)))
                             + SIGMA);
                 }
 
-                for field in plo.field_vec_bigram1.iter() {
+                for field in plo.field_vec_bigram2.iter() {
                     let field_id = field.0 as usize;
 
                     let document_length_normalized = DOCUMENT_LENGTH_COMPRESSION[index
@@ -714,8 +727,9 @@ pub(crate) fn get_bm25f_multiterm_multifield_uncommitted(
 
                     bm25f += weight
                         * plo.idf_bigram2
-                        * ((tf_bigram2 * (K + 1.0)
-                            / (tf_bigram2 + (K * (1.0 - B + (B * document_length_quotient)))))
+                        * ((tf_bigram2 * (index.meta.k1 + 1.0)
+                            / (tf_bigram2
+                                + index.bm25_component(field_id, document_length_quotient)))
                             + SIGMA);
                 }
             }
@@ -766,11 +780,15 @@ impl Index {
             self.level_index.len()
         };
         let mut preceding_bigram_count = 0;
+        let mut preceding_gap_count = 0;
 
         for non_unique_term in non_unique_terms.iter() {
             let term = unique_terms.get(&non_unique_term.term).unwrap();
             let key0: u32 = term.key0;
             let key_hash: u64 = term.key_hash;
+            if non_unique_term.op != QueryType::Not {
+                preceding_gap_count += non_unique_term.position_gap;
+            }
 
             match self.segments_level0[key0 as usize].segment.get(&key_hash) {
                 Some(value1) => {
@@ -859,7 +877,8 @@ impl Index {
                         let nu_plo = NonUniquePostingListObjectQuery {
                             term_index_unique,
                             term_index_nonunique: non_unique_query_list.len()
-                                + preceding_bigram_count,
+                                + preceding_bigram_count
+                                + preceding_gap_count,
                             pos: 0,
                             p_pos: 0,
                             positions_pointer: 0,
@@ -895,7 +914,7 @@ impl Index {
 
         let non_unique_query_list_count = non_unique_query_list.len();
 
-        if query_list_len == 0 {
+        if query_list_len == 0 || search_result.minimum_should_match > query_list_len {
         } else if query_list_len == 1 {
             self.single_docid_uncommitted(
                 block_id,
@@ -1607,7 +1626,7 @@ impl Index {
     ) {
         let mut result_count: i32 = 0;
 
-        if result_type == &ResultType::Count {
+        if result_type == &ResultType::Count && search_result.minimum_should_match <= 1 {
             self.union_count_uncommitted(&mut result_count, query_list);
             result_count_arc.fetch_add(result_count as usize, Ordering::Relaxed);
             return;
@@ -1702,19 +1721,20 @@ impl Index {
                 }
             }
 
-            if result_type != &ResultType::Count {
-                let mut term_match_count = 0;
-                let mut term_index = 0;
-                for (i, plo) in query_list.iter_mut().enumerate() {
-                    if !plo.end_flag && (plo.docid as u16 == docid_min) {
-                        plo.bm25_flag = true;
-                        term_match_count += 1;
-                        term_index = i;
-                    } else {
-                        plo.bm25_flag = false;
-                    }
+            let mut term_match_count = 0;
+            let mut term_index = 0;
+            for (i, plo) in query_list.iter_mut().enumerate() {
+                if !plo.end_flag && (plo.docid as u16 == docid_min) {
+                    plo.bm25_flag = true;
+                    term_match_count += 1;
+                    term_index = i;
+                } else {
+                    plo.bm25_flag = false;
                 }
+            }
 
+            if term_match_count < search_result.minimum_should_match {
+            } else if result_type != &ResultType::Count {
                 if term_match_count == 1 {
                     add_result_singleterm_uncommitted(
                         self,
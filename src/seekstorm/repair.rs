@@ -0,0 +1,255 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+    sync::Arc,
+    thread::available_parallelism,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    commit::commit_index,
+    index::{
+        create_index, open_index, DeleteDocuments, Document, FileType, IndexArc, IndexDocument,
+        IndexMetaObject, SchemaField, Synonym, CHECKSUMS_FILENAME, DELETE_FILENAME,
+        DOCSTORE_FILENAME, FACET_FILENAME, FACET_VALUES_FILENAME, INDEX_FILENAME, META_FILENAME,
+        ROARING_BLOCK_SIZE, SCHEMA_FILENAME, SYNONYMS_FILENAME,
+    },
+    utils::{read_u32, read_u64},
+    verify::{verify_index, IndexChecksums},
+};
+
+/// Temporary subdirectory of the index directory, where the repaired index is built
+const REPAIR_PATH: &str = "repair";
+/// Subdirectory of the index directory, where the original index files are kept after a repair
+const REPAIR_BACKUP_PATH: &str = "repair_backup";
+
+/// Result of an index repair
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RepairResult {
+    /// Levels (segments of 65.536 documents) reported as corrupted by the verification before the repair
+    pub corrupted_levels: Vec<usize>,
+    /// Number of documents salvaged from the document store and reindexed
+    pub recovered_doc_count: usize,
+    /// Document ids of documents which could not be salvaged. They are marked as deleted, to preserve the ids of all other documents.
+    pub lost_doc_ids: Vec<usize>,
+    /// Number of documents in the repaired index, including deleted documents
+    pub indexed_doc_count: usize,
+}
+
+/// Repairs a partially corrupted index, e.g. with damaged or truncated levels (segments of 65.536 documents), without opening it.
+/// All documents are salvaged from the intact parts of the document store and reindexed, which rebuilds all derived structures:
+/// posting lists, facets, deleted documents and checksums.
+/// * Document ids are preserved: documents which can't be salvaged are marked as deleted and reported as lost_doc_ids.
+/// * Requires that all indexed fields are stored, and intact index.json and schema.json files.
+/// * The original index files are kept in the repair_backup subdirectory of the index directory.
+pub async fn repair_index(index_path: &Path, mute: bool) -> Result<RepairResult, String> {
+    let meta: IndexMetaObject = serde_json::from_reader(BufReader::new(
+        File::open(index_path.join(META_FILENAME))
+            .map_err(|e| format!("{}: {}", META_FILENAME, e))?,
+    ))
+    .map_err(|e| format!("{}: {}", META_FILENAME, e))?;
+    let schema: Vec<SchemaField> = serde_json::from_reader(BufReader::new(
+        File::open(index_path.join(SCHEMA_FILENAME))
+            .map_err(|e| format!("{}: {}", SCHEMA_FILENAME, e))?,
+    ))
+    .map_err(|e| format!("{}: {}", SCHEMA_FILENAME, e))?;
+    let synonyms: Vec<Synonym> = fs::read(index_path.join(SYNONYMS_FILENAME))
+        .ok()
+        .and_then(|synonyms| serde_json::from_slice(&synonyms).ok())
+        .unwrap_or_default();
+
+    if let Some(schema_field) = schema
+        .iter()
+        .find(|schema_field| schema_field.indexed && !schema_field.stored)
+    {
+        return Err(format!(
+            "repair requires all indexed fields to be stored: {}",
+            schema_field.field
+        ));
+    }
+
+    let mut result = RepairResult {
+        corrupted_levels: verify_index(index_path)
+            .map(|verify_result| verify_result.corrupted_levels)
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let checksums: Option<IndexChecksums> = fs::read(index_path.join(CHECKSUMS_FILENAME))
+        .ok()
+        .and_then(|checksums| serde_json::from_slice(&checksums).ok());
+    let level_starts: Vec<usize> = checksums.as_ref().map_or(Vec::new(), |checksums| {
+        checksums
+            .levels
+            .iter()
+            .map(|level_checksum| level_checksum.docstore.start as usize)
+            .collect()
+    });
+    let mut documents = read_docstore(
+        &fs::read(index_path.join(DOCSTORE_FILENAME)).unwrap_or_default(),
+        &level_starts,
+    );
+    let committed_doc_count =
+        checksums.map_or(documents.len(), |checksums| checksums.committed_doc_count);
+    documents.resize(committed_doc_count, None);
+
+    let mut deleted_doc_ids: BTreeSet<usize> = BTreeSet::new();
+    let delete_data = fs::read(index_path.join(DELETE_FILENAME)).unwrap_or_default();
+    for i in (0..delete_data.len() / 8 * 8).step_by(8) {
+        let doc_id = read_u64(&delete_data, i) as usize;
+        if doc_id < committed_doc_count {
+            deleted_doc_ids.insert(doc_id);
+        }
+    }
+
+    let repair_path = index_path.join(REPAIR_PATH);
+    let _ = fs::remove_dir_all(&repair_path);
+    fs::create_dir_all(&repair_path).map_err(|e| e.to_string())?;
+
+    let repair_index_arc: IndexArc = Arc::new(RwLock::new(create_index(
+        &repair_path,
+        meta,
+        &schema,
+        true,
+        &synonyms,
+        11,
+        true,
+    )?));
+    let permits = repair_index_arc.read().await.permits.clone();
+    let thread_number = available_parallelism().unwrap().get() as u32;
+
+    for (doc_id, document) in documents.into_iter().enumerate() {
+        let document = match document {
+            Some(document) => {
+                result.recovered_doc_count += 1;
+                document
+            }
+            None => {
+                if !deleted_doc_ids.contains(&doc_id) {
+                    result.lost_doc_ids.push(doc_id);
+                }
+                deleted_doc_ids.insert(doc_id);
+                Document::new()
+            }
+        };
+
+        repair_index_arc
+            .index_document(document, FileType::None)
+            .await;
+        // documents are indexed in parallel: wait until the document is indexed, to preserve the document ids
+        drop(permits.acquire_many(thread_number).await.unwrap());
+    }
+    commit_index(&repair_index_arc).await;
+    repair_index_arc
+        .delete_documents(
+            deleted_doc_ids
+                .into_iter()
+                .map(|doc_id| doc_id as u64)
+                .collect(),
+        )
+        .await;
+    result.indexed_doc_count = repair_index_arc.read().await.indexed_doc_count;
+    drop(repair_index_arc);
+
+    let backup_path = index_path.join(REPAIR_BACKUP_PATH);
+    let _ = fs::remove_dir_all(&backup_path);
+    fs::create_dir_all(&backup_path).map_err(|e| e.to_string())?;
+    for filename in [
+        INDEX_FILENAME,
+        DOCSTORE_FILENAME,
+        DELETE_FILENAME,
+        FACET_FILENAME,
+        FACET_VALUES_FILENAME,
+        CHECKSUMS_FILENAME,
+    ] {
+        if index_path.join(filename).exists() {
+            fs::rename(index_path.join(filename), backup_path.join(filename))
+                .map_err(|e| e.to_string())?;
+        }
+        if repair_path.join(filename).exists() {
+            fs::rename(repair_path.join(filename), index_path.join(filename))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let _ = fs::remove_dir_all(&repair_path);
+
+    if !mute {
+        println!(
+            "index repaired: documents recovered {} lost {}",
+            result.recovered_doc_count,
+            result.lost_doc_ids.len()
+        );
+    }
+
+    Ok(result)
+}
+
+/// Opens an index in repair mode: if the verification against its checksums detects corrupted index files,
+/// the index is repaired with repair_index before it is opened, instead of refusing to open or failing unpredictably during queries.
+/// Returns the opened index, and the result of the repair if a repair was necessary.
+pub async fn open_index_with_repair(
+    index_path: &Path,
+    mute: bool,
+) -> Result<(IndexArc, Option<RepairResult>), String> {
+    let repair_result = match verify_index(index_path) {
+        Ok(verify_result) if !verify_result.valid => Some(repair_index(index_path, mute).await?),
+        _ => None,
+    };
+    Ok((open_index(index_path, mute).await?, repair_result))
+}
+
+/// Salvages all intact documents from the document store, by document id.
+/// Documents which can't be decompressed or parsed are None.
+/// The start positions of the levels from the checksums are preferred over the level sizes in the document store, which might be corrupted.
+fn read_docstore(docstore_data: &[u8], level_starts: &[usize]) -> Vec<Option<Document>> {
+    let mut documents = Vec::new();
+    let mut level_start = 0;
+    for level in 0.. {
+        if let Some(start) = level_starts.get(level) {
+            level_start = *start;
+        }
+        if level_start + 4 > docstore_data.len() {
+            break;
+        }
+        let level_size = read_u32(docstore_data, level_start) as usize;
+        let level_data = &docstore_data
+            [level_start + 4..(level_start + 4 + level_size).min(docstore_data.len())];
+        level_start += 4 + level_size;
+        let is_last_level = level + 1 >= level_starts.len() && level_start >= docstore_data.len();
+        if level_data.len() < ROARING_BLOCK_SIZE * 4 {
+            break;
+        }
+
+        let mut level_documents = Vec::new();
+        let mut previous_pointer = ROARING_BLOCK_SIZE * 4;
+        for doc_id_local in 0..ROARING_BLOCK_SIZE {
+            let pointer = read_u32(level_data, doc_id_local * 4) as usize;
+            if pointer <= previous_pointer || pointer > level_data.len() {
+                level_documents.push(None);
+                continue;
+            }
+            level_documents.push(
+                zstd::decode_all(&level_data[previous_pointer..pointer])
+                    .ok()
+                    .and_then(|document| serde_json::from_slice::<Document>(&document).ok()),
+            );
+            previous_pointer = pointer;
+        }
+
+        if !is_last_level {
+            documents.extend(level_documents);
+        } else {
+            let level_doc_count = level_documents
+                .iter()
+                .rposition(|document| document.is_some())
+                .map_or(0, |position| position + 1);
+            level_documents.truncate(level_doc_count);
+            documents.extend(level_documents);
+        }
+    }
+    documents
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    expression::Expression,
+    index::{Index, Language},
+    ltr::LtrRescore,
+    search::{FunctionScore, ResultObject},
+};
+
+/// Rescore phase of a two-phase retrieval: the first phase collects the top window_size results with the cheap configuration of the search
+/// (BM25, without score functions), the rescore phase rescores only this window with the expensive configuration and reorders it:
+/// first the score functions, then the LTR model. This cuts the latency on huge indices, where the expensive configuration would otherwise be evaluated for every matching document.
+/// The results beyond the window keep their first phase order and score.
+/// The offset and length of the search should be applied after the rescore phase, with the first phase collecting at least offset + length results.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Rescore {
+    /// Number of top results of the first phase to rescore. Should be at least offset + length of the search.
+    #[serde(default = "default_window_size")]
+    pub window_size: usize,
+    /// Score functions combined with the first phase score of the results in the window (see Search::search)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_score: Option<FunctionScore>,
+    /// Name of an LTR model loaded into the index with Index::set_ltr_model, applied after the score functions (see LtrRescore)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+fn default_window_size() -> usize {
+    100
+}
+
+impl Index {
+    /// Checks that the score functions of the rescore are valid and its LTR model is loaded into the index
+    pub fn check_rescore(&self, rescore: &Rescore) -> Result<(), String> {
+        if let Some(function_score) = rescore.function_score.as_ref() {
+            Expression::from_function_score(function_score, self)?;
+        }
+        if let Some(model) = rescore.model.as_ref() {
+            if !self.ltr_models.contains_key(model) {
+                return Err(format!("ltr model not found: {}", model));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rescores the top window_size results of the first phase with the score functions and then the LTR model of the rescore, and reorders them by the new score.
+    pub fn rescore_window(
+        &self,
+        query_string: &str,
+        query_language: Option<Language>,
+        result_object: &mut ResultObject,
+        rescore: &Rescore,
+    ) -> Result<(), String> {
+        self.check_rescore(rescore)?;
+
+        if let Some(function_score) = rescore.function_score.as_ref() {
+            let expression = Expression::from_function_score(function_score, self)?;
+            let window_size = rescore.window_size.min(result_object.results.len());
+            let window = &mut result_object.results[..window_size];
+            for result in window.iter_mut() {
+                result.score = expression.evaluate(self, result.doc_id, result.score) as f32;
+            }
+            window.sort_by(|a, b| {
+                b.score
+                    .partial_cmp(&a.score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+
+        if let Some(model) = rescore.model.as_ref() {
+            self.rescore(
+                query_string,
+                query_language,
+                result_object,
+                &LtrRescore {
+                    model: model.clone(),
+                    window_size: rescore.window_size,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{expression::Expression, index::Index};
+
+pub(crate) const RUNTIME_FIELDS_FILENAME: &str = "runtime_fields.json";
+
+/// Runtime field: a named expression of numerical facet fields (doc-values), evaluated at query time, without reindexing, e.g. `{"name":"price_with_tax","expression":"price * 1.19"}`.
+/// Stored with the index in runtime_fields.json. See expression::Expression for operands, operators and functions; _score is not available in runtime fields.
+/// A runtime field can refer to the runtime fields defined before it.
+/// Runtime fields can be used:
+/// - as ResultSort field, and within sort expressions
+/// - in range filters: FacetFilter::F64 with the name of the runtime field
+/// - in returned documents: the values of the runtime fields are inserted into the documents returned by get_document, if no fields are specified or the runtime field is among them
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct RuntimeField {
+    pub name: String,
+    pub expression: String,
+}
+
+pub(crate) fn load_runtime_fields(index_path: &Path) -> Vec<RuntimeField> {
+    if let Ok(runtime_fields_file) = File::open(index_path.join(RUNTIME_FIELDS_FILENAME)) {
+        serde_json::from_reader(BufReader::new(runtime_fields_file)).unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+
+impl Index {
+    /// Set/replace the runtime fields of the index, which are available to every subsequent query (see RuntimeField).
+    /// Names have to be unique and must not be fields of the schema, expressions have to compile against the numerical facet fields and the preceding runtime fields.
+    pub fn set_runtime_fields(
+        &mut self,
+        runtime_fields: Vec<RuntimeField>,
+    ) -> Result<usize, String> {
+        for (i, runtime_field) in runtime_fields.iter().enumerate() {
+            if runtime_field.name.is_empty()
+                || self.schema_map.contains_key(&runtime_field.name)
+                || ["_score", "_id", "_random", "now"].contains(&runtime_field.name.as_str())
+            {
+                return Err(format!(
+                    "runtime field {}: name is empty, reserved or a field of the schema",
+                    runtime_field.name
+                ));
+            }
+            if runtime_fields[..i]
+                .iter()
+                .any(|previous| previous.name == runtime_field.name)
+            {
+                return Err(format!(
+                    "runtime field {}: duplicate name",
+                    runtime_field.name
+                ));
+            }
+            Expression::compile_runtime_field(
+                &runtime_field.expression,
+                self,
+                &runtime_fields[..i],
+            )
+            .map_err(|e| format!("runtime field {}: {}", runtime_field.name, e))?;
+        }
+
+        serde_json::to_writer(
+            &File::create(Path::new(&self.index_path_string).join(RUNTIME_FIELDS_FILENAME))
+                .map_err(|e| e.to_string())?,
+            &runtime_fields,
+        )
+        .map_err(|e| e.to_string())?;
+
+        self.runtime_fields = runtime_fields;
+        Ok(self.runtime_fields.len())
+    }
+
+    /// Get the runtime fields of the index
+    pub fn get_runtime_fields(&self) -> Vec<RuntimeField> {
+        self.runtime_fields.clone()
+    }
+
+    /// Names of the runtime fields of the index which read any of the given facet fields, directly or via another runtime field,
+    /// e.g. to hide the runtime fields derived from the hidden fields of a scoped API key.
+    pub fn runtime_fields_referencing(&self, fields: &[String]) -> Vec<String> {
+        self.runtime_fields
+            .iter()
+            .filter(|runtime_field| {
+                self.compile_runtime_field(&runtime_field.name)
+                    .and_then(Result::ok)
+                    .is_some_and(|expression| {
+                        expression
+                            .fields(self)
+                            .iter()
+                            .any(|field| fields.contains(field))
+                    })
+            })
+            .map(|runtime_field| runtime_field.name.clone())
+            .collect()
+    }
+
+    /// Compiles a runtime field of the index by name, None if there is no runtime field with that name
+    pub(crate) fn compile_runtime_field(&self, name: &str) -> Option<Result<Expression, String>> {
+        let i = self
+            .runtime_fields
+            .iter()
+            .position(|runtime_field| runtime_field.name == name)?;
+        Some(Expression::compile_runtime_field(
+            &self.runtime_fields[i].expression,
+            self,
+            &self.runtime_fields[..i],
+        ))
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use crate::{
+    index::{Document, Index, IndexArc},
+    snapshot::Snapshot,
+};
+
+/// Iterator over all live documents of an index in document id order, see Index::documents
+pub struct DocumentIterator<'a> {
+    index: &'a Index,
+    doc_id: usize,
+    doc_count: usize,
+    include_uncommited: bool,
+    fields: HashSet<String>,
+}
+
+impl Iterator for DocumentIterator<'_> {
+    type Item = (usize, Document);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.doc_id < self.doc_count {
+            let doc_id = self.doc_id;
+            self.doc_id += 1;
+            if let Ok(document) =
+                self.index
+                    .get_document(doc_id, self.include_uncommited, &None, &self.fields, &[])
+            {
+                return Some((doc_id, document));
+            }
+        }
+        None
+    }
+}
+
+impl Index {
+    /// Iterates over all live documents of the index in document id order: document id and stored fields, without going through search,
+    /// e.g. for exports, re-embedding jobs and offline analytics. Deleted documents are skipped.
+    /// * `include_uncommited`: include the documents which are not yet committed.
+    /// * `fields`: stored fields to return, all stored fields if empty.
+    ///
+    /// The iterator borrows the index, i.e. it holds the read lock of an IndexArc while iterating: for a long scan of a live index use DocumentCursor instead.
+    pub fn documents(&self, include_uncommited: bool, fields: Vec<String>) -> DocumentIterator<'_> {
+        let doc_count = if self.stored_field_names.is_empty() {
+            0
+        } else if include_uncommited {
+            self.indexed_doc_count
+        } else {
+            self.committed_doc_count
+        };
+        DocumentIterator {
+            index: self,
+            doc_id: 0,
+            doc_count,
+            include_uncommited,
+            fields: HashSet::from_iter(fields),
+        }
+    }
+}
+
+/// Full scan of all live documents of an IndexArc in batches: the read lock of the index is only held while a batch is read,
+/// so that documents can be indexed, updated and deleted concurrently.
+/// The scan is restricted to the documents indexed before the cursor was created (see Snapshot), documents deleted during the scan are skipped.
+/// ```no_run
+/// use seekstorm::{index::IndexArc, scan::DocumentCursor};
+///
+/// async fn print_documents(index_arc: &IndexArc) -> Result<(), String> {
+///     let mut cursor = DocumentCursor::new(index_arc, false, vec!["title".into()], 1000).await;
+///     loop {
+///         let batch = cursor.next_batch().await?;
+///         if batch.is_empty() {
+///             break;
+///         }
+///         for (doc_id, document) in batch {
+///             println!("{} {:?}", doc_id, document);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct DocumentCursor {
+    index_arc: IndexArc,
+    snapshot: Snapshot,
+    doc_id: usize,
+    doc_count: usize,
+    include_uncommited: bool,
+    fields: Vec<String>,
+    batch_size: usize,
+}
+
+impl DocumentCursor {
+    /// Cursor at the first document of the index, see Index::documents for the parameters.
+    /// * `batch_size`: maximum number of documents returned by next_batch.
+    pub async fn new(
+        index_arc: &IndexArc,
+        include_uncommited: bool,
+        fields: Vec<String>,
+        batch_size: usize,
+    ) -> DocumentCursor {
+        let index_ref = index_arc.read().await;
+        let doc_count = if include_uncommited {
+            index_ref.indexed_doc_count
+        } else {
+            index_ref.committed_doc_count
+        };
+        DocumentCursor {
+            index_arc: index_arc.clone(),
+            snapshot: index_ref.snapshot(),
+            doc_id: 0,
+            doc_count,
+            include_uncommited,
+            fields,
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    /// Returns the next batch of up to batch_size documents with their document id, or an empty batch at the end of the scan.
+    /// Returns an error if the index was compacted or cleared since the cursor was created, as the document ids were reassigned.
+    pub async fn next_batch(&mut self) -> Result<Vec<(usize, Document)>, String> {
+        let index_ref = self.index_arc.read().await;
+        if !index_ref.is_snapshot_current(&self.snapshot) {
+            return Err("index was compacted or cleared during the scan".to_string());
+        }
+
+        let mut documents = index_ref.documents(self.include_uncommited, self.fields.clone());
+        documents.doc_id = self.doc_id;
+        documents.doc_count = documents.doc_count.min(self.doc_count);
+
+        let mut batch = Vec::new();
+        for (doc_id, document) in documents.by_ref() {
+            batch.push((doc_id, document));
+            if batch.len() == self.batch_size {
+                break;
+            }
+        }
+        self.doc_id = documents.doc_id;
+        Ok(batch)
+    }
+}
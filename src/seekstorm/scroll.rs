@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::IndexArc,
+    search::{
+        FacetFilter, FacetValue, MissingOrder, QueryExpansion, QueryType, ResultSort, ResultType,
+        SearchFrom, SortOrder,
+    },
+};
+
+/// A batch of results of a scroll, in ascending document id order
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ScrollResult {
+    /// Document ids of the results in this batch
+    pub results: Vec<usize>,
+    /// Number of all documents matching the query
+    pub result_count_total: usize,
+    /// Continuation token for the next batch, None if all matching documents have been returned
+    pub scroll_id: Option<String>,
+}
+
+/// Scroll through all documents matching a query, e.g. to export millions of matches for analytics.
+/// The results are returned in batches in stable, ascending document id order, without ranking, and without offset limits.
+/// Each call continues after the last document of the previous batch, identified by the scroll_id continuation token.
+/// Scrolling is stateless: documents indexed after the scroll started are returned at the end, deleted documents are skipped.
+#[allow(clippy::too_many_arguments)]
+#[allow(async_fn_in_trait)]
+pub trait Scroll {
+    /// * `query_string`: query string
+    /// * `query_type_default`: default query type, if not specified in the query string
+    /// * `length`: maximum number of results per batch
+    /// * `include_uncommited`: include documents not yet committed
+    /// * `field_filter`: search only in the specified fields, empty for all fields
+    /// * `facet_filter`: filter the results by facet field values
+    /// * `scroll_id`: None for the first batch, otherwise the scroll_id returned with the previous batch
+    async fn scroll(
+        &self,
+        query_string: String,
+        query_type_default: QueryType,
+        length: usize,
+        include_uncommited: bool,
+        field_filter: Vec<String>,
+        facet_filter: Vec<FacetFilter>,
+        scroll_id: Option<String>,
+    ) -> Result<ScrollResult, String>;
+}
+
+impl Scroll for IndexArc {
+    async fn scroll(
+        &self,
+        query_string: String,
+        query_type_default: QueryType,
+        length: usize,
+        include_uncommited: bool,
+        field_filter: Vec<String>,
+        facet_filter: Vec<FacetFilter>,
+        scroll_id: Option<String>,
+    ) -> Result<ScrollResult, String> {
+        let min_doc_id = match scroll_id {
+            Some(scroll_id) => scroll_id
+                .parse::<usize>()
+                .map_err(|_| format!("scroll_id invalid: {}", scroll_id))?,
+            None => 0,
+        };
+
+        if length == 0 {
+            return Err("length must be greater than 0".to_string());
+        }
+
+        let result_object = self
+            .search_from(
+                query_string,
+                query_type_default,
+                0,
+                length,
+                ResultType::TopkCount,
+                include_uncommited,
+                field_filter,
+                Vec::new(),
+                facet_filter,
+                vec![ResultSort {
+                    field: "_id".into(),
+                    order: SortOrder::Ascending,
+                    base: FacetValue::None,
+                    missing: MissingOrder::Last,
+                }],
+                None,
+                None,
+                None,
+                QueryExpansion::default(),
+                min_doc_id,
+                None,
+            )
+            .await;
+
+        let results: Vec<usize> = result_object
+            .results
+            .iter()
+            .map(|result| result.doc_id)
+            .collect();
+
+        Ok(ScrollResult {
+            scroll_id: if results.len() == length {
+                results.last().map(|doc_id| (doc_id + 1).to_string())
+            } else {
+                None
+            },
+            result_count_total: result_object.result_count_total,
+            results,
+        })
+    }
+}
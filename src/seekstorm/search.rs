@@ -1,16 +1,24 @@
 use crate::commit::KEY_HEAD_SIZE;
+use crate::date::{date_histogram_ranges, parse_date_math, DateInterval};
+use crate::expression::Expression;
 use crate::geo_search::{decode_morton_2_d, point_distance_to_morton_range};
-use crate::index::{DistanceUnit, Facet, FieldType, ResultFacet};
+use crate::geo_shape::GeoShapeFilter;
+use crate::index::{DistanceUnit, Facet, FieldType, Language, ResultFacet, ValueType};
+use crate::ip::{ip_to_string, parse_cidr, parse_ip};
+use crate::join::{join_filter_sparse, JoinFilter};
 use crate::min_heap::Result;
-use crate::tokenizer::tokenizer;
+use crate::query_rewrite::SearchQuery;
+use crate::snapshot::Snapshot;
+use crate::tokenizer::{self, tokenizer, UNEXPANDED_TERM_PREFIX, UNSTEMMED_TERM_PREFIX};
 use crate::union::{union_docid_2, union_docid_3};
 use crate::utils::{
-    read_f32, read_f64, read_i16, read_i32, read_i64, read_i8, read_u16, read_u32, read_u64,
-    read_u8,
+    decimal_from_f64, decimal_to_f64, read_f32, read_f64, read_i16, read_i32, read_i64, read_i8,
+    read_u128, read_u16, read_u32, read_u64, read_u8,
 };
 use crate::{
+    add_result::{facet_count, is_facet_filter},
     index::{
-        get_max_score, AccessType, BlockObjectIndex, Index, IndexArc,
+        get_max_score, normalize_keyword, AccessType, BlockObjectIndex, Index, IndexArc,
         NonUniquePostingListObjectQuery, NonUniqueTermObject, PostingListObjectIndex,
         PostingListObjectQuery, QueueObject, SegmentIndex, SimilarityType, TermObject, DUMMY_VEC,
         DUMMY_VEC_8, MAX_POSITIONS_PER_TERM, SPEEDUP_FLAG,
@@ -22,15 +30,17 @@ use crate::{
 };
 
 use ahash::{AHashMap, AHashSet};
+use chrono::Utc;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use std::mem;
-use std::ops::Range;
+use std::ops::{Range, RangeInclusive};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc,
 };
+use std::time::{Duration, Instant};
 
 /// Specifies the default QueryType: The following query types are supported: **Union** (OR, disjunction), **Intersection** (AND, conjunction), **Phrase** (""), **Not** (-).
 /// The default QueryType is superseded if the query parser detects that a different query type is specified within the query string (+ - "").
@@ -55,10 +65,64 @@ pub enum ResultType {
     TopkCount = 2,
 }
 
+/// Minimum number of query terms a document has to match in a Union query, e.g. to require 75% of the terms of long queries to match.
+/// Query terms which don't occur in the index are included in the number of query terms, NOT (-) terms are excluded.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum MinimumShouldMatch {
+    /// Absolute number of query terms
+    Count(usize),
+    /// Percentage of the number of query terms, rounded down, e.g. 75.0 requires 3 of 4 or 2 of 3 terms to match
+    Percentage(f32),
+}
+
+impl MinimumShouldMatch {
+    /// Number of query terms a document has to match, for a query with term_count terms. 0 or 1 if all Union results are returned.
+    pub(crate) fn required_terms(&self, term_count: usize) -> usize {
+        match self {
+            MinimumShouldMatch::Count(count) => *count,
+            MinimumShouldMatch::Percentage(percentage) => {
+                (term_count as f32 * percentage.clamp(0.0, 100.0) / 100.0) as usize
+            }
+        }
+    }
+}
+
+/// Disables the query expansion of the index for a single query, without modifying the index configuration, e.g. for a "verbatim" search of exactly the entered terms.
+#[derive(Default, PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct QueryExpansion {
+    /// Query terms don't match their synonyms. Affects only documents indexed after the synonyms were defined.
+    #[serde(default)]
+    pub disable_synonyms: bool,
+    /// Query terms are not stemmed and match only the unstemmed terms of documents, which implies disable_synonyms.
+    /// Requires IndexMetaObject.index_unstemmed, otherwise ignored. Affects only documents indexed with IndexMetaObject.index_unstemmed.
+    #[serde(default)]
+    pub disable_stemming: bool,
+}
+
 pub(crate) struct SearchResult<'a> {
     pub topk_candidates: MinHeap<'a>,
     pub query_facets: Vec<ResultFacet>,
     pub skip_facet_count: bool,
+    /// Number of query terms a document has to match in a Union query, see MinimumShouldMatch. 0 or 1 if all Union results are returned.
+    pub minimum_should_match: usize,
+    /// Time after which the collection of results is aborted, see SearchRequest::timeout_ms
+    pub deadline: Option<Instant>,
+    /// True if the collection of results was aborted at the deadline
+    pub partial: bool,
+}
+
+impl SearchResult<'_> {
+    /// True if the deadline of the search is exceeded: the block loops stop collecting results, and the results and facets collected so far are returned as partial.
+    /// Checked per block of 65k document ids, so the deadline can be exceeded by the time required to process a block.
+    #[inline]
+    pub(crate) fn timed_out(&mut self) -> bool {
+        if !self.partial {
+            if let Some(deadline) = self.deadline {
+                self.partial = Instant::now() >= deadline;
+            }
+        }
+        self.partial
+    }
 }
 
 /// Contains the results returned when searching the index.
@@ -68,6 +132,10 @@ pub struct ResultObject {
     pub query: String,
     /// Vector of search query terms. Can be used e.g. for custom highlighting.
     pub query_terms: Vec<String>,
+    /// Phrases of the search query: sequences of query terms that have to appear adjacent and in order.
+    /// Used by the highlighter to highlight only the terms participating in a phrase match.
+    #[serde(default)]
+    pub query_phrases: Vec<Vec<String>>,
     /// Number of returned search results. Identical to results.len()
     pub result_count: usize,
 
@@ -80,6 +148,12 @@ pub struct ResultObject {
     /// List of facet fields: field name and vector of unique values and their counts.
     /// Unique values and their counts are only accurate if result_type=TopkCount or ResultType=Count, but not for ResultType=Topk
     pub facets: AHashMap<String, Facet>,
+    /// Point-in-time view of the index the search was executed on: follow-up steps of the query, e.g. get_document, should check it with Index::is_snapshot_current
+    #[serde(default)]
+    pub snapshot: Snapshot,
+    /// True if the search exceeded SearchRequest::timeout_ms: results, result_count_total and facets contain only the matches collected until then.
+    #[serde(default)]
+    pub partial: bool,
 }
 
 /// Create query_list and non_unique_query_list
@@ -97,6 +171,16 @@ pub enum RangeType {
     CountBelowRange,
 }
 
+/// Sort order of the values of a String or StringSet facet
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq)]
+pub enum FacetSort {
+    /// By count in descending order, ties by value in ascending order
+    #[default]
+    Count,
+    /// By value in ascending order
+    Value,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq)]
 pub enum QueryFacet {
     U8 {
@@ -154,15 +238,57 @@ pub enum QueryFacet {
         range_type: RangeType,
         ranges: Vec<(String, f64)>,
     },
+    /// Ranges of a Decimal field: the range boundaries are converted to the scale of the field without floating point artifacts
+    Decimal {
+        field: String,
+        range_type: RangeType,
+        ranges: Vec<(String, f64)>,
+    },
+    /// Ranges of a Date field: label and start of each range as date or date math expression, e.g. `["last week","now-7d/d"]`
+    Date {
+        field: String,
+        range_type: RangeType,
+        ranges: Vec<(String, String)>,
+    },
+    /// Date histogram of a Date field: counts per calendar interval, labeled with the start of the interval.
+    /// The buckets span the min/max values of the field in the index, limited to 65.535 buckets.
+    DateHistogram {
+        field: String,
+        interval: DateInterval,
+    },
+    /// Ranges of an Ip field: label and first IP address of each range, e.g. `["private","10.0.0.0"]`
+    Ip {
+        field: String,
+        range_type: RangeType,
+        ranges: Vec<(String, String)>,
+    },
     String {
         field: String,
         prefix: String,
         length: u16,
+        /// Sort order of the facet values: by count (default) or by value
+        #[serde(default)]
+        sort: FacetSort,
+        /// Only facet values with at least min_count matching documents are returned
+        #[serde(default)]
+        min_count: usize,
+        /// Number of facet values to skip, for paginating through facet values together with length
+        #[serde(default)]
+        offset: usize,
     },
     StringSet {
         field: String,
         prefix: String,
         length: u16,
+        /// Sort order of the facet values: by count (default) or by value
+        #[serde(default)]
+        sort: FacetSort,
+        /// Only facet values with at least min_count matching documents are returned
+        #[serde(default)]
+        min_count: usize,
+        /// Number of facet values to skip, for paginating through facet values together with length
+        #[serde(default)]
+        offset: usize,
     },
     Point {
         field: String,
@@ -189,6 +315,7 @@ pub enum Ranges {
     Timestamp(RangeType, Vec<(String, i64)>),
     F32(RangeType, Vec<(String, f32)>),
     F64(RangeType, Vec<(String, f64)>),
+    Ip(RangeType, Vec<(String, u128)>),
     Point(RangeType, Vec<(String, f64)>, Point, DistanceUnit),
     #[default]
     None,
@@ -209,6 +336,12 @@ pub enum FacetValue {
     Timestamp(i64),
     F32(f32),
     F64(f64),
+    /// Decimal value of a Decimal field, converted from the scaled integer to the nearest f64
+    Decimal(f64),
+    /// Date value of a Date field: Unix milliseconds (UTC)
+    Date(i64),
+    /// IP address of an Ip field, IPv4-mapped addresses in IPv4 notation
+    Ip(String),
     String(String),
     StringSet(Vec<String>),
     Point(Point),
@@ -216,6 +349,51 @@ pub enum FacetValue {
 }
 
 impl Index {
+    /// Returns true if the facet field (facet index idx) was not set in the document at index time.
+    /// Always false for indices created before index format version 3.3.
+    #[inline]
+    pub(crate) fn is_facet_missing(&self, idx: usize, doc_id: usize) -> bool {
+        if let Some(facets_presence_offset) = self.facets_presence_offset {
+            self.facets_file_mmap
+                [(self.facets_size_sum * doc_id) + facets_presence_offset + (idx >> 3)]
+                & (1 << (idx & 7))
+                == 0
+        } else {
+            false
+        }
+    }
+
+    /// Returns the value of a numerical facet field (facet index idx) as f64, used for expression evaluation. Missing and non-numerical values are returned as 0.
+    #[inline]
+    pub(crate) fn get_facet_value_f64(&self, idx: usize, doc_id: usize) -> f64 {
+        if self.is_facet_missing(idx, doc_id) {
+            return 0.0;
+        }
+
+        let address = (self.facets_size_sum * doc_id) + self.facets[idx].offset;
+        match self.facets[idx].field_type {
+            FieldType::U8 => self.facets_file_mmap[address] as f64,
+            FieldType::U16 => read_u16(&self.facets_file_mmap, address) as f64,
+            FieldType::U32 => read_u32(&self.facets_file_mmap, address) as f64,
+            FieldType::U64 => read_u64(&self.facets_file_mmap, address) as f64,
+            FieldType::I8 => read_i8(&self.facets_file_mmap, address) as f64,
+            FieldType::I16 => read_i16(&self.facets_file_mmap, address) as f64,
+            FieldType::I32 => read_i32(&self.facets_file_mmap, address) as f64,
+            FieldType::I64 | FieldType::Timestamp => {
+                read_i64(&self.facets_file_mmap, address) as f64
+            }
+            FieldType::Decimal => decimal_to_f64(
+                read_i64(&self.facets_file_mmap, address),
+                self.facets[idx].scale,
+            ),
+            // seconds, as the Unix timestamp `now` of expressions
+            FieldType::Date => read_i64(&self.facets_file_mmap, address) as f64 / 1000.0,
+            FieldType::F32 => read_f32(&self.facets_file_mmap, address) as f64,
+            FieldType::F64 => read_f64(&self.facets_file_mmap, address),
+            _ => 0.0,
+        }
+    }
+
     /// get_facet_value: Returns value from facet field for a doc_id even if schema stored=false (field not stored in document JSON).  
     /// Facet fields are more compact than fields stored in document JSON.
     /// Strings are stored more compact as indices to a unique term dictionary. Numbers are stored binary, not as strings.
@@ -300,8 +478,29 @@ impl Index {
                     );
                     FacetValue::F64(facet_value)
                 }
+                FieldType::Decimal => {
+                    let facet_value = read_i64(
+                        &self.facets_file_mmap,
+                        (self.facets_size_sum * doc_id) + self.facets[*field_idx].offset,
+                    );
+                    FacetValue::Decimal(decimal_to_f64(facet_value, self.facets[*field_idx].scale))
+                }
+                FieldType::Date => {
+                    let facet_value = read_i64(
+                        &self.facets_file_mmap,
+                        (self.facets_size_sum * doc_id) + self.facets[*field_idx].offset,
+                    );
+                    FacetValue::Date(facet_value)
+                }
+                FieldType::Ip => {
+                    let facet_value = read_u128(
+                        &self.facets_file_mmap,
+                        (self.facets_size_sum * doc_id) + self.facets[*field_idx].offset,
+                    );
+                    FacetValue::Ip(ip_to_string(facet_value))
+                }
 
-                FieldType::String => {
+                FieldType::String | FieldType::Keyword => {
                     let facet_id = read_u16(
                         &self.facets_file_mmap,
                         (self.facets_size_sum * doc_id) + self.facets[*field_idx].offset,
@@ -393,10 +592,26 @@ pub enum FacetFilter {
         field: String,
         filter: Range<f32>,
     },
+    /// Range of a F64 facet field, or of a runtime field (see runtime::RuntimeField)
     F64 {
         field: String,
         filter: Range<f64>,
     },
+    /// Range of a Decimal field: the range boundaries are converted to the scale of the field without floating point artifacts
+    Decimal {
+        field: String,
+        filter: Range<f64>,
+    },
+    /// Range of a Date field: start (inclusive) and end (exclusive) as date or date math expression, e.g. `{"start":"now-7d/d","end":"now"}`
+    Date {
+        field: String,
+        filter: Range<String>,
+    },
+    /// IP addresses or CIDR subnets of an Ip field, e.g. `["10.0.0.0/8","2001:db8::1"]`: matches documents with an IP address contained in any of them
+    Ip {
+        field: String,
+        filter: Vec<String>,
+    },
     String {
         field: String,
         filter: Vec<String>,
@@ -409,6 +624,355 @@ pub enum FacetFilter {
         field: String,
         filter: (Point, Range<f64>, DistanceUnit),
     },
+    /// Shape of a GeoShape field: matches documents whose shape intersects, contains or lies within the shape of the filter,
+    /// e.g. `{"field":"territory","filter":{"relation":"Contains","shape":{"type":"Point","coordinates":[13.4,52.5]}}}`
+    GeoShape {
+        field: String,
+        filter: GeoShapeFilter,
+    },
+    /// Join field: matches the parent documents with at least one child document of the relation matching the query of the filter,
+    /// e.g. `{"field":"join","filter":{"relation":"answer","query":"borrow checker"}}` for the questions with a matching answer
+    HasChild {
+        field: String,
+        filter: JoinFilter,
+    },
+    /// Join field: matches the child documents whose parent document of the relation matches the query of the filter,
+    /// e.g. `{"field":"join","filter":{"relation":"question","query":"lifetimes"}}` for the answers of matching questions
+    HasParent {
+        field: String,
+        filter: JoinFilter,
+    },
+}
+
+/// Converts the facet filters into a sparse filter per facet field of the index, followed by the F64 range filters of runtime fields (see runtime::RuntimeField)
+/// and the matching document ids of GeoShape filters, obtained from the R-tree of the field.
+/// HasChild and HasParent filters are skipped, they are resolved by join::join_filter_sparse before the search.
+/// Filters of fields, which are not facet fields of the matching type, are ignored. A later filter of the same facet field replaces an earlier one.
+pub(crate) fn facet_filter_sparse(
+    index_ref: &Index,
+    facet_filter: &[FacetFilter],
+) -> Vec<FilterSparse> {
+    let mut facet_filter_sparse: Vec<FilterSparse> = Vec::new();
+    if !facet_filter.is_empty() {
+        facet_filter_sparse = vec![FilterSparse::None; index_ref.facets.len()];
+        for facet_filter_item in facet_filter.iter() {
+            match &facet_filter_item {
+                FacetFilter::U8 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::U8 {
+                            facet_filter_sparse[*idx] = FilterSparse::U8(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::U16 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::U16 {
+                            facet_filter_sparse[*idx] = FilterSparse::U16(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::U32 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::U32 {
+                            facet_filter_sparse[*idx] = FilterSparse::U32(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::U64 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::U64 {
+                            facet_filter_sparse[*idx] = FilterSparse::U64(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::I8 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::I8 {
+                            facet_filter_sparse[*idx] = FilterSparse::I8(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::I16 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::I16 {
+                            facet_filter_sparse[*idx] = FilterSparse::I16(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::I32 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::I32 {
+                            facet_filter_sparse[*idx] = FilterSparse::I32(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::I64 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::I64 {
+                            facet_filter_sparse[*idx] = FilterSparse::I64(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::Timestamp { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::Timestamp {
+                            facet_filter_sparse[*idx] = FilterSparse::Timestamp(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::F32 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::F32 {
+                            facet_filter_sparse[*idx] = FilterSparse::F32(filter.clone())
+                        }
+                    }
+                }
+                FacetFilter::F64 { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::F64 {
+                            facet_filter_sparse[*idx] = FilterSparse::F64(filter.clone())
+                        }
+                    } else if let Some(Ok(expression)) = index_ref.compile_runtime_field(field) {
+                        facet_filter_sparse
+                            .push(FilterSparse::Expression(expression, filter.clone()))
+                    }
+                }
+                FacetFilter::Decimal { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::Decimal {
+                            let scale = index_ref.facets[*idx].scale;
+                            facet_filter_sparse[*idx] = FilterSparse::I64(
+                                decimal_from_f64(filter.start, scale)
+                                    ..decimal_from_f64(filter.end, scale),
+                            )
+                        }
+                    }
+                }
+                FacetFilter::Date { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        let facet = &index_ref.facets[*idx];
+                        if facet.field_type == FieldType::Date {
+                            let now = Utc::now().timestamp_millis();
+                            if let (Some(start), Some(end)) = (
+                                parse_date_math(&filter.start, now, &facet.date_formats),
+                                parse_date_math(&filter.end, now, &facet.date_formats),
+                            ) {
+                                facet_filter_sparse[*idx] = FilterSparse::I64(start..end)
+                            }
+                        }
+                    }
+                }
+                FacetFilter::Ip { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::Ip {
+                            if let Some(ranges) = filter
+                                .iter()
+                                .map(|cidr| parse_cidr(cidr))
+                                .collect::<Option<Vec<_>>>()
+                            {
+                                facet_filter_sparse[*idx] = FilterSparse::Ip(ranges)
+                            }
+                        }
+                    }
+                }
+                FacetFilter::String { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        let facet = &index_ref.facets[*idx];
+                        if matches!(facet.field_type, FieldType::String | FieldType::Keyword) {
+                            let mut string_id_vec = Vec::new();
+                            for value in filter
+                                .iter()
+                                .flat_map(|value| index_ref.facet_filter_values(field, value))
+                                .map(|value| normalize_keyword(&value, &facet.normalizers))
+                            {
+                                if let Some(facet_value_id) = facet.values.get_index_of(&value) {
+                                    string_id_vec.push(facet_value_id as u16);
+                                }
+                            }
+                            facet_filter_sparse[*idx] = FilterSparse::String(string_id_vec);
+                        }
+                    }
+                }
+
+                FacetFilter::StringSet { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::StringSet {
+                            let mut string_id_vec = Vec::new();
+                            for value in filter
+                                .iter()
+                                .flat_map(|value| index_ref.facet_filter_values(field, value))
+                            {
+                                if let Some(facet_value_ids) =
+                                    index_ref.string_set_to_single_term_id_vec[*idx].get(&value)
+                                {
+                                    for code in facet_value_ids.iter() {
+                                        string_id_vec.push(*code);
+                                    }
+                                }
+                            }
+                            facet_filter_sparse[*idx] = FilterSparse::String(string_id_vec);
+                        }
+                    }
+                }
+
+                FacetFilter::Point { field, filter } => {
+                    if let Some(idx) = index_ref.facets_map.get(field) {
+                        if index_ref.facets[*idx].field_type == FieldType::Point {
+                            facet_filter_sparse[*idx] = FilterSparse::Point(
+                                filter.0.clone(),
+                                filter.1.clone(),
+                                filter.2.clone(),
+                                point_distance_to_morton_range(&filter.0, filter.1.end, &filter.2),
+                            );
+                        }
+                    }
+                }
+
+                FacetFilter::GeoShape { field, filter } => {
+                    if let Some(shape_index) = index_ref.geo_shapes.get(field) {
+                        if filter.shape.validate().is_ok() {
+                            facet_filter_sparse
+                                .push(FilterSparse::DocIds(shape_index.search(filter)));
+                        }
+                    }
+                }
+
+                FacetFilter::HasChild { .. } | FacetFilter::HasParent { .. } => {}
+            }
+        }
+    }
+
+    facet_filter_sparse
+}
+
+impl QueryFacet {
+    /// Name of the facet field, empty for QueryFacet::None
+    pub fn field(&self) -> &str {
+        match self {
+            QueryFacet::U8 { field, .. }
+            | QueryFacet::U16 { field, .. }
+            | QueryFacet::U32 { field, .. }
+            | QueryFacet::U64 { field, .. }
+            | QueryFacet::I8 { field, .. }
+            | QueryFacet::I16 { field, .. }
+            | QueryFacet::I32 { field, .. }
+            | QueryFacet::I64 { field, .. }
+            | QueryFacet::Timestamp { field, .. }
+            | QueryFacet::F32 { field, .. }
+            | QueryFacet::F64 { field, .. }
+            | QueryFacet::Decimal { field, .. }
+            | QueryFacet::Date { field, .. }
+            | QueryFacet::DateHistogram { field, .. }
+            | QueryFacet::Ip { field, .. }
+            | QueryFacet::String { field, .. }
+            | QueryFacet::StringSet { field, .. }
+            | QueryFacet::Point { field, .. } => field,
+            QueryFacet::None => "",
+        }
+    }
+}
+
+impl FacetFilter {
+    /// Name of the filtered facet field
+    pub fn field(&self) -> &str {
+        match self {
+            FacetFilter::U8 { field, .. }
+            | FacetFilter::U16 { field, .. }
+            | FacetFilter::U32 { field, .. }
+            | FacetFilter::U64 { field, .. }
+            | FacetFilter::I8 { field, .. }
+            | FacetFilter::I16 { field, .. }
+            | FacetFilter::I32 { field, .. }
+            | FacetFilter::I64 { field, .. }
+            | FacetFilter::Timestamp { field, .. }
+            | FacetFilter::F32 { field, .. }
+            | FacetFilter::F64 { field, .. }
+            | FacetFilter::Decimal { field, .. }
+            | FacetFilter::Date { field, .. }
+            | FacetFilter::Ip { field, .. }
+            | FacetFilter::String { field, .. }
+            | FacetFilter::StringSet { field, .. }
+            | FacetFilter::Point { field, .. }
+            | FacetFilter::GeoShape { field, .. }
+            | FacetFilter::HasChild { field, .. }
+            | FacetFilter::HasParent { field, .. } => field,
+        }
+    }
+
+    /// Whether the filter is a HasChild or HasParent filter of a Join field
+    pub fn is_join(&self) -> bool {
+        matches!(
+            self,
+            FacetFilter::HasChild { .. } | FacetFilter::HasParent { .. }
+        )
+    }
+}
+
+impl Index {
+    /// Checks that the sort expressions of result_sort and the function score compile against the index (see Expression).
+    /// Search ignores a sort field or function score which can't be compiled, e.g. with an unknown field or a syntax error.
+    pub fn check_ranking(
+        &self,
+        result_sort: &[ResultSort],
+        function_score: Option<&FunctionScore>,
+    ) -> std::result::Result<(), String> {
+        if let Some(function_score) = function_score {
+            Expression::from_function_score(function_score, self)?;
+        }
+        for sort in result_sort.iter() {
+            if sort.field != "_score"
+                && sort.field != "_id"
+                && !self.facets_map.contains_key(&sort.field)
+            {
+                Expression::compile(&sort.field, self)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that all facet filters refer to facet fields of the matching type, are F64 range filters of runtime fields, or join filters of Join fields.
+    /// Search silently ignores other filters, which is not acceptable if a filter enforces access restrictions.
+    pub fn check_facet_filter(
+        &self,
+        facet_filter: &[FacetFilter],
+    ) -> std::result::Result<(), String> {
+        for facet_filter_item in facet_filter.iter() {
+            if let FacetFilter::HasChild { field, filter }
+            | FacetFilter::HasParent { field, filter } = facet_filter_item
+            {
+                if !self.joins.contains_key(field) {
+                    return Err(format!("join filter field is not a join field: {}", field));
+                }
+                self.check_facet_filter(&filter.facet_filter)?;
+                continue;
+            }
+            if let Some(Err(e)) = self.compile_runtime_field(facet_filter_item.field()) {
+                return Err(e);
+            }
+            if facet_filter_sparse(self, std::slice::from_ref(facet_filter_item))
+                .iter()
+                .all(|filter| *filter == FilterSparse::None)
+            {
+                return Err(format!(
+                    "facet filter field is not a facet field of the matching type: {}",
+                    facet_filter_item.field()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the document matches all facet filters, the same way search applies them, e.g. before returning a document by its id.
+    /// Returns false if a facet filter can't be applied (see check_facet_filter), and for join filters, which require a search.
+    pub fn is_facet_filter_match(&self, doc_id: usize, facet_filter: &[FacetFilter]) -> bool {
+        if facet_filter.is_empty() {
+            return true;
+        }
+        doc_id < self.indexed_doc_count
+            && !facet_filter.iter().any(FacetFilter::is_join)
+            && self.check_facet_filter(facet_filter).is_ok()
+            && !is_facet_filter(self, &facet_filter_sparse(self, facet_filter), doc_id)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq)]
@@ -426,7 +990,13 @@ pub(crate) enum FilterSparse {
     F32(Range<f32>),
     F64(Range<f64>),
     String(Vec<u16>),
+    Ip(Vec<RangeInclusive<u128>>),
     Point(Point, Range<f64>, DistanceUnit, Range<u64>),
+    /// Range filter of a runtime field, evaluated per document
+    #[serde(skip)]
+    Expression(Expression, Range<f64>),
+    /// Matching document ids, e.g. of a GeoShape filter
+    DocIds(AHashSet<usize>),
     #[default]
     None,
 }
@@ -437,27 +1007,300 @@ pub enum SortOrder {
     Descending = 1,
 }
 
+/// Position of results with a missing sort field value (the field was not set in the document), independent of the sort order.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub enum MissingOrder {
+    First,
+    #[default]
+    Last,
+}
+
+/// Sort key: facet field name or the special _score or _id (document id) fields, sort order, base for Point distance sorting, and position of missing values.
+/// The field can also be an expression (see expression::Expression), e.g. `random(42)` for a seeded random order, stable across pages, to fetch a representative sample of the matching documents.
 #[derive(Clone, Deserialize, Serialize)]
 pub struct ResultSort {
     pub field: String,
     pub order: SortOrder,
     pub base: FacetValue,
+    #[serde(default)]
+    pub missing: MissingOrder,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone)]
 pub struct ResultSortIndex<'a> {
-    pub idx: usize,
+    /// facet index, or None for the special _score field and expressions
+    pub idx: Option<usize>,
     pub order: SortOrder,
     pub base: &'a FacetValue,
+    pub missing: MissingOrder,
+    /// compiled expression, if the sort field is neither a facet field nor _score
+    pub expression: Option<Expression>,
+    /// true for the special _id field: the document id
+    pub doc_id: bool,
 }
 
 /// latitude lat
 /// longitude lon
 pub type Point = Vec<f64>;
 
+/// Modifier applied to the facet field value of ScoreFunction::FieldValueFactor.
+/// Log: log10(x), Log1p: log10(1+x), Log2p: log10(2+x), Ln: ln(x), Ln1p: ln(1+x), Ln2p: ln(2+x), Square: x², Sqrt: √x, Reciprocal: 1/x
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub enum FieldValueModifier {
+    #[default]
+    None,
+    Log,
+    Log1p,
+    Log2p,
+    Ln,
+    Ln1p,
+    Ln2p,
+    Square,
+    Sqrt,
+    Reciprocal,
+}
+
+/// Shape of the decay curve of ScoreFunction::Decay.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub enum DecayFunction {
+    #[default]
+    Gauss,
+    Linear,
+    Exponential,
+}
+
+/// Declarative score function, evaluated per result from its facet field value.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum ScoreFunction {
+    /// factor * facet field value, transformed by modifier. missing: value used if the field is not set in the document.
+    FieldValueFactor {
+        field: String,
+        #[serde(default = "default_factor")]
+        factor: f64,
+        #[serde(default)]
+        modifier: FieldValueModifier,
+        #[serde(default)]
+        missing: f64,
+    },
+    /// 1.0 within origin ± offset, decaying to the value decay at distance offset + scale from origin.
+    /// Numerical and Timestamp facet fields: origin defaults to 0, or to now for Timestamp fields (Unix timestamp in seconds), scale and offset in field units (seconds for Timestamp).
+    /// Point facet fields: distance from base [lat, lon] in unit, origin is ignored.
+    /// Results where the field is not set in the document get the score function value 0.
+    Decay {
+        field: String,
+        #[serde(default)]
+        function: DecayFunction,
+        #[serde(default)]
+        origin: Option<f64>,
+        #[serde(default)]
+        base: Option<Point>,
+        #[serde(default = "default_unit")]
+        unit: DistanceUnit,
+        scale: f64,
+        #[serde(default)]
+        offset: f64,
+        #[serde(default = "default_decay")]
+        decay: f64,
+    },
+}
+
+fn default_factor() -> f64 {
+    1.0
+}
+
+fn default_decay() -> f64 {
+    0.5
+}
+
+fn default_unit() -> DistanceUnit {
+    DistanceUnit::Kilometers
+}
+
+/// How the values of multiple score functions are combined with each other.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub enum ScoreMode {
+    #[default]
+    Multiply,
+    Sum,
+    Avg,
+    Max,
+    Min,
+}
+
+/// How the combined score function value is combined with the BM25 score of the result.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub enum BoostMode {
+    #[default]
+    Multiply,
+    Sum,
+    Replace,
+}
+
+/// Score functions (e.g. freshness or proximity boosting) combined with the BM25 score of the results.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+pub struct FunctionScore {
+    pub functions: Vec<ScoreFunction>,
+    #[serde(default)]
+    pub score_mode: ScoreMode,
+    #[serde(default)]
+    pub boost_mode: BoostMode,
+}
+
+/// Parameters of Search::search, built with SearchRequest::new and the builder methods, so that only the used parameters have to be set:
+/// ```no_run
+/// use seekstorm::{
+///     index::IndexArc,
+///     search::{FacetFilter, ResultObject, Search, SearchRequest},
+/// };
+///
+/// async fn search_german(index_arc: &IndexArc) -> ResultObject {
+///     let search_request = SearchRequest::new("test")
+///         .length(20)
+///         .include_uncommited(true)
+///         .facet_filter(vec![FacetFilter::String {field: "language".into(),filter: vec!["german".into()]}]);
+///     index_arc.search(search_request).await
+/// }
+/// ```
+/// See Search::search for the description of the parameters.
+#[derive(Clone)]
+pub struct SearchRequest {
+    /// Query string, + - "" search operators are recognized. Default: empty (index facets are returned).
+    pub query_string: String,
+    /// Default: QueryType::Intersection
+    pub query_type_default: QueryType,
+    /// Default: 0
+    pub offset: usize,
+    /// Default: 10
+    pub length: usize,
+    /// Default: ResultType::TopkCount
+    pub result_type: ResultType,
+    /// Default: false
+    pub include_uncommited: bool,
+    /// Default: all indexed fields are searched
+    pub field_filter: Vec<String>,
+    /// Default: no facets are returned
+    pub query_facets: Vec<QueryFacet>,
+    /// Default: results are not facet filtered
+    pub facet_filter: Vec<FacetFilter>,
+    /// Default: results are sorted by _score. Sort expressions which can't be compiled are ignored, see Index::check_ranking
+    pub result_sort: Vec<ResultSort>,
+    /// Default: the query language is detected
+    pub query_language: Option<Language>,
+    /// Default: None. A function score which can't be compiled is ignored, see Index::check_ranking
+    pub function_score: Option<FunctionScore>,
+    /// Default: None
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    /// Default: synonym expansion and stemming as configured for the index
+    pub query_expansion: QueryExpansion,
+    /// Default: None (no timeout)
+    pub timeout_ms: Option<u64>,
+}
+
+impl Default for SearchRequest {
+    fn default() -> Self {
+        SearchRequest {
+            query_string: String::new(),
+            query_type_default: QueryType::Intersection,
+            offset: 0,
+            length: 10,
+            result_type: ResultType::TopkCount,
+            include_uncommited: false,
+            field_filter: Vec::new(),
+            query_facets: Vec::new(),
+            facet_filter: Vec::new(),
+            result_sort: Vec::new(),
+            query_language: None,
+            function_score: None,
+            minimum_should_match: None,
+            query_expansion: QueryExpansion::default(),
+            timeout_ms: None,
+        }
+    }
+}
+
+impl SearchRequest {
+    /// Search request for the query string, with the default values for all other parameters
+    pub fn new(query_string: impl Into<String>) -> Self {
+        SearchRequest {
+            query_string: query_string.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn query_type_default(mut self, query_type_default: QueryType) -> Self {
+        self.query_type_default = query_type_default;
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn length(mut self, length: usize) -> Self {
+        self.length = length;
+        self
+    }
+
+    pub fn result_type(mut self, result_type: ResultType) -> Self {
+        self.result_type = result_type;
+        self
+    }
+
+    pub fn include_uncommited(mut self, include_uncommited: bool) -> Self {
+        self.include_uncommited = include_uncommited;
+        self
+    }
+
+    pub fn field_filter(mut self, field_filter: Vec<String>) -> Self {
+        self.field_filter = field_filter;
+        self
+    }
+
+    pub fn query_facets(mut self, query_facets: Vec<QueryFacet>) -> Self {
+        self.query_facets = query_facets;
+        self
+    }
+
+    pub fn facet_filter(mut self, facet_filter: Vec<FacetFilter>) -> Self {
+        self.facet_filter = facet_filter;
+        self
+    }
+
+    pub fn result_sort(mut self, result_sort: Vec<ResultSort>) -> Self {
+        self.result_sort = result_sort;
+        self
+    }
+
+    pub fn query_language(mut self, query_language: Language) -> Self {
+        self.query_language = Some(query_language);
+        self
+    }
+
+    pub fn function_score(mut self, function_score: FunctionScore) -> Self {
+        self.function_score = Some(function_score);
+        self
+    }
+
+    pub fn minimum_should_match(mut self, minimum_should_match: MinimumShouldMatch) -> Self {
+        self.minimum_should_match = Some(minimum_should_match);
+        self
+    }
+
+    pub fn query_expansion(mut self, query_expansion: QueryExpansion) -> Self {
+        self.query_expansion = query_expansion;
+        self
+    }
+
+    pub fn timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+}
+
 /// Search the index for all indexed documents, both for committed and uncommitted documents.
 /// The latter enables true realtime search: documents are available for search in exact the same millisecond they are indexed.
-/// Arguments:
+/// Arguments: the fields of SearchRequest (see SearchRequest::new for a search request with default values)
 /// * `query_string`: query string + - "" search operators are recognized.
 /// * `query_type_default`: Specifiy default QueryType: **Union** (OR, disjunction), **Intersection** (AND, conjunction), **Phrase** (""), **Not** (-).
 ///    The default QueryType is superseded if the query parser detects that a different query type is specified within the query string (+ - "").
@@ -473,7 +1316,7 @@ pub type Point = Vec<f64>;
 ///    If the length property of a QueryFacet is set to 0 then no facet values for that facet are collected, counted and returned at query time. That decreases the query latency significantly.
 ///    The facet values are sorted by the frequency of the appearance of the value within the indexed documents matching the query in descending order.
 ///    Examples:
-///    query_facets = vec![QueryFacet::String {field: "language".into(),prefix: "ger".into(),length: 5},QueryFacet::String {field: "brand".into(),prefix: "a".into(),length: 5}];
+///    query_facets = vec![QueryFacet::String {field: "language".into(),prefix: "ger".into(),length: 5,sort: FacetSort::Count,min_count: 0,offset: 0},QueryFacet::String {field: "brand".into(),prefix: "a".into(),length: 5,sort: FacetSort::Count,min_count: 0,offset: 0}];
 ///    query_facets = vec![QueryFacet::U8 {field: "age".into(), range_type: RangeType::CountWithinRange, ranges: vec![("0-20".into(), 0),("20-40".into(), 20), ("40-60".into(), 40),("60-80".into(), 60), ("80-100".into(), 80)]}];
 ///    query_facets = vec![QueryFacet::Point {field: "location".into(),base:vec![38.8951, -77.0364],unit:DistanceUnit::Kilometers,range_type: RangeType::CountWithinRange,ranges: vec![ ("0-200".into(), 0.0),("200-400".into(), 200.0), ("400-600".into(), 400.0), ("600-800".into(), 600.0), ("800-1000".into(), 800.0)]}];
 /// * `facet_filter`: Search results are filtered to documents matching specific string values or numerical ranges in the facet fields. If set to Vec::new() then result are not facet filtered.
@@ -491,9 +1334,29 @@ pub type Point = Vec<f64>;
 ///    until the n-th field value is either not equal or the last field is reached.
 ///    A special _score field (BM25x), reflecting how relevant the result is for a given search query (phrase match, match in title etc.) can be combined with any of the other sort fields as primary, secondary or n-th search criterium.
 ///    Sort is only enabled on facet fields that are defined in schema at create_index!
+///   Results where a sort field is missing (not set in the document) are sorted last per default, or first with missing: MissingOrder::First, independent of the sort order.
+///   If _score is not among the sort fields, then ties of all sort fields are broken by _score in descending order.
+///   The sort field can also be an expression of numerical facet fields, _score and now, e.g. "_score * log(1+popularity)", compiled once per request (see expression::Expression).
+///   If the first sort field is an expression, then it is used as scoring modifier: its value replaces the _score of the results.
 ///    Examples:
-///    result_sort = vec![ResultSort {field: "price".into(), order: SortOrder::Descending, base: FacetValue::None},ResultSort {field: "lamguage".into(), order: SortOrder::Ascending, base: FacetValue::None}];
-///    result_sort = vec![ResultSort {field: "location".into(),order: SortOrder::Ascending, base: FacetValue::Point(vec![38.8951, -77.0364])}];
+///    result_sort = vec![ResultSort {field: "price".into(), order: SortOrder::Descending, base: FacetValue::None, missing: MissingOrder::Last},ResultSort {field: "language".into(), order: SortOrder::Ascending, base: FacetValue::None, missing: MissingOrder::Last}];
+///    result_sort = vec![ResultSort {field: "location".into(),order: SortOrder::Ascending, base: FacetValue::Point(vec![38.8951, -77.0364]), missing: MissingOrder::Last}];
+///   result_sort = vec![ResultSort {field: "_score".into(),order: SortOrder::Descending, base: FacetValue::None, missing: MissingOrder::Last},ResultSort {field: "date".into(),order: SortOrder::Descending, base: FacetValue::None, missing: MissingOrder::First}];
+/// * `query_language`: Language hint used to stem the query, if the index was created with StemmerType::Detect.
+///   If None, then the query language is detected, falling back to the default_language of the index if the detection is not reliable (e.g. for short queries).
+///   Ignored for StemmerType::None and StemmerType::Language.
+///   Example: query_language = Some(Language::German);
+/// * `function_score`: Score functions combined with the BM25 score, replacing the _score of the results (scoring modifier), e.g. for freshness or proximity boosting.
+///   Compiled to an expression (see expression::Expression), which is used as primary sort key before the result_sort fields.
+///   Example: function_score = Some(FunctionScore {functions: vec![ScoreFunction::Decay {field: "date".into(), function: DecayFunction::Gauss, origin: None, base: None, unit: DistanceUnit::Kilometers, scale: 30.0*86400.0, offset: 86400.0, decay: 0.5}], score_mode: ScoreMode::Multiply, boost_mode: BoostMode::Multiply});
+/// * `minimum_should_match`: Minimum number of query terms a document has to match in a Union query, as absolute count or percentage of the query terms.
+///   Reduces the noisy recall of long queries, where documents matching only a single term would be returned. Ignored for other query types.
+///   Example: minimum_should_match = Some(MinimumShouldMatch::Percentage(75.0));
+/// * `query_expansion`: Disables synonym expansion and/or stemming for this query, without modifying the index configuration (see QueryExpansion).
+///   Example: query_expansion = QueryExpansion {disable_synonyms: true, disable_stemming: true};
+/// * `timeout_ms`: Maximum time in milliseconds to collect the results. If exceeded, the search stops collecting and returns the results and facets collected so far,
+///   with ResultObject::partial set. The results are then not necessarily the top-k of all matches, and result_count_total and the facet counts are incomplete.
+///   Example: timeout_ms = Some(100);
 ///  
 ///    If query_string is empty, then index facets (collected at index time) are returned, otherwise query facets (collected at query time) are returned.
 ///    Facets are defined in 3 different places:
@@ -501,22 +1364,9 @@ pub type Point = Vec<f64>;
 ///    the facet field values are set in index_document at index time,
 ///    the query_facets/facet_filter search parameters are specified at query time.
 ///    Facets are then returned in the search result object.
-#[allow(clippy::too_many_arguments)]
 #[allow(async_fn_in_trait)]
 pub trait Search {
-    async fn search(
-        &self,
-        query_string: String,
-        query_type_default: QueryType,
-        offset: usize,
-        length: usize,
-        result_type: ResultType,
-        include_uncommited: bool,
-        field_filter: Vec<String>,
-        query_facets: Vec<QueryFacet>,
-        facet_filter: Vec<FacetFilter>,
-        result_sort: Vec<ResultSort>,
-    ) -> ResultObject;
+    async fn search(&self, search_request: SearchRequest) -> ResultObject;
 }
 
 /// Non-recursive binary search of non-consecutive u64 values in a slice of bytes
@@ -627,53 +1477,286 @@ pub(crate) fn decode_posting_list_object(
     }
 }
 
-impl Search for IndexArc {
-    /// Search the index for all indexed documents, both for committed and uncommitted documents.
-    /// The latter enables true realtime search: documents are available for search in exact the same millisecond they are indexed.
-    /// Arguments:
-    /// * `query_string`: query string + - "" search operators are recognized.
-    /// * `query_type_default`: Specifiy default QueryType: **Union** (OR, disjunction), **Intersection** (AND, conjunction), **Phrase** (""), **Not** (-).
-    ///    The default QueryType is superseded if the query parser detects that a different query type is specified within the query string (+ - "").
-    /// * `offset`: offset of search results to return.
-    /// * `length`: number of search results to return.
-    /// * `result_type`: type of search results to return: Count, Topk, TopkCount.
-    /// * `include_uncommited`: true realtime search: include indexed documents which where not yet committed into search results.
-    /// * `field_filter`: Specify field names where to search at querytime, whereas SchemaField.indexed is set at indextime. If set to Vec::new() then all indexed fields are searched.
-    /// * `query_facets`: Must be set if facets should be returned in ResultObject. If set to Vec::new() then no facet fields are returned.
-    ///    Facet fields are only collected, counted and returned for ResultType::Count and ResultType::TopkCount, but not for ResultType::Topk.
-    ///    The prefix property of a QueryFacet allows at query time to filter the returned facet values to those matching a given prefix, if there are too many distinct values per facet field.
-    ///    The length property of a QueryFacet allows at query time limiting the number of returned distinct values per facet field, if there are too many distinct values.  The prefix and length properties can be used to improve the usability in an UI.
-    ///    If the length property of a QueryFacet is set to 0 then no facet values for that facet are collected, counted and returned at query time. That decreases the query latency significantly.
-    ///    The facet values are sorted by the frequency of the appearance of the value within the indexed documents matching the query in descending order.
-    ///    Examples:
-    ///    query_facets = vec![QueryFacet::String {field: "language".into(),prefix: "ger".into(),length: 5},QueryFacet::String {field: "brand".into(),prefix: "a".into(),length: 5}];
-    ///    query_facets = vec![QueryFacet::U8 {field: "age".into(), range_type: RangeType::CountWithinRange, ranges: vec![("0-20".into(), 0),("20-40".into(), 20), ("40-60".into(), 40),("60-80".into(), 60), ("80-100".into(), 80)]}];
-    ///    query_facets = vec![QueryFacet::Point {field: "location".into(),base:vec![38.8951, -77.0364],unit:DistanceUnit::Kilometers,range_type: RangeType::CountWithinRange,ranges: vec![ ("0-200".into(), 0.0),("200-400".into(), 200.0), ("400-600".into(), 400.0), ("600-800".into(), 600.0), ("800-1000".into(), 800.0)]}];
-    /// * `facet_filter`: Search results are filtered to documents matching specific string values or numerical ranges in the facet fields. If set to Vec::new() then result are not facet filtered.
-    ///    The filter parameter filters the returned results to those documents both matching the query AND matching for all (boolean AND) stated facet filter fields at least one (boolean OR) of the stated values.
-    ///    If the query is changed then both facet counts and search results are changed. If the facet filter is changed then only the search results are changed, while facet counts remain unchanged.
-    ///    The facet counts depend only from the query and not which facet filters are selected.
-    ///    Examples:
-    ///    facet_filter=vec![FacetFilter::String{field:"language".into(),filter:vec!["german".into()]},FacetFilter::String{field:"brand".into(),filter:vec!["apple".into(),"google".into()]}];
-    ///    facet_filter=vec![FacetFilter::U8{field:"age".into(),filter: 21..65}];
-    ///    facet_filter = vec![FacetFilter::Point {field: "location".into(),filter: (vec![38.8951, -77.0364], 0.0..1000.0, DistanceUnit::Kilometers)}];
-    /// * `result_sort`: Sort field and order: Search results are sorted by the specified facet field, either in ascending or descending order.
-    ///    If no sort field is specified, then the search results are sorted by rank in descending order per default.
-    ///    Multiple sort fields are combined by a "sort by, then sort by"-method ("tie-breaking"-algorithm).
-    ///    The results are sorted by the first field, and only for those results where the first field value is identical (tie) the results are sub-sorted by the second field,
-    ///    until the n-th field value is either not equal or the last field is reached.
-    ///    A special _score field (BM25x), reflecting how relevant the result is for a given search query (phrase match, match in title etc.) can be combined with any of the other sort fields as primary, secondary or n-th search criterium.
-    ///    Sort is only enabled on facet fields that are defined in schema at create_index!   
-    ///    Examples:
-    ///    result_sort = vec![ResultSort {field: "price".into(), order: SortOrder::Descending, base: FacetValue::None},ResultSort {field: "lamguage".into(), order: SortOrder::Ascending, base: FacetValue::None}];
-    ///    result_sort = vec![ResultSort {field: "location".into(),order: SortOrder::Ascending, base: FacetValue::Point(vec![38.8951, -77.0364])}];
-    ///    If query_string is empty, then index facets (collected at index time) are returned, otherwise query facets (collected at query time) are returned.
-    ///    Facets are defined in 3 different places:
-    ///    the facet fields are defined in schema at create_index,
-    ///    the facet field values are set in index_document at index time,
-    ///    the query_facets/facet_filter search parameters are specified at query time.
-    ///    Facets are then returned in the search result object.
-    async fn search(
+impl Search for IndexArc {
+    /// Search the index for all indexed documents, both for committed and uncommitted documents.
+    /// The latter enables true realtime search: documents are available for search in exact the same millisecond they are indexed.
+    /// Arguments: the fields of SearchRequest (see SearchRequest::new for a search request with default values)
+    /// * `query_string`: query string + - "" search operators are recognized.
+    /// * `query_type_default`: Specifiy default QueryType: **Union** (OR, disjunction), **Intersection** (AND, conjunction), **Phrase** (""), **Not** (-).
+    ///    The default QueryType is superseded if the query parser detects that a different query type is specified within the query string (+ - "").
+    /// * `offset`: offset of search results to return.
+    /// * `length`: number of search results to return.
+    /// * `result_type`: type of search results to return: Count, Topk, TopkCount.
+    /// * `include_uncommited`: true realtime search: include indexed documents which where not yet committed into search results.
+    /// * `field_filter`: Specify field names where to search at querytime, whereas SchemaField.indexed is set at indextime. If set to Vec::new() then all indexed fields are searched.
+    /// * `query_facets`: Must be set if facets should be returned in ResultObject. If set to Vec::new() then no facet fields are returned.
+    ///    Facet fields are only collected, counted and returned for ResultType::Count and ResultType::TopkCount, but not for ResultType::Topk.
+    ///    The prefix property of a QueryFacet allows at query time to filter the returned facet values to those matching a given prefix, if there are too many distinct values per facet field.
+    ///    The length property of a QueryFacet allows at query time limiting the number of returned distinct values per facet field, if there are too many distinct values.  The prefix and length properties can be used to improve the usability in an UI.
+    ///    If the length property of a QueryFacet is set to 0 then no facet values for that facet are collected, counted and returned at query time. That decreases the query latency significantly.
+    ///    The facet values are sorted by the frequency of the appearance of the value within the indexed documents matching the query in descending order.
+    ///    Examples:
+    ///    query_facets = vec![QueryFacet::String {field: "language".into(),prefix: "ger".into(),length: 5,sort: FacetSort::Count,min_count: 0,offset: 0},QueryFacet::String {field: "brand".into(),prefix: "a".into(),length: 5,sort: FacetSort::Count,min_count: 0,offset: 0}];
+    ///    query_facets = vec![QueryFacet::U8 {field: "age".into(), range_type: RangeType::CountWithinRange, ranges: vec![("0-20".into(), 0),("20-40".into(), 20), ("40-60".into(), 40),("60-80".into(), 60), ("80-100".into(), 80)]}];
+    ///    query_facets = vec![QueryFacet::Point {field: "location".into(),base:vec![38.8951, -77.0364],unit:DistanceUnit::Kilometers,range_type: RangeType::CountWithinRange,ranges: vec![ ("0-200".into(), 0.0),("200-400".into(), 200.0), ("400-600".into(), 400.0), ("600-800".into(), 600.0), ("800-1000".into(), 800.0)]}];
+    /// * `facet_filter`: Search results are filtered to documents matching specific string values or numerical ranges in the facet fields. If set to Vec::new() then result are not facet filtered.
+    ///    The filter parameter filters the returned results to those documents both matching the query AND matching for all (boolean AND) stated facet filter fields at least one (boolean OR) of the stated values.
+    ///    If the query is changed then both facet counts and search results are changed. If the facet filter is changed then only the search results are changed, while facet counts remain unchanged.
+    ///    The facet counts depend only from the query and not which facet filters are selected.
+    ///    Examples:
+    ///    facet_filter=vec![FacetFilter::String{field:"language".into(),filter:vec!["german".into()]},FacetFilter::String{field:"brand".into(),filter:vec!["apple".into(),"google".into()]}];
+    ///    facet_filter=vec![FacetFilter::U8{field:"age".into(),filter: 21..65}];
+    ///    facet_filter = vec![FacetFilter::Point {field: "location".into(),filter: (vec![38.8951, -77.0364], 0.0..1000.0, DistanceUnit::Kilometers)}];
+    /// * `result_sort`: Sort field and order: Search results are sorted by the specified facet field, either in ascending or descending order.
+    ///    If no sort field is specified, then the search results are sorted by rank in descending order per default.
+    ///    Multiple sort fields are combined by a "sort by, then sort by"-method ("tie-breaking"-algorithm).
+    ///    The results are sorted by the first field, and only for those results where the first field value is identical (tie) the results are sub-sorted by the second field,
+    ///    until the n-th field value is either not equal or the last field is reached.
+    ///    A special _score field (BM25x), reflecting how relevant the result is for a given search query (phrase match, match in title etc.) can be combined with any of the other sort fields as primary, secondary or n-th search criterium. The special _id field sorts by document id, i.e. in indexing order.
+    ///    Sort is only enabled on facet fields that are defined in schema at create_index!   
+    ///   Results where a sort field is missing (not set in the document) are sorted last per default, or first with missing: MissingOrder::First, independent of the sort order.
+    ///   If _score is not among the sort fields, then ties of all sort fields are broken by _score in descending order.
+    ///   The sort field can also be an expression of numerical facet fields, _score and now, e.g. "_score * log(1+popularity)", compiled once per request (see expression::Expression).
+    ///   If the first sort field is an expression, then it is used as scoring modifier: its value replaces the _score of the results.
+    ///    Examples:
+    ///    result_sort = vec![ResultSort {field: "price".into(), order: SortOrder::Descending, base: FacetValue::None, missing: MissingOrder::Last},ResultSort {field: "language".into(), order: SortOrder::Ascending, base: FacetValue::None, missing: MissingOrder::Last}];
+    ///    result_sort = vec![ResultSort {field: "location".into(),order: SortOrder::Ascending, base: FacetValue::Point(vec![38.8951, -77.0364]), missing: MissingOrder::Last}];
+    ///   result_sort = vec![ResultSort {field: "_score".into(),order: SortOrder::Descending, base: FacetValue::None, missing: MissingOrder::Last},ResultSort {field: "date".into(),order: SortOrder::Descending, base: FacetValue::None, missing: MissingOrder::First}];
+    ///    If query_string is empty, then index facets (collected at index time) are returned, otherwise query facets (collected at query time) are returned.
+    ///    Facets are defined in 3 different places:
+    ///    the facet fields are defined in schema at create_index,
+    ///    the facet field values are set in index_document at index time,
+    ///    the query_facets/facet_filter search parameters are specified at query time.
+    ///    Facets are then returned in the search result object.
+    async fn search(&self, search_request: SearchRequest) -> ResultObject {
+        let SearchRequest {
+            query_string,
+            query_type_default,
+            offset,
+            length,
+            result_type,
+            include_uncommited,
+            field_filter,
+            query_facets,
+            facet_filter,
+            result_sort,
+            query_language,
+            function_score,
+            minimum_should_match,
+            query_expansion,
+            timeout_ms,
+        } = search_request;
+        let deadline =
+            timeout_ms.map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms));
+
+        let curation = if result_type != ResultType::Count {
+            self.read().await.query_curation(&query_string)
+        } else {
+            None
+        };
+
+        let Some((key_field, pinned_keys, hidden_keys)) = curation else {
+            return self
+                .search_from(
+                    query_string,
+                    query_type_default,
+                    offset,
+                    length,
+                    result_type,
+                    include_uncommited,
+                    field_filter,
+                    query_facets,
+                    facet_filter,
+                    result_sort,
+                    query_language,
+                    function_score,
+                    minimum_should_match,
+                    query_expansion,
+                    0,
+                    deadline,
+                )
+                .await;
+        };
+
+        // organic results replaced by pinned documents or removed as hidden documents are compensated from the following results.
+        // The search is repeated if the index was compacted or cleared before the results are curated, as their document ids are no longer current.
+        loop {
+            let mut result_object = self
+                .search_from(
+                    query_string.clone(),
+                    query_type_default.clone(),
+                    0,
+                    offset + length + pinned_keys.len() + hidden_keys.len(),
+                    result_type.clone(),
+                    include_uncommited,
+                    field_filter.clone(),
+                    query_facets.clone(),
+                    facet_filter.clone(),
+                    result_sort.clone(),
+                    query_language,
+                    function_score.clone(),
+                    minimum_should_match,
+                    query_expansion,
+                    0,
+                    deadline,
+                )
+                .await;
+            let index_ref = self.read().await;
+            if !index_ref.is_snapshot_current(&result_object.snapshot) {
+                continue;
+            }
+
+            // pinned documents have to match the facet filters of the query as rewritten by the search, e.g. with the security filter of a scoped API key
+            let mut search_query = SearchQuery {
+                query_string: query_string.clone(),
+                query_type_default: query_type_default.clone(),
+                field_filter: field_filter.clone(),
+                query_facets: query_facets.clone(),
+                facet_filter: facet_filter.clone(),
+                result_sort: result_sort.clone(),
+            };
+            index_ref.rewrite_query(&mut search_query);
+            let doc_count = if include_uncommited {
+                result_object.snapshot.doc_count
+            } else {
+                index_ref
+                    .committed_doc_count
+                    .min(result_object.snapshot.doc_count)
+            };
+
+            curate_results(
+                &index_ref,
+                &mut result_object,
+                &search_query.facet_filter,
+                doc_count,
+                &key_field,
+                &pinned_keys,
+                &hidden_keys,
+                offset,
+                length,
+            );
+            return result_object;
+        }
+    }
+}
+
+/// Pins documents to the top of the results and removes hidden documents from the results (see QueryRule::Curate), then applies offset and length.
+/// Documents are identified by the value of the key field. Pinned documents among the first doc_count documents are returned in the order of the pinned keys,
+/// if they match the facet filters; join filters, which require a search, exclude all pinned documents. Pinned documents get the score of the top organic result.
+#[allow(clippy::too_many_arguments)]
+fn curate_results(
+    index: &Index,
+    result_object: &mut ResultObject,
+    facet_filter: &[FacetFilter],
+    doc_count: usize,
+    key_field: &str,
+    pinned_keys: &[String],
+    hidden_keys: &[String],
+    offset: usize,
+    length: usize,
+) {
+    let key_filter = |keys: &[String]| {
+        [FacetFilter::String {
+            field: key_field.to_string(),
+            filter: keys.to_vec(),
+        }]
+    };
+    let hidden_filter = key_filter(hidden_keys);
+    let is_hidden = |doc_id: usize| {
+        !hidden_keys.is_empty() && index.is_facet_filter_match(doc_id, &hidden_filter)
+    };
+
+    // a facet field has no inverted index: the pinned documents are looked up by scanning the key field, until all pinned keys are found
+    let mut pinned_key_doc_ids: Vec<Option<usize>> = vec![None; pinned_keys.len()];
+    if !pinned_keys.is_empty() {
+        let pinned_filter_sparse = facet_filter_sparse(index, &key_filter(pinned_keys));
+        let mut found_count = 0;
+        for doc_id in 0..doc_count {
+            if index.delete_hashset.contains(&doc_id)
+                || is_facet_filter(index, &pinned_filter_sparse, doc_id)
+            {
+                continue;
+            }
+            for (pinned_key, pinned_key_doc_id) in
+                pinned_keys.iter().zip(pinned_key_doc_ids.iter_mut())
+            {
+                if pinned_key_doc_id.is_none()
+                    && index.is_facet_filter_match(
+                        doc_id,
+                        &key_filter(std::slice::from_ref(pinned_key)),
+                    )
+                {
+                    *pinned_key_doc_id = Some(doc_id);
+                    found_count += 1;
+                }
+            }
+            if found_count == pinned_keys.len() {
+                break;
+            }
+        }
+    }
+
+    let mut pinned_doc_id_set: AHashSet<usize> = AHashSet::new();
+    let pinned_doc_ids: Vec<usize> = pinned_key_doc_ids
+        .into_iter()
+        .flatten()
+        .filter(|doc_id| {
+            index.is_facet_filter_match(*doc_id, facet_filter)
+                && !is_hidden(*doc_id)
+                && pinned_doc_id_set.insert(*doc_id)
+        })
+        .collect();
+
+    let organic_count = result_object.results.len();
+    let score = result_object
+        .results
+        .first()
+        .map_or(0.0, |result| result.score);
+    result_object
+        .results
+        .retain(|result| !pinned_doc_id_set.contains(&result.doc_id) && !is_hidden(result.doc_id));
+    let removed_count = organic_count - result_object.results.len();
+
+    let mut results: Vec<Result> = pinned_doc_ids
+        .into_iter()
+        .map(|doc_id| Result { doc_id, score })
+        .collect();
+    results.extend(mem::take(&mut result_object.results));
+    result_object.result_count_total =
+        (result_object.result_count_total + pinned_doc_id_set.len()).saturating_sub(removed_count);
+    result_object.results = results.into_iter().skip(offset).take(length).collect();
+    result_object.result_count = result_object.results.len();
+}
+
+/// Search starting from a document id, used by scroll for paginating through all results in document id order.
+/// If a deadline is set, the collection of results is aborted when it is exceeded (see SearchRequest::timeout_ms).
+#[allow(clippy::too_many_arguments)]
+#[allow(async_fn_in_trait)]
+pub(crate) trait SearchFrom {
+    async fn search_from(
+        &self,
+        query_string: String,
+        query_type_default: QueryType,
+        offset: usize,
+        length: usize,
+        result_type: ResultType,
+        include_uncommited: bool,
+        field_filter: Vec<String>,
+        query_facets: Vec<QueryFacet>,
+        facet_filter: Vec<FacetFilter>,
+        result_sort: Vec<ResultSort>,
+        query_language: Option<Language>,
+        function_score: Option<FunctionScore>,
+        minimum_should_match: Option<MinimumShouldMatch>,
+        query_expansion: QueryExpansion,
+        min_doc_id: usize,
+        deadline: Option<Instant>,
+    ) -> ResultObject;
+}
+
+impl SearchFrom for IndexArc {
+    /// Search as Search::search, but results with a document id lower than min_doc_id are skipped
+    async fn search_from(
         &self,
         query_string: String,
         query_type_default: QueryType,
@@ -685,11 +1768,57 @@ impl Search for IndexArc {
         query_facets: Vec<QueryFacet>,
         facet_filter: Vec<FacetFilter>,
         result_sort: Vec<ResultSort>,
+        query_language: Option<Language>,
+        function_score: Option<FunctionScore>,
+        minimum_should_match: Option<MinimumShouldMatch>,
+        query_expansion: QueryExpansion,
+        min_doc_id: usize,
+        deadline: Option<Instant>,
     ) -> ResultObject {
-        let index_ref = self.read().await;
+        let mut index_ref = self.read().await;
+
+        let mut search_query = SearchQuery {
+            query_string,
+            query_type_default,
+            field_filter,
+            query_facets,
+            facet_filter,
+            result_sort,
+        };
+        index_ref.rewrite_query(&mut search_query);
+        let SearchQuery {
+            query_string,
+            query_type_default,
+            field_filter,
+            query_facets,
+            facet_filter,
+            result_sort,
+        } = search_query;
+
+        // documents indexed while the index lock is released to resolve the join filters are excluded by the snapshot,
+        // compaction in the meantime reassigns the document ids and requires to resolve the join filters again
+        let mut snapshot = index_ref.snapshot();
+        let join_filter_sparse = if facet_filter.iter().any(FacetFilter::is_join) {
+            loop {
+                drop(index_ref);
+                let join_filter_sparse =
+                    join_filter_sparse(self, &facet_filter, include_uncommited, &snapshot).await;
+                index_ref = self.read().await;
+                if index_ref.is_snapshot_current(&snapshot) {
+                    break join_filter_sparse;
+                }
+                snapshot = index_ref.snapshot();
+            }
+        } else {
+            Vec::new()
+        };
+
         let mut query_type_mut = query_type_default;
 
-        let mut result_object: ResultObject = Default::default();
+        let mut result_object = ResultObject {
+            snapshot,
+            ..Default::default()
+        };
 
         if index_ref.segments_index.is_empty() {
             return result_object;
@@ -709,170 +1838,61 @@ impl Search for IndexArc {
             }
         }
 
+        let base_none = FacetValue::None;
         let mut result_sort_index: Vec<ResultSortIndex> = Vec::new();
-        if !result_sort.is_empty() && result_type != ResultType::Count {
-            for rs in result_sort.iter() {
-                if let Some(idx) = index_ref.facets_map.get(&rs.field) {
+        if let Some(function_score) = function_score.as_ref() {
+            if result_type != ResultType::Count {
+                if let Ok(compiled) = Expression::from_function_score(function_score, &index_ref) {
                     result_sort_index.push(ResultSortIndex {
-                        idx: *idx,
-                        order: rs.order.clone(),
-                        base: &rs.base,
+                        idx: None,
+                        order: SortOrder::Descending,
+                        base: &base_none,
+                        missing: MissingOrder::Last,
+                        expression: Some(compiled),
+                        doc_id: false,
                     });
                 }
             }
         }
+        if !result_sort.is_empty() && result_type != ResultType::Count {
+            for rs in result_sort.iter() {
+                let mut expression = None;
+                let idx = if rs.field == "_score" || rs.field == "_id" {
+                    None
+                } else if let Some(idx) = index_ref.facets_map.get(&rs.field) {
+                    Some(*idx)
+                } else {
+                    match Expression::compile(&rs.field, &index_ref) {
+                        Ok(compiled) => {
+                            expression = Some(compiled);
+                            None
+                        }
+                        Err(_) => continue,
+                    }
+                };
+                result_sort_index.push(ResultSortIndex {
+                    idx,
+                    order: rs.order.clone(),
+                    base: &rs.base,
+                    missing: rs.missing.clone(),
+                    expression,
+                    doc_id: rs.field == "_id",
+                });
+            }
+        }
 
         let mut search_result = SearchResult {
             topk_candidates: MinHeap::new(offset + length, &index_ref, &result_sort_index),
             query_facets: Vec::new(),
             skip_facet_count: false,
+            minimum_should_match: 0,
+            deadline,
+            partial: false,
         };
+        search_result.topk_candidates.min_doc_id = min_doc_id;
 
-        let mut facet_filter_sparse: Vec<FilterSparse> = Vec::new();
-        if !facet_filter.is_empty() {
-            facet_filter_sparse = vec![FilterSparse::None; index_ref.facets.len()];
-            for facet_filter_item in facet_filter.iter() {
-                match &facet_filter_item {
-                    FacetFilter::U8 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::U8 {
-                                facet_filter_sparse[*idx] = FilterSparse::U8(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::U16 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::U16 {
-                                facet_filter_sparse[*idx] = FilterSparse::U16(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::U32 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::U32 {
-                                facet_filter_sparse[*idx] = FilterSparse::U32(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::U64 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::U64 {
-                                facet_filter_sparse[*idx] = FilterSparse::U64(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::I8 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::I8 {
-                                facet_filter_sparse[*idx] = FilterSparse::I8(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::I16 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::I16 {
-                                facet_filter_sparse[*idx] = FilterSparse::I16(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::I32 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::I32 {
-                                facet_filter_sparse[*idx] = FilterSparse::I32(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::I64 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::I64 {
-                                facet_filter_sparse[*idx] = FilterSparse::I64(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::Timestamp { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::Timestamp {
-                                facet_filter_sparse[*idx] = FilterSparse::Timestamp(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::F32 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::F32 {
-                                facet_filter_sparse[*idx] = FilterSparse::F32(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::F64 { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::F64 {
-                                facet_filter_sparse[*idx] = FilterSparse::F64(filter.clone())
-                            }
-                        }
-                    }
-                    FacetFilter::String { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            let facet = &index_ref.facets[*idx];
-                            if index_ref.facets[*idx].field_type == FieldType::String {
-                                let mut string_id_vec = Vec::new();
-                                for value in filter.iter() {
-                                    let key = [value.clone()];
-                                    if let Some(facet_value_id) = facet.values.get_index_of(&key[0])
-                                    {
-                                        string_id_vec.push(facet_value_id as u16);
-                                    }
-                                }
-                                facet_filter_sparse[*idx] = FilterSparse::String(string_id_vec);
-                            }
-                        }
-                    }
-
-                    FacetFilter::StringSet { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            let facet = &index_ref.facets[*idx];
-                            if index_ref.facets[*idx].field_type == FieldType::StringSet {
-                                let mut string_id_vec = Vec::new();
-                                for value in filter.iter() {
-                                    let key = [value.clone()];
-                                    if let Some(facet_value_id) =
-                                        facet.values.get_index_of(&key.join("_"))
-                                    {
-                                        string_id_vec.push(facet_value_id as u16);
-                                    }
-
-                                    if let Some(facet_value_ids) = index_ref
-                                        .string_set_to_single_term_id_vec[*idx]
-                                        .get(&value.clone())
-                                    {
-                                        for code in facet_value_ids.iter() {
-                                            string_id_vec.push(*code);
-                                        }
-                                    }
-                                }
-                                facet_filter_sparse[*idx] = FilterSparse::String(string_id_vec);
-                            }
-                        }
-                    }
-
-                    FacetFilter::Point { field, filter } => {
-                        if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::Point {
-                                facet_filter_sparse[*idx] = FilterSparse::Point(
-                                    filter.0.clone(),
-                                    filter.1.clone(),
-                                    filter.2.clone(),
-                                    point_distance_to_morton_range(
-                                        &filter.0,
-                                        filter.1.end,
-                                        &filter.2,
-                                    ),
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let mut facet_filter_sparse = facet_filter_sparse(&index_ref, &facet_filter);
+        facet_filter_sparse.extend(join_filter_sparse);
 
         let mut is_range_facet = false;
         if !query_facets.is_empty() {
@@ -1066,17 +2086,118 @@ impl Search for IndexArc {
                             }
                         }
                     }
+                    QueryFacet::Decimal {
+                        field,
+                        range_type,
+                        ranges,
+                    } => {
+                        if let Some(idx) = index_ref.facets_map.get(field) {
+                            if index_ref.facets[*idx].field_type == FieldType::Decimal {
+                                let scale = index_ref.facets[*idx].scale;
+                                is_range_facet = true;
+                                search_result.query_facets[*idx] = ResultFacet {
+                                    field: field.clone(),
+                                    length: u16::MAX,
+                                    ranges: Ranges::I64(
+                                        range_type.clone(),
+                                        ranges
+                                            .iter()
+                                            .map(|(label, value)| {
+                                                (label.clone(), decimal_from_f64(*value, scale))
+                                            })
+                                            .collect(),
+                                    ),
+                                    ..Default::default()
+                                };
+                            }
+                        }
+                    }
+                    QueryFacet::Date {
+                        field,
+                        range_type,
+                        ranges,
+                    } => {
+                        if let Some(idx) = index_ref.facets_map.get(field) {
+                            let facet = &index_ref.facets[*idx];
+                            if facet.field_type == FieldType::Date {
+                                let now = Utc::now().timestamp_millis();
+                                let ranges: Vec<(String, i64)> = ranges
+                                    .iter()
+                                    .filter_map(|(label, value)| {
+                                        parse_date_math(value, now, &facet.date_formats)
+                                            .map(|value| (label.clone(), value))
+                                    })
+                                    .collect();
+                                is_range_facet = true;
+                                search_result.query_facets[*idx] = ResultFacet {
+                                    field: field.clone(),
+                                    length: u16::MAX,
+                                    ranges: Ranges::I64(range_type.clone(), ranges),
+                                    ..Default::default()
+                                };
+                            }
+                        }
+                    }
+                    QueryFacet::DateHistogram { field, interval } => {
+                        if let Some(idx) = index_ref.facets_map.get(field) {
+                            let facet = &index_ref.facets[*idx];
+                            if let (FieldType::Date, ValueType::Date(min), ValueType::Date(max)) =
+                                (&facet.field_type, &facet.min, &facet.max)
+                            {
+                                is_range_facet = true;
+                                search_result.query_facets[*idx] = ResultFacet {
+                                    field: field.clone(),
+                                    length: u16::MAX,
+                                    ranges: Ranges::I64(
+                                        RangeType::CountWithinRange,
+                                        date_histogram_ranges(*min, *max, *interval),
+                                    ),
+                                    ..Default::default()
+                                };
+                            }
+                        }
+                    }
+                    QueryFacet::Ip {
+                        field,
+                        range_type,
+                        ranges,
+                    } => {
+                        if let Some(idx) = index_ref.facets_map.get(field) {
+                            if index_ref.facets[*idx].field_type == FieldType::Ip {
+                                let ranges: Vec<(String, u128)> = ranges
+                                    .iter()
+                                    .filter_map(|(label, value)| {
+                                        parse_ip(value).map(|value| (label.clone(), value))
+                                    })
+                                    .collect();
+                                is_range_facet = true;
+                                search_result.query_facets[*idx] = ResultFacet {
+                                    field: field.clone(),
+                                    length: u16::MAX,
+                                    ranges: Ranges::Ip(range_type.clone(), ranges),
+                                    ..Default::default()
+                                };
+                            }
+                        }
+                    }
                     QueryFacet::String {
                         field,
                         prefix,
                         length,
+                        sort,
+                        min_count,
+                        offset,
                     } => {
                         if let Some(idx) = index_ref.facets_map.get(field) {
-                            if index_ref.facets[*idx].field_type == FieldType::String {
+                            let facet = &index_ref.facets[*idx];
+                            if matches!(facet.field_type, FieldType::String | FieldType::Keyword) {
                                 search_result.query_facets[*idx] = ResultFacet {
                                     field: field.clone(),
-                                    prefix: prefix.clone(),
+                                    prefix: normalize_keyword(prefix, &facet.normalizers),
                                     length: *length,
+                                    sort: *sort,
+                                    min_count: *min_count,
+                                    offset: *offset,
                                     ..Default::default()
                                 }
                             }
@@ -1086,6 +2207,9 @@ impl Search for IndexArc {
                         field,
                         prefix,
                         length,
+                        sort,
+                        min_count,
+                        offset,
                     } => {
                         if let Some(idx) = index_ref.facets_map.get(field) {
                             if index_ref.facets[*idx].field_type == FieldType::StringSet {
@@ -1093,6 +2217,9 @@ impl Search for IndexArc {
                                     field: field.clone(),
                                     prefix: prefix.clone(),
                                     length: *length,
+                                    sort: *sort,
+                                    min_count: *min_count,
+                                    offset: *offset,
                                     ..Default::default()
                                 }
                             }
@@ -1132,6 +2259,12 @@ impl Search for IndexArc {
         let result_count_arc = Arc::new(AtomicUsize::new(0));
         let result_count_uncommitted_arc = Arc::new(AtomicUsize::new(0));
 
+        let language = if query_expansion.disable_stemming && index_ref.is_unstemmed_indexed() {
+            None
+        } else {
+            tokenizer::query_language(&index_ref.meta.stemmer, query_language, &query_string)
+        };
+
         'fallback: loop {
             let mut unique_terms: AHashMap<String, TermObject> = AHashMap::new();
             let mut non_unique_terms: Vec<NonUniqueTermObject> = Vec::new();
@@ -1152,6 +2285,45 @@ impl Search for IndexArc {
                 index_ref.enable_bigram,
                 0,
                 1,
+                language,
+            );
+
+            search_result.minimum_should_match = match minimum_should_match {
+                Some(minimum_should_match) if query_type_mut == QueryType::Union => {
+                    minimum_should_match.required_terms(
+                        non_unique_terms
+                            .iter()
+                            .filter(|term| term.op != QueryType::Not)
+                            .map(|term| &term.term)
+                            .unique()
+                            .count(),
+                    )
+                }
+                _ => 0,
+            };
+
+            if query_type_mut == QueryType::Phrase {
+                let query_phrase: Vec<String> = non_unique_terms
+                    .iter()
+                    .filter(|term| term.op != QueryType::Not)
+                    .flat_map(|term| {
+                        if term.is_bigram {
+                            vec![term.term_bigram1.clone(), term.term_bigram2.clone()]
+                        } else {
+                            vec![term.term.clone()]
+                        }
+                    })
+                    .collect();
+                if query_phrase.len() >= 2 {
+                    result_object.query_phrases.push(query_phrase);
+                }
+            }
+
+            tokenizer::apply_query_expansion(
+                &index_ref,
+                &query_expansion,
+                &mut unique_terms,
+                &mut non_unique_terms,
             );
 
             if include_uncommited && index_ref.uncommitted {
@@ -1176,6 +2348,7 @@ impl Search for IndexArc {
 
             let mut non_unique_query_list: Vec<NonUniquePostingListObjectQuery> = Vec::new();
             let mut preceding_bigram_count = 0;
+            let mut preceding_gap_count = 0;
 
             let mut blocks_vec: Vec<Vec<BlockObjectIndex>> = Vec::new();
 
@@ -1270,6 +2443,7 @@ impl Search for IndexArc {
                         }
                     }
                 } else {
+                    preceding_gap_count += non_unique_term.position_gap;
                     let query_list_map_len = query_list_map.len();
                     let mut found = true;
                     let query_list_option = query_list_map.get(&key_hash);
@@ -1398,6 +2572,8 @@ impl Search for IndexArc {
                                     not_found_terms_hashset.insert(key_hash);
                                     found = false;
                                 }
+                            } else {
+                                found = false;
                             }
                         }
                         Some(value) => {
@@ -1409,7 +2585,8 @@ impl Search for IndexArc {
                         let nu_plo = NonUniquePostingListObjectQuery {
                             term_index_unique,
                             term_index_nonunique: non_unique_query_list.len()
-                                + preceding_bigram_count,
+                                + preceding_bigram_count
+                                + preceding_gap_count,
                             pos: 0,
                             p_pos: 0,
                             positions_pointer: 0,
@@ -1438,7 +2615,11 @@ impl Search for IndexArc {
                         .push(term.term_bigram2.to_string());
                 }
                 {
-                    result_object.query_terms.push(term.term.to_string());
+                    result_object.query_terms.push(
+                        term.term
+                            .trim_start_matches([UNEXPANDED_TERM_PREFIX, UNSTEMMED_TERM_PREFIX])
+                            .to_string(),
+                    );
                 }
             }
 
@@ -1458,10 +2639,11 @@ impl Search for IndexArc {
             let non_unique_query_list_len = non_unique_query_list.len();
 
             let mut matching_blocks: i32 = 0;
-            if query_list_len == 0 {
+            if query_list_len == 0 || search_result.minimum_should_match > query_list_len {
             } else if query_list_len == 1 {
                 if !(index_ref.uncommitted && include_uncommited)
                     && offset + length <= 1000
+                    && min_doc_id == 0
                     && not_query_list.is_empty()
                     && field_filter_set.is_empty()
                     && index_ref.delete_hashset.is_empty()
@@ -1502,15 +2684,18 @@ impl Search for IndexArc {
                                     continue;
                                 }
 
-                                let v = stopword_result_object.facets[&facet.field]
-                                    .iter()
-                                    .sorted_unstable_by(|a, b| b.1.cmp(&a.1))
-                                    .map(|(a, c)| (a.clone(), *c))
-                                    .filter(|(a, _c)| {
-                                        facet.prefix.is_empty() || a.starts_with(&facet.prefix)
-                                    })
-                                    .take(facet.length as usize)
-                                    .collect::<Vec<_>>();
+                                let mut label_counts: AHashMap<String, usize> = AHashMap::new();
+                                for (value, count) in
+                                    stopword_result_object.facets[&facet.field].iter()
+                                {
+                                    *label_counts
+                                        .entry(
+                                            index_ref.facet_label(&facet.field, value).to_string(),
+                                        )
+                                        .or_insert(0) += count;
+                                }
+
+                                let v = facet.page(label_counts);
 
                                 if !v.is_empty() {
                                     facets.insert(facet.field.clone(), v);
@@ -1538,7 +2723,7 @@ impl Search for IndexArc {
                 )
                 .await;
             } else if query_type_mut == QueryType::Union {
-                search_result.skip_facet_count = true;
+                search_result.skip_facet_count = search_result.minimum_should_match <= 1;
 
                 if result_type == ResultType::Count {
                     union_blockid(
@@ -1556,6 +2741,7 @@ impl Search for IndexArc {
                     .await;
                 } else if SPEEDUP_FLAG
                     && query_list_len == 2
+                    && search_result.minimum_should_match <= 1
                     && search_result.query_facets.is_empty()
                     && facet_filter_sparse.is_empty()
                     && search_result.topk_candidates.result_sort.is_empty()
@@ -1574,7 +2760,10 @@ impl Search for IndexArc {
                         &mut matching_blocks,
                     )
                     .await;
-                } else if SPEEDUP_FLAG && search_result.topk_candidates.result_sort.is_empty() {
+                } else if SPEEDUP_FLAG
+                    && search_result.minimum_should_match <= 1
+                    && search_result.topk_candidates.result_sort.is_empty()
+                {
                     union_docid_3(
                         &index_ref,
                         &mut non_unique_query_list,
@@ -1625,6 +2814,7 @@ impl Search for IndexArc {
                 )
                 .await;
                 if index_ref.enable_fallback
+                    && !search_result.partial
                     && (result_count_arc.load(Ordering::Relaxed) < offset + length)
                 {
                     continue 'fallback;
@@ -1635,6 +2825,7 @@ impl Search for IndexArc {
         }
 
         result_object.result_count = search_result.topk_candidates.current_heap_size;
+        result_object.partial = search_result.partial;
 
         if search_result.topk_candidates.current_heap_size > offset {
             result_object.results = mem::take(&mut search_result.topk_candidates._elements);
@@ -1658,7 +2849,26 @@ impl Search for IndexArc {
             + result_count_arc.load(Ordering::Relaxed);
 
         if !search_result.query_facets.is_empty() {
-            result_object.facets = if result_object.query_terms.is_empty() {
+            // without query terms the facets are counted over all documents: index-wide without facet filter,
+            // otherwise over the documents matching the facet filter, e.g. the security filter of a scoped API key
+            if result_object.query_terms.is_empty() && !facet_filter.is_empty() {
+                search_result.skip_facet_count = false;
+                for doc_id in 0..index_ref.indexed_doc_count {
+                    if doc_id % 65_536 == 0 && search_result.timed_out() {
+                        break;
+                    }
+                    if !index_ref.delete_hashset.contains(&doc_id)
+                        && !is_facet_filter(&index_ref, &facet_filter_sparse, doc_id)
+                    {
+                        facet_count(&index_ref, &mut search_result, doc_id);
+                    }
+                }
+                result_object.partial = search_result.partial;
+            }
+
+            result_object.facets = if result_object.query_terms.is_empty()
+                && facet_filter.is_empty()
+            {
                 index_ref
                     .get_index_string_facets(query_facets)
                     .unwrap_or_default()
@@ -1670,50 +2880,10 @@ impl Search for IndexArc {
                     }
 
                     let v = if facet.ranges == Ranges::None {
-                        if index_ref.facets[i].field_type == FieldType::StringSet {
-                            let mut hash_map: AHashMap<String, usize> = AHashMap::new();
-                            for value in facet.values.iter() {
-                                let value2 = index_ref.facets[i]
-                                    .values
-                                    .get_index((*value.0).into())
-                                    .unwrap();
-
-                                for term in value2.1 .0.iter() {
-                                    *hash_map.entry(term.clone()).or_insert(0) += value.1;
-                                }
-                            }
-
-                            hash_map
-                                .iter()
-                                .sorted_unstable_by(|a, b| b.1.cmp(a.1))
-                                .map(|(a, c)| (a.clone(), *c))
-                                .filter(|(a, _c)| {
-                                    facet.prefix.is_empty() || a.starts_with(&facet.prefix)
-                                })
-                                .take(facet.length as usize)
-                                .collect::<Vec<_>>()
-                        } else {
-                            facet
-                                .values
-                                .iter()
-                                .sorted_unstable_by(|a, b| b.1.cmp(a.1))
-                                .map(|(a, c)| {
-                                    (
-                                        index_ref.facets[i]
-                                            .values
-                                            .get_index((*a).into())
-                                            .unwrap()
-                                            .0
-                                            .clone(),
-                                        *c,
-                                    )
-                                })
-                                .filter(|(a, _c)| {
-                                    facet.prefix.is_empty() || a.starts_with(&facet.prefix)
-                                })
-                                .take(facet.length as usize)
-                                .collect::<Vec<_>>()
-                        }
+                        facet.page(index_ref.facet_label_counts(
+                            i,
+                            facet.values.iter().map(|(a, c)| ((*a).into(), *c)),
+                        ))
                     } else {
                         let range_type = match &facet.ranges {
                             Ranges::U8(range_type, _ranges) => range_type.clone(),
@@ -1727,6 +2897,7 @@ impl Search for IndexArc {
                             Ranges::Timestamp(range_type, _ranges) => range_type.clone(),
                             Ranges::F32(range_type, _ranges) => range_type.clone(),
                             Ranges::F64(range_type, _ranges) => range_type.clone(),
+                            Ranges::Ip(range_type, _ranges) => range_type.clone(),
                             Ranges::Point(range_type, _ranges, _base, _unit) => range_type.clone(),
                             _ => RangeType::CountWithinRange,
                         };
@@ -1797,6 +2968,9 @@ impl Search for IndexArc {
                                         Ranges::F64(_range_type, ranges) => {
                                             ranges[*a as usize].0.clone()
                                         }
+                                        Ranges::Ip(_range_type, ranges) => {
+                                            ranges[*a as usize].0.clone()
+                                        }
 
                                         Ranges::Point(_range_type, ranges, _base, _unit) => {
                                             ranges[*a as usize].0.clone()
@@ -325,6 +325,9 @@ pub(crate) async fn single_blockid<'a>(
     let mut block_vec: Vec<BlockObject> = Vec::new();
 
     for (p_block, blo) in query_list[term_index].blocks.iter().enumerate() {
+        if search_result.timed_out() {
+            break;
+        }
         if !enable_inter_query_threading_single {
             let block_score = blo.max_block_score;
 
@@ -364,6 +367,9 @@ pub(crate) async fn single_blockid<'a>(
         block_vec.sort_unstable_by(|x, y| y.block_score.partial_cmp(&x.block_score).unwrap());
         let mut block_index = 0;
         for block in block_vec {
+            if search_result.timed_out() {
+                break;
+            }
             block_index += 1;
             let blo = &query_list[term_index].blocks[block.p_block_vec[0] as usize];
 
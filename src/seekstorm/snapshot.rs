@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::index::Index;
+
+/// Point-in-time view of an index, captured by search under the index lock: the committed and the uncommitted (in-memory) documents indexed so far.
+/// A document is indexed under the write lock of the index, so a snapshot contains only completely indexed documents.
+/// A query consists of multiple steps, which lock the index separately: search, join filters, inner hits, document retrieval.
+/// Concurrently indexed documents are excluded from all steps by their document id, which is not lower than the doc_count of the snapshot,
+/// while concurrently deleted documents are still returned by get_document, as they remain in the document store until compaction.
+/// The snapshot is invalidated when the document ids are reassigned by compaction or clear_index: the query has to be repeated with a new snapshot.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+pub struct Snapshot {
+    /// Number of documents visible to the snapshot: documents with a lower document id
+    pub doc_count: usize,
+    /// Generation of the document ids of the index at the time of the snapshot
+    pub generation: u64,
+}
+
+impl Snapshot {
+    /// The document was indexed before the snapshot was taken
+    pub fn contains(&self, doc_id: usize) -> bool {
+        doc_id < self.doc_count
+    }
+}
+
+impl Index {
+    /// Point-in-time view of the committed and uncommitted documents of the index
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            doc_count: self.indexed_doc_count,
+            generation: self.doc_id_generation,
+        }
+    }
+
+    /// The document ids of the snapshot still refer to the same documents: the index has not been compacted or cleared since the snapshot was taken
+    pub fn is_snapshot_current(&self, snapshot: &Snapshot) -> bool {
+        snapshot.generation == self.doc_id_generation
+            && snapshot.doc_count <= self.indexed_doc_count
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::index::{
+    AccessType, Index, DELETE_FILENAME, DOCSTORE_FILENAME, FACET_FILENAME, FACET_VALUES_FILENAME,
+    FILE_PATH, INDEX_FILENAME, ROARING_BLOCK_SIZE,
+};
+
+/// Bytes on disk of the index directory, broken down by component
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// Posting lists (index.bin)
+    pub postings: u64,
+    /// Stored documents (docstore.bin)
+    pub docstore: u64,
+    /// Facet values (facet.bin and facet.json)
+    pub facets: u64,
+    /// Deleted document ids (delete.bin)
+    pub deletes: u64,
+    /// Original files of ingested documents, e.g. PDF (files directory)
+    pub files: u64,
+    /// All other files: schema, synonyms, checksums, LTR models, query rules, ...
+    pub metadata: u64,
+    /// Sum of all components
+    pub total: u64,
+}
+
+/// Disk and memory usage of an index, for capacity planning
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StorageStats {
+    pub access_type: AccessType,
+    pub disk: DiskUsage,
+    /// Bytes of the index files mapped into memory: posting lists and documents with AccessType::Mmap, facets always.
+    /// Mapped pages are loaded into memory by the operating system on access, and can be evicted under memory pressure.
+    pub mmap_bytes: u64,
+    /// Heap memory of the index, estimated from the used part of its buffers: posting lists and documents loaded into RAM with AccessType::Ram,
+    /// document lengths, deleted document ids, and the uncommitted documents. Hash map overhead and preallocated scratch buffers are not included.
+    pub resident_bytes: u64,
+}
+
+fn file_size(path: &Path) -> u64 {
+    path.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+}
+
+impl Index {
+    /// Returns the bytes on disk of the index broken down by component, the mmap footprint, and the estimated resident memory of the index.
+    pub fn get_storage_stats(&self) -> StorageStats {
+        let index_path = Path::new(&self.index_path_string);
+
+        let mut disk = DiskUsage {
+            postings: file_size(&index_path.join(INDEX_FILENAME)),
+            docstore: file_size(&index_path.join(DOCSTORE_FILENAME)),
+            facets: file_size(&index_path.join(FACET_FILENAME))
+                + file_size(&index_path.join(FACET_VALUES_FILENAME)),
+            deletes: file_size(&index_path.join(DELETE_FILENAME)),
+            ..Default::default()
+        };
+        for entry in WalkDir::new(index_path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+        {
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            disk.total += size;
+            if entry.path().starts_with(index_path.join(FILE_PATH)) {
+                disk.files += size;
+            }
+        }
+        disk.metadata = disk.total.saturating_sub(
+            disk.postings + disk.docstore + disk.facets + disk.deletes + disk.files,
+        );
+
+        let mmap_bytes = (self.index_file_mmap.len()
+            + self.docstore_file_mmap.len()
+            + self.facets_file_mmap.len()) as u64;
+
+        StorageStats {
+            access_type: self.meta.access_type.clone(),
+            disk,
+            mmap_bytes,
+            resident_bytes: self.resident_bytes(),
+        }
+    }
+
+    /// Estimated heap memory of the index (see StorageStats.resident_bytes), e.g. for a memory budget across indices
+    pub fn resident_bytes(&self) -> u64 {
+        let mut resident_bytes = self.uncommitted_bytes() as usize
+            + self.delete_hashset.len() * size_of::<usize>()
+            + self.document_length_compressed_array.len() * ROARING_BLOCK_SIZE;
+        for level in self.level_index.iter() {
+            resident_bytes += level.docstore_pointer_docs.len()
+                + level.document_length_compressed_array.len() * ROARING_BLOCK_SIZE;
+        }
+        if self.meta.access_type == AccessType::Ram {
+            for segment in self.segments_index.iter() {
+                resident_bytes += segment
+                    .byte_array_blocks
+                    .iter()
+                    .map(|block| block.len())
+                    .sum::<usize>();
+            }
+        }
+        resident_bytes as u64
+    }
+
+    /// Estimated heap memory of the uncommitted documents, which is released or compressed by commit
+    pub fn uncommitted_bytes(&self) -> u64 {
+        let mut uncommitted_bytes = self.postings_buffer_pointer;
+        for segment in self.segments_level0.iter() {
+            uncommitted_bytes += segment.positions_compressed.len();
+        }
+        uncommitted_bytes as u64
+    }
+}
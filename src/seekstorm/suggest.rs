@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::IndexArc,
+    search::{FacetFilter, ResultType, Search, SearchRequest},
+};
+
+/// Number of candidate completions per requested suggestion, whose results are counted
+const SUGGEST_CANDIDATES_FACTOR: usize = 10;
+
+/// Context of a suggest request, e.g. the store section the user is browsing
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SuggestContext {
+    /// Facet filter defining the context, e.g. a String facet filter for the field section
+    pub facet_filter: Vec<FacetFilter>,
+    /// If None, only completions with results within the context are suggested.
+    /// Otherwise completions with results outside the context are suggested too,
+    /// but the popularity of completions with results within the context is multiplied by boost.
+    #[serde(default)]
+    pub boost: Option<f64>,
+}
+
+/// Completion of a prefix, returned by Suggest::suggest
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Suggestion {
+    /// Completed query, normalized: lowercase, single spaces
+    pub query: String,
+    /// Sum of searches, clicks and conversions of the query, from the click and conversion feedback (see Index::register_search)
+    pub popularity: u64,
+    /// Popularity, multiplied by SuggestContext.boost if the query has results within the context
+    pub score: f64,
+    /// Number of results of the query: within the context if a context is given, otherwise in the whole index
+    pub count: usize,
+}
+
+/// Query completions for a prefix, ranked by popularity and optionally filtered or boosted by a context
+#[allow(async_fn_in_trait)]
+pub trait Suggest {
+    async fn suggest(
+        &self,
+        prefix: &str,
+        context: Option<SuggestContext>,
+        length: usize,
+    ) -> Result<Vec<Suggestion>, String>;
+}
+
+impl Suggest for IndexArc {
+    /// Completes a prefix with previous queries of the index, ranked by their popularity from the click and conversion feedback:
+    /// the popularity weights are updated continuously by searches and feedback events (see Index::register_search and Index::record_feedback_events).
+    /// Only completions with results in the committed index are suggested: documents containing all terms of the completion.
+    /// * `prefix`: beginning of the query typed by the user. Trailing whitespace completes only the following term.
+    /// * `context`: filter or boost the completions by a facet filter, e.g. only suggest queries with products within the store section the user is browsing.
+    /// * `length`: maximum number of suggestions to return
+    async fn suggest(
+        &self,
+        prefix: &str,
+        context: Option<SuggestContext>,
+        length: usize,
+    ) -> Result<Vec<Suggestion>, String> {
+        let index_ref = self.read().await;
+        if let Some(context) = context.as_ref() {
+            index_ref.check_facet_filter(&context.facet_filter)?;
+        }
+        let mut candidates = index_ref.get_popular_queries(prefix);
+        drop(index_ref);
+
+        candidates.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        candidates.truncate(length.saturating_mul(SUGGEST_CANDIDATES_FACTOR));
+
+        let mut suggestions = Vec::new();
+        for (query, popularity) in candidates {
+            let boost = context.as_ref().and_then(|context| context.boost);
+            if boost.is_some() && count(self, &query, Vec::new()).await == 0 {
+                continue;
+            }
+
+            let count = count(
+                self,
+                &query,
+                context
+                    .as_ref()
+                    .map(|context| context.facet_filter.clone())
+                    .unwrap_or_default(),
+            )
+            .await;
+            let score = match boost {
+                Some(boost) if count > 0 => popularity as f64 * boost,
+                Some(_) => popularity as f64,
+                None if count > 0 => popularity as f64,
+                None => continue,
+            };
+            suggestions.push(Suggestion {
+                query,
+                popularity,
+                score,
+                count,
+            });
+
+            // without boost the candidates are already in descending order of score
+            if boost.is_none() && suggestions.len() == length {
+                break;
+            }
+        }
+
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions.truncate(length);
+        Ok(suggestions)
+    }
+}
+
+/// Number of documents of the committed index containing all terms of a query and matching the facet filter
+async fn count(index_arc: &IndexArc, query: &str, facet_filter: Vec<FacetFilter>) -> usize {
+    index_arc
+        .search(
+            SearchRequest::new(query)
+                .length(0)
+                .result_type(ResultType::Count)
+                .facet_filter(facet_filter),
+        )
+        .await
+        .result_count_total
+}
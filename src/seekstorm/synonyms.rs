@@ -0,0 +1,83 @@
+use crate::{error::SeekStormError, index::Synonym};
+
+/// Parses synonyms in the Solr/Elasticsearch text format, one rule per line, so that existing synonym files of other engines can be reused:
+/// * `a, b, c`: multi-way synonyms, all terms are synonyms of each other (Synonym.multiway = true)
+/// * `a, b => c, d`: explicit mapping, a and b are one-way synonyms of c and d (a Synonym with multiway = false per term left of =>)
+///
+/// Empty lines and comments starting with # are skipped, terms are trimmed. Backslash escapes of `,` and `=>` are not supported.
+/// As only single terms are supported as synonyms, terms containing whitespace are rejected with the line number.
+pub fn parse_solr_synonyms(text: &str) -> Result<Vec<Synonym>, SeekStormError> {
+    let mut synonyms = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parse_terms = |terms: &str| -> Result<Vec<String>, SeekStormError> {
+            let terms: Vec<String> = terms
+                .split(',')
+                .map(|term| term.trim().to_string())
+                .filter(|term| !term.is_empty())
+                .collect();
+            if let Some(term) = terms.iter().find(|term| term.contains(char::is_whitespace)) {
+                return Err(SeekStormError::InvalidRequest(format!(
+                    "line {}: multi-term synonyms are not supported: {}",
+                    line_number + 1,
+                    term
+                )));
+            }
+            Ok(terms)
+        };
+
+        match line.split_once("=>") {
+            Some((inputs, outputs)) => {
+                let outputs = parse_terms(outputs)?;
+                if outputs.is_empty() || outputs.iter().any(|output| output.contains("=>")) {
+                    return Err(SeekStormError::InvalidRequest(format!(
+                        "line {}: invalid synonym mapping: {}",
+                        line_number + 1,
+                        line
+                    )));
+                }
+                for input in parse_terms(inputs)? {
+                    let mut terms = vec![input.clone()];
+                    terms.extend(outputs.iter().filter(|output| **output != input).cloned());
+                    if terms.len() > 1 {
+                        synonyms.push(Synonym {
+                            terms,
+                            multiway: false,
+                        });
+                    }
+                }
+            }
+            None => {
+                let terms = parse_terms(line)?;
+                if terms.len() > 1 {
+                    synonyms.push(Synonym {
+                        terms,
+                        multiway: true,
+                    });
+                }
+            }
+        }
+    }
+    Ok(synonyms)
+}
+
+/// Formats synonyms in the Solr/Elasticsearch text format, one rule per line (see parse_solr_synonyms):
+/// multi-way synonyms as `a, b, c`, one-way synonyms as `a => b, c`.
+pub fn format_solr_synonyms(synonyms: &[Synonym]) -> String {
+    let mut text = String::new();
+    for synonym in synonyms.iter().filter(|synonym| synonym.terms.len() > 1) {
+        if synonym.multiway {
+            text.push_str(&synonym.terms.join(", "));
+        } else {
+            text.push_str(&synonym.terms[0]);
+            text.push_str(" => ");
+            text.push_str(&synonym.terms[1..].join(", "));
+        }
+        text.push('\n');
+    }
+    text
+}
@@ -0,0 +1,349 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{
+        document_language, AccessType, Document, FieldType, Index, SchemaField, TermObject,
+        MAX_POSITIONS_PER_TERM,
+    },
+    search::{decode_posting_list_object, QueryType},
+    tokenizer::tokenizer,
+};
+
+/// A term of a field with the number of documents containing the term in that field
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TermFrequency {
+    /// Term as indexed: tokenized, normalized and stemmed
+    pub term: String,
+    /// Number of documents containing the term in the field
+    pub document_frequency: usize,
+}
+
+/// Terms of a field with document frequencies, returned by Index::get_terms
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FieldTerms {
+    /// Field name
+    pub field: String,
+    /// Number of distinct terms of the field starting with the prefix
+    pub count_total: usize,
+    /// Terms with document frequencies, sorted by term, within offset and length
+    pub terms: Vec<TermFrequency>,
+}
+
+/// Analyzed term of a document field, as returned by Index::get_term_vectors
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TermVector {
+    /// Term as indexed: tokenized, normalized and stemmed
+    pub term: String,
+    /// Stopword bigram of two consecutive stopwords, indexed in addition to the single terms
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default_bool")]
+    pub is_bigram: bool,
+    /// Number of occurrences of the term within the field
+    pub term_frequency: usize,
+    /// Token positions of the term within the field
+    pub positions: Vec<u16>,
+    /// Number of committed documents containing the term, in any field
+    pub document_frequency: usize,
+}
+
+/// Vocabulary of the committed documents: the analyzed terms of all indexed and stored fields, sorted by term, with their hash keys.
+/// Built lazily by Index::suggest_terms and extended incrementally with the documents committed since, rebuilt after compaction or clear_index.
+#[derive(Default, Clone)]
+pub(crate) struct TermVocabulary(Arc<Mutex<TermVocabularyData>>);
+
+#[derive(Default)]
+struct TermVocabularyData {
+    /// Generation of the document ids, the vocabulary was built for (see Snapshot)
+    generation: u64,
+    /// Documents with lower document ids have been added to the vocabulary
+    doc_count: usize,
+    /// Term -> (key_hash, key0)
+    terms: BTreeMap<String, (u64, u32)>,
+}
+
+fn is_default_bool(value: &bool) -> bool {
+    !*value
+}
+
+/// Analyzed terms of a document field, as returned by Index::get_term_vectors
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FieldTermVectors {
+    /// Field name
+    pub field: String,
+    /// Number of tokens of the field
+    pub field_length: usize,
+    /// Terms of the field, sorted by first position
+    pub terms: Vec<TermVector>,
+}
+
+impl Index {
+    /// Lists the terms of a field with their document frequencies, sorted by term, optionally filtered by prefix and paginated with offset and length.
+    /// Useful for building filter UIs, debugging analyzers, and exporting vocabularies.
+    /// The index stores only term hashes, therefore the terms are obtained by analyzing the stored field values of all documents
+    /// with the tokenizer and stemmer of the index, identical to indexing. The field has to be both indexed and stored.
+    /// Stopword bigrams are not included, deleted documents are skipped.
+    /// * `field`: name of an indexed and stored field
+    /// * `prefix`: only terms starting with prefix (lowercase) are returned, empty for all terms
+    /// * `offset`: offset within the sorted term list, for pagination
+    /// * `length`: maximum number of terms to return
+    /// * `include_uncommitted`: include the terms of documents not yet committed
+    pub fn get_terms(
+        &self,
+        field: &str,
+        prefix: &str,
+        offset: usize,
+        length: usize,
+        include_uncommitted: bool,
+    ) -> Result<FieldTerms, String> {
+        let schema_field = self.analyzable_field(field)?;
+        let prefix = prefix.to_lowercase();
+
+        let doc_count = if include_uncommitted {
+            self.indexed_doc_count
+        } else {
+            self.committed_doc_count
+        };
+
+        let mut document_frequencies: AHashMap<String, usize> = AHashMap::new();
+        for doc_id in 0..doc_count {
+            let Ok(document) =
+                self.get_document(doc_id, include_uncommitted, &None, &HashSet::new(), &[])
+            else {
+                continue;
+            };
+
+            for term in self
+                .analyze_field(schema_field, &document, false)
+                .into_keys()
+            {
+                if term.starts_with(&prefix) {
+                    *document_frequencies.entry(term).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut terms: Vec<TermFrequency> = document_frequencies
+            .into_iter()
+            .map(|(term, document_frequency)| TermFrequency {
+                term,
+                document_frequency,
+            })
+            .collect();
+        terms.sort_unstable_by(|a, b| a.term.cmp(&b.term));
+
+        Ok(FieldTerms {
+            field: field.to_string(),
+            count_total: terms.len(),
+            terms: terms.into_iter().skip(offset).take(length).collect(),
+        })
+    }
+
+    /// Suggests terms of the index vocabulary starting with a prefix, ranked by their document frequency, e.g. for query builder UIs completing the term while typing.
+    /// Unlike Suggest::suggest, which completes previous queries, the suggestions are obtained from the indexed documents.
+    /// The vocabulary consists of the analyzed terms of all indexed and stored fields of the committed documents (see Index::get_terms).
+    /// It is cached, and only the documents committed since the previous call are analyzed. Terms of deleted documents remain in the vocabulary until compaction.
+    /// * `prefix`: beginning of the term typed by the user (lowercase), empty for the most frequent terms of the index
+    /// * `length`: maximum number of terms to return
+    pub fn suggest_terms(&self, prefix: &str, length: usize) -> Vec<TermFrequency> {
+        let prefix = prefix.to_lowercase();
+        let mut vocabulary = self.term_vocabulary.0.lock().unwrap();
+
+        if vocabulary.generation != self.doc_id_generation
+            || vocabulary.doc_count > self.committed_doc_count
+        {
+            *vocabulary = TermVocabularyData {
+                generation: self.doc_id_generation,
+                ..Default::default()
+            };
+        }
+
+        let schema_fields: Vec<&SchemaField> = self
+            .indexed_schema_vec
+            .iter()
+            .filter(|schema_field| schema_field.stored)
+            .collect();
+        for doc_id in vocabulary.doc_count..self.committed_doc_count {
+            let Ok(document) = self.get_document(doc_id, false, &None, &HashSet::new(), &[]) else {
+                continue;
+            };
+            for schema_field in schema_fields.iter() {
+                for (term, term_object) in self.analyze_field(schema_field, &document, false) {
+                    vocabulary
+                        .terms
+                        .entry(term)
+                        .or_insert((term_object.key_hash, term_object.key0));
+                }
+            }
+        }
+        vocabulary.doc_count = self.committed_doc_count;
+
+        let mut terms: Vec<TermFrequency> = vocabulary
+            .terms
+            .range(prefix.clone()..)
+            .take_while(|(term, _)| term.starts_with(&prefix))
+            .filter_map(|(term, (key_hash, key0))| {
+                let document_frequency = self.committed_posting_count(&TermObject {
+                    key_hash: *key_hash,
+                    key0: *key0,
+                    ..Default::default()
+                });
+                (document_frequency > 0).then(|| TermFrequency {
+                    term: term.clone(),
+                    document_frequency,
+                })
+            })
+            .collect();
+        drop(vocabulary);
+
+        terms.sort_unstable_by(|a, b| {
+            b.document_frequency
+                .cmp(&a.document_frequency)
+                .then_with(|| a.term.cmp(&b.term))
+        });
+        terms.truncate(length);
+        terms
+    }
+
+    /// Returns the analyzed terms of selected fields of a document, with their frequencies and positions, and their document frequencies,
+    /// so relevance engineers can inspect exactly what got indexed for a problematic document.
+    /// The terms are obtained by analyzing the stored field values with the tokenizer and stemmer of the index, identical to indexing.
+    /// * `doc_id`: document ID
+    /// * `fields`: names of indexed and stored fields, if empty all indexed and stored fields are returned
+    /// * `include_uncommitted`: allow documents not yet committed
+    pub fn get_term_vectors(
+        &self,
+        doc_id: usize,
+        fields: &[String],
+        include_uncommitted: bool,
+    ) -> Result<Vec<FieldTermVectors>, String> {
+        let schema_fields: Vec<&SchemaField> = if fields.is_empty() {
+            self.indexed_schema_vec
+                .iter()
+                .filter(|schema_field| schema_field.stored)
+                .collect()
+        } else {
+            fields
+                .iter()
+                .map(|field| self.analyzable_field(field))
+                .collect::<Result<Vec<&SchemaField>, String>>()?
+        };
+
+        let document =
+            self.get_document(doc_id, include_uncommitted, &None, &HashSet::new(), &[])?;
+
+        Ok(schema_fields
+            .into_iter()
+            .map(|schema_field| {
+                let unique_terms = self.analyze_field(schema_field, &document, true);
+
+                let field_length = unique_terms
+                    .values()
+                    .filter(|term_object| !term_object.is_bigram)
+                    .map(|term_object| term_object.field_positions_vec[0].len())
+                    .sum();
+
+                let mut terms: Vec<TermVector> = unique_terms
+                    .into_values()
+                    .map(|term_object| TermVector {
+                        document_frequency: self.committed_posting_count(&term_object),
+                        term_frequency: term_object.field_positions_vec[0].len(),
+                        is_bigram: term_object.is_bigram,
+                        positions: term_object.field_positions_vec[0].clone(),
+                        term: term_object.term,
+                    })
+                    .collect();
+                terms
+                    .sort_unstable_by_key(|term| (term.positions.first().copied(), term.is_bigram));
+
+                FieldTermVectors {
+                    field: schema_field.field.clone(),
+                    field_length,
+                    terms,
+                }
+            })
+            .collect())
+    }
+
+    /// Number of committed documents containing the term, in any field
+    pub(crate) fn committed_posting_count(&self, term: &TermObject) -> usize {
+        (if self.segments_index.is_empty() {
+            0
+        } else if self.meta.access_type == AccessType::Mmap {
+            decode_posting_list_object(
+                &self.segments_index[term.key0 as usize],
+                self,
+                term.key_hash,
+                false,
+            )
+            .map_or(0, |plo| plo.posting_count)
+        } else {
+            self.segments_index[term.key0 as usize]
+                .segment
+                .get(&term.key_hash)
+                .map_or(0, |plo| plo.posting_count)
+        }) as usize
+    }
+
+    /// Returns the schema field, if it is both indexed and stored, i.e. its terms can be obtained by analyzing the stored field value
+    pub(crate) fn analyzable_field(&self, field: &str) -> Result<&SchemaField, String> {
+        let Some(schema_field) = self.schema_map.get(field) else {
+            return Err(format!("field not found: {}", field));
+        };
+        if !schema_field.indexed || !schema_field.stored {
+            return Err(format!("field is not indexed and stored: {}", field));
+        }
+        Ok(schema_field)
+    }
+
+    /// Analyzes the stored value of a field of a document with the tokenizer and stemmer of the index, identical to indexing.
+    /// Returns the terms of the field with their positions (field_positions_vec[0]).
+    pub(crate) fn analyze_field(
+        &self,
+        schema_field: &SchemaField,
+        document: &Document,
+        enable_bigram: bool,
+    ) -> AHashMap<String, TermObject> {
+        let mut unique_terms: AHashMap<String, TermObject> = AHashMap::new();
+
+        let Some(field_value) = document.get(&schema_field.field) else {
+            return unique_terms;
+        };
+
+        let text = match schema_field.field_type {
+            FieldType::Text | FieldType::String => serde_json::from_str(&field_value.to_string())
+                .unwrap_or(field_value.to_string())
+                .to_string(),
+            _ => field_value.to_string(),
+        };
+
+        let language = document_language(&self.meta.stemmer, &self.indexed_schema_vec, document);
+
+        let mut non_unique_terms = Vec::new();
+        let mut nonunique_terms_count = 0u32;
+        tokenizer(
+            self,
+            &text,
+            &mut unique_terms,
+            &mut non_unique_terms,
+            self.meta.tokenizer,
+            self.segment_number_mask1,
+            &mut nonunique_terms_count,
+            u16::MAX as u32,
+            MAX_POSITIONS_PER_TERM,
+            false,
+            &mut QueryType::Union,
+            enable_bigram && self.enable_bigram,
+            0,
+            1,
+            language,
+        );
+
+        unique_terms
+    }
+}
@@ -1,17 +1,24 @@
-use std::cmp;
+use std::{cmp, sync::Arc};
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use finl_unicode::categories::{CharacterCategories, MinorCategory};
+use rust_stemmers::{Algorithm, Stemmer};
+use whatlang::Lang;
 
 use crate::{
     index::{
-        Index, NonUniqueTermObject, TermObject, TokenizerType, HASHER_32, HASHER_64,
-        STOPWORD_HASHSET,
+        Index, Language, NonUniqueTermObject, StemmerType, TermObject, TokenizerType, HASHER_32,
+        HASHER_64, MAX_POSITIONS_PER_TERM, STOPWORD_HASHSET,
     },
-    search::QueryType,
+    search::{QueryExpansion, QueryType},
 };
 
 const APOSTROPH: [char; 2] = ['\u{2019}', '\u{0027}'];
+/// Prefix of the terms which are indexed additionally without synonym expansion, for all terms which are synonyms of other terms.
+/// Control characters are never part of a token, so prefixed terms can't collide with regular terms.
+pub(crate) const UNEXPANDED_TERM_PREFIX: char = '\u{1}';
+/// Prefix of the unstemmed terms, which are indexed additionally with IndexMetaObject.index_unstemmed
+pub(crate) const UNSTEMMED_TERM_PREFIX: char = '\u{2}';
 const ZALGO_CHAR_CATEGORIES: [MinorCategory; 2] = [MinorCategory::Mn, MinorCategory::Me];
 
 /// fold_diacritics_accents_zalgo_umlaut() (used by TokenizerType::UnicodeAlphanumericFolded):
@@ -91,7 +98,106 @@ pub fn fold_diacritics_accents_zalgo_umlaut(string: &str) -> String {
         })
 }
 
+/// Detects the language of a text. Returns None if the language is not supported for stemming or the detection is not reliable.
+pub fn detect_language(text: &str) -> Option<Language> {
+    let info = whatlang::detect(text)?;
+    if !info.is_reliable() {
+        return None;
+    }
+
+    match info.lang() {
+        Lang::Ara => Some(Language::Arabic),
+        Lang::Dan => Some(Language::Danish),
+        Lang::Nld => Some(Language::Dutch),
+        Lang::Eng => Some(Language::English),
+        Lang::Fin => Some(Language::Finnish),
+        Lang::Fra => Some(Language::French),
+        Lang::Deu => Some(Language::German),
+        Lang::Ell => Some(Language::Greek),
+        Lang::Hun => Some(Language::Hungarian),
+        Lang::Ita => Some(Language::Italian),
+        Lang::Nob => Some(Language::Norwegian),
+        Lang::Por => Some(Language::Portuguese),
+        Lang::Ron => Some(Language::Romanian),
+        Lang::Rus => Some(Language::Russian),
+        Lang::Spa => Some(Language::Spanish),
+        Lang::Swe => Some(Language::Swedish),
+        Lang::Tam => Some(Language::Tamil),
+        Lang::Tur => Some(Language::Turkish),
+        _ => None,
+    }
+}
+
+/// Language used to stem a query: the fixed language of the index, or for StemmerType::Detect the query_language hint, the detected query language or the default language.
+pub(crate) fn query_language(
+    stemmer: &StemmerType,
+    query_language: Option<Language>,
+    query_string: &str,
+) -> Option<Language> {
+    match stemmer {
+        StemmerType::None => None,
+        StemmerType::Language(language) => Some(*language),
+        StemmerType::Detect {
+            default_language, ..
+        } => query_language
+            .or_else(|| detect_language(query_string))
+            .or(*default_language),
+    }
+}
+
+fn stemmer_algorithm(language: Language) -> Algorithm {
+    match language {
+        Language::Arabic => Algorithm::Arabic,
+        Language::Danish => Algorithm::Danish,
+        Language::Dutch => Algorithm::Dutch,
+        Language::English => Algorithm::English,
+        Language::Finnish => Algorithm::Finnish,
+        Language::French => Algorithm::French,
+        Language::German => Algorithm::German,
+        Language::Greek => Algorithm::Greek,
+        Language::Hungarian => Algorithm::Hungarian,
+        Language::Italian => Algorithm::Italian,
+        Language::Norwegian => Algorithm::Norwegian,
+        Language::Portuguese => Algorithm::Portuguese,
+        Language::Romanian => Algorithm::Romanian,
+        Language::Russian => Algorithm::Russian,
+        Language::Spanish => Algorithm::Spanish,
+        Language::Swedish => Algorithm::Swedish,
+        Language::Tamil => Algorithm::Tamil,
+        Language::Turkish => Algorithm::Turkish,
+    }
+}
+
+/// Custom tokenization of the embedding application, e.g. domain-specific token rules or an external morphological analyzer,
+/// used by indices with TokenizerType::Custom (see Index::set_tokenizer).
+/// Stemming, stopwords, bigrams, synonyms and the query operators are applied to the returned terms as for the built-in tokenizers.
+pub trait Tokenizer: Send + Sync {
+    /// Splits the text of a document field (is_query=false) or of a query (is_query=true) into terms, in the order of the text.
+    /// The terms should be normalized, e.g. lowercase, with the same rules for documents and queries.
+    /// For queries the search operators have to be kept: `+` and `-` as prefix, `"` as prefix of the first and suffix of the last term of a phrase.
+    /// Removed tokens, e.g. stopwords, can be returned as empty strings: they are not indexed, but keep the positions of the following terms,
+    /// so that phrase queries still match with the removed tokens, e.g. "the lord of the rings" with "the" and "of" removed.
+    fn tokenize(&self, text: &str, is_query: bool) -> Vec<String>;
+}
+
+impl Index {
+    /// Registers the tokenizer of an index with TokenizerType::Custom after create_index, before indexing or searching.
+    /// Existing indices are opened with open_index_with_tokenizer, with the same tokenizer, as the index stores the terms, but not the tokenizer.
+    pub fn set_tokenizer(&mut self, tokenizer: Arc<dyn Tokenizer>) -> Result<(), String> {
+        if self.meta.tokenizer != TokenizerType::Custom {
+            return Err(format!(
+                "index tokenizer is {:?}, not Custom",
+                self.meta.tokenizer
+            ));
+        }
+        self.custom_tokenizer = Some(tokenizer);
+        Ok(())
+    }
+}
+
 /// Tokenizer splits text to terms
+/// If a language is specified, terms are stemmed with the stemmer of that language, except stopwords and protected words.
+/// Custom stopwords of the index are removed, protected words are not split (see WordListType).
 #[allow(clippy::too_many_arguments)]
 #[allow(clippy::assigning_clones)]
 pub(crate) fn tokenizer(
@@ -110,7 +216,11 @@ pub(crate) fn tokenizer(
     enable_bigram: bool,
     indexed_field_id: usize,
     indexed_field_number: usize,
+    language: Option<Language>,
 ) {
+    let stemmer_option = language.map(|language| Stemmer::create(stemmer_algorithm(language)));
+    let enable_bigram = enable_bigram && matches!(language, None | Some(Language::English));
+
     let token_per_field_max_capped = cmp::max(token_per_field_max, 65_536);
 
     let text_normalized;
@@ -208,6 +318,13 @@ pub(crate) fn tokenizer(
                 }
             }
 
+            TokenizerType::Custom => {
+                text_normalized = String::new();
+                if let Some(custom_tokenizer) = index.custom_tokenizer.as_ref() {
+                    non_unique_terms_line_string = custom_tokenizer.tokenize(text, is_query);
+                }
+            }
+
             #[cfg(feature = "zh")]
             TokenizerType::UnicodeAlphanumericZH => {
                 text_normalized = text.to_lowercase();
@@ -323,6 +440,13 @@ pub(crate) fn tokenizer(
                 }
             }
 
+            TokenizerType::Custom => {
+                text_normalized = String::new();
+                if let Some(custom_tokenizer) = index.custom_tokenizer.as_ref() {
+                    non_unique_terms_line_string = custom_tokenizer.tokenize(text, is_query);
+                }
+            }
+
             #[cfg(feature = "zh")]
             TokenizerType::UnicodeAlphanumericZH => {
                 text_normalized = text.to_lowercase();
@@ -387,6 +511,13 @@ pub(crate) fn tokenizer(
             .collect();
     }
 
+    if tokenizer == TokenizerType::Custom {
+        non_unique_terms_line = non_unique_terms_line_string
+            .iter()
+            .map(|s| s.as_str())
+            .collect();
+    }
+
     if tokenizer == TokenizerType::AsciiAlphabetic
         || tokenizer == TokenizerType::UnicodeAlphanumeric
         || tokenizer == TokenizerType::UnicodeAlphanumericFolded
@@ -400,21 +531,38 @@ pub(crate) fn tokenizer(
         } else if !first_part.is_empty() {
             non_unique_terms_line.push(first_part);
         }
+
+        index
+            .word_lists
+            .merge_protected_words(&text_normalized, &mut non_unique_terms_line);
     }
 
     let mut position: u32 = 0;
     let mut is_phrase = query_type == &QueryType::Phrase;
     let mut previous_term_string = "".to_string();
     let mut previous_term_hash = 0;
+    let mut position_gap = 0;
 
     let mut bigrams: Vec<TermObject> = Vec::new();
     for term_string in non_unique_terms_line.iter_mut() {
+        // a removed token leaves a position gap instead of shifting the positions of the following terms, and no bigram spans it
+        if term_string.is_empty() {
+            position += 1;
+            position_gap += 1;
+            previous_term_string = "".to_string();
+            previous_term_hash = 0;
+            if position >= token_per_field_max_capped {
+                break;
+            }
+            continue;
+        }
+
+        let mut query_type_term = if is_phrase {
+            QueryType::Phrase
+        } else {
+            query_type.clone()
+        };
         if is_query {
-            let mut query_type_term = if is_phrase {
-                QueryType::Phrase
-            } else {
-                query_type.clone()
-            };
             if term_string.starts_with('+') {
                 if query_type != &QueryType::Phrase {
                     *query_type = QueryType::Intersection;
@@ -440,13 +588,40 @@ pub(crate) fn tokenizer(
             if term_string.is_empty() {
                 continue;
             }
+        }
 
+        // a custom stopword of the index is removed like a removed token of a custom tokenizer
+        if index.word_lists.is_stopword(term_string) {
+            position += 1;
+            position_gap += 1;
+            previous_term_string = "".to_string();
+            previous_term_hash = 0;
+            if position >= token_per_field_max_capped {
+                break;
+            }
+            continue;
+        }
+
+        let term_stemmed;
+        let term_string: &str = match &stemmer_option {
+            Some(stemmer)
+                if !STOPWORD_HASHSET.contains(&HASHER_64.hash_one(term_string.as_bytes()))
+                    && !index.word_lists.is_protected(term_string) =>
+            {
+                term_stemmed = stemmer.stem(term_string);
+                &term_stemmed
+            }
+            _ => term_string,
+        };
+
+        if is_query {
             non_unique_terms.push(NonUniqueTermObject {
                 term: term_string.to_string(),
                 term_bigram1: "".to_string(),
                 term_bigram2: "".to_string(),
                 is_bigram: false,
                 op: query_type_term,
+                position_gap: std::mem::take(&mut position_gap),
             });
         }
 
@@ -562,3 +737,112 @@ pub(crate) fn tokenizer(
     }
     *nonunique_terms_count = position;
 }
+
+/// Copy of a term with a prefixed key, e.g. to index a term additionally without synonym expansion or stemming
+pub(crate) fn prefixed_term(
+    prefix: char,
+    term: &TermObject,
+    segment_number_mask1: u32,
+) -> TermObject {
+    let prefixed_term_string = prefix.to_string() + &term.term;
+    let term_bytes = prefixed_term_string.as_bytes();
+    TermObject {
+        key0: HASHER_32.hash_one(term_bytes) as u32 & segment_number_mask1,
+        key_hash: HASHER_64.hash_one(term_bytes),
+        term: prefixed_term_string,
+        ..term.clone()
+    }
+}
+
+/// Adds the unstemmed terms of a field, except stopwords which are never stemmed, prefixed with UNSTEMMED_TERM_PREFIX.
+/// Used at index time with IndexMetaObject.index_unstemmed, so that queries can disable stemming (QueryExpansion.disable_stemming).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn add_unstemmed_terms(
+    index: &Index,
+    text: &str,
+    unique_terms: &mut AHashMap<String, TermObject>,
+    tokenizer_type: TokenizerType,
+    segment_number_mask1: u32,
+    token_per_field_max: u32,
+    indexed_field_id: usize,
+    indexed_field_number: usize,
+) {
+    let mut unstemmed_terms: AHashMap<String, TermObject> = AHashMap::new();
+    tokenizer(
+        index,
+        text,
+        &mut unstemmed_terms,
+        &mut Vec::new(),
+        tokenizer_type,
+        segment_number_mask1,
+        &mut 0,
+        token_per_field_max,
+        MAX_POSITIONS_PER_TERM,
+        false,
+        &mut QueryType::Union,
+        false,
+        indexed_field_id,
+        indexed_field_number,
+        None,
+    );
+
+    for term in unstemmed_terms
+        .values()
+        .filter(|term| !STOPWORD_HASHSET.contains(&term.key_hash))
+    {
+        let unstemmed_term = prefixed_term(UNSTEMMED_TERM_PREFIX, term, segment_number_mask1);
+        if let Some(existing) = unique_terms.get_mut(&unstemmed_term.term) {
+            existing.field_positions_vec[indexed_field_id]
+                .extend_from_slice(&term.field_positions_vec[indexed_field_id]);
+        } else {
+            unique_terms.insert(unstemmed_term.term.clone(), unstemmed_term);
+        }
+    }
+}
+
+/// Replaces the query terms with their prefixed copies, which were indexed without synonym expansion or without stemming, as requested by query_expansion.
+/// Stemming can only be disabled for indices with IndexMetaObject.index_unstemmed, the query then has to be tokenized without stemming.
+/// Unstemmed terms are indexed without synonym expansion, hence disabling stemming disables synonyms too.
+pub(crate) fn apply_query_expansion(
+    index: &Index,
+    query_expansion: &QueryExpansion,
+    unique_terms: &mut AHashMap<String, TermObject>,
+    non_unique_terms: &mut [NonUniqueTermObject],
+) {
+    let disable_stemming = query_expansion.disable_stemming && index.is_unstemmed_indexed();
+    let disable_synonyms = query_expansion.disable_synonyms && !index.synonym_targets.is_empty();
+    if !disable_stemming && !disable_synonyms {
+        return;
+    }
+
+    let mut replaced_terms: AHashSet<String> = AHashSet::new();
+    for term in unique_terms.values() {
+        if term.is_bigram {
+            continue;
+        }
+        let is_replaced = if disable_stemming {
+            !STOPWORD_HASHSET.contains(&term.key_hash)
+        } else {
+            index.synonym_targets.contains(&term.key_hash)
+        };
+        if is_replaced {
+            replaced_terms.insert(term.term.clone());
+        }
+    }
+
+    let prefix = if disable_stemming {
+        UNSTEMMED_TERM_PREFIX
+    } else {
+        UNEXPANDED_TERM_PREFIX
+    };
+    for term_string in replaced_terms.iter() {
+        let term = unique_terms.remove(term_string).unwrap();
+        let term = prefixed_term(prefix, &term, index.segment_number_mask1);
+        unique_terms.insert(term.term.clone(), term);
+    }
+    for non_unique_term in non_unique_terms.iter_mut() {
+        if replaced_terms.contains(&non_unique_term.term) {
+            non_unique_term.term.insert(0, prefix);
+        }
+    }
+}
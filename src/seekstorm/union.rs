@@ -3,7 +3,7 @@ use crate::{
     compatible::{_blsr_u64, _mm_tzcnt_64},
     geo_search::{decode_morton_2_d, euclidian_distance},
     index::{
-        AccessType, CompressionType, Index, NonUniquePostingListObjectQuery,
+        AccessType, CompressionType, FieldType, Index, NonUniquePostingListObjectQuery,
         PostingListObjectQuery, QueueObject, ROARING_BLOCK_SIZE,
     },
     intersection::intersection_blockid,
@@ -11,7 +11,7 @@ use crate::{
     single::{single_blockid, single_docid},
     utils::{
         block_copy, cast_byte_ulong_slice, cast_byte_ushort_slice, read_f32, read_f64, read_i16,
-        read_i32, read_i64, read_i8, read_u16, read_u32, read_u64,
+        read_i32, read_i64, read_i8, read_u128, read_u16, read_u32, read_u64,
     },
 };
 
@@ -165,7 +165,8 @@ pub(crate) async fn union_docid<'a>(
         query_list_item_mut.run_end = 0;
     }
 
-    if valid_term_count == 0 {
+    // no document of the block can match the minimum number of query terms
+    if valid_term_count == 0 || valid_term_count < search_result.minimum_should_match {
         return;
     }
 
@@ -197,7 +198,7 @@ pub(crate) async fn union_docid<'a>(
         return;
     };
 
-    if result_type == &ResultType::Count {
+    if result_type == &ResultType::Count && search_result.minimum_should_match <= 1 {
         union_count(
             index,
             result_count,
@@ -250,6 +251,9 @@ pub(crate) async fn union_blockid<'a>(
     let mut task_list = Vec::new();
 
     loop {
+        if search_result.timed_out() {
+            break;
+        }
         let mut break_loop = true;
         let mut block_id_min = usize::MAX;
 
@@ -484,12 +488,16 @@ pub(crate) async fn union_scan<'a>(
         }
     }
 
-    let block_skip = search_result.topk_candidates.current_heap_size >= top_k
+    let query_list_len = cmp::min(query_list.len(), 8);
+    let minimum_should_match = cmp::min(search_result.minimum_should_match, query_list_len) as u32;
+    // with minimum_should_match, all matching documents are added, which applies the facet filter and counts results and facets
+    let add_all = minimum_should_match > 1 && result_type != &ResultType::Topk;
+
+    let block_skip = !add_all
+        && search_result.topk_candidates.current_heap_size >= top_k
         && max_score <= search_result.topk_candidates._elements[0].score
         && search_result.topk_candidates.result_sort.is_empty();
 
-    let query_list_len = cmp::min(query_list.len(), 8);
-
     let query_combination_count = 1 << query_list_len;
     let mut query_terms_max_score_sum_table: Vec<f32> = vec![0.0; query_combination_count];
     for (i, max_score) in query_terms_max_score_sum_table.iter_mut().enumerate() {
@@ -502,17 +510,22 @@ pub(crate) async fn union_scan<'a>(
 
     let mut p_docid_array: [u16; 8] = [0; 8];
 
-    let mut _result_count = 0;
+    let mut result_count_added = 0;
     let block_id_msb = block_id << 16;
 
     for (i, query_terms_bitset) in query_terms_bitset_table.iter().enumerate() {
         if *query_terms_bitset > 0 {
-            result_count_local += 1;
+            let is_match = query_terms_bitset.count_ones() >= minimum_should_match;
+            if is_match {
+                result_count_local += 1;
+            }
 
-            if !block_skip
-                && (search_result.topk_candidates.current_heap_size < top_k
-                    || query_terms_max_score_sum_table[*query_terms_bitset as usize]
-                        > search_result.topk_candidates._elements[0].score)
+            if is_match
+                && (add_all
+                    || (!block_skip
+                        && (search_result.topk_candidates.current_heap_size < top_k
+                            || query_terms_max_score_sum_table[*query_terms_bitset as usize]
+                                > search_result.topk_candidates._elements[0].score)))
             {
                 for (j, query_term) in query_list.iter_mut().take(query_list_len).enumerate() {
                     query_term.bm25_flag = (query_terms_bitset & MASK_ARRAY[j]) > 0;
@@ -523,7 +536,7 @@ pub(crate) async fn union_scan<'a>(
                 add_result_multiterm_multifield(
                     index,
                     block_id_msb | i,
-                    &mut _result_count,
+                    &mut result_count_added,
                     search_result,
                     top_k,
                     result_type,
@@ -548,7 +561,11 @@ pub(crate) async fn union_scan<'a>(
         }
     }
 
-    *result_count += result_count_local;
+    *result_count += if add_all {
+        result_count_added
+    } else {
+        result_count_local
+    };
 }
 
 pub(crate) async fn union_count<'a>(
@@ -744,7 +761,10 @@ pub(crate) async fn union_count<'a>(
                 }
 
                 for (i, facet) in index.facets.iter().enumerate() {
-                    if search_result.query_facets[i].length == 0 {
+                    if search_result.query_facets[i].length == 0
+                        || (matches!(facet.field_type, FieldType::Date | FieldType::Ip)
+                            && index.is_facet_missing(i, docid))
+                    {
                         continue;
                     }
 
@@ -850,6 +870,17 @@ pub(crate) async fn union_count<'a>(
                                 })
                                 .map_or_else(|idx| idx as u16 - 1, |idx| idx as u16)
                         }
+                        Ranges::Ip(_range_type, ranges) => {
+                            let facet_value = read_u128(
+                                &index.facets_file_mmap,
+                                (index.facets_size_sum * docid) + facet.offset,
+                            );
+                            match ranges.binary_search_by_key(&facet_value, |range| range.1) {
+                                Ok(idx) => idx as u16,
+                                Err(0) => continue,
+                                Err(idx) => idx as u16 - 1,
+                            }
+                        }
                         Ranges::Point(_range_type, ranges, base, unit) => {
                             let facet_value = read_u64(
                                 &index.facets_file_mmap,
@@ -1132,6 +1163,7 @@ pub(crate) async fn union_docid_3<'a>(
         }
 
         if !query_queue.is_empty()
+            && !search_result.timed_out()
             && (search_result.topk_candidates.current_heap_size < top_k
                 || query_queue.first().unwrap().max_score
                     > search_result.topk_candidates._elements[0].score)
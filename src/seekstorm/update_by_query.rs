@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    computed::ComputedExpression,
+    index::{DeleteDocumentsByQuery, Document, IndexArc, SchemaField, UpdateDocuments},
+    search::{FacetFilter, QueryType, ResultSort},
+};
+
+/// Update of the documents matching a query (see UpdateByQuery): a partial-document patch and/or a field-set script.
+/// Example: `{"patch":{"in_stock":false},"set":{"price":"round(price * 0.9)"}}`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DocumentUpdate {
+    /// Partial document: its fields are added to the document, replacing the fields with the same name. The other fields of the document are kept.
+    #[serde(default)]
+    pub patch: Document,
+    /// Field-set script: field name and expression (see ComputedExpression), e.g. `"price": "round(price * 0.9)"`.
+    /// The expressions are evaluated against the document after the patch is applied. If an expression has no value for the document, the field is left unchanged.
+    #[serde(default)]
+    pub set: HashMap<String, String>,
+}
+
+/// Document of an update by query which was not updated
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UpdateError {
+    pub doc_id: u64,
+    pub error: String,
+}
+
+/// Result of an update by query
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UpdateResult {
+    /// Number of updated documents
+    pub updated_count: usize,
+    /// Documents which were not updated, e.g. because the updated document is invalid with IndexMetaObject.strict_schema
+    pub errors: Vec<UpdateError>,
+}
+
+/// Update by query: applies a DocumentUpdate to every document matching a query.
+/// Update by query and delete_documents_by_query have identical query parameters (see DeleteDocumentsByQuery).
+/// The documents are read from the document store, updated, and reindexed with update_documents: an updated document gets a new document id.
+/// * Requires that all indexed fields are stored, as the fields which are not stored are lost by the update.
+/// * As with update_documents, the updated documents are searchable after the next commit (or immediately with include_uncommited=true).
+#[allow(clippy::too_many_arguments)]
+#[allow(async_fn_in_trait)]
+pub trait UpdateByQuery {
+    /// Updates the documents matching the query, returns the number of updated documents and the documents which were not updated.
+    async fn update_by_query(
+        &self,
+        query_string: String,
+        query_type_default: QueryType,
+        offset: usize,
+        length: usize,
+        include_uncommited: bool,
+        field_filter: Vec<String>,
+        facet_filter: Vec<FacetFilter>,
+        result_sort: Vec<ResultSort>,
+        update: &DocumentUpdate,
+    ) -> Result<UpdateResult, String>;
+
+    /// Updates the documents with the document ids, e.g. in batches of the document ids of delete_documents_by_query_dry_run.
+    /// Document ids of non-existing or deleted documents are skipped.
+    async fn update_documents_by_id(
+        &self,
+        docid_vec: Vec<u64>,
+        update: &DocumentUpdate,
+    ) -> Result<UpdateResult, String>;
+}
+
+impl UpdateByQuery for IndexArc {
+    async fn update_by_query(
+        &self,
+        query_string: String,
+        query_type_default: QueryType,
+        offset: usize,
+        length: usize,
+        include_uncommited: bool,
+        field_filter: Vec<String>,
+        facet_filter: Vec<FacetFilter>,
+        result_sort: Vec<ResultSort>,
+        update: &DocumentUpdate,
+    ) -> Result<UpdateResult, String> {
+        let docid_vec = self
+            .delete_documents_by_query_dry_run(
+                query_string,
+                query_type_default,
+                offset,
+                length,
+                include_uncommited,
+                field_filter,
+                facet_filter,
+                result_sort,
+            )
+            .await;
+        self.update_documents_by_id(docid_vec, update).await
+    }
+
+    async fn update_documents_by_id(
+        &self,
+        docid_vec: Vec<u64>,
+        update: &DocumentUpdate,
+    ) -> Result<UpdateResult, String> {
+        let index_ref = self.read().await;
+        index_ref.check_writable()?;
+        if let Some(schema_field) = index_ref
+            .indexed_schema_vec
+            .iter()
+            .find(|schema_field| !schema_field.stored)
+        {
+            return Err(format!(
+                "update by query requires all indexed fields to be stored: {}",
+                schema_field.field
+            ));
+        }
+
+        let mut set_vec: Vec<(&SchemaField, ComputedExpression)> = Vec::new();
+        for (field, expression) in update.set.iter() {
+            let Some(schema_field) = index_ref.schema_map.get(field) else {
+                return Err(format!("update by query: field {} not in schema", field));
+            };
+            set_vec.push((
+                schema_field,
+                ComputedExpression::compile(expression, &index_ref.schema_map)?,
+            ));
+        }
+
+        let mut update_result = UpdateResult::default();
+        let mut id_document_vec = Vec::new();
+        for doc_id in docid_vec {
+            let Ok(mut document) =
+                index_ref.get_document(doc_id as usize, true, &None, &HashSet::new(), &[])
+            else {
+                continue;
+            };
+
+            for (key, value) in update.patch.iter() {
+                document.insert(key.clone(), value.clone());
+            }
+            let values: Vec<_> = set_vec
+                .iter()
+                .filter_map(|(schema_field, expression)| {
+                    expression
+                        .evaluate(&document, &schema_field.field_type)
+                        .map(|value| (schema_field.field.clone(), value))
+                })
+                .collect();
+            document.extend(values);
+
+            if index_ref.meta.strict_schema {
+                if let Err(error) = index_ref.validate_document(&document) {
+                    update_result.errors.push(UpdateError { doc_id, error });
+                    continue;
+                }
+            }
+            id_document_vec.push((doc_id, document));
+        }
+        drop(index_ref);
+
+        update_result.updated_count = id_document_vec.len();
+        self.update_documents(id_document_vec).await;
+        Ok(update_result)
+    }
+}
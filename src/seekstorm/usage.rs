@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::Index;
+
+pub(crate) const USAGE_COUNTERS_FILENAME: &str = "usage_counters.json";
+
+/// Type of an operation counted by the usage counters of an index
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum OperationType {
+    /// Document indexed or updated
+    Indexing,
+    /// Document deleted
+    Delete,
+    /// Search request
+    Query,
+}
+
+/// Usage counters of an index since its creation, e.g. for billing and monitoring
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UsageCounters {
+    /// Number of indexed and updated documents
+    pub indexing_count: u64,
+    /// Number of deleted documents
+    pub delete_count: u64,
+    /// Number of search requests
+    pub query_count: u64,
+}
+
+impl UsageCounters {
+    /// Number of all operations: indexing, delete and query
+    pub fn operations_count(&self) -> u64 {
+        self.indexing_count + self.delete_count + self.query_count
+    }
+}
+
+impl std::ops::AddAssign for UsageCounters {
+    fn add_assign(&mut self, other: Self) {
+        self.indexing_count += other.indexing_count;
+        self.delete_count += other.delete_count;
+        self.query_count += other.query_count;
+    }
+}
+
+/// Usage counters, updated by concurrent operations holding a read lock of the index
+#[derive(Default)]
+pub(crate) struct UsageCountersAtomic {
+    indexing_count: AtomicU64,
+    delete_count: AtomicU64,
+    query_count: AtomicU64,
+    unsaved: AtomicBool,
+}
+
+pub(crate) fn load_usage_counters(index_path: &Path) -> UsageCountersAtomic {
+    let usage_counters: UsageCounters =
+        if let Ok(usage_counters_file) = File::open(index_path.join(USAGE_COUNTERS_FILENAME)) {
+            serde_json::from_reader(BufReader::new(usage_counters_file)).unwrap_or_default()
+        } else {
+            UsageCounters::default()
+        };
+    UsageCountersAtomic {
+        indexing_count: AtomicU64::new(usage_counters.indexing_count),
+        delete_count: AtomicU64::new(usage_counters.delete_count),
+        query_count: AtomicU64::new(usage_counters.query_count),
+        unsaved: AtomicBool::new(false),
+    }
+}
+
+impl Index {
+    /// Adds count operations of a type to the usage counters of the index.
+    /// The counters are not updated by the library itself, but by the application, e.g. the SeekStorm server per API request,
+    /// so that internal operations like percolation or alert evaluation are not counted.
+    pub fn record_operations(&self, operation_type: OperationType, count: u64) {
+        let counter = match operation_type {
+            OperationType::Indexing => &self.usage_counters.indexing_count,
+            OperationType::Delete => &self.usage_counters.delete_count,
+            OperationType::Query => &self.usage_counters.query_count,
+        };
+        counter.fetch_add(count, Ordering::Relaxed);
+        self.usage_counters.unsaved.store(true, Ordering::Relaxed);
+    }
+
+    /// Get the usage counters of the index
+    pub fn get_usage_counters(&self) -> UsageCounters {
+        UsageCounters {
+            indexing_count: self.usage_counters.indexing_count.load(Ordering::Relaxed),
+            delete_count: self.usage_counters.delete_count.load(Ordering::Relaxed),
+            query_count: self.usage_counters.query_count.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Saves the usage counters to the index directory, if they have changed since the last save.
+    /// Invoked by close_index. Without closing the index, it has to be invoked periodically, as the SeekStorm server does for all its indices.
+    pub fn save_usage_counters(&self) -> Result<(), String> {
+        if !self.usage_counters.unsaved.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let result = File::create(Path::new(&self.index_path_string).join(USAGE_COUNTERS_FILENAME))
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                serde_json::to_writer(&file, &self.get_usage_counters()).map_err(|e| e.to_string())
+            });
+        if result.is_err() {
+            self.usage_counters.unsaved.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
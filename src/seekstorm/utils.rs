@@ -46,6 +46,10 @@ pub(crate) fn write_i64(value: i64, vec8: &mut [u8], pos: usize) {
     vec8[pos..(pos + 8)].copy_from_slice(&value.to_le_bytes());
 }
 
+pub(crate) fn write_u128(value: u128, vec8: &mut [u8], pos: usize) {
+    vec8[pos..(pos + 16)].copy_from_slice(&value.to_le_bytes());
+}
+
 pub(crate) fn write_f32(value: f32, vec8: &mut [u8], pos: usize) {
     vec8[pos..(pos + 4)].copy_from_slice(&value.to_le_bytes());
 }
@@ -118,6 +122,11 @@ pub(crate) fn read_i64(vec8: &[u8], pos: usize) -> i64 {
     i64::from_le_bytes(vec8[pos..pos + 8].try_into().unwrap())
 }
 
+#[inline]
+pub(crate) fn read_u128(vec8: &[u8], pos: usize) -> u128 {
+    u128::from_le_bytes(vec8[pos..pos + 16].try_into().unwrap())
+}
+
 #[inline]
 pub(crate) fn read_f32(vec8: &[u8], pos: usize) -> f32 {
     f32::from_le_bytes(vec8[pos..pos + 8].try_into().unwrap())
@@ -168,3 +177,70 @@ pub(crate) fn truncate(s: &str, max_chars: usize) -> &str {
         Some((idx, _)) => &s[..idx],
     }
 }
+
+/// Parses a decimal number (e.g. "19.99", "-0.5", "1e3") into an integer scaled by 10^scale, without floating point artifacts.
+/// Digits beyond the scale are rounded half away from zero. Returns None for invalid numbers and on overflow.
+pub(crate) fn parse_decimal(text: &str, scale: u8) -> Option<i64> {
+    let text = text.trim();
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(position) => (&text[..position], text[position + 1..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if (integer.is_empty() && fraction.is_empty())
+        || !integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let digits: Vec<u8> = integer
+        .bytes()
+        .chain(fraction.bytes())
+        .map(|byte| byte - b'0')
+        .collect();
+    // number of digits before the decimal point of the scaled value
+    let point = (integer.len() as i64).saturating_add(exponent) + scale as i64;
+    if point > 38 {
+        return digits.iter().all(|digit| *digit == 0).then_some(0);
+    }
+
+    let mut value: i128 = 0;
+    for i in 0..point.max(0) as usize {
+        value = value * 10 + digits.get(i).copied().unwrap_or(0) as i128;
+    }
+    if point >= 0 && digits.get(point as usize).is_some_and(|digit| *digit >= 5) {
+        value += 1;
+    }
+    i64::try_from(if negative { -value } else { value }).ok()
+}
+
+/// Converts a JSON number or string into a decimal scaled by 10^scale
+pub(crate) fn decimal_from_json(value: &serde_json::Value, scale: u8) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(number) => parse_decimal(&number.to_string(), scale),
+        serde_json::Value::String(string) => parse_decimal(string, scale),
+        _ => None,
+    }
+}
+
+/// Converts a f64 (e.g. a filter or range boundary) into a decimal scaled by 10^scale, via its shortest decimal representation.
+/// Values exceeding the range of the decimal are saturated.
+pub(crate) fn decimal_from_f64(value: f64, scale: u8) -> i64 {
+    parse_decimal(&value.to_string(), scale).unwrap_or(if value < 0.0 {
+        i64::MIN
+    } else {
+        i64::MAX
+    })
+}
+
+/// Converts a decimal scaled by 10^scale into the nearest f64
+pub(crate) fn decimal_to_f64(value: i64, scale: u8) -> f64 {
+    value as f64 / 10f64.powi(scale as i32)
+}
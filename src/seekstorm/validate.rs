@@ -0,0 +1,163 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+use crate::{
+    date::date_from_json,
+    geo_search::points_from_json,
+    geo_shape::GeoShape,
+    index::{index_document_notify, Document, FieldType, FileType, Index, IndexArc, SchemaField},
+    ip::parse_ip,
+    join::JoinValue,
+    utils::decimal_from_json,
+};
+
+/// Result of a document of a batch, returned by index_documents_with_results: either the document id of the indexed document, or the reason why it was not indexed
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DocumentResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DocumentResult {
+    /// Result of a document which was not indexed
+    pub fn error(error: String) -> Self {
+        DocumentResult {
+            doc_id: None,
+            error: Some(error),
+        }
+    }
+}
+
+impl SchemaField {
+    /// Checks a value against the field type. Without strict_schema, values of Bool, String, Keyword and Text fields are always valid.
+    pub(crate) fn is_valid_value(&self, value: &Value, strict_schema: bool) -> bool {
+        match self.field_type {
+            FieldType::U8 => value.as_u64().is_some_and(|v| v <= u8::MAX as u64),
+            FieldType::U16 => value.as_u64().is_some_and(|v| v <= u16::MAX as u64),
+            FieldType::U32 => value.as_u64().is_some_and(|v| v <= u32::MAX as u64),
+            FieldType::U64 => value.as_u64().is_some(),
+            FieldType::I8 => value.as_i64().is_some_and(|v| i8::try_from(v).is_ok()),
+            FieldType::I16 => value.as_i64().is_some_and(|v| i16::try_from(v).is_ok()),
+            FieldType::I32 => value.as_i64().is_some_and(|v| i32::try_from(v).is_ok()),
+            FieldType::I64 | FieldType::Timestamp => value.as_i64().is_some(),
+            FieldType::F32 | FieldType::F64 => value.as_f64().is_some(),
+            FieldType::Decimal => decimal_from_json(value, self.scale).is_some(),
+            FieldType::Ip => value.as_str().and_then(parse_ip).is_some(),
+            FieldType::Date => date_from_json(value, &self.date_formats).is_some(),
+            FieldType::StringSet => serde_json::from_value::<Vec<String>>(value.clone()).is_ok(),
+            FieldType::Point => points_from_json(value).is_some(),
+            FieldType::GeoShape => serde_json::from_value::<GeoShape>(value.clone())
+                .is_ok_and(|shape| shape.validate().is_ok()),
+            FieldType::Join => serde_json::from_value::<JoinValue>(value.clone())
+                .is_ok_and(|join_value| join_value.validate().is_ok()),
+            FieldType::Bool => !strict_schema || value.is_boolean(),
+            FieldType::String | FieldType::Keyword | FieldType::Text => {
+                !strict_schema || value.is_string()
+            }
+        }
+    }
+}
+
+impl Index {
+    /// Checks the values of the facet fields, GeoShape fields and Join fields of a document against their field type, e.g. before indexing a batch of documents from an untrusted source.
+    /// index_document doesn't reject invalid values: numbers of the wrong type or out of range, and IPs, dates or decimals which can't be parsed are indexed as 0,
+    /// points outside the valid coordinate range and invalid geo shapes and join values are not indexed.
+    /// With IndexMetaObject.strict_schema, additionally fields which are not in the schema, and values of all other fields of the wrong type
+    /// (e.g. a number for a Text field) are invalid.
+    /// Values are checked after type coercion (SchemaField.coerce).
+    /// Null values are valid: the field is absent. Returns all invalid fields of the document, separated by "; ".
+    pub fn validate_document(&self, document: &Document) -> Result<(), String> {
+        let strict_schema = self.meta.strict_schema;
+        let mut errors = Vec::new();
+        for (field_name, value) in document.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+            let Some(schema_field) = self.schema_map.get(field_name) else {
+                if strict_schema {
+                    errors.push(format!("unknown field {}: not in the schema", field_name));
+                }
+                continue;
+            };
+            if value.is_null()
+                || !(strict_schema
+                    || schema_field.facet
+                    || matches!(
+                        schema_field.field_type,
+                        FieldType::GeoShape | FieldType::Join
+                    ))
+            {
+                continue;
+            }
+
+            if !schema_field.is_valid_value(&schema_field.coerce_value(value), strict_schema) {
+                errors.push(format!(
+                    "invalid value of {:?} field {}: {}",
+                    schema_field.field_type, field_name, value
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+/// Indexes a batch of documents, with a result per document, in the order of the batch, e.g. for ingestion pipelines to retry precisely the failed documents.
+/// Each document is validated with Index::validate_document: invalid documents are not indexed, valid documents are indexed in parallel as with index_document.
+/// The document ids of the indexed documents are not necessarily ascending in the order of the batch.
+#[allow(async_fn_in_trait)]
+pub trait IndexDocumentsWithResults {
+    async fn index_documents_with_results(
+        &self,
+        document_vec: Vec<Document>,
+    ) -> Vec<DocumentResult>;
+}
+
+impl IndexDocumentsWithResults for IndexArc {
+    async fn index_documents_with_results(
+        &self,
+        document_vec: Vec<Document>,
+    ) -> Vec<DocumentResult> {
+        let index_ref = self.read().await;
+        if let Err(e) = index_ref.check_writable() {
+            return vec![DocumentResult::error(e.to_string()); document_vec.len()];
+        }
+        let validation_vec: Vec<Result<(), String>> = document_vec
+            .iter()
+            .map(|document| index_ref.validate_document(document))
+            .collect();
+        drop(index_ref);
+
+        let mut results = vec![DocumentResult::default(); document_vec.len()];
+        let mut doc_id_receivers = Vec::new();
+        for (position, (document, validation)) in
+            document_vec.into_iter().zip(validation_vec).enumerate()
+        {
+            match validation {
+                Ok(()) => {
+                    let (doc_id_sender, doc_id_receiver) = oneshot::channel();
+                    index_document_notify(self, document, FileType::None, Some(doc_id_sender))
+                        .await;
+                    doc_id_receivers.push((position, doc_id_receiver));
+                }
+                Err(e) => results[position] = DocumentResult::error(e),
+            }
+        }
+
+        for (position, doc_id_receiver) in doc_id_receivers {
+            results[position] = match doc_id_receiver.await {
+                Ok(doc_id) => DocumentResult {
+                    doc_id: Some(doc_id as u64),
+                    error: None,
+                },
+                Err(_) => DocumentResult::error("document not indexed".to_string()),
+            };
+        }
+        results
+    }
+}
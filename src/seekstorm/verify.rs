@@ -0,0 +1,261 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    commit::commit_index,
+    index::{
+        Index, IndexArc, CHECKSUMS_FILENAME, DELETE_FILENAME, DOCSTORE_FILENAME, FACET_FILENAME,
+        FACET_VALUES_FILENAME, INDEX_FILENAME, ROARING_BLOCK_SIZE,
+    },
+    utils::read_u64,
+};
+
+/// Byte range and checksum of a level (segment of 65.536 documents) in an index file
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct RangeChecksum {
+    pub start: u64,
+    pub end: u64,
+    pub checksum: u64,
+}
+
+/// Checksums of a level in the index files
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct LevelChecksum {
+    pub index: RangeChecksum,
+    pub docstore: RangeChecksum,
+    pub facet: RangeChecksum,
+}
+
+/// Checksums of the committed index files, persisted in checksums.json at every commit
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct IndexChecksums {
+    pub committed_doc_count: usize,
+    pub levels: Vec<LevelChecksum>,
+    pub facet_values: u64,
+}
+
+/// Result of an index verification
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct VerifyResult {
+    /// All index files are intact
+    pub valid: bool,
+    /// Number of levels (segments of 65.536 documents) with valid checksums
+    pub verified_levels: usize,
+    /// Levels whose index, document store or facet data is corrupted
+    pub corrupted_levels: Vec<usize>,
+    /// Description of each detected corruption
+    pub errors: Vec<String>,
+}
+
+/// Index integrity verification: validates the index files against the checksums stored at the last commit.
+/// Per level (segment of 65.536 documents), the index, document store and facet data are verified,
+/// as well as the facet values and the deleted documents.
+#[allow(async_fn_in_trait)]
+pub trait Verify {
+    /// Commits uncommitted documents, then verifies the index files
+    async fn verify(&self) -> Result<VerifyResult, String>;
+}
+
+impl Verify for IndexArc {
+    async fn verify(&self) -> Result<VerifyResult, String> {
+        if self.read().await.uncommitted {
+            commit_index(self).await;
+        }
+        let index_ref = self.read().await;
+        verify_index(Path::new(&index_ref.index_path_string))
+    }
+}
+
+/// Verifies the index files of an index directory against their checksums, without opening the index.
+/// Corrupted files can't crash the verification, which makes it suitable to check an index before opening it.
+/// * `index_path` - index path.
+pub fn verify_index(index_path: &Path) -> Result<VerifyResult, String> {
+    let checksums: IndexChecksums = serde_json::from_slice(
+        &fs::read(index_path.join(CHECKSUMS_FILENAME))
+            .map_err(|_| "no checksums: commit the index to create them".to_string())?,
+    )
+    .map_err(|e| format!("{}: {}", CHECKSUMS_FILENAME, e))?;
+
+    let mut result = VerifyResult::default();
+
+    let read_file =
+        |filename: &str| -> Vec<u8> { fs::read(index_path.join(filename)).unwrap_or_default() };
+    let index_data = read_file(INDEX_FILENAME);
+    let docstore_data = read_file(DOCSTORE_FILENAME);
+    let facet_data = read_file(FACET_FILENAME);
+    let facet_values_data = read_file(FACET_VALUES_FILENAME);
+    let delete_data = read_file(DELETE_FILENAME);
+
+    for (level, level_checksum) in checksums.levels.iter().enumerate() {
+        let mut is_valid = true;
+        for (filename, data, range_checksum) in [
+            (INDEX_FILENAME, &index_data, &level_checksum.index),
+            (DOCSTORE_FILENAME, &docstore_data, &level_checksum.docstore),
+            (FACET_FILENAME, &facet_data, &level_checksum.facet),
+        ] {
+            let Some(level_data) =
+                data.get(range_checksum.start as usize..range_checksum.end as usize)
+            else {
+                result.errors.push(format!(
+                    "{}: level {} truncated: expected {} bytes, found {}",
+                    filename,
+                    level,
+                    range_checksum.end,
+                    data.len()
+                ));
+                is_valid = false;
+                continue;
+            };
+            if checksum(level_data) != range_checksum.checksum {
+                result
+                    .errors
+                    .push(format!("{}: level {} checksum mismatch", filename, level));
+                is_valid = false;
+            }
+        }
+        if is_valid {
+            result.verified_levels += 1;
+        } else {
+            result.corrupted_levels.push(level);
+        }
+    }
+
+    if let Some(level_checksum) = checksums.levels.last() {
+        if (index_data.len() as u64) > level_checksum.index.end {
+            result.errors.push(format!(
+                "{}: {} bytes of uncommitted or unexpected data",
+                INDEX_FILENAME,
+                index_data.len() as u64 - level_checksum.index.end
+            ));
+        }
+    }
+
+    if checksum(&facet_values_data) != checksums.facet_values {
+        result
+            .errors
+            .push(format!("{}: checksum mismatch", FACET_VALUES_FILENAME));
+    }
+
+    if delete_data.len() % 8 != 0 {
+        result
+            .errors
+            .push(format!("{}: truncated", DELETE_FILENAME));
+    }
+    for i in (0..delete_data.len() / 8 * 8).step_by(8) {
+        let doc_id = read_u64(&delete_data, i);
+        if doc_id >= checksums.committed_doc_count as u64 {
+            result.errors.push(format!(
+                "{}: invalid document id {}",
+                DELETE_FILENAME, doc_id
+            ));
+            break;
+        }
+    }
+
+    result.valid = result.errors.is_empty();
+    Ok(result)
+}
+
+/// Checksum of data: the first 64 bit of its SHA-256 hash
+fn checksum(data: &[u8]) -> u64 {
+    u64::from_le_bytes(Sha256::digest(data)[0..8].try_into().unwrap())
+}
+
+/// Checksum of a byte range of a file
+fn file_range_checksum(path: &Path, start: u64, end: u64) -> Result<u64, String> {
+    if start == end {
+        return Ok(checksum(&[]));
+    }
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| e.to_string())?;
+    let mut data = vec![0; (end - start) as usize];
+    file.read_exact(&mut data).map_err(|e| e.to_string())?;
+    Ok(checksum(&data))
+}
+
+impl Index {
+    /// Checksums of the index after a commit: the checksums of the levels changed by the commit are computed, those of the unchanged levels are kept
+    pub(crate) fn level_checksums(
+        &self,
+        committed_doc_count: usize,
+    ) -> Result<IndexChecksums, String> {
+        let index_path = Path::new(&self.index_path_string).to_path_buf();
+        let level_count = self.level_index.len();
+        let index_len = self.index_file.metadata().map_err(|e| e.to_string())?.len();
+        let docstore_len = self
+            .docstore_file
+            .metadata()
+            .map_err(|e| e.to_string())?
+            .len();
+        let facet_level_size = (self.facets_size_sum * ROARING_BLOCK_SIZE) as u64;
+
+        let mut checksums = self.checksums.clone();
+        checksums.levels.truncate(level_count.saturating_sub(1));
+        for level in checksums.levels.len()..level_count {
+            let index_start = self.level_index[level].index_file_start_pos;
+            let index_end = self
+                .level_index
+                .get(level + 1)
+                .map_or(index_len, |level_index| level_index.index_file_start_pos);
+            let docstore_start = self.level_index[level].docstore_file_start_pos;
+            let docstore_end = self
+                .level_index
+                .get(level + 1)
+                .map_or(docstore_len, |level_index| {
+                    level_index.docstore_file_start_pos
+                });
+            let facet_start = level as u64 * facet_level_size;
+            let facet_end = facet_start + facet_level_size;
+
+            checksums.levels.push(LevelChecksum {
+                index: RangeChecksum {
+                    start: index_start,
+                    end: index_end,
+                    checksum: file_range_checksum(
+                        &index_path.join(INDEX_FILENAME),
+                        index_start,
+                        index_end,
+                    )?,
+                },
+                docstore: RangeChecksum {
+                    start: docstore_start,
+                    end: docstore_end,
+                    checksum: file_range_checksum(
+                        &index_path.join(DOCSTORE_FILENAME),
+                        docstore_start,
+                        docstore_end,
+                    )?,
+                },
+                facet: RangeChecksum {
+                    start: facet_start,
+                    end: facet_end,
+                    checksum: checksum(
+                        &self.facets_file_mmap[facet_start as usize..facet_end as usize],
+                    ),
+                },
+            });
+        }
+
+        checksums.facet_values =
+            checksum(&fs::read(index_path.join(FACET_VALUES_FILENAME)).unwrap_or_default());
+        checksums.committed_doc_count = committed_doc_count;
+
+        Ok(checksums)
+    }
+
+    /// Persists the checksums in checksums.json
+    pub(crate) fn save_checksums(&self, checksums: &IndexChecksums) -> Result<(), String> {
+        fs::write(
+            Path::new(&self.index_path_string).join(CHECKSUMS_FILENAME),
+            serde_json::to_vec(checksums).map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| e.to_string())
+    }
+}
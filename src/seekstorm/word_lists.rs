@@ -0,0 +1,258 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use ahash::AHashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::SeekStormError,
+    index::{Index, HASHER_64},
+};
+
+pub(crate) const WORD_LISTS_FILENAME: &str = "word_lists.json";
+
+/// Per-index word list, managed at runtime and stored with the index in word_lists.json
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct WordList {
+    /// Incremented with every change of the list. Changes can be made conditional on the expected version, to detect concurrent modifications.
+    pub version: u64,
+    /// Words of the list: lowercase, sorted and without duplicates
+    pub words: Vec<String>,
+}
+
+/// Type of a per-index word list
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum WordListType {
+    /// Custom stopwords: removed from documents at index time and from queries, leaving a position gap for phrase matching (see Tokenizer)
+    Stopwords,
+    /// Protected words: never stemmed, and never split into multiple terms at non-alphanumeric characters, e.g. "node.js" or "at&t"
+    ProtectedWords,
+}
+
+/// Custom stopwords and protected words of an index, with the term hashes used by the tokenizer
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct WordLists {
+    #[serde(default)]
+    stopwords: WordList,
+    #[serde(default)]
+    protected_words: WordList,
+
+    #[serde(skip)]
+    stopword_hashes: AHashSet<u64>,
+    #[serde(skip)]
+    protected_word_hashes: AHashSet<u64>,
+    /// Protected words containing non-alphanumeric characters, which would otherwise be split by the tokenizer
+    #[serde(skip)]
+    compound_protected_words: Vec<String>,
+}
+
+pub(crate) fn load_word_lists(index_path: &Path) -> WordLists {
+    let mut word_lists: WordLists =
+        if let Ok(word_lists_file) = File::open(index_path.join(WORD_LISTS_FILENAME)) {
+            serde_json::from_reader(BufReader::new(word_lists_file)).unwrap_or_default()
+        } else {
+            WordLists::default()
+        };
+    word_lists.update_hashes();
+    word_lists
+}
+
+impl WordLists {
+    fn update_hashes(&mut self) {
+        self.stopword_hashes = self
+            .stopwords
+            .words
+            .iter()
+            .map(|word| HASHER_64.hash_one(word.as_bytes()))
+            .collect();
+        self.protected_word_hashes = self
+            .protected_words
+            .words
+            .iter()
+            .map(|word| HASHER_64.hash_one(word.as_bytes()))
+            .collect();
+        self.compound_protected_words = self
+            .protected_words
+            .words
+            .iter()
+            .filter(|word| !word.chars().all(regex_syntax::is_word_character))
+            .cloned()
+            .collect();
+    }
+
+    fn list(&self, list_type: WordListType) -> &WordList {
+        match list_type {
+            WordListType::Stopwords => &self.stopwords,
+            WordListType::ProtectedWords => &self.protected_words,
+        }
+    }
+
+    fn list_mut(&mut self, list_type: WordListType) -> &mut WordList {
+        match list_type {
+            WordListType::Stopwords => &mut self.stopwords,
+            WordListType::ProtectedWords => &mut self.protected_words,
+        }
+    }
+
+    /// The term (before stemming) is a custom stopword of the index
+    pub(crate) fn is_stopword(&self, term: &str) -> bool {
+        !self.stopword_hashes.is_empty()
+            && self
+                .stopword_hashes
+                .contains(&HASHER_64.hash_one(term.as_bytes()))
+    }
+
+    /// The term is a protected word of the index, which is never stemmed
+    pub(crate) fn is_protected(&self, term: &str) -> bool {
+        !self.protected_word_hashes.is_empty()
+            && self
+                .protected_word_hashes
+                .contains(&HASHER_64.hash_one(term.as_bytes()))
+    }
+
+    /// Merges consecutive terms which together form a protected word with non-alphanumeric characters, e.g. "node" and "js" to "node.js".
+    /// The terms have to be slices of the normalized text. Operators of query terms (+, -, ") before and after the protected word are kept.
+    pub(crate) fn merge_protected_words<'a>(&self, text: &'a str, terms: &mut Vec<&'a str>) {
+        if self.compound_protected_words.is_empty() || terms.len() < 2 {
+            return;
+        }
+        let is_boundary = |c: Option<char>| !c.is_some_and(regex_syntax::is_word_character);
+
+        for protected_word in self.compound_protected_words.iter() {
+            for (start, _) in text.match_indices(protected_word.as_str()) {
+                let end = start + protected_word.len();
+                if !is_boundary(text[..start].chars().next_back())
+                    || !is_boundary(text[end..].chars().next())
+                {
+                    continue;
+                }
+
+                let offset = |term: &str| term.as_ptr() as usize - text.as_ptr() as usize;
+                let first = terms
+                    .iter()
+                    .position(|term| offset(term) + term.len() > start);
+                let Some(first) = first else {
+                    continue;
+                };
+                let last = terms[first..]
+                    .iter()
+                    .take_while(|term| offset(term) < end)
+                    .count();
+                if last < 2 {
+                    continue;
+                }
+
+                let merged_start = offset(terms[first]).min(start);
+                let last_term = terms[first + last - 1];
+                let merged_end = (offset(last_term) + last_term.len()).max(end);
+                terms.splice(first..first + last, [&text[merged_start..merged_end]]);
+            }
+        }
+    }
+}
+
+impl Index {
+    /// Get a per-index word list: the custom stopwords or the protected words, with its version
+    pub fn get_word_list(&self, list_type: WordListType) -> WordList {
+        self.word_lists.list(list_type).clone()
+    }
+
+    /// Set/replace/overwrite a per-index word list.
+    /// Takes effect immediately for subsequently indexed documents and for queries, previously indexed documents are not changed.
+    /// * `expected_version`: if specified, the list is only changed if its current version matches, otherwise a Conflict error is returned
+    pub fn set_word_list(
+        &mut self,
+        list_type: WordListType,
+        words: &[String],
+        expected_version: Option<u64>,
+    ) -> Result<WordList, SeekStormError> {
+        self.update_word_list(list_type, expected_version, |list| list.clear(), words)
+    }
+
+    /// Add words to a per-index word list (see set_word_list)
+    pub fn add_words(
+        &mut self,
+        list_type: WordListType,
+        words: &[String],
+        expected_version: Option<u64>,
+    ) -> Result<WordList, SeekStormError> {
+        self.update_word_list(list_type, expected_version, |_list| {}, words)
+    }
+
+    /// Delete words from a per-index word list (see set_word_list)
+    pub fn delete_words(
+        &mut self,
+        list_type: WordListType,
+        words: &[String],
+        expected_version: Option<u64>,
+    ) -> Result<WordList, SeekStormError> {
+        let words = normalize_words(words)?;
+        self.update_word_list(
+            list_type,
+            expected_version,
+            |list| list.retain(|word| !words.contains(word)),
+            &[],
+        )
+    }
+
+    fn update_word_list(
+        &mut self,
+        list_type: WordListType,
+        expected_version: Option<u64>,
+        update: impl FnOnce(&mut Vec<String>),
+        added_words: &[String],
+    ) -> Result<WordList, SeekStormError> {
+        let added_words = normalize_words(added_words)?;
+        let version = self.word_lists.list(list_type).version;
+        if let Some(expected_version) = expected_version {
+            if expected_version != version {
+                return Err(SeekStormError::Conflict(format!(
+                    "word list version is {}, expected {}",
+                    version, expected_version
+                )));
+            }
+        }
+
+        let mut word_lists = self.word_lists.clone();
+        let list = word_lists.list_mut(list_type);
+        update(&mut list.words);
+        list.words.extend(added_words);
+        list.words.sort_unstable();
+        list.words.dedup();
+        list.version = version + 1;
+        let list = list.clone();
+
+        serde_json::to_writer(&File::create(self.word_lists_path())?, &word_lists)
+            .map_err(|e| SeekStormError::Io(e.to_string()))?;
+
+        word_lists.update_hashes();
+        self.word_lists = word_lists;
+        Ok(list)
+    }
+
+    fn word_lists_path(&self) -> PathBuf {
+        Path::new(&self.index_path_string).join(WORD_LISTS_FILENAME)
+    }
+}
+
+/// Words are trimmed and lowercase, and must not contain whitespace, as only single terms are matched
+fn normalize_words(words: &[String]) -> Result<Vec<String>, SeekStormError> {
+    words
+        .iter()
+        .map(|word| word.trim().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            if word.contains(char::is_whitespace) {
+                Err(SeekStormError::InvalidRequest(format!(
+                    "word contains whitespace: {}",
+                    word
+                )))
+            } else {
+                Ok(word)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,386 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::Path,
+};
+
+use seekstorm::{
+    compact::Compact,
+    export::import_index,
+    freeze::Freeze,
+    index::{
+        create_index, open_index, AccessType, Document, Durability, IndexArc, IndexMetaObject,
+        SchemaField, SimilarityType, Synonym, TokenizerType,
+    },
+    ingest::{IngestCsv, IngestJson, IngestPdf},
+    repair::repair_index,
+    search::{Search, SearchRequest},
+    verify::verify_index,
+};
+use serde_json::json;
+use walkdir::WalkDir;
+
+use crate::{get_bool, get_f32, get_fields, get_language, get_param, get_stemmer, get_usize};
+
+/// Runs a command directly against an index directory via the SeekStorm library
+pub(crate) async fn run(command: &str, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_path = Path::new(get_param(params, "index_path")?);
+
+    match command {
+        "create" => create(index_path, params),
+        "ingest" => ingest(index_path, params).await,
+        "search" => search(index_path, params).await,
+        "stats" => stats(index_path).await,
+        "terms" => terms(index_path, params).await,
+        "termvectors" => term_vectors(index_path, params).await,
+        "snapshot" => snapshot(index_path, params),
+        "restore" => restore(index_path, params),
+        "compact" => compact(index_path).await,
+        "freeze" => freeze(index_path).await,
+        "unfreeze" => unfreeze(index_path).await,
+        "verify" => verify(index_path),
+        "repair" => repair(index_path).await,
+        "export" => export(index_path, params).await,
+        "import" => import(index_path, params).await,
+        _ => Err(format!("unknown command: {}", command)),
+    }
+}
+
+fn create(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    if index_path.join("index.json").exists() {
+        return Err(format!("index already exists: {}", index_path.display()));
+    }
+
+    let schema_string =
+        fs::read_to_string(get_param(params, "schema")?).map_err(|e| format!("schema: {}", e))?;
+    let schema: Vec<SchemaField> =
+        serde_json::from_str(&schema_string).map_err(|e| format!("schema: {}", e))?;
+
+    let synonyms: Vec<Synonym> = if let Some(synonyms_path) = params.get("synonyms") {
+        let synonyms_string =
+            fs::read_to_string(synonyms_path).map_err(|e| format!("synonyms: {}", e))?;
+        serde_json::from_str(&synonyms_string).map_err(|e| format!("synonyms: {}", e))?
+    } else {
+        Vec::new()
+    };
+
+    let similarity: SimilarityType = match params.get("similarity") {
+        Some(similarity) => serde_json::from_value(json!(similarity))
+            .map_err(|_| format!("similarity invalid: {}", similarity))?,
+        None => SimilarityType::Bm25f,
+    };
+
+    let tokenizer: TokenizerType = match params.get("tokenizer") {
+        Some(tokenizer) => serde_json::from_value(json!(tokenizer))
+            .map_err(|_| format!("tokenizer invalid: {}", tokenizer))?,
+        None => TokenizerType::UnicodeAlphanumeric,
+    };
+    if tokenizer == TokenizerType::Custom {
+        return Err(
+            "tokenizer Custom is only supported by the library (Index::set_tokenizer)".to_string(),
+        );
+    }
+
+    let durability: Durability = match params.get("durability") {
+        Some(durability) => serde_json::from_value(json!(durability))
+            .map_err(|_| format!("durability invalid: {}", durability))?,
+        None => Durability::Os,
+    };
+
+    fs::create_dir_all(index_path).map_err(|e| e.to_string())?;
+
+    let meta = IndexMetaObject {
+        id: 0,
+        name: params
+            .get("index_name")
+            .cloned()
+            .unwrap_or("index".to_string()),
+        similarity,
+        tokenizer,
+        access_type: AccessType::Mmap,
+        stemmer: get_stemmer(params)?,
+        k1: get_f32(params, "k1", 1.2)?,
+        b: get_f32(params, "b", 0.75)?,
+        compact_deleted_ratio: get_f32(params, "compact_deleted_ratio", 0.0)?,
+        durability,
+        sync_interval_ms: get_usize(params, "sync_interval_ms", 1000)? as u64,
+        index_unstemmed: get_bool(params, "index_unstemmed", false)?,
+        strict_schema: get_bool(params, "strict_schema", false)?,
+        metadata: serde_json::Value::Null,
+        created: 0,
+    };
+
+    let mut index = create_index(index_path, meta, &schema, true, &synonyms, 11, true)?;
+    index.close_index();
+
+    println!("index created: {}", index_path.display());
+    Ok(())
+}
+
+async fn ingest(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let data_path = Path::new(get_param(params, "data_path")?);
+    if !data_path.exists() {
+        return Err(format!("data_path not found: {}", data_path.display()));
+    }
+
+    let mut index_arc = open_index(index_path, true).await?;
+
+    if data_path.is_dir() {
+        index_arc.ingest_pdf(data_path).await;
+    } else {
+        match data_path
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or("")
+            .to_lowercase()
+            .as_str()
+        {
+            "pdf" => index_arc.ingest_pdf(data_path).await,
+            "json" | "ndjson" | "jsonl" => index_arc.ingest_json(data_path).await,
+            "csv" => index_arc.ingest_csv(data_path).await,
+            extension => {
+                return Err(format!("file extension not supported: {}", extension));
+            }
+        }
+    }
+
+    index_arc.write().await.close_index();
+    Ok(())
+}
+
+async fn search(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let query = get_param(params, "query")?;
+    let offset = match params.get("offset") {
+        Some(offset) => offset.parse().map_err(|_| "offset invalid".to_string())?,
+        None => 0,
+    };
+    let length = match params.get("length") {
+        Some(length) => length.parse().map_err(|_| "length invalid".to_string())?,
+        None => 10,
+    };
+    let realtime = match params.get("realtime") {
+        Some(realtime) => realtime
+            .parse()
+            .map_err(|_| "realtime invalid".to_string())?,
+        None => true,
+    };
+
+    let index_arc = open_index(index_path, true).await?;
+
+    let mut search_request = SearchRequest::new(query)
+        .offset(offset)
+        .length(length)
+        .include_uncommited(realtime);
+    search_request.query_language = get_language(params, "query_language")?;
+    let result_object = index_arc.search(search_request).await;
+
+    let index_ref = index_arc.read().await;
+    let mut results: Vec<Document> = Vec::new();
+    for result in result_object.results.iter() {
+        if let Ok(mut doc) =
+            index_ref.get_document(result.doc_id, realtime, &None, &HashSet::new(), &[])
+        {
+            doc.insert("_id".to_string(), result.doc_id.into());
+            doc.insert("_score".to_string(), result.score.into());
+            results.push(doc);
+        }
+    }
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "query": query,
+            "offset": offset,
+            "length": length,
+            "count": result_object.results.len(),
+            "count_total": result_object.result_count_total,
+            "results": results,
+            "facets": result_object.facets,
+        }))
+        .unwrap()
+    );
+
+    Ok(())
+}
+
+async fn stats(index_path: &Path) -> Result<(), String> {
+    let index_arc: IndexArc = open_index(index_path, true).await?;
+    let index_ref = index_arc.read().await;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "name": index_ref.meta.name,
+            "schema": index_ref.schema_map,
+            "indexed_doc_count": index_ref.indexed_doc_count,
+            "committed_doc_count": index_ref.committed_doc_count,
+            "facets_minmax": index_ref.get_index_facets_minmax(),
+        }))
+        .unwrap()
+    );
+
+    Ok(())
+}
+
+async fn terms(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_arc: IndexArc = open_index(index_path, true).await?;
+    let index_ref = index_arc.read().await;
+
+    let field_terms = index_ref.get_terms(
+        get_param(params, "field")?,
+        params.get("prefix").map_or("", |prefix| prefix.as_str()),
+        get_usize(params, "offset", 0)?,
+        get_usize(params, "length", 100)?,
+        true,
+    )?;
+
+    println!("{}", serde_json::to_string_pretty(&field_terms).unwrap());
+    Ok(())
+}
+
+async fn term_vectors(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_arc: IndexArc = open_index(index_path, true).await?;
+    let index_ref = index_arc.read().await;
+
+    let doc_id = get_param(params, "doc_id")?
+        .parse::<usize>()
+        .map_err(|_| "doc_id invalid".to_string())?;
+    let fields = get_fields(params);
+
+    let term_vectors = index_ref.get_term_vectors(doc_id, &fields, true)?;
+
+    println!("{}", serde_json::to_string_pretty(&term_vectors).unwrap());
+    Ok(())
+}
+
+fn snapshot(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let snapshot_path = Path::new(get_param(params, "snapshot_path")?);
+    if !index_path.join("index.json").exists() {
+        return Err(format!("index not found: {}", index_path.display()));
+    }
+    if snapshot_path.exists() {
+        return Err(format!(
+            "snapshot_path already exists: {}",
+            snapshot_path.display()
+        ));
+    }
+
+    copy_dir(index_path, snapshot_path)?;
+    println!("snapshot created: {}", snapshot_path.display());
+    Ok(())
+}
+
+fn restore(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let snapshot_path = Path::new(get_param(params, "snapshot_path")?);
+    if !snapshot_path.join("index.json").exists() {
+        return Err(format!("snapshot not found: {}", snapshot_path.display()));
+    }
+    if index_path.exists() {
+        if params.get("force").map(|force| force.as_str()) != Some("true") {
+            return Err(format!(
+                "index_path already exists: {} (use force=true to overwrite)",
+                index_path.display()
+            ));
+        }
+        fs::remove_dir_all(index_path).map_err(|e| e.to_string())?;
+    }
+
+    copy_dir(snapshot_path, index_path)?;
+    println!("index restored: {}", index_path.display());
+    Ok(())
+}
+
+async fn compact(index_path: &Path) -> Result<(), String> {
+    let index_arc: IndexArc = open_index(index_path, true).await?;
+    let compact_result = index_arc.compact().await?;
+
+    println!(
+        "index compacted: deleted documents removed {} docs {}",
+        compact_result.deleted_doc_count, compact_result.indexed_doc_count
+    );
+    Ok(())
+}
+
+async fn freeze(index_path: &Path) -> Result<(), String> {
+    let index_arc: IndexArc = open_index(index_path, true).await?;
+    index_arc.freeze().await?;
+
+    println!("index frozen: {}", index_path.display());
+    Ok(())
+}
+
+async fn unfreeze(index_path: &Path) -> Result<(), String> {
+    let index_arc: IndexArc = open_index(index_path, true).await?;
+    index_arc.unfreeze().await?;
+
+    println!("index unfrozen: {}", index_path.display());
+    Ok(())
+}
+
+fn verify(index_path: &Path) -> Result<(), String> {
+    let verify_result = verify_index(index_path)?;
+
+    println!("{}", serde_json::to_string_pretty(&verify_result).unwrap());
+    if verify_result.valid {
+        Ok(())
+    } else {
+        Err(format!("index corrupted: {}", index_path.display()))
+    }
+}
+
+async fn repair(index_path: &Path) -> Result<(), String> {
+    if !index_path.join("index.json").exists() {
+        return Err(format!("index not found: {}", index_path.display()));
+    }
+    let repair_result = repair_index(index_path, false).await?;
+
+    println!("{}", serde_json::to_string_pretty(&repair_result).unwrap());
+    Ok(())
+}
+
+async fn export(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let export_path = Path::new(get_param(params, "export_path")?);
+    let raw: bool = match params.get("raw") {
+        Some(raw) => raw.parse().map_err(|_| "raw invalid".to_string())?,
+        None => false,
+    };
+
+    let index_arc: IndexArc = open_index(index_path, true).await?;
+    let manifest = index_arc.read().await.export_index(export_path, raw)?;
+
+    println!(
+        "index exported: {} docs {}",
+        export_path.display(),
+        manifest.document_count
+    );
+    Ok(())
+}
+
+async fn import(index_path: &Path, params: &HashMap<String, String>) -> Result<(), String> {
+    let export_path = Path::new(get_param(params, "export_path")?);
+
+    let index_arc = import_index(export_path, index_path).await?;
+
+    println!(
+        "index imported: {} docs {}",
+        index_path.display(),
+        index_arc.read().await.indexed_doc_count
+    );
+    Ok(())
+}
+
+/// Recursively copies all files of the source directory into the (new) target directory
+fn copy_dir(source_path: &Path, target_path: &Path) -> Result<(), String> {
+    for entry in WalkDir::new(source_path) {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let relative_path = entry.path().strip_prefix(source_path).unwrap();
+        let target_entry_path = target_path.join(relative_path);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target_entry_path).map_err(|e| e.to_string())?;
+        } else {
+            fs::copy(entry.path(), &target_entry_path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,225 @@
+#![crate_name = "seekstorm_cli"]
+#![doc(html_logo_url = "http://seekstorm.com/assets/logo.svg")]
+
+//! # `seekstorm-cli`
+//! **SeekStorm CLI** is a command line administration tool for scripting and offline maintenance.
+//! * works directly against index directories via the SeekStorm library (parameter `index_path`)
+//! * or against a running SeekStorm server via the REST API (parameters `server`, `apikey`, `index_id`)
+//! ### Commands
+//! ```text
+//! create   index_path=... schema=schema.json [index_name=...] [similarity=Bm25f] [tokenizer=UnicodeAlphanumeric] [synonyms=synonyms.json]
+//!          [stemmer=English|...|Detect] [default_language=English] [language_field=...] [durability=Os|Commit|Periodic] [sync_interval_ms=1000]
+//!          [index_unstemmed=false] [strict_schema=false]
+//! ingest   index_path=... data_path=...      (PDF, JSON, Newline-delimited JSON, Concatenated JSON, CSV file, or directory of PDF files)
+//! search   index_path=... query=... [offset=0] [length=10] [realtime=true] [query_language=English]
+//! stats    index_path=...
+//! terms    index_path=... field=... [prefix=...] [offset=0] [length=100]
+//! termvectors index_path=... doc_id=... [fields=title,body]
+//! compact  index_path=...
+//! freeze   index_path=...
+//! unfreeze index_path=...
+//! verify   index_path=...      (server: [quarantine=true])
+//! repair   index_path=...
+//! snapshot index_path=... snapshot_path=...
+//! restore  index_path=... snapshot_path=... [force=true]
+//! export   index_path=... export_path=... [raw=false]
+//! import   index_path=... export_path=...
+//! ```
+//! Replace `index_path=...` with `server=http://127.0.0.1:80 apikey=... index_id=0` to run create, ingest, search, stats, terms, termvectors, compact, freeze, unfreeze and verify against a running server.
+//! snapshot, restore, repair, export and import access index directories and are only supported locally, with the server stopped.
+//! ### Example
+//! ```text
+//! ./seekstorm-cli.exe ingest index_path="c:/seekstorm_index/0/0" data_path="c:/data/wiki-articles.json"
+//! ./seekstorm-cli.exe search server="http://127.0.0.1" apikey="AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=" index_id=0 query="hello world"
+//! ```
+
+use colored::Colorize;
+use seekstorm::index::{Language, StemmerType};
+use std::collections::HashMap;
+use std::env;
+use std::process;
+
+#[doc(hidden)]
+mod local;
+#[doc(hidden)]
+mod remote;
+
+#[doc(hidden)]
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[doc(hidden)]
+fn help() {
+    println!("{} v{}", "SeekStorm CLI".bright_green(), VERSION);
+    println!();
+    println!(
+        "{}",
+        "Usage: seekstorm-cli [command] [parameter=value] ...".yellow()
+    );
+    println!();
+    println!("{:10} Create an index from a schema JSON file: index_path schema [index_name] [similarity] [tokenizer] [synonyms] [stemmer] [default_language] [language_field] [durability] [sync_interval_ms] [index_unstemmed] [strict_schema]","create".green());
+    println!("{:10} Index a PDF, JSON, Newline-delimited JSON, Concatenated JSON or CSV file, or a directory of PDF files: index_path data_path","ingest".green());
+    println!(
+        "{:10} Search the index: index_path query [offset] [length] [realtime] [query_language]",
+        "search".green()
+    );
+    println!("{:10} Show index statistics: index_path", "stats".green());
+    println!("{:10} List the terms of an indexed and stored field with document frequencies: index_path field [prefix] [offset] [length]","terms".green());
+    println!("{:10} Show the analyzed terms, frequencies and positions of a document: index_path doc_id [fields]","termvectors".green());
+    println!(
+        "{:10} Rewrite the index without deleted documents: index_path",
+        "compact".green()
+    );
+    println!("{:10} Freeze the index to read-only: commit, compact, reject document mutations: index_path","freeze".green());
+    println!(
+        "{:10} Unfreeze a frozen index, to allow document mutations again: index_path",
+        "unfreeze".green()
+    );
+    println!("{:10} Verify the index files against their checksums and report corruption: index_path (server: [quarantine])","verify".green());
+    println!("{:10} Rebuild a corrupted index from the intact documents of its document store: index_path","repair".green());
+    println!(
+        "{:10} Copy the index directory to a snapshot directory: index_path snapshot_path",
+        "snapshot".green()
+    );
+    println!("{:10} Restore the index directory from a snapshot directory: index_path snapshot_path [force]","restore".green());
+    println!("{:10} Export the index to a portable directory (manifest, schema, synonyms, NDJSON documents): index_path export_path [raw]","export".green());
+    println!(
+        "{:10} Import an exported index into a new index directory: index_path export_path",
+        "import".green()
+    );
+    println!("{:10} Show this help.", "help".green());
+    println!();
+    println!("Replace index_path with server apikey index_id to run create, ingest, search, stats, terms, termvectors, compact, freeze, unfreeze and verify against a running SeekStorm server.");
+}
+
+#[doc(hidden)]
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().collect();
+    let command = if args.len() > 1 {
+        args[1].to_lowercase()
+    } else {
+        String::new()
+    };
+
+    let mut params = HashMap::new();
+    for s in args.iter().skip(2) {
+        let split: Vec<&str> = s.splitn(2, '=').collect();
+        if split.len() == 2 {
+            params.insert(split[0].trim().to_owned(), split[1].trim().to_owned());
+        }
+    }
+
+    let result = match command.as_str() {
+        "" | "help" => {
+            help();
+            Ok(())
+        }
+        _ => {
+            if params.contains_key("server") {
+                remote::run(&command, &params).await
+            } else {
+                local::run(&command, &params).await
+            }
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{} {}", "Error:".bright_red(), e);
+        process::exit(1);
+    }
+}
+
+/// Parses the optional stemmer parameter: None, a language (e.g. English), or Detect with optional default_language and language_field
+#[doc(hidden)]
+pub(crate) fn get_stemmer(params: &HashMap<String, String>) -> Result<StemmerType, String> {
+    match params.get("stemmer").map(|stemmer| stemmer.as_str()) {
+        None | Some("None") => Ok(StemmerType::None),
+        Some("Detect") => Ok(StemmerType::Detect {
+            default_language: get_language(params, "default_language")?,
+            language_field: params.get("language_field").cloned().unwrap_or_default(),
+        }),
+        Some(_) => Ok(StemmerType::Language(
+            get_language(params, "stemmer")?.unwrap(),
+        )),
+    }
+}
+
+#[doc(hidden)]
+pub(crate) fn get_language(
+    params: &HashMap<String, String>,
+    name: &str,
+) -> Result<Option<Language>, String> {
+    match params.get(name) {
+        Some(language) => serde_json::from_value(serde_json::json!(language))
+            .map(Some)
+            .map_err(|_| format!("{} invalid: {}", name, language)),
+        None => Ok(None),
+    }
+}
+
+/// Parses an optional f32 parameter, e.g. the BM25 parameters k1 and b
+#[doc(hidden)]
+pub(crate) fn get_f32(
+    params: &HashMap<String, String>,
+    name: &str,
+    default: f32,
+) -> Result<f32, String> {
+    match params.get(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("{} invalid: {}", name, value)),
+        None => Ok(default),
+    }
+}
+
+/// Comma separated list of field names in parameter fields, empty if not specified
+#[doc(hidden)]
+pub(crate) fn get_fields(params: &HashMap<String, String>) -> Vec<String> {
+    params
+        .get("fields")
+        .map(|fields| {
+            fields
+                .split(',')
+                .map(|field| field.trim().to_string())
+                .filter(|field| !field.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[doc(hidden)]
+pub(crate) fn get_bool(
+    params: &HashMap<String, String>,
+    name: &str,
+    default: bool,
+) -> Result<bool, String> {
+    match params.get(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("{} invalid: {}", name, value)),
+        None => Ok(default),
+    }
+}
+
+pub(crate) fn get_usize(
+    params: &HashMap<String, String>,
+    name: &str,
+    default: usize,
+) -> Result<usize, String> {
+    match params.get(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("{} invalid: {}", name, value)),
+        None => Ok(default),
+    }
+}
+
+#[doc(hidden)]
+pub(crate) fn get_param<'a>(
+    params: &'a HashMap<String, String>,
+    name: &str,
+) -> Result<&'a String, String> {
+    params
+        .get(name)
+        .ok_or(format!("parameter {} missing", name))
+}
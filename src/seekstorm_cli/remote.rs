@@ -0,0 +1,456 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use hyper::{body, Body, Client, Method, Request};
+use seekstorm::{
+    index::{Document, SchemaField},
+    ingest::CsvDocumentReader,
+};
+use serde::Deserialize;
+use serde_json::{json, Deserializer};
+use walkdir::WalkDir;
+
+use crate::{get_bool, get_f32, get_fields, get_language, get_param, get_stemmer, get_usize};
+
+/// Number of documents sent per index documents request
+const BATCH_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+struct IndexSchemaResponse {
+    schema: HashMap<String, SchemaField>,
+}
+
+/// Connection parameters of a running SeekStorm server
+struct Server {
+    url: String,
+    apikey: String,
+    client: Client<hyper::client::HttpConnector>,
+}
+
+impl Server {
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Body,
+    ) -> Result<String, String> {
+        let mut request_builder = Request::builder()
+            .method(method)
+            .uri(format!("{}{}", self.url, path))
+            .header("apikey", &self.apikey);
+        if !headers.iter().any(|(name, _)| *name == "content-type") {
+            request_builder = request_builder.header("content-type", "application/json");
+        }
+        for (name, value) in headers {
+            request_builder = request_builder.header(*name, *value);
+        }
+        let request = request_builder.body(body).map_err(|e| e.to_string())?;
+
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let response_bytes = body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| e.to_string())?;
+        let response_string = String::from_utf8_lossy(&response_bytes).to_string();
+
+        if status.is_success() {
+            Ok(response_string)
+        } else {
+            Err(format!("{} {}", status, response_string))
+        }
+    }
+
+    async fn index_documents(&self, index_id: &str, documents: &[Document]) -> Result<(), String> {
+        self.request(
+            Method::POST,
+            &format!("/api/v1/index/{}/doc", index_id),
+            &[],
+            serde_json::to_string(documents).unwrap().into(),
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+/// Runs a command against a running SeekStorm server via the REST API
+pub(crate) async fn run(command: &str, params: &HashMap<String, String>) -> Result<(), String> {
+    let server = Server {
+        url: get_param(params, "server")?
+            .trim_end_matches('/')
+            .to_string(),
+        apikey: get_param(params, "apikey")?.to_string(),
+        client: Client::new(),
+    };
+
+    match command {
+        "create" => create(&server, params).await,
+        "ingest" => ingest(&server, params).await,
+        "search" => search(&server, params).await,
+        "stats" => stats(&server, params).await,
+        "terms" => terms(&server, params).await,
+        "termvectors" => term_vectors(&server, params).await,
+        "compact" => compact(&server, params).await,
+        "freeze" | "unfreeze" => freeze(&server, command, params).await,
+        "verify" => verify(&server, params).await,
+        "snapshot" | "restore" | "repair" | "export" | "import" => Err(format!(
+            "{} is only supported directly against index directories (index_path), with the server stopped",
+            command
+        )),
+        _ => Err(format!("unknown command: {}", command)),
+    }
+}
+
+async fn create(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let schema_string =
+        fs::read_to_string(get_param(params, "schema")?).map_err(|e| format!("schema: {}", e))?;
+    let schema: serde_json::Value =
+        serde_json::from_str(&schema_string).map_err(|e| format!("schema: {}", e))?;
+
+    let synonyms: serde_json::Value = if let Some(synonyms_path) = params.get("synonyms") {
+        let synonyms_string =
+            fs::read_to_string(synonyms_path).map_err(|e| format!("synonyms: {}", e))?;
+        serde_json::from_str(&synonyms_string).map_err(|e| format!("synonyms: {}", e))?
+    } else {
+        json!([])
+    };
+
+    let create_index_request = json!({
+        "schema": schema,
+        "index_name": params.get("index_name").cloned().unwrap_or("index".to_string()),
+        "similarity": params.get("similarity").cloned().unwrap_or("Bm25f".to_string()),
+        "tokenizer": params.get("tokenizer").cloned().unwrap_or("UnicodeAlphanumeric".to_string()),
+        "synonyms": synonyms,
+        "stemmer": get_stemmer(params)?,
+        "k1": get_f32(params, "k1", 1.2)?,
+        "b": get_f32(params, "b", 0.75)?,
+        "compact_deleted_ratio": get_f32(params, "compact_deleted_ratio", 0.0)?,
+        "durability": params.get("durability").cloned().unwrap_or("Os".to_string()),
+        "sync_interval_ms": get_usize(params, "sync_interval_ms", 1000)?,
+        "index_unstemmed": get_bool(params, "index_unstemmed", false)?,
+        "strict_schema": get_bool(params, "strict_schema", false)?,
+    });
+
+    let index_id = server
+        .request(
+            Method::POST,
+            "/api/v1/index",
+            &[],
+            create_index_request.to_string().into(),
+        )
+        .await?;
+
+    println!("index created: index_id {}", index_id);
+    Ok(())
+}
+
+fn read_skipping_ws(mut reader: impl Read) -> io::Result<u8> {
+    loop {
+        let mut byte = 0u8;
+        reader.read_exact(std::slice::from_mut(&mut byte))?;
+        if !byte.is_ascii_whitespace() {
+            return Ok(byte);
+        }
+    }
+}
+
+async fn ingest(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    let data_path = Path::new(get_param(params, "data_path")?);
+    if !data_path.exists() {
+        return Err(format!("data_path not found: {}", data_path.display()));
+    }
+
+    let extension = data_path
+        .extension()
+        .and_then(OsStr::to_str)
+        .unwrap_or("")
+        .to_lowercase();
+
+    let mut docid = 0usize;
+    if data_path.is_dir() || extension == "pdf" {
+        for entry in WalkDir::new(data_path) {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let file_path = entry.path();
+            if !entry.file_type().is_file()
+                || file_path
+                    .extension()
+                    .and_then(OsStr::to_str)
+                    .map(|e| e.to_lowercase())
+                    != Some("pdf".to_string())
+            {
+                continue;
+            }
+
+            let file_bytes = fs::read(file_path).map_err(|e| e.to_string())?;
+            let file_path_string = file_path.display().to_string();
+            server
+                .request(
+                    Method::POST,
+                    &format!("/api/v1/index/{}/file", index_id),
+                    &[
+                        ("content-type", "application/pdf"),
+                        ("file", &file_path_string),
+                    ],
+                    file_bytes.into(),
+                )
+                .await?;
+            docid += 1;
+        }
+    } else {
+        let mut documents: Vec<Document> = Vec::with_capacity(BATCH_SIZE);
+        let file = File::open(data_path).map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(file);
+
+        match extension.as_str() {
+            "csv" => {
+                let index_schema: IndexSchemaResponse = serde_json::from_str(
+                    &server
+                        .request(
+                            Method::GET,
+                            &format!("/api/v1/index/{}", index_id),
+                            &[],
+                            Body::empty(),
+                        )
+                        .await?,
+                )
+                .map_err(|e| e.to_string())?;
+
+                for document in CsvDocumentReader::new(reader, &index_schema.schema)? {
+                    documents.push(document?);
+                    if documents.len() == BATCH_SIZE {
+                        server.index_documents(index_id, &documents).await?;
+                        docid += documents.len();
+                        documents.clear();
+                    }
+                }
+            }
+            "json" | "ndjson" | "jsonl" => {
+                let is_vector = read_skipping_ws(&mut reader).map_err(|e| e.to_string())? == b'[';
+
+                if !is_vector {
+                    reader.seek_relative(-1).map_err(|e| e.to_string())?;
+                    for document in Deserializer::from_reader(reader).into_iter::<Document>() {
+                        documents.push(document.map_err(|e| e.to_string())?);
+                        if documents.len() == BATCH_SIZE {
+                            server.index_documents(index_id, &documents).await?;
+                            docid += documents.len();
+                            documents.clear();
+                        }
+                    }
+                } else {
+                    while let Some(document) = Deserializer::from_reader(reader.by_ref())
+                        .into_iter::<Document>()
+                        .next()
+                    {
+                        documents.push(document.map_err(|e| e.to_string())?);
+                        if documents.len() == BATCH_SIZE {
+                            server.index_documents(index_id, &documents).await?;
+                            docid += documents.len();
+                            documents.clear();
+                        }
+
+                        if read_skipping_ws(reader.by_ref()).map_err(|e| e.to_string())? != b',' {
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => return Err(format!("file extension not supported: {}", extension)),
+        }
+
+        if !documents.is_empty() {
+            server.index_documents(index_id, &documents).await?;
+            docid += documents.len();
+        }
+    }
+
+    server
+        .request(
+            Method::PATCH,
+            &format!("/api/v1/index/{}", index_id),
+            &[],
+            Body::empty(),
+        )
+        .await?;
+
+    println!("Indexing finished: docs {}", docid);
+    Ok(())
+}
+
+async fn search(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    let query = get_param(params, "query")?;
+    let offset: usize = match params.get("offset") {
+        Some(offset) => offset.parse().map_err(|_| "offset invalid".to_string())?,
+        None => 0,
+    };
+    let length: usize = match params.get("length") {
+        Some(length) => length.parse().map_err(|_| "length invalid".to_string())?,
+        None => 10,
+    };
+    let realtime: bool = match params.get("realtime") {
+        Some(realtime) => realtime
+            .parse()
+            .map_err(|_| "realtime invalid".to_string())?,
+        None => true,
+    };
+
+    let search_request = json!({
+        "query": query,
+        "offset": offset,
+        "length": length,
+        "realtime": realtime,
+        "query_language": get_language(params, "query_language")?,
+    });
+
+    let search_result = server
+        .request(
+            Method::POST,
+            &format!("/api/v1/index/{}/query", index_id),
+            &[],
+            search_request.to_string().into(),
+        )
+        .await?;
+
+    print_json(&search_result);
+    Ok(())
+}
+
+async fn stats(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    let index_stats = server
+        .request(
+            Method::GET,
+            &format!("/api/v1/index/{}", index_id),
+            &[],
+            Body::empty(),
+        )
+        .await?;
+
+    print_json(&index_stats);
+    Ok(())
+}
+
+async fn terms(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    let field = get_param(params, "field")?;
+    let query: String = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair(
+            "prefix",
+            params.get("prefix").map_or("", |prefix| prefix.as_str()),
+        )
+        .append_pair("offset", &get_usize(params, "offset", 0)?.to_string())
+        .append_pair("length", &get_usize(params, "length", 100)?.to_string())
+        .finish();
+
+    let field_terms = server
+        .request(
+            Method::GET,
+            &format!("/api/v1/index/{}/terms/{}?{}", index_id, field, query),
+            &[],
+            Body::empty(),
+        )
+        .await?;
+
+    print_json(&field_terms);
+    Ok(())
+}
+
+async fn term_vectors(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    let doc_id = get_param(params, "doc_id")?;
+    let query: String = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("fields", &get_fields(params).join(","))
+        .finish();
+
+    let term_vectors = server
+        .request(
+            Method::GET,
+            &format!(
+                "/api/v1/index/{}/termvectors/{}?{}",
+                index_id, doc_id, query
+            ),
+            &[],
+            Body::empty(),
+        )
+        .await?;
+
+    print_json(&term_vectors);
+    Ok(())
+}
+
+async fn compact(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    let compact_result = server
+        .request(
+            Method::POST,
+            &format!("/api/v1/index/{}/compact", index_id),
+            &[],
+            Body::empty(),
+        )
+        .await?;
+
+    print_json(&compact_result);
+    Ok(())
+}
+
+async fn freeze(
+    server: &Server,
+    command: &str,
+    params: &HashMap<String, String>,
+) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    server
+        .request(
+            Method::POST,
+            &format!("/api/v1/index/{}/{}", index_id, command),
+            &[],
+            Body::empty(),
+        )
+        .await?;
+
+    println!(
+        "index {}: index_id {}",
+        command.replace("freeze", "frozen"),
+        index_id
+    );
+    Ok(())
+}
+
+async fn verify(server: &Server, params: &HashMap<String, String>) -> Result<(), String> {
+    let index_id = get_param(params, "index_id")?;
+    let quarantine: bool = match params.get("quarantine") {
+        Some(quarantine) => quarantine
+            .parse()
+            .map_err(|_| "quarantine invalid".to_string())?,
+        None => false,
+    };
+    let verify_result = server
+        .request(
+            Method::POST,
+            &format!("/api/v1/index/{}/verify", index_id),
+            &[],
+            json!({ "quarantine": quarantine }).to_string().into(),
+        )
+        .await?;
+
+    print_json(&verify_result);
+    Ok(())
+}
+
+fn print_json(json_string: &str) {
+    match serde_json::from_str::<serde_json::Value>(json_string) {
+        Ok(value) => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
+        Err(_) => println!("{}", json_string),
+    }
+}
@@ -1,38 +1,100 @@
 use std::{
     collections::HashMap,
     fs::{self},
+    ops::Range,
     path::{Path, PathBuf},
-    sync::Arc,
-    time::Instant,
+    sync::{Arc, OnceLock},
+    thread::available_parallelism,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use ahash::AHashMap;
+use chrono::Utc;
 use itertools::Itertools;
 use std::collections::HashSet;
 
 use seekstorm::{
+    alert::{Alert, AlertNotification, EvaluateAlerts},
+    bulk::{Bulk, BulkOperation, BulkResult},
     commit::Commit,
-    highlighter::{highlighter, Highlight},
+    compact::{compaction_index_path, Compact, CompactResult},
+    diversify::Diversify,
+    error::SeekStormError,
+    experiment::{RankingVariant, RankingVariantMetrics},
+    expression::Expression,
+    feedback::{FeedbackEvent, QueryAnalytics},
+    freeze::Freeze,
+    highlighter::{highlighter_with_phrases, Highlight, HighlightOutput, Highlighter},
     index::{
         create_index, open_index, AccessType, DeleteDocument, DeleteDocuments,
-        DeleteDocumentsByQuery, DistanceField, Document, Facet, FileType, IndexArc, IndexDocument,
-        IndexDocuments, IndexMetaObject, MinMaxFieldJson, SchemaField, SimilarityType, Synonym,
-        TokenizerType, UpdateDocument, UpdateDocuments,
+        DeleteDocumentsByQuery, DistanceField, Document, Durability, Facet, FacetAlias, FieldType,
+        FileType, Index, IndexArc, IndexDocument, IndexMetaObject, Language, MinMaxFieldJson,
+        SchemaField, SimilarityType, StemmerType, Synonym, TokenizerType, UpdateDocument,
+        UpdateDocuments,
     },
     ingest::IndexPdfBytes,
-    search::{FacetFilter, QueryFacet, QueryType, ResultSort, ResultType, Search},
+    join::inner_hits,
+    level::LevelStats,
+    ltr::LtrModel,
+    percolator::{Percolate, PercolatorQuery},
+    query_rewrite::QueryRule,
+    rescore::Rescore,
+    runtime::RuntimeField,
+    scroll::Scroll,
+    search::{
+        FacetFilter, FunctionScore, MinimumShouldMatch, QueryExpansion, QueryFacet, QueryType,
+        ResultObject, ResultSort, ResultType, Search, SearchRequest,
+    },
+    storage::StorageStats,
+    suggest::{Suggest, SuggestContext, Suggestion},
+    terms::{FieldTermVectors, FieldTerms, TermFrequency},
+    update_by_query::{DocumentUpdate, UpdateByQuery, UpdateResult},
+    usage::{OperationType, UsageCounters},
+    validate::{DocumentResult, IndexDocumentsWithResults},
+    verify::{Verify, VerifyResult},
+    word_lists::{WordList, WordListType},
 };
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
+use walkdir::WalkDir;
 
 use crate::{
-    http_server::calculate_hash,
-    multi_tenancy::{ApikeyObject, ApikeyQuotaObject},
+    api_version::ApiVersion,
+    commit_webhooks::load_commit_webhooks,
+    encoding::serialize_u128_as_u64,
+    http_server::{calculate_hash, RequestLimits},
+    memory_budget::memory_usage,
+    multi_tenancy::{ApikeyObject, ApikeyQuotaObject, RecentQuery, ScopedApikeyObject},
+    tasks::{TaskHandle, TASK_BATCH_SIZE},
+    webhook::{check_email, check_webhook_url, post_webhook, send_email},
     VERSION,
 };
 
 const APIKEY_PATH: &str = "apikey.json";
 
+/// Start time of the server, for the uptime reported by the server info
+pub(crate) static SERVER_START: OnceLock<Instant> = OnceLock::new();
+
+/// Subdirectory of the API key directory, where deleted indices are kept until their retention period expires
+const TRASH_PATH: &str = "trash";
+
+/// Subdirectory of the API key directory, where corrupted indices are moved by verify with quarantine
+const QUARANTINE_PATH: &str = "quarantine";
+
+/// Deleted index in the trash of an API key, recoverable until it expires
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrashObject {
+    /// Id of the trash entry: {index_id}_{deleted}
+    pub trash_id: String,
+    /// Index id before deletion
+    pub index_id: u64,
+    pub name: String,
+    /// Unix timestamp of the deletion, in seconds
+    pub deleted: u64,
+    /// Unix timestamp, in seconds, when the index is purged from the trash
+    pub expires: u64,
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct SearchRequestObject {
     #[serde(rename = "query")]
@@ -46,6 +108,8 @@ pub struct SearchRequestObject {
     #[serde(default)]
     pub highlights: Vec<Highlight>,
     #[serde(default)]
+    pub highlight_output: HighlightOutput,
+    #[serde(default)]
     pub field_filter: Vec<String>,
     #[serde(default)]
     pub fields: Vec<String>,
@@ -59,14 +123,101 @@ pub struct SearchRequestObject {
     pub result_sort: Vec<ResultSort>,
     #[serde(default = "query_type_api")]
     pub query_type_default: QueryType,
+    #[serde(default)]
+    pub query_language: Option<Language>,
+    #[serde(default)]
+    pub function_score: Option<FunctionScore>,
+    #[serde(default)]
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    #[serde(default)]
+    pub query_expansion: QueryExpansion,
+    #[serde(default)]
+    pub rescore: Option<Rescore>,
+    /// Limits how many consecutive results may share the same value of a facet field, applied after the rescore phase (see Diversify)
+    #[serde(default)]
+    pub diversify: Option<Diversify>,
+    /// Id of the user, session or device, which assigns the request deterministically to a ranking variant of the index for A/B testing
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Maximum time in milliseconds to collect the results, the results collected so far are returned with partial: true (see SearchRequest::timeout_ms)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl SearchRequestObject {
+    /// Number of top results collected for the rescore and diversify phases: the largest window_size, None without rescore and diversify
+    pub(crate) fn window_size(&self) -> Option<usize> {
+        self.rescore
+            .iter()
+            .map(|rescore| rescore.window_size)
+            .chain(self.diversify.iter().map(|diversify| diversify.window_size))
+            .max()
+    }
 }
 
 fn query_type_api() -> QueryType {
     QueryType::Intersection
 }
 
+/// Request of update by query: the query parameters of delete documents by query, and the update (patch and/or set) of the matching documents
+#[derive(Deserialize, Serialize, Clone)]
+pub struct UpdateByQueryRequestObject {
+    #[serde(rename = "query")]
+    pub query_string: String,
+    pub offset: usize,
+    pub length: usize,
+    #[serde(default)]
+    pub realtime: bool,
+    #[serde(default)]
+    pub field_filter: Vec<String>,
+    #[serde(default)]
+    pub facet_filter: Vec<FacetFilter>,
+    #[serde(default)]
+    pub result_sort: Vec<ResultSort>,
+    #[serde(default = "query_type_api")]
+    pub query_type_default: QueryType,
+    #[serde(flatten)]
+    pub update: DocumentUpdate,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ScrollRequestObject {
+    #[serde(rename = "query")]
+    pub query_string: String,
+    #[serde(default = "scroll_length_api")]
+    pub length: usize,
+    #[serde(default)]
+    pub scroll_id: Option<String>,
+    #[serde(default)]
+    pub realtime: bool,
+    #[serde(default)]
+    pub field_filter: Vec<String>,
+    #[serde(default)]
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub facet_filter: Vec<FacetFilter>,
+    #[serde(default = "query_type_api")]
+    pub query_type_default: QueryType,
+}
+
+fn scroll_length_api() -> usize {
+    1000
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ScrollResultObject {
+    #[serde(serialize_with = "serialize_u128_as_u64")]
+    pub time: u128,
+    pub query: String,
+    pub count: usize,
+    pub count_total: usize,
+    pub scroll_id: Option<String>,
+    pub results: Vec<Document>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SearchResultObject {
+    #[serde(serialize_with = "serialize_u128_as_u64")]
     pub time: u128,
     pub query: String,
     pub offset: usize,
@@ -77,6 +228,17 @@ pub struct SearchResultObject {
     pub results: Vec<Document>,
     pub facets: AHashMap<String, Facet>,
     pub suggestions: Vec<String>,
+    /// Name of the ranking variant the request was assigned to, for A/B testing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranking_variant: Option<String>,
+    /// Id of the search, referenced by click and conversion feedback events for the results
+    pub search_id: String,
+    /// True if the search exceeded timeout_ms, and only the results and facets collected until then are returned
+    #[serde(default)]
+    pub partial: bool,
+    /// True if the query exceeded max_query_cost and was executed without query facets and total count (see downgrade_expensive_queries)
+    #[serde(default)]
+    pub downgraded: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -90,6 +252,37 @@ pub struct CreateIndexRequest {
     pub tokenizer: TokenizerType,
     #[serde(default)]
     pub synonyms: Vec<Synonym>,
+    #[serde(default)]
+    pub stemmer: StemmerType,
+    #[serde(default = "k1_api")]
+    pub k1: f32,
+    #[serde(default = "b_api")]
+    pub b: f32,
+    #[serde(default)]
+    pub compact_deleted_ratio: f32,
+    #[serde(default)]
+    pub durability: Durability,
+    #[serde(default = "sync_interval_ms_api")]
+    pub sync_interval_ms: u64,
+    #[serde(default)]
+    pub index_unstemmed: bool,
+    #[serde(default)]
+    pub strict_schema: bool,
+    /// Custom metadata of the application, an arbitrary JSON value stored with the index
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+fn sync_interval_ms_api() -> u64 {
+    1000
+}
+
+fn k1_api() -> f32 {
+    1.2
+}
+
+fn b_api() -> f32 {
+    0.75
 }
 
 fn similarity_type_api() -> SimilarityType {
@@ -105,13 +298,25 @@ pub struct DeleteApikeyRequest {
     pub apikey_base64: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateScopedApikeyRequest {
+    #[serde(default)]
+    pub facet_filter: Vec<FacetFilter>,
+    #[serde(default)]
+    pub hidden_fields: Vec<String>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GetDocumentRequest {
     #[serde(default)]
     pub query_terms: Vec<String>,
     #[serde(default)]
+    pub query_phrases: Vec<Vec<String>>,
+    #[serde(default)]
     pub highlights: Vec<Highlight>,
     #[serde(default)]
+    pub highlight_output: HighlightOutput,
+    #[serde(default)]
     pub fields: Vec<String>,
     #[serde(default)]
     pub distance_fields: Vec<DistanceField>,
@@ -123,30 +328,105 @@ pub(crate) struct IndexResponseObject {
     pub name: String,
     pub schema: HashMap<String, SchemaField>,
     pub indexed_doc_count: usize,
+    pub deleted_doc_count: usize,
+    pub frozen: bool,
+    pub durability: Durability,
+    pub strict_schema: bool,
+    /// Custom metadata of the application, see set index metadata
+    pub metadata: serde_json::Value,
+    /// Number of all operations since the index was created: indexing, delete and query
     pub operations_count: u64,
     pub query_count: u64,
+    /// Number of indexed and updated documents
+    pub indexing_count: u64,
+    /// Number of deleted documents
+    pub delete_count: u64,
     pub version: String,
     pub facets_minmax: HashMap<String, MinMaxFieldJson>,
+    /// Bytes on disk per component, mmap footprint and resident memory of the index
+    pub storage: StorageStats,
+}
+
+/// Aggregate of all indices of an apikey, e.g. for billing and the dashboard
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct ApikeySummaryObject {
+    pub index_count: usize,
+    pub indexed_doc_count: usize,
+    pub deleted_doc_count: usize,
+    /// Bytes on disk of all indices
+    pub disk_size: u64,
+    /// Sum of the usage counters of all indices
+    pub usage: UsageCounters,
+    /// Queries per second of the apikey, mean over the last minute
+    pub qps: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct AllIndexStatsResponseObject {
+    pub summary: ApikeySummaryObject,
+    pub indices: Vec<IndexResponseObject>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct DashboardIndexObject {
+    pub id: u64,
+    pub name: String,
+    pub indexed_doc_count: usize,
+    pub disk_size: u64,
+    pub usage: UsageCounters,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct DashboardResponseObject {
+    pub version: String,
+    pub indices: Vec<DashboardIndexObject>,
+    pub summary: ApikeySummaryObject,
+    pub recent_queries: Vec<RecentQuery>,
 }
 
-/// Save file atomically
-pub(crate) fn save_file_atomically(path: &PathBuf, content: String) {
+/// Save file atomically: the content is written to a temporary file, which then replaces the file.
+/// If writing fails, the file is unchanged.
+pub(crate) fn save_file_atomically(
+    path: &PathBuf,
+    content: impl AsRef<[u8]>,
+) -> Result<(), SeekStormError> {
     let mut temp_path = path.clone();
     temp_path.set_extension("bak");
-    fs::write(&temp_path, content).unwrap();
-    match fs::rename(temp_path, path) {
-        Ok(_) => {}
-        Err(e) => println!("error: {e:?}"),
-    }
+    fs::write(&temp_path, content)
+        .map_err(|e| SeekStormError::Io(format!("{}: {}", temp_path.display(), e)))?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        SeekStormError::Io(format!("{}: {}", path.display(), e))
+    })
 }
 
-pub(crate) fn save_apikey_data(apikey: &ApikeyObject, index_path: &PathBuf) {
+pub(crate) fn save_apikey_data(
+    apikey: &ApikeyObject,
+    index_path: &PathBuf,
+) -> Result<(), SeekStormError> {
     let apikey_id: u64 = apikey.id;
 
     let apikey_id_path = Path::new(&index_path).join(apikey_id.to_string());
-    let apikey_persistence_json = serde_json::to_string(&apikey).unwrap();
+    let apikey_persistence_json =
+        serde_json::to_vec(&apikey).map_err(|e| SeekStormError::Internal(e.to_string()))?;
     let apikey_persistence_path = Path::new(&apikey_id_path).join(APIKEY_PATH);
-    save_file_atomically(&apikey_persistence_path, apikey_persistence_json);
+    save_file_atomically(&apikey_persistence_path, apikey_persistence_json).inspect_err(|e| {
+        println!("error saving api key {}: {}", apikey_id, e);
+    })
+}
+
+/// Reads an apikey.json file
+fn read_apikey_data(apikey_path: &Path) -> Result<ApikeyObject, String> {
+    let apikey_data =
+        fs::read(apikey_path).map_err(|e| format!("{}: {}", apikey_path.display(), e))?;
+    serde_json::from_slice(&apikey_data).map_err(|e| format!("{}: {}", apikey_path.display(), e))
+}
+
+/// Opens an index, and registers its commit webhooks
+async fn open_server_index(index_path: &Path) -> Result<IndexArc, String> {
+    let index_arc = open_index(index_path, false).await?;
+    load_commit_webhooks(index_path, &mut *index_arc.write().await);
+    Ok(index_arc)
 }
 
 pub(crate) fn create_apikey_api<'a>(
@@ -154,7 +434,7 @@ pub(crate) fn create_apikey_api<'a>(
     apikey_quota_request_object: ApikeyQuotaObject,
     apikey: &[u8],
     apikey_list: &'a mut HashMap<u128, ApikeyObject>,
-) -> &'a mut ApikeyObject {
+) -> Result<&'a mut ApikeyObject, SeekStormError> {
     let apikey_hash_u128 = calculate_hash(&apikey) as u128;
 
     let mut apikey_id: u64 = 0;
@@ -172,33 +452,117 @@ pub(crate) fn create_apikey_api<'a>(
         id: apikey_id,
         apikey_hash: apikey_hash_u128,
         quota: apikey_quota_request_object,
+        scoped_apikeys: Vec::new(),
+        aliases: Vec::new(),
+        lifecycle_policies: Vec::new(),
+        metadata: serde_json::Value::Null,
         index_list: HashMap::new(),
+        recent_queries: Default::default(),
+        query_rate: Default::default(),
+        tasks: Default::default(),
+        search_permits: Default::default(),
     };
 
     let apikey_id_path = Path::new(&index_path).join(apikey_id.to_string());
-    fs::create_dir_all(apikey_id_path).unwrap();
+    fs::create_dir_all(&apikey_id_path)
+        .map_err(|e| SeekStormError::Io(format!("{}: {}", apikey_id_path.display(), e)))?;
 
-    save_apikey_data(&apikey_object, index_path);
+    // the api key is only added to the list after it was persisted, otherwise it would be lost on restart
+    save_apikey_data(&apikey_object, index_path)?;
 
-    apikey_list.insert(apikey_hash_u128, apikey_object);
-    apikey_list.get_mut(&apikey_hash_u128).unwrap()
+    Ok(apikey_list.entry(apikey_hash_u128).or_insert(apikey_object))
 }
 
 pub(crate) fn delete_apikey_api(
     index_path: &PathBuf,
     apikey_list: &mut HashMap<u128, ApikeyObject>,
     apikey_hash: u128,
-) -> Result<u64, String> {
+) -> Result<u64, SeekStormError> {
     if let Some(apikey_object) = apikey_list.get(&apikey_hash) {
         let apikey_id_path = Path::new(&index_path).join(apikey_object.id.to_string());
         println!("delete path {}", apikey_id_path.to_string_lossy());
-        fs::remove_dir_all(&apikey_id_path).unwrap();
+        fs::remove_dir_all(&apikey_id_path).map_err(|e| {
+            println!("error: {}: {}", apikey_id_path.display(), e);
+            SeekStormError::Io(format!("{}: {}", apikey_id_path.display(), e))
+        })?;
 
         apikey_list.remove(&apikey_hash);
         Ok(apikey_list.len() as u64)
     } else {
-        Err("not found".to_string())
+        Err(SeekStormError::NotFound(
+            "api_key does not exists".to_string(),
+        ))
+    }
+}
+
+/// Creates a scoped API key with read-only access to the indices of the parent API key, restricted by the security filter and hidden fields
+pub(crate) fn create_scoped_apikey_api(
+    index_path: &PathBuf,
+    create_scoped_apikey_request: CreateScopedApikeyRequest,
+    apikey: &[u8],
+    apikey_object: &mut ApikeyObject,
+) -> Result<u64, String> {
+    if create_scoped_apikey_request.facet_filter.is_empty()
+        && create_scoped_apikey_request.hidden_fields.is_empty()
+    {
+        return Err("facet_filter and hidden_fields missing".to_string());
+    }
+
+    apikey_object.scoped_apikeys.push(ScopedApikeyObject {
+        apikey_hash: calculate_hash(&apikey) as u128,
+        facet_filter: create_scoped_apikey_request.facet_filter,
+        hidden_fields: create_scoped_apikey_request.hidden_fields,
+    });
+    save_apikey_data(apikey_object, index_path)?;
+
+    Ok(apikey_object.scoped_apikeys.len() as u64)
+}
+
+/// Deletes a scoped API key of the parent API key
+pub(crate) fn delete_scoped_apikey_api(
+    index_path: &PathBuf,
+    apikey_hash: u128,
+    apikey_object: &mut ApikeyObject,
+) -> Result<u64, String> {
+    let scoped_apikeys_len = apikey_object.scoped_apikeys.len();
+    apikey_object
+        .scoped_apikeys
+        .retain(|scoped_apikey| scoped_apikey.apikey_hash != apikey_hash);
+    if apikey_object.scoped_apikeys.len() == scoped_apikeys_len {
+        return Err("not found".to_string());
+    }
+    save_apikey_data(apikey_object, index_path)?;
+
+    Ok(apikey_object.scoped_apikeys.len() as u64)
+}
+
+/// Sets/replaces the custom metadata of the API key, returns the metadata
+pub(crate) fn set_apikey_metadata_api(
+    index_path: &PathBuf,
+    metadata: serde_json::Value,
+    apikey_object: &mut ApikeyObject,
+) -> Result<serde_json::Value, SeekStormError> {
+    let previous_metadata = std::mem::replace(&mut apikey_object.metadata, metadata);
+    if let Err(e) = save_apikey_data(apikey_object, index_path) {
+        apikey_object.metadata = previous_metadata;
+        return Err(e);
     }
+
+    Ok(apikey_object.metadata.clone())
+}
+
+/// Sets/replaces the custom metadata of the index, returns the metadata
+pub(crate) async fn set_index_metadata_api(
+    index_arc: &IndexArc,
+    metadata: serde_json::Value,
+) -> Result<serde_json::Value, SeekStormError> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.set_metadata(metadata)?;
+    Ok(index_mut.get_metadata())
+}
+
+pub(crate) async fn get_index_metadata_api(index_arc: &IndexArc) -> serde_json::Value {
+    index_arc.read().await.get_metadata()
 }
 
 /// Open all indices below a single apikey
@@ -206,19 +570,48 @@ pub(crate) async fn open_all_indices(
     index_path: &PathBuf,
     index_list: &mut HashMap<u64, IndexArc>,
 ) {
-    if !Path::exists(index_path) {
-        fs::create_dir_all(index_path).unwrap();
-    }
+    let read_dir = match fs::create_dir_all(index_path).and_then(|_| fs::read_dir(index_path)) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            println!("error: {}: {}", index_path.display(), e);
+            return;
+        }
+    };
 
-    for result in fs::read_dir(index_path).unwrap() {
-        let path = result.unwrap();
-        if path.path().is_dir() {
-            let single_index_path = path.path();
-            let Ok(index_arc) = open_index(&single_index_path, false).await else {
+    for result in read_dir {
+        let path = match result {
+            Ok(path) => path,
+            Err(e) => {
+                println!("error: {}: {}", index_path.display(), e);
+                continue;
+            }
+        };
+        if path.path().is_dir()
+            && path.file_name() != TRASH_PATH
+            && path.file_name() != QUARANTINE_PATH
+        {
+            // a working directory of a compaction interrupted by a crash opens its index directory, which recovers the compaction
+            let single_index_path = compaction_index_path(&path.path()).unwrap_or(path.path());
+            // the index id is not stored in the index meta, but is the name of the index directory
+            let Some(index_id) = single_index_path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            if index_list.contains_key(&index_id) {
                 continue;
+            }
+            let index_arc = match open_server_index(&single_index_path).await {
+                Ok(index_arc) => index_arc,
+                Err(e) => {
+                    println!("error: {}", e);
+                    continue;
+                }
             };
 
-            let index_id = index_arc.read().await.meta.id;
+            index_arc.write().await.meta.id = index_id;
             index_list.insert(index_id, index_arc);
         }
     }
@@ -230,17 +623,36 @@ pub(crate) async fn open_apikey(
     apikey_list: &mut HashMap<u128, ApikeyObject>,
 ) -> bool {
     let apikey_path = Path::new(&index_path).join(APIKEY_PATH);
-    match fs::read_to_string(apikey_path) {
-        Ok(apikey_string) => {
-            let mut apikey_object: ApikeyObject = serde_json::from_str(&apikey_string).unwrap();
-
+    if !apikey_path.exists() {
+        return false;
+    }
+    match read_apikey_data(&apikey_path) {
+        Ok(mut apikey_object) => {
             open_all_indices(index_path, &mut apikey_object.index_list).await;
             apikey_list.insert(apikey_object.apikey_hash, apikey_object);
 
             true
         }
-        Err(_) => false,
+        Err(e) => {
+            println!("error: {}", e);
+            false
+        }
+    }
+}
+
+/// Reload the quota (e.g. rate_limit) of all opened apikeys from their apikey.json files, without re-opening their indices
+pub(crate) fn reload_apikey_quotas(
+    index_path: &PathBuf,
+    apikey_list: &mut HashMap<u128, ApikeyObject>,
+) -> Result<(), String> {
+    for apikey_object in apikey_list.values_mut() {
+        let apikey_path = Path::new(&index_path)
+            .join(apikey_object.id.to_string())
+            .join(APIKEY_PATH);
+        let apikey_object_reloaded = read_apikey_data(&apikey_path)?;
+        apikey_object.quota = apikey_object_reloaded.quota;
     }
+    Ok(())
 }
 
 /// Open all apikeys in the specified path
@@ -251,11 +663,24 @@ pub(crate) async fn open_all_apikeys(
     let mut test_index_flag = false;
     if !Path::exists(index_path) {
         println!("index path not found: {} ", index_path.to_string_lossy());
-        fs::create_dir_all(index_path).unwrap();
     }
 
-    for result in fs::read_dir(index_path).unwrap() {
-        let path = result.unwrap();
+    let read_dir = match fs::create_dir_all(index_path).and_then(|_| fs::read_dir(index_path)) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            println!("error: {}: {}", index_path.display(), e);
+            return test_index_flag;
+        }
+    };
+
+    for result in read_dir {
+        let path = match result {
+            Ok(path) => path,
+            Err(e) => {
+                println!("error: {}: {}", index_path.display(), e);
+                continue;
+            }
+        };
         if path.path().is_dir() {
             let single_index_path = path.path();
             test_index_flag |= open_apikey(&single_index_path, apikey_list).await;
@@ -264,6 +689,7 @@ pub(crate) async fn open_all_apikeys(
     test_index_flag
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create_index_api<'a>(
     index_path: &'a PathBuf,
     index_name: String,
@@ -271,21 +697,29 @@ pub(crate) fn create_index_api<'a>(
     similarity: SimilarityType,
     tokenizer: TokenizerType,
     synonyms: Vec<Synonym>,
+    stemmer: StemmerType,
+    k1: f32,
+    b: f32,
+    compact_deleted_ratio: f32,
+    durability: Durability,
+    sync_interval_ms: u64,
+    index_unstemmed: bool,
+    strict_schema: bool,
+    metadata: serde_json::Value,
     apikey_object: &'a mut ApikeyObject,
-) -> u64 {
-    let mut index_id: u64 = 0;
-    for id in apikey_object.index_list.keys().sorted() {
-        if *id == index_id {
-            index_id = id + 1;
-        } else {
-            break;
-        }
+) -> Result<u64, String> {
+    if tokenizer == TokenizerType::Custom {
+        return Err(
+            "tokenizer Custom is only supported by the library (Index::set_tokenizer)".to_string(),
+        );
     }
+    let index_id = free_index_id(&apikey_object.index_list);
 
     let index_id_path = Path::new(&index_path)
         .join(apikey_object.id.to_string())
         .join(index_id.to_string());
-    fs::create_dir_all(&index_id_path).unwrap();
+    fs::create_dir_all(&index_id_path)
+        .map_err(|e| format!("{}: {}", index_id_path.display(), e))?;
 
     let meta = IndexMetaObject {
         id: index_id,
@@ -293,43 +727,299 @@ pub(crate) fn create_index_api<'a>(
         similarity,
         tokenizer,
         access_type: AccessType::Mmap,
+        stemmer,
+        k1,
+        b,
+        compact_deleted_ratio,
+        durability,
+        sync_interval_ms,
+        index_unstemmed,
+        strict_schema,
+        metadata,
+        created: 0,
     };
 
-    let index = create_index(&index_id_path, meta, &schema, true, &synonyms, 11, false).unwrap();
+    let index = match create_index(&index_id_path, meta, &schema, true, &synonyms, 11, false) {
+        Ok(index) => index,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&index_id_path);
+            return Err(e);
+        }
+    };
 
     let index_arc = Arc::new(RwLock::new(index));
     apikey_object.index_list.insert(index_id, index_arc);
 
+    Ok(index_id)
+}
+
+/// Lowest index id not used by an index of the API key
+fn free_index_id(index_list: &HashMap<u64, IndexArc>) -> u64 {
+    let mut index_id: u64 = 0;
+    for id in index_list.keys().sorted() {
+        if *id == index_id {
+            index_id = id + 1;
+        } else {
+            break;
+        }
+    }
     index_id
 }
 
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Deletes an index: moves the index directory to the trash of the API key, where it can be restored until trash_retention_hours expire.
+/// trash_retention_hours=0 deletes the index immediately.
 pub(crate) async fn delete_index_api(
+    index_path: &Path,
     index_id: u64,
-    index_list: &mut HashMap<u64, IndexArc>,
-) -> Result<u64, String> {
-    if let Some(index_arc) = index_list.get(&index_id) {
+    apikey_object: &mut ApikeyObject,
+    trash_retention_hours: u64,
+) -> Result<u64, SeekStormError> {
+    if let Some(index_arc) = apikey_object.index_list.remove(&index_id) {
         let mut index_mut = index_arc.write().await;
-        index_mut.delete_index();
+        if trash_retention_hours == 0 {
+            index_mut.delete_index();
+        } else {
+            index_mut.close_index();
+            let apikey_path = index_path.join(apikey_object.id.to_string());
+            let index_id_path = apikey_path.join(index_id.to_string());
+            let trash_path = apikey_path.join(TRASH_PATH);
+            fs::create_dir_all(&trash_path)?;
+            fs::rename(
+                &index_id_path,
+                trash_path.join(format!("{}_{}", index_id, unix_timestamp())),
+            )?;
+        }
         drop(index_mut);
-        index_list.remove(&index_id);
 
-        Ok(index_list.len() as u64)
+        Ok(apikey_object.index_list.len() as u64)
     } else {
-        Err("index_id not found".to_string())
+        Err(SeekStormError::IndexNotFound(
+            "index_id not found".to_string(),
+        ))
+    }
+}
+
+/// Verify index request object
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct VerifyIndexRequest {
+    /// Move a corrupted index to the quarantine directory of the API key, instead of failing unpredictably during queries
+    #[serde(default)]
+    pub quarantine: bool,
+}
+
+/// Verify index response object
+#[derive(Deserialize, Serialize, Clone)]
+pub struct VerifyIndexResponse {
+    #[serde(flatten)]
+    pub result: VerifyResult,
+    /// The corrupted index was moved to the quarantine directory of the API key
+    pub quarantined: bool,
+}
+
+pub(crate) async fn verify_index_api(index_arc: &IndexArc) -> Result<VerifyResult, String> {
+    index_arc.verify().await
+}
+
+/// Moves the index to the quarantine, if the verification found it corrupted and the quarantine was requested. Returns true if quarantined.
+pub(crate) async fn quarantine_invalid_index_api(
+    index_path: &Path,
+    apikey_list: &RwLock<HashMap<u128, ApikeyObject>>,
+    apikey_hash: u128,
+    index_id: u64,
+    result: &VerifyResult,
+    quarantine: bool,
+) -> Result<bool, String> {
+    if result.valid || !quarantine {
+        return Ok(false);
+    }
+    let mut apikey_list_mut = apikey_list.write().await;
+    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+        return Ok(false);
+    };
+    quarantine_index_api(index_path, index_id, apikey_object).await?;
+    Ok(true)
+}
+
+/// Closes a corrupted index and moves it to the quarantine directory of the API key: {apikey_id}/quarantine/{index_id}_{timestamp}.
+/// The index is no longer opened or searched, its files are kept for inspection and recovery.
+pub(crate) async fn quarantine_index_api(
+    index_path: &Path,
+    index_id: u64,
+    apikey_object: &mut ApikeyObject,
+) -> Result<(), String> {
+    let Some(index_arc) = apikey_object.index_list.remove(&index_id) else {
+        return Err("index_id not found".to_string());
+    };
+    let index_mut = index_arc.write().await;
+    let apikey_path = index_path.join(apikey_object.id.to_string());
+    let quarantine_path = apikey_path.join(QUARANTINE_PATH);
+    fs::create_dir_all(&quarantine_path).map_err(|e| e.to_string())?;
+    fs::rename(
+        apikey_path.join(index_id.to_string()),
+        quarantine_path.join(format!("{}_{}", index_id, unix_timestamp())),
+    )
+    .map_err(|e| e.to_string())?;
+    drop(index_mut);
+
+    Ok(())
+}
+
+/// Parses a trash id {index_id}_{deleted}, which also rejects any path traversal
+fn parse_trash_id(trash_id: &str) -> Option<(u64, u64)> {
+    let (index_id, deleted) = trash_id.split_once('_')?;
+    Some((index_id.parse().ok()?, deleted.parse().ok()?))
+}
+
+/// Lists the deleted indices in the trash of the API key, oldest first
+pub(crate) fn get_trash_api(
+    index_path: &Path,
+    apikey_object: &ApikeyObject,
+    trash_retention_hours: u64,
+) -> Vec<TrashObject> {
+    let trash_path = index_path
+        .join(apikey_object.id.to_string())
+        .join(TRASH_PATH);
+    let Ok(read_dir) = fs::read_dir(trash_path) else {
+        return Vec::new();
+    };
+
+    let mut trash_list = Vec::new();
+    for entry in read_dir.flatten() {
+        let trash_id = entry.file_name().to_string_lossy().to_string();
+        let Some((index_id, deleted)) = parse_trash_id(&trash_id) else {
+            continue;
+        };
+        let name = fs::read_to_string(entry.path().join("index.json"))
+            .ok()
+            .and_then(|meta_string| serde_json::from_str::<IndexMetaObject>(&meta_string).ok())
+            .map(|meta| meta.name)
+            .unwrap_or_default();
+
+        trash_list.push(TrashObject {
+            trash_id,
+            index_id,
+            name,
+            deleted,
+            expires: deleted + trash_retention_hours * 3600,
+        });
+    }
+    trash_list.sort_by_key(|trash_object| trash_object.deleted);
+    trash_list
+}
+
+/// Restores a deleted index from the trash of the API key.
+/// The index gets its former index id if it is still free, otherwise the lowest free index id.
+/// Returns the index id of the restored index.
+pub(crate) async fn restore_index_api(
+    index_path: &Path,
+    trash_id: &str,
+    apikey_object: &mut ApikeyObject,
+) -> Result<u64, String> {
+    let Some((former_index_id, _)) = parse_trash_id(trash_id) else {
+        return Err("trash_id invalid".to_string());
+    };
+    let apikey_path = index_path.join(apikey_object.id.to_string());
+    let trash_index_path = apikey_path.join(TRASH_PATH).join(trash_id);
+    if !trash_index_path.exists() {
+        return Err("trash_id not found".to_string());
     }
+
+    let index_id = if apikey_object.index_list.contains_key(&former_index_id) {
+        free_index_id(&apikey_object.index_list)
+    } else {
+        former_index_id
+    };
+    let index_id_path = apikey_path.join(index_id.to_string());
+    if index_id_path.exists() {
+        return Err(format!("index directory already exists: {}", index_id));
+    }
+
+    fs::rename(&trash_index_path, &index_id_path).map_err(|e| e.to_string())?;
+    let index_arc = match open_server_index(&index_id_path).await {
+        Ok(index_arc) => index_arc,
+        Err(e) => {
+            let _ = fs::rename(&index_id_path, &trash_index_path);
+            return Err(e);
+        }
+    };
+    index_arc.write().await.meta.id = index_id;
+    apikey_object.index_list.insert(index_id, index_arc);
+
+    Ok(index_id)
+}
+
+/// Permanently deletes a deleted index from the trash of the API key.
+/// Returns the number of remaining indices in the trash.
+pub(crate) fn purge_index_api(
+    index_path: &Path,
+    trash_id: &str,
+    apikey_object: &ApikeyObject,
+) -> Result<u64, String> {
+    if parse_trash_id(trash_id).is_none() {
+        return Err("trash_id invalid".to_string());
+    }
+    let trash_path = index_path
+        .join(apikey_object.id.to_string())
+        .join(TRASH_PATH);
+    let trash_index_path = trash_path.join(trash_id);
+    if !trash_index_path.exists() {
+        return Err("trash_id not found".to_string());
+    }
+    fs::remove_dir_all(&trash_index_path).map_err(|e| e.to_string())?;
+
+    Ok(fs::read_dir(&trash_path)
+        .map(|read_dir| read_dir.count())
+        .unwrap_or(0) as u64)
 }
 
-pub(crate) async fn commit_index_api(index_arc: &IndexArc) -> Result<u64, String> {
+/// Permanently deletes all indices of the API key, whose retention period in the trash has expired
+pub(crate) fn purge_expired_trash(
+    index_path: &Path,
+    apikey_object: &ApikeyObject,
+    trash_retention_hours: u64,
+) {
+    let now = unix_timestamp();
+    for trash_object in get_trash_api(index_path, apikey_object, trash_retention_hours) {
+        if trash_object.expires <= now {
+            let _ = purge_index_api(index_path, &trash_object.trash_id, apikey_object);
+        }
+    }
+}
+
+pub(crate) async fn commit_index_api(index_arc: &IndexArc) -> Result<u64, SeekStormError> {
     let mut index_arc_clone = index_arc.clone();
     let index_ref = index_arc.read().await;
     let indexed_doc_count = index_ref.indexed_doc_count;
 
     drop(index_ref);
-    index_arc_clone.commit().await;
+    index_arc_clone.commit().await?;
 
     Ok(indexed_doc_count as u64)
 }
 
+pub(crate) async fn compact_index_api(
+    index_arc: &IndexArc,
+) -> Result<CompactResult, SeekStormError> {
+    index_arc.compact().await
+}
+
+pub(crate) async fn freeze_index_api(index_arc: &IndexArc) -> Result<u64, SeekStormError> {
+    index_arc.freeze().await?;
+    Ok(index_arc.read().await.indexed_doc_count as u64)
+}
+
+pub(crate) async fn unfreeze_index_api(index_arc: &IndexArc) -> Result<u64, SeekStormError> {
+    index_arc.unfreeze().await?;
+    Ok(index_arc.read().await.indexed_doc_count as u64)
+}
+
 pub(crate) async fn close_index_api(index_arc: &IndexArc) -> Result<u64, String> {
     let mut index_mut = index_arc.write().await;
     let indexed_doc_count = index_mut.indexed_doc_count;
@@ -342,7 +1032,7 @@ pub(crate) async fn close_index_api(index_arc: &IndexArc) -> Result<u64, String>
 pub(crate) async fn set_synonyms_api(
     index_arc: &IndexArc,
     synonyms: Vec<Synonym>,
-) -> Result<usize, String> {
+) -> Result<usize, SeekStormError> {
     let mut index_mut = index_arc.write().await;
     index_mut.set_synonyms(&synonyms)
 }
@@ -350,16 +1040,636 @@ pub(crate) async fn set_synonyms_api(
 pub(crate) async fn add_synonyms_api(
     index_arc: &IndexArc,
     synonyms: Vec<Synonym>,
-) -> Result<usize, String> {
+) -> Result<usize, SeekStormError> {
     let mut index_mut = index_arc.write().await;
     index_mut.add_synonyms(&synonyms)
 }
 
-pub(crate) async fn get_synonyms_api(index_arc: &IndexArc) -> Result<Vec<Synonym>, String> {
+pub(crate) async fn get_synonyms_api(index_arc: &IndexArc) -> Result<Vec<Synonym>, SeekStormError> {
     let index_ref = index_arc.read().await;
     index_ref.get_synonyms()
 }
 
+pub(crate) async fn get_word_list_api(index_arc: &IndexArc, list_type: WordListType) -> WordList {
+    let index_ref = index_arc.read().await;
+    index_ref.get_word_list(list_type)
+}
+
+/// Changes a per-index word list: PUT replaces the list, POST adds the words, DELETE removes the words.
+/// With expected_version the list is only changed if its version matches, otherwise CONFLICT is returned.
+pub(crate) async fn update_word_list_api(
+    index_arc: &IndexArc,
+    list_type: WordListType,
+    method: &hyper::Method,
+    words: Vec<String>,
+    expected_version: Option<u64>,
+) -> Result<WordList, SeekStormError> {
+    let mut index_mut = index_arc.write().await;
+    match *method {
+        hyper::Method::PUT => index_mut.set_word_list(list_type, &words, expected_version),
+        hyper::Method::POST => index_mut.add_words(list_type, &words, expected_version),
+        hyper::Method::DELETE => index_mut.delete_words(list_type, &words, expected_version),
+        _ => Err(SeekStormError::InvalidRequest(
+            "method not implemented".to_string(),
+        )),
+    }
+}
+
+pub(crate) async fn set_facet_aliases_api(
+    index_arc: &IndexArc,
+    facet_aliases: Vec<FacetAlias>,
+) -> Result<usize, SeekStormError> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.set_facet_aliases(&facet_aliases)
+}
+
+pub(crate) async fn add_facet_aliases_api(
+    index_arc: &IndexArc,
+    facet_aliases: Vec<FacetAlias>,
+) -> Result<usize, SeekStormError> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.add_facet_aliases(&facet_aliases)
+}
+
+pub(crate) async fn get_facet_aliases_api(
+    index_arc: &IndexArc,
+) -> Result<Vec<FacetAlias>, SeekStormError> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_facet_aliases()
+}
+
+pub(crate) async fn set_ltr_model_api(
+    index_arc: &IndexArc,
+    name: &str,
+    ltr_model: LtrModel,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.set_ltr_model(name, ltr_model)
+}
+
+pub(crate) async fn delete_ltr_model_api(
+    index_arc: &IndexArc,
+    name: &str,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.delete_ltr_model(name)
+}
+
+pub(crate) async fn get_ltr_models_api(index_arc: &IndexArc) -> Vec<String> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_ltr_models()
+}
+
+pub(crate) async fn set_query_rules_api(
+    index_arc: &IndexArc,
+    query_rules: Vec<QueryRule>,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.set_query_rules(query_rules)
+}
+
+pub(crate) async fn get_query_rules_api(index_arc: &IndexArc) -> Vec<QueryRule> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_query_rules()
+}
+
+pub(crate) async fn set_runtime_fields_api(
+    index_arc: &IndexArc,
+    runtime_fields: Vec<RuntimeField>,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.set_runtime_fields(runtime_fields)
+}
+
+pub(crate) async fn get_runtime_fields_api(index_arc: &IndexArc) -> Vec<RuntimeField> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_runtime_fields()
+}
+
+pub(crate) async fn add_percolator_queries_api(
+    index_arc: &IndexArc,
+    percolator_queries: Vec<PercolatorQuery>,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.add_percolator_queries(percolator_queries)
+}
+
+pub(crate) async fn get_percolator_queries_api(index_arc: &IndexArc) -> Vec<PercolatorQuery> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_percolator_queries()
+}
+
+pub(crate) async fn delete_percolator_queries_api(
+    index_arc: &IndexArc,
+    ids: Vec<String>,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.delete_percolator_queries(ids)
+}
+
+pub(crate) async fn set_ranking_variants_api(
+    index_arc: &IndexArc,
+    ranking_variants: Vec<RankingVariant>,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.set_ranking_variants(ranking_variants)
+}
+
+pub(crate) async fn get_ranking_variants_api(index_arc: &IndexArc) -> Vec<RankingVariant> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_ranking_variants()
+}
+
+pub(crate) async fn get_ranking_metrics_api(index_arc: &IndexArc) -> Vec<RankingVariantMetrics> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_ranking_metrics()
+}
+
+/// Click on a search result, reported for the metrics of the ranking variant the user is assigned to
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RankingClickRequest {
+    pub user_id: String,
+    /// Position of the clicked result within the results (0 for the top result), including the offset of the request
+    pub position: usize,
+}
+
+/// Records a click for the ranking variant of the user. Returns the name of the ranking variant.
+pub(crate) async fn record_ranking_click_api(
+    index_arc: &IndexArc,
+    ranking_click_request: RankingClickRequest,
+) -> Result<String, String> {
+    let index_ref = index_arc.read().await;
+    let Some(ranking_variant) = index_ref.assign_ranking_variant(&ranking_click_request.user_id)
+    else {
+        return Err("index has no ranking variants".to_string());
+    };
+    index_ref.record_ranking_click(&ranking_variant.name, ranking_click_request.position);
+    Ok(ranking_variant.name.clone())
+}
+
+/// Records click and conversion feedback events. Returns the number of recorded events.
+pub(crate) async fn record_feedback_events_api(
+    index_arc: &IndexArc,
+    feedback_events: Vec<FeedbackEvent>,
+) -> Result<usize, String> {
+    let index_ref = index_arc.read().await;
+    index_ref.record_feedback_events(&feedback_events)
+}
+
+pub(crate) async fn get_query_analytics_api(
+    index_arc: &IndexArc,
+    length: usize,
+) -> Vec<QueryAnalytics> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_query_analytics(length)
+}
+
+pub(crate) async fn get_level_stats_api(index_arc: &IndexArc) -> Vec<LevelStats> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_level_stats()
+}
+
+fn default_suggest_length() -> usize {
+    10
+}
+
+/// Completions of a prefix, optionally filtered or boosted by a context (see Suggest::suggest)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SuggestRequest {
+    pub prefix: String,
+    #[serde(default)]
+    pub context: Option<SuggestContext>,
+    #[serde(default = "default_suggest_length")]
+    pub length: usize,
+}
+
+pub(crate) async fn suggest_api(
+    index_arc: &IndexArc,
+    suggest_request: SuggestRequest,
+) -> Result<Vec<Suggestion>, String> {
+    index_arc
+        .suggest(
+            &suggest_request.prefix,
+            suggest_request.context,
+            suggest_request.length,
+        )
+        .await
+}
+
+pub(crate) async fn add_alerts_api(
+    index_arc: &IndexArc,
+    alerts: Vec<Alert>,
+) -> Result<usize, String> {
+    for alert in alerts.iter() {
+        if !alert.webhook_url.is_empty() {
+            check_webhook_url(&alert.webhook_url)?;
+        }
+        if let Some(email) = alert.email.as_ref() {
+            check_email(email)?;
+        }
+    }
+    let mut index_mut = index_arc.write().await;
+    index_mut.add_alerts(alerts)
+}
+
+pub(crate) async fn get_alerts_api(index_arc: &IndexArc) -> Vec<Alert> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_alerts()
+}
+
+pub(crate) async fn delete_alerts_api(
+    index_arc: &IndexArc,
+    ids: Vec<String>,
+) -> Result<usize, String> {
+    let mut index_mut = index_arc.write().await;
+    index_mut.delete_alerts(ids)
+}
+
+/// Evaluates the alerts of the index for newly committed documents, and delivers the notifications to the webhooks and email addresses.
+/// Notifications which can't be delivered within NOTIFICATION_TIMEOUT are dropped: each match is notified at most once.
+pub(crate) async fn send_alert_notifications(index_arc: &IndexArc) {
+    let notifications: Vec<AlertNotification> = match index_arc.evaluate_alerts().await {
+        Ok(notifications) => notifications,
+        Err(e) => {
+            println!("alerts not evaluated: {}", e);
+            return;
+        }
+    };
+
+    for notification in notifications {
+        if !notification.webhook_url.is_empty() {
+            if let Err(e) = post_webhook(
+                &notification.webhook_url,
+                serde_json::to_string(&notification).unwrap(),
+            )
+            .await
+            {
+                println!(
+                    "alert notification not delivered: {} {} {}",
+                    notification.alert_id, notification.webhook_url, e
+                );
+            }
+        }
+        if let Some(email) = notification.email.as_ref() {
+            if let Err(e) = send_email(
+                email,
+                &format!(
+                    "New results for alert {} ({} matches)",
+                    notification.alert_id,
+                    notification.matches.len()
+                ),
+                serde_json::to_string_pretty(&notification).unwrap(),
+            )
+            .await
+            {
+                println!(
+                    "alert notification not delivered: {} {} {}",
+                    notification.alert_id, email, e
+                );
+            }
+        }
+    }
+}
+
+pub(crate) async fn percolate_api(
+    index_arc: &IndexArc,
+    documents: Vec<Document>,
+) -> Result<Vec<Vec<String>>, String> {
+    index_arc.percolate(documents).await
+}
+
+/// Field-level security: restricts the searched fields to the indexed fields which are not hidden, so that hidden fields can't be probed by queries.
+/// An empty field_filter (all indexed fields) is replaced by the list of all visible indexed fields.
+pub(crate) async fn visible_field_filter(
+    index_arc: &IndexArc,
+    field_filter: Vec<String>,
+    hidden_fields: &[String],
+) -> Result<Vec<String>, String> {
+    if hidden_fields.is_empty() {
+        return Ok(field_filter);
+    }
+
+    let field_filter = if field_filter.is_empty() {
+        index_arc
+            .read()
+            .await
+            .schema_map
+            .values()
+            .filter(|schema_field| schema_field.indexed)
+            .map(|schema_field| schema_field.field.clone())
+            .collect()
+    } else {
+        field_filter
+    };
+
+    let field_filter: Vec<String> = field_filter
+        .into_iter()
+        .filter(|field| !hidden_fields.contains(field))
+        .collect();
+    if field_filter.is_empty() {
+        Err("all searched fields are hidden".to_string())
+    } else {
+        Ok(field_filter)
+    }
+}
+
+/// The hidden fields of a scoped API key, with the runtime fields of the index derived from them (see Index::runtime_fields_referencing),
+/// which are both removed from the result documents.
+pub(crate) async fn hidden_result_fields(
+    index_arc: &IndexArc,
+    hidden_fields: &[String],
+) -> Vec<String> {
+    let mut result_fields = hidden_fields.to_vec();
+    if !hidden_fields.is_empty() {
+        result_fields.extend(
+            index_arc
+                .read()
+                .await
+                .runtime_fields_referencing(hidden_fields),
+        );
+    }
+    result_fields
+}
+
+/// Field-level security: removes the hidden fields from a search request,
+/// so that they are neither searched, highlighted, faceted, filtered nor sorted by.
+/// Sort expressions, function scores and runtime field filters which read a hidden field are rejected,
+/// as the order or the matches of the results would reveal its values.
+pub(crate) async fn hide_fields_search_request(
+    index_arc: &IndexArc,
+    search_request: &mut SearchRequestObject,
+    hidden_fields: &[String],
+) -> Result<(), String> {
+    if hidden_fields.is_empty() {
+        return Ok(());
+    }
+
+    let index_ref = index_arc.read().await;
+    let hidden_field = |expression: &Expression| {
+        expression
+            .fields(&index_ref)
+            .into_iter()
+            .find(|field| hidden_fields.contains(field))
+    };
+    for result_sort in search_request.result_sort.iter() {
+        if ["_score", "_id"].contains(&result_sort.field.as_str())
+            || hidden_fields.contains(&result_sort.field)
+            || index_ref.schema_map.contains_key(&result_sort.field)
+        {
+            continue;
+        }
+        if let Some(field) = Expression::compile(&result_sort.field, &index_ref)
+            .ok()
+            .as_ref()
+            .and_then(hidden_field)
+        {
+            return Err(format!(
+                "sort expression {} reads the hidden field {}",
+                result_sort.field, field
+            ));
+        }
+    }
+    for function_score in search_request.function_score.iter().chain(
+        search_request
+            .rescore
+            .iter()
+            .filter_map(|rescore| rescore.function_score.as_ref()),
+    ) {
+        if let Some(field) = Expression::from_function_score(function_score, &index_ref)
+            .ok()
+            .as_ref()
+            .and_then(hidden_field)
+        {
+            return Err(format!("function score reads the hidden field {}", field));
+        }
+    }
+    let hidden_runtime_fields = index_ref.runtime_fields_referencing(hidden_fields);
+    if let Some(facet_filter) = search_request.facet_filter.iter().find(|facet_filter| {
+        hidden_runtime_fields
+            .iter()
+            .any(|field| field == facet_filter.field())
+    }) {
+        return Err(format!(
+            "filter on runtime field {} which reads a hidden field",
+            facet_filter.field()
+        ));
+    }
+    drop(index_ref);
+
+    search_request.field_filter = visible_field_filter(
+        index_arc,
+        std::mem::take(&mut search_request.field_filter),
+        hidden_fields,
+    )
+    .await?;
+    search_request
+        .highlights
+        .retain(|highlight| !hidden_fields.contains(&highlight.field));
+    search_request.query_facets.retain(|query_facet| {
+        !hidden_fields
+            .iter()
+            .any(|field| field == query_facet.field())
+    });
+    search_request.facet_filter.retain(|facet_filter| {
+        !hidden_fields
+            .iter()
+            .any(|field| field == facet_filter.field())
+    });
+    search_request
+        .result_sort
+        .retain(|result_sort| !hidden_fields.contains(&result_sort.field));
+    Ok(())
+}
+
+/// Applies the security filter and the hidden fields of a scoped API key to the join filters of a request, including nested join filters,
+/// as their queries and inner hits are searched separately from the request. visible_fields are the indexed fields which are not hidden (see visible_field_filter).
+pub(crate) fn scope_join_filters(
+    facet_filter: &mut [FacetFilter],
+    security_filter: &[FacetFilter],
+    visible_fields: &[String],
+    hidden_fields: &[String],
+) -> Result<(), String> {
+    for facet_filter_item in facet_filter.iter_mut() {
+        let (FacetFilter::HasChild { filter, .. } | FacetFilter::HasParent { filter, .. }) =
+            facet_filter_item
+        else {
+            continue;
+        };
+        filter.facet_filter.retain(|facet_filter| {
+            !hidden_fields
+                .iter()
+                .any(|field| field == facet_filter.field())
+        });
+        scope_join_filters(
+            &mut filter.facet_filter,
+            security_filter,
+            visible_fields,
+            hidden_fields,
+        )?;
+        filter.facet_filter.extend(security_filter.iter().cloned());
+
+        if !hidden_fields.is_empty() {
+            if filter.field_filter.is_empty() {
+                filter.field_filter = visible_fields.to_vec();
+            } else {
+                filter
+                    .field_filter
+                    .retain(|field| !hidden_fields.contains(field));
+                if filter.field_filter.is_empty() {
+                    return Err("all searched fields of a join filter are hidden".to_string());
+                }
+            }
+        }
+        if let Some(inner_hits) = filter.inner_hits.as_mut() {
+            inner_hits
+                .highlights
+                .retain(|highlight| !hidden_fields.contains(&highlight.field));
+        }
+    }
+    Ok(())
+}
+
+/// Removes the hidden fields of a scoped API key from a result document
+fn remove_hidden_fields(doc: &mut Document, hidden_fields: &[String]) {
+    for field in hidden_fields.iter() {
+        doc.remove(field);
+    }
+}
+
+pub(crate) async fn scroll_api(
+    index_arc: &IndexArc,
+    scroll_request: ScrollRequestObject,
+    hidden_fields: &[String],
+) -> Result<ScrollResultObject, String> {
+    let start_time = Instant::now();
+
+    let scroll_result = index_arc
+        .scroll(
+            scroll_request.query_string.to_owned(),
+            scroll_request.query_type_default,
+            scroll_request.length,
+            scroll_request.realtime,
+            scroll_request.field_filter,
+            scroll_request.facet_filter,
+            scroll_request.scroll_id,
+        )
+        .await?;
+
+    let elapsed_time = start_time.elapsed().as_nanos();
+
+    index_arc
+        .read()
+        .await
+        .record_operations(OperationType::Query, 1);
+
+    let return_fields_filter = HashSet::from_iter(scroll_request.fields);
+
+    let mut results: Vec<Document> = Vec::new();
+    let index_ref = index_arc.read().await;
+    if !index_ref.stored_field_names.is_empty() {
+        for doc_id in scroll_result.results.iter() {
+            if let Ok(mut doc) = index_ref.get_document(
+                *doc_id,
+                scroll_request.realtime,
+                &None,
+                &return_fields_filter,
+                &[],
+            ) {
+                remove_hidden_fields(&mut doc, hidden_fields);
+                doc.insert("_id".to_string(), (*doc_id).into());
+                results.push(doc);
+            }
+        }
+    }
+
+    Ok(ScrollResultObject {
+        time: elapsed_time,
+        query: scroll_request.query_string,
+        count: scroll_result.results.len(),
+        count_total: scroll_result.result_count_total,
+        scroll_id: scroll_result.scroll_id,
+        results,
+    })
+}
+
+pub(crate) async fn get_terms_api(
+    index_arc: &IndexArc,
+    field: &str,
+    prefix: &str,
+    offset: usize,
+    length: usize,
+    realtime: bool,
+) -> Result<FieldTerms, String> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_terms(field, prefix, offset, length, realtime)
+}
+
+/// Suggests terms of the index vocabulary starting with a prefix, ranked by document frequency (see Index::suggest_terms)
+pub(crate) async fn suggest_terms_api(
+    index_arc: &IndexArc,
+    prefix: &str,
+    length: usize,
+) -> Vec<TermFrequency> {
+    let index_ref = index_arc.read().await;
+    index_ref.suggest_terms(prefix, length)
+}
+
+/// Distinct values of a String or StringSet facet field with their counts
+#[derive(Deserialize, Serialize, Clone)]
+pub struct FieldValuesResult {
+    pub field: String,
+    pub values: Facet,
+}
+
+/// Returns the distinct values of a String or StringSet facet field with their document counts, from the facet structures.
+/// If the query is empty, the values of all documents of the index are counted, otherwise only those of the documents matching the query.
+pub(crate) async fn get_field_values_api(
+    index_arc: &IndexArc,
+    field: &str,
+    query: String,
+    query_facet: QueryFacet,
+    realtime: bool,
+) -> Result<FieldValuesResult, String> {
+    match index_arc.read().await.schema_map.get(field) {
+        Some(schema_field)
+            if schema_field.field_type == FieldType::Keyword
+                || (schema_field.facet
+                    && (schema_field.field_type == FieldType::String
+                        || schema_field.field_type == FieldType::StringSet)) => {}
+        _ => {
+            return Err(format!(
+                "not a String, Keyword or StringSet facet field: {}",
+                field
+            ))
+        }
+    }
+
+    let mut result_object = index_arc
+        .search(
+            SearchRequest::new(query)
+                .length(1)
+                .result_type(ResultType::Count)
+                .include_uncommited(realtime)
+                .query_facets(vec![query_facet]),
+        )
+        .await;
+
+    Ok(FieldValuesResult {
+        field: field.to_string(),
+        values: result_object.facets.remove(field).unwrap_or_default(),
+    })
+}
+
+pub(crate) async fn get_term_vectors_api(
+    index_arc: &IndexArc,
+    document_id: usize,
+    fields: &[String],
+    realtime: bool,
+) -> Result<Vec<FieldTermVectors>, String> {
+    let index_ref = index_arc.read().await;
+    index_ref.get_term_vectors(document_id, fields, realtime)
+}
+
 pub(crate) async fn get_index_stats_api(
     _index_path: &Path,
     index_id: u64,
@@ -367,6 +1677,7 @@ pub(crate) async fn get_index_stats_api(
 ) -> Result<IndexResponseObject, String> {
     if let Some(index_arc) = index_list.get(&index_id) {
         let index_ref = index_arc.read().await;
+        let usage_counters = index_ref.get_usage_counters();
 
         Ok(IndexResponseObject {
             version: VERSION.to_string(),
@@ -374,28 +1685,269 @@ pub(crate) async fn get_index_stats_api(
             id: index_ref.meta.id,
             name: index_ref.meta.name.clone(),
             indexed_doc_count: index_ref.indexed_doc_count,
-            operations_count: 0,
-            query_count: 0,
+            deleted_doc_count: index_ref.deleted_doc_count(),
+            frozen: index_ref.frozen,
+            durability: index_ref.meta.durability,
+            strict_schema: index_ref.meta.strict_schema,
+            metadata: index_ref.meta.metadata.clone(),
+            operations_count: usage_counters.operations_count(),
+            query_count: usage_counters.query_count,
+            indexing_count: usage_counters.indexing_count,
+            delete_count: usage_counters.delete_count,
             facets_minmax: index_ref.get_index_facets_minmax(),
+            storage: index_ref.get_storage_stats(),
         })
     } else {
         Err("index_id not found".to_string())
     }
 }
 
+/// Stats of all indices of an apikey, in ascending order of index id, and their aggregate
 pub(crate) async fn get_all_index_stats_api(
-    _index_path: &Path,
-    _index_list: &HashMap<u64, IndexArc>,
-) -> Result<Vec<IndexResponseObject>, String> {
-    Err("err".to_string())
+    index_path: &Path,
+    apikey_object: &ApikeyObject,
+) -> AllIndexStatsResponseObject {
+    let mut indices = Vec::new();
+    for index_id in apikey_object.index_list.keys().sorted() {
+        if let Ok(index_stats) =
+            get_index_stats_api(index_path, *index_id, &apikey_object.index_list).await
+        {
+            indices.push(index_stats);
+        }
+    }
+
+    let mut summary = ApikeySummaryObject {
+        index_count: indices.len(),
+        qps: apikey_object
+            .query_rate
+            .lock()
+            .unwrap()
+            .qps(Utc::now().timestamp()),
+        ..Default::default()
+    };
+    for index_stats in indices.iter() {
+        summary.indexed_doc_count += index_stats.indexed_doc_count;
+        summary.deleted_doc_count += index_stats.deleted_doc_count;
+        summary.disk_size += index_stats.storage.disk.total;
+        summary.usage += UsageCounters {
+            indexing_count: index_stats.indexing_count,
+            delete_count: index_stats.delete_count,
+            query_count: index_stats.query_count,
+        };
+    }
+
+    AllIndexStatsResponseObject { summary, indices }
+}
+
+/// Sum of the sizes of all files within a directory and its sub-directories
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Dashboard of an apikey, None if the apikey doesn't exist. The apikey list and each index are only locked to read their counters,
+/// the disk sizes of the index directories are summed afterwards on a blocking thread, without holding any lock.
+pub(crate) async fn get_dashboard_api(
+    index_path: &Path,
+    apikey_hash: u128,
+    apikey_list: &Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
+) -> Option<DashboardResponseObject> {
+    let apikey_list_ref = apikey_list.read().await;
+    let apikey_object = apikey_list_ref.get(&apikey_hash)?;
+    let apikey_path = index_path.join(apikey_object.id.to_string());
+    let index_arcs: Vec<(u64, IndexArc)> = apikey_object
+        .index_list
+        .iter()
+        .sorted_by_key(|(index_id, _)| **index_id)
+        .map(|(index_id, index_arc)| (*index_id, index_arc.clone()))
+        .collect();
+    let mut summary = ApikeySummaryObject {
+        index_count: index_arcs.len(),
+        qps: apikey_object
+            .query_rate
+            .lock()
+            .unwrap()
+            .qps(Utc::now().timestamp()),
+        ..Default::default()
+    };
+    let recent_queries = apikey_object
+        .recent_queries
+        .lock()
+        .unwrap()
+        .iter()
+        .cloned()
+        .collect();
+    drop(apikey_list_ref);
+
+    let mut indices = Vec::new();
+    for (index_id, index_arc) in index_arcs {
+        let index_ref = index_arc.read().await;
+        let usage = index_ref.get_usage_counters();
+        summary.indexed_doc_count += index_ref.indexed_doc_count;
+        summary.deleted_doc_count += index_ref.deleted_doc_count();
+        summary.usage += usage;
+        indices.push(DashboardIndexObject {
+            id: index_id,
+            name: index_ref.meta.name.clone(),
+            indexed_doc_count: index_ref.indexed_doc_count,
+            disk_size: 0,
+            usage,
+        });
+    }
+
+    let index_paths: Vec<PathBuf> = indices
+        .iter()
+        .map(|index| apikey_path.join(index.id.to_string()))
+        .collect();
+    let disk_sizes = tokio::task::spawn_blocking(move || {
+        index_paths
+            .iter()
+            .map(|index_path| dir_size(index_path))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+    for (index, disk_size) in indices.iter_mut().zip(disk_sizes) {
+        index.disk_size = disk_size;
+        summary.disk_size += disk_size;
+    }
+
+    Some(DashboardResponseObject {
+        version: VERSION.to_string(),
+        indices,
+        summary,
+        recent_queries,
+    })
+}
+
+/// Memory of the host and the server process, in bytes. None if not available on the operating system (read from /proc on Linux).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct MemoryInfoObject {
+    pub total: Option<u64>,
+    pub available: Option<u64>,
+    /// Resident set size of the server process
+    pub resident: Option<u64>,
+    /// Estimated resident memory of all open indices, as counted against the memory budget
+    pub indices: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct ServerInfoObject {
+    pub version: String,
+    pub api_versions: Vec<String>,
+    /// Cargo features the server was built with, e.g. zh (Chinese word segmentation)
+    pub features: Vec<String>,
+    /// Unix timestamp of the server start
+    pub started: i64,
+    pub uptime_secs: u64,
+    pub request_limits: RequestLimits,
+    pub apikey_count: usize,
+    pub index_count: usize,
+    pub os: String,
+    pub arch: String,
+    pub cpu_count: usize,
+    pub memory: MemoryInfoObject,
 }
 
+/// Value in bytes of a line "key: value kB" of a /proc file
+fn read_proc_bytes(path: &str, key: &str) -> Option<u64> {
+    fs::read_to_string(path).ok()?.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.strip_prefix(':')?;
+        let kilobytes = value
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kilobytes * 1024)
+    })
+}
+
+/// Server info for fleet inventory: version, build features, uptime, request limits, loaded apikeys and indices, operating system and memory
+pub(crate) fn get_server_info_api(
+    apikey_list: &HashMap<u128, ApikeyObject>,
+    request_limits: RequestLimits,
+) -> ServerInfoObject {
+    let uptime = SERVER_START
+        .get()
+        .map(|server_start| server_start.elapsed().as_secs())
+        .unwrap_or(0);
+
+    let mut features = Vec::new();
+    if cfg!(feature = "zh") {
+        features.push("zh".to_string());
+    }
+
+    ServerInfoObject {
+        version: VERSION.to_string(),
+        api_versions: ApiVersion::ALL
+            .iter()
+            .map(|api_version| api_version.as_str().to_string())
+            .collect(),
+        features,
+        started: Utc::now().timestamp() - uptime as i64,
+        uptime_secs: uptime,
+        request_limits,
+        apikey_count: apikey_list.len(),
+        index_count: apikey_list
+            .values()
+            .map(|apikey_object| apikey_object.index_list.len())
+            .sum(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpu_count: available_parallelism().map(|n| n.get()).unwrap_or(1),
+        memory: MemoryInfoObject {
+            total: read_proc_bytes("/proc/meminfo", "MemTotal"),
+            available: read_proc_bytes("/proc/meminfo", "MemAvailable"),
+            resident: read_proc_bytes("/proc/self/status", "VmRSS"),
+            indices: memory_usage(),
+        },
+    }
+}
+
+/// Checks that the index is writable, and validates the documents with IndexMetaObject.strict_schema.
+/// Returns the errors of all invalid documents, with their position in the request.
+async fn check_strict_schema<'a>(
+    index_arc: &IndexArc,
+    documents: impl IntoIterator<Item = &'a Document>,
+) -> Result<(), SeekStormError> {
+    let index_ref = index_arc.read().await;
+    index_ref.check_writable()?;
+    if !index_ref.meta.strict_schema {
+        return Ok(());
+    }
+    let errors: Vec<String> = documents
+        .into_iter()
+        .enumerate()
+        .filter_map(|(position, document)| {
+            index_ref
+                .validate_document(document)
+                .err()
+                .map(|e| format!("document {}: {}", position, e))
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(SeekStormError::SchemaMismatch(errors.join("; ")))
+    }
+}
+
+/// Documents with unknown fields or values of the wrong type are rejected with IndexMetaObject.strict_schema
 pub(crate) async fn index_document_api(
     index_arc: &IndexArc,
     document: Document,
-) -> Result<usize, String> {
+) -> Result<usize, SeekStormError> {
+    check_strict_schema(index_arc, [&document]).await?;
     index_arc.index_document(document, FileType::None).await;
-    Ok(index_arc.read().await.indexed_doc_count)
+    let index_ref = index_arc.read().await;
+    index_ref.record_operations(OperationType::Indexing, 1);
+    Ok(index_ref.indexed_doc_count)
 }
 
 pub(crate) async fn index_file_api(
@@ -403,13 +1955,18 @@ pub(crate) async fn index_file_api(
     file_path: &Path,
     file_date: i64,
     document: &[u8],
-) -> Result<usize, String> {
+) -> Result<usize, SeekStormError> {
+    index_arc.read().await.check_writable()?;
     match index_arc
         .index_pdf_bytes(file_path, file_date, document)
         .await
     {
-        Ok(_) => Ok(index_arc.read().await.indexed_doc_count),
-        Err(e) => Err(e),
+        Ok(_) => {
+            let index_ref = index_arc.read().await;
+            index_ref.record_operations(OperationType::Indexing, 1);
+            Ok(index_ref.indexed_doc_count)
+        }
+        Err(e) => Err(SeekStormError::InvalidRequest(e)),
     }
 }
 
@@ -424,19 +1981,97 @@ pub(crate) async fn get_file_api(index_arc: &IndexArc, document_id: usize) -> Op
     }
 }
 
+/// Indexes the documents with a result per document, in the order of the request: the document id of the indexed document,
+/// or the error if the document is no JSON object or has invalid field values (see Index::validate_document).
 pub(crate) async fn index_documents_api(
     index_arc: &IndexArc,
-    document_vec: Vec<Document>,
+    value_vec: Vec<serde_json::Value>,
+) -> Result<Vec<DocumentResult>, SeekStormError> {
+    index_arc.read().await.check_writable()?;
+    let mut results = vec![DocumentResult::default(); value_vec.len()];
+    let mut positions = Vec::new();
+    let mut document_vec = Vec::new();
+    for (position, value) in value_vec.into_iter().enumerate() {
+        match serde_json::from_value::<Document>(value) {
+            Ok(document) => {
+                positions.push(position);
+                document_vec.push(document);
+            }
+            Err(e) => results[position] = DocumentResult::error(e.to_string()),
+        }
+    }
+    let document_results = index_arc.index_documents_with_results(document_vec).await;
+    for (position, document_result) in positions.into_iter().zip(document_results) {
+        results[position] = document_result;
+    }
+
+    let indexed_count = results
+        .iter()
+        .filter(|result| result.doc_id.is_some())
+        .count();
+    index_arc
+        .read()
+        .await
+        .record_operations(OperationType::Indexing, indexed_count as u64);
+    Ok(results)
+}
+
+/// Bulk ingest as background task: indexes the documents in batches of TASK_BATCH_SIZE,
+/// updates the progress of the task and checks for its cancellation before each batch.
+/// Documents which are not indexed (see index_documents_api) are added to the document errors of the task.
+pub(crate) async fn index_documents_task_api(
+    index_arc: &IndexArc,
+    value_vec: Vec<serde_json::Value>,
+    task_handle: TaskHandle,
 ) -> Result<usize, String> {
-    index_arc.index_documents(document_vec).await;
+    let total = value_vec.len();
+    let mut done = 0;
+    task_handle.set_progress(done, total);
+    let mut value_iter = value_vec.into_iter();
+    while !task_handle.is_cancelled() {
+        let batch: Vec<serde_json::Value> = value_iter.by_ref().take(TASK_BATCH_SIZE).collect();
+        let batch_len = batch.len();
+        if batch_len > 0 {
+            let results = index_documents_api(index_arc, batch).await?;
+            for (position, result) in results.into_iter().enumerate() {
+                if let Some(error) = result.error {
+                    task_handle.add_document_error(done + position, error);
+                }
+            }
+        }
+        done += batch_len;
+        task_handle.set_progress(done, total);
+        if done == total {
+            break;
+        }
+    }
     Ok(index_arc.read().await.indexed_doc_count)
 }
 
+/// Returns None if the document doesn't exist or doesn't match the security filter of a scoped API key.
+/// Hidden fields of a scoped API key are neither highlighted nor returned.
 pub(crate) async fn get_document_api(
     index_arc: &IndexArc,
     document_id: usize,
-    get_document_request: GetDocumentRequest,
+    mut get_document_request: GetDocumentRequest,
+    scoped_apikey: &Option<ScopedApikeyObject>,
 ) -> Option<Document> {
+    let hidden_fields = if let Some(scoped_apikey) = scoped_apikey {
+        if !index_arc
+            .read()
+            .await
+            .is_facet_filter_match(document_id, &scoped_apikey.facet_filter)
+        {
+            return None;
+        }
+        get_document_request
+            .highlights
+            .retain(|highlight| !scoped_apikey.hidden_fields.contains(&highlight.field));
+        hidden_result_fields(index_arc, &scoped_apikey.hidden_fields).await
+    } else {
+        Vec::new()
+    };
+
     if !index_arc.read().await.stored_field_names.is_empty() {
         let highlighter_option = if get_document_request.highlights.is_empty()
             || get_document_request.query_terms.is_empty()
@@ -444,12 +2079,14 @@ pub(crate) async fn get_document_api(
             None
         } else {
             Some(
-                highlighter(
+                highlighter_with_phrases(
                     index_arc,
                     get_document_request.highlights,
                     get_document_request.query_terms,
+                    get_document_request.query_phrases,
                 )
-                .await,
+                .await
+                .with_output(get_document_request.highlight_output),
             )
         };
 
@@ -460,7 +2097,10 @@ pub(crate) async fn get_document_api(
             &HashSet::from_iter(get_document_request.fields),
             &get_document_request.distance_fields,
         ) {
-            Ok(doc) => Some(doc),
+            Ok(mut doc) => {
+                remove_hidden_fields(&mut doc, &hidden_fields);
+                Some(doc)
+            }
             Err(_e) => None,
         }
     } else {
@@ -471,40 +2111,61 @@ pub(crate) async fn get_document_api(
 pub(crate) async fn update_document_api(
     index_arc: &IndexArc,
     id_document: (u64, Document),
-) -> Result<u64, String> {
+) -> Result<u64, SeekStormError> {
+    check_strict_schema(index_arc, [&id_document.1]).await?;
     index_arc.update_document(id_document).await;
-    Ok(index_arc.read().await.indexed_doc_count as u64)
+    let index_ref = index_arc.read().await;
+    index_ref.record_operations(OperationType::Indexing, 1);
+    Ok(index_ref.indexed_doc_count as u64)
 }
 
+/// With IndexMetaObject.strict_schema, no document is updated if any document is invalid
 pub(crate) async fn update_documents_api(
     index_arc: &IndexArc,
     id_document_vec: Vec<(u64, Document)>,
-) -> Result<u64, String> {
+) -> Result<u64, SeekStormError> {
+    check_strict_schema(
+        index_arc,
+        id_document_vec.iter().map(|(_, document)| document),
+    )
+    .await?;
+    let document_count = id_document_vec.len() as u64;
     index_arc.update_documents(id_document_vec).await;
-    Ok(index_arc.read().await.indexed_doc_count as u64)
+    let index_ref = index_arc.read().await;
+    index_ref.record_operations(OperationType::Indexing, document_count);
+    Ok(index_ref.indexed_doc_count as u64)
 }
 
 pub(crate) async fn delete_document_api(
     index_arc: &IndexArc,
     document_id: u64,
-) -> Result<u64, String> {
+) -> Result<u64, SeekStormError> {
+    index_arc.read().await.check_writable()?;
     index_arc.delete_document(document_id).await;
-    Ok(index_arc.read().await.indexed_doc_count as u64)
+    let index_ref = index_arc.read().await;
+    index_ref.record_operations(OperationType::Delete, 1);
+    Ok(index_ref.indexed_doc_count as u64)
 }
 
 pub(crate) async fn delete_documents_api(
     index_arc: &IndexArc,
     document_id_vec: Vec<u64>,
-) -> Result<u64, String> {
+) -> Result<u64, SeekStormError> {
+    index_arc.read().await.check_writable()?;
+    let document_count = document_id_vec.len() as u64;
     index_arc.delete_documents(document_id_vec).await;
-    Ok(index_arc.read().await.indexed_doc_count as u64)
+    let index_ref = index_arc.read().await;
+    index_ref.record_operations(OperationType::Delete, document_count);
+    Ok(index_ref.indexed_doc_count as u64)
 }
 
+/// Returns the number of documents which matched the query and were deleted
 pub(crate) async fn delete_documents_by_query_api(
     index_arc: &IndexArc,
     search_request: SearchRequestObject,
-) -> Result<u64, String> {
-    index_arc
+) -> Result<u64, SeekStormError> {
+    index_arc.read().await.check_writable()?;
+    let deleted_doc_count = index_arc
         .delete_documents_by_query(
             search_request.query_string.to_owned(),
             search_request.query_type_default,
@@ -517,80 +2178,440 @@ pub(crate) async fn delete_documents_by_query_api(
         )
         .await;
 
-    Ok(index_arc.read().await.indexed_doc_count as u64)
+    index_arc
+        .read()
+        .await
+        .record_operations(OperationType::Delete, deleted_doc_count as u64);
+    Ok(deleted_doc_count as u64)
 }
 
-pub(crate) async fn query_index_api(
+/// Maximum number of document ids returned by a dry run of delete documents by query
+const DRY_RUN_SAMPLE_SIZE: usize = 100;
+
+/// Result of a dry run of delete documents by query: nothing is deleted
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct DeleteByQueryDryRunObject {
+    /// Number of documents which would be deleted
+    pub count: usize,
+    /// Document ids of the first documents which would be deleted, at most DRY_RUN_SAMPLE_SIZE
+    pub doc_ids: Vec<u64>,
+}
+
+/// Previews delete_documents_by_query_api with identical parameters, without deleting any documents
+pub(crate) async fn delete_documents_by_query_dry_run_api(
     index_arc: &IndexArc,
     search_request: SearchRequestObject,
-) -> SearchResultObject {
-    let start_time = Instant::now();
-
-    let result_object = index_arc
-        .search(
+) -> Result<DeleteByQueryDryRunObject, SeekStormError> {
+    index_arc.read().await.check_writable()?;
+    let mut doc_ids = index_arc
+        .delete_documents_by_query_dry_run(
             search_request.query_string.to_owned(),
             search_request.query_type_default,
             search_request.offset,
             search_request.length,
-            search_request.result_type,
             search_request.realtime,
             search_request.field_filter,
-            search_request.query_facets,
             search_request.facet_filter,
             search_request.result_sort,
         )
         .await;
 
-    let elapsed_time = start_time.elapsed().as_nanos();
+    let count = doc_ids.len();
+    doc_ids.truncate(DRY_RUN_SAMPLE_SIZE);
+    Ok(DeleteByQueryDryRunObject { count, doc_ids })
+}
 
-    let return_fields_filter = HashSet::from_iter(search_request.fields);
+/// Returns the number of updated documents and the documents which were not updated
+pub(crate) async fn update_by_query_api(
+    index_arc: &IndexArc,
+    update_request: UpdateByQueryRequestObject,
+) -> Result<UpdateResult, String> {
+    let update_result = index_arc
+        .update_by_query(
+            update_request.query_string,
+            update_request.query_type_default,
+            update_request.offset,
+            update_request.length,
+            update_request.realtime,
+            update_request.field_filter,
+            update_request.facet_filter,
+            update_request.result_sort,
+            &update_request.update,
+        )
+        .await?;
 
-    let mut results: Vec<Document> = Vec::new();
+    index_arc
+        .read()
+        .await
+        .record_operations(OperationType::Indexing, update_result.updated_count as u64);
+    Ok(update_result)
+}
 
-    if !index_arc.read().await.stored_field_names.is_empty() {
-        let highlighter_option = if search_request.highlights.is_empty() {
-            None
-        } else {
-            Some(
-                highlighter(
-                    index_arc,
-                    search_request.highlights,
-                    result_object.query_terms.clone(),
-                )
-                .await,
-            )
+/// Update by query as background task: updates the matching documents in batches of TASK_BATCH_SIZE,
+/// updates the progress of the task and checks for its cancellation before each batch.
+/// Documents which are not updated are added to the document errors of the task, with their position in the matching documents.
+pub(crate) async fn update_by_query_task_api(
+    index_arc: &IndexArc,
+    update_request: UpdateByQueryRequestObject,
+    task_handle: TaskHandle,
+) -> Result<UpdateResult, String> {
+    index_arc.read().await.check_writable()?;
+    let docid_vec = index_arc
+        .delete_documents_by_query_dry_run(
+            update_request.query_string,
+            update_request.query_type_default,
+            update_request.offset,
+            update_request.length,
+            update_request.realtime,
+            update_request.field_filter,
+            update_request.facet_filter,
+            update_request.result_sort,
+        )
+        .await;
+
+    let total = docid_vec.len();
+    let mut done = 0;
+    let mut updated_count = 0;
+    task_handle.set_progress(done, total);
+    for batch in docid_vec.chunks(TASK_BATCH_SIZE) {
+        if task_handle.is_cancelled() {
+            break;
+        }
+        let update_result = index_arc
+            .update_documents_by_id(batch.to_vec(), &update_request.update)
+            .await?;
+        for update_error in update_result.errors {
+            let position = batch
+                .iter()
+                .position(|doc_id| *doc_id == update_error.doc_id)
+                .unwrap_or_default();
+            task_handle.add_document_error(
+                done + position,
+                format!(
+                    "document id {}: {}",
+                    update_error.doc_id, update_error.error
+                ),
+            );
+        }
+        index_arc
+            .read()
+            .await
+            .record_operations(OperationType::Indexing, update_result.updated_count as u64);
+        updated_count += update_result.updated_count;
+        done += batch.len();
+        task_handle.set_progress(done, total);
+    }
+
+    Ok(UpdateResult {
+        updated_count,
+        errors: Vec::new(),
+    })
+}
+
+pub(crate) async fn bulk_api(
+    index_arc: &IndexArc,
+    operations: Vec<BulkOperation>,
+) -> Result<BulkResult, String> {
+    let bulk_result = index_arc.bulk(operations).await?;
+    let index_ref = index_arc.read().await;
+    index_ref.record_operations(
+        OperationType::Indexing,
+        (bulk_result.index_count + bulk_result.update_count) as u64,
+    );
+    index_ref.record_operations(OperationType::Delete, bulk_result.delete_count as u64);
+    Ok(bulk_result)
+}
+
+/// Search of a query request before the documents of the results are fetched (see query_index_api):
+/// the documents can be fetched at once, or in chunks while streaming the response (see stream_search_response).
+pub(crate) struct PreparedSearch {
+    search_request: SearchRequestObject,
+    ranking_variant: Option<RankingVariant>,
+    start_time: Instant,
+    elapsed_time: u128,
+    result_object: ResultObject,
+    inner_hits: Vec<Document>,
+    highlighter_option: Option<Highlighter>,
+    return_fields_filter: HashSet<String>,
+}
+
+/// Searches the index, with the ranking variant of the user applied to the search request, but doesn't fetch the documents of the results yet
+pub(crate) async fn prepare_search(
+    index_arc: &IndexArc,
+    mut search_request: SearchRequestObject,
+) -> PreparedSearch {
+    let start_time = Instant::now();
+
+    let ranking_variant = match search_request.user_id.as_ref() {
+        Some(user_id) => index_arc
+            .read()
+            .await
+            .assign_ranking_variant(user_id)
+            .cloned(),
+        None => None,
+    };
+    if let Some(ranking_variant) = ranking_variant.as_ref() {
+        if ranking_variant.function_score.is_some() {
+            search_request.function_score = ranking_variant.function_score.clone();
+        }
+        if !ranking_variant.result_sort.is_empty() {
+            search_request.result_sort = ranking_variant.result_sort.clone();
+        }
+        if ranking_variant.minimum_should_match.is_some() {
+            search_request.minimum_should_match = ranking_variant.minimum_should_match;
+        }
+        if ranking_variant.rescore.is_some() {
+            search_request.rescore = ranking_variant.rescore.clone();
+        }
+    }
+
+    PreparedSearch::search(index_arc, search_request, ranking_variant, start_time).await
+}
+
+impl PreparedSearch {
+    // the steps of the query lock the index separately: documents indexed in the meantime are excluded by the snapshot of the search,
+    // if the index is compacted or cleared in the meantime, the document ids of the results are no longer current and the query is repeated
+    async fn search(
+        index_arc: &IndexArc,
+        search_request: SearchRequestObject,
+        ranking_variant: Option<RankingVariant>,
+        start_time: Instant,
+    ) -> PreparedSearch {
+        let window_size = search_request.window_size();
+        let (offset, length) = match window_size {
+            Some(window_size) => (
+                0,
+                (search_request.offset + search_request.length).max(window_size),
+            ),
+            None => (search_request.offset, search_request.length),
         };
 
-        for result in result_object.results.iter() {
-            match index_arc.read().await.get_document(
-                result.doc_id,
+        loop {
+            let mut result_object = index_arc
+                .search(SearchRequest {
+                    query_string: search_request.query_string.to_owned(),
+                    query_type_default: search_request.query_type_default.clone(),
+                    offset,
+                    length,
+                    result_type: search_request.result_type.clone(),
+                    include_uncommited: search_request.realtime,
+                    field_filter: search_request.field_filter.clone(),
+                    query_facets: search_request.query_facets.clone(),
+                    facet_filter: search_request.facet_filter.clone(),
+                    result_sort: search_request.result_sort.clone(),
+                    query_language: search_request.query_language,
+                    function_score: search_request.function_score.clone(),
+                    minimum_should_match: search_request.minimum_should_match,
+                    query_expansion: search_request.query_expansion,
+                    timeout_ms: search_request.timeout_ms,
+                })
+                .await;
+
+            if window_size.is_some() {
+                let index_ref = index_arc.read().await;
+                if !index_ref.is_snapshot_current(&result_object.snapshot) {
+                    continue;
+                }
+                if let Some(rescore) = &search_request.rescore {
+                    if let Err(e) = index_ref.rescore_window(
+                        &search_request.query_string,
+                        search_request.query_language,
+                        &mut result_object,
+                        rescore,
+                    ) {
+                        println!("{}", e);
+                    }
+                }
+                if let Some(diversify) = &search_request.diversify {
+                    if let Err(e) = index_ref.diversify_window(&mut result_object, diversify) {
+                        println!("{}", e);
+                    }
+                }
+                drop(index_ref);
+                result_object
+                    .results
+                    .drain(..search_request.offset.min(result_object.results.len()));
+                result_object.results.truncate(search_request.length);
+                result_object.result_count = result_object.results.len();
+            }
+
+            let elapsed_time = start_time.elapsed().as_nanos();
+
+            let doc_ids: Vec<usize> = result_object
+                .results
+                .iter()
+                .map(|result| result.doc_id)
+                .collect();
+            let inner_hits = inner_hits(
+                index_arc,
+                &search_request.facet_filter,
+                &doc_ids,
                 search_request.realtime,
-                &highlighter_option,
-                &return_fields_filter,
-                &search_request.distance_fields,
-            ) {
-                Ok(doc) => {
-                    let mut doc = doc;
-                    doc.insert("_id".to_string(), result.doc_id.into());
-                    doc.insert("_score".to_string(), result.score.into());
+                &result_object.snapshot,
+            )
+            .await;
+
+            let highlighter_option = if search_request.highlights.is_empty() {
+                None
+            } else {
+                Some(
+                    highlighter_with_phrases(
+                        index_arc,
+                        search_request.highlights.clone(),
+                        result_object.query_terms.clone(),
+                        result_object.query_phrases.clone(),
+                    )
+                    .await
+                    .with_output(search_request.highlight_output),
+                )
+            };
 
-                    results.push(doc);
+            let return_fields_filter = HashSet::from_iter(search_request.fields.clone());
+            break PreparedSearch {
+                search_request,
+                ranking_variant,
+                start_time,
+                elapsed_time,
+                result_object,
+                inner_hits,
+                highlighter_option,
+                return_fields_filter,
+            };
+        }
+    }
+
+    /// Repeats the search, if the index was compacted or cleared since the search (see Index::is_snapshot_current)
+    pub(crate) async fn research(self, index_arc: &IndexArc) -> PreparedSearch {
+        PreparedSearch::search(
+            index_arc,
+            self.search_request,
+            self.ranking_variant,
+            self.start_time,
+        )
+        .await
+    }
+
+    /// Number of results of the search, whose documents are fetched by result_documents
+    pub(crate) fn result_count(&self) -> usize {
+        self.result_object.results.len()
+    }
+
+    /// Fetches the documents of a range of the results, with the reserved fields _id, _score and _inner_hits.
+    /// Returns None if the index was compacted or cleared since the search, as the document ids are no longer current.
+    /// Hidden fields of a scoped API key are removed from the documents and inner hits.
+    pub(crate) fn result_documents(
+        &mut self,
+        index_ref: &Index,
+        range: Range<usize>,
+        hidden_fields: &[String],
+    ) -> Option<Vec<Document>> {
+        if !index_ref.is_snapshot_current(&self.result_object.snapshot) {
+            return None;
+        }
+
+        let mut results: Vec<Document> = Vec::new();
+        if !index_ref.stored_field_names.is_empty() {
+            for (result, inner_hits) in self.result_object.results[range.clone()]
+                .iter()
+                .zip(self.inner_hits[range].iter_mut())
+            {
+                match index_ref.get_document(
+                    result.doc_id,
+                    self.search_request.realtime,
+                    &self.highlighter_option,
+                    &self.return_fields_filter,
+                    &self.search_request.distance_fields,
+                ) {
+                    Ok(doc) => {
+                        let mut doc = doc;
+                        remove_hidden_fields(&mut doc, hidden_fields);
+                        doc.insert("_id".to_string(), result.doc_id.into());
+                        doc.insert("_score".to_string(), result.score.into());
+                        if !inner_hits.is_empty() {
+                            for hits in inner_hits.values_mut() {
+                                if let Some(hits) = hits.as_array_mut() {
+                                    for hit in hits.iter_mut().filter_map(|hit| hit.as_object_mut())
+                                    {
+                                        for field in hidden_fields.iter() {
+                                            hit.remove(field);
+                                        }
+                                    }
+                                }
+                            }
+                            doc.insert(
+                                "_inner_hits".to_string(),
+                                serde_json::to_value(std::mem::take(inner_hits))
+                                    .unwrap_or_default(),
+                            );
+                        }
+
+                        results.push(doc);
+                    }
+                    Err(_e) => {}
                 }
-                Err(_e) => {}
             }
         }
+        Some(results)
     }
 
-    SearchResultObject {
-        query: search_request.query_string.to_owned(),
-        time: elapsed_time,
-        offset: search_request.offset,
-        length: search_request.length,
-        count: result_object.results.len(),
-        count_total: result_object.result_count_total as usize,
-        query_terms: result_object.query_terms,
-        results,
-        facets: result_object.facets,
-        suggestions: Vec::new(),
+    /// Search result with the fetched documents, records the query in the usage counters and the ranking variant metrics
+    pub(crate) async fn search_result(
+        &self,
+        index_arc: &IndexArc,
+        results: Vec<Document>,
+    ) -> SearchResultObject {
+        let index_ref = index_arc.read().await;
+        index_ref.record_operations(OperationType::Query, 1);
+        let search_id = index_ref.register_search(&self.search_request.query_string);
+        drop(index_ref);
+
+        if let Some(ranking_variant) = self.ranking_variant.as_ref() {
+            index_arc.read().await.record_ranking_query(
+                &ranking_variant.name,
+                self.result_object.result_count_total,
+                self.elapsed_time,
+            );
+        }
+
+        SearchResultObject {
+            query: self.search_request.query_string.to_owned(),
+            time: self.elapsed_time,
+            offset: self.search_request.offset,
+            length: self.search_request.length,
+            count: self.result_object.results.len(),
+            count_total: self.result_object.result_count_total,
+            query_terms: self.result_object.query_terms.clone(),
+            results,
+            facets: self.result_object.facets.clone(),
+            suggestions: Vec::new(),
+            ranking_variant: self
+                .ranking_variant
+                .as_ref()
+                .map(|ranking_variant| ranking_variant.name.clone()),
+            search_id,
+            partial: self.result_object.partial,
+            downgraded: false,
+        }
+    }
+}
+
+/// Hidden fields of a scoped API key are removed from the result documents, see also hide_fields_search_request
+pub(crate) async fn query_index_api(
+    index_arc: &IndexArc,
+    search_request: SearchRequestObject,
+    hidden_fields: &[String],
+) -> SearchResultObject {
+    let mut prepared_search = prepare_search(index_arc, search_request).await;
+    loop {
+        let index_ref = index_arc.read().await;
+        let result_count = prepared_search.result_count();
+        if let Some(results) =
+            prepared_search.result_documents(&index_ref, 0..result_count, hidden_fields)
+        {
+            drop(index_ref);
+            break prepared_search.search_result(index_arc, results).await;
+        }
+        drop(index_ref);
+        prepared_search = prepared_search.research(index_arc).await;
     }
 }
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use ahash::AHashMap;
+use hyper::{Body, Response, StatusCode};
+use seekstorm::index::{Document, Facet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_endpoints::{ScrollResultObject, SearchResultObject},
+    arrow_results::results_to_arrow_ipc,
+    encoding::{serialize_u128_as_u64, Encoding},
+    http_server::status,
+    search_stream::{ndjson_scroll_response, ndjson_search_response},
+};
+
+/// Version of the REST API, the second segment of the path: /api/{version}/...
+/// All versions share the endpoints and the request formats. Breaking changes of a response format are introduced with a new version,
+/// while the compatibility layer (e.g. search_result_response) keeps returning the previous format for clients of older versions.
+/// Changes of v2:
+/// * Search results (query, alias query, federated query) are returned as `hits` with `id`, `score`, `index` and `inner_hits` separated from the `document`,
+///   instead of `results` documents with the reserved fields `_id`, `_score`, `_index` and `_inner_hits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// Versions of the REST API supported by the server, the oldest first
+    pub(crate) const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    pub(crate) fn parse(version: &str) -> Option<ApiVersion> {
+        ApiVersion::ALL
+            .into_iter()
+            .find(|api_version| api_version.as_str() == version)
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+}
+
+/// Search result of API v2
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct SearchHit {
+    pub id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Index id of the result of a federated or alias query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inner_hits: Option<serde_json::Value>,
+    /// Highlights keyed by field name, if highlight_output is Keyed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<serde_json::Value>,
+    /// Stored fields of the document
+    pub document: Document,
+}
+
+impl From<Document> for SearchHit {
+    fn from(mut document: Document) -> Self {
+        SearchHit {
+            id: document
+                .remove("_id")
+                .and_then(|value| value.as_u64())
+                .unwrap_or_default(),
+            score: document.remove("_score").and_then(|value| value.as_f64()),
+            index: document.remove("_index").and_then(|value| value.as_u64()),
+            inner_hits: document.remove("_inner_hits"),
+            highlight: document.remove("_highlight"),
+            document,
+        }
+    }
+}
+
+/// Search response of API v2: as SearchResultObject of API v1, but with the results as hits
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct SearchResultObjectV2 {
+    #[serde(serialize_with = "serialize_u128_as_u64")]
+    pub time: u128,
+    pub query: String,
+    pub offset: usize,
+    pub length: usize,
+    pub count: usize,
+    pub count_total: usize,
+    pub query_terms: Vec<String>,
+    pub hits: Vec<SearchHit>,
+    pub facets: AHashMap<String, Facet>,
+    pub suggestions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranking_variant: Option<String>,
+    pub search_id: String,
+    pub partial: bool,
+    pub downgraded: bool,
+}
+
+impl From<SearchResultObject> for SearchResultObjectV2 {
+    fn from(search_result: SearchResultObject) -> Self {
+        SearchResultObjectV2 {
+            time: search_result.time,
+            query: search_result.query,
+            offset: search_result.offset,
+            length: search_result.length,
+            count: search_result.count,
+            count_total: search_result.count_total,
+            query_terms: search_result.query_terms,
+            hits: search_result
+                .results
+                .into_iter()
+                .map(SearchHit::from)
+                .collect(),
+            facets: search_result.facets,
+            suggestions: search_result.suggestions,
+            ranking_variant: search_result.ranking_variant,
+            search_id: search_result.search_id,
+            partial: search_result.partial,
+            downgraded: search_result.downgraded,
+        }
+    }
+}
+
+/// Search response in the format of the API version of the request, encoded in the encoding of the request (see Encoding::from_accept).
+/// As Arrow IPC the results are returned as columns in both API versions, with the search metadata in the schema metadata.
+/// As newline-delimited JSON the results are returned as a line per result, after the search result without the results.
+pub(crate) fn search_result_response(
+    search_result: SearchResultObject,
+    api_version: ApiVersion,
+    encoding: Encoding,
+) -> Response<Body> {
+    match api_version {
+        _ if encoding == Encoding::Arrow => {
+            let mut metadata = HashMap::from([
+                ("time".to_string(), search_result.time.to_string()),
+                ("query".to_string(), search_result.query.clone()),
+                ("offset".to_string(), search_result.offset.to_string()),
+                ("length".to_string(), search_result.length.to_string()),
+                ("count".to_string(), search_result.count.to_string()),
+                (
+                    "count_total".to_string(),
+                    search_result.count_total.to_string(),
+                ),
+                ("search_id".to_string(), search_result.search_id.clone()),
+                ("partial".to_string(), search_result.partial.to_string()),
+                (
+                    "downgraded".to_string(),
+                    search_result.downgraded.to_string(),
+                ),
+            ]);
+            if let Some(ranking_variant) = search_result.ranking_variant.as_ref() {
+                metadata.insert("ranking_variant".to_string(), ranking_variant.clone());
+            }
+            match results_to_arrow_ipc(&search_result.results, metadata) {
+                Ok(bytes) => encoding.bytes_response(bytes),
+                Err(e) => status(StatusCode::INTERNAL_SERVER_ERROR, e),
+            }
+        }
+        _ if encoding == Encoding::Ndjson => ndjson_search_response(search_result, api_version),
+        ApiVersion::V1 => encoding.response(&search_result),
+        ApiVersion::V2 => encoding.response(&SearchResultObjectV2::from(search_result)),
+    }
+}
+
+/// Scroll response encoded in the encoding of the request. As Arrow IPC the scroll_id for the next page is in the schema metadata.
+pub(crate) fn scroll_result_response(
+    scroll_result: ScrollResultObject,
+    encoding: Encoding,
+) -> Response<Body> {
+    match encoding {
+        Encoding::Arrow => {}
+        Encoding::Ndjson => return ndjson_scroll_response(scroll_result),
+        _ => return encoding.response(&scroll_result),
+    }
+
+    let mut metadata = HashMap::from([
+        ("time".to_string(), scroll_result.time.to_string()),
+        ("query".to_string(), scroll_result.query.clone()),
+        ("count".to_string(), scroll_result.count.to_string()),
+        (
+            "count_total".to_string(),
+            scroll_result.count_total.to_string(),
+        ),
+    ]);
+    if let Some(scroll_id) = scroll_result.scroll_id.as_ref() {
+        metadata.insert("scroll_id".to_string(), scroll_id.clone());
+    }
+    match results_to_arrow_ipc(&scroll_result.results, metadata) {
+        Ok(bytes) => encoding.bytes_response(bytes),
+        Err(e) => status(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
@@ -0,0 +1,122 @@
+use std::{collections::HashMap, sync::Arc};
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema};
+use seekstorm::index::Document;
+use serde_json::Value;
+
+/// Reserved fields of the results, which become the first columns of the record batch
+const RESERVED_FIELDS: [&str; 4] = ["_id", "_score", "_index", "_inner_hits"];
+
+/// Arrow data type of a column, inferred from the values of the field in the results
+fn column_type(values: &[Option<&Value>]) -> DataType {
+    let mut values = values
+        .iter()
+        .flatten()
+        .filter(|value| !value.is_null())
+        .peekable();
+    if values.peek().is_none() {
+        return DataType::Utf8;
+    }
+
+    let mut data_type: Option<DataType> = None;
+    for value in values {
+        let value_type = match value {
+            Value::Bool(_) => DataType::Boolean,
+            Value::Number(number) if number.is_i64() => DataType::Int64,
+            Value::Number(number) if number.is_u64() => DataType::UInt64,
+            Value::Number(_) => DataType::Float64,
+            _ => DataType::Utf8,
+        };
+        data_type = Some(match (data_type, value_type) {
+            (None, value_type) => value_type,
+            (Some(data_type), value_type) if data_type == value_type => data_type,
+            (
+                Some(DataType::Int64 | DataType::UInt64 | DataType::Float64),
+                DataType::Int64 | DataType::UInt64 | DataType::Float64,
+            ) => DataType::Float64,
+            _ => return DataType::Utf8,
+        });
+    }
+    data_type.unwrap_or(DataType::Utf8)
+}
+
+fn column_array(data_type: &DataType, values: &[Option<&Value>]) -> ArrayRef {
+    let values = values
+        .iter()
+        .map(|value| value.filter(|value| !value.is_null()));
+    match data_type {
+        DataType::Boolean => Arc::new(BooleanArray::from_iter(
+            values.map(|value| value.and_then(Value::as_bool)),
+        )),
+        DataType::Int64 => Arc::new(Int64Array::from_iter(
+            values.map(|value| value.and_then(Value::as_i64)),
+        )),
+        DataType::UInt64 => Arc::new(UInt64Array::from_iter(
+            values.map(|value| value.and_then(Value::as_u64)),
+        )),
+        DataType::Float64 => Arc::new(Float64Array::from_iter(
+            values.map(|value| value.and_then(Value::as_f64)),
+        )),
+        _ => Arc::new(StringArray::from_iter(values.map(|value| {
+            value.map(|value| match value {
+                Value::String(string) => string.clone(),
+                value => value.to_string(),
+            })
+        }))),
+    }
+}
+
+/// Encodes the results of a search or scroll as Arrow IPC stream with a single record batch, e.g. for loading into Polars or pandas without parsing JSON.
+/// Each field of the results becomes a column: the reserved fields `_id`, `_score`, `_index` and `_inner_hits` first, followed by the stored fields
+/// (the requested `fields`) in alphabetical order. Documents without a field have null values.
+/// The column type is inferred from the values of the field: Boolean, Int64, UInt64, Float64 (mixed numbers), otherwise Utf8 (strings, and other values as JSON text).
+/// The metadata of the search (e.g. count_total, scroll_id) is added to the metadata of the Arrow schema.
+pub(crate) fn results_to_arrow_ipc(
+    results: &[Document],
+    metadata: HashMap<String, String>,
+) -> Result<Vec<u8>, String> {
+    let mut field_names: Vec<&str> = Vec::new();
+    for reserved_field in RESERVED_FIELDS {
+        if results
+            .iter()
+            .any(|document| document.contains_key(reserved_field))
+        {
+            field_names.push(reserved_field);
+        }
+    }
+    let mut stored_field_names: Vec<&str> = results
+        .iter()
+        .flat_map(|document| document.keys().map(String::as_str))
+        .filter(|field_name| !RESERVED_FIELDS.contains(field_name))
+        .collect();
+    stored_field_names.sort_unstable();
+    stored_field_names.dedup();
+    field_names.extend(stored_field_names);
+
+    let mut fields = Vec::new();
+    let mut columns = Vec::new();
+    for field_name in field_names {
+        let values: Vec<Option<&Value>> = results
+            .iter()
+            .map(|document| document.get(field_name))
+            .collect();
+        let data_type = column_type(&values);
+        columns.push(column_array(&data_type, &values));
+        fields.push(Field::new(field_name, data_type, true));
+    }
+
+    let schema = Arc::new(Schema::new_with_metadata(fields, metadata));
+    let record_batch = if columns.is_empty() {
+        RecordBatch::new_empty(schema.clone())
+    } else {
+        RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())?
+    };
+
+    let mut writer = StreamWriter::try_new(Vec::new(), &schema).map_err(|e| e.to_string())?;
+    writer.write(&record_batch).map_err(|e| e.to_string())?;
+    writer.into_inner().map_err(|e| e.to_string())
+}
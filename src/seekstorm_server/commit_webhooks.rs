@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use seekstorm::{
+    commit_hook::{CommitEvent, CommitHook},
+    error::SeekStormError,
+    index::{Index, IndexArc},
+};
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+
+use crate::{
+    api_endpoints::save_file_atomically,
+    webhook::{check_webhook_url, post_webhook},
+};
+
+/// Stored in the index directory, so that the webhooks are deleted, moved to the trash and restored together with the index
+pub(crate) const COMMIT_WEBHOOKS_FILENAME: &str = "commit_webhooks.json";
+
+/// Maximum number of commit events of an index queued for delivery. Further events are merged into a single event until the queue is drained,
+/// so that slow or unreachable webhooks can't make the queue grow without limit.
+const COMMIT_EVENT_QUEUE_CAPACITY: usize = 64;
+
+/// Commit hook of the server: the commit events are posted to the webhooks by a delivery task in the order of the commits,
+/// so that the commit is not delayed by the webhooks
+struct CommitWebhooks {
+    sender: Sender<CommitEvent>,
+    /// Events which didn't fit into the full queue, merged into one event, delivered after the queued events
+    overflow: Arc<Mutex<Option<CommitEvent>>>,
+}
+
+impl CommitHook for CommitWebhooks {
+    fn on_commit(&self, event: &CommitEvent) {
+        let mut overflow_event = self.overflow.lock().unwrap();
+        if let Some(overflow_event) = overflow_event.as_mut() {
+            merge_commit_event(overflow_event, event);
+            return;
+        }
+        if let Err(TrySendError::Full(event)) = self.sender.try_send(event.clone()) {
+            *overflow_event = Some(event);
+        }
+    }
+}
+
+/// Merges a later commit event into an earlier one. A document added and deleted again is reported both as added and deleted, as by two separate events.
+fn merge_commit_event(event: &mut CommitEvent, later_event: &CommitEvent) {
+    event.added_doc_ids.extend(&later_event.added_doc_ids);
+    event.updated_doc_ids.extend(&later_event.updated_doc_ids);
+    event.deleted_doc_ids.extend(&later_event.deleted_doc_ids);
+}
+
+/// Posts the queued commit events to the webhooks, followed by the merged overflow event once the queue is drained
+async fn deliver_commit_events(
+    webhook_urls: Vec<String>,
+    mut receiver: Receiver<CommitEvent>,
+    overflow: Arc<Mutex<Option<CommitEvent>>>,
+) {
+    while let Some(mut event) = receiver.recv().await {
+        loop {
+            let body = serde_json::to_string(&event).unwrap();
+            for webhook_url in webhook_urls.iter() {
+                if let Err(e) = post_webhook(webhook_url, body.clone()).await {
+                    println!(
+                        "commit notification not delivered: {} {} {}",
+                        event.index_id, webhook_url, e
+                    );
+                }
+            }
+
+            let mut overflow_event = overflow.lock().unwrap();
+            if !receiver.is_empty() {
+                break;
+            }
+            match overflow_event.take() {
+                Some(overflow_event) => event = overflow_event,
+                None => break,
+            }
+        }
+    }
+}
+
+/// Registers the webhooks as commit hook of the index, or removes the commit hook if there are no webhooks.
+/// Events which can't be delivered within NOTIFICATION_TIMEOUT are dropped.
+fn register_commit_webhooks(index_mut: &mut Index, webhook_urls: Vec<String>) {
+    if webhook_urls.is_empty() {
+        index_mut.set_commit_hook(None);
+        return;
+    }
+
+    let (sender, receiver) = channel::<CommitEvent>(COMMIT_EVENT_QUEUE_CAPACITY);
+    let overflow = Arc::new(Mutex::new(None));
+    tokio::spawn(deliver_commit_events(
+        webhook_urls,
+        receiver,
+        overflow.clone(),
+    ));
+    index_mut.set_commit_hook(Some(Arc::new(CommitWebhooks { sender, overflow })));
+}
+
+/// Loads the commit webhooks of an index from its index directory and registers them, when the index is opened
+pub(crate) fn load_commit_webhooks(index_id_path: &Path, index_mut: &mut Index) {
+    match get_commit_webhooks_api(index_id_path) {
+        Ok(webhook_urls) if !webhook_urls.is_empty() => {
+            register_commit_webhooks(index_mut, webhook_urls);
+        }
+        Ok(_) => {}
+        Err(e) => println!("commit webhooks not loaded: {}", e),
+    }
+}
+
+/// Sets/replaces the webhooks of the index, which are notified after every commit with the added, updated and deleted document ids (CommitEvent).
+/// An empty list removes the webhooks. Returns the number of webhooks.
+pub(crate) async fn set_commit_webhooks_api(
+    index_id_path: &Path,
+    index_arc: &IndexArc,
+    webhook_urls: Vec<String>,
+) -> Result<usize, SeekStormError> {
+    for webhook_url in webhook_urls.iter() {
+        check_webhook_url(webhook_url).map_err(SeekStormError::InvalidRequest)?;
+    }
+
+    let mut index_mut = index_arc.write().await;
+    let commit_webhooks_path = index_id_path.join(COMMIT_WEBHOOKS_FILENAME);
+    if webhook_urls.is_empty() {
+        if commit_webhooks_path.exists() {
+            fs::remove_file(&commit_webhooks_path).map_err(|e| {
+                SeekStormError::Io(format!("{}: {}", commit_webhooks_path.display(), e))
+            })?;
+        }
+    } else {
+        save_file_atomically(
+            &commit_webhooks_path,
+            serde_json::to_vec(&webhook_urls)
+                .map_err(|e| SeekStormError::Internal(e.to_string()))?,
+        )?;
+    }
+
+    let webhooks_count = webhook_urls.len();
+    register_commit_webhooks(&mut index_mut, webhook_urls);
+    Ok(webhooks_count)
+}
+
+/// The commit webhooks of the index. A missing file means no webhooks, a file which can't be read or parsed is an error.
+pub(crate) fn get_commit_webhooks_api(index_id_path: &Path) -> Result<Vec<String>, SeekStormError> {
+    let commit_webhooks_path = index_id_path.join(COMMIT_WEBHOOKS_FILENAME);
+    match fs::read(&commit_webhooks_path) {
+        Ok(commit_webhooks) => serde_json::from_slice(&commit_webhooks).map_err(|e| {
+            SeekStormError::Internal(format!("{}: {}", commit_webhooks_path.display(), e))
+        }),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(SeekStormError::Io(format!(
+            "{}: {}",
+            commit_webhooks_path.display(),
+            e
+        ))),
+    }
+}
@@ -0,0 +1,147 @@
+use hyper::{header, Body, HeaderMap, Response, StatusCode};
+use serde::{de::DeserializeOwned, Serialize, Serializer};
+
+use crate::http_server::status;
+
+/// Encoding of request and response bodies of the document and search endpoints, negotiated per request:
+/// the request body is decoded according to the Content-Type header, the response body is encoded according to the Accept header.
+/// Binary encodings avoid the text conversion of numbers and the escaping of strings of large stored documents.
+/// Missing or other media types are JSON, so that existing clients keep working.
+/// Search and scroll results can additionally be returned as Arrow IPC stream (see results_to_arrow_ipc), or as newline-delimited JSON (see stream_search_response).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Encoding {
+    #[default]
+    Json,
+    /// application/msgpack, application/x-msgpack or application/vnd.msgpack
+    MessagePack,
+    /// application/cbor
+    Cbor,
+    /// application/vnd.apache.arrow.stream: only for the responses of search and scroll, not for request bodies
+    Arrow,
+    /// application/x-ndjson or application/ndjson: search and scroll responses with a line per result, other responses and request bodies as JSON
+    Ndjson,
+}
+
+impl Encoding {
+    fn from_media_type(media_type: &str) -> Option<Encoding> {
+        match media_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "application/json" => Some(Encoding::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Encoding::MessagePack)
+            }
+            "application/cbor" => Some(Encoding::Cbor),
+            "application/vnd.apache.arrow.stream" => Some(Encoding::Arrow),
+            "application/x-ndjson" | "application/ndjson" => Some(Encoding::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// Encoding of the request body, from the Content-Type header
+    pub(crate) fn from_content_type(headers: &HeaderMap) -> Encoding {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Encoding::from_media_type)
+            .unwrap_or_default()
+    }
+
+    /// Encoding of the response body: the first supported media type of the Accept header (quality values are ignored)
+    pub(crate) fn from_accept(headers: &HeaderMap) -> Encoding {
+        headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').find_map(Encoding::from_media_type))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn content_type(&self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::MessagePack => "application/msgpack",
+            Encoding::Cbor => "application/cbor",
+            Encoding::Arrow => "application/vnd.apache.arrow.stream",
+            Encoding::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub(crate) fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Encoding::Json | Encoding::Ndjson => {
+                serde_json::from_slice(bytes).map_err(|e| e.to_string())
+            }
+            Encoding::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            Encoding::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string()),
+            Encoding::Arrow => Err("Arrow IPC request bodies are not supported".to_string()),
+        }
+    }
+
+    pub(crate) fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Encoding::Ndjson => {
+                let mut bytes = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+                bytes.push(b'\n');
+                Ok(bytes)
+            }
+            Encoding::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+            Encoding::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+            Encoding::Arrow => {
+                Err("Arrow IPC is only supported for search and scroll results".to_string())
+            }
+        }
+    }
+
+    /// Response with the encoded value. Responses in JSON have no Content-Type header, as before content negotiation.
+    pub(crate) fn response<T: Serialize>(&self, value: &T) -> Response<Body> {
+        match self.encode(value) {
+            Err(e) if *self == Encoding::Arrow => status(StatusCode::NOT_ACCEPTABLE, e),
+            Ok(bytes) if *self == Encoding::Json => Response::new(bytes.into()),
+            Ok(bytes) => self.bytes_response(bytes),
+            Err(e) => status(StatusCode::INTERNAL_SERVER_ERROR, e),
+        }
+    }
+
+    /// Response with an already encoded body and the Content-Type header of the encoding
+    pub(crate) fn bytes_response(&self, bytes: Vec<u8>) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, self.content_type())
+            .body(bytes.into())
+            .unwrap()
+    }
+}
+
+/// The Content-Type or Accept header has the media type text/plain, e.g. for synonyms in the Solr/Elasticsearch text format instead of JSON
+pub(crate) fn is_text_plain(headers: &HeaderMap, name: header::HeaderName) -> bool {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.split(',').any(|media_type| {
+                media_type
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .eq_ignore_ascii_case("text/plain")
+            })
+        })
+}
+
+/// Serializes a u128 value (e.g. the query time in nanoseconds) as u64, as MessagePack has no 128 bit integers and would encode it as binary
+pub(crate) fn serialize_u128_as_u64<S: Serializer>(
+    value: &u128,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::try_from(*value).unwrap_or(u64::MAX))
+}
@@ -0,0 +1,278 @@
+use std::{cmp::Ordering, collections::HashMap, time::Instant};
+
+use ahash::AHashMap;
+use seekstorm::{
+    error::SeekStormError,
+    index::{Document, Facet, IndexArc},
+    search::{MissingOrder, ResultSort, SortOrder},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api_endpoints::{query_index_api, SearchRequestObject, SearchResultObject};
+
+/// Normalization of the scores of the results of each index, before the results of multiple indices are merged:
+/// raw BM25 scores depend on the term statistics of each index, and are not comparable across indices.
+/// The scores are normalized over the results returned by each index, i.e. the top offset+length results.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+pub(crate) enum ScoreNormalization {
+    /// Raw scores
+    #[default]
+    None,
+    /// (score - min) / (max - min): scores between 0.0 and 1.0, all 1.0 if all scores of an index are equal
+    MinMax,
+    /// (score - mean) / standard deviation: the distance from the mean score of the index in standard deviations, all 0.0 if all scores of an index are equal
+    ZScore,
+}
+
+/// Search request over multiple indices of an API key, whose results are merged into a single ranking
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct FederatedSearchRequest {
+    #[serde(flatten)]
+    pub search_request: SearchRequestObject,
+    /// Ids of the searched indices, empty for all indices of the API key. Ignored by alias queries, which search the indices of the alias.
+    #[serde(default)]
+    pub indices: Vec<u64>,
+    #[serde(default)]
+    pub score_normalization: ScoreNormalization,
+    /// Weights of the normalized scores per index id or index name, e.g. {"products":2.0,"blog":0.5} to prefer the results of a products index over those of a blog index.
+    /// A weight for the index id takes precedence over a weight for the index name. Default: 1.0
+    #[serde(default)]
+    pub index_weights: HashMap<String, f64>,
+}
+
+impl FederatedSearchRequest {
+    /// Checks that the index weights are finite and not negative: a negative weight would invert the ranking of the results of an index
+    pub(crate) fn check_index_weights(&self) -> Result<(), SeekStormError> {
+        match self
+            .index_weights
+            .iter()
+            .find(|(_, weight)| !weight.is_finite() || **weight < 0.0)
+        {
+            Some((index, weight)) => Err(SeekStormError::InvalidRequest(format!(
+                "index weight has to be a finite number >= 0.0: {} is {}",
+                index, weight
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Weight of the normalized scores of an index, by index id or index name (see FederatedSearchRequest.index_weights)
+fn index_weight(index_weights: &HashMap<String, f64>, index_id: u64, index_name: &str) -> f64 {
+    index_weights
+        .get(&index_id.to_string())
+        .or_else(|| index_weights.get(index_name))
+        .copied()
+        .unwrap_or(1.0)
+}
+
+/// Normalizes the _score of the results of an index, then applies the index weight
+fn normalize_scores(
+    results: &mut [Document],
+    score_normalization: ScoreNormalization,
+    weight: f64,
+) {
+    let scores: Vec<f64> = results
+        .iter()
+        .map(|document| {
+            document
+                .get("_score")
+                .and_then(|score| score.as_f64())
+                .unwrap_or_default()
+        })
+        .collect();
+    if scores.is_empty() {
+        return;
+    }
+
+    let normalized: Vec<f64> = match score_normalization {
+        ScoreNormalization::None => scores,
+        ScoreNormalization::MinMax => {
+            let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            scores
+                .iter()
+                .map(|score| {
+                    if max > min {
+                        (score - min) / (max - min)
+                    } else {
+                        1.0
+                    }
+                })
+                .collect()
+        }
+        ScoreNormalization::ZScore => {
+            let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+            let deviation = (scores
+                .iter()
+                .map(|score| (score - mean).powi(2))
+                .sum::<f64>()
+                / scores.len() as f64)
+                .sqrt();
+            scores
+                .iter()
+                .map(|score| {
+                    if deviation > 0.0 {
+                        (score - mean) / deviation
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        }
+    };
+
+    for (document, score) in results.iter_mut().zip(normalized) {
+        document.insert("_score".to_string(), (score * weight).into());
+    }
+}
+
+/// Ordering of a result with a sort field value before a result without, independent of the sort order
+fn missing_order(missing: &MissingOrder) -> Ordering {
+    match missing {
+        MissingOrder::First => Ordering::Greater,
+        MissingOrder::Last => Ordering::Less,
+    }
+}
+
+/// Compares two results by the result_sort fields, with the values of the returned documents, and by _score
+fn compare_results(result_sort: &[ResultSort], a: &Document, b: &Document) -> Ordering {
+    for sort in result_sort.iter() {
+        let ordering = match (a.get(&sort.field), b.get(&sort.field)) {
+            (Some(Value::Number(a)), Some(Value::Number(b))) => a
+                .as_f64()
+                .unwrap_or_default()
+                .total_cmp(&b.as_f64().unwrap_or_default()),
+            (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+            (Some(_), None) => return missing_order(&sort.missing),
+            (None, Some(_)) => return missing_order(&sort.missing).reverse(),
+            _ => Ordering::Equal,
+        };
+        let ordering = match sort.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    let score = |document: &Document| {
+        document
+            .get("_score")
+            .and_then(|score| score.as_f64())
+            .unwrap_or_default()
+    };
+    score(b).total_cmp(&score(a))
+}
+
+/// Searches multiple indices, in the given order, and merges their results: for federated queries and the indices of a rollover alias.
+/// Results are ranked by _score, after the score normalization and index weights, or by the result_sort fields:
+/// with their values in the returned documents, so the sort fields have to be stored.
+/// Each result has the additional field _index with the id of its index, as document ids are unique only within an index.
+/// Counts and facet counts are summed up over all indices, the facet values of all indices are returned in descending order of their counts.
+pub(crate) async fn query_indices_api(
+    index_arcs: &[(u64, IndexArc)],
+    federated_search_request: FederatedSearchRequest,
+    hidden_fields: &[String],
+) -> Result<SearchResultObject, SeekStormError> {
+    let start_time = Instant::now();
+    let search_request = federated_search_request.search_request;
+    for (_index_id, index_arc) in index_arcs.iter() {
+        index_arc
+            .read()
+            .await
+            .check_ranking(
+                &search_request.result_sort,
+                search_request.function_score.as_ref(),
+            )
+            .map_err(SeekStormError::InvalidRequest)?;
+    }
+
+    let mut index_search_request = search_request.clone();
+    index_search_request.offset = 0;
+    index_search_request.length = search_request.offset + search_request.length;
+
+    let mut results: Vec<Document> = Vec::new();
+    let mut count_total = 0;
+    let mut facets: AHashMap<String, AHashMap<String, usize>> = AHashMap::new();
+    let mut query_terms = Vec::new();
+    let mut search_id = String::new();
+    let mut partial = false;
+    for (index_id, index_arc) in index_arcs.iter() {
+        // the timeout applies to the whole query: the remaining time is passed to the search of each index, indices not searched in time are skipped
+        if let Some(timeout_ms) = search_request.timeout_ms {
+            let elapsed_ms = start_time.elapsed().as_millis() as u64;
+            if elapsed_ms >= timeout_ms {
+                partial = true;
+                break;
+            }
+            index_search_request.timeout_ms = Some(timeout_ms - elapsed_ms);
+        }
+        let mut result_object =
+            query_index_api(index_arc, index_search_request.clone(), hidden_fields).await;
+
+        count_total += result_object.count_total;
+        partial |= result_object.partial;
+        let weight = if federated_search_request.index_weights.is_empty() {
+            1.0
+        } else {
+            let index_name = index_arc.read().await.meta.name.clone();
+            index_weight(
+                &federated_search_request.index_weights,
+                *index_id,
+                &index_name,
+            )
+        };
+        normalize_scores(
+            &mut result_object.results,
+            federated_search_request.score_normalization,
+            weight,
+        );
+        for mut document in result_object.results {
+            document.insert("_index".to_string(), (*index_id).into());
+            results.push(document);
+        }
+        for (field, facet) in result_object.facets {
+            let facet_counts = facets.entry(field).or_default();
+            for (value, count) in facet {
+                *facet_counts.entry(value).or_default() += count;
+            }
+        }
+        if search_id.is_empty() {
+            query_terms = result_object.query_terms;
+            search_id = result_object.search_id;
+        }
+    }
+
+    results.sort_by(|a, b| compare_results(&search_request.result_sort, a, b));
+    results.drain(..search_request.offset.min(results.len()));
+    results.truncate(search_request.length);
+
+    let facets = facets
+        .into_iter()
+        .map(|(field, facet_counts)| {
+            let mut facet: Facet = facet_counts.into_iter().collect();
+            facet.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            (field, facet)
+        })
+        .collect();
+
+    Ok(SearchResultObject {
+        query: search_request.query_string,
+        time: start_time.elapsed().as_nanos(),
+        offset: search_request.offset,
+        length: search_request.length,
+        count: results.len(),
+        count_total,
+        query_terms,
+        results,
+        facets,
+        suggestions: Vec::new(),
+        ranking_variant: None,
+        search_id,
+        partial,
+        downgraded: false,
+    })
+}
@@ -1,52 +1,133 @@
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::hash::{Hash, Hasher};
 use std::io;
 use std::io::Write;
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
 use std::process;
 use std::str;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use chrono::Utc;
+use futures::FutureExt;
 use rand::rngs::OsRng;
 use rand::RngCore;
 
-use hyper::body;
+use hyper::body::HttpBody;
 use hyper::server::conn::AddrStream;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::Method;
 use hyper::StatusCode;
 use hyper::{Body, Request, Response, Server};
-use seekstorm::index::{Document, Synonym};
-use seekstorm::search::{QueryType, ResultType};
+use seekstorm::alert::Alert;
+use seekstorm::bulk::BulkOperation;
+use seekstorm::error::SeekStormError;
+use seekstorm::experiment::RankingVariant;
+use seekstorm::feedback::FeedbackEvent;
+use seekstorm::highlighter::HighlightOutput;
+use seekstorm::index::{Document, FacetAlias, FieldType, IndexArc, Synonym};
+use seekstorm::ltr::LtrModel;
+use seekstorm::percolator::PercolatorQuery;
+use seekstorm::query_cost::QueryCost;
+use seekstorm::query_rewrite::QueryRule;
+use seekstorm::runtime::RuntimeField;
+use seekstorm::search::{FacetFilter, FacetSort, QueryFacet, QueryType, ResultType, SearchRequest};
+use seekstorm::synonyms::{format_solr_synonyms, parse_solr_synonyms};
+use seekstorm::validate::DocumentResult;
+use seekstorm::word_lists::WordListType;
+use serde::{Deserialize, Serialize};
 use sha2::Digest;
 use sha2::Sha256;
 use std::{convert::Infallible, net::SocketAddr};
 
 use base64::{engine::general_purpose, Engine as _};
 
+use crate::api_endpoints::get_server_info_api;
 use crate::api_endpoints::index_document_api;
 use crate::api_endpoints::index_documents_api;
-use crate::api_endpoints::query_index_api;
+use crate::api_endpoints::index_documents_task_api;
 use crate::api_endpoints::update_document_api;
 use crate::api_endpoints::update_documents_api;
 use crate::api_endpoints::CreateIndexRequest;
 use crate::api_endpoints::DeleteApikeyRequest;
+use crate::api_endpoints::{add_alerts_api, delete_alerts_api, get_alerts_api};
+use crate::api_endpoints::{add_facet_aliases_api, get_facet_aliases_api, set_facet_aliases_api};
+use crate::api_endpoints::{
+    add_percolator_queries_api, delete_percolator_queries_api, get_percolator_queries_api,
+    percolate_api,
+};
 use crate::api_endpoints::{add_synonyms_api, get_index_stats_api, set_synonyms_api};
-use crate::api_endpoints::{close_index_api, delete_document_api};
-use crate::api_endpoints::{commit_index_api, create_apikey_api};
+use crate::api_endpoints::{bulk_api, close_index_api, delete_document_api};
+use crate::api_endpoints::{commit_index_api, compact_index_api, create_apikey_api};
 use crate::api_endpoints::{create_index_api, SearchRequestObject};
+use crate::api_endpoints::{
+    create_scoped_apikey_api, delete_scoped_apikey_api, CreateScopedApikeyRequest,
+};
 use crate::api_endpoints::{delete_apikey_api, GetDocumentRequest};
-use crate::api_endpoints::{delete_documents_api, delete_documents_by_query_api};
-use crate::api_endpoints::{delete_index_api, get_file_api};
-use crate::api_endpoints::{get_all_index_stats_api, index_file_api};
-use crate::api_endpoints::{get_document_api, get_synonyms_api};
-use crate::multi_tenancy::get_apikey_hash;
+use crate::api_endpoints::{
+    delete_documents_api, delete_documents_by_query_api, delete_documents_by_query_dry_run_api,
+};
+use crate::api_endpoints::{
+    delete_index_api, get_file_api, get_trash_api, purge_index_api, restore_index_api,
+};
+use crate::api_endpoints::{
+    delete_ltr_model_api, get_field_values_api, get_ltr_models_api, get_term_vectors_api,
+    get_terms_api, set_ltr_model_api, suggest_terms_api,
+};
+use crate::api_endpoints::{freeze_index_api, unfreeze_index_api};
+use crate::api_endpoints::{get_all_index_stats_api, get_level_stats_api, index_file_api};
+use crate::api_endpoints::{get_dashboard_api, get_document_api, get_synonyms_api};
+use crate::api_endpoints::{
+    get_index_metadata_api, set_apikey_metadata_api, set_index_metadata_api,
+};
+use crate::api_endpoints::{get_query_analytics_api, record_feedback_events_api};
+use crate::api_endpoints::{get_query_rules_api, set_query_rules_api};
+use crate::api_endpoints::{
+    get_ranking_metrics_api, get_ranking_variants_api, record_ranking_click_api,
+    set_ranking_variants_api, RankingClickRequest,
+};
+use crate::api_endpoints::{get_runtime_fields_api, set_runtime_fields_api};
+use crate::api_endpoints::{get_word_list_api, update_word_list_api};
+use crate::api_endpoints::{
+    hidden_result_fields, hide_fields_search_request, scope_join_filters, scroll_api,
+    visible_field_filter, ScrollRequestObject,
+};
+use crate::api_endpoints::{prepare_search, query_index_api};
+use crate::api_endpoints::{
+    quarantine_invalid_index_api, verify_index_api, VerifyIndexRequest, VerifyIndexResponse,
+};
+use crate::api_endpoints::{suggest_api, SuggestRequest};
+use crate::api_endpoints::{
+    update_by_query_api, update_by_query_task_api, UpdateByQueryRequestObject,
+};
+use crate::api_version::{scroll_result_response, search_result_response, ApiVersion};
+use crate::commit_webhooks::{get_commit_webhooks_api, set_commit_webhooks_api};
+use crate::encoding::{is_text_plain, Encoding};
+use crate::federation::{query_indices_api, FederatedSearchRequest};
+use crate::idempotency::idempotent_request;
+use crate::lifecycle::{delete_lifecycle_policy_api, set_lifecycle_policy_api, LifecyclePolicy};
+use crate::memory_budget::wait_for_memory_budget;
 use crate::multi_tenancy::ApikeyObject;
+use crate::multi_tenancy::{get_apikey_hash, get_scoped_apikey, ScopedApikeyObject};
+use crate::multi_tenancy::{QueryRate, RecentQuery, RECENT_QUERIES_MAX};
+use crate::partition::{alias_search_index_ids, alias_write_indices};
+use crate::rollover::{create_alias_api, delete_alias_api, rollover_api, CreateAliasRequest};
+use crate::search_permits::{is_expensive_search, SearchPermitPools};
+use crate::search_stream::stream_search_response;
+use crate::search_templates::{
+    delete_search_template_api, get_search_template_api, get_search_templates_api,
+    render_search_template, set_search_template_api, TemplateSearchRequest,
+};
+use crate::server::reload_config;
+use crate::tasks::{spawn_task, TaskObject, TaskType};
+use crate::webhook::check_webhook_url;
 use crate::{MASTER_KEY_SECRET, VERSION};
+use tokio::sync::OwnedSemaphorePermit;
 
 const INDEX_HTML: &str = include_str!("web/index.html");
+const DASHBOARD_HTML: &str = include_str!("web/dashboard.html");
+const DASHBOARD_JS: &str = include_str!("web/js/dashboard.js");
 const FLEXBOX_CSS: &str = include_str!("web/css/flexboxgrid.min.css");
 const MASTER_CSS: &str = include_str!("web/css/master.css");
 const MASTER_JS: &str = include_str!("web/js/master.js");
@@ -66,21 +147,504 @@ pub(crate) fn calculate_hash<T: Hash>(t: &T) -> u64 {
     s.finish()
 }
 
+/// Default error code of an error response with the HTTP status code, see error_status
+fn status_error_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "INVALID_REQUEST",
+        StatusCode::UNAUTHORIZED => "UNAUTHORIZED",
+        StatusCode::FORBIDDEN => "FORBIDDEN",
+        StatusCode::NOT_FOUND => "NOT_FOUND",
+        StatusCode::NOT_ACCEPTABLE => "NOT_ACCEPTABLE",
+        StatusCode::REQUEST_TIMEOUT => "REQUEST_TIMEOUT",
+        StatusCode::CONFLICT => "CONFLICT",
+        StatusCode::PAYLOAD_TOO_LARGE | StatusCode::TOO_MANY_REQUESTS => "QUOTA_EXCEEDED",
+        StatusCode::UNPROCESSABLE_ENTITY => "UNPROCESSABLE_ENTITY",
+        StatusCode::NOT_IMPLEMENTED => "NOT_IMPLEMENTED",
+        _ => "INTERNAL_ERROR",
+    }
+}
+
+/// Error response with the default error code of the HTTP status code, see error_status
 pub(crate) fn status(status: StatusCode, error_message: String) -> Response<Body> {
+    error_status(status, status_error_code(status), error_message, None)
+}
+
+/// JSON body of an error response
+#[derive(Serialize)]
+struct ErrorResponseObject<'a> {
+    code: &'a str,
+    message: String,
+    details: Option<serde_json::Value>,
+}
+
+/// Error response with a JSON body: a machine-readable error code, the error message, and optional details (null otherwise):
+/// `{"code":"INDEX_NOT_FOUND","message":"index does not exists","details":null}`
+pub(crate) fn error_status(
+    status: StatusCode,
+    code: &str,
+    message: String,
+    details: Option<serde_json::Value>,
+) -> Response<Body> {
+    let error_object = ErrorResponseObject {
+        code,
+        message,
+        details,
+    };
     Response::builder()
         .status(status)
-        .body(error_message.into())
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(&error_object).unwrap().into())
         .unwrap()
 }
 
+/// Error response of a SeekStormError, with the HTTP status code of the error and its error code
+pub(crate) fn error_response(error: SeekStormError) -> Response<Body> {
+    let status = match error {
+        SeekStormError::NotFound(_) | SeekStormError::IndexNotFound(_) => StatusCode::NOT_FOUND,
+        SeekStormError::InvalidRequest(_)
+        | SeekStormError::SchemaMismatch(_)
+        | SeekStormError::QueryParseError(_) => StatusCode::BAD_REQUEST,
+        SeekStormError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+        SeekStormError::Conflict(_) => StatusCode::CONFLICT,
+        SeekStormError::Io(_) | SeekStormError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error_status(status, error.code(), error.to_string(), None)
+}
+
+/// Response of an API result: the value with the response encoding, or the error response
+pub(crate) fn result_response<T: Serialize>(
+    encoding: Encoding,
+    result: Result<T, SeekStormError>,
+) -> Response<Body> {
+    match result {
+        Ok(value) => encoding.response(&value),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Error response of a missing index
+pub(crate) fn index_not_found() -> Response<Body> {
+    error_response(SeekStormError::IndexNotFound(
+        "index does not exists".to_string(),
+    ))
+}
+
+/// Error response of a query request exceeding max_result_window, with the limit as details
+pub(crate) fn max_result_window_exceeded(
+    message: &str,
+    max_result_window: usize,
+) -> Response<Body> {
+    error_status(
+        StatusCode::BAD_REQUEST,
+        "QUOTA_EXCEEDED",
+        format!(
+            "{} exceeds max_result_window of {}",
+            message, max_result_window
+        ),
+        Some(serde_json::json!({ "max_result_window": max_result_window })),
+    )
+}
+
+/// Error response of a query request exceeding max_query_cost, with the estimated query cost as details
+pub(crate) fn query_cost_exceeded(query_cost: &QueryCost, max_query_cost: u64) -> Response<Body> {
+    error_status(
+        StatusCode::BAD_REQUEST,
+        "QUOTA_EXCEEDED",
+        format!(
+            "estimated query cost of {} exceeds max_query_cost of {}: use a more selective query, fewer query_facets, a smaller offset+length or window_size",
+            query_cost.cost, max_query_cost
+        ),
+        Some(serde_json::json!({ "query_cost": query_cost, "max_query_cost": max_query_cost })),
+    )
+}
+
+/// Estimated cost of a query request, see Index::estimate_query_cost
+async fn estimate_query_cost(
+    index_arc: &IndexArc,
+    search_request: &SearchRequestObject,
+) -> QueryCost {
+    let mut request = SearchRequest::new(search_request.query_string.clone())
+        .query_type_default(search_request.query_type_default.clone())
+        .offset(search_request.offset)
+        .length(search_request.length)
+        .result_type(search_request.result_type.clone())
+        .include_uncommited(search_request.realtime)
+        .query_facets(search_request.query_facets.clone());
+    request.query_language = search_request.query_language;
+    index_arc.read().await.estimate_query_cost(
+        &request,
+        search_request.rescore.as_ref(),
+        search_request.diversify.as_ref(),
+    )
+}
+
+/// Estimated cost of a federated or alias query: the costs of the searched indices are summed up,
+/// facet_count and rescore_document_cost are the largest of the indices.
+async fn estimate_indices_query_cost(
+    index_arcs: &[(u64, IndexArc)],
+    search_request: &SearchRequestObject,
+) -> QueryCost {
+    let mut indices_query_cost = QueryCost::default();
+    for (_index_id, index_arc) in index_arcs.iter() {
+        let query_cost = estimate_query_cost(index_arc, search_request).await;
+        indices_query_cost.matching_documents += query_cost.matching_documents;
+        indices_query_cost.facet_count = indices_query_cost.facet_count.max(query_cost.facet_count);
+        indices_query_cost.result_window += query_cost.result_window;
+        indices_query_cost.rescore_window += query_cost.rescore_window;
+        indices_query_cost.rescore_document_cost = indices_query_cost
+            .rescore_document_cost
+            .max(query_cost.rescore_document_cost);
+        indices_query_cost.cost += query_cost.cost;
+    }
+    indices_query_cost
+}
+
+/// Applies the window_size, max_query_cost, request_timeout_ms and max_concurrent_searches limits of query_index_limited to a federated or alias query over multiple indices.
+/// request_timeout_ms caps the timeout_ms of the whole query, not of each index. The estimated query costs of the indices are summed up.
+/// An expensive search waits for a search permit of each searched index, in the order of the index ids, and holds them until the query is finished.
+/// Returns the search permits and whether the query was downgraded (see downgrade_expensive_queries), or the error response.
+async fn limit_indices_query(
+    index_arcs: &[(u64, IndexArc)],
+    search_request: &mut SearchRequestObject,
+    request_limits: &RequestLimits,
+    search_permits: &SearchPermitPools,
+) -> Result<(Vec<OwnedSemaphorePermit>, bool), Response<Body>> {
+    if let Some(window_size) = search_request.window_size() {
+        if request_limits.exceeds_result_window(window_size) {
+            return Err(max_result_window_exceeded(
+                "window_size",
+                request_limits.max_result_window,
+            ));
+        }
+    }
+    search_request.timeout_ms = request_limits.search_timeout_ms(search_request.timeout_ms);
+
+    let mut downgraded = false;
+    if request_limits.max_query_cost > 0 {
+        let mut query_cost = estimate_indices_query_cost(index_arcs, search_request).await;
+        if query_cost.cost > request_limits.max_query_cost
+            && request_limits.downgrade_expensive_queries
+            && search_request.result_type == ResultType::TopkCount
+        {
+            search_request.result_type = ResultType::Topk;
+            search_request.query_facets.clear();
+            query_cost = estimate_indices_query_cost(index_arcs, search_request).await;
+            downgraded = true;
+        }
+        if query_cost.cost > request_limits.max_query_cost {
+            return Err(query_cost_exceeded(
+                &query_cost,
+                request_limits.max_query_cost,
+            ));
+        }
+    }
+
+    let mut permits = Vec::new();
+    if is_expensive_search(search_request) {
+        let mut index_ids: Vec<u64> = index_arcs.iter().map(|(index_id, _)| *index_id).collect();
+        index_ids.sort_unstable();
+        index_ids.dedup();
+        for index_id in index_ids {
+            match search_permits.acquire(index_id, request_limits).await {
+                Ok(Some(permit)) => permits.push(permit),
+                Ok(None) => {}
+                Err(e) => return Err(error_response(e)),
+            }
+        }
+    }
+
+    Ok((permits, downgraded))
+}
+
+/// Query parameters of a long-running operation: wait_for_completion (default true) and webhook_url.
+/// With wait_for_completion=false the operation is started as background task, and the task is returned immediately with status ACCEPTED, see /api/v1/tasks.
+/// The finished task is posted to the webhook_url, if any.
+fn task_params(req: &Request<Body>) -> Result<(bool, Option<String>), String> {
+    let params: HashMap<String, String> = req
+        .uri()
+        .query()
+        .map(|v| {
+            url::form_urlencoded::parse(v.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let wait_for_completion = params
+        .get("wait_for_completion")
+        .is_none_or(|value| value != "false");
+    let webhook_url = params.get("webhook_url").cloned();
+    if let Some(webhook_url) = webhook_url.as_ref() {
+        check_webhook_url(webhook_url)?;
+    }
+    Ok((wait_for_completion, webhook_url))
+}
+
+fn task_started(task: TaskObject) -> Response<Body> {
+    status(StatusCode::ACCEPTED, serde_json::to_string(&task).unwrap())
+}
+
+/// Limits protecting the server from abusive or accidental heavy requests, and the retention period of deleted indices.
+/// Set via the command line parameters or the config_file: max_request_body_size, request_timeout_ms, max_result_window, trash_retention_hours,
+/// memory_budget, memory_budget_wait_ms, idempotency_window_secs, max_concurrent_searches, max_queued_searches, max_query_cost and downgrade_expensive_queries.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct RequestLimits {
+    /// Maximum size of a request body in bytes.
+    pub max_request_body_size: usize,
+    /// Maximum execution time of a query request in milliseconds, the results collected so far are returned with partial: true. 0 = no timeout.
+    pub request_timeout_ms: u64,
+    /// Maximum result window (offset+length, and the window_size of rescore and diversify) of a query request. 0 = unlimited.
+    pub max_result_window: usize,
+    /// Retention period of deleted indices in the trash in hours, before they are purged. 0 = delete indices immediately.
+    pub trash_retention_hours: u64,
+    /// Memory budget in bytes across all open indices of all API keys (see memory_budget). Indexing requests exceeding it are delayed and then rejected. 0 = unlimited.
+    pub memory_budget: u64,
+    /// Maximum time in milliseconds an indexing request waits for the memory usage to drop below the memory budget, before it is rejected with 429 Too Many Requests.
+    pub memory_budget_wait_ms: u64,
+    /// Period in seconds for which the outcome of a request with Idempotency-Key header is stored and replayed for retries. 0 = the header is ignored.
+    pub idempotency_window_secs: u64,
+    /// Maximum number of expensive searches (see search_permits::is_expensive_search) executed concurrently per index. 0 = unlimited.
+    pub max_concurrent_searches: usize,
+    /// Maximum number of expensive searches per index waiting for max_concurrent_searches, further searches are rejected with 429 Too Many Requests.
+    pub max_queued_searches: usize,
+    /// Maximum estimated cost of a query request (see Index::estimate_query_cost), more expensive queries are rejected with 400 Bad Request. 0 = unlimited.
+    pub max_query_cost: u64,
+    /// Queries exceeding max_query_cost are executed without query facets and total count (ResultType::Topk) instead of being rejected, if that brings them within max_query_cost.
+    pub downgrade_expensive_queries: bool,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            max_request_body_size: 100_000_000,
+            request_timeout_ms: 0,
+            max_result_window: 0,
+            trash_retention_hours: 72,
+            memory_budget: 0,
+            memory_budget_wait_ms: 5_000,
+            idempotency_window_secs: 86_400,
+            max_concurrent_searches: 0,
+            max_queued_searches: 100,
+            max_query_cost: 0,
+            downgrade_expensive_queries: false,
+        }
+    }
+}
+
+impl RequestLimits {
+    /// True if the result window (offset+length) of a query request exceeds max_result_window
+    pub(crate) fn exceeds_result_window(&self, result_window: usize) -> bool {
+        self.max_result_window > 0 && result_window > self.max_result_window
+    }
+
+    /// Timeout of a search: the smaller of the timeout_ms of the search request and request_timeout_ms
+    pub(crate) fn search_timeout_ms(&self, timeout_ms: Option<u64>) -> Option<u64> {
+        match (timeout_ms, self.request_timeout_ms) {
+            (timeout_ms, 0) => timeout_ms,
+            (Some(timeout_ms), request_timeout_ms) => Some(timeout_ms.min(request_timeout_ms)),
+            (None, request_timeout_ms) => Some(request_timeout_ms),
+        }
+    }
+}
+
+/// Reads the request body, aborting as soon as it exceeds max_request_body_size.
+pub(crate) async fn read_body(
+    mut body: Body,
+    max_request_body_size: usize,
+) -> Result<Vec<u8>, Response<Body>> {
+    if body.size_hint().lower() as usize > max_request_body_size {
+        return Err(status(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "request body exceeds max_request_body_size of {} bytes",
+                max_request_body_size
+            ),
+        ));
+    }
+
+    let mut request_bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return Err(status(StatusCode::BAD_REQUEST, e.to_string())),
+        };
+        if request_bytes.len() + chunk.len() > max_request_body_size {
+            return Err(status(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "request body exceeds max_request_body_size of {} bytes",
+                    max_request_body_size
+                ),
+            ));
+        }
+        request_bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(request_bytes)
+}
+
+/// Executes a query request within the max_result_window, max_query_cost, request_timeout_ms and max_concurrent_searches limits.
+/// request_timeout_ms caps the timeout_ms of the search request: on timeout the search stops collecting and returns the results so far with partial: true.
+/// Expensive searches wait for a search permit of the index (see SearchPermitPools), held for the whole search, and for streamed responses until all results are sent.
+/// Successful queries are added to the recent queries and the query rate of the apikey, shown in the dashboard.
+/// The security filter of a scoped apikey is added to the facet filters, it takes precedence over a facet filter of the same field.
+/// The hidden fields of a scoped apikey are removed from the request and the results.
+/// Both also apply to the queries and inner hits of join filters.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn query_index_limited(
+    index_arc: IndexArc,
+    index_id: u64,
+    mut search_request: SearchRequestObject,
+    request_limits: &RequestLimits,
+    recent_queries: &Mutex<VecDeque<RecentQuery>>,
+    query_rate: &Mutex<QueryRate>,
+    search_permits: &SearchPermitPools,
+    scoped_apikey: Option<ScopedApikeyObject>,
+    api_version: ApiVersion,
+    encoding: Encoding,
+) -> Response<Body> {
+    let mut hidden_fields = Vec::new();
+    if let Some(scoped_apikey) = scoped_apikey {
+        if let Err(e) = hide_fields_search_request(
+            &index_arc,
+            &mut search_request,
+            &scoped_apikey.hidden_fields,
+        )
+        .await
+        {
+            return status(StatusCode::FORBIDDEN, format!("hidden fields: {}", e));
+        }
+        if let Err(e) = index_arc
+            .read()
+            .await
+            .check_facet_filter(&scoped_apikey.facet_filter)
+        {
+            return status(StatusCode::FORBIDDEN, format!("security filter: {}", e));
+        }
+        if search_request.facet_filter.iter().any(FacetFilter::is_join) {
+            if let Err(e) =
+                visible_field_filter(&index_arc, Vec::new(), &scoped_apikey.hidden_fields)
+                    .await
+                    .and_then(|visible_fields| {
+                        scope_join_filters(
+                            &mut search_request.facet_filter,
+                            &scoped_apikey.facet_filter,
+                            &visible_fields,
+                            &scoped_apikey.hidden_fields,
+                        )
+                    })
+            {
+                return status(StatusCode::FORBIDDEN, format!("hidden fields: {}", e));
+            }
+        }
+        search_request
+            .facet_filter
+            .extend(scoped_apikey.facet_filter);
+        hidden_fields = hidden_result_fields(&index_arc, &scoped_apikey.hidden_fields).await;
+    }
+
+    if request_limits
+        .exceeds_result_window(search_request.offset.saturating_add(search_request.length))
+    {
+        return max_result_window_exceeded("offset+length", request_limits.max_result_window);
+    }
+    if let Some(window_size) = search_request.window_size() {
+        if request_limits.exceeds_result_window(window_size) {
+            return max_result_window_exceeded("window_size", request_limits.max_result_window);
+        }
+    }
+    search_request.timeout_ms = request_limits.search_timeout_ms(search_request.timeout_ms);
+
+    if let Err(e) = index_arc.read().await.check_ranking(
+        &search_request.result_sort,
+        search_request.function_score.as_ref(),
+    ) {
+        return error_response(SeekStormError::InvalidRequest(e));
+    }
+
+    let mut downgraded = false;
+    if request_limits.max_query_cost > 0 {
+        let mut query_cost = estimate_query_cost(&index_arc, &search_request).await;
+        if query_cost.cost > request_limits.max_query_cost
+            && request_limits.downgrade_expensive_queries
+            && search_request.result_type == ResultType::TopkCount
+        {
+            search_request.result_type = ResultType::Topk;
+            search_request.query_facets.clear();
+            query_cost = estimate_query_cost(&index_arc, &search_request).await;
+            downgraded = true;
+        }
+        if query_cost.cost > request_limits.max_query_cost {
+            return query_cost_exceeded(&query_cost, request_limits.max_query_cost);
+        }
+    }
+
+    let search_permit = if is_expensive_search(&search_request) {
+        match search_permits.acquire(index_id, request_limits).await {
+            Ok(search_permit) => search_permit,
+            Err(e) => return error_response(e),
+        }
+    } else {
+        None
+    };
+
+    // newline-delimited JSON responses are streamed: the documents of the results are fetched while streaming (see stream_search_response)
+    let (mut search_result_local, prepared_search) = if encoding == Encoding::Ndjson {
+        let prepared_search = prepare_search(&index_arc, search_request).await;
+        let search_result = prepared_search.search_result(&index_arc, Vec::new()).await;
+        (search_result, Some(prepared_search))
+    } else {
+        (
+            query_index_api(&index_arc, search_request, &hidden_fields).await,
+            None,
+        )
+    };
+
+    search_result_local.downgraded = downgraded;
+
+    let timestamp = Utc::now().timestamp();
+    query_rate.lock().unwrap().record(timestamp);
+    let mut recent_queries = recent_queries.lock().unwrap();
+    recent_queries.push_front(RecentQuery {
+        index_id,
+        query: search_result_local.query.clone(),
+        count_total: search_result_local.count_total,
+        time: search_result_local.time,
+        timestamp,
+    });
+    recent_queries.truncate(RECENT_QUERIES_MAX);
+    drop(recent_queries);
+
+    match prepared_search {
+        Some(prepared_search) => stream_search_response(
+            index_arc,
+            prepared_search,
+            search_result_local,
+            hidden_fields,
+            api_version,
+            search_permit,
+        ),
+        None => search_result_response(search_result_local, api_version, encoding),
+    }
+}
+
 pub(crate) async fn http_request_handler(
     index_path: PathBuf,
     apikey_list: Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
+    request_limits_arc: Arc<tokio::sync::RwLock<RequestLimits>>,
+    params: Arc<HashMap<String, String>>,
     req: Request<Body>,
     _remote_addr: SocketAddr,
 ) -> Result<Response<Body>, Infallible> {
+    let request_limits = *request_limits_arc.read().await;
     let headers = req.headers();
 
+    let scoped_apikey = if let Some(apikey) = headers.get("apikey") {
+        get_scoped_apikey(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+    } else {
+        None
+    };
+
     let mut parts: [&str; 6] = ["", "", "", "", "", ""];
     let mut i = 0;
     let path = req.uri().path();
@@ -97,6 +661,57 @@ pub(crate) async fn http_request_handler(
         }
     }
 
+    let api_version = if parts[0] == "api" {
+        let Some(api_version) = ApiVersion::parse(parts[1]) else {
+            return Ok(status(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "API version {} not supported, supported versions: {}",
+                    parts[1],
+                    ApiVersion::ALL
+                        .map(|api_version| api_version.as_str())
+                        .join(", ")
+                ),
+            ));
+        };
+        api_version
+    } else {
+        ApiVersion::V1
+    };
+    let request_encoding = Encoding::from_content_type(headers);
+    let response_encoding = Encoding::from_accept(headers);
+
+    if scoped_apikey.is_some()
+        && !matches!(
+            (parts[0], parts[1], parts[2], parts[4], req.method()),
+            ("api", _, "index", "query", &Method::POST | &Method::GET)
+                | ("api", _, "index", "scroll", &Method::POST)
+                | ("api", _, "index", "template", &Method::POST)
+                | ("api", _, "index", "doc", &Method::GET)
+        )
+    {
+        return Ok(status(
+            StatusCode::FORBIDDEN,
+            "scoped api_key: only search and get document are permitted".to_string(),
+        ));
+    }
+
+    if matches!(
+        (parts[0], parts[1], parts[2], parts[4], req.method()),
+        (
+            "api",
+            _,
+            "index",
+            "doc" | "file" | "bulk" | "update_by_query",
+            &Method::POST
+        ) | ("api", _, "index", "doc", &Method::PATCH)
+            | ("api", _, "alias", "doc", &Method::POST)
+    ) {
+        if let Err(e) = wait_for_memory_budget(&request_limits).await {
+            return Ok(status(StatusCode::TOO_MANY_REQUESTS, e));
+        }
+    }
+
     match (
         parts[0],
         parts[1],
@@ -106,41 +721,61 @@ pub(crate) async fn http_request_handler(
         parts[5],
         req.method(),
     ) {
-        ("api", "v1", "index", _, "query", _, &Method::POST) => {
+        ("api", _, "index", _, "query", _, &Method::POST) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
-                    let index_id: u64 = parts[3].parse().unwrap();
+                    let Ok(index_id) = parts[3].parse::<u64>() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
 
                     let apikey_list_ref = apikey_list.read().await;
                     if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
                         if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
                             let index_arc_clone = index_arc.clone();
+                            let recent_queries = apikey_object.recent_queries.clone();
+                            let query_rate = apikey_object.query_rate.clone();
+                            let search_permits = apikey_object.search_permits.clone();
                             drop(apikey_list_ref);
 
-                            let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-
-                            let search_request =
-                                match serde_json::from_slice::<SearchRequestObject>(&request_bytes)
-                                {
-                                    Ok(search_request) => search_request,
-                                    Err(e) => {
-                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
-                                    }
-                                };
+                            let request_bytes = match read_body(
+                                req.into_body(),
+                                request_limits.max_request_body_size,
+                            )
+                            .await
+                            {
+                                Ok(request_bytes) => request_bytes,
+                                Err(response) => return Ok(response),
+                            };
 
-                            let search_result_local =
-                                query_index_api(&index_arc_clone, search_request).await;
+                            let search_request = match request_encoding
+                                .decode::<SearchRequestObject>(&request_bytes)
+                            {
+                                Ok(search_request) => search_request,
+                                Err(e) => {
+                                    return Ok(error_response(SeekStormError::QueryParseError(e)));
+                                }
+                            };
 
-                            let search_result_json =
-                                serde_json::to_string(&search_result_local).unwrap();
-                            Ok(Response::new(search_result_json.into()))
+                            Ok(query_index_limited(
+                                index_arc_clone,
+                                index_id,
+                                search_request,
+                                &request_limits,
+                                &recent_queries,
+                                &query_rate,
+                                &search_permits,
+                                scoped_apikey,
+                                api_version,
+                                response_encoding,
+                            )
+                            .await)
                         } else {
-                            Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            ))
+                            Ok(index_not_found())
                         }
                     } else {
                         Ok(status(
@@ -162,10 +797,148 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "query", _, &Method::GET) => {
+        ("api", _, "index", _, "scroll", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+
+                                let scroll_request = match request_encoding
+                                    .decode::<ScrollRequestObject>(&request_bytes)
+                                {
+                                    Ok(scroll_request) => scroll_request,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e));
+                                    }
+                                };
+
+                                if request_limits.exceeds_result_window(scroll_request.length) {
+                                    return Ok(max_result_window_exceeded(
+                                        "length",
+                                        request_limits.max_result_window,
+                                    ));
+                                }
+
+                                let mut scroll_request = scroll_request;
+                                let mut hidden_fields = Vec::new();
+                                if let Some(scoped_apikey) = scoped_apikey {
+                                    scroll_request.field_filter = match visible_field_filter(
+                                        &index_arc_clone,
+                                        scroll_request.field_filter,
+                                        &scoped_apikey.hidden_fields,
+                                    )
+                                    .await
+                                    {
+                                        Ok(field_filter) => field_filter,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::FORBIDDEN,
+                                                format!("hidden fields: {}", e),
+                                            ));
+                                        }
+                                    };
+                                    scroll_request.facet_filter.retain(|facet_filter| {
+                                        !scoped_apikey
+                                            .hidden_fields
+                                            .iter()
+                                            .any(|field| field == facet_filter.field())
+                                    });
+                                    if let Err(e) = index_arc_clone
+                                        .read()
+                                        .await
+                                        .check_facet_filter(&scoped_apikey.facet_filter)
+                                    {
+                                        return Ok(status(
+                                            StatusCode::FORBIDDEN,
+                                            format!("security filter: {}", e),
+                                        ));
+                                    }
+                                    if scroll_request.facet_filter.iter().any(FacetFilter::is_join)
+                                    {
+                                        if let Err(e) = visible_field_filter(
+                                            &index_arc_clone,
+                                            Vec::new(),
+                                            &scoped_apikey.hidden_fields,
+                                        )
+                                        .await
+                                        .and_then(|visible_fields| {
+                                            scope_join_filters(
+                                                &mut scroll_request.facet_filter,
+                                                &scoped_apikey.facet_filter,
+                                                &visible_fields,
+                                                &scoped_apikey.hidden_fields,
+                                            )
+                                        }) {
+                                            return Ok(status(
+                                                StatusCode::FORBIDDEN,
+                                                format!("hidden fields: {}", e),
+                                            ));
+                                        }
+                                    }
+                                    scroll_request
+                                        .facet_filter
+                                        .extend(scoped_apikey.facet_filter);
+                                    hidden_fields = hidden_result_fields(
+                                        &index_arc_clone,
+                                        &scoped_apikey.hidden_fields,
+                                    )
+                                    .await;
+                                }
+
+                                match scroll_api(&index_arc_clone, scroll_request, &hidden_fields)
+                                    .await
+                                {
+                                    Ok(result) => {
+                                        Ok(scroll_result_response(result, response_encoding))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "query", _, &Method::GET) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let Ok(index_id) = parts[3].parse() else {
                         return Ok(status(
@@ -178,6 +951,9 @@ pub(crate) async fn http_request_handler(
                     if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
                         if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
                             let index_arc_clone = index_arc.clone();
+                            let recent_queries = apikey_object.recent_queries.clone();
+                            let query_rate = apikey_object.query_rate.clone();
+                            let search_permits = apikey_object.search_permits.clone();
                             drop(apikey_list_ref);
 
                             let params: HashMap<String, String> = req
@@ -199,9 +975,10 @@ pub(crate) async fn http_request_handler(
 
                                 let offset = if let Some(value) = params.get("offset") {
                                     let Ok(api_offset) = value.parse::<usize>() else {
-                                        return Ok(status(
-                                            StatusCode::BAD_REQUEST,
-                                            "api_offset invalid or missing".to_string(),
+                                        return Ok(error_response(
+                                            SeekStormError::QueryParseError(
+                                                "api_offset invalid or missing".to_string(),
+                                            ),
                                         ));
                                     };
                                     api_offset
@@ -211,9 +988,10 @@ pub(crate) async fn http_request_handler(
 
                                 let length = if let Some(value) = params.get("length") {
                                     let Ok(api_length) = value.parse::<usize>() else {
-                                        return Ok(status(
-                                            StatusCode::BAD_REQUEST,
-                                            "api_length invalid or missing".to_string(),
+                                        return Ok(error_response(
+                                            SeekStormError::QueryParseError(
+                                                "api_length invalid or missing".to_string(),
+                                            ),
                                         ));
                                     };
                                     api_length
@@ -223,9 +1001,10 @@ pub(crate) async fn http_request_handler(
 
                                 let realtime = if let Some(value) = params.get("realtime") {
                                     let Ok(realtime) = value.parse::<bool>() else {
-                                        return Ok(status(
-                                            StatusCode::BAD_REQUEST,
-                                            "api_length invalid or missing".to_string(),
+                                        return Ok(error_response(
+                                            SeekStormError::QueryParseError(
+                                                "api_length invalid or missing".to_string(),
+                                            ),
                                         ));
                                     };
                                     realtime
@@ -233,6 +1012,35 @@ pub(crate) async fn http_request_handler(
                                     true
                                 };
 
+                                let timeout_ms = if let Some(value) = params.get("timeout_ms") {
+                                    let Ok(timeout_ms) = value.parse::<u64>() else {
+                                        return Ok(error_response(
+                                            SeekStormError::QueryParseError(
+                                                "timeout_ms invalid".to_string(),
+                                            ),
+                                        ));
+                                    };
+                                    Some(timeout_ms)
+                                } else {
+                                    None
+                                };
+
+                                let query_language =
+                                    if let Some(value) = params.get("query_language") {
+                                        let Ok(query_language) =
+                                            serde_json::from_value(serde_json::json!(value))
+                                        else {
+                                            return Ok(error_response(
+                                                SeekStormError::QueryParseError(
+                                                    "query_language invalid".to_string(),
+                                                ),
+                                            ));
+                                        };
+                                        Some(query_language)
+                                    } else {
+                                        None
+                                    };
+
                                 SearchRequestObject {
                                     query_string,
                                     offset,
@@ -240,6 +1048,7 @@ pub(crate) async fn http_request_handler(
                                     result_type: ResultType::default(),
                                     realtime,
                                     highlights: Vec::new(),
+                                    highlight_output: HighlightOutput::Field,
                                     field_filter: Vec::new(),
                                     fields: Vec::new(),
                                     distance_fields: Vec::new(),
@@ -247,27 +1056,43 @@ pub(crate) async fn http_request_handler(
                                     facet_filter: Vec::new(),
                                     result_sort: Vec::new(),
                                     query_type_default: QueryType::Intersection,
+                                    query_language,
+                                    function_score: None,
+                                    minimum_should_match: None,
+                                    query_expansion: Default::default(),
+                                    rescore: None,
+                                    diversify: None,
+                                    user_id: params.get("user_id").cloned(),
+                                    timeout_ms,
                                 }
                             } else {
-                                let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
 
                                 match request_bytes.is_empty() {
                                     true => {
-                                        return Ok(status(
-                                            StatusCode::BAD_REQUEST,
-                                            "no query specified".to_string(),
+                                        return Ok(error_response(
+                                            SeekStormError::QueryParseError(
+                                                "no query specified".to_string(),
+                                            ),
                                         ));
                                     }
                                     false => {
                                         let search_request: SearchRequestObject =
-                                            match serde_json::from_slice::<SearchRequestObject>(
-                                                &request_bytes,
-                                            ) {
+                                            match request_encoding
+                                                .decode::<SearchRequestObject>(&request_bytes)
+                                            {
                                                 Ok(document_object) => document_object,
                                                 Err(e) => {
-                                                    return Ok(status(
-                                                        StatusCode::BAD_REQUEST,
-                                                        e.to_string(),
+                                                    return Ok(error_response(
+                                                        SeekStormError::QueryParseError(e),
                                                     ));
                                                 }
                                             };
@@ -276,17 +1101,21 @@ pub(crate) async fn http_request_handler(
                                 }
                             };
 
-                            let search_result_local =
-                                query_index_api(&index_arc_clone, search_request).await;
-                            let search_result_json =
-                                serde_json::to_string(&search_result_local).unwrap();
-
-                            Ok(Response::new(search_result_json.into()))
+                            Ok(query_index_limited(
+                                index_arc_clone,
+                                index_id,
+                                search_request,
+                                &request_limits,
+                                &recent_queries,
+                                &query_rate,
+                                &search_permits,
+                                scoped_apikey,
+                                api_version,
+                                response_encoding,
+                            )
+                            .await)
                         } else {
-                            Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            ))
+                            Ok(index_not_found())
                         }
                     } else {
                         Ok(status(
@@ -308,12 +1137,20 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", "", _, _, &Method::POST) => {
+        ("api", _, "index", "", _, _, &Method::POST) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
-                    let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
 
                     let create_index_request_object =
                         match serde_json::from_slice::<CreateIndexRequest>(&request_bytes) {
@@ -325,17 +1162,29 @@ pub(crate) async fn http_request_handler(
 
                     let mut apikey_list_mut = apikey_list.write().await;
                     if let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) {
-                        let index_id = create_index_api(
+                        let result = create_index_api(
                             &index_path,
                             create_index_request_object.index_name,
                             create_index_request_object.schema,
                             create_index_request_object.similarity,
                             create_index_request_object.tokenizer,
                             create_index_request_object.synonyms,
+                            create_index_request_object.stemmer,
+                            create_index_request_object.k1,
+                            create_index_request_object.b,
+                            create_index_request_object.compact_deleted_ratio,
+                            create_index_request_object.durability,
+                            create_index_request_object.sync_interval_ms,
+                            create_index_request_object.index_unstemmed,
+                            create_index_request_object.strict_schema,
+                            create_index_request_object.metadata,
                             apikey_object,
                         );
                         drop(apikey_list_mut);
-                        Ok(Response::new(index_id.to_string().into()))
+                        match result {
+                            Ok(index_id) => Ok(Response::new(index_id.to_string().into())),
+                            Err(e) => Ok(error_response(SeekStormError::SchemaMismatch(e))),
+                        }
                     } else {
                         Ok(status(
                             StatusCode::NOT_FOUND,
@@ -356,10 +1205,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "", "", &Method::DELETE) => {
+        ("api", _, "index", _, "", "", &Method::DELETE) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let Ok(index_id) = parts[3].parse() else {
                         return Ok(status(
@@ -370,12 +1219,15 @@ pub(crate) async fn http_request_handler(
 
                     let mut apikey_list_mut = apikey_list.write().await;
                     if let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) {
-                        let Ok(_) = delete_index_api(index_id, &mut apikey_object.index_list).await
-                        else {
-                            return Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index_id does not exists".to_string(),
-                            ));
+                        if let Err(e) = delete_index_api(
+                            &index_path,
+                            index_id,
+                            apikey_object,
+                            request_limits.trash_retention_hours,
+                        )
+                        .await
+                        {
+                            return Ok(error_response(e));
                         };
 
                         let index_count = apikey_object.index_list.len();
@@ -402,10 +1254,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "", "", &Method::PATCH) => {
+        ("api", _, "index", _, "", "", &Method::PATCH) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let Ok(index_id) = parts[3].parse() else {
                         return Ok(status(
@@ -419,14 +1271,14 @@ pub(crate) async fn http_request_handler(
                         if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
                             let index_arc_clone = index_arc.clone();
                             drop(apikey_list_ref);
-                            let result = commit_index_api(&index_arc_clone).await;
-
-                            Ok(Response::new(result.unwrap().to_string().into()))
+                            match commit_index_api(&index_arc_clone).await {
+                                Ok(indexed_doc_count) => {
+                                    Ok(Response::new(indexed_doc_count.to_string().into()))
+                                }
+                                Err(e) => Ok(error_response(e)),
+                            }
                         } else {
-                            Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            ))
+                            Ok(index_not_found())
                         }
                     } else {
                         Ok(status(
@@ -448,10 +1300,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "", "", &Method::PUT) => {
+        ("api", _, "index", _, "", "", &Method::PUT) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let Ok(index_id) = parts[3].parse() else {
                         return Ok(status(
@@ -469,10 +1321,7 @@ pub(crate) async fn http_request_handler(
 
                             Ok(Response::new(result.unwrap().to_string().into()))
                         } else {
-                            Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            ))
+                            Ok(index_not_found())
                         }
                     } else {
                         Ok(status(
@@ -494,15 +1343,19 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", "", "", "", &Method::GET) => {
+        ("api", _, "index", "", "", "", &Method::GET) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let apikey_list_ref = apikey_list.read().await;
-                    let apikey_object = apikey_list_ref.get(&apikey_hash).unwrap();
-                    let status_object =
-                        get_all_index_stats_api(&index_path, &apikey_object.index_list).await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::UNAUTHORIZED,
+                            String::from("api_key does not exists"),
+                        ));
+                    };
+                    let status_object = get_all_index_stats_api(&index_path, apikey_object).await;
                     drop(apikey_list_ref);
                     let status_object_json = serde_json::to_string(&status_object).unwrap();
 
@@ -521,10 +1374,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "", _, &Method::GET) => {
+        ("api", _, "index", _, "", _, &Method::GET) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let Ok(index_id) = parts[3].parse() else {
                         return Ok(status(
@@ -547,10 +1400,7 @@ pub(crate) async fn http_request_handler(
                                     serde_json::to_string(&status_object).unwrap();
                                 Ok(Response::new(status_object_json.into()))
                             }
-                            Err(_e) => Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            )),
+                            Err(_e) => Ok(index_not_found()),
                         }
                     } else {
                         Ok(status(
@@ -572,10 +1422,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "file", _, &Method::POST) => {
+        ("api", _, "index", _, "file", _, &Method::POST) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let Ok(index_id) = parts[3].parse() else {
                         return Ok(status(
@@ -595,31 +1445,61 @@ pub(crate) async fn http_request_handler(
                         .and_then(|file_path| file_path.to_str().ok())
                         .unwrap_or("")
                         .to_string();
-                    let file_path = Path::new(&file_path);
+                    let (wait_for_completion, webhook_url) = match task_params(&req) {
+                        Ok(task_params) => task_params,
+                        Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                    };
 
-                    let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
 
                     let apikey_list_ref = apikey_list.read().await;
 
                     if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
                         if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
                             let index_arc_clone = index_arc.clone();
+                            let tasks = apikey_object.tasks.clone();
                             drop(apikey_list_ref);
 
+                            if !wait_for_completion {
+                                let task = spawn_task(
+                                    &tasks,
+                                    TaskType::IndexFile,
+                                    index_id,
+                                    false,
+                                    webhook_url,
+                                    |_| async move {
+                                        index_file_api(
+                                            &index_arc_clone,
+                                            Path::new(&file_path),
+                                            file_date,
+                                            &request_bytes,
+                                        )
+                                        .await
+                                        .map(|indexed_doc_count| indexed_doc_count.into())
+                                        .map_err(String::from)
+                                    },
+                                );
+                                return Ok(task_started(task));
+                            }
+
                             let status_object = index_file_api(
                                 &index_arc_clone,
-                                file_path,
+                                Path::new(&file_path),
                                 file_date,
                                 &request_bytes,
                             )
                             .await;
-                            let status_object_json = serde_json::to_string(&status_object).unwrap();
-                            Ok(Response::new(status_object_json.into()))
+                            Ok(result_response(Encoding::Json, status_object))
                         } else {
-                            Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            ))
+                            Ok(index_not_found())
                         }
                     } else {
                         Ok(status(
@@ -641,10 +1521,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "synonyms", _, &Method::POST) => {
+        ("api", _, "index", _, "synonyms", _, &Method::POST) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let apikey_list_ref = apikey_list.read().await;
                     if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
@@ -653,37 +1533,46 @@ pub(crate) async fn http_request_handler(
                                 let index_arc_clone = index_arc.clone();
                                 drop(apikey_list_ref);
 
-                                let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-                                let synonyms =
+                                let solr_format =
+                                    is_text_plain(headers, hyper::header::CONTENT_TYPE);
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let synonyms = if solr_format {
+                                    match parse_solr_synonyms(&String::from_utf8_lossy(
+                                        &request_bytes,
+                                    )) {
+                                        Ok(synonyms) => synonyms,
+                                        Err(e) => return Ok(error_response(e)),
+                                    }
+                                } else {
                                     match serde_json::from_slice::<Vec<Synonym>>(&request_bytes) {
-                                        Ok(create_index_request_object) => {
-                                            create_index_request_object
-                                        }
+                                        Ok(synonyms) => synonyms,
                                         Err(e) => {
                                             return Ok(status(
                                                 StatusCode::BAD_REQUEST,
                                                 e.to_string(),
                                             ));
                                         }
-                                    };
+                                    }
+                                };
 
-                                if let Ok(result) =
-                                    add_synonyms_api(&index_arc_clone, synonyms).await
-                                {
-                                    let result_object_json =
-                                        serde_json::to_string(&result).unwrap();
-                                    Ok(Response::new(result_object_json.into()))
-                                } else {
-                                    Ok(status(
-                                        StatusCode::NOT_FOUND,
-                                        "synonyms not found".to_string(),
-                                    ))
+                                match add_synonyms_api(&index_arc_clone, synonyms).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
                                 }
                             } else {
-                                Ok(status(
-                                    StatusCode::NOT_FOUND,
-                                    "index does not exists".to_string(),
-                                ))
+                                Ok(index_not_found())
                             }
                         } else {
                             Ok(status(
@@ -708,10 +1597,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "synonyms", _, &Method::PUT) => {
+        ("api", _, "index", _, "synonyms", _, &Method::PUT) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let apikey_list_ref = apikey_list.read().await;
                     if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
@@ -720,9 +1609,145 @@ pub(crate) async fn http_request_handler(
                                 let index_arc_clone = index_arc.clone();
                                 drop(apikey_list_ref);
 
-                                let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-                                let synonyms =
+                                let solr_format =
+                                    is_text_plain(headers, hyper::header::CONTENT_TYPE);
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let synonyms = if solr_format {
+                                    match parse_solr_synonyms(&String::from_utf8_lossy(
+                                        &request_bytes,
+                                    )) {
+                                        Ok(synonyms) => synonyms,
+                                        Err(e) => return Ok(error_response(e)),
+                                    }
+                                } else {
                                     match serde_json::from_slice::<Vec<Synonym>>(&request_bytes) {
+                                        Ok(synonyms) => synonyms,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    }
+                                };
+
+                                match set_synonyms_api(&index_arc_clone, synonyms).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "synonyms", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                match get_synonyms_api(&index_arc_clone).await {
+                                    Ok(result) if is_text_plain(headers, hyper::header::ACCEPT) => {
+                                        Ok(Response::builder()
+                                            .header(hyper::header::CONTENT_TYPE, "text/plain")
+                                            .body(format_solr_synonyms(&result).into())
+                                            .unwrap())
+                                    }
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "facet_aliases", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let facet_aliases =
+                                    match serde_json::from_slice::<Vec<FacetAlias>>(&request_bytes)
+                                    {
                                         Ok(create_index_request_object) => {
                                             create_index_request_object
                                         }
@@ -734,23 +1759,85 @@ pub(crate) async fn http_request_handler(
                                         }
                                     };
 
-                                if let Ok(result) =
-                                    set_synonyms_api(&index_arc_clone, synonyms).await
+                                match add_facet_aliases_api(&index_arc_clone, facet_aliases).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "facet_aliases", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
                                 {
-                                    let result_object_json =
-                                        serde_json::to_string(&result).unwrap();
-                                    Ok(Response::new(result_object_json.into()))
-                                } else {
-                                    Ok(status(
-                                        StatusCode::NOT_FOUND,
-                                        "synonyms not found".to_string(),
-                                    ))
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let facet_aliases =
+                                    match serde_json::from_slice::<Vec<FacetAlias>>(&request_bytes)
+                                    {
+                                        Ok(create_index_request_object) => {
+                                            create_index_request_object
+                                        }
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                match set_facet_aliases_api(&index_arc_clone, facet_aliases).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
                                 }
                             } else {
-                                Ok(status(
-                                    StatusCode::NOT_FOUND,
-                                    "index does not exists".to_string(),
-                                ))
+                                Ok(index_not_found())
                             }
                         } else {
                             Ok(status(
@@ -775,10 +1862,10 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "synonyms", _, &Method::GET) => {
+        ("api", _, "index", _, "facet_aliases", _, &Method::GET) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
                     let apikey_list_ref = apikey_list.read().await;
                     if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
@@ -786,21 +1873,16 @@ pub(crate) async fn http_request_handler(
                             if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
                                 let index_arc_clone = index_arc.clone();
                                 drop(apikey_list_ref);
-                                if let Ok(result) = get_synonyms_api(&index_arc_clone).await {
-                                    let result_object_json =
-                                        serde_json::to_string(&result).unwrap();
-                                    Ok(Response::new(result_object_json.into()))
-                                } else {
-                                    Ok(status(
-                                        StatusCode::NOT_FOUND,
-                                        "synonyms not found".to_string(),
-                                    ))
+                                match get_facet_aliases_api(&index_arc_clone).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
                                 }
                             } else {
-                                Ok(status(
-                                    StatusCode::NOT_FOUND,
-                                    "index does not exists".to_string(),
-                                ))
+                                Ok(index_not_found())
                             }
                         } else {
                             Ok(status(
@@ -825,120 +1907,4229 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "doc", _, &Method::POST) => {
+        ("api", _, "index", _, "verify", "", &Method::POST) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
-                    let Ok(index_id) = parts[3].parse() else {
+                    let Ok(index_id) = parts[3].parse::<u64>() else {
                         return Ok(status(
                             StatusCode::BAD_REQUEST,
-                            "index_id invalid or missing".to_string(),
+                            "index_id missing".to_string(),
                         ));
                     };
-
-                    let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-                    let request_string = str::from_utf8(&request_bytes).unwrap();
+                    let (wait_for_completion, webhook_url) = match task_params(&req) {
+                        Ok(task_params) => task_params,
+                        Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                    };
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let verify_index_request = if request_bytes.is_empty() {
+                        VerifyIndexRequest::default()
+                    } else {
+                        match serde_json::from_slice::<VerifyIndexRequest>(&request_bytes) {
+                            Ok(verify_index_request) => verify_index_request,
+                            Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e.to_string())),
+                        }
+                    };
 
                     let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ));
+                    };
+                    let Some(index_arc) = apikey_object.index_list.get(&index_id) else {
+                        return Ok(index_not_found());
+                    };
+                    let index_arc_clone = index_arc.clone();
+                    let tasks = apikey_object.tasks.clone();
+                    drop(apikey_list_ref);
 
-                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
-                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
-                            let index_arc_clone = index_arc.clone();
-                            drop(apikey_list_ref);
+                    if !wait_for_completion {
+                        let apikey_list = apikey_list.clone();
+                        let task = spawn_task(
+                            &tasks,
+                            TaskType::Verify,
+                            index_id,
+                            false,
+                            webhook_url,
+                            |_| async move {
+                                let result = verify_index_api(&index_arc_clone).await?;
+                                drop(index_arc_clone);
+                                let quarantined = quarantine_invalid_index_api(
+                                    &index_path,
+                                    &apikey_list,
+                                    apikey_hash,
+                                    index_id,
+                                    &result,
+                                    verify_index_request.quarantine,
+                                )
+                                .await?;
+                                serde_json::to_value(VerifyIndexResponse {
+                                    result,
+                                    quarantined,
+                                })
+                                .map_err(|e| e.to_string())
+                            },
+                        );
+                        return Ok(task_started(task));
+                    }
 
-                            let status_object = if !request_string.trim().starts_with('[') {
-                                let document_object = match serde_json::from_str(request_string) {
-                                    Ok(document_object) => document_object,
-                                    Err(e) => {
-                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
-                                    }
-                                };
+                    let result = match verify_index_api(&index_arc_clone).await {
+                        Ok(result) => result,
+                        Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                    };
+                    drop(index_arc_clone);
 
-                                index_document_api(&index_arc_clone, document_object).await
-                            } else {
-                                let document_object_vec = match serde_json::from_str(request_string)
-                                {
-                                    Ok(document_object_vec) => document_object_vec,
-                                    Err(e) => {
-                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
-                                    }
-                                };
+                    let quarantined = match quarantine_invalid_index_api(
+                        &index_path,
+                        &apikey_list,
+                        apikey_hash,
+                        index_id,
+                        &result,
+                        verify_index_request.quarantine,
+                    )
+                    .await
+                    {
+                        Ok(quarantined) => quarantined,
+                        Err(e) => return Ok(status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+                    };
 
-                                index_documents_api(&index_arc_clone, document_object_vec).await
-                            };
-                            let status_object_json = serde_json::to_string(&status_object).unwrap();
-                            Ok(Response::new(status_object_json.into()))
+                    let verify_index_response = VerifyIndexResponse {
+                        result,
+                        quarantined,
+                    };
+                    Ok(Response::new(
+                        serde_json::to_string(&verify_index_response)
+                            .unwrap()
+                            .into(),
+                    ))
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "compact", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                let tasks = apikey_object.tasks.clone();
+                                drop(apikey_list_ref);
+                                let (wait_for_completion, webhook_url) = match task_params(&req) {
+                                    Ok(task_params) => task_params,
+                                    Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                                };
+                                if !wait_for_completion {
+                                    let task = spawn_task(
+                                        &tasks,
+                                        TaskType::Compact,
+                                        index_id,
+                                        false,
+                                        webhook_url,
+                                        |_| async move {
+                                            let result =
+                                                compact_index_api(&index_arc_clone).await?;
+                                            serde_json::to_value(result).map_err(|e| e.to_string())
+                                        },
+                                    );
+                                    return Ok(task_started(task));
+                                }
+                                match compact_index_api(&index_arc_clone).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
                         } else {
                             Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
                             ))
                         }
                     } else {
                         Ok(status(
                             StatusCode::NOT_FOUND,
-                            "api_key does not exists".to_string(),
+                            "api_key not found".to_string(),
                         ))
                     }
                 } else {
                     Ok(status(
-                        StatusCode::UNAUTHORIZED,
-                        String::from("api_key does not exists"),
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
                     ))
                 }
             } else {
-                Ok(status(
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "update_by_query", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(index_id) = parts[3].parse() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
+
+                    let (wait_for_completion, webhook_url) = match task_params(&req) {
+                        Ok(task_params) => task_params,
+                        Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                    };
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let update_request = match serde_json::from_slice::<UpdateByQueryRequestObject>(
+                        &request_bytes,
+                    ) {
+                        Ok(update_request) => update_request,
+                        Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e.to_string())),
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                            let index_arc_clone = index_arc.clone();
+                            let tasks = apikey_object.tasks.clone();
+                            drop(apikey_list_ref);
+
+                            if !wait_for_completion {
+                                let task = spawn_task(
+                                    &tasks,
+                                    TaskType::UpdateByQuery,
+                                    index_id,
+                                    true,
+                                    webhook_url,
+                                    |task_handle| async move {
+                                        let result = update_by_query_task_api(
+                                            &index_arc_clone,
+                                            update_request,
+                                            task_handle,
+                                        )
+                                        .await?;
+                                        serde_json::to_value(result).map_err(|e| e.to_string())
+                                    },
+                                );
+                                return Ok(task_started(task));
+                            }
+
+                            match update_by_query_api(&index_arc_clone, update_request).await {
+                                Ok(result) => {
+                                    let result_object_json =
+                                        serde_json::to_string(&result).unwrap();
+                                    Ok(Response::new(result_object_json.into()))
+                                }
+                                Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                            }
+                        } else {
+                            Ok(index_not_found())
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "index", _, "freeze" | "unfreeze", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = if parts[4] == "freeze" {
+                                    freeze_index_api(&index_arc_clone).await
+                                } else {
+                                    unfreeze_index_api(&index_arc_clone).await
+                                };
+                                match result {
+                                    Ok(indexed_doc_count) => {
+                                        Ok(Response::new(indexed_doc_count.to_string().into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "query_rules", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let query_rules = match serde_json::from_slice::<Vec<QueryRule>>(
+                                    &request_bytes,
+                                ) {
+                                    Ok(query_rules) => query_rules,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match set_query_rules_api(&index_arc_clone, query_rules).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "query_rules", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_query_rules_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "runtime_fields", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let runtime_fields = match serde_json::from_slice::<Vec<RuntimeField>>(
+                                    &request_bytes,
+                                ) {
+                                    Ok(runtime_fields) => runtime_fields,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match set_runtime_fields_api(&index_arc_clone, runtime_fields).await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "runtime_fields", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_runtime_fields_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "ranking_variants", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let ranking_variants = match serde_json::from_slice::<
+                                    Vec<RankingVariant>,
+                                >(
+                                    &request_bytes
+                                ) {
+                                    Ok(ranking_variants) => ranking_variants,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match set_ranking_variants_api(&index_arc_clone, ranking_variants)
+                                    .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "ranking_variants", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_ranking_variants_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "ranking_metrics", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_ranking_metrics_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "ranking_click", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let ranking_click_request = match serde_json::from_slice::<
+                                    RankingClickRequest,
+                                >(
+                                    &request_bytes
+                                ) {
+                                    Ok(ranking_click_request) => ranking_click_request,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match record_ranking_click_api(
+                                    &index_arc_clone,
+                                    ranking_click_request,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "percolator", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let percolator_queries = match serde_json::from_slice::<
+                                    Vec<PercolatorQuery>,
+                                >(
+                                    &request_bytes
+                                ) {
+                                    Ok(percolator_queries) => percolator_queries,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match add_percolator_queries_api(
+                                    &index_arc_clone,
+                                    percolator_queries,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "percolator", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_percolator_queries_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "percolator", _, &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let ids =
+                                    match serde_json::from_slice::<Vec<String>>(&request_bytes) {
+                                        Ok(ids) => ids,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                match delete_percolator_queries_api(&index_arc_clone, ids).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "alerts", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let alerts =
+                                    match serde_json::from_slice::<Vec<Alert>>(&request_bytes) {
+                                        Ok(alerts) => alerts,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                match add_alerts_api(&index_arc_clone, alerts).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "alerts", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_alerts_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "alerts", _, &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let ids =
+                                    match serde_json::from_slice::<Vec<String>>(&request_bytes) {
+                                        Ok(ids) => ids,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                match delete_alerts_api(&index_arc_clone, ids).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "commit_webhooks", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                let index_id_path = index_path
+                                    .join(apikey_object.id.to_string())
+                                    .join(index_id.to_string());
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let webhook_urls =
+                                    match serde_json::from_slice::<Vec<String>>(&request_bytes) {
+                                        Ok(webhook_urls) => webhook_urls,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                match set_commit_webhooks_api(
+                                    &index_id_path,
+                                    &index_arc_clone,
+                                    webhook_urls,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "commit_webhooks", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if apikey_object.index_list.contains_key(&index_id) {
+                                let index_id_path = index_path
+                                    .join(apikey_object.id.to_string())
+                                    .join(index_id.to_string());
+                                drop(apikey_list_ref);
+                                match get_commit_webhooks_api(&index_id_path) {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "commit_webhooks", _, &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                let index_id_path = index_path
+                                    .join(apikey_object.id.to_string())
+                                    .join(index_id.to_string());
+                                drop(apikey_list_ref);
+                                match set_commit_webhooks_api(
+                                    &index_id_path,
+                                    &index_arc_clone,
+                                    Vec::new(),
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "template", _, &Method::POST) => {
+            let is_template_search =
+                path.split('/').filter(|part| !part.is_empty()).nth(6) == Some("search");
+            if !is_template_search || parts[5].is_empty() {
+                return Ok(status(
+                    StatusCode::NOT_FOUND,
+                    "template search: POST /api/v1/index/{index_id}/template/{name}/search"
+                        .to_string(),
+                ));
+            }
+
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(index_id) = parts[3].parse::<u64>() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                            let index_arc_clone = index_arc.clone();
+                            let index_id_path = index_path
+                                .join(apikey_object.id.to_string())
+                                .join(index_id.to_string());
+                            let recent_queries = apikey_object.recent_queries.clone();
+                            let query_rate = apikey_object.query_rate.clone();
+                            let search_permits = apikey_object.search_permits.clone();
+                            drop(apikey_list_ref);
+
+                            let template = match get_search_template_api(&index_id_path, parts[5]) {
+                                Ok(template) => template,
+                                Err(e) => return Ok(error_response(e)),
+                            };
+
+                            let request_bytes = match read_body(
+                                req.into_body(),
+                                request_limits.max_request_body_size,
+                            )
+                            .await
+                            {
+                                Ok(request_bytes) => request_bytes,
+                                Err(response) => return Ok(response),
+                            };
+
+                            let template_search_request = if request_bytes.is_empty() {
+                                TemplateSearchRequest::default()
+                            } else {
+                                match request_encoding
+                                    .decode::<TemplateSearchRequest>(&request_bytes)
+                                {
+                                    Ok(template_search_request) => template_search_request,
+                                    Err(e) => {
+                                        return Ok(error_response(
+                                            SeekStormError::QueryParseError(e),
+                                        ));
+                                    }
+                                }
+                            };
+
+                            let search_request = match render_search_template(
+                                &template,
+                                &template_search_request.params,
+                            ) {
+                                Ok(search_request) => search_request,
+                                Err(e) => {
+                                    return Ok(error_response(e));
+                                }
+                            };
+
+                            Ok(query_index_limited(
+                                index_arc_clone,
+                                index_id,
+                                search_request,
+                                &request_limits,
+                                &recent_queries,
+                                &query_rate,
+                                &search_permits,
+                                scoped_apikey,
+                                api_version,
+                                response_encoding,
+                            )
+                            .await)
+                        } else {
+                            Ok(index_not_found())
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "index", _, "template", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                let index_id_path = index_path
+                                    .join(apikey_object.id.to_string())
+                                    .join(index_id.to_string());
+                                let template_name = parts[5].to_string();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let template = match serde_json::from_slice::<serde_json::Value>(
+                                    &request_bytes,
+                                ) {
+                                    Ok(template) => template,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match set_search_template_api(
+                                    &index_id_path,
+                                    &index_arc_clone,
+                                    &template_name,
+                                    template,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "template", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if apikey_object.index_list.contains_key(&index_id) {
+                                let index_id_path = index_path
+                                    .join(apikey_object.id.to_string())
+                                    .join(index_id.to_string());
+                                drop(apikey_list_ref);
+                                let result_object_json = if parts[5].is_empty() {
+                                    get_search_templates_api(&index_id_path)
+                                        .map(|templates| serde_json::to_string(&templates).unwrap())
+                                } else {
+                                    get_search_template_api(&index_id_path, parts[5])
+                                        .map(|template| serde_json::to_string(&template).unwrap())
+                                };
+                                match result_object_json {
+                                    Ok(result_object_json) => {
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "template", _, &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                let index_id_path = index_path
+                                    .join(apikey_object.id.to_string())
+                                    .join(index_id.to_string());
+                                drop(apikey_list_ref);
+                                match delete_search_template_api(
+                                    &index_id_path,
+                                    &index_arc_clone,
+                                    parts[5],
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "stopwords" | "protected_words", _, method) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let list_type = if parts[4] == "stopwords" {
+                        WordListType::Stopwords
+                    } else {
+                        WordListType::ProtectedWords
+                    };
+                    let params: HashMap<String, String> = req
+                        .uri()
+                        .query()
+                        .map(|v| {
+                            url::form_urlencoded::parse(v.as_bytes())
+                                .into_owned()
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    let Ok(expected_version) = params
+                        .get("version")
+                        .map(|value| value.parse::<u64>())
+                        .transpose()
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "version invalid".to_string(),
+                        ));
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                if method == Method::GET {
+                                    let result =
+                                        get_word_list_api(&index_arc_clone, list_type).await;
+                                    let result_object_json =
+                                        serde_json::to_string(&result).unwrap();
+                                    return Ok(Response::new(result_object_json.into()));
+                                }
+
+                                let method = method.clone();
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let words =
+                                    match serde_json::from_slice::<Vec<String>>(&request_bytes) {
+                                        Ok(words) => words,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                match update_word_list_api(
+                                    &index_arc_clone,
+                                    list_type,
+                                    &method,
+                                    words,
+                                    expected_version,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "metadata", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let metadata = match serde_json::from_slice::<serde_json::Value>(
+                                    &request_bytes,
+                                ) {
+                                    Ok(metadata) => metadata,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match set_index_metadata_api(&index_arc_clone, metadata).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(error_response(e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "metadata", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_index_metadata_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "analytics", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let params: HashMap<String, String> = req
+                                    .uri()
+                                    .query()
+                                    .map(|v| {
+                                        url::form_urlencoded::parse(v.as_bytes())
+                                            .into_owned()
+                                            .collect()
+                                    })
+                                    .unwrap_or_default();
+                                let length = match params.get("length") {
+                                    Some(value) => match value.parse::<usize>() {
+                                        Ok(length) => length,
+                                        Err(_) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                "length invalid".to_string(),
+                                            ));
+                                        }
+                                    },
+                                    None => 100,
+                                };
+                                let result =
+                                    get_query_analytics_api(&index_arc_clone, length).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "events", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let Ok(request_string) = str::from_utf8(&request_bytes) else {
+                                    return Ok(status(
+                                        StatusCode::BAD_REQUEST,
+                                        "request body is not valid UTF-8".to_string(),
+                                    ));
+                                };
+                                let is_array = request_string.trim().starts_with('[');
+                                let feedback_events = if is_array {
+                                    serde_json::from_str::<Vec<FeedbackEvent>>(request_string)
+                                } else {
+                                    serde_json::from_str::<FeedbackEvent>(request_string)
+                                        .map(|feedback_event| vec![feedback_event])
+                                };
+                                let feedback_events = match feedback_events {
+                                    Ok(feedback_events) => feedback_events,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match record_feedback_events_api(&index_arc_clone, feedback_events)
+                                    .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "suggest", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let suggest_request = match serde_json::from_slice::<SuggestRequest>(
+                                    &request_bytes,
+                                ) {
+                                    Ok(suggest_request) => suggest_request,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match suggest_api(&index_arc_clone, suggest_request).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "levels", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_level_stats_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "percolate", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let Ok(request_string) = str::from_utf8(&request_bytes) else {
+                                    return Ok(status(
+                                        StatusCode::BAD_REQUEST,
+                                        "request body is not valid UTF-8".to_string(),
+                                    ));
+                                };
+                                let is_array = request_string.trim().starts_with('[');
+                                let documents = if is_array {
+                                    serde_json::from_str::<Vec<Document>>(request_string)
+                                } else {
+                                    serde_json::from_str::<Document>(request_string)
+                                        .map(|document| vec![document])
+                                };
+                                let documents = match documents {
+                                    Ok(documents) => documents,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match percolate_api(&index_arc_clone, documents).await {
+                                    Ok(mut result) => {
+                                        let result_object_json = if is_array {
+                                            serde_json::to_string(&result).unwrap()
+                                        } else {
+                                            serde_json::to_string(&result.remove(0)).unwrap()
+                                        };
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "ltr_model", _, &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                if parts[5].is_empty() {
+                                    return Ok(status(
+                                        StatusCode::BAD_REQUEST,
+                                        "model name missing".to_string(),
+                                    ));
+                                }
+                                let name = parts[5].to_string();
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let ltr_model =
+                                    match serde_json::from_slice::<LtrModel>(&request_bytes) {
+                                        Ok(ltr_model) => ltr_model,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                match set_ltr_model_api(&index_arc_clone, &name, ltr_model).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "ltr_model", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result = get_ltr_models_api(&index_arc_clone).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "ltr_model", _, &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                if let Ok(result) =
+                                    delete_ltr_model_api(&index_arc_clone, parts[5]).await
+                                {
+                                    let result_object_json =
+                                        serde_json::to_string(&result).unwrap();
+                                    Ok(Response::new(result_object_json.into()))
+                                } else {
+                                    Ok(status(
+                                        StatusCode::NOT_FOUND,
+                                        "ltr model not found".to_string(),
+                                    ))
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "terms", "suggest", &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let params: HashMap<String, String> = req
+                        .uri()
+                        .query()
+                        .map(|v| {
+                            url::form_urlencoded::parse(v.as_bytes())
+                                .into_owned()
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    let prefix = params.get("prefix").cloned().unwrap_or_default();
+                    let Ok(length) = params
+                        .get("length")
+                        .map_or(Ok(10), |value| value.parse::<usize>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "length invalid".to_string(),
+                        ));
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                let result =
+                                    suggest_terms_api(&index_arc_clone, &prefix, length).await;
+                                let result_object_json = serde_json::to_string(&result).unwrap();
+                                Ok(Response::new(result_object_json.into()))
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "terms", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let params: HashMap<String, String> = req
+                        .uri()
+                        .query()
+                        .map(|v| {
+                            url::form_urlencoded::parse(v.as_bytes())
+                                .into_owned()
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    let prefix = params.get("prefix").cloned().unwrap_or_default();
+                    let Ok(offset) = params
+                        .get("offset")
+                        .map_or(Ok(0), |value| value.parse::<usize>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "offset invalid".to_string(),
+                        ));
+                    };
+                    let Ok(length) = params
+                        .get("length")
+                        .map_or(Ok(100), |value| value.parse::<usize>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "length invalid".to_string(),
+                        ));
+                    };
+                    let Ok(realtime) = params
+                        .get("realtime")
+                        .map_or(Ok(true), |value| value.parse::<bool>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "realtime invalid".to_string(),
+                        ));
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                match get_terms_api(
+                                    &index_arc_clone,
+                                    parts[5],
+                                    &prefix,
+                                    offset,
+                                    length,
+                                    realtime,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "field", _, &Method::GET)
+            if path.trim_end_matches('/').ends_with("/values") =>
+        {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let params: HashMap<String, String> = req
+                        .uri()
+                        .query()
+                        .map(|v| {
+                            url::form_urlencoded::parse(v.as_bytes())
+                                .into_owned()
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    let query = params.get("query").cloned().unwrap_or_default();
+                    let prefix = params.get("prefix").cloned().unwrap_or_default();
+                    let Ok(offset) = params
+                        .get("offset")
+                        .map_or(Ok(0), |value| value.parse::<usize>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "offset invalid".to_string(),
+                        ));
+                    };
+                    let Ok(length) = params
+                        .get("length")
+                        .map_or(Ok(100), |value| value.parse::<u16>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "length invalid".to_string(),
+                        ));
+                    };
+                    let Ok(min_count) = params
+                        .get("min_count")
+                        .map_or(Ok(0), |value| value.parse::<usize>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "min_count invalid".to_string(),
+                        ));
+                    };
+                    let sort = match params.get("sort").map(|value| value.as_str()) {
+                        None | Some("Count") => FacetSort::Count,
+                        Some("Value") => FacetSort::Value,
+                        Some(_) => {
+                            return Ok(status(StatusCode::BAD_REQUEST, "sort invalid".to_string()));
+                        }
+                    };
+                    let Ok(realtime) = params
+                        .get("realtime")
+                        .map_or(Ok(true), |value| value.parse::<bool>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "realtime invalid".to_string(),
+                        ));
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let query_facet = if index_arc_clone
+                                    .read()
+                                    .await
+                                    .schema_map
+                                    .get(parts[5])
+                                    .is_some_and(|schema_field| {
+                                        schema_field.field_type == FieldType::StringSet
+                                    }) {
+                                    QueryFacet::StringSet {
+                                        field: parts[5].to_string(),
+                                        prefix,
+                                        length,
+                                        sort,
+                                        min_count,
+                                        offset,
+                                    }
+                                } else {
+                                    QueryFacet::String {
+                                        field: parts[5].to_string(),
+                                        prefix,
+                                        length,
+                                        sort,
+                                        min_count,
+                                        offset,
+                                    }
+                                };
+
+                                match get_field_values_api(
+                                    &index_arc_clone,
+                                    parts[5],
+                                    query,
+                                    query_facet,
+                                    realtime,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "termvectors", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(document_id) = parts[5].parse::<usize>() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "document_id invalid or missing".to_string(),
+                        ));
+                    };
+
+                    let params: HashMap<String, String> = req
+                        .uri()
+                        .query()
+                        .map(|v| {
+                            url::form_urlencoded::parse(v.as_bytes())
+                                .into_owned()
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    let fields: Vec<String> = params
+                        .get("fields")
+                        .map(|fields| {
+                            fields
+                                .split(',')
+                                .map(|field| field.trim().to_string())
+                                .filter(|field| !field.is_empty())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    let Ok(realtime) = params
+                        .get("realtime")
+                        .map_or(Ok(true), |value| value.parse::<bool>())
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "realtime invalid".to_string(),
+                        ));
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+                                match get_term_vectors_api(
+                                    &index_arc_clone,
+                                    document_id,
+                                    &fields,
+                                    realtime,
+                                )
+                                .await
+                                {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::NOT_FOUND, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "doc", _, &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(index_id) = parts[3].parse() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
+
+                    let (wait_for_completion, webhook_url) = match task_params(&req) {
+                        Ok(task_params) => task_params,
+                        Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                    };
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_value =
+                        match request_encoding.decode::<serde_json::Value>(&request_bytes) {
+                            Ok(request_value) => request_value,
+                            Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                        };
+
+                    let apikey_list_ref = apikey_list.read().await;
+
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                            let index_arc_clone = index_arc.clone();
+                            let tasks = apikey_object.tasks.clone();
+                            drop(apikey_list_ref);
+
+                            if !wait_for_completion {
+                                let value_vec = match request_value {
+                                    serde_json::Value::Array(value_vec) => value_vec,
+                                    value => vec![value],
+                                };
+                                let task = spawn_task(
+                                    &tasks,
+                                    TaskType::IndexDocuments,
+                                    index_id,
+                                    true,
+                                    webhook_url,
+                                    |task_handle| async move {
+                                        index_documents_task_api(
+                                            &index_arc_clone,
+                                            value_vec,
+                                            task_handle,
+                                        )
+                                        .await
+                                        .map(|indexed_doc_count| indexed_doc_count.into())
+                                    },
+                                );
+                                return Ok(task_started(task));
+                            }
+
+                            if let serde_json::Value::Array(value_vec) = request_value {
+                                let results_object =
+                                    index_documents_api(&index_arc_clone, value_vec).await;
+                                return Ok(result_response(response_encoding, results_object));
+                            }
+
+                            let document_object = match serde_json::from_value(request_value) {
+                                Ok(document_object) => document_object,
+                                Err(e) => {
+                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                }
+                            };
+
+                            let status_object =
+                                index_document_api(&index_arc_clone, document_object).await;
+                            Ok(result_response(response_encoding, status_object))
+                        } else {
+                            Ok(index_not_found())
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "index", _, "bulk", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Ok(index_id) = parts[3].parse::<u64>() {
+                            if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                                let index_arc_clone = index_arc.clone();
+                                drop(apikey_list_ref);
+
+                                let request_bytes = match read_body(
+                                    req.into_body(),
+                                    request_limits.max_request_body_size,
+                                )
+                                .await
+                                {
+                                    Ok(request_bytes) => request_bytes,
+                                    Err(response) => return Ok(response),
+                                };
+                                let Ok(request_string) = str::from_utf8(&request_bytes) else {
+                                    return Ok(status(
+                                        StatusCode::BAD_REQUEST,
+                                        "request body is not valid UTF-8".to_string(),
+                                    ));
+                                };
+                                let is_array = request_string.trim().starts_with('[');
+                                let operations = if is_array {
+                                    serde_json::from_str::<Vec<BulkOperation>>(request_string)
+                                } else {
+                                    serde_json::Deserializer::from_str(request_string)
+                                        .into_iter::<BulkOperation>()
+                                        .collect()
+                                };
+                                let operations = match operations {
+                                    Ok(operations) => operations,
+                                    Err(e) => {
+                                        return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                    }
+                                };
+
+                                match bulk_api(&index_arc_clone, operations).await {
+                                    Ok(result) => {
+                                        let result_object_json =
+                                            serde_json::to_string(&result).unwrap();
+                                        Ok(Response::new(result_object_json.into()))
+                                    }
+                                    Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                                }
+                            } else {
+                                Ok(index_not_found())
+                            }
+                        } else {
+                            Ok(status(
+                                StatusCode::BAD_REQUEST,
+                                "index_id missing".to_string(),
+                            ))
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key not found".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::NOT_FOUND,
+                        "api_key not found".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(StatusCode::NOT_FOUND, "api_key missing".to_string()))
+            }
+        }
+
+        ("api", _, "index", _, "doc", _, &Method::PATCH) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(index_id) = parts[3].parse::<u64>() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_value =
+                        match request_encoding.decode::<serde_json::Value>(&request_bytes) {
+                            Ok(request_value) => request_value,
+                            Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                        };
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::UNAUTHORIZED,
+                            String::from("api_key does not exists"),
+                        ));
+                    };
+                    let Some(index_arc) = apikey_object.index_list.get(&index_id) else {
+                        return Ok(index_not_found());
+                    };
+                    let index_arc_clone = index_arc.clone();
+                    drop(apikey_list_ref);
+
+                    let is_doc_vector = match &request_value {
+                        serde_json::Value::Array(value_vec) => {
+                            matches!(value_vec.first(), Some(serde_json::Value::Array(_)))
+                        }
+                        _ => return Ok(status(StatusCode::BAD_REQUEST, String::new())),
+                    };
+
+                    let status_object = if !is_doc_vector {
+                        let document_object: (u64, Document) =
+                            match serde_json::from_value(request_value) {
+                                Ok(id_document_object) => id_document_object,
+                                Err(e) => {
+                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                }
+                            };
+
+                        update_document_api(&index_arc_clone, document_object).await
+                    } else {
+                        let id_document_object_vec: Vec<(u64, Document)> =
+                            match serde_json::from_value(request_value) {
+                                Ok(document_object_vec) => document_object_vec,
+                                Err(e) => {
+                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                }
+                            };
+
+                        update_documents_api(&index_arc_clone, id_document_object_vec).await
+                    };
+
+                    Ok(result_response(response_encoding, status_object))
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "index", _, "file", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(index_id) = parts[3].parse() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
+                    let Ok(doc_id) = parts[5].parse() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "doc_id invalid or missing".to_string(),
+                        ));
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                            let file = get_file_api(index_arc, doc_id).await;
+                            drop(apikey_list_ref);
+
+                            if let Some(file) = file {
+                                let response = Response::builder()
+                                    .header("Content-Type", "application/pdf")
+                                    .header("content-length", file.len())
+                                    .header("Content-Disposition", "attachment;filename=file.pdf")
+                                    .body(file.into())
+                                    .unwrap();
+                                Ok(response)
+                            } else {
+                                Ok(status(
+                                    StatusCode::NOT_FOUND,
+                                    "doc_id does not exists".to_string(),
+                                ))
+                            }
+                        } else {
+                            Ok(index_not_found())
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "index", _, "doc", _, &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(index_id) = parts[3].parse() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
+                    let Ok(doc_id) = parts[5].parse() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "doc_id invalid or missing".to_string(),
+                        ));
+                    };
+
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+
+                    let get_document_request = if !request_bytes.is_empty() {
+                        let get_document_request: GetDocumentRequest =
+                            match request_encoding.decode(&request_bytes) {
+                                Ok(document_object) => document_object,
+                                Err(e) => {
+                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                }
+                            };
+                        get_document_request
+                    } else {
+                        GetDocumentRequest {
+                            query_terms: Vec::new(),
+                            query_phrases: Vec::new(),
+                            highlights: Vec::new(),
+                            highlight_output: HighlightOutput::Field,
+                            fields: Vec::new(),
+                            distance_fields: Vec::new(),
+                        }
+                    };
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
+                            let status_object = get_document_api(
+                                index_arc,
+                                doc_id,
+                                get_document_request,
+                                &scoped_apikey,
+                            )
+                            .await;
+                            drop(apikey_list_ref);
+
+                            if let Some(status_object) = status_object {
+                                Ok(response_encoding.response(&status_object))
+                            } else {
+                                Ok(status(
+                                    StatusCode::NOT_FOUND,
+                                    "doc_id does not exists".to_string(),
+                                ))
+                            }
+                        } else {
+                            Ok(index_not_found())
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ))
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "index", _, "doc", _, &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let Ok(index_id) = parts[3].parse() else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            "index_id invalid or missing".to_string(),
+                        ));
+                    };
+
+                    let (wait_for_completion, webhook_url) = match task_params(&req) {
+                        Ok(task_params) => task_params,
+                        Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                    };
+
+                    let dry_run = req.uri().query().is_some_and(|v| {
+                        url::form_urlencoded::parse(v.as_bytes())
+                            .any(|(key, value)| key == "dry_run" && value == "true")
+                    });
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::UNAUTHORIZED,
+                            String::from("api_key does not exists"),
+                        ));
+                    };
+                    let Some(index_arc) = apikey_object.index_list.get(&index_id) else {
+                        return Ok(index_not_found());
+                    };
+                    let index_arc_clone = index_arc.clone();
+                    let tasks = apikey_object.tasks.clone();
+                    drop(apikey_list_ref);
+
+                    let Ok(document_id) = parts[5].parse() else {
+                        let request_bytes =
+                            match read_body(req.into_body(), request_limits.max_request_body_size)
+                                .await
+                            {
+                                Ok(request_bytes) => request_bytes,
+                                Err(response) => return Ok(response),
+                            };
+
+                        match serde_json::from_slice::<SearchRequestObject>(&request_bytes) {
+                            Ok(search_request) => {
+                                if dry_run {
+                                    let status_object = delete_documents_by_query_dry_run_api(
+                                        &index_arc_clone,
+                                        search_request,
+                                    )
+                                    .await;
+                                    return Ok(result_response(Encoding::Json, status_object));
+                                }
+
+                                if !wait_for_completion {
+                                    let task = spawn_task(
+                                        &tasks,
+                                        TaskType::DeleteByQuery,
+                                        index_id,
+                                        false,
+                                        webhook_url,
+                                        |_| async move {
+                                            delete_documents_by_query_api(
+                                                &index_arc_clone,
+                                                search_request,
+                                            )
+                                            .await
+                                            .map(|deleted_doc_count| deleted_doc_count.into())
+                                            .map_err(String::from)
+                                        },
+                                    );
+                                    return Ok(task_started(task));
+                                }
+
+                                let status_object =
+                                    delete_documents_by_query_api(&index_arc_clone, search_request)
+                                        .await;
+                                return Ok(result_response(Encoding::Json, status_object));
+                            }
+                            Err(_) => {
+                                let Ok(request_string) = str::from_utf8(&request_bytes) else {
+                                    return Ok(status(
+                                        StatusCode::BAD_REQUEST,
+                                        "request body is not valid UTF-8".to_string(),
+                                    ));
+                                };
+                                let is_doc_vector = request_string.trim().starts_with('[');
+                                let status_object = if !is_doc_vector {
+                                    let document_id = match serde_json::from_str(request_string) {
+                                        Ok(document_id) => document_id,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                    delete_document_api(&index_arc_clone, document_id).await
+                                } else {
+                                    let document_id_vec = match serde_json::from_str(request_string)
+                                    {
+                                        Ok(document_id_vec) => document_id_vec,
+                                        Err(e) => {
+                                            return Ok(status(
+                                                StatusCode::BAD_REQUEST,
+                                                e.to_string(),
+                                            ));
+                                        }
+                                    };
+
+                                    delete_documents_api(&index_arc_clone, document_id_vec).await
+                                };
+                                return Ok(result_response(Encoding::Json, status_object));
+                            }
+                        };
+                    };
+
+                    let status_object = delete_document_api(&index_arc_clone, document_id).await;
+                    Ok(result_response(Encoding::Json, status_object))
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "apikey", "scoped", "", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_object: CreateScopedApikeyRequest =
+                        match serde_json::from_slice(&request_bytes) {
+                            Ok(request_object) => request_object,
+                            Err(e) => {
+                                return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                            }
+                        };
+
+                    let mut scoped_apikey = [0u8; 32];
+                    OsRng.fill_bytes(&mut scoped_apikey);
+                    let scoped_api_key_base64 = general_purpose::STANDARD.encode(scoped_apikey);
+
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result = create_scoped_apikey_api(
+                        &index_path,
+                        request_object,
+                        &scoped_apikey,
+                        apikey_object,
+                    );
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(_) => Ok(Response::new(scoped_api_key_base64.into())),
+                        Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "apikey", "scoped", "", "", &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_object: DeleteApikeyRequest =
+                        match serde_json::from_slice(&request_bytes) {
+                            Ok(request_object) => request_object,
+                            Err(e) => {
+                                return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                            }
+                        };
+
+                    let Ok(scoped_apikey) =
+                        general_purpose::STANDARD.decode(&request_object.apikey_base64)
+                    else {
+                        return Ok(status(
+                            StatusCode::BAD_REQUEST,
+                            String::from("scoped api_key invalid"),
+                        ));
+                    };
+
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result = delete_scoped_apikey_api(
+                        &index_path,
+                        calculate_hash(&scoped_apikey) as u128,
+                        apikey_object,
+                    );
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(count) => Ok(Response::new(count.to_string().into())),
+                        Err(_) => Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "scoped api_key does not exists".to_string(),
+                        )),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "apikey", "metadata", "", "", &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let metadata = match serde_json::from_slice::<serde_json::Value>(&request_bytes)
+                    {
+                        Ok(metadata) => metadata,
+                        Err(e) => {
+                            return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                        }
+                    };
+
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result = set_apikey_metadata_api(&index_path, metadata, apikey_object);
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(metadata) => Ok(Response::new(
+                            serde_json::to_string(&metadata).unwrap().into(),
+                        )),
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "apikey", "metadata", "", "", &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    Ok(Response::new(
+                        serde_json::to_string(&apikey_object.metadata)
+                            .unwrap()
+                            .into(),
+                    ))
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "apikey", "", "", "", &Method::POST) => {
+            if let Some(apikey_header) = headers.get("apikey") {
+                let mut hasher = Sha256::new();
+                hasher.update(MASTER_KEY_SECRET.to_string());
+                let master_apikey = hasher.finalize();
+                let master_apikey_base64 = general_purpose::STANDARD.encode(master_apikey);
+
+                if apikey_header.to_str().unwrap_or("") == master_apikey_base64 {
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let apikey_quota_object = match serde_json::from_slice(&request_bytes) {
+                        Ok(apikey_quota_object) => apikey_quota_object,
+                        Err(e) => {
+                            return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                        }
+                    };
+
+                    let mut apikey = [0u8; 32];
+                    OsRng.fill_bytes(&mut apikey);
+                    let api_key_base64 = general_purpose::STANDARD.encode(apikey);
+
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let result = create_apikey_api(
+                        &index_path,
+                        apikey_quota_object,
+                        &apikey,
+                        &mut apikey_list_mut,
+                    )
+                    .map(|_| ());
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(()) => Ok(Response::new(api_key_base64.into())),
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("master_apikey invalid"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("master_apikey missing"),
+                ))
+            }
+        }
+
+        ("api", _, "apikey", "", "", "", &Method::DELETE) => {
+            if let Some(apikey_header) = headers.get("apikey") {
+                let mut hasher = Sha256::new();
+                hasher.update(MASTER_KEY_SECRET.to_string());
+                let master_apikey = hasher.finalize();
+                let master_apikey_base64 = general_purpose::STANDARD.encode(master_apikey);
+
+                if apikey_header.to_str().unwrap_or("") == master_apikey_base64 {
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_object: DeleteApikeyRequest =
+                        match serde_json::from_slice(&request_bytes) {
+                            Ok(request_object) => request_object,
+                            Err(e) => {
+                                return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                            }
+                        };
+
+                    let Ok(apikey) =
+                        general_purpose::STANDARD.decode(&request_object.apikey_base64)
+                    else {
+                        return Ok(status(
+                            StatusCode::UNAUTHORIZED,
+                            String::from("api_key invalid"),
+                        ));
+                    };
+
+                    let apikey_hash = calculate_hash(&apikey) as u128;
+
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let result = delete_apikey_api(&index_path, &mut apikey_list_mut, apikey_hash);
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(count) => Ok(Response::new(count.to_string().into())),
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        "api_key invalid or missing".to_string(),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    "api_key invalid or missing".to_string(),
+                ))
+            }
+        }
+
+        ("api", _, "query", "", "", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let mut federated_search_request: FederatedSearchRequest =
+                        match request_encoding.decode(&request_bytes) {
+                            Ok(federated_search_request) => federated_search_request,
+                            Err(e) => {
+                                return Ok(error_response(SeekStormError::QueryParseError(e)));
+                            }
+                        };
+                    let search_request = &federated_search_request.search_request;
+                    if request_limits.exceeds_result_window(
+                        search_request.offset.saturating_add(search_request.length),
+                    ) {
+                        return Ok(max_result_window_exceeded(
+                            "offset+length",
+                            request_limits.max_result_window,
+                        ));
+                    }
+                    if let Err(e) = federated_search_request.check_index_weights() {
+                        return Ok(error_response(e));
+                    }
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let index_ids: Vec<u64> = if federated_search_request.indices.is_empty() {
+                        let mut index_ids: Vec<u64> =
+                            apikey_object.index_list.keys().copied().collect();
+                        index_ids.sort_unstable();
+                        index_ids
+                    } else {
+                        federated_search_request.indices.clone()
+                    };
+                    let mut index_arcs: Vec<(u64, IndexArc)> = Vec::new();
+                    for index_id in index_ids {
+                        let Some(index_arc) = apikey_object.index_list.get(&index_id) else {
+                            return Ok(error_response(SeekStormError::IndexNotFound(format!(
+                                "index_id does not exists: {}",
+                                index_id
+                            ))));
+                        };
+                        index_arcs.push((index_id, index_arc.clone()));
+                    }
+                    let search_permits = apikey_object.search_permits.clone();
+                    drop(apikey_list_ref);
+
+                    let (_search_permits, downgraded) = match limit_indices_query(
+                        &index_arcs,
+                        &mut federated_search_request.search_request,
+                        &request_limits,
+                        &search_permits,
+                    )
+                    .await
+                    {
+                        Ok(limited) => limited,
+                        Err(response) => return Ok(response),
+                    };
+
+                    match query_indices_api(&index_arcs, federated_search_request, &[]).await {
+                        Ok(mut search_result) => {
+                            search_result.downgraded = downgraded;
+                            Ok(search_result_response(
+                                search_result,
+                                api_version,
+                                response_encoding,
+                            ))
+                        }
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "alias", "", "", "", &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let aliases_json = serde_json::to_string(&apikey_object.aliases).unwrap();
+                    Ok(Response::new(aliases_json.into()))
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "alias", alias_name, "", "", &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let alias_name = alias_name.to_string();
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_object: CreateAliasRequest =
+                        match serde_json::from_slice(&request_bytes) {
+                            Ok(request_object) => request_object,
+                            Err(e) => {
+                                return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                            }
+                        };
+
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result =
+                        create_alias_api(&index_path, &alias_name, request_object, apikey_object)
+                            .await;
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(alias) => {
+                            Ok(Response::new(serde_json::to_string(&alias).unwrap().into()))
+                        }
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "alias", alias_name, "", "", &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    match apikey_object
+                        .aliases
+                        .iter()
+                        .find(|alias| alias.name == alias_name)
+                    {
+                        Some(alias) => {
+                            Ok(Response::new(serde_json::to_string(alias).unwrap().into()))
+                        }
+                        None => Ok(status(StatusCode::NOT_FOUND, "alias not found".to_string())),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "alias", alias_name, "", "", &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result = delete_alias_api(&index_path, alias_name, apikey_object);
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(alias_count) => Ok(Response::new(alias_count.to_string().into())),
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "alias", alias_name, "rollover", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result = rollover_api(&index_path, alias_name, apikey_object).await;
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(index_id) => Ok(Response::new(index_id.to_string().into())),
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "alias", alias_name, "doc", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let alias_name = alias_name.to_string();
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_value =
+                        match request_encoding.decode::<serde_json::Value>(&request_bytes) {
+                            Ok(request_value) => request_value,
+                            Err(e) => return Ok(status(StatusCode::BAD_REQUEST, e)),
+                        };
+
+                    let (value_vec, is_array) = match request_value {
+                        serde_json::Value::Array(value_vec) => (value_vec, true),
+                        request_value => (vec![request_value], false),
+                    };
+
+                    let write_indices = match alias_write_indices(
+                        &index_path,
+                        &alias_name,
+                        apikey_hash,
+                        &apikey_list,
+                        &value_vec,
+                    )
+                    .await
+                    {
+                        Ok(write_indices) => write_indices,
+                        Err(e) => return Ok(error_response(e)),
+                    };
+
+                    if !is_array {
+                        let Some((index_arc, _positions)) = write_indices.first() else {
+                            return Ok(status(
+                                StatusCode::NOT_FOUND,
+                                "index of the alias not found".to_string(),
+                            ));
+                        };
+                        let document_object =
+                            match serde_json::from_value(value_vec.into_iter().next().unwrap()) {
+                                Ok(document_object) => document_object,
+                                Err(e) => {
+                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                                }
+                            };
+
+                        let status_object = index_document_api(index_arc, document_object).await;
+                        return Ok(result_response(response_encoding, status_object));
+                    }
+
+                    let mut results = vec![DocumentResult::default(); value_vec.len()];
+                    for (index_arc, positions) in write_indices {
+                        let values = positions
+                            .iter()
+                            .map(|position| value_vec[*position].clone())
+                            .collect();
+                        match index_documents_api(&index_arc, values).await {
+                            Ok(document_results) => {
+                                for (position, document_result) in
+                                    positions.into_iter().zip(document_results)
+                                {
+                                    results[position] = document_result;
+                                }
+                            }
+                            Err(e) => return Ok(error_response(e)),
+                        }
+                    }
+                    Ok(result_response(response_encoding, Ok(results)))
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "alias", alias_name, "query", "", &Method::POST) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let alias_name = alias_name.to_string();
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let mut federated_search_request: FederatedSearchRequest =
+                        match request_encoding.decode(&request_bytes) {
+                            Ok(federated_search_request) => federated_search_request,
+                            Err(e) => {
+                                return Ok(error_response(SeekStormError::QueryParseError(e)));
+                            }
+                        };
+                    let search_request = &federated_search_request.search_request;
+                    if request_limits.exceeds_result_window(
+                        search_request.offset.saturating_add(search_request.length),
+                    ) {
+                        return Ok(max_result_window_exceeded(
+                            "offset+length",
+                            request_limits.max_result_window,
+                        ));
+                    }
+                    if let Err(e) = federated_search_request.check_index_weights() {
+                        return Ok(error_response(e));
+                    }
+
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let Some(alias) = apikey_object
+                        .aliases
+                        .iter()
+                        .find(|alias| alias.name == alias_name)
+                    else {
+                        return Ok(status(StatusCode::NOT_FOUND, "alias not found".to_string()));
+                    };
+                    let schema = match apikey_object.index_list.get(&alias.current_index_id()) {
+                        Some(index_arc) => index_arc.read().await.get_schema(),
+                        None => Vec::new(),
+                    };
+                    let index_arcs: Vec<(u64, IndexArc)> =
+                        alias_search_index_ids(alias, &schema, &search_request.facet_filter)
+                            .iter()
+                            .filter_map(|index_id| {
+                                apikey_object
+                                    .index_list
+                                    .get(index_id)
+                                    .map(|index_arc| (*index_id, index_arc.clone()))
+                            })
+                            .collect();
+                    let search_permits = apikey_object.search_permits.clone();
+                    drop(apikey_list_ref);
+
+                    let (_search_permits, downgraded) = match limit_indices_query(
+                        &index_arcs,
+                        &mut federated_search_request.search_request,
+                        &request_limits,
+                        &search_permits,
+                    )
+                    .await
+                    {
+                        Ok(limited) => limited,
+                        Err(response) => return Ok(response),
+                    };
+
+                    match query_indices_api(&index_arcs, federated_search_request, &[]).await {
+                        Ok(mut search_result) => {
+                            search_result.downgraded = downgraded;
+                            Ok(search_result_response(
+                                search_result,
+                                api_version,
+                                response_encoding,
+                            ))
+                        }
+                        Err(e) => Ok(error_response(e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "lifecycle", "", "", "", &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let policies_json =
+                        serde_json::to_string(&apikey_object.lifecycle_policies).unwrap();
+                    Ok(Response::new(policies_json.into()))
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "lifecycle", policy_name, "", "", &Method::PUT) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let policy_name = policy_name.to_string();
+                    let request_bytes = match read_body(
+                        req.into_body(),
+                        request_limits.max_request_body_size,
+                    )
+                    .await
+                    {
+                        Ok(request_bytes) => request_bytes,
+                        Err(response) => return Ok(response),
+                    };
+                    let request_object: LifecyclePolicy =
+                        match serde_json::from_slice(&request_bytes) {
+                            Ok(request_object) => request_object,
+                            Err(e) => {
+                                return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                            }
+                        };
+
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result = set_lifecycle_policy_api(
+                        &index_path,
+                        &policy_name,
+                        request_object,
+                        apikey_object,
+                    );
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(policy) => Ok(Response::new(
+                            serde_json::to_string(&policy).unwrap().into(),
+                        )),
+                        Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "lifecycle", policy_name, "", "", &Method::GET) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let apikey_list_ref = apikey_list.read().await;
+                    let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    match apikey_object
+                        .lifecycle_policies
+                        .iter()
+                        .find(|policy| policy.name == policy_name)
+                    {
+                        Some(policy) => {
+                            Ok(Response::new(serde_json::to_string(policy).unwrap().into()))
+                        }
+                        None => Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "policy not found".to_string(),
+                        )),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "lifecycle", policy_name, "", "", &Method::DELETE) => {
+            if let Some(apikey) = headers.get("apikey") {
+                if let Some(apikey_hash) =
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+                {
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        return Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ));
+                    };
+                    let result =
+                        delete_lifecycle_policy_api(&index_path, policy_name, apikey_object);
+                    drop(apikey_list_mut);
+
+                    match result {
+                        Ok(policy_count) => Ok(Response::new(policy_count.to_string().into())),
+                        Err(e) => Ok(status(StatusCode::NOT_FOUND, e)),
+                    }
+                } else {
+                    Ok(status(
+                        StatusCode::UNAUTHORIZED,
+                        String::from("api_key does not exists"),
+                    ))
+                }
+            } else {
+                Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key missing"),
+                ))
+            }
+        }
+
+        ("api", _, "tasks", task_id, "" | "cancel", "", &Method::GET | &Method::POST) => {
+            let Some(apikey) = headers.get("apikey") else {
+                return Ok(status(
                     StatusCode::UNAUTHORIZED,
                     String::from("api_key missing"),
-                ))
+                ));
+            };
+            let Some(apikey_hash) =
+                get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
+            else {
+                return Ok(status(
+                    StatusCode::UNAUTHORIZED,
+                    String::from("api_key does not exists"),
+                ));
+            };
+            let apikey_list_ref = apikey_list.read().await;
+            let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+                return Ok(status(
+                    StatusCode::NOT_FOUND,
+                    "api_key not found".to_string(),
+                ));
+            };
+            let mut tasks = apikey_object.tasks.lock().unwrap();
+
+            if task_id.is_empty() {
+                return Ok(match (parts[4], req.method()) {
+                    ("", &Method::GET) => {
+                        Response::new(serde_json::to_string(&tasks.list()).unwrap().into())
+                    }
+                    _ => status(
+                        StatusCode::NOT_IMPLEMENTED,
+                        String::from("method not implemented"),
+                    ),
+                });
             }
+            let Ok(task_id) = task_id.parse::<u64>() else {
+                return Ok(status(
+                    StatusCode::BAD_REQUEST,
+                    "task_id invalid".to_string(),
+                ));
+            };
+            Ok(match (parts[4], req.method()) {
+                ("", &Method::GET) => match tasks.get(task_id) {
+                    Some(task) => Response::new(serde_json::to_string(&task).unwrap().into()),
+                    None => status(StatusCode::NOT_FOUND, "task not found".to_string()),
+                },
+                ("cancel", &Method::POST) => match tasks.cancel(task_id) {
+                    Ok(task) => Response::new(serde_json::to_string(&task).unwrap().into()),
+                    Err(e) => status(StatusCode::CONFLICT, e),
+                },
+                _ => status(
+                    StatusCode::NOT_IMPLEMENTED,
+                    String::from("method not implemented"),
+                ),
+            })
         }
 
-        ("api", "v1", "index", _, "doc", _, &Method::PATCH) => {
+        ("api", _, "dashboard", "", "", "", &Method::GET) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
-                    let index_id: u64 = parts[3].parse().unwrap();
-                    let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-                    let request_string = str::from_utf8(&request_bytes).unwrap();
-                    let apikey_list_ref = apikey_list.read().await;
-                    let apikey_object = apikey_list_ref.get(&apikey_hash).unwrap();
-                    let index_arc = apikey_object.index_list.get(&index_id).unwrap();
-                    let index_arc_clone = index_arc.clone();
-                    drop(apikey_list_ref);
-
-                    let is_doc_vector = if let Some(pos) = request_string.find('[') {
-                        request_string[pos + 1..].find('[').is_some()
-                    } else {
-                        return Ok(status(StatusCode::BAD_REQUEST, String::new()));
-                    };
-
-                    let status_object = if !is_doc_vector {
-                        let document_object: (u64, Document) =
-                            match serde_json::from_str(request_string) {
-                                Ok(id_document_object) => id_document_object,
-                                Err(e) => {
-                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
-                                }
-                            };
-
-                        update_document_api(&index_arc_clone, document_object).await
+                    if let Some(dashboard_object) =
+                        get_dashboard_api(&index_path, apikey_hash, &apikey_list).await
+                    {
+                        let dashboard_object_json =
+                            serde_json::to_string(&dashboard_object).unwrap();
+                        Ok(Response::new(dashboard_object_json.into()))
                     } else {
-                        let id_document_object_vec: Vec<(u64, Document)> =
-                            match serde_json::from_str(request_string) {
-                                Ok(document_object_vec) => document_object_vec,
-                                Err(e) => {
-                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
-                                }
-                            };
-
-                        update_documents_api(&index_arc_clone, id_document_object_vec).await
-                    };
-
-                    let status_object_json = serde_json::to_string(&status_object).unwrap();
-                    Ok(Response::new(status_object_json.into()))
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ))
+                    }
                 } else {
                     Ok(status(
                         StatusCode::UNAUTHORIZED,
@@ -953,50 +6144,23 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "file", _, &Method::GET) => {
+        ("api", _, "trash", "", "", "", &Method::GET) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
-                    let Ok(index_id) = parts[3].parse() else {
-                        return Ok(status(
-                            StatusCode::BAD_REQUEST,
-                            "index_id invalid or missing".to_string(),
-                        ));
-                    };
-                    let Ok(doc_id) = parts[5].parse() else {
-                        return Ok(status(
-                            StatusCode::BAD_REQUEST,
-                            "doc_id invalid or missing".to_string(),
-                        ));
-                    };
-
                     let apikey_list_ref = apikey_list.read().await;
                     if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
-                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
-                            let file = get_file_api(index_arc, doc_id).await;
-                            drop(apikey_list_ref);
+                        let trash_list = get_trash_api(
+                            &index_path,
+                            apikey_object,
+                            request_limits.trash_retention_hours,
+                        );
+                        drop(apikey_list_ref);
 
-                            if let Some(file) = file {
-                                let response = Response::builder()
-                                    .header("Content-Type", "application/pdf")
-                                    .header("content-length", file.len())
-                                    .header("Content-Disposition", "attachment;filename=file.pdf")
-                                    .body(file.into())
-                                    .unwrap();
-                                Ok(response)
-                            } else {
-                                Ok(status(
-                                    StatusCode::NOT_FOUND,
-                                    "doc_id does not exists".to_string(),
-                                ))
-                            }
-                        } else {
-                            Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            ))
-                        }
+                        Ok(Response::new(
+                            serde_json::to_string(&trash_list).unwrap().into(),
+                        ))
                     } else {
                         Ok(status(
                             StatusCode::NOT_FOUND,
@@ -1017,66 +6181,16 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "doc", _, &Method::GET) => {
+        ("api", _, "trash", _, "restore", "", &Method::POST) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
-                    let Ok(index_id) = parts[3].parse() else {
-                        return Ok(status(
-                            StatusCode::BAD_REQUEST,
-                            "index_id invalid or missing".to_string(),
-                        ));
-                    };
-                    let Ok(doc_id) = parts[5].parse() else {
-                        return Ok(status(
-                            StatusCode::BAD_REQUEST,
-                            "doc_id invalid or missing".to_string(),
-                        ));
-                    };
-
-                    let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-
-                    let get_document_request = if !request_bytes.is_empty() {
-                        let get_document_request: GetDocumentRequest =
-                            match serde_json::from_slice(&request_bytes) {
-                                Ok(document_object) => document_object,
-                                Err(e) => {
-                                    return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
-                                }
-                            };
-                        get_document_request
-                    } else {
-                        GetDocumentRequest {
-                            query_terms: Vec::new(),
-                            highlights: Vec::new(),
-                            fields: Vec::new(),
-                            distance_fields: Vec::new(),
-                        }
-                    };
-
-                    let apikey_list_ref = apikey_list.read().await;
-                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
-                        if let Some(index_arc) = apikey_object.index_list.get(&index_id) {
-                            let status_object =
-                                get_document_api(index_arc, doc_id, get_document_request).await;
-                            drop(apikey_list_ref);
-
-                            if let Some(status_object) = status_object {
-                                let status_object_json =
-                                    serde_json::to_string(&status_object).unwrap();
-                                Ok(Response::new(status_object_json.into()))
-                            } else {
-                                Ok(status(
-                                    StatusCode::NOT_FOUND,
-                                    "doc_id does not exists".to_string(),
-                                ))
-                            }
-                        } else {
-                            Ok(status(
-                                StatusCode::NOT_FOUND,
-                                "index does not exists".to_string(),
-                            ))
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    if let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) {
+                        match restore_index_api(&index_path, parts[3], apikey_object).await {
+                            Ok(index_id) => Ok(Response::new(index_id.to_string().into())),
+                            Err(e) => Ok(status(StatusCode::NOT_FOUND, e)),
                         }
                     } else {
                         Ok(status(
@@ -1098,75 +6212,23 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "index", _, "doc", _, &Method::DELETE) => {
+        ("api", _, "trash", _, "", "", &Method::DELETE) => {
             if let Some(apikey) = headers.get("apikey") {
                 if let Some(apikey_hash) =
-                    get_apikey_hash(apikey.to_str().unwrap().to_string(), &apikey_list).await
+                    get_apikey_hash(apikey.to_str().unwrap_or("").to_string(), &apikey_list).await
                 {
-                    let Ok(index_id) = parts[3].parse() else {
-                        return Ok(status(
-                            StatusCode::BAD_REQUEST,
-                            "index_id invalid or missing".to_string(),
-                        ));
-                    };
-
                     let apikey_list_ref = apikey_list.read().await;
-                    let apikey_object = apikey_list_ref.get(&apikey_hash).unwrap();
-                    let index_arc = apikey_object.index_list.get(&index_id).unwrap();
-                    let index_arc_clone = index_arc.clone();
-                    drop(apikey_list_ref);
-
-                    let Ok(document_id) = parts[5].parse() else {
-                        let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-
-                        match serde_json::from_slice::<SearchRequestObject>(&request_bytes) {
-                            Ok(search_request) => {
-                                let status_object =
-                                    delete_documents_by_query_api(&index_arc_clone, search_request)
-                                        .await;
-                                let status_object_json =
-                                    serde_json::to_string(&status_object).unwrap();
-                                return Ok(Response::new(status_object_json.into()));
-                            }
-                            Err(_) => {
-                                let request_string = str::from_utf8(&request_bytes).unwrap();
-                                let is_doc_vector = request_string.trim().starts_with('[');
-                                let status_object = if !is_doc_vector {
-                                    let document_id = match serde_json::from_str(request_string) {
-                                        Ok(document_id) => document_id,
-                                        Err(e) => {
-                                            return Ok(status(
-                                                StatusCode::BAD_REQUEST,
-                                                e.to_string(),
-                                            ));
-                                        }
-                                    };
-
-                                    delete_document_api(&index_arc_clone, document_id).await
-                                } else {
-                                    let document_id_vec = match serde_json::from_str(request_string)
-                                    {
-                                        Ok(document_id_vec) => document_id_vec,
-                                        Err(e) => {
-                                            return Ok(status(
-                                                StatusCode::BAD_REQUEST,
-                                                e.to_string(),
-                                            ));
-                                        }
-                                    };
-
-                                    delete_documents_api(&index_arc_clone, document_id_vec).await
-                                };
-                                let status_object_json =
-                                    serde_json::to_string(&status_object).unwrap();
-                                return Ok(Response::new(status_object_json.into()));
-                            }
-                        };
-                    };
-
-                    let status_object = delete_document_api(&index_arc_clone, document_id).await;
-                    let status_object_json = serde_json::to_string(&status_object).unwrap();
-                    Ok(Response::new(status_object_json.into()))
+                    if let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) {
+                        match purge_index_api(&index_path, parts[3], apikey_object) {
+                            Ok(trash_count) => Ok(Response::new(trash_count.to_string().into())),
+                            Err(e) => Ok(status(StatusCode::NOT_FOUND, e)),
+                        }
+                    } else {
+                        Ok(status(
+                            StatusCode::NOT_FOUND,
+                            "api_key does not exists".to_string(),
+                        ))
+                    }
                 } else {
                     Ok(status(
                         StatusCode::UNAUTHORIZED,
@@ -1181,7 +6243,7 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "apikey", "", "", "", &Method::POST) => {
+        ("api", _, "reload", "", "", "", &Method::POST) => {
             if let Some(apikey_header) = headers.get("apikey") {
                 let mut hasher = Sha256::new();
                 hasher.update(MASTER_KEY_SECRET.to_string());
@@ -1189,28 +6251,16 @@ pub(crate) async fn http_request_handler(
                 let master_apikey_base64 = general_purpose::STANDARD.encode(master_apikey);
 
                 if apikey_header.to_str().unwrap_or("") == master_apikey_base64 {
-                    let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-                    let apikey_quota_object = match serde_json::from_slice(&request_bytes) {
-                        Ok(apikey_quota_object) => apikey_quota_object,
-                        Err(e) => {
-                            return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
+                    match reload_config(&params, &index_path, &apikey_list, &request_limits_arc)
+                        .await
+                    {
+                        Ok(request_limits) => {
+                            let request_limits_json =
+                                serde_json::to_string(&request_limits).unwrap();
+                            Ok(Response::new(request_limits_json.into()))
                         }
-                    };
-
-                    let mut apikey = [0u8; 32];
-                    OsRng.fill_bytes(&mut apikey);
-                    let api_key_base64 = general_purpose::STANDARD.encode(apikey);
-
-                    let mut apikey_list_mut = apikey_list.write().await;
-                    create_apikey_api(
-                        &index_path,
-                        apikey_quota_object,
-                        &apikey,
-                        &mut apikey_list_mut,
-                    );
-                    drop(apikey_list_mut);
-
-                    Ok(Response::new(api_key_base64.into()))
+                        Err(e) => Ok(status(StatusCode::BAD_REQUEST, e)),
+                    }
                 } else {
                     Ok(status(
                         StatusCode::UNAUTHORIZED,
@@ -1225,7 +6275,7 @@ pub(crate) async fn http_request_handler(
             }
         }
 
-        ("api", "v1", "apikey", "", "", "", &Method::DELETE) => {
+        ("api", _, "server", "info", "", "", &Method::GET) => {
             if let Some(apikey_header) = headers.get("apikey") {
                 let mut hasher = Sha256::new();
                 hasher.update(MASTER_KEY_SECRET.to_string());
@@ -1233,52 +6283,26 @@ pub(crate) async fn http_request_handler(
                 let master_apikey_base64 = general_purpose::STANDARD.encode(master_apikey);
 
                 if apikey_header.to_str().unwrap_or("") == master_apikey_base64 {
-                    let request_bytes = body::to_bytes(req.into_body()).await.unwrap();
-                    let request_object: DeleteApikeyRequest =
-                        match serde_json::from_slice(&request_bytes) {
-                            Ok(request_object) => request_object,
-                            Err(e) => {
-                                return Ok(status(StatusCode::BAD_REQUEST, e.to_string()));
-                            }
-                        };
-
-                    let Ok(apikey) =
-                        general_purpose::STANDARD.decode(&request_object.apikey_base64)
-                    else {
-                        return Ok(status(
-                            StatusCode::UNAUTHORIZED,
-                            String::from("api_key invalid"),
-                        ));
-                    };
-
-                    let apikey_hash = calculate_hash(&apikey) as u128;
-
-                    let mut apikey_list_mut = apikey_list.write().await;
-                    let result = delete_apikey_api(&index_path, &mut apikey_list_mut, apikey_hash);
-                    drop(apikey_list_mut);
-
-                    match result {
-                        Ok(count) => Ok(Response::new(count.to_string().into())),
-                        Err(_) => Ok(status(
-                            StatusCode::NOT_FOUND,
-                            "api_key does not exists".to_string(),
-                        )),
-                    }
+                    let apikey_list_ref = apikey_list.read().await;
+                    let server_info = get_server_info_api(&apikey_list_ref, request_limits);
+                    drop(apikey_list_ref);
+                    let server_info_json = serde_json::to_string(&server_info).unwrap();
+                    Ok(Response::new(server_info_json.into()))
                 } else {
                     Ok(status(
                         StatusCode::UNAUTHORIZED,
-                        "api_key invalid or missing".to_string(),
+                        String::from("master_apikey invalid"),
                     ))
                 }
             } else {
                 Ok(status(
                     StatusCode::UNAUTHORIZED,
-                    "api_key invalid or missing".to_string(),
+                    String::from("master_apikey missing"),
                 ))
             }
         }
 
-        ("api", "v1", "status", "", "", "", &Method::GET) => Ok(status(
+        ("api", _, "status", "", "", "", &Method::GET) => Ok(status(
             StatusCode::NOT_IMPLEMENTED,
             String::from("method not implemented"),
         )),
@@ -1288,6 +6312,8 @@ pub(crate) async fn http_request_handler(
             "/css/flexboxgrid.min.css" => Ok(Response::new(FLEXBOX_CSS.into())),
             "/css/master.css" => Ok(Response::new(MASTER_CSS.into())),
             "/js/master.js" => Ok(Response::new(MASTER_JS.into())),
+            "/dashboard" => Ok(Response::new(DASHBOARD_HTML.into())),
+            "/js/dashboard.js" => Ok(Response::new(DASHBOARD_JS.into())),
             "/js/jquery-3.7.1.min.js" => Ok(Response::new(JQUERY_JS.into())),
 
             "/css/bootstrap.histogram.slider.css" => Ok(Response::new(HISTOGRAM_CSS.into())),
@@ -1322,6 +6348,8 @@ pub(crate) async fn http_server(
     apikey_list: Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
     local_ip: &String,
     local_port: &u16,
+    request_limits: Arc<tokio::sync::RwLock<RequestLimits>>,
+    params: Arc<HashMap<String, String>>,
 ) {
     let addr: SocketAddr = format!("{}:{}", local_ip, local_port)
         .parse()
@@ -1331,10 +6359,39 @@ pub(crate) async fn http_server(
         let index_path = index_path.to_path_buf();
         let addr = conn.remote_addr();
         let apikey_list = apikey_list.clone();
+        let request_limits = request_limits.clone();
+        let params = params.clone();
 
         async move {
             Ok::<_, Infallible>(service_fn(move |req| {
-                http_request_handler(index_path.clone(), apikey_list.clone(), req, addr)
+                let index_path = index_path.clone();
+                let apikey_list = apikey_list.clone();
+                let request_limits = request_limits.clone();
+                let params = params.clone();
+                async move {
+                    let request_limits_copy = *request_limits.read().await;
+                    idempotent_request(req, request_limits_copy, |req| {
+                        // a panic while handling the request returns an INTERNAL_ERROR response, instead of closing the connection
+                        AssertUnwindSafe(http_request_handler(
+                            index_path,
+                            apikey_list,
+                            request_limits,
+                            params,
+                            req,
+                            addr,
+                        ))
+                        .catch_unwind()
+                        .map(|result| {
+                            result.unwrap_or_else(|_| {
+                                Ok(status(
+                                    StatusCode::INTERNAL_SERVER_ERROR,
+                                    "internal error while handling the request".to_string(),
+                                ))
+                            })
+                        })
+                    })
+                    .await
+                }
             }))
         }
     });
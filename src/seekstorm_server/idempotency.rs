@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use hyper::{body::Bytes, http::HeaderValue, Body, Method, Request, Response, StatusCode};
+use lazy_static::lazy_static;
+
+use crate::http_server::{calculate_hash, read_body, status, RequestLimits};
+
+/// Request header with a client-generated key, which identifies retries of the same mutating request
+pub(crate) const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header of a replayed response
+const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Maximum number of idempotency keys kept across all API keys, the oldest keys are removed first
+const IDEMPOTENCY_KEYS_MAX: usize = 10_000;
+
+/// Outcome of a request with idempotency key
+enum IdempotencyEntry {
+    /// The request is still executed: a concurrent retry is rejected
+    InProgress,
+    Completed(StoredResponse),
+}
+
+struct StoredResponse {
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+struct IdempotencyRecord {
+    /// Hash of method, path, query and body of the request: a key can't be reused for a different request
+    fingerprint: u64,
+    created: Instant,
+    entry: IdempotencyEntry,
+}
+
+/// Removes the record of a request in progress, if its execution is aborted, e.g. when the client disconnects, so that the retry is executed
+struct InProgressGuard {
+    record_key: Option<(u64, String)>,
+}
+
+impl Drop for InProgressGuard {
+    fn drop(&mut self) {
+        if let Some(record_key) = self.record_key.take() {
+            let mut records = IDEMPOTENCY_RECORDS.lock().unwrap();
+            if matches!(
+                records.get(&record_key),
+                Some(IdempotencyRecord {
+                    entry: IdempotencyEntry::InProgress,
+                    ..
+                })
+            ) {
+                records.remove(&record_key);
+            }
+        }
+    }
+}
+
+lazy_static! {
+    /// Outcomes of the requests with idempotency key, by hash of the API key and idempotency key
+    static ref IDEMPOTENCY_RECORDS: Mutex<HashMap<(u64, String), IdempotencyRecord>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Indexing, update and delete requests of documents, which support the Idempotency-Key header:
+/// index documents, index file, bulk, update and delete documents, delete by query, update by query, and index documents via alias.
+fn is_idempotent_request(parts: &[&str], method: &Method) -> bool {
+    matches!(
+        (parts, method),
+        (
+            [
+                "api",
+                _,
+                "index",
+                _,
+                "doc" | "file" | "bulk" | "update_by_query",
+                ..
+            ],
+            &Method::POST
+        ) | (
+            ["api", _, "index", _, "doc", ..],
+            &Method::PATCH | &Method::DELETE
+        ) | (["api", _, "alias", _, "doc", ..], &Method::POST)
+    )
+}
+
+/// Executes a mutating request at most once per Idempotency-Key header within idempotency_window_secs:
+/// the outcome of the first request is stored, and replayed for retries with the same key, e.g. of a bulk pipeline after a network error,
+/// so that a retried request doesn't index the documents twice. Replayed responses have the header `Idempotent-Replayed: true`.
+/// * A retry while the first request is still executed is rejected with 409 Conflict.
+/// * Reusing a key for a different request (method, path, query or body) is rejected with 422 Unprocessable Entity.
+/// * Server errors (5xx) and 429 Too Many Requests are not stored: the retry is executed.
+///
+/// Requests without the header, other requests, or with idempotency_window_secs = 0 are executed as usual.
+pub(crate) async fn idempotent_request<F, Fut>(
+    req: Request<Body>,
+    request_limits: RequestLimits,
+    handler: F,
+) -> Result<Response<Body>, Infallible>
+where
+    F: FnOnce(Request<Body>) -> Fut,
+    Fut: Future<Output = Result<Response<Body>, Infallible>>,
+{
+    let parts: Vec<&str> = req
+        .uri()
+        .path()
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect();
+    let idempotency_key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(idempotency_key)
+            if request_limits.idempotency_window_secs > 0
+                && is_idempotent_request(&parts, req.method()) =>
+        {
+            idempotency_key.to_str().unwrap_or_default().to_string()
+        }
+        _ => return handler(req).await,
+    };
+    let apikey_hash = calculate_hash(
+        &req.headers()
+            .get("apikey")
+            .and_then(|apikey| apikey.to_str().ok())
+            .unwrap_or_default(),
+    );
+
+    let (request_parts, body) = req.into_parts();
+    let request_bytes = match read_body(body, request_limits.max_request_body_size).await {
+        Ok(request_bytes) => request_bytes,
+        Err(response) => return Ok(response),
+    };
+    let fingerprint = calculate_hash(&(
+        request_parts.method.as_str(),
+        request_parts.uri.to_string(),
+        &request_bytes,
+    ));
+    let record_key = (apikey_hash, idempotency_key);
+
+    {
+        let mut records = IDEMPOTENCY_RECORDS.lock().unwrap();
+        let window = Duration::from_secs(request_limits.idempotency_window_secs);
+        records.retain(|_, record| record.created.elapsed() < window);
+
+        if let Some(record) = records.get(&record_key) {
+            if record.fingerprint != fingerprint {
+                return Ok(status(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Idempotency-Key was already used for a different request".to_string(),
+                ));
+            }
+            return Ok(match &record.entry {
+                IdempotencyEntry::InProgress => status(
+                    StatusCode::CONFLICT,
+                    "a request with the same Idempotency-Key is in progress".to_string(),
+                ),
+                IdempotencyEntry::Completed(stored_response) => {
+                    let mut response = Response::builder()
+                        .status(stored_response.status)
+                        .header(IDEMPOTENT_REPLAYED_HEADER, "true");
+                    if let Some(content_type) = stored_response.content_type.as_ref() {
+                        response = response.header(hyper::header::CONTENT_TYPE, content_type);
+                    }
+                    response
+                        .body(Body::from(stored_response.body.clone()))
+                        .unwrap()
+                }
+            });
+        }
+
+        if records.len() >= IDEMPOTENCY_KEYS_MAX {
+            if let Some(oldest_key) = records
+                .iter()
+                .min_by_key(|(_, record)| record.created)
+                .map(|(key, _)| key.clone())
+            {
+                records.remove(&oldest_key);
+            }
+        }
+        records.insert(
+            record_key.clone(),
+            IdempotencyRecord {
+                fingerprint,
+                created: Instant::now(),
+                entry: IdempotencyEntry::InProgress,
+            },
+        );
+    }
+
+    let mut in_progress_guard = InProgressGuard {
+        record_key: Some(record_key.clone()),
+    };
+    let response = handler(Request::from_parts(
+        request_parts,
+        Body::from(request_bytes),
+    ))
+    .await?;
+
+    let (response_parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body).await;
+    in_progress_guard.record_key = None;
+    let mut records = IDEMPOTENCY_RECORDS.lock().unwrap();
+    match body {
+        Ok(body)
+            if !response_parts.status.is_server_error()
+                && response_parts.status != StatusCode::TOO_MANY_REQUESTS =>
+        {
+            if let Some(record) = records.get_mut(&record_key) {
+                record.entry = IdempotencyEntry::Completed(StoredResponse {
+                    status: response_parts.status,
+                    content_type: response_parts
+                        .headers
+                        .get(hyper::header::CONTENT_TYPE)
+                        .cloned(),
+                    body: body.clone(),
+                });
+            }
+            Ok(Response::from_parts(response_parts, Body::from(body)))
+        }
+        Ok(body) => {
+            records.remove(&record_key);
+            Ok(Response::from_parts(response_parts, Body::from(body)))
+        }
+        Err(e) => {
+            records.remove(&record_key);
+            Ok(status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use seekstorm::index::IndexArc;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_endpoints::{
+        commit_index_api, compact_index_api, delete_index_api, freeze_index_api, save_apikey_data,
+    },
+    multi_tenancy::ApikeyObject,
+};
+
+/// Subdirectory of the API key directory, where the snapshots of the lifecycle policies are stored
+pub(crate) const SNAPSHOT_PATH: &str = "snapshots";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Index lifecycle management policy of an API key, e.g. for log retention: the actions are applied by a scheduler of the server to the matching indices,
+/// when their age (days since index creation) reaches the threshold of the action. Actions without a threshold are skipped. In the order of the phases:
+/// - hot: the index is written and searched without restrictions
+/// - optimize: the index is compacted, if it has deleted documents
+/// - freeze: the index is frozen to read-only
+/// - snapshot: the index is exported with its raw index files (Index::export_index) to the snapshots directory of the API key, from where it can be restored with import_index
+/// - delete: the index is deleted (moved to the trash of the API key, if the server has a trash retention)
+///
+/// The current index of a rollover alias is never deleted.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct LifecyclePolicy {
+    #[serde(default)]
+    pub name: String,
+    /// Index names the policy applies to, with `*` as wildcard, e.g. `logs-*`. An index is managed by the first matching policy.
+    pub index_pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimize_after_days: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze_after_days: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_after_days: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_after_days: Option<u64>,
+}
+
+/// Matches an index name against a pattern with `*` wildcards, each matching any number of characters
+fn is_pattern_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Creates or replaces a lifecycle policy of the API key
+pub(crate) fn set_lifecycle_policy_api(
+    index_path: &PathBuf,
+    policy_name: &str,
+    mut policy: LifecyclePolicy,
+    apikey_object: &mut ApikeyObject,
+) -> Result<LifecyclePolicy, String> {
+    if policy_name.is_empty() || policy.index_pattern.is_empty() {
+        return Err("policy name or index_pattern is empty".to_string());
+    }
+    let days = [
+        policy.optimize_after_days,
+        policy.freeze_after_days,
+        policy.snapshot_after_days,
+        policy.delete_after_days,
+    ];
+    if days.iter().all(Option::is_none) {
+        return Err("policy has no action".to_string());
+    }
+    if !days.iter().flatten().is_sorted() {
+        return Err("the days of the actions have to be ascending in the order optimize, freeze, snapshot, delete".to_string());
+    }
+
+    policy.name = policy_name.to_string();
+    match apikey_object
+        .lifecycle_policies
+        .iter_mut()
+        .find(|lifecycle_policy| lifecycle_policy.name == policy_name)
+    {
+        Some(lifecycle_policy) => *lifecycle_policy = policy.clone(),
+        None => apikey_object.lifecycle_policies.push(policy.clone()),
+    }
+    save_apikey_data(apikey_object, index_path)?;
+
+    Ok(policy)
+}
+
+/// Deletes a lifecycle policy of the API key, the managed indices remain in their current phase
+pub(crate) fn delete_lifecycle_policy_api(
+    index_path: &PathBuf,
+    policy_name: &str,
+    apikey_object: &mut ApikeyObject,
+) -> Result<u64, String> {
+    let policies_len = apikey_object.lifecycle_policies.len();
+    apikey_object
+        .lifecycle_policies
+        .retain(|lifecycle_policy| lifecycle_policy.name != policy_name);
+    if apikey_object.lifecycle_policies.len() == policies_len {
+        return Err("policy not found".to_string());
+    }
+    save_apikey_data(apikey_object, index_path)?;
+
+    Ok(apikey_object.lifecycle_policies.len() as u64)
+}
+
+fn is_due(after_days: Option<u64>, age: u64) -> bool {
+    after_days.is_some_and(|after_days| age >= after_days * SECONDS_PER_DAY)
+}
+
+/// Applies the due actions of a lifecycle policy to an index, in the order of the phases. Returns true if the index is due for deletion.
+async fn apply_lifecycle_actions(
+    policy: &LifecyclePolicy,
+    index_arc: &IndexArc,
+    snapshot_path: &Path,
+    age: u64,
+) -> Result<bool, String> {
+    let index_ref = index_arc.read().await;
+    let (frozen, deleted_doc_count) = (index_ref.frozen, index_ref.deleted_doc_count());
+    drop(index_ref);
+
+    if is_due(policy.optimize_after_days, age) && !frozen && deleted_doc_count > 0 {
+        compact_index_api(index_arc).await?;
+    }
+    if is_due(policy.freeze_after_days, age) && !frozen {
+        freeze_index_api(index_arc).await?;
+    }
+    if is_due(policy.snapshot_after_days, age) && !snapshot_path.exists() {
+        if index_arc.read().await.uncommitted {
+            commit_index_api(index_arc).await?;
+        }
+        if let Err(e) = index_arc.read().await.export_index(snapshot_path, true) {
+            let _ = fs::remove_dir_all(snapshot_path);
+            return Err(e);
+        }
+    }
+
+    Ok(is_due(policy.delete_after_days, age))
+}
+
+/// Applies the lifecycle policies of all API keys to their indices, invoked periodically by the server
+pub(crate) async fn apply_lifecycle_policies(
+    index_path: &Path,
+    apikey_list: &Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
+    trash_retention_hours: u64,
+) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let apikey_hashes: Vec<u128> = apikey_list.read().await.keys().copied().collect();
+    for apikey_hash in apikey_hashes {
+        let apikey_list_ref = apikey_list.read().await;
+        let Some(apikey_object) = apikey_list_ref.get(&apikey_hash) else {
+            continue;
+        };
+        if apikey_object.lifecycle_policies.is_empty() {
+            continue;
+        }
+        let apikey_path = index_path.join(apikey_object.id.to_string());
+        let mut managed_indices = Vec::new();
+        for (index_id, index_arc) in apikey_object.index_list.iter() {
+            let index_name = index_arc.read().await.meta.name.clone();
+            if let Some(policy) = apikey_object
+                .lifecycle_policies
+                .iter()
+                .find(|policy| is_pattern_match(&policy.index_pattern, &index_name))
+            {
+                managed_indices.push((*index_id, index_arc.clone(), policy.clone()));
+            }
+        }
+        let write_index_ids: Vec<u64> = apikey_object
+            .aliases
+            .iter()
+            .map(|alias| alias.current_index_id())
+            .collect();
+        drop(apikey_list_ref);
+
+        for (index_id, index_arc, policy) in managed_indices {
+            let created = index_arc.read().await.meta.created;
+            let age = now.saturating_sub(created);
+            let snapshot_path = apikey_path
+                .join(SNAPSHOT_PATH)
+                .join(format!("{}_{}", index_id, created));
+
+            match apply_lifecycle_actions(&policy, &index_arc, &snapshot_path, age).await {
+                Ok(true) if !write_index_ids.contains(&index_id) => {
+                    let mut apikey_list_mut = apikey_list.write().await;
+                    let Some(apikey_object) = apikey_list_mut.get_mut(&apikey_hash) else {
+                        break;
+                    };
+                    if let Err(e) =
+                        delete_index_api(index_path, index_id, apikey_object, trash_retention_hours)
+                            .await
+                    {
+                        println!("lifecycle policy {} index {}: {}", policy.name, index_id, e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => println!("lifecycle policy {} index {}: {}", policy.name, index_id, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use seekstorm::commit::Commit;
+
+use crate::{http_server::RequestLimits, multi_tenancy::ApikeyObject};
+
+/// Interval of measuring the memory usage of all open indices, and of checking the memory budget by waiting indexing requests
+pub(crate) const MEMORY_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Estimated resident memory of all open indices of all API keys in bytes, measured every MEMORY_CHECK_INTERVAL
+static MEMORY_USAGE: AtomicU64 = AtomicU64::new(0);
+
+/// Estimated resident memory of all open indices of all API keys in bytes (see Index::resident_bytes)
+pub(crate) fn memory_usage() -> u64 {
+    MEMORY_USAGE.load(Ordering::Relaxed)
+}
+
+/// Measures the memory usage of all open indices, invoked periodically by the server.
+/// If the memory budget is exceeded, the index with the most uncommitted memory is committed,
+/// which releases its RAM buffers, so that indexing requests waiting for the memory budget can proceed.
+pub(crate) async fn update_memory_usage(
+    apikey_list: &Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
+    memory_budget: u64,
+) {
+    let index_arc_vec: Vec<_> = apikey_list
+        .read()
+        .await
+        .values()
+        .flat_map(|apikey_object| apikey_object.index_list.values().cloned())
+        .collect();
+
+    let mut usage = 0;
+    let mut largest_uncommitted = None;
+    for index_arc in index_arc_vec {
+        let index_ref = index_arc.read().await;
+        usage += index_ref.resident_bytes();
+        let uncommitted_bytes = index_ref.uncommitted_bytes();
+        drop(index_ref);
+        if uncommitted_bytes > 0
+            && largest_uncommitted
+                .as_ref()
+                .is_none_or(|(bytes, _)| uncommitted_bytes > *bytes)
+        {
+            largest_uncommitted = Some((uncommitted_bytes, index_arc));
+        }
+    }
+    MEMORY_USAGE.store(usage, Ordering::Relaxed);
+
+    if memory_budget > 0 && usage > memory_budget {
+        if let Some((_, mut index_arc)) = largest_uncommitted {
+            if let Err(e) = index_arc.commit().await {
+                println!("index not compacted: {}", e);
+            }
+        }
+    }
+}
+
+/// Indexing backpressure: waits until the memory usage of all open indices is within the memory budget of the server,
+/// at most memory_budget_wait_ms. Returns an error if the memory budget is still exceeded, which is returned as 429 Too Many Requests,
+/// so that a single API key bulk-loading documents can't exhaust the memory of the server.
+pub(crate) async fn wait_for_memory_budget(request_limits: &RequestLimits) -> Result<(), String> {
+    if request_limits.memory_budget == 0 {
+        return Ok(());
+    }
+
+    let start_time = Instant::now();
+    while memory_usage() > request_limits.memory_budget {
+        if start_time.elapsed() >= Duration::from_millis(request_limits.memory_budget_wait_ms) {
+            return Err(format!(
+                "memory budget of {} bytes exceeded: {} bytes used by the indices, retry later",
+                request_limits.memory_budget,
+                memory_usage()
+            ));
+        }
+        tokio::time::sleep(MEMORY_CHECK_INTERVAL).await;
+    }
+    Ok(())
+}
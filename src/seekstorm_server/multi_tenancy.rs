@@ -1,10 +1,16 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
 
 use base64::{engine::general_purpose, Engine as _};
-use seekstorm::index::IndexArc;
+use seekstorm::{index::IndexArc, search::FacetFilter};
 use serde::{Deserialize, Serialize};
 
-use crate::http_server::calculate_hash;
+use crate::{
+    http_server::calculate_hash, lifecycle::LifecyclePolicy, rollover::RolloverAlias,
+    search_permits::SearchPermitPools, tasks::TaskList,
+};
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub(crate) struct ApikeyQuotaObject {
@@ -15,16 +21,103 @@ pub(crate) struct ApikeyQuotaObject {
     pub rate_limit: u64,
 }
 
+/// Maximum number of recent queries kept per apikey for the dashboard
+pub(crate) const RECENT_QUERIES_MAX: usize = 100;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct RecentQuery {
+    pub index_id: u64,
+    pub query: String,
+    pub count_total: usize,
+    pub time: u128,
+    pub timestamp: i64,
+}
+
+/// Window of the query rate of an apikey in seconds
+const QUERY_RATE_WINDOW: i64 = 60;
+
+/// Query rate of an apikey: number of queries per second within the last QUERY_RATE_WINDOW seconds
+#[derive(Debug, Default)]
+pub(crate) struct QueryRate {
+    /// Unix timestamp in seconds and number of queries within that second, in ascending order of timestamps
+    seconds: VecDeque<(i64, u64)>,
+}
+
+impl QueryRate {
+    pub(crate) fn record(&mut self, timestamp: i64) {
+        match self.seconds.back_mut() {
+            Some((second, count)) if *second == timestamp => *count += 1,
+            _ => self.seconds.push_back((timestamp, 1)),
+        }
+        while self
+            .seconds
+            .front()
+            .is_some_and(|(second, _)| *second <= timestamp - QUERY_RATE_WINDOW)
+        {
+            self.seconds.pop_front();
+        }
+    }
+
+    /// Mean number of queries per second within the last QUERY_RATE_WINDOW seconds
+    pub(crate) fn qps(&self, timestamp: i64) -> f64 {
+        self.seconds
+            .iter()
+            .filter(|(second, _)| *second > timestamp - QUERY_RATE_WINDOW)
+            .map(|(_, count)| count)
+            .sum::<u64>() as f64
+            / QUERY_RATE_WINDOW as f64
+    }
+}
+
+/// Scoped API key: read-only access to the indices of its parent API key, restricted by a security filter and hidden fields.
+/// Every search and get document request with the scoped API key is intersected with the facet_filter,
+/// e.g. for row-level security of multiple tenants sharing an index.
+/// Hidden fields are never returned, highlighted, searched, faceted, filtered or sorted by, e.g. internal_notes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct ScopedApikeyObject {
+    pub apikey_hash: u128,
+    #[serde(default)]
+    pub facet_filter: Vec<FacetFilter>,
+    #[serde(default)]
+    pub hidden_fields: Vec<String>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub(crate) struct ApikeyObject {
     pub id: u64,
     pub apikey_hash: u128,
     pub quota: ApikeyQuotaObject,
 
+    #[serde(default)]
+    pub scoped_apikeys: Vec<ScopedApikeyObject>,
+
+    #[serde(default)]
+    pub aliases: Vec<RolloverAlias>,
+
+    #[serde(default)]
+    pub lifecycle_policies: Vec<LifecyclePolicy>,
+
+    /// Custom metadata of the application, an arbitrary JSON value stored with the API key, e.g. owner info or UI configuration
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+
     #[serde(skip)]
     pub index_list: HashMap<u64, IndexArc>,
+
+    #[serde(skip)]
+    pub recent_queries: Arc<Mutex<VecDeque<RecentQuery>>>,
+
+    #[serde(skip)]
+    pub query_rate: Arc<Mutex<QueryRate>>,
+
+    #[serde(skip)]
+    pub tasks: Arc<Mutex<TaskList>>,
+
+    #[serde(skip)]
+    pub search_permits: Arc<SearchPermitPools>,
 }
 
+/// Returns the hash of the API key, or of the parent API key for a scoped API key
 pub(crate) async fn get_apikey_hash(
     api_key_base64: String,
     apikey_list: &Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
@@ -37,9 +130,33 @@ pub(crate) async fn get_apikey_hash(
             if apikey_list_ref.contains_key(&apikey_hash) {
                 Some(apikey_hash)
             } else {
-                None
+                apikey_list_ref
+                    .values()
+                    .find(|apikey_object| {
+                        apikey_object
+                            .scoped_apikeys
+                            .iter()
+                            .any(|scoped_apikey| scoped_apikey.apikey_hash == apikey_hash)
+                    })
+                    .map(|apikey_object| apikey_object.apikey_hash)
             }
         }
         Err(_e) => None,
     }
 }
+
+/// Returns the scoped API key object, or None for any other API key
+pub(crate) async fn get_scoped_apikey(
+    api_key_base64: String,
+    apikey_list: &Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
+) -> Option<ScopedApikeyObject> {
+    let apikey = general_purpose::STANDARD.decode(api_key_base64).ok()?;
+    let apikey_hash = calculate_hash(&apikey) as u128;
+    let apikey_list_ref = apikey_list.read().await;
+
+    apikey_list_ref
+        .values()
+        .flat_map(|apikey_object| apikey_object.scoped_apikeys.iter())
+        .find(|scoped_apikey| scoped_apikey.apikey_hash == apikey_hash)
+        .cloned()
+}
@@ -0,0 +1,312 @@
+use std::{collections::HashMap, ops::Range, path::PathBuf, sync::Arc};
+
+use chrono::Utc;
+use seekstorm::{
+    date::{date_interval_range, parse_date, parse_date_math, DateInterval},
+    error::SeekStormError,
+    index::{FieldType, IndexArc, SchemaField},
+    search::FacetFilter,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    api_endpoints::save_apikey_data,
+    multi_tenancy::ApikeyObject,
+    rollover::{alias_write_index, create_index_like, RolloverAlias},
+};
+
+/// Default maximum number of partition indices of a partitioned alias, see PartitionBy.max_partitions
+pub(crate) const DEFAULT_MAX_PARTITIONS: usize = 1_000;
+
+fn default_max_partitions() -> usize {
+    DEFAULT_MAX_PARTITIONS
+}
+
+/// Partitioning of an alias by a field: each document written via the alias is routed to the partition index of its field value,
+/// which is created on demand with the schema and settings of the index of the alias, e.g. logs-2024-05 for the documents of May 2024.
+/// Searches via the alias skip the partitions which can't match the range or value filters of the partition field,
+/// which speeds up time-bounded queries over years of data.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct PartitionBy {
+    /// Date or Timestamp field partitioned by calendar interval, or String or Keyword field partitioned by value
+    pub field: String,
+    /// Calendar interval of the partitions of a Date or Timestamp field, e.g. Month or Year. Not used for String and Keyword fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<DateInterval>,
+    /// Maximum number of partition indices of the alias, default 1000. A write which would create more partitions is rejected,
+    /// which protects against a partition field with unbounded values, e.g. a unique id instead of a category.
+    #[serde(default = "default_max_partitions")]
+    pub max_partitions: usize,
+}
+
+/// Partition index of a partitioned alias
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Partition {
+    /// Value of a String or Keyword field, or label of the date interval, e.g. "2024-05" for Month
+    pub key: String,
+    pub index_id: u64,
+    /// Start (inclusive) and end (exclusive) of the date interval of the partition in Unix milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range<i64>>,
+}
+
+impl PartitionBy {
+    /// Checks that the partition field exists in the schema, and that only Date and Timestamp fields have an interval
+    pub(crate) fn check(&self, schema: &[SchemaField]) -> Result<(), SeekStormError> {
+        let Some(schema_field) = schema
+            .iter()
+            .find(|schema_field| schema_field.field == self.field)
+        else {
+            return Err(SeekStormError::InvalidRequest(format!(
+                "partition field not found: {}",
+                self.field
+            )));
+        };
+        if self.max_partitions == 0 {
+            return Err(SeekStormError::InvalidRequest(
+                "max_partitions has to be greater than 0".to_string(),
+            ));
+        }
+        match (&schema_field.field_type, self.interval) {
+            (FieldType::Date | FieldType::Timestamp, Some(_)) => Ok(()),
+            (FieldType::String | FieldType::Keyword, None) => Ok(()),
+            (FieldType::Date | FieldType::Timestamp, None) => Err(SeekStormError::InvalidRequest(
+                "interval is required for Date and Timestamp partition fields".to_string(),
+            )),
+            (field_type, _) => Err(SeekStormError::InvalidRequest(format!(
+                "partition field has to be of type Date or Timestamp with interval, or String or Keyword without: {} is {:?}",
+                self.field, field_type
+            ))),
+        }
+    }
+
+    /// Partition key and date range of a document, None if the document has no valid value of the partition field
+    fn partition_of(
+        &self,
+        document: &Value,
+        schema_field: &SchemaField,
+    ) -> Option<(String, Option<Range<i64>>)> {
+        let value = match document.get(&self.field)? {
+            Value::Array(values) => values.first()?,
+            value => value,
+        };
+        match self.interval {
+            Some(interval) => {
+                let date = match (&schema_field.field_type, value) {
+                    (FieldType::Timestamp, value) => value.as_i64()?.checked_mul(1000)?,
+                    (_, Value::Number(number)) => number.as_i64()?,
+                    (_, Value::String(string)) => parse_date(string, &schema_field.date_formats)?,
+                    _ => return None,
+                };
+                let (label, start, end) = date_interval_range(date, interval)?;
+                Some((label, Some(start..end)))
+            }
+            None => match value {
+                Value::String(string) if !string.is_empty() => Some((string.clone(), None)),
+                _ => None,
+            },
+        }
+    }
+
+    /// The partition may contain documents matching the facet filter: the date range of the partition overlaps the Date or Timestamp range filters of the partition field,
+    /// or the key of the partition is one of the values of the String filters of the partition field. Other filters don't prune partitions.
+    /// Date filters are parsed with the date_formats of the partition field, like the filter of the search itself.
+    fn may_match(
+        &self,
+        partition: &Partition,
+        date_formats: &[String],
+        facet_filter: &[FacetFilter],
+    ) -> bool {
+        facet_filter.iter().all(|filter| {
+            let filter_range = match filter {
+                FacetFilter::Date { field, filter } if *field == self.field => {
+                    let now = Utc::now().timestamp_millis();
+                    match (
+                        parse_date_math(&filter.start, now, date_formats),
+                        parse_date_math(&filter.end, now, date_formats),
+                    ) {
+                        (Some(start), Some(end)) => start..end,
+                        _ => return true,
+                    }
+                }
+                FacetFilter::Timestamp { field, filter } if *field == self.field => {
+                    filter.start.saturating_mul(1000)..filter.end.saturating_mul(1000)
+                }
+                FacetFilter::String { field, filter } if *field == self.field => {
+                    return partition.range.is_some() || filter.contains(&partition.key);
+                }
+                _ => return true,
+            };
+            partition.range.as_ref().is_none_or(|range| {
+                range.start < filter_range.end && filter_range.start < range.end
+            })
+        })
+    }
+}
+
+/// Ids of the indices searched by a query via the alias: all indices of a rollover alias, or of a partitioned alias the index of the alias,
+/// with the documents without partition field value, and the partitions which may match the facet filter (see PartitionBy).
+/// The schema is the schema of the index of the alias, which is shared by its partitions.
+pub(crate) fn alias_search_index_ids(
+    alias: &RolloverAlias,
+    schema: &[SchemaField],
+    facet_filter: &[FacetFilter],
+) -> Vec<u64> {
+    let mut index_ids: Vec<u64> = alias.index_ids.iter().rev().copied().collect();
+    if let Some(partition_by) = alias.partition_by.as_ref() {
+        let date_formats = schema
+            .iter()
+            .find(|schema_field| schema_field.field == partition_by.field)
+            .map(|schema_field| schema_field.date_formats.as_slice())
+            .unwrap_or_default();
+        index_ids.extend(
+            alias
+                .partitions
+                .iter()
+                .filter(|partition| partition_by.may_match(partition, date_formats, facet_filter))
+                .map(|partition| partition.index_id),
+        );
+    }
+    index_ids
+}
+
+/// Indices of an alias for writing documents, with the positions of the documents written to each index.
+/// A rollover alias writes all documents to its current index (see alias_write_index). A partitioned alias writes each document to the partition of its
+/// partition field value, and creates the missing partition indices. Documents without a valid value of the partition field are written to the index of the alias.
+/// A write which would exceed PartitionBy.max_partitions is rejected as a whole, before any partition is created.
+pub(crate) async fn alias_write_indices(
+    index_path: &PathBuf,
+    alias_name: &str,
+    apikey_hash: u128,
+    apikey_list: &Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
+    documents: &[Value],
+) -> Result<Vec<(IndexArc, Vec<usize>)>, SeekStormError> {
+    let apikey_list_ref = apikey_list.read().await;
+    let apikey_object = apikey_list_ref
+        .get(&apikey_hash)
+        .ok_or_else(|| SeekStormError::NotFound("api_key does not exists".to_string()))?;
+    let alias = apikey_object
+        .aliases
+        .iter()
+        .find(|alias| alias.name == alias_name)
+        .ok_or_else(|| SeekStormError::NotFound("alias not found".to_string()))?
+        .clone();
+    let Some(partition_by) = alias.partition_by.as_ref() else {
+        drop(apikey_list_ref);
+        let (_index_id, index_arc) =
+            alias_write_index(index_path, alias_name, apikey_hash, apikey_list).await?;
+        return Ok(vec![(index_arc, (0..documents.len()).collect())]);
+    };
+    let alias_index_arc = apikey_object
+        .index_list
+        .get(&alias.current_index_id())
+        .ok_or_else(|| SeekStormError::NotFound("index of the alias not found".to_string()))?
+        .clone();
+    drop(apikey_list_ref);
+
+    let schema = alias_index_arc.read().await.get_schema();
+    let Some(schema_field) = schema
+        .iter()
+        .find(|schema_field| schema_field.field == partition_by.field)
+    else {
+        return Err(SeekStormError::NotFound(format!(
+            "partition field not found: {}",
+            partition_by.field
+        )));
+    };
+
+    let mut unpartitioned = Vec::new();
+    let mut partitioned: Vec<(String, Option<Range<i64>>, Vec<usize>)> = Vec::new();
+    for (position, document) in documents.iter().enumerate() {
+        match partition_by.partition_of(document, schema_field) {
+            Some((key, range)) => {
+                match partitioned
+                    .iter_mut()
+                    .find(|(partition_key, _, _)| *partition_key == key)
+                {
+                    Some((_, _, positions)) => positions.push(position),
+                    None => partitioned.push((key, range, vec![position])),
+                }
+            }
+            None => unpartitioned.push(position),
+        }
+    }
+
+    if partitioned.iter().any(|(key, _, _)| {
+        !alias
+            .partitions
+            .iter()
+            .any(|partition| partition.key == *key)
+    }) {
+        let mut apikey_list_mut = apikey_list.write().await;
+        let apikey_object = apikey_list_mut
+            .get_mut(&apikey_hash)
+            .ok_or_else(|| SeekStormError::NotFound("api_key does not exists".to_string()))?;
+        // a concurrent write may have created some of the partitions in the meantime
+        let partitions = &apikey_object
+            .aliases
+            .iter()
+            .find(|alias| alias.name == alias_name)
+            .ok_or_else(|| SeekStormError::NotFound("alias not found".to_string()))?
+            .partitions;
+        let missing: Vec<_> = partitioned
+            .iter()
+            .filter(|(key, _, _)| !partitions.iter().any(|partition| partition.key == *key))
+            .collect();
+        if partitions.len() + missing.len() > partition_by.max_partitions {
+            return Err(SeekStormError::QuotaExceeded(format!(
+                "alias {} exceeds the maximum of {} partitions",
+                alias_name, partition_by.max_partitions
+            )));
+        }
+        for (key, range, _) in missing {
+            let index_id = create_index_like(
+                index_path,
+                format!("{}-{}", alias_name, key),
+                &alias_index_arc,
+                apikey_object,
+            )
+            .await?;
+            apikey_object
+                .aliases
+                .iter_mut()
+                .find(|alias| alias.name == alias_name)
+                .unwrap()
+                .partitions
+                .push(Partition {
+                    key: key.clone(),
+                    index_id,
+                    range: range.clone(),
+                });
+        }
+        save_apikey_data(apikey_object, index_path)?;
+    }
+
+    let apikey_list_ref = apikey_list.read().await;
+    let apikey_object = apikey_list_ref
+        .get(&apikey_hash)
+        .ok_or_else(|| SeekStormError::NotFound("api_key does not exists".to_string()))?;
+    let alias = apikey_object
+        .aliases
+        .iter()
+        .find(|alias| alias.name == alias_name)
+        .ok_or_else(|| SeekStormError::NotFound("alias not found".to_string()))?;
+
+    let mut write_indices = Vec::new();
+    if !unpartitioned.is_empty() {
+        write_indices.push((alias_index_arc, unpartitioned));
+    }
+    for (key, _, positions) in partitioned {
+        let index_arc = alias
+            .partitions
+            .iter()
+            .find(|partition| partition.key == key)
+            .and_then(|partition| apikey_object.index_list.get(&partition.index_id))
+            .ok_or_else(|| {
+                SeekStormError::NotFound(format!("partition index not found: {}", key))
+            })?;
+        write_indices.push((index_arc.clone(), positions));
+    }
+    Ok(write_indices)
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use seekstorm::{error::SeekStormError, index::IndexArc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_endpoints::{commit_index_api, create_index_api, save_apikey_data},
+    multi_tenancy::ApikeyObject,
+    partition::{Partition, PartitionBy},
+};
+
+/// Thresholds of the current index of a rollover alias: when any of them is reached, the next write via the alias rolls over to a new index.
+/// Without conditions, the alias is only rolled over explicitly.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct RolloverConditions {
+    /// Number of indexed documents, including the uncommitted documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_documents: Option<usize>,
+    /// Bytes on disk of the index files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    /// Seconds since the index became the current index of the alias
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<u64>,
+}
+
+/// Rollover alias: a named time series of indices, e.g. for logs and events.
+/// Documents are written to the current index, the last of the series, which is rolled over to a new index when it reaches a RolloverConditions threshold.
+/// The new index is named `<alias>-<generation>` (e.g. logs-000002) and gets the schema, settings, synonyms and runtime fields of the previous index,
+/// which is committed and remains searchable. Searches via the alias span all indices of the series.
+/// A partitioned alias is not rolled over, but routes the documents to partition indices by a field, and searches only the partitions matching the filters (see PartitionBy).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct RolloverAlias {
+    pub name: String,
+    #[serde(default)]
+    pub conditions: RolloverConditions,
+    /// Index ids of the series, in the order of creation: the last is the current index
+    pub index_ids: Vec<u64>,
+    /// Unix timestamp, in seconds, when the current index became the current index of the alias
+    pub rollover_timestamp: u64,
+    /// Partitioning of a partitioned alias: documents are routed to a partition index per value or date interval of the field (see PartitionBy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_by: Option<PartitionBy>,
+    /// Partition indices of a partitioned alias, created on demand by the writes via the alias
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<Partition>,
+}
+
+impl RolloverAlias {
+    pub(crate) fn current_index_id(&self) -> u64 {
+        *self.index_ids.last().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct CreateAliasRequest {
+    /// Existing index which becomes the first and current index of the series
+    pub index_id: u64,
+    #[serde(default)]
+    pub conditions: RolloverConditions,
+    /// Partitions the alias by a field instead of rolling it over: the index is the template of the partition indices,
+    /// and receives the documents without a value of the partition field
+    #[serde(default)]
+    pub partition_by: Option<PartitionBy>,
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Creates a rollover alias or a partitioned alias for an existing index of the API key
+pub(crate) async fn create_alias_api(
+    index_path: &PathBuf,
+    alias_name: &str,
+    create_alias_request: CreateAliasRequest,
+    apikey_object: &mut ApikeyObject,
+) -> Result<RolloverAlias, SeekStormError> {
+    if alias_name.is_empty() || alias_name.parse::<u64>().is_ok() {
+        return Err(SeekStormError::InvalidRequest(
+            "alias name is empty or numeric".to_string(),
+        ));
+    }
+    if apikey_object
+        .aliases
+        .iter()
+        .any(|alias| alias.name == alias_name)
+    {
+        return Err(SeekStormError::Conflict(format!(
+            "alias {} already exists",
+            alias_name
+        )));
+    }
+    let Some(index_arc) = apikey_object.index_list.get(&create_alias_request.index_id) else {
+        return Err(SeekStormError::IndexNotFound(
+            "index_id not found".to_string(),
+        ));
+    };
+    if let Some(partition_by) = create_alias_request.partition_by.as_ref() {
+        let conditions = &create_alias_request.conditions;
+        if conditions.max_documents.is_some()
+            || conditions.max_size.is_some()
+            || conditions.max_age_seconds.is_some()
+        {
+            return Err(SeekStormError::InvalidRequest(
+                "a partitioned alias has no rollover conditions".to_string(),
+            ));
+        }
+        partition_by.check(&index_arc.read().await.get_schema())?;
+    }
+
+    let alias = RolloverAlias {
+        name: alias_name.to_string(),
+        conditions: create_alias_request.conditions,
+        index_ids: vec![create_alias_request.index_id],
+        rollover_timestamp: unix_timestamp(),
+        partition_by: create_alias_request.partition_by,
+        partitions: Vec::new(),
+    };
+    apikey_object.aliases.push(alias.clone());
+    if let Err(e) = save_apikey_data(apikey_object, index_path) {
+        apikey_object.aliases.pop();
+        return Err(e);
+    }
+
+    Ok(alias)
+}
+
+/// Deletes a rollover alias, the indices of the series are kept
+pub(crate) fn delete_alias_api(
+    index_path: &PathBuf,
+    alias_name: &str,
+    apikey_object: &mut ApikeyObject,
+) -> Result<u64, SeekStormError> {
+    let Some(position) = apikey_object
+        .aliases
+        .iter()
+        .position(|alias| alias.name == alias_name)
+    else {
+        return Err(SeekStormError::NotFound("alias not found".to_string()));
+    };
+    let alias = apikey_object.aliases.remove(position);
+    if let Err(e) = save_apikey_data(apikey_object, index_path) {
+        apikey_object.aliases.insert(position, alias);
+        return Err(e);
+    }
+
+    Ok(apikey_object.aliases.len() as u64)
+}
+
+/// True if the current index of the alias has reached any of the rollover conditions
+async fn is_rollover_due(alias: &RolloverAlias, index_arc: &IndexArc) -> bool {
+    let conditions = &alias.conditions;
+    if conditions.max_age_seconds.is_some_and(|max_age_seconds| {
+        unix_timestamp() >= alias.rollover_timestamp + max_age_seconds
+    }) {
+        return true;
+    }
+
+    let index_ref = index_arc.read().await;
+    conditions
+        .max_documents
+        .is_some_and(|max_documents| index_ref.indexed_doc_count >= max_documents)
+        || conditions
+            .max_size
+            .is_some_and(|max_size| index_ref.get_storage_stats().disk.total >= max_size)
+}
+
+/// Creates a new index with the schema, settings, synonyms and runtime fields of an existing index, e.g. the next index of a rollover alias or a partition.
+/// Returns the id of the new index.
+pub(crate) async fn create_index_like(
+    index_path: &PathBuf,
+    index_name: String,
+    template_index_arc: &IndexArc,
+    apikey_object: &mut ApikeyObject,
+) -> Result<u64, String> {
+    let template_index_ref = template_index_arc.read().await;
+    let meta = template_index_ref.meta.clone();
+    let schema = template_index_ref.get_schema();
+    let synonyms = template_index_ref.get_synonyms().unwrap_or_default();
+    let runtime_fields = template_index_ref.get_runtime_fields();
+    drop(template_index_ref);
+
+    let index_id = create_index_api(
+        index_path,
+        index_name,
+        schema,
+        meta.similarity,
+        meta.tokenizer,
+        synonyms,
+        meta.stemmer,
+        meta.k1,
+        meta.b,
+        meta.compact_deleted_ratio,
+        meta.durability,
+        meta.sync_interval_ms,
+        meta.index_unstemmed,
+        meta.strict_schema,
+        meta.metadata,
+        apikey_object,
+    )?;
+    if !runtime_fields.is_empty() {
+        apikey_object.index_list[&index_id]
+            .write()
+            .await
+            .set_runtime_fields(runtime_fields)?;
+    }
+
+    Ok(index_id)
+}
+
+/// Rolls the alias over to a new current index, with the schema, settings, synonyms and runtime fields of the previous current index, which is committed.
+/// Returns the id of the new current index.
+pub(crate) async fn rollover_api(
+    index_path: &PathBuf,
+    alias_name: &str,
+    apikey_object: &mut ApikeyObject,
+) -> Result<u64, SeekStormError> {
+    let Some(alias) = apikey_object
+        .aliases
+        .iter()
+        .find(|alias| alias.name == alias_name)
+    else {
+        return Err(SeekStormError::NotFound("alias not found".to_string()));
+    };
+    if alias.partition_by.is_some() {
+        return Err(SeekStormError::InvalidRequest(
+            "a partitioned alias can't be rolled over".to_string(),
+        ));
+    }
+    let generation = alias.index_ids.len() + 1;
+    let Some(current_index_arc) = apikey_object
+        .index_list
+        .get(&alias.current_index_id())
+        .cloned()
+    else {
+        return Err(SeekStormError::IndexNotFound(
+            "current index of the alias not found".to_string(),
+        ));
+    };
+
+    commit_index_api(&current_index_arc).await?;
+    let index_id = create_index_like(
+        index_path,
+        format!("{}-{:06}", alias_name, generation),
+        &current_index_arc,
+        apikey_object,
+    )
+    .await?;
+
+    let alias = apikey_object
+        .aliases
+        .iter_mut()
+        .find(|alias| alias.name == alias_name)
+        .unwrap();
+    let rollover_timestamp = std::mem::replace(&mut alias.rollover_timestamp, unix_timestamp());
+    alias.index_ids.push(index_id);
+    if let Err(e) = save_apikey_data(apikey_object, index_path) {
+        let alias = apikey_object
+            .aliases
+            .iter_mut()
+            .find(|alias| alias.name == alias_name)
+            .unwrap();
+        alias.index_ids.pop();
+        alias.rollover_timestamp = rollover_timestamp;
+        return Err(e);
+    }
+
+    Ok(index_id)
+}
+
+/// Returns the id and index of the current index of the alias, for writing documents.
+/// If the current index has reached a rollover condition, the alias is rolled over first.
+pub(crate) async fn alias_write_index(
+    index_path: &PathBuf,
+    alias_name: &str,
+    apikey_hash: u128,
+    apikey_list: &Arc<tokio::sync::RwLock<HashMap<u128, ApikeyObject>>>,
+) -> Result<(u64, IndexArc), String> {
+    let apikey_list_ref = apikey_list.read().await;
+    let apikey_object = apikey_list_ref
+        .get(&apikey_hash)
+        .ok_or("api_key does not exists".to_string())?;
+    let alias = apikey_object
+        .aliases
+        .iter()
+        .find(|alias| alias.name == alias_name)
+        .ok_or("alias not found".to_string())?
+        .clone();
+    let index_arc = apikey_object
+        .index_list
+        .get(&alias.current_index_id())
+        .ok_or("current index of the alias not found".to_string())?
+        .clone();
+    drop(apikey_list_ref);
+
+    if !is_rollover_due(&alias, &index_arc).await {
+        return Ok((alias.current_index_id(), index_arc));
+    }
+
+    let mut apikey_list_mut = apikey_list.write().await;
+    let apikey_object = apikey_list_mut
+        .get_mut(&apikey_hash)
+        .ok_or("api_key does not exists".to_string())?;
+    // a concurrent write may have rolled the alias over in the meantime
+    let current_index_id = apikey_object
+        .aliases
+        .iter()
+        .find(|alias| alias.name == alias_name)
+        .ok_or("alias not found".to_string())?
+        .current_index_id();
+    let index_id = if current_index_id == alias.current_index_id() {
+        rollover_api(index_path, alias_name, apikey_object).await?
+    } else {
+        current_index_id
+    };
+
+    Ok((index_id, apikey_object.index_list[&index_id].clone()))
+}
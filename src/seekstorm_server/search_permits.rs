@@ -0,0 +1,127 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use seekstorm::{error::SeekStormError, search::FacetFilter};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{api_endpoints::SearchRequestObject, http_server::RequestLimits};
+
+/// Expensive searches: with query facets, sorting, score functions, rescoring, diversification or join filters.
+/// Other searches (e.g. keyword searches and lookups by filter) are not limited by the search permits.
+pub(crate) fn is_expensive_search(search_request: &SearchRequestObject) -> bool {
+    !search_request.query_facets.is_empty()
+        || !search_request.result_sort.is_empty()
+        || search_request.function_score.is_some()
+        || search_request.rescore.is_some()
+        || search_request.diversify.is_some()
+        || search_request.facet_filter.iter().any(FacetFilter::is_join)
+}
+
+/// Search permits of an index: at most max_concurrent_searches expensive searches are executed concurrently,
+/// at most max_queued_searches further expensive searches wait for a permit.
+struct SearchPermitPool {
+    semaphore: Arc<Semaphore>,
+    max_concurrent_searches: usize,
+    queued: AtomicUsize,
+}
+
+/// Decrements the number of waiting searches, also if the waiting request is aborted, e.g. when the client disconnects
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Search permit pools of the indices of an API key, by index id.
+/// Limits the concurrent expensive searches per index, so that a burst of heavy faceted queries can't starve indexing and lightweight searches.
+#[derive(Default)]
+pub(crate) struct SearchPermitPools {
+    pools: Mutex<HashMap<u64, Arc<SearchPermitPool>>>,
+}
+
+impl SearchPermitPools {
+    /// Acquires a search permit of the index, which is held until the permit is dropped.
+    /// Waits at most request_timeout_ms for a permit. Returns an error if the queue of waiting searches is full or the wait times out,
+    /// which is returned as 429 Too Many Requests. Returns None if max_concurrent_searches is 0 (unlimited).
+    pub(crate) async fn acquire(
+        &self,
+        index_id: u64,
+        request_limits: &RequestLimits,
+    ) -> Result<Option<OwnedSemaphorePermit>, SeekStormError> {
+        if request_limits.max_concurrent_searches == 0 {
+            return Ok(None);
+        }
+
+        // after a configuration reload with a different max_concurrent_searches the pool is replaced, searches holding a permit of the previous pool complete as usual
+        let pool = self
+            .pools
+            .lock()
+            .unwrap()
+            .entry(index_id)
+            .and_modify(|pool| {
+                if pool.max_concurrent_searches != request_limits.max_concurrent_searches {
+                    *pool = Arc::new(SearchPermitPool::new(
+                        request_limits.max_concurrent_searches,
+                    ));
+                }
+            })
+            .or_insert_with(|| {
+                Arc::new(SearchPermitPool::new(
+                    request_limits.max_concurrent_searches,
+                ))
+            })
+            .clone();
+
+        if let Ok(permit) = pool.semaphore.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+
+        if pool.queued.fetch_add(1, Ordering::Relaxed) >= request_limits.max_queued_searches {
+            pool.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(SeekStormError::QuotaExceeded(format!(
+                "too many concurrent searches: max_concurrent_searches {} and max_queued_searches {} of the index exceeded, retry later",
+                request_limits.max_concurrent_searches, request_limits.max_queued_searches
+            )));
+        }
+        let _queued_guard = QueuedGuard(&pool.queued);
+
+        let permit = pool.semaphore.clone().acquire_owned();
+        let permit = if request_limits.request_timeout_ms > 0 {
+            tokio::time::timeout(
+                Duration::from_millis(request_limits.request_timeout_ms),
+                permit,
+            )
+            .await
+            .map_err(|_| {
+                SeekStormError::QuotaExceeded(format!(
+                    "no search permit within request_timeout_ms of {} ms, retry later",
+                    request_limits.request_timeout_ms
+                ))
+            })?
+        } else {
+            permit.await
+        };
+
+        permit
+            .map(Some)
+            .map_err(|e| SeekStormError::Internal(e.to_string()))
+    }
+}
+
+impl SearchPermitPool {
+    fn new(max_concurrent_searches: usize) -> Self {
+        SearchPermitPool {
+            semaphore: Arc::new(Semaphore::new(max_concurrent_searches)),
+            max_concurrent_searches,
+            queued: AtomicUsize::new(0),
+        }
+    }
+}
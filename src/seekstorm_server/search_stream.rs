@@ -0,0 +1,131 @@
+use hyper::{body::Bytes, Body, Response};
+use seekstorm::index::{Document, IndexArc};
+use serde::Serialize;
+use tokio::sync::OwnedSemaphorePermit;
+
+use crate::{
+    api_endpoints::{PreparedSearch, ScrollResultObject, SearchResultObject},
+    api_version::{ApiVersion, SearchHit, SearchResultObjectV2},
+    encoding::Encoding,
+};
+
+/// Number of result documents fetched, highlighted and sent per chunk of a streamed search response
+const STREAM_CHUNK_SIZE: usize = 100;
+
+/// Appends a value as line of newline-delimited JSON
+fn push_line<T: Serialize>(bytes: &mut Vec<u8>, value: &T) {
+    if serde_json::to_writer(&mut *bytes, value).is_ok() {
+        bytes.push(b'\n');
+    }
+}
+
+/// Appends the value without its results field (results or hits) as first line of a newline-delimited JSON search response
+fn push_header_line<T: Serialize>(bytes: &mut Vec<u8>, value: &T, results_field: &str) {
+    let mut header = serde_json::to_value(value).unwrap_or_default();
+    if let Some(header) = header.as_object_mut() {
+        header.remove(results_field);
+    }
+    push_line(bytes, &header);
+}
+
+/// Appends the result documents as lines of a newline-delimited JSON search response, in the format of the API version
+fn push_result_lines(bytes: &mut Vec<u8>, results: Vec<Document>, api_version: ApiVersion) {
+    for document in results {
+        match api_version {
+            ApiVersion::V1 => push_line(bytes, &document),
+            ApiVersion::V2 => push_line(bytes, &SearchHit::from(document)),
+        }
+    }
+}
+
+/// First line of a newline-delimited JSON search response: the search result in the format of the API version, without the results
+fn search_header_line(search_result: &SearchResultObject, api_version: ApiVersion) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match api_version {
+        ApiVersion::V1 => push_header_line(&mut bytes, search_result, "results"),
+        ApiVersion::V2 => push_header_line(
+            &mut bytes,
+            &SearchResultObjectV2::from(SearchResultObject {
+                results: Vec::new(),
+                ..search_result.clone()
+            }),
+            "hits",
+        ),
+    }
+    bytes
+}
+
+/// Search response as newline-delimited JSON (Encoding::Ndjson): the search result without the results as first line, followed by a line per result.
+pub(crate) fn ndjson_search_response(
+    mut search_result: SearchResultObject,
+    api_version: ApiVersion,
+) -> Response<Body> {
+    let results = std::mem::take(&mut search_result.results);
+    let mut bytes = search_header_line(&search_result, api_version);
+    push_result_lines(&mut bytes, results, api_version);
+    Encoding::Ndjson.bytes_response(bytes)
+}
+
+/// Scroll response as newline-delimited JSON: the scroll result (with the scroll_id of the next page) without the results as first line, followed by a line per result.
+pub(crate) fn ndjson_scroll_response(mut scroll_result: ScrollResultObject) -> Response<Body> {
+    let results = std::mem::take(&mut scroll_result.results);
+    let mut bytes = Vec::new();
+    push_header_line(&mut bytes, &scroll_result, "results");
+    push_result_lines(&mut bytes, results, ApiVersion::V1);
+    Encoding::Ndjson.bytes_response(bytes)
+}
+
+/// Streams the search response as newline-delimited JSON: the first line (search_result without results) is sent immediately,
+/// the documents of the results are fetched, highlighted and sent in chunks of STREAM_CHUNK_SIZE, while the client receives the previous chunks.
+/// Instead of buffering the whole response, the memory of the server is limited to a chunk, and the client receives the first results earlier.
+/// The index is only locked while a chunk is fetched. If the index is compacted or cleared while streaming,
+/// the document ids of the remaining results are no longer current and the response ends with an `{"error":...}` line.
+/// The search permit of an expensive search (see SearchPermitPools) is held until all chunks are sent.
+pub(crate) fn stream_search_response(
+    index_arc: IndexArc,
+    mut prepared_search: PreparedSearch,
+    search_result: SearchResultObject,
+    hidden_fields: Vec<String>,
+    api_version: ApiVersion,
+    search_permit: Option<OwnedSemaphorePermit>,
+) -> Response<Body> {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let _search_permit = search_permit;
+        let header_line = search_header_line(&search_result, api_version);
+        if sender.send_data(Bytes::from(header_line)).await.is_err() {
+            return;
+        }
+
+        let result_count = prepared_search.result_count();
+        for chunk_start in (0..result_count).step_by(STREAM_CHUNK_SIZE) {
+            let chunk_end = (chunk_start + STREAM_CHUNK_SIZE).min(result_count);
+            let index_ref = index_arc.read().await;
+            let results = prepared_search.result_documents(
+                &index_ref,
+                chunk_start..chunk_end,
+                &hidden_fields,
+            );
+            drop(index_ref);
+
+            let mut bytes = Vec::new();
+            let Some(results) = results else {
+                push_line(
+                    &mut bytes,
+                    &serde_json::json!({"error": "index was compacted or cleared while streaming the results"}),
+                );
+                let _ = sender.send_data(Bytes::from(bytes)).await;
+                return;
+            };
+            push_result_lines(&mut bytes, results, api_version);
+            if sender.send_data(Bytes::from(bytes)).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Response::builder()
+        .header(hyper::header::CONTENT_TYPE, Encoding::Ndjson.content_type())
+        .body(body)
+        .unwrap()
+}
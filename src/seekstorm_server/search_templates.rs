@@ -0,0 +1,218 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+use seekstorm::{error::SeekStormError, index::IndexArc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api_endpoints::{save_file_atomically, SearchRequestObject};
+
+/// Stored in the index directory, so that the templates are deleted, moved to the trash and restored together with the index
+pub(crate) const SEARCH_TEMPLATES_FILENAME: &str = "search_templates.json";
+
+/// Parameters of a search via a stored search template
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub(crate) struct TemplateSearchRequest {
+    /// Values of the placeholders of the template, by parameter name
+    #[serde(default)]
+    pub params: HashMap<String, Value>,
+}
+
+/// Names of the placeholders {{name}} of a string, or an error if a placeholder is not closed or has an empty name
+fn placeholders(text: &str) -> Result<Vec<&str>, SeekStormError> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            return Err(SeekStormError::InvalidRequest(format!(
+                "placeholder not closed: {}",
+                text
+            )));
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        if name.is_empty() {
+            return Err(SeekStormError::InvalidRequest(format!(
+                "placeholder without name: {}",
+                text
+            )));
+        }
+        names.push(name);
+        rest = &rest[start + 2 + end + 2..];
+    }
+    Ok(names)
+}
+
+/// Replaces the placeholders in the string values of the template, recursively.
+/// A string consisting of a single placeholder is replaced by the parameter value with its JSON type, e.g. "length":"{{size}}" with a number,
+/// otherwise the placeholders are replaced by the parameter values within the string, e.g. "query":"{{term}} -discontinued".
+/// Keys are not replaced. As the values are inserted into the parsed template, they can't change the structure of the request.
+fn render(template: &Value, params: &HashMap<String, Value>) -> Result<Value, SeekStormError> {
+    let param = |name: &str| {
+        params.get(name).ok_or_else(|| {
+            SeekStormError::QueryParseError(format!("template parameter missing: {}", name))
+        })
+    };
+
+    match template {
+        Value::String(text) => {
+            let names = placeholders(text)?;
+            if names.is_empty() {
+                return Ok(template.clone());
+            }
+            if names.len() == 1 {
+                let trimmed = text.trim();
+                if trimmed.starts_with("{{") && trimmed.ends_with("}}") {
+                    return param(names[0]).cloned();
+                }
+            }
+
+            let mut rendered = String::new();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let end = start + 2 + rest[start + 2..].find("}}").unwrap();
+                rendered.push_str(&rest[..start]);
+                match param(rest[start + 2..end].trim())? {
+                    Value::String(value) => rendered.push_str(value),
+                    value => rendered.push_str(&value.to_string()),
+                }
+                rest = &rest[end + 2..];
+            }
+            rendered.push_str(rest);
+            Ok(Value::String(rendered))
+        }
+        Value::Array(values) => values
+            .iter()
+            .map(|value| render(value, params))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), render(value, params)?)))
+            .collect::<Result<serde_json::Map<_, _>, SeekStormError>>()
+            .map(Value::Object),
+        _ => Ok(template.clone()),
+    }
+}
+
+/// Renders a search template with the parameters into a search request (see render)
+pub(crate) fn render_search_template(
+    template: &Value,
+    params: &HashMap<String, Value>,
+) -> Result<SearchRequestObject, SeekStormError> {
+    serde_json::from_value(render(template, params)?).map_err(|e| {
+        SeekStormError::QueryParseError(format!(
+            "invalid search request after rendering the template: {}",
+            e
+        ))
+    })
+}
+
+/// Checks that the template is a JSON object of a search request, with well-formed placeholders
+fn check_search_template(template: &Value) -> Result<(), SeekStormError> {
+    fn check_placeholders(value: &Value) -> Result<(), SeekStormError> {
+        match value {
+            Value::String(text) => placeholders(text).map(|_| ()),
+            Value::Array(values) => values.iter().try_for_each(check_placeholders),
+            Value::Object(object) => object.values().try_for_each(check_placeholders),
+            _ => Ok(()),
+        }
+    }
+
+    if !template.is_object() {
+        return Err(SeekStormError::InvalidRequest(
+            "search template has to be a JSON object".to_string(),
+        ));
+    }
+    check_placeholders(template)
+}
+
+/// The search templates of the index. A missing file means no templates,
+/// a file which can't be read or parsed is an error instead of silently dropping all templates with the next change.
+pub(crate) fn get_search_templates_api(
+    index_id_path: &Path,
+) -> Result<BTreeMap<String, Value>, SeekStormError> {
+    let search_templates_path = index_id_path.join(SEARCH_TEMPLATES_FILENAME);
+    match fs::read(&search_templates_path) {
+        Ok(search_templates) => serde_json::from_slice(&search_templates).map_err(|e| {
+            SeekStormError::Internal(format!("{}: {}", search_templates_path.display(), e))
+        }),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(SeekStormError::Io(format!(
+            "{}: {}",
+            search_templates_path.display(),
+            e
+        ))),
+    }
+}
+
+pub(crate) fn get_search_template_api(
+    index_id_path: &Path,
+    name: &str,
+) -> Result<Value, SeekStormError> {
+    get_search_templates_api(index_id_path)?
+        .remove(name)
+        .ok_or_else(|| SeekStormError::NotFound("template not found".to_string()))
+}
+
+fn save_search_templates(
+    index_id_path: &Path,
+    search_templates: &BTreeMap<String, Value>,
+) -> Result<(), SeekStormError> {
+    let search_templates_path = index_id_path.join(SEARCH_TEMPLATES_FILENAME);
+    if search_templates.is_empty() {
+        if search_templates_path.exists() {
+            fs::remove_file(&search_templates_path).map_err(|e| {
+                SeekStormError::Io(format!("{}: {}", search_templates_path.display(), e))
+            })?;
+        }
+        Ok(())
+    } else {
+        save_file_atomically(
+            &search_templates_path,
+            serde_json::to_vec(search_templates)
+                .map_err(|e| SeekStormError::Internal(e.to_string()))?,
+        )
+    }
+}
+
+/// Stores/replaces a named search template of the index: a search request object whose string values may contain {{name}} placeholders,
+/// which are replaced by the parameters of a template search (see render). Returns the number of templates of the index.
+pub(crate) async fn set_search_template_api(
+    index_id_path: &Path,
+    index_arc: &IndexArc,
+    name: &str,
+    template: Value,
+) -> Result<usize, SeekStormError> {
+    if name.is_empty() {
+        return Err(SeekStormError::InvalidRequest(
+            "template name missing".to_string(),
+        ));
+    }
+    check_search_template(&template)?;
+
+    // the index write lock serializes concurrent changes of the templates file
+    let _index_mut = index_arc.write().await;
+    let mut search_templates = get_search_templates_api(index_id_path)?;
+    search_templates.insert(name.to_string(), template);
+    save_search_templates(index_id_path, &search_templates)?;
+    Ok(search_templates.len())
+}
+
+/// Deletes a named search template of the index. Returns the number of remaining templates.
+pub(crate) async fn delete_search_template_api(
+    index_id_path: &Path,
+    index_arc: &IndexArc,
+    name: &str,
+) -> Result<usize, SeekStormError> {
+    let _index_mut = index_arc.write().await;
+    let mut search_templates = get_search_templates_api(index_id_path)?;
+    if search_templates.remove(name).is_none() {
+        return Err(SeekStormError::NotFound("template not found".to_string()));
+    }
+    save_search_templates(index_id_path, &search_templates)?;
+    Ok(search_templates.len())
+}
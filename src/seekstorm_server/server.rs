@@ -2,23 +2,32 @@ use base64::{engine::general_purpose, Engine};
 use colored::Colorize;
 use crossbeam_channel::{bounded, select, Receiver};
 use seekstorm::{
-    index::{SimilarityType, TokenizerType},
-    ingest::{IngestJson, IngestPdf},
+    index::{Durability, SimilarityType, StemmerType, TokenizerType},
+    ingest::{IngestCsv, IngestJson, IngestPdf},
 };
 use std::{
     collections::HashMap,
     env::current_exe,
     ffi::OsStr,
     fs::{self, metadata},
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant},
 };
-use tokio::sync::RwLock;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::{sync::RwLock, task::JoinHandle};
 
 use crate::{
-    api_endpoints::{create_apikey_api, create_index_api, delete_apikey_api, open_all_apikeys},
-    http_server::{calculate_hash, http_server},
+    api_endpoints::{
+        create_apikey_api, create_index_api, delete_apikey_api, open_all_apikeys,
+        purge_expired_trash, reload_apikey_quotas, send_alert_notifications, SERVER_START,
+    },
+    http_server::{calculate_hash, http_server, RequestLimits},
+    lifecycle::apply_lifecycle_policies,
+    memory_budget::{update_memory_usage, MEMORY_CHECK_INTERVAL},
     multi_tenancy::{get_apikey_hash, ApikeyObject, ApikeyQuotaObject},
+    webhook::{SmtpSettings, ALLOW_PRIVATE_WEBHOOKS, SMTP_SETTINGS},
 };
 
 const WIKIPEDIA_FILENAME: &str = "wiki-articles.json";
@@ -47,7 +56,134 @@ async fn commandline(sender: crossbeam_channel::Sender<String>) {
     }
 }
 
+/// Loads the request limits: defaults, overwritten by the command line parameters, overwritten by the settings of the optional config_file.
+/// The config_file takes precedence, so that a reload applies its changes also to the settings given on the command line.
+/// Only the request limits are reloadable, a config_file with any other setting is rejected.
+pub(crate) fn load_request_limits(
+    params: &HashMap<String, String>,
+) -> Result<RequestLimits, String> {
+    let mut request_limits = RequestLimits::default();
+
+    if let Some(value) = params.get("max_request_body_size") {
+        request_limits.max_request_body_size = value
+            .parse::<usize>()
+            .map_err(|_| "max_request_body_size invalid".to_string())?;
+    }
+    if let Some(value) = params.get("request_timeout_ms") {
+        request_limits.request_timeout_ms = value
+            .parse::<u64>()
+            .map_err(|_| "request_timeout_ms invalid".to_string())?;
+    }
+    if let Some(value) = params.get("max_result_window") {
+        request_limits.max_result_window = value
+            .parse::<usize>()
+            .map_err(|_| "max_result_window invalid".to_string())?;
+    }
+    if let Some(value) = params.get("trash_retention_hours") {
+        request_limits.trash_retention_hours = value
+            .parse::<u64>()
+            .map_err(|_| "trash_retention_hours invalid".to_string())?;
+    }
+    if let Some(value) = params.get("memory_budget") {
+        request_limits.memory_budget = value
+            .parse::<u64>()
+            .map_err(|_| "memory_budget invalid".to_string())?;
+    }
+    if let Some(value) = params.get("memory_budget_wait_ms") {
+        request_limits.memory_budget_wait_ms = value
+            .parse::<u64>()
+            .map_err(|_| "memory_budget_wait_ms invalid".to_string())?;
+    }
+    if let Some(value) = params.get("idempotency_window_secs") {
+        request_limits.idempotency_window_secs = value
+            .parse::<u64>()
+            .map_err(|_| "idempotency_window_secs invalid".to_string())?;
+    }
+    if let Some(value) = params.get("max_concurrent_searches") {
+        request_limits.max_concurrent_searches = value
+            .parse::<usize>()
+            .map_err(|_| "max_concurrent_searches invalid".to_string())?;
+    }
+    if let Some(value) = params.get("max_queued_searches") {
+        request_limits.max_queued_searches = value
+            .parse::<usize>()
+            .map_err(|_| "max_queued_searches invalid".to_string())?;
+    }
+    if let Some(value) = params.get("max_query_cost") {
+        request_limits.max_query_cost = value
+            .parse::<u64>()
+            .map_err(|_| "max_query_cost invalid".to_string())?;
+    }
+    if let Some(value) = params.get("downgrade_expensive_queries") {
+        request_limits.downgrade_expensive_queries = value
+            .parse::<bool>()
+            .map_err(|_| "downgrade_expensive_queries invalid".to_string())?;
+    }
+
+    if let Some(config_file) = params.get("config_file") {
+        let config_error = |e: String| format!("config_file {}: {}", config_file, e);
+        let config_string =
+            fs::read_to_string(config_file).map_err(|e| config_error(e.to_string()))?;
+        let serde_json::Value::Object(config) =
+            serde_json::from_str(&config_string).map_err(|e| config_error(e.to_string()))?
+        else {
+            return Err(config_error("not a JSON object".to_string()));
+        };
+        let mut settings = serde_json::to_value(request_limits).unwrap();
+        for (key, value) in config {
+            let Some(setting) = settings.get_mut(&key) else {
+                return Err(config_error(format!(
+                    "unknown or not reloadable setting {}",
+                    key
+                )));
+            };
+            *setting = value;
+        }
+        request_limits =
+            serde_json::from_value(settings).map_err(|e| config_error(e.to_string()))?;
+    }
+
+    Ok(request_limits)
+}
+
+/// Interval of purging deleted indices from the trash, whose retention period has expired
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Interval of checking the indices with Durability::Periodic for a due flush of their index files to stable storage
+const SYNC_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval of applying the index lifecycle policies of the API keys
+const LIFECYCLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval of evaluating the alerts for newly committed documents: all new matches within an interval are batched into the notifications
+const ALERT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Interval of saving the click and conversion feedback statistics and the usage counters of the indices, if changed
+const STATISTICS_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Reloads the server configuration without restarting the server and re-opening all indices:
+/// the request limits from config_file and command line parameters (see load_request_limits), and the API key quotas from the apikey.json files.
+/// All other command line parameters, e.g. local_ip, local_port, and index_path, require a restart.
+/// Triggered by SIGHUP, the console command `reload`, or the REST API endpoint `POST /api/v1/reload`.
+pub(crate) async fn reload_config(
+    params: &HashMap<String, String>,
+    index_path: &PathBuf,
+    apikey_list: &Arc<RwLock<HashMap<u128, ApikeyObject>>>,
+    request_limits: &Arc<RwLock<RequestLimits>>,
+) -> Result<RequestLimits, String> {
+    let request_limits_reloaded = load_request_limits(params)?;
+
+    let mut apikey_list_mut = apikey_list.write().await;
+    reload_apikey_quotas(index_path, &mut apikey_list_mut)?;
+    drop(apikey_list_mut);
+
+    *request_limits.write().await = request_limits_reloaded;
+    Ok(request_limits_reloaded)
+}
+
 pub(crate) async fn initialize(params: HashMap<String, String>) {
+    let _ = SERVER_START.set(Instant::now());
+
     let mut ingest_path_str = "";
     if params.contains_key("ingest_path") {
         ingest_path_str = params.get("ingest_path").unwrap();
@@ -87,6 +223,36 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
         );
     }
 
+    if let Some(value) = params.get("allow_private_webhooks") {
+        let Ok(allow_private_webhooks) = value.parse::<bool>() else {
+            println!(
+                "{} allow_private_webhooks invalid",
+                "Invalid server configuration:".bright_red()
+            );
+            return;
+        };
+        let _ = ALLOW_PRIVATE_WEBHOOKS.set(allow_private_webhooks);
+    }
+    match (params.get("smtp_url"), params.get("smtp_from")) {
+        (Some(smtp_url), Some(smtp_from)) => match SmtpSettings::new(smtp_url, smtp_from) {
+            Ok(smtp_settings) => {
+                let _ = SMTP_SETTINGS.set(smtp_settings);
+            }
+            Err(e) => {
+                println!("{} {}", "Invalid server configuration:".bright_red(), e);
+                return;
+            }
+        },
+        (None, None) => {}
+        _ => {
+            println!(
+                "{} smtp_url and smtp_from have to be set together",
+                "Invalid server configuration:".bright_red()
+            );
+            return;
+        }
+    }
+
     let apikey_list_map: HashMap<u128, ApikeyObject> = HashMap::new();
     let apikey_list = Arc::new(RwLock::new(apikey_list_map));
     let apikey_list_clone = apikey_list.clone();
@@ -109,10 +275,183 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
         local_port = params.get("local_port").unwrap().parse::<u16>().unwrap();
     }
 
+    let params = Arc::new(params.clone());
+    let request_limits = match load_request_limits(&params) {
+        Ok(request_limits) => request_limits,
+        Err(e) => {
+            println!("{} {}", "Invalid server configuration:".bright_red(), e);
+            return;
+        }
+    };
+    let request_limits = Arc::new(RwLock::new(request_limits));
+
+    #[cfg(unix)]
+    {
+        let params_clone = params.clone();
+        let index_path_clone = index_path.clone();
+        let apikey_list_clone2 = apikey_list.clone();
+        let request_limits_clone = request_limits.clone();
+        tokio::spawn(async move {
+            let mut hangup = signal(SignalKind::hangup()).unwrap();
+            while hangup.recv().await.is_some() {
+                match reload_config(
+                    &params_clone,
+                    &index_path_clone,
+                    &apikey_list_clone2,
+                    &request_limits_clone,
+                )
+                .await
+                {
+                    Ok(_) => println!("Configuration reloaded by SIGHUP"),
+                    Err(e) => println!("{} {}", "Configuration reload failed:".bright_red(), e),
+                }
+            }
+        });
+    }
+
+    {
+        let index_path_clone = index_path.clone();
+        let apikey_list_clone2 = apikey_list.clone();
+        let request_limits_clone = request_limits.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let trash_retention_hours = request_limits_clone.read().await.trash_retention_hours;
+                let apikey_list_ref = apikey_list_clone2.read().await;
+                for apikey_object in apikey_list_ref.values() {
+                    purge_expired_trash(&index_path_clone, apikey_object, trash_retention_hours);
+                }
+            }
+        });
+    }
+
+    {
+        let index_path_clone = index_path.clone();
+        let apikey_list_clone2 = apikey_list.clone();
+        let request_limits_clone = request_limits.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(LIFECYCLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let trash_retention_hours = request_limits_clone.read().await.trash_retention_hours;
+                apply_lifecycle_policies(
+                    &index_path_clone,
+                    &apikey_list_clone2,
+                    trash_retention_hours,
+                )
+                .await;
+            }
+        });
+    }
+
+    {
+        let apikey_list_clone2 = apikey_list.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SYNC_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let index_arc_vec: Vec<_> = apikey_list_clone2
+                    .read()
+                    .await
+                    .values()
+                    .flat_map(|apikey_object| apikey_object.index_list.values().cloned())
+                    .collect();
+                for index_arc in index_arc_vec {
+                    if index_arc.read().await.is_sync_due() {
+                        if let Err(e) = index_arc.write().await.sync_if_due() {
+                            println!("index files not flushed to disk: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    {
+        let apikey_list_clone2 = apikey_list.clone();
+        let request_limits_clone = request_limits.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MEMORY_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let memory_budget = request_limits_clone.read().await.memory_budget;
+                update_memory_usage(&apikey_list_clone2, memory_budget).await;
+            }
+        });
+    }
+
+    {
+        let apikey_list_clone2 = apikey_list.clone();
+        tokio::spawn(async move {
+            // the alerts of each index are delivered by a separate task, so that a slow webhook or SMTP server delays only the alerts of its index,
+            // an index is skipped while the delivery of its previous notifications is still running
+            let mut alert_tasks: HashMap<(u128, u64), JoinHandle<()>> = HashMap::new();
+            let mut interval = tokio::time::interval(ALERT_INTERVAL);
+            loop {
+                interval.tick().await;
+                let index_arc_vec: Vec<_> = apikey_list_clone2
+                    .read()
+                    .await
+                    .iter()
+                    .flat_map(|(apikey_hash, apikey_object)| {
+                        apikey_object
+                            .index_list
+                            .iter()
+                            .map(|(index_id, index_arc)| {
+                                ((*apikey_hash, *index_id), index_arc.clone())
+                            })
+                    })
+                    .collect();
+                alert_tasks.retain(|_, alert_task| !alert_task.is_finished());
+                for (alert_task_key, index_arc) in index_arc_vec {
+                    alert_tasks.entry(alert_task_key).or_insert_with(|| {
+                        tokio::spawn(async move { send_alert_notifications(&index_arc).await })
+                    });
+                }
+            }
+        });
+    }
+
+    {
+        let apikey_list_clone2 = apikey_list.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(STATISTICS_SAVE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let index_arc_vec: Vec<_> = apikey_list_clone2
+                    .read()
+                    .await
+                    .values()
+                    .flat_map(|apikey_object| apikey_object.index_list.values().cloned())
+                    .collect();
+                for index_arc in index_arc_vec {
+                    let index_ref = index_arc.read().await;
+                    if let Err(e) = index_ref.save_query_feedback() {
+                        println!("query feedback not saved: {}", e);
+                    }
+                    if let Err(e) = index_ref.save_usage_counters() {
+                        println!("usage counters not saved: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
     let index_path_local = index_path.clone();
+    let request_limits_clone = request_limits.clone();
+    let params_clone = params.clone();
 
     tokio::spawn(async move {
-        http_server(&index_path_local, apikey_list, &local_ip, &local_port).await
+        http_server(
+            &index_path_local,
+            apikey_list,
+            &local_ip,
+            &local_port,
+            request_limits_clone,
+            params_clone,
+        )
+        .await
     });
 
     let demo_api_key = [0u8; 32];
@@ -182,28 +521,31 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
                                     } else if parameter[2]==demo_api_key_base64{
 
                                         let apikey_quota_object=ApikeyQuotaObject {..Default::default()};
-                                        Some(create_apikey_api(
+                                        create_apikey_api(
                                             &index_path,
                                             apikey_quota_object,
                                             &demo_api_key,
                                             &mut apikey_list_mut,
-                                        ))
+                                        ).inspect_err(|e| println!("{} {}", "Creating api_key failed:".bright_red(), e)).ok()
                                     } else{
                                         None
                                     }
                                 } else {
                                     let apikey_quota_object=ApikeyQuotaObject {..Default::default()};
-                                    Some(create_apikey_api(
+                                    create_apikey_api(
                                         &index_path,
                                         apikey_quota_object,
                                         &demo_api_key,
                                         &mut apikey_list_mut,
-                                    ))
+                                    ).inspect_err(|e| println!("{} {}", "Creating api_key failed:".bright_red(), e)).ok()
                                 };
 
                                 if let Some(apikey_object) = apikey_object_option {
 
-                                    let md = metadata(data_path).unwrap();
+                                    let Ok(md) = metadata(data_path) else {
+                                        println!("{} {}", "File not found:".bright_red(), data_path.display());
+                                        continue;
+                                    };
 
 
                                     if parameter.len() > 3 || !md.is_file() || data_path.display().to_string().to_lowercase().ends_with(".pdf") || data_path.display().to_string().to_lowercase().ends_with(WIKIPEDIA_FILENAME) ||
@@ -230,8 +572,18 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
                                                     SimilarityType::Bm25fProximity,
                                                     TokenizerType::UnicodeAlphanumeric,
                                                     Vec::new(),
+                                                    StemmerType::None,
+                                                    1.2,
+                                                    0.75,
+                                                    0.0,
+                                                    Durability::Os,
+                                                    1000,
+                                                    false,
+                                                    false,
+                                                    serde_json::Value::Null,
                                                     apikey_object,
                                                 )
+                                                .unwrap()
                                             } else {
                                                 0
                                             };
@@ -248,6 +600,9 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
                                                         "json" =>{
                                                             index_arc.ingest_json(data_path).await;
                                                         }
+                                                        "csv" =>{
+                                                            index_arc.ingest_csv(data_path).await;
+                                                        }
                                                         _ =>{
                                                             println!("{} {}","File extension not supported:".bright_red(),extension);
                                                         }
@@ -290,12 +645,14 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
                         println!("create demo api_key");
                         let mut apikey_list_mut = apikey_list_clone.write().await;
                         let apikey_quota_object=ApikeyQuotaObject {..Default::default()};
-                        create_apikey_api(
+                        if let Err(e) = create_apikey_api(
                             &index_path,
                             apikey_quota_object,
                             &demo_api_key,
                             &mut apikey_list_mut,
-                        );
+                        ) {
+                            println!("{} {}", "Creating api_key failed:".bright_red(), e);
+                        }
                     }
 
                     "delete" =>
@@ -303,10 +660,20 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
                         println!("delete demo api_key");
                         let apikey_hash = calculate_hash(&demo_api_key) as u128;
                         let mut apikey_list_mut = apikey_list_clone.write().await;
-                        let _ = delete_apikey_api(&index_path, &mut apikey_list_mut, apikey_hash);
+                        if let Err(e) = delete_apikey_api(&index_path, &mut apikey_list_mut, apikey_hash) {
+                            println!("{} {}", "Deleting api_key failed:".bright_red(), e);
+                        }
                         drop(apikey_list_mut);
                     },
 
+                    "reload" =>
+                    {
+                        match reload_config(&params, &index_path, &apikey_list_clone, &request_limits).await {
+                            Ok(request_limits) => println!("Configuration reloaded: {:?}", request_limits),
+                            Err(e) => println!("{} {}", "Configuration reload failed:".bright_red(), e),
+                        }
+                    },
+
                     "list" =>
                     {
                         println!("delete indices");
@@ -335,10 +702,11 @@ pub(crate) async fn initialize(params: HashMap<String, String>) {
                         println!("{}","Server console commands:".yellow());
                         println!();
                         println!("{:40} Index {} if present in the seekstorm_server.exe directory or the directory specified by the command line parameter `ingest_path`.","ingest".green(),WIKIPEDIA_FILENAME);
-                        println!("{:40} Index a local file in PDF, JSON, Newline-delimited JSON, or Concatenated JSON format, from the seekstorm_server.exe directory or the directory specified by the command line parameter.","ingest [data_path]".green());
-                        println!("{:40} Index a local file in PDF, JSON, Newline-delimited JSON, or Concatenated JSON format.","ingest [data_path] [apikey] [index_id]".green());
+                        println!("{:40} Index a local file in PDF, JSON, Newline-delimited JSON, Concatenated JSON, or CSV format, from the seekstorm_server.exe directory or the directory specified by the command line parameter.","ingest [data_path]".green());
+                        println!("{:40} Index a local file in PDF, JSON, Newline-delimited JSON, Concatenated JSON, or CSV format.","ingest [data_path] [apikey] [index_id]".green());
                         println!("{:40} Create the demo API key manually to allow a subsequent custom create index via REST API.","create".green());
                         println!("{:40} Delete the demo API key and all its indices.","delete".green());
+                        println!("{:40} Reload the server configuration (config_file, API key quotas) without restarting.","reload".green());
                         println!("{:40} Stop the server.","quit".green());
                         println!("{:40} Show this help.","help".green());
                         println!();
@@ -0,0 +1,281 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::webhook::post_webhook;
+
+/// Maximum number of finished tasks kept per apikey, the oldest finished tasks are removed first
+pub(crate) const FINISHED_TASKS_MAX: usize = 100;
+
+/// Number of documents per batch of a bulk ingest or update by query task: progress is updated and cancellation is checked after each batch
+pub(crate) const TASK_BATCH_SIZE: usize = 10_000;
+
+/// Maximum number of document errors listed per task, further errors are only counted
+pub(crate) const TASK_ERRORS_MAX: usize = 1_000;
+
+/// Operation executed by a background task
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub(crate) enum TaskType {
+    IndexDocuments,
+    IndexFile,
+    DeleteByQuery,
+    UpdateByQuery,
+    Compact,
+    Verify,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub(crate) enum TaskStatus {
+    Running,
+    Completed,
+    Failed,
+    /// Cancelled by the user: the work done before the cancellation is kept, e.g. the documents of the already indexed batches
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub(crate) struct TaskProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Document of a bulk ingest or update by query task which was not indexed or updated
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct DocumentError {
+    /// Position of the document in the request, or in the documents matching the query of an update by query, starting with 0
+    pub position: usize,
+    pub error: String,
+}
+
+/// Background task of a long-running operation, started with the query parameter wait_for_completion=false
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct TaskObject {
+    pub id: u64,
+    pub task_type: TaskType,
+    pub index_id: u64,
+    pub status: TaskStatus,
+    /// The task can be cancelled while running: bulk ingest and update by query. Compaction, verification and delete by query run to completion.
+    pub cancellable: bool,
+    /// Unix timestamp of the task start
+    pub created: i64,
+    /// Unix timestamp of the task completion, failure or cancellation
+    pub finished: Option<i64>,
+    /// Progress of tasks which are executed in batches, e.g. the number of indexed documents of a bulk ingest
+    pub progress: Option<TaskProgress>,
+    /// Response of the operation, the same as with wait_for_completion=true
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// Documents which were not indexed, e.g. because of invalid field values: the first TASK_ERRORS_MAX errors
+    pub document_errors: Vec<DocumentError>,
+    pub document_error_count: usize,
+    /// URL where the task is posted as JSON when it is finished
+    pub webhook_url: Option<String>,
+}
+
+struct Task {
+    object: TaskObject,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Background tasks of an apikey, in ascending order of task id. Tasks are kept in memory only, they are lost on server restart.
+#[derive(Default)]
+pub(crate) struct TaskList {
+    next_id: u64,
+    tasks: VecDeque<Task>,
+}
+
+impl TaskList {
+    /// All tasks, the most recent first
+    pub(crate) fn list(&self) -> Vec<TaskObject> {
+        self.tasks
+            .iter()
+            .rev()
+            .map(|task| task.object.clone())
+            .collect()
+    }
+
+    pub(crate) fn get(&self, id: u64) -> Option<TaskObject> {
+        self.find(id).map(|task| task.object.clone())
+    }
+
+    /// Requests the cancellation of a running task. The task is cancelled at its next checkpoint, e.g. after the current batch of documents.
+    pub(crate) fn cancel(&mut self, id: u64) -> Result<TaskObject, String> {
+        let Some(task) = self.find(id) else {
+            return Err("task not found".to_string());
+        };
+        if task.object.status != TaskStatus::Running {
+            return Err("task already finished".to_string());
+        }
+        if !task.object.cancellable {
+            return Err(format!(
+                "task of type {:?} can not be cancelled",
+                task.object.task_type
+            ));
+        }
+        task.cancel.store(true, Ordering::Relaxed);
+        Ok(task.object.clone())
+    }
+
+    fn find(&self, id: u64) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.object.id == id)
+    }
+
+    fn find_mut(&mut self, id: u64) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|task| task.object.id == id)
+    }
+
+    fn push(
+        &mut self,
+        task_type: TaskType,
+        index_id: u64,
+        cancellable: bool,
+        webhook_url: Option<String>,
+    ) -> &Task {
+        let finished_count = self
+            .tasks
+            .iter()
+            .filter(|task| task.object.status != TaskStatus::Running)
+            .count();
+        if finished_count >= FINISHED_TASKS_MAX {
+            if let Some(position) = self
+                .tasks
+                .iter()
+                .position(|task| task.object.status != TaskStatus::Running)
+            {
+                self.tasks.remove(position);
+            }
+        }
+
+        self.tasks.push_back(Task {
+            object: TaskObject {
+                id: self.next_id,
+                task_type,
+                index_id,
+                status: TaskStatus::Running,
+                cancellable,
+                created: Utc::now().timestamp(),
+                finished: None,
+                progress: None,
+                result: None,
+                error: None,
+                document_errors: Vec::new(),
+                document_error_count: 0,
+                webhook_url,
+            },
+            cancel: Arc::new(AtomicBool::new(false)),
+        });
+        self.next_id += 1;
+        self.tasks.back().unwrap()
+    }
+}
+
+/// Handle of a running task, to report its progress and check for its cancellation
+pub(crate) struct TaskHandle {
+    id: u64,
+    tasks: Arc<Mutex<TaskList>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    pub(crate) fn set_progress(&self, done: usize, total: usize) {
+        if let Some(task) = self.tasks.lock().unwrap().find_mut(self.id) {
+            task.object.progress = Some(TaskProgress { done, total });
+        }
+    }
+
+    /// Records a document which was not indexed
+    pub(crate) fn add_document_error(&self, position: usize, error: String) {
+        if let Some(task) = self.tasks.lock().unwrap().find_mut(self.id) {
+            if task.object.document_errors.len() < TASK_ERRORS_MAX {
+                task.object
+                    .document_errors
+                    .push(DocumentError { position, error });
+            }
+            task.object.document_error_count += 1;
+        }
+    }
+
+    /// Checkpoint of the task: returns true if the cancellation was requested, then the task is cancelled and has to return
+    pub(crate) fn is_cancelled(&self) -> bool {
+        if !self.cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+        if let Some(task) = self.tasks.lock().unwrap().find_mut(self.id) {
+            task.object.status = TaskStatus::Cancelled;
+        }
+        true
+    }
+}
+
+/// Starts a background task and returns it. The operation receives the handle of the task, and returns the result of the task as JSON value.
+/// When the task is finished, it is posted to the webhook_url, if any.
+pub(crate) fn spawn_task<F, Fut>(
+    tasks: &Arc<Mutex<TaskList>>,
+    task_type: TaskType,
+    index_id: u64,
+    cancellable: bool,
+    webhook_url: Option<String>,
+    operation: F,
+) -> TaskObject
+where
+    F: FnOnce(TaskHandle) -> Fut,
+    Fut: Future<Output = Result<serde_json::Value, String>> + Send + 'static,
+{
+    let mut task_list = tasks.lock().unwrap();
+    let task = task_list.push(task_type, index_id, cancellable, webhook_url);
+    let task_object = task.object.clone();
+    let task_handle = TaskHandle {
+        id: task_object.id,
+        tasks: tasks.clone(),
+        cancel: task.cancel.clone(),
+    };
+    drop(task_list);
+
+    let future = operation(task_handle);
+    let tasks = tasks.clone();
+    let id = task_object.id;
+    tokio::spawn(async move {
+        let result = future.await;
+        let task_object = {
+            let mut task_list = tasks.lock().unwrap();
+            let Some(task) = task_list.find_mut(id) else {
+                return;
+            };
+            task.object.finished = Some(Utc::now().timestamp());
+            match result {
+                Ok(result) => {
+                    if task.object.status == TaskStatus::Running {
+                        task.object.status = TaskStatus::Completed;
+                    }
+                    task.object.result = Some(result);
+                }
+                Err(e) => {
+                    task.object.status = TaskStatus::Failed;
+                    task.object.error = Some(e);
+                }
+            }
+            task.object.clone()
+        };
+
+        if let Some(webhook_url) = task_object.webhook_url.as_ref() {
+            if let Err(e) =
+                post_webhook(webhook_url, serde_json::to_string(&task_object).unwrap()).await
+            {
+                println!(
+                    "task notification not delivered: {} {} {}",
+                    task_object.id, webhook_url, e
+                );
+            }
+        }
+    });
+
+    task_object
+}
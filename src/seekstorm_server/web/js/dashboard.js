@@ -0,0 +1,101 @@
+var API_KEY = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+var FACET_LENGTH = 10;
+var indexId = null;
+var facetFields = [];
+var facetFilter = {};
+
+function setHeader(xhr) { xhr.setRequestHeader('apikey', API_KEY); }
+
+function escapeHtml(text) { return $("<div>").text(text).html(); }
+
+function formatBytes(bytes) {
+    var units = ["B", "KB", "MB", "GB", "TB"];
+    var i = 0;
+    while (bytes >= 1024 && i < units.length - 1) { bytes /= 1024; i++; }
+    return bytes.toLocaleString(undefined, { maximumFractionDigits: 1 }) + " " + units[i];
+}
+
+function showError(xhr) { $("#error").text(xhr.status + " " + xhr.responseText); }
+
+function loadDashboard() {
+    $("#error").text("");
+    $.ajax({
+        url: "api/v1/dashboard", type: "GET", cache: false, dataType: 'json', beforeSend: setHeader, error: showError,
+        success: function (data) {
+            $("#version").text(data.version);
+            var summary = data.summary;
+            var operationsCount = summary.usage.indexing_count + summary.usage.delete_count + summary.usage.query_count;
+            $("#usage").text(`${summary.indexed_doc_count.toLocaleString()} documents, ${formatBytes(summary.disk_size)}, ${operationsCount.toLocaleString()} operations, ${summary.qps.toLocaleString(undefined, { maximumFractionDigits: 2 })} queries/s`);
+            var indices = $("#indices").empty();
+            data.indices.forEach(index => {
+                var row = $(`<tr class="indexRow"><td>${index.id}</td><td>${escapeHtml(index.name)}</td><td>${index.indexed_doc_count.toLocaleString()}</td><td>${formatBytes(index.disk_size)}</td><td>${index.usage.indexing_count.toLocaleString()}</td><td>${index.usage.delete_count.toLocaleString()}</td><td>${index.usage.query_count.toLocaleString()}</td></tr>`);
+                if (index.id === indexId) row.addClass("active");
+                row.click(() => { $(".indexRow").removeClass("active"); row.addClass("active"); selectIndex(index.id, index.name); });
+                indices.append(row);
+            });
+            if (indexId === null && data.indices.length > 0) selectIndex(data.indices[0].id, data.indices[0].name);
+
+            var recentQueries = $("#recentQueries").empty();
+            data.recent_queries.forEach(recent => {
+                var row = $(`<tr><td>${new Date(recent.timestamp * 1000).toLocaleTimeString()}</td><td>${recent.index_id}</td><td>${escapeHtml(recent.query)}</td><td>${recent.count_total.toLocaleString()}</td><td>${(recent.time / 1000000).toLocaleString(undefined, { maximumFractionDigits: 3 })} ms</td></tr>`);
+                recentQueries.append(row);
+            });
+        }
+    });
+}
+
+function selectIndex(id, name) {
+    indexId = id;
+    facetFilter = {};
+    $("#playgroundIndex").text(`index ${id}: ${name}`);
+    $.ajax({
+        url: `api/v1/index/${id}`, type: "GET", cache: false, dataType: 'json', beforeSend: setHeader, error: showError,
+        success: function (data) {
+            facetFields = Object.entries(data.schema)
+                .filter(([_, field]) => field.facet && (field.field_type == "String" || field.field_type == "StringSet"))
+                .map(([name, field]) => ({ name: name, field_type: field.field_type }));
+            search();
+        }
+    });
+}
+
+function search() {
+    if (indexId === null) return;
+    var queryFacets = facetFields.map(field => ({ [field.field_type]: { field: field.name, prefix: "", length: FACET_LENGTH } }));
+    var filters = facetFields.filter(field => facetFilter[field.name]).map(field => ({ [field.field_type]: { field: field.name, filter: [facetFilter[field.name]] } }));
+    $.ajax({
+        url: `api/v1/index/${indexId}/query`, type: "POST", cache: false, dataType: 'json', beforeSend: setHeader, error: showError,
+        data: JSON.stringify({ query: $("#queryInput").val(), offset: 0, length: 10, realtime: true, query_facets: queryFacets, facet_filter: filters }),
+        success: function (data) {
+            $("#queryStats").text(`${data.count_total.toLocaleString()} results in ${(data.time / 1000000).toLocaleString(undefined, { maximumFractionDigits: 3 })} ms`);
+
+            var facets = $("#facets").empty();
+            facetFields.forEach(field => {
+                facets.append($(`<h2>${escapeHtml(field.name)}</h2>`));
+                (data.facets[field.name] || []).forEach(([value, count]) => {
+                    var entry = $(`<div class="facetValue">${escapeHtml(value)} <span class="count">${count.toLocaleString()}</span></div>`);
+                    if (facetFilter[field.name] === value) entry.addClass("active");
+                    entry.click(() => { facetFilter[field.name] = facetFilter[field.name] === value ? undefined : value; search(); });
+                    facets.append(entry);
+                });
+            });
+
+            var results = $("#results").empty();
+            data.results.forEach(result => {
+                results.append($(`<tr><td>${result._id}</td><td><pre>${escapeHtml(JSON.stringify(result, null, 2))}</pre></td></tr>`));
+            });
+            loadDashboard();
+        }
+    });
+}
+
+$(function () {
+    var apiKey = new URLSearchParams(window.location.search).get("api_key");
+    if (apiKey) API_KEY = apiKey.replace(/ /g, "+");
+    $("#apiKeyInput").val(API_KEY);
+    $("#apiKeyInput").change(() => { API_KEY = $("#apiKeyInput").val(); indexId = null; loadDashboard(); });
+    $("#refreshBtn").click(loadDashboard);
+    $("#searchBtn").click(search);
+    $("#queryInput").keydown(e => { if (e.which == 13) search(); });
+    loadDashboard();
+});